@@ -0,0 +1,442 @@
+//! Multi-agent code review pipeline
+//!
+//! Wires a workspace entity and two review agents into a pipeline that uses
+//! branches the way a human reviewer would:
+//!
+//!   1. the `workspace` entity grants a read capability on a source file;
+//!   2. the *suggester* agent reviews the file and asserts one
+//!      `<suggestion id line original replacement rationale>` per finding;
+//!   3. every suggestion is explored on its own fork, where the *approver*
+//!      agent asserts a `<verdict id approved|rejected reason>`;
+//!   4. approved forks are merged back into `main`, and the verdicts that
+//!      reached `main` are applied through a workspace write capability.
+//!
+//! The coordinator below plays the part of the orchestration program. Both
+//! agents are deterministic, rule-based stand-ins for LLM-backed agents so
+//! the example runs offline; pointing them at `agent-claude-code` or
+//! `agent-codex` only changes the entity types and message shapes.
+//!
+//! Run with `cargo run --example review_pipeline`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use duet::codebase;
+use duet::runtime::actor::{Activation, Entity};
+use duet::runtime::branch::BranchDetails;
+use duet::runtime::error::{ActorResult, Result, RuntimeError};
+use duet::runtime::registry::EntityCatalog;
+use duet::runtime::turn::{ActorId, BranchId, FacetId, Handle};
+use duet::runtime::{Control, RuntimeConfig};
+use duet::util::io_value::record_with_label;
+use preserves::{IOValue, ValueImpl};
+
+const SUGGESTER_TYPE: &str = "example/review-suggester";
+const APPROVER_TYPE: &str = "example/review-approver";
+
+const REVIEW_LABEL: &str = "review";
+const SUGGESTION_LABEL: &str = "suggestion";
+const JUDGE_LABEL: &str = "judge";
+const VERDICT_LABEL: &str = "verdict";
+
+const REVIEWED_FILE: &str = "src/lib.rs";
+
+const SAMPLE_SOURCE: &str = r#"pub fn parse_port(text: &str) -> u16 {
+    let port = text.trim().parse::<u16>().unwrap();
+    println!("parsed port {port}");
+    port
+}
+
+pub fn first_word(text: &str) -> &str {
+    text.split_whitespace().next().unwrap()
+}
+"#;
+
+/// Reviews a file line by line and asserts a suggestion per finding.
+struct Suggester;
+
+impl Entity for Suggester {
+    fn on_message(&self, activation: &mut Activation, payload: &IOValue) -> ActorResult<()> {
+        let Some(review) = record_with_label(payload, REVIEW_LABEL) else {
+            return Ok(());
+        };
+        let contents = review.field_string(1).unwrap_or_default();
+
+        for (index, line) in contents.lines().enumerate() {
+            let finding = if line.contains(".unwrap()") {
+                Some((
+                    line.replace(".unwrap()", ".expect(\"invalid input\")"),
+                    "unwrap panics without context",
+                ))
+            } else if line.trim_start().starts_with("println!") {
+                Some((
+                    line.replace("println!", "log::info!"),
+                    "library code should log instead of printing",
+                ))
+            } else {
+                None
+            };
+
+            if let Some((replacement, rationale)) = finding {
+                let line_no = index + 1;
+                activation.assert(
+                    Handle::new(),
+                    IOValue::record(
+                        IOValue::symbol(SUGGESTION_LABEL),
+                        vec![
+                            IOValue::new(format!("L{line_no}")),
+                            IOValue::new(line_no as i64),
+                            IOValue::new(line.to_string()),
+                            IOValue::new(replacement),
+                            IOValue::new(rationale.to_string()),
+                        ],
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Judges a single suggestion: anything that needs a new dependency is rejected.
+struct Approver;
+
+impl Entity for Approver {
+    fn on_message(&self, activation: &mut Activation, payload: &IOValue) -> ActorResult<()> {
+        let Some(judge) = record_with_label(payload, JUDGE_LABEL) else {
+            return Ok(());
+        };
+        let id = judge.field_string(0).unwrap_or_default();
+        let replacement = judge.field_string(1).unwrap_or_default();
+
+        let (verdict, reason) = if replacement.contains("log::") {
+            ("rejected", "the crate does not depend on `log`")
+        } else {
+            ("approved", "behaviour preserved, failure is explained")
+        };
+        activation.assert(
+            Handle::new(),
+            IOValue::record(
+                IOValue::symbol(VERDICT_LABEL),
+                vec![
+                    IOValue::new(id),
+                    IOValue::symbol(verdict),
+                    IOValue::new(reason.to_string()),
+                ],
+            ),
+        );
+        Ok(())
+    }
+}
+
+/// A finding asserted by the suggester.
+#[derive(Debug, Clone)]
+struct Suggestion {
+    id: String,
+    line: usize,
+    original: String,
+    replacement: String,
+    rationale: String,
+}
+
+impl Suggestion {
+    fn from_value(value: &IOValue) -> Option<Self> {
+        let record = record_with_label(value, SUGGESTION_LABEL)?;
+        let line = record
+            .field(1)
+            .as_signed_integer()
+            .and_then(|line| i64::try_from(line.as_ref()).ok())?;
+        Some(Self {
+            id: record.field_string(0)?,
+            line: usize::try_from(line).ok()?,
+            original: record.field_string(2)?,
+            replacement: record.field_string(3)?,
+            rationale: record.field_string(4)?,
+        })
+    }
+}
+
+/// Outcome of one pipeline run.
+#[derive(Debug, Default)]
+struct PipelineReport {
+    approved: Vec<String>,
+    rejected: Vec<String>,
+    branches: Vec<String>,
+    merged_verdicts: Vec<String>,
+    final_contents: String,
+}
+
+/// A registered entity addressed by its actor and facet.
+#[derive(Clone)]
+struct Endpoint {
+    actor: ActorId,
+    facet: FacetId,
+}
+
+impl Endpoint {
+    fn register(control: &mut Control, entity_type: &str, config: IOValue) -> Result<Self> {
+        let endpoint = Self {
+            actor: ActorId::new(),
+            facet: FacetId::new(),
+        };
+        control.register_entity(
+            endpoint.actor.clone(),
+            endpoint.facet.clone(),
+            entity_type.to_string(),
+            config,
+        )?;
+        Ok(endpoint)
+    }
+
+    fn send(&self, control: &mut Control, payload: IOValue) -> Result<()> {
+        control.send_message(self.actor.clone(), self.facet.clone(), payload)?;
+        Ok(())
+    }
+}
+
+fn register_agents() {
+    codebase::register_codebase_entities();
+    let catalog = EntityCatalog::global();
+    catalog.register(SUGGESTER_TYPE, |_config| Ok(Box::new(Suggester)));
+    catalog.register(APPROVER_TYPE, |_config| Ok(Box::new(Approver)));
+}
+
+fn pipeline_error(message: impl Into<String>) -> RuntimeError {
+    RuntimeError::Init(message.into())
+}
+
+/// Ask the workspace for a capability of `kind` on the reviewed file.
+fn grant(
+    control: &mut Control,
+    workspace: &Endpoint,
+    request: &'static str,
+    kind: &str,
+) -> Result<uuid::Uuid> {
+    workspace.send(
+        control,
+        IOValue::record(
+            IOValue::symbol(request),
+            vec![IOValue::new(REVIEWED_FILE.to_string())],
+        ),
+    )?;
+    control
+        .list_capabilities()
+        .into_iter()
+        .find(|cap| cap.kind == kind)
+        .map(|cap| cap.id)
+        .ok_or_else(|| pipeline_error(format!("workspace did not grant {kind}")))
+}
+
+/// Verdicts visible on `branch`, keyed by suggestion id.
+fn verdicts_on(control: &Control, branch: &BranchId) -> Result<BTreeMap<String, bool>> {
+    Ok(control
+        .assertions_on(branch, None)?
+        .iter()
+        .filter_map(|info| {
+            let verdict = record_with_label(&info.value, VERDICT_LABEL)?;
+            Some((
+                verdict.field_string(0)?,
+                verdict.field_symbol(1)? == "approved",
+            ))
+        })
+        .collect())
+}
+
+fn run_pipeline(project: &Path, state_dir: PathBuf) -> Result<PipelineReport> {
+    register_agents();
+
+    let mut control = Control::init(RuntimeConfig {
+        root: state_dir,
+        snapshot_interval: 50,
+        flow_control_limit: 1000,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    })?;
+
+    let workspace = Endpoint::register(
+        &mut control,
+        "workspace",
+        IOValue::new(project.to_string_lossy().to_string()),
+    )?;
+    let suggester = Endpoint::register(&mut control, SUGGESTER_TYPE, IOValue::symbol("review"))?;
+    let approver = Endpoint::register(&mut control, APPROVER_TYPE, IOValue::symbol("review"))?;
+
+    // 1. Read the file through a workspace capability
+    workspace.send(&mut control, IOValue::symbol("workspace-rescan"))?;
+    let read_cap = grant(&mut control, &workspace, "workspace-read", "workspace/read")?;
+    let read_request = IOValue::record(
+        IOValue::symbol("workspace-read"),
+        vec![IOValue::new(REVIEWED_FILE.to_string())],
+    );
+    let contents = control
+        .invoke_capability(read_cap, read_request)?
+        .as_string()
+        .map(|text| text.to_string())
+        .ok_or_else(|| pipeline_error("workspace read did not return text"))?;
+
+    // 2. Ask the suggester for findings
+    suggester.send(
+        &mut control,
+        IOValue::record(
+            IOValue::symbol(REVIEW_LABEL),
+            vec![
+                IOValue::new(REVIEWED_FILE.to_string()),
+                IOValue::new(contents.clone()),
+            ],
+        ),
+    )?;
+    let mut suggestions: Vec<Suggestion> = control
+        .list_assertions_with_label(SUGGESTION_LABEL, Some(&suggester.actor))
+        .iter()
+        .filter_map(|info| Suggestion::from_value(&info.value))
+        .collect();
+    suggestions.sort_by_key(|suggestion| suggestion.line);
+
+    // 3. Explore every suggestion on its own fork and merge the approved ones
+    let main = BranchId::main();
+    let mut report = PipelineReport::default();
+    for suggestion in &suggestions {
+        let fork = control.fork(
+            main.clone(),
+            BranchId::new(format!("review/{}", suggestion.id)),
+            None,
+            BranchDetails {
+                description: Some(suggestion.rationale.clone()),
+                creator: Some("review-pipeline".to_string()),
+                tags: vec!["review".to_string()],
+            },
+        )?;
+        control.switch_branch(fork.clone())?;
+        approver.send(
+            &mut control,
+            IOValue::record(
+                IOValue::symbol(JUDGE_LABEL),
+                vec![
+                    IOValue::new(suggestion.id.clone()),
+                    IOValue::new(suggestion.replacement.clone()),
+                ],
+            ),
+        )?;
+        let approved = verdicts_on(&control, &fork)?
+            .get(&suggestion.id)
+            .copied()
+            .unwrap_or(false);
+        control.switch_branch(main.clone())?;
+
+        println!(
+            "{:>4} {:<8} {} ({})",
+            suggestion.id,
+            if approved { "approved" } else { "rejected" },
+            suggestion.replacement.trim(),
+            suggestion.rationale
+        );
+        if approved {
+            control.merge(fork.clone(), main.clone())?;
+            report.approved.push(suggestion.id.clone());
+        } else {
+            report.rejected.push(suggestion.id.clone());
+        }
+        report.branches.push(fork.0.clone());
+    }
+
+    // 4. Apply only what reached main through merges
+    let accepted = verdicts_on(&control, &main)?;
+    report.merged_verdicts = accepted.keys().cloned().collect();
+    let lines: Vec<String> = contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            suggestions
+                .iter()
+                .find(|suggestion| {
+                    suggestion.line == index + 1
+                        && suggestion.original == line
+                        && accepted.get(&suggestion.id) == Some(&true)
+                })
+                .map(|suggestion| suggestion.replacement.clone())
+                .unwrap_or_else(|| line.to_string())
+        })
+        .collect();
+    let revised = format!("{}\n", lines.join("\n"));
+
+    let write_cap = grant(
+        &mut control,
+        &workspace,
+        "workspace-write",
+        "workspace/write",
+    )?;
+    control.invoke_capability(
+        write_cap,
+        IOValue::record(
+            IOValue::symbol("workspace-write"),
+            vec![
+                IOValue::new(REVIEWED_FILE.to_string()),
+                IOValue::new(revised),
+            ],
+        ),
+    )?;
+
+    report.final_contents = fs::read_to_string(project.join(REVIEWED_FILE))
+        .map_err(|err| pipeline_error(format!("failed to read revised file: {err}")))?;
+    Ok(report)
+}
+
+fn prepare_project(project: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(project.join("src"))?;
+    fs::write(project.join(REVIEWED_FILE), SAMPLE_SOURCE)
+}
+
+fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let scratch = tempfile::TempDir::new()?;
+    let project = scratch.path().join("project");
+    prepare_project(&project)?;
+
+    let report = run_pipeline(&project, scratch.path().join("state"))?;
+    println!();
+    println!("approved: {}", report.approved.join(", "));
+    println!("rejected: {}", report.rejected.join(", "));
+    println!("review branches: {}", report.branches.join(", "));
+    println!();
+    print!("{}", report.final_contents);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipeline_merges_only_approved_suggestions() {
+        let scratch = tempfile::TempDir::new().unwrap();
+        let project = scratch.path().join("project");
+        prepare_project(&project).unwrap();
+
+        let report = run_pipeline(&project, scratch.path().join("state")).unwrap();
+
+        assert_eq!(report.approved, vec!["L2", "L8"]);
+        assert_eq!(report.rejected, vec!["L3"]);
+        assert_eq!(report.branches.len(), 3);
+        // Verdicts on rejected forks never reach main
+        assert_eq!(report.merged_verdicts, vec!["L2", "L8"]);
+        assert!(!report.final_contents.contains(".unwrap()"));
+        assert!(report.final_contents.contains("println!"));
+        assert!(!report.final_contents.contains("log::info!"));
+    }
+}
@@ -257,6 +257,9 @@ class CLIState:
     codebased_bin: Optional[Path]
     daemon_host: Optional[str]
     daemon_port: Optional[int]
+    tenant: Optional[str] = None
+    identity: Optional[str] = None
+    identity_token: Optional[str] = None
 
 
 def _show_group_help(ctx: typer.Context, examples: Optional[List[str]] = None) -> NoReturn:
@@ -316,6 +319,26 @@ def main(
         max=65535,
         rich_help_panel="Global Options",
     ),
+    tenant: Optional[str] = typer.Option(  # noqa: B008
+        None,
+        "--tenant",
+        help="Runtime to use on a daemon hosting several (see codebased --tenant).",
+        rich_help_panel="Global Options",
+    ),
+    identity: Optional[str] = typer.Option(  # noqa: B008
+        None,
+        "--as",
+        envvar="DUET_IDENTITY",
+        help="Identity recorded on the turns and branches this command causes.",
+        rich_help_panel="Global Options",
+    ),
+    identity_token: Optional[str] = typer.Option(  # noqa: B008
+        None,
+        "--identity-token",
+        envvar="DUET_IDENTITY_TOKEN",
+        help="Token authenticating --as against the daemon's configured principals.",
+        rich_help_panel="Global Options",
+    ),
 ) -> None:
     """Top-level callback storing shared CLI state."""
 
@@ -329,6 +352,9 @@ def main(
         codebased_bin=codebased_bin,
         daemon_host=daemon_host,
         daemon_port=daemon_port,
+        tenant=tenant,
+        identity=identity,
+        identity_token=identity_token,
     )
 
     if ctx.invoked_subcommand is None:
@@ -354,13 +380,43 @@ def history(
     branch: str = typer.Option("main", help="Branch name to inspect."),
     start: int = typer.Option(0, help="Starting index of the history slice."),
     limit: int = typer.Option(20, help="Number of turns to display."),
+    full: bool = typer.Option(False, "--full", help="Include every output with structured payloads."),
 ) -> None:
     """Show branch turn history."""
 
-    params = {"branch": branch, "start": start, "limit": limit}
+    params: Dict[str, Any] = {"branch": branch, "start": start, "limit": limit}
+    if full:
+        params["detail"] = "full"
     _run(_run_call(ctx.obj, "history", params, "history"))
 
 
+@time_app.command("actor-history")
+def actor_history(
+    ctx: typer.Context,
+    actor: str = typer.Argument(..., help="Actor identifier (UUID)."),
+    facet: Optional[str] = typer.Option(None, "--facet", help="Only turns involving this facet (UUID)."),
+    branch: str = typer.Option("main", help="Branch name to inspect."),
+    start: int = typer.Option(0, help="Starting index within the actor's turns."),
+    limit: int = typer.Option(20, help="Number of turns to display."),
+) -> None:
+    """Show the turns one actor executed."""
+
+    params: Dict[str, Any] = {"actor": actor, "branch": branch, "start": start, "limit": limit}
+    if facet:
+        params["facet"] = facet
+    _run(_run_call(ctx.obj, "actor_history", params, "actor-history"))
+
+
+@time_app.command("explain")
+def explain(
+    ctx: typer.Context,
+    turn_id: str = typer.Argument(..., help="Turn identifier to explain."),
+) -> None:
+    """Explain why a turn ran, what routed it, and which turns it triggered."""
+
+    _run(_run_call(ctx.obj, "explain", {"turn_id": turn_id}, "explain"))
+
+
 @debug_app.command("send")
 def send(
     ctx: typer.Context,
@@ -401,6 +457,494 @@ def list_capabilities(
     _run(_run_call(ctx.obj, "list_capabilities", params, "list-capabilities"))
 
 
+@debug_app.command("export-capability")
+def export_capability(
+    ctx: typer.Context,
+    capability: str = typer.Argument(..., help="Capability identifier (UUID)."),
+    caveat: List[str] = typer.Option([], "--caveat", "-c", help="Pattern every redemption payload must match, e.g. '(deploy $env _)'."),
+    expires_at: Optional[str] = typer.Option(None, help="RFC 3339 timestamp after which the token is rejected."),
+) -> None:
+    """Export a capability as a signed sturdy-ref token."""
+
+    params: Dict[str, Any] = {"capability": capability}
+    if caveat:
+        params["caveats"] = list(caveat)
+    if expires_at:
+        params["expires_at"] = expires_at
+    _run(_run_call(ctx.obj, "capability_export", params, "capability-export"))
+
+
+@debug_app.command("redeem-capability")
+def redeem_capability(
+    ctx: typer.Context,
+    token: str = typer.Argument(..., help="Sturdy-ref token produced by export-capability."),
+    payload: Optional[str] = typer.Option(None, help="Invocation payload in Preserves text syntax."),
+    sync: bool = typer.Option(False, "--sync", help="Wait until induced turns have run."),
+) -> None:
+    """Redeem a sturdy-ref token by invoking its capability."""
+
+    params: Dict[str, Any] = {"token": token, "sync": sync}
+    if payload:
+        params["payload"] = payload
+    _run(_run_call(ctx.obj, "capability_redeem", params, "capability-redeem"))
+
+
+@debug_app.command("approvals")
+def approvals(ctx: typer.Context) -> None:
+    """List capability invocations awaiting approval."""
+
+    _run(_run_call(ctx.obj, "approval_list", {}, "approvals"))
+
+
+@debug_app.command("approve")
+def approve(
+    ctx: typer.Context,
+    approval_id: str = typer.Argument(..., help="Identifier of the parked invocation."),
+) -> None:
+    """Approve a parked capability invocation so it runs."""
+
+    _run(_run_call(ctx.obj, "approve", {"approval_id": approval_id}, "approve"))
+
+
+@debug_app.command("deny")
+def deny(
+    ctx: typer.Context,
+    approval_id: str = typer.Argument(..., help="Identifier of the parked invocation."),
+    reason: Optional[str] = typer.Option(None, help="Reason recorded in the denial."),
+) -> None:
+    """Deny a parked capability invocation."""
+
+    params: Dict[str, Any] = {"approval_id": approval_id}
+    if reason:
+        params["reason"] = reason
+    _run(_run_call(ctx.obj, "deny", params, "deny"))
+
+
+@debug_app.command("describe-entity-type")
+def describe_entity_type(
+    ctx: typer.Context,
+    entity_type: str = typer.Argument(..., help="Registered entity type name."),
+) -> None:
+    """Show the messages, capability kinds and config an entity type advertises."""
+
+    _run(_run_call(ctx.obj, "describe_entity_type", {"entity_type": entity_type}, "describe-entity-type"))
+
+
+@debug_app.command("entity-state")
+def entity_state(
+    ctx: typer.Context,
+    entity_id: str = typer.Argument(..., help="Entity identifier (UUID)."),
+) -> None:
+    """Show one entity's hydrated private state."""
+
+    _run(_run_call(ctx.obj, "entity_state", {"entity_id": entity_id}, "entity-state"))
+
+
+@debug_app.command("set-entity-state")
+def set_entity_state(
+    ctx: typer.Context,
+    entity_id: str = typer.Argument(..., help="Entity identifier (UUID)."),
+    state: str = typer.Argument(..., help="Replacement state in Preserves text syntax."),
+    as_json: bool = typer.Option(False, "--json", help="Treat STATE as JSON instead of Preserves text."),
+) -> None:
+    """Overwrite one entity's private state (repair tool)."""
+
+    params: Dict[str, Any] = {"entity_id": entity_id, "state": state}
+    if as_json:
+        try:
+            params["state"] = json.loads(state)
+        except json.JSONDecodeError as exc:
+            raise typer.BadParameter(f"Invalid JSON state: {exc}", param_hint="state")
+    _run(_run_call(ctx.obj, "set_entity_state", params, "set-entity-state"))
+
+
+@debug_app.command("retract-matching")
+def retract_matching(
+    ctx: typer.Context,
+    actor: str = typer.Argument(..., help="Actor identifier (UUID) owning the assertions."),
+    pattern: str = typer.Argument(..., help="Pattern such as '(agent-request $id _ _)'; matching assertions are retracted."),
+    sync: bool = typer.Option(False, "--sync", help="Wait until induced turns have run."),
+) -> None:
+    """Retract every assertion of an actor matching a pattern in one turn."""
+
+    params = {"actor": actor, "pattern": pattern, "sync": sync}
+    _run(_run_call(ctx.obj, "retract_matching", params, "retract-matching"))
+
+
+@debug_app.command("send-message")
+def send_message(
+    ctx: typer.Context,
+    actor: str = typer.Argument(
+        ..., help="Target actor id, entity id, or entity type name with a single instance."
+    ),
+    payload: str = typer.Argument(..., help="Message payload in Preserves text syntax."),
+    facet: Optional[str] = typer.Option(
+        None, "--facet", help="Target facet (UUID). Defaults to the entity's or actor's root facet."
+    ),
+    as_json: bool = typer.Option(
+        False,
+        "--json",
+        help='Treat PAYLOAD as JSON; use {"$record": label, "fields": [...]} and {"$symbol": name} for records and symbols.',
+    ),
+    validate: bool = typer.Option(
+        False, "--validate", help="Reject payloads the target entity does not advertise."
+    ),
+    sync: bool = typer.Option(False, "--sync", help="Wait until induced turns have run."),
+) -> None:
+    """Send a message to an actor's facet."""
+
+    params: Dict[str, Any] = {"actor": actor, "payload": payload, "validate": validate, "sync": sync}
+    if as_json:
+        try:
+            params["payload"] = json.loads(payload)
+        except json.JSONDecodeError as exc:
+            raise typer.BadParameter(f"Invalid JSON payload: {exc}", param_hint="payload")
+    if facet:
+        params["facet"] = facet
+    _run(_run_call(ctx.obj, "send_message", params, "send-message"))
+
+
+@debug_app.command("invoke-capability")
+def invoke_capability(
+    ctx: typer.Context,
+    capability: str = typer.Argument(..., help="Capability identifier (UUID)."),
+    payload: str = typer.Argument(..., help="Invocation payload in Preserves text syntax."),
+    sync: bool = typer.Option(False, "--sync", help="Wait until induced turns have run."),
+) -> None:
+    """Invoke a capability and print its result."""
+
+    params = {"capability": capability, "payload": payload, "sync": sync}
+    _run(_run_call(ctx.obj, "invoke_capability", params, "invoke-capability"))
+
+
+@debug_app.command("set-log-level")
+def set_log_level(
+    ctx: typer.Context,
+    level: str = typer.Argument(..., help="Level: off, error, warn, info, debug or trace."),
+    subsystem: Optional[str] = typer.Option(
+        None, "--subsystem", help="Module path such as runtime::journal (default: all subsystems)."
+    ),
+) -> None:
+    """Change the daemon's log level while it runs."""
+
+    params: Dict[str, Any] = {"level": level}
+    if subsystem:
+        params["subsystem"] = subsystem
+    _run(_run_call(ctx.obj, "set_log_level", params, "set-log-level"))
+
+
+@debug_app.command("memory-report")
+def memory_report(
+    ctx: typer.Context,
+    limit: Optional[int] = typer.Option(None, "--limit", help="Show only the largest N actors."),
+) -> None:
+    """Show per-actor assertion, facet and byte counts with memory warnings."""
+
+    params: Dict[str, Any] = {}
+    if limit is not None:
+        params["limit"] = limit
+    _run(_run_call(ctx.obj, "memory_report", params, "memory-report"))
+
+
+@debug_app.command("doctor")
+def doctor(ctx: typer.Context) -> None:
+    """Run environment checks (storage, journals, entity factories, agent CLIs)."""
+
+    _run(_run_call(ctx.obj, "doctor", {}, "doctor"))
+
+
+@debug_app.command("tenants")
+def tenants(ctx: typer.Context) -> None:
+    """List the runtimes hosted by the daemon and the one this client uses."""
+
+    _run(_run_call(ctx.obj, "tenants", {}, "tenants"))
+
+
+@debug_app.command("service-stats")
+def service_stats(
+    ctx: typer.Context,
+    limit: Optional[int] = typer.Option(None, "--limit", help="Show only the N most recent commands."),
+) -> None:
+    """Show recent control-plane commands with their timings and per-command totals."""
+
+    params: Dict[str, Any] = {}
+    if limit is not None:
+        params["limit"] = limit
+    _run(_run_call(ctx.obj, "service_stats", params, "service-stats"))
+
+
+@debug_app.command("wait-register")
+def wait_register(
+    ctx: typer.Context,
+    branch: str = typer.Option("main", "--branch", help="Branch to watch."),
+    since: Optional[str] = typer.Option(None, "--since", help="Last turn already seen."),
+) -> None:
+    """Register a long-poll wait that can be polled across reconnects."""
+
+    params: Dict[str, Any] = {"branch": branch}
+    if since is not None:
+        params["since"] = since
+    _run(_run_call(ctx.obj, "wait_register", params, "wait-register"))
+
+
+@debug_app.command("wait-poll")
+def wait_poll(
+    ctx: typer.Context,
+    registration: str = typer.Argument(..., help="Registration identifier from wait-register."),
+    wait_ms: int = typer.Option(0, "--wait-ms", help="How long to wait for a new turn."),
+) -> None:
+    """Collect the turn a registered wait has queued, if any."""
+
+    params = {"registration": registration, "wait_ms": wait_ms}
+    _run(_run_call(ctx.obj, "wait_poll", params, "wait-poll"))
+
+
+@debug_app.command("wait-cancel")
+def wait_cancel(
+    ctx: typer.Context,
+    registration: str = typer.Argument(..., help="Registration identifier from wait-register."),
+) -> None:
+    """Drop a registered wait."""
+
+    _run(_run_call(ctx.obj, "wait_cancel", {"registration": registration}, "wait-cancel"))
+
+
+@debug_app.command("checkpoint-push")
+def checkpoint_push(
+    ctx: typer.Context,
+    store: str = typer.Argument(..., help="Artifact store: oci://host/name, https://base or a directory."),
+    branch: str = typer.Option("main", "--branch", help="Branch to export."),
+    reference: str = typer.Option("latest", "--reference", help="Tag to push the checkpoint under."),
+    token: Optional[str] = typer.Option(None, "--token", envvar="DUET_ARTIFACT_TOKEN", help="Bearer token for the store."),
+) -> None:
+    """Push a branch's journal, snapshot and entities as a checkpoint artifact."""
+
+    params: Dict[str, Any] = {"branch": branch, "store": store, "reference": reference}
+    if token is not None:
+        params["token"] = token
+    _run(_run_call(ctx.obj, "checkpoint_push", params, "checkpoint-push"))
+
+
+@debug_app.command("checkpoint-pull")
+def checkpoint_pull(
+    ctx: typer.Context,
+    store: str = typer.Argument(..., help="Artifact store: oci://host/name, https://base or a directory."),
+    new_branch: str = typer.Argument(..., help="Name of the branch to rebuild the checkpoint into."),
+    reference: str = typer.Option("latest", "--reference", help="Tag or sha256 digest to pull."),
+    token: Optional[str] = typer.Option(None, "--token", envvar="DUET_ARTIFACT_TOKEN", help="Bearer token for the store."),
+) -> None:
+    """Pull a checkpoint artifact and rebuild it as a new branch."""
+
+    params: Dict[str, Any] = {"store": store, "new_branch": new_branch, "reference": reference}
+    if token is not None:
+        params["token"] = token
+    _run(_run_call(ctx.obj, "checkpoint_pull", params, "checkpoint-pull"))
+
+
+@debug_app.command("assertion-schemas")
+def assertion_schemas(ctx: typer.Context) -> None:
+    """List the assertion types dataspace events can be filtered by."""
+
+    _run(_run_call(ctx.obj, "assertion_schemas", {}, "assertion-schemas"))
+
+
+@debug_app.command("branch-archive")
+def branch_archive(
+    ctx: typer.Context,
+    branch: str = typer.Argument(..., help="Branch to move to cold storage."),
+    dest: str = typer.Argument(..., help="Directory to write the archive into."),
+) -> None:
+    """Export a branch to an archive directory and drop it from the active branches."""
+
+    _run(_run_call(ctx.obj, "branch_archive", {"branch": branch, "dest": dest}, "branch-archive"))
+
+
+@debug_app.command("branch-unarchive")
+def branch_unarchive(
+    ctx: typer.Context,
+    branch: str = typer.Argument(..., help="Archived branch to restore."),
+) -> None:
+    """Restore an archived branch under its original name."""
+
+    _run(_run_call(ctx.obj, "branch_unarchive", {"branch": branch}, "branch-unarchive"))
+
+
+@debug_app.command("branch-delete")
+def branch_delete(
+    ctx: typer.Context,
+    branch: str = typer.Argument(..., help="Branch to delete."),
+    force: bool = typer.Option(False, "--force", help="Delete even if the branch has unmerged turns."),
+) -> None:
+    """Delete a branch with its journal, snapshots and metadata."""
+
+    _run(_run_call(ctx.obj, "delete_branch", {"branch": branch, "force": force}, "branch-delete"))
+
+
+@debug_app.command("outbox")
+def outbox(
+    ctx: typer.Context,
+    wait_ms: int = typer.Option(0, "--wait-ms", min=0, help="Wait this long for in-flight deliveries."),
+) -> None:
+    """Show outbox deliveries still waiting for an acknowledgement."""
+
+    _run(_run_call(ctx.obj, "outbox", {"wait_ms": wait_ms}, "outbox"))
+
+
+@debug_app.command("backup")
+def backup(
+    ctx: typer.Context,
+    dest: str = typer.Argument(..., help="Empty directory to write the backup into."),
+) -> None:
+    """Back up the runtime's storage without stopping the daemon."""
+
+    _run(_run_call(ctx.obj, "backup", {"dest": dest}, "backup"))
+
+
+@debug_app.command("flush")
+def flush(ctx: typer.Context) -> None:
+    """Fsync the journal so every committed turn survives a machine crash."""
+
+    _run(_run_call(ctx.obj, "flush", {}, "flush"))
+
+
+@debug_app.command("journal-compression")
+def journal_compression(
+    ctx: typer.Context,
+    train: bool = typer.Option(False, "--train", help="Train a dictionary on recent journal records and enable it."),
+    disable: bool = typer.Option(False, "--disable", help="Stop compressing new journal records."),
+    max_samples: Optional[int] = typer.Option(None, "--max-samples", help="Number of most recent records to sample."),
+    max_size: Optional[int] = typer.Option(None, "--max-size", help="Upper bound on the dictionary size in bytes."),
+    level: Optional[int] = typer.Option(None, "--level", help="zstd compression level."),
+) -> None:
+    """Show, train or disable dictionary compression of journal records."""
+
+    if train and disable:
+        raise typer.BadParameter("--train and --disable are mutually exclusive")
+    if disable:
+        _run(_run_call(ctx.obj, "journal_dictionary_disable", {}, "journal-compression"))
+        return
+    if not train:
+        _run(_run_call(ctx.obj, "journal_dictionary", {}, "journal-compression"))
+        return
+
+    params: Dict[str, Any] = {}
+    if max_samples is not None:
+        params["max_samples"] = max_samples
+    if max_size is not None:
+        params["max_dictionary_size"] = max_size
+    if level is not None:
+        params["level"] = level
+    _run(_run_call(ctx.obj, "journal_dictionary_train", params, "journal-compression"))
+
+
+@debug_app.command("compact")
+def compact(
+    ctx: typer.Context,
+    branch: Optional[str] = typer.Option(
+        None, "--branch", help="Branch whose journal to compact (defaults to the current branch)."
+    ),
+    keep_turns: Optional[int] = typer.Option(None, "--keep-turns", help="Keep at least this many recent turns."),
+    keep_days: Optional[int] = typer.Option(None, "--keep-days", help="Keep turns recorded within this many days."),
+) -> None:
+    """Compact a branch journal below its retention horizon (configured policy unless overridden)."""
+
+    params: Dict[str, Any] = {}
+    if branch:
+        params["branch"] = branch
+    if keep_turns is not None:
+        params["keep_turns"] = keep_turns
+    if keep_days is not None:
+        params["keep_days"] = keep_days
+    _run(_run_call(ctx.obj, "compact", params, "compact"))
+
+
+@debug_app.command("snapshot-prune")
+def snapshot_prune(
+    ctx: typer.Context,
+    branch: str = typer.Option("main", "--branch", help="Branch whose snapshots to prune."),
+    keep_last: Optional[int] = typer.Option(None, "--keep-last", help="Keep this many of the most recent snapshots."),
+    keep_every: Optional[int] = typer.Option(None, "--keep-every", help="Keep one snapshot per this many turns."),
+    keep_tagged: Optional[bool] = typer.Option(
+        None, "--keep-tagged/--drop-tagged", help="Whether tagged snapshots are kept."
+    ),
+) -> None:
+    """Delete snapshots outside the snapshot retention (configured policy unless overridden)."""
+
+    params: Dict[str, Any] = {"branch": branch}
+    if keep_last is not None:
+        params["keep_last"] = keep_last
+    if keep_every is not None:
+        params["keep_every"] = keep_every
+    if keep_tagged is not None:
+        params["keep_tagged"] = keep_tagged
+    _run(_run_call(ctx.obj, "prune_snapshots", params, "snapshot-prune"))
+
+
+@debug_app.command("snapshot-tag")
+def snapshot_tag(
+    ctx: typer.Context,
+    turn_id: str = typer.Argument(..., help="Turn the snapshot was taken at."),
+    tag: str = typer.Argument(..., help="Tag to attach."),
+    branch: str = typer.Option("main", "--branch", help="Branch the snapshot belongs to."),
+) -> None:
+    """Tag a snapshot so snapshot retention keeps it."""
+
+    params: Dict[str, Any] = {"branch": branch, "turn_id": turn_id, "tag": tag}
+    _run(_run_call(ctx.obj, "tag_snapshot", params, "snapshot-tag"))
+
+
+@debug_app.command("fixture-run")
+def fixture_run(
+    ctx: typer.Context,
+    path: Path = typer.Argument(..., help="Preserves fixture file describing inputs and expected reaction outputs."),
+) -> None:
+    """Run reaction/pattern regression fixtures in scratch runtimes."""
+
+    _run(_run_call(ctx.obj, "fixture_run", {"path": str(path.resolve())}, "fixture-run"))
+
+
+@debug_app.command("pause-actor")
+def pause_actor(
+    ctx: typer.Context,
+    actor: str = typer.Argument(..., help="Actor identifier (UUID) to pause."),
+) -> None:
+    """Hold an actor's turns while everything else keeps running."""
+
+    _run(_run_call(ctx.obj, "pause_actor", {"actor": actor}, "pause-actor"))
+
+
+@debug_app.command("resume-actor")
+def resume_actor(
+    ctx: typer.Context,
+    actor: str = typer.Argument(..., help="Actor identifier (UUID) to resume."),
+) -> None:
+    """Resume a paused actor, releasing its held inputs."""
+
+    _run(_run_call(ctx.obj, "resume_actor", {"actor": actor}, "resume-actor"))
+
+
+@debug_app.command("flags")
+def flags(ctx: typer.Context) -> None:
+    """Show the runtime feature flags and whether each was set or defaulted."""
+
+    _run(_run_call(ctx.obj, "flags", {}, "flags"))
+
+
+@debug_app.command("set-flag")
+def set_flag(
+    ctx: typer.Context,
+    flag: str = typer.Argument(..., help="Flag name (debug, chaos, approval-gates)."),
+    enabled: bool = typer.Argument(..., help="New value (true/false)."),
+) -> None:
+    """Switch a runtime feature flag in a journaled administrative turn."""
+
+    _run(
+        _run_call(
+            ctx.obj, "set_flag", {"flag": flag, "enabled": enabled}, "set-flag"
+        )
+    )
+
+
 @time_app.command("goto")
 def goto(
     ctx: typer.Context,
@@ -429,6 +973,36 @@ def back(
     _run(_run_call(ctx.obj, "back", params, "back"))
 
 
+@time_app.command("reflog")
+def reflog(
+    ctx: typer.Context,
+    branch: Optional[str] = typer.Option(None, help="Branch to inspect (defaults to the current branch)."),
+    limit: Optional[int] = typer.Option(None, min=1, help="Show at most this many entries."),
+) -> None:
+    """Show where a branch head has been, newest first."""
+
+    params: Dict[str, Any] = {}
+    if branch:
+        params["branch"] = branch
+    if limit is not None:
+        params["limit"] = limit
+    _run(_run_call(ctx.obj, "reflog", params, "reflog"))
+
+
+@time_app.command("restore")
+def restore_head(
+    ctx: typer.Context,
+    index: int = typer.Argument(..., min=0, help="Reflog entry to return to (0 = latest movement)."),
+    branch: Optional[str] = typer.Option(None, help="Switch to this branch before executing the command."),
+) -> None:
+    """Put the branch head back where a reflog entry left it."""
+
+    params: Dict[str, Any] = {"index": index}
+    if branch:
+        params["branch"] = branch
+    _run(_run_call(ctx.obj, "restore_head", params, "restore"))
+
+
 @time_app.command("fork")
 def fork(
     ctx: typer.Context,
@@ -456,6 +1030,18 @@ def merge(
     _run(_run_call(ctx.obj, "merge", params, "merge"))
 
 
+@time_app.command("merge-forecast")
+def merge_forecast(
+    ctx: typer.Context,
+    source: str = typer.Option(..., help="Name of the branch that would be merged."),
+    target: str = typer.Option(..., help="Name of the branch it would be merged into."),
+) -> None:
+    """Preview the warnings and assertion changes of a merge without merging."""
+
+    params = {"source": source, "target": target}
+    _run(_run_call(ctx.obj, "merge_forecast", params, "merge-forecast"))
+
+
 @debug_app.command("invoke-capability")
 def invoke_capability(
     ctx: typer.Context,
@@ -488,6 +1074,37 @@ def workspace_entries(ctx: typer.Context) -> None:
     _run(_run_call(ctx.obj, "workspace_entries", {}, "workspace:entries"))
 
 
+@debug_app.command("workspace-restore")
+def workspace_restore(
+    ctx: typer.Context,
+    path: str = typer.Argument(..., help="Workspace-relative path to restore."),
+    turn_id: str = typer.Argument(..., help="Turn whose write should be undone."),
+) -> None:
+    """Restore a workspace file to its content before a turn wrote it."""
+
+    params = {"path": path, "turn_id": turn_id}
+    _run(_run_call(ctx.obj, "workspace_restore", params, "workspace:restore"))
+
+
+@debug_app.command("workspace-offers")
+def workspace_offers(ctx: typer.Context) -> None:
+    """List capability offers awaiting acceptance."""
+
+    _run(_run_call(ctx.obj, "workspace_offers", {}, "workspace:offers"))
+
+
+@debug_app.command("workspace-accept")
+def workspace_accept(
+    ctx: typer.Context,
+    offer_id: str = typer.Argument(..., help="Offer identifier (UUID)."),
+    decline: bool = typer.Option(False, "--decline", help="Decline the offer instead of accepting it."),
+) -> None:
+    """Accept (or decline) a pending workspace capability offer."""
+
+    command = "workspace_offer_decline" if decline else "workspace_offer_accept"
+    _run(_run_call(ctx.obj, command, {"offer_id": offer_id}, "workspace:accept"))
+
+
 @debug_app.command("agent-invoke")
 def agent_invoke(
     ctx: typer.Context,
@@ -750,7 +1367,7 @@ def reaction_register(
     ctx: typer.Context,
     actor: str = typer.Option(..., help="Actor identifier (UUID) that owns the reaction."),
     facet: str = typer.Option(..., help="Facet identifier (UUID) that scopes the pattern."),
-    pattern: str = typer.Option(..., help="Pattern expression encoded as Preserves text."),
+    pattern: str = typer.Option(..., help="Pattern such as '(agent-request $id _ _)'."),
     effect: str = typer.Option(
         "assert",
         help="Reaction effect type (assert or send-message).",
@@ -780,6 +1397,11 @@ def reaction_register(
         help="Use the Nth element from the matched value as the message payload.",
         min=0,
     ),
+    priority: int = typer.Option(0, help="Dispatch priority; higher fires first on the same assertion."),
+    consume: bool = typer.Option(False, "--consume", help="Stop lower-priority reactions once this one fires."),
+    dataspace_wide: bool = typer.Option(
+        False, "--dataspace-wide", help="Match assertions made by any actor, not just the owner."
+    ),
 ) -> None:
     """Register a new reaction."""
 
@@ -860,6 +1482,9 @@ def reaction_register(
         "facet": facet,
         "pattern": pattern,
         "effect": effect_payload,
+        "priority": priority,
+        "consume": consume,
+        "scope": "dataspace" if dataspace_wide else "actor",
     }
 
     _run(_run_call(ctx.obj, "reaction_register", params, "reaction:register"))
@@ -876,6 +1501,28 @@ def reaction_unregister(
     _run(_run_call(ctx.obj, "reaction_unregister", params, "reaction:unregister"))
 
 
+@reaction_app.command("pattern")
+def reaction_pattern(
+    ctx: typer.Context,
+    entity: str = typer.Argument(..., help="Entity identifier (UUID) that receives the matches."),
+    pattern: str = typer.Argument(..., help="Pattern such as '(agent-request $id _ _)'."),
+    namespace: Optional[str] = typer.Option(None, help="Named dataspace to observe."),
+    dataspace_wide: bool = typer.Option(
+        False, "--dataspace-wide", help="Match assertions made by any actor, not just the entity's."
+    ),
+) -> None:
+    """Subscribe an entity to a dataspace pattern."""
+
+    params: Dict[str, Any] = {
+        "entity": entity,
+        "pattern": pattern,
+        "scope": "dataspace" if dataspace_wide else "actor",
+    }
+    if namespace:
+        params["namespace"] = namespace
+    _run(_run_call(ctx.obj, "pattern_register", params, "reaction:pattern"))
+
+
 @reaction_app.command("list")
 def reaction_list(ctx: typer.Context) -> None:
     """List registered reactions."""
@@ -891,6 +1538,10 @@ def dataspace_assertions(
     request_id: Optional[str] = typer.Option(
         None, help="Only include assertions whose first field matches this request identifier."
     ),
+    namespace: Optional[str] = typer.Option(None, help="Restrict to a named dataspace."),
+    pattern: Optional[str] = typer.Option(
+        None, help="Only include assertions matching a pattern such as '(agent-request $id _ _)'."
+    ),
     limit: Optional[int] = typer.Option(None, help="Maximum number of assertions to return."),
 ) -> None:
     """Inspect assertions currently in the dataspace."""
@@ -902,6 +1553,10 @@ def dataspace_assertions(
         params["label"] = label
     if request_id:
         params["request_id"] = request_id
+    if namespace:
+        params["namespace"] = namespace
+    if pattern:
+        params["pattern"] = pattern
     if limit is not None:
         params["limit"] = limit
 
@@ -1054,6 +1709,7 @@ def dataspace_tail(
     limit: int = typer.Option(10, help="Number of events to return per request.", min=1),
     actor: Optional[str] = typer.Option(None, help="Filter by actor identifier (UUID)."),
     label: Optional[str] = typer.Option(None, help="Filter by record label or symbol."),
+    schema: Optional[str] = typer.Option(None, help="Filter by assertion type (e.g. AgentResponse); see `debug assertion-schemas`."),
     request_id: Optional[str] = typer.Option(None, help="Only include events whose first field matches this request identifier."),
     request_select: bool = typer.Option(
         False,
@@ -1061,6 +1717,7 @@ def dataspace_tail(
         help="Interactively choose a request identifier to filter events.",
     ),
     event_type: List[str] = typer.Option([], "--event-type", "-e", help="Restrict to specific event types (assert or retract)."),
+    namespace: Optional[str] = typer.Option(None, help="Restrict to a named dataspace."),
     follow: bool = typer.Option(False, help="Continue polling for new events."),
     interval: float = typer.Option(1.0, help="Polling interval in seconds when following.", min=0.1),
 ) -> None:
@@ -1081,10 +1738,14 @@ def dataspace_tail(
         params["actor"] = actor
     if label:
         params["label"] = label
+    if schema:
+        params["schema"] = schema
     if request_id:
         params["request_id"] = request_id
     if event_type:
         params["event_types"] = [et.lower() for et in event_type]
+    if namespace:
+        params["namespace"] = namespace
     if since:
         params["since"] = since
     if follow:
@@ -1582,12 +2243,18 @@ async def _connect_client(state: CLIState) -> ControlClient:
             else:
                 _clear_daemon_state(root)
 
+    identity: Optional[Dict[str, Any]] = None
+    if state.identity:
+        identity = {"id": state.identity}
+        if state.identity_token:
+            identity["token"] = state.identity_token
+
     if runtime_addr:
-        client = ControlClient(runtime_addr=runtime_addr)
+        client = ControlClient(runtime_addr=runtime_addr, tenant=state.tenant, identity=identity)
     else:
         cmd = list(_codebased_command(state))
         cmd.extend(["--root", str(root)])
-        client = ControlClient(tuple(cmd))
+        client = ControlClient(tuple(cmd), tenant=state.tenant, identity=identity)
     await client.connect()
     return client
 
@@ -2109,6 +2776,9 @@ def _print_status(result: Any) -> None:
     tree = Tree(f"[bold cyan]Branch[/bold cyan] [white]{branch}[/white]")
     tree.add(f"[dim]Head Turn:[/dim] {head_turn}")
     tree.add(f"[dim]Pending Inputs:[/dim] {pending_inputs}")
+    paused_actors = result.get("paused_actors") or []
+    if paused_actors:
+        tree.add(f"[dim]Paused Actors:[/dim] {', '.join(paused_actors)}")
     tree.add(f"[dim]Snapshot Interval:[/dim] {snapshot_interval}")
 
     console.print(Panel.fit(tree, title="[bold]Runtime Status[/bold]", border_style="cyan"))
@@ -2124,22 +2794,77 @@ def _print_history(result: Any) -> None:
         console.print("[yellow]No turns recorded[/yellow]")
         return
 
+    if any("outputs" in turn for turn in turns):
+        console.print(JSON.from_data(result))
+        return
+
     table = Table(title="Turn History", border_style="blue")
     table.add_column("Turn ID", style="cyan", no_wrap=True)
     table.add_column("Actor", style="magenta", no_wrap=True)
-    table.add_column("Clock", style="yellow", justify="right")
-    table.add_column("Inputs", style="green", justify="right")
+    table.add_column("Logical Time", style="yellow", justify="right")
+    table.add_column("Inputs", style="green")
     table.add_column("Outputs", style="green", justify="right")
-    table.add_column("Timestamp", style="dim")
+    table.add_column("By", style="blue", no_wrap=True)
+    table.add_column("Wall Clock", style="dim")
 
     for turn in turns:
         turn_id = str(turn.get("turn_id", ""))[:16] + "..."
         actor = str(turn.get("actor", ""))[:12] + "..."
-        clock = str(turn.get("clock", 0))
-        inputs = str(turn.get("input_count", 0))
-        outputs = str(turn.get("output_count", 0))
+        logical = turn.get("logical_time")
+        if isinstance(logical, dict):
+            clock = f"{logical.get('sequence', 0)}.{logical.get('clock', 0)}"
+        else:
+            clock = str(turn.get("clock", 0))
+        input_views = turn.get("inputs")
+        if isinstance(input_views, list) and input_views:
+            inputs = ", ".join(
+                f"{view.get('kind', '?')} {view['summary']}" if view.get("summary") else str(view.get("kind", "?"))
+                for view in input_views
+            )
+        else:
+            inputs = str(turn.get("input_count", 0))
+        counts = turn.get("counts")
+        if isinstance(counts, dict):
+            outputs = f"+{counts.get('asserts', 0)} -{counts.get('retracts', 0)} {counts.get('messages', 0)} msg"
+        else:
+            outputs = str(turn.get("output_count", 0))
+        initiator = turn.get("initiator")
+        by = str(initiator.get("id", "")) if isinstance(initiator, dict) else ""
         timestamp = turn.get("timestamp", "N/A")
-        table.add_row(turn_id, actor, clock, inputs, outputs, timestamp)
+        table.add_row(turn_id, actor, clock, inputs, outputs, by, timestamp)
+
+    console.print(table)
+
+
+def _print_reflog(result: Any) -> None:
+    if not isinstance(result, dict) or "entries" not in result:
+        console.print(JSON.from_data(result))
+        return
+
+    entries = result["entries"]
+    if not entries:
+        console.print("[yellow]No head movements recorded[/yellow]")
+        return
+
+    table = Table(title=f"Reflog of {result.get('branch', '?')}", border_style="blue")
+    table.add_column("#", style="yellow", justify="right")
+    table.add_column("Action", style="green", no_wrap=True)
+    table.add_column("From", style="dim", no_wrap=True)
+    table.add_column("To", style="cyan", no_wrap=True)
+    table.add_column("By", style="blue", no_wrap=True)
+    table.add_column("When", style="dim")
+
+    for entry in entries:
+        client = entry.get("client")
+        by = str(client.get("id", "")) if isinstance(client, dict) else ""
+        table.add_row(
+            str(entry.get("index", "")),
+            str(entry.get("action", "")),
+            str(entry.get("from", ""))[:16] + "...",
+            str(entry.get("to", ""))[:16] + "...",
+            by,
+            str(entry.get("at", "")),
+        )
 
     console.print(table)
 
@@ -3371,13 +4096,15 @@ def _print_workspace_write(result: Any) -> None:
 def _print_result(result: Any, command: str) -> None:
     if command == "status":
         _print_status(result)
-    elif command == "history":
+    elif command in {"history", "actor-history"}:
         _print_history(result)
     elif command == "list-entities":
         _print_entities(result)
     elif command == "list-capabilities":
         _print_capabilities(result)
-    elif command in ("goto", "back", "fork", "merge"):
+    elif command == "reflog":
+        _print_reflog(result)
+    elif command in ("goto", "back", "fork", "merge", "restore"):
         _print_navigation_result(result, command)
     elif command in ("send", "invoke-capability", "workspace:scan", "workspace:write", "raw"):
         _print_operation_result(result, command)
@@ -27,11 +27,15 @@ class ControlClient:
         self,
         runtime_cmd: Optional[Tuple[str, ...]] = None,
         runtime_addr: Optional[Tuple[str, int]] = None,
+        tenant: Optional[str] = None,
+        identity: Optional[Dict[str, Any]] = None,
     ) -> None:
         if runtime_cmd is None and runtime_addr is None:
             raise ValueError("either runtime_cmd or runtime_addr must be provided")
         self._runtime_cmd = runtime_cmd
         self._runtime_addr = runtime_addr
+        self._tenant = tenant
+        self._identity = identity
         self._process: asyncio.subprocess.Process | None = None
         self._reader: asyncio.StreamReader | None = None
         self._writer: asyncio.StreamWriter | None = None
@@ -110,13 +114,15 @@ class ControlClient:
         return response
 
     async def _handshake(self) -> None:
-        await self._send(
-            "handshake",
-            {
-                "client": "duet-cli",
-                "protocol_version": PROTOCOL_VERSION,
-            },
-        )
+        params: Dict[str, Any] = {
+            "client": "duet-cli",
+            "protocol_version": PROTOCOL_VERSION,
+        }
+        if self._tenant is not None:
+            params["tenant"] = self._tenant
+        if self._identity is not None:
+            params["identity"] = self._identity
+        await self._send("handshake", params)
 
     async def _send(self, command: str, params: Dict[str, Any]) -> Any:
         if self._writer is None or self._reader is None:
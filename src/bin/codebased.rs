@@ -1,7 +1,8 @@
 //! `codebased` – Codebase daemon built on the Duet runtime.
 
-use duet::codebase;
-use duet::runtime::{Control, RuntimeConfig};
+use duet::codebase::template::{self, InitTemplate};
+use duet::runtime::manager::RuntimeManager;
+use duet::runtime::{Control, RuntimeConfig, logging};
 use duet::service::Service;
 use std::env;
 use std::io::{self, BufReader, BufWriter};
@@ -13,6 +14,15 @@ fn main() -> io::Result<()> {
     let mut root: Option<PathBuf> = None;
     let mut init_storage = true;
     let mut listen_addr: Option<String> = None;
+    let mut approval_kinds: Vec<String> = Vec::new();
+    let mut log_levels: Vec<String> = Vec::new();
+    let mut init_template = InitTemplate::default();
+    let mut tenants: Vec<(String, PathBuf)> = Vec::new();
+    let mut tenant_dir: Option<PathBuf> = None;
+    #[cfg(feature = "dashboard")]
+    let mut dashboard_addr: Option<String> = None;
+    #[cfg(feature = "grpc")]
+    let mut grpc_addr: Option<String> = None;
 
     while let Some(arg) = args.next() {
         match arg.as_str() {
@@ -30,6 +40,46 @@ fn main() -> io::Result<()> {
                 };
                 root = Some(PathBuf::from(path));
             }
+            "--tenant" => {
+                let spec = match args.next() {
+                    Some(spec) => spec,
+                    None => {
+                        eprintln!("--tenant requires a NAME=PATH argument");
+                        print_usage();
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "missing value for --tenant",
+                        ));
+                    }
+                };
+                match spec.split_once('=') {
+                    Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+                        tenants.push((name.to_string(), PathBuf::from(path)));
+                    }
+                    _ => {
+                        eprintln!("--tenant expects NAME=PATH, got {spec}");
+                        print_usage();
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "invalid value for --tenant",
+                        ));
+                    }
+                }
+            }
+            "--tenant-dir" => {
+                let path = match args.next() {
+                    Some(path) => path,
+                    None => {
+                        eprintln!("--tenant-dir requires a path argument");
+                        print_usage();
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "missing value for --tenant-dir",
+                        ));
+                    }
+                };
+                tenant_dir = Some(PathBuf::from(path));
+            }
             "--no-init" => {
                 init_storage = false;
             }
@@ -50,6 +100,78 @@ fn main() -> io::Result<()> {
                 };
                 listen_addr = Some(addr);
             }
+            "--require-approval" => {
+                let kind = match args.next() {
+                    Some(kind) => kind,
+                    None => {
+                        eprintln!("--require-approval requires a capability kind argument");
+                        print_usage();
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "missing value for --require-approval",
+                        ));
+                    }
+                };
+                approval_kinds.push(kind);
+            }
+            "--template" => {
+                let name = match args.next() {
+                    Some(name) => name,
+                    None => {
+                        eprintln!("--template requires a template name");
+                        print_usage();
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "missing value for --template",
+                        ));
+                    }
+                };
+                init_template = name.parse().map_err(to_io_error)?;
+            }
+            "--log" => {
+                let level = match args.next() {
+                    Some(level) => level,
+                    None => {
+                        eprintln!("--log requires a LEVEL or SUBSYSTEM=LEVEL argument");
+                        print_usage();
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "missing value for --log",
+                        ));
+                    }
+                };
+                log_levels.push(level);
+            }
+            #[cfg(feature = "dashboard")]
+            "--dashboard" => {
+                let addr = match args.next() {
+                    Some(addr) => addr,
+                    None => {
+                        eprintln!("--dashboard requires an address argument");
+                        print_usage();
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "missing value for --dashboard",
+                        ));
+                    }
+                };
+                dashboard_addr = Some(addr);
+            }
+            #[cfg(feature = "grpc")]
+            "--grpc" => {
+                let addr = match args.next() {
+                    Some(addr) => addr,
+                    None => {
+                        eprintln!("--grpc requires an address argument");
+                        print_usage();
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "missing value for --grpc",
+                        ));
+                    }
+                };
+                grpc_addr = Some(addr);
+            }
             "--help" | "-h" => {
                 print_usage();
                 return Ok(());
@@ -69,6 +191,17 @@ fn main() -> io::Result<()> {
     if let Some(root_path) = root {
         config.root = root_path;
     }
+    config.approval_kinds = approval_kinds;
+    for spec in &log_levels {
+        let result = match spec.split_once('=') {
+            Some((subsystem, level)) => config.logging.set(Some(subsystem), level),
+            None => config.logging.set(None, spec),
+        };
+        result.map_err(to_io_error)?;
+    }
+    if let Err(err) = logging::init(&config.logging.effective(config.debug)) {
+        eprintln!("Failed to initialize logging: {err}");
+    }
 
     let workspace_root = config.root.clone();
 
@@ -80,45 +213,57 @@ fn main() -> io::Result<()> {
         Control::new(config).map_err(to_io_error)?
     };
 
-    if let Err(err) = codebase::ensure_workspace_entity(&mut control, &workspace_root) {
-        eprintln!("Failed to ensure workspace entity: {err}");
+    if let Err(err) = template::apply_template(&mut control, init_template, &workspace_root) {
+        eprintln!("Failed to apply {init_template} template: {err}");
     }
 
-    if let Err(err) = codebase::ensure_claude_agent(&mut control) {
-        eprintln!("Failed to ensure Claude agent: {err}");
+    #[cfg(feature = "dashboard")]
+    if let Some(addr) = dashboard_addr {
+        return run_dashboard(control, &addr);
     }
 
-    if let Err(err) = codebase::ensure_codex_agent(&mut control) {
-        eprintln!("Failed to ensure Codex agent: {err}");
+    #[cfg(feature = "grpc")]
+    if let Some(addr) = grpc_addr {
+        return run_grpc(control, &addr);
     }
 
-    if let Err(err) = codebase::ensure_harness_agent(&mut control) {
-        eprintln!("Failed to ensure harness agent: {err}");
+    // Other tenants are opened on first use, initialized from the same template
+    let mut runtimes = RuntimeManager::new("default", control);
+    runtimes.on_open(move |control| {
+        let root = control.runtime().config().root.clone();
+        if let Err(err) = template::apply_template(control, init_template, &root) {
+            eprintln!("Failed to apply {init_template} template: {err}");
+        }
+    });
+    for (name, path) in tenants {
+        runtimes.register(name, path).map_err(to_io_error)?;
+    }
+    if let Some(dir) = tenant_dir {
+        runtimes.allow_roots_under(dir);
     }
+    let service = Service::with_manager(runtimes);
 
     if let Some(addr) = listen_addr {
-        return run_tcp(control, &addr);
+        return run_tcp(service, &addr);
     }
 
-    run_stdio(control)
+    run_stdio(service)
 }
 
-fn run_stdio(control: Control) -> io::Result<()> {
+fn run_stdio(mut service: Service) -> io::Result<()> {
     let stdin = io::stdin();
     let stdout = io::stdout();
     let reader = stdin.lock();
     let writer = BufWriter::new(stdout.lock());
 
-    let mut service = Service::new(control);
     service.handle(reader, writer)
 }
 
-fn run_tcp(control: Control, addr: &str) -> io::Result<()> {
+fn run_tcp(mut service: Service, addr: &str) -> io::Result<()> {
     let listener = TcpListener::bind(addr)?;
     let actual = listener.local_addr()?;
     eprintln!("codebased listening on {}", actual);
 
-    let mut service = Service::new(control);
     for incoming in listener.incoming() {
         match incoming {
             Ok(stream) => {
@@ -138,15 +283,44 @@ fn run_tcp(control: Control, addr: &str) -> io::Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "dashboard")]
+fn run_dashboard(control: Control, addr: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let actual = listener.local_addr()?;
+    let dashboard = duet::service::dashboard::Dashboard::new(control);
+    eprintln!("codebased dashboard at {}", dashboard.url(actual));
+
+    dashboard.serve(listener)
+}
+
+#[cfg(feature = "grpc")]
+fn run_grpc(control: Control, addr: &str) -> io::Result<()> {
+    let addr: std::net::SocketAddr = addr.parse().map_err(|err| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("--grpc {addr}: {err}"))
+    })?;
+    eprintln!("codebased gRPC control plane on {}", addr);
+
+    tokio::runtime::Runtime::new()?.block_on(duet::service::grpc::serve(control, addr))
+}
+
 fn print_usage() {
     eprintln!(
-        "Usage: codebased [--root PATH] [--no-init] [--stdio] [--listen ADDR]\n\
+        "Usage: codebased [--root PATH] [--no-init] [--template NAME] [--stdio] [--listen ADDR]\n\
+                   [--tenant NAME=PATH]... [--tenant-dir PATH] [--require-approval KIND]...\n\
+                   [--log [SUBSYSTEM=]LEVEL]...\n\
          \n\
          Options:\n\
            --root PATH   Runtime root directory (default: nearest .duet folder)\n\
            --no-init     Skip storage initialization (assumes existing data)\n\
+           --template NAME  Project template: bare, codebase-daemon (default), agent-sandbox\n\
+           --tenant NAME=PATH  Also host the runtime rooted at PATH; clients select it in the handshake\n\
+           --tenant-dir PATH  Let clients host new runtimes under PATH by naming a root in the handshake\n\
            --stdio       Communicate over stdin/stdout (default)\n\
-           --listen ADDR Listen on TCP ADDR instead of stdio\n"
+           --listen ADDR Listen on TCP ADDR instead of stdio\n\
+           --require-approval KIND  Park invocations of capability KIND until approved\n\
+           --log [SUBSYSTEM=]LEVEL  Log level, optionally for a subsystem such as runtime::journal\n\
+           --dashboard ADDR  Serve the web dashboard on ADDR (requires the `dashboard` feature)\n\
+           --grpc ADDR   Serve the control plane over gRPC on ADDR (requires the `grpc` feature)\n"
     );
 }
 
@@ -4,11 +4,11 @@ use super::{
     AgentEntity, AgentExchange, REQUEST_LABEL, RESPONSE_LABEL, exchanges_from_preserves,
     exchanges_to_preserves, parse_response_fields, response_fields,
 };
-use crate::runtime::AsyncMessage;
 use crate::runtime::actor::{Activation, Entity, HydratableEntity};
 use crate::runtime::error::{ActorError, ActorResult};
-use crate::runtime::registry::EntityCatalog;
+use crate::runtime::registry::{EntityCatalog, EntityDescriptor};
 use crate::runtime::turn::{Handle, TurnOutput};
+use crate::util::canonical::canonical_timestamp;
 use crate::util::io_value::record_with_label;
 use chrono::Utc;
 use once_cell::sync::Lazy;
@@ -49,6 +49,13 @@ pub fn set_external_command(command: Option<String>, args: Vec<String>) {
     settings.args = args;
 }
 
+/// Advisory check that the configured CLI can be found.
+pub(crate) fn doctor_check() -> crate::runtime::doctor::DoctorCheck {
+    let settings = DEFAULT_SETTINGS.lock().unwrap();
+    let command = settings.command.as_deref().unwrap_or("claude");
+    super::command_check(CLAUDE_KIND, command)
+}
+
 /// Entity type name registered in the global registry.
 pub const ENTITY_TYPE: &str = "agent-claude-code";
 /// Agent kind identifier exposed in dataspace assertions.
@@ -188,11 +195,8 @@ impl ClaudeCodeAgent {
         request_id: String,
         prompt: String,
     ) -> ActorResult<()> {
-        let actor = activation.actor_id.clone();
-        let facet = activation.current_facet.clone();
         let request_uuid = Uuid::new_v4();
         let settings = self.settings.clone();
-        let async_sender = activation.async_sender();
         let agent_entity_id = activation
             .current_entity_id()
             .map(|id| id.to_string())
@@ -215,38 +219,30 @@ impl ClaudeCodeAgent {
 
         let settings_clone = settings.clone();
 
-        if let Some(async_sender) = async_sender {
-            let agent_id_for_response = agent_entity_id.clone();
-            std::thread::spawn(move || {
-                let response = match Self::execute_prompt(&settings_clone, &prompt) {
-                    Ok(value) => value,
-                    Err(err) => format!("Claude Code error: {err}"),
-                };
-
-                let timestamp = Utc::now().to_rfc3339();
-                let response_record = preserves::IOValue::record(
-                    preserves::IOValue::symbol(RESPONSE_LABEL),
-                    response_fields(
-                        agent_id_for_response,
-                        request_id,
-                        prompt,
-                        response,
-                        agent_kind,
-                        timestamp,
-                        Some(DEFAULT_ROLE),
-                        None,
-                    ),
-                );
-
-                let message = AsyncMessage {
-                    actor,
-                    facet,
-                    payload: response_record,
-                };
-
-                let _ = async_sender.send(message);
-            });
-        }
+        let agent_id_for_response = agent_entity_id.clone();
+        activation.spawn_task(format!("claude-code-{request_id}"), move |task| {
+            let response = match Self::execute_prompt(&settings_clone, &prompt) {
+                Ok(value) => value,
+                Err(err) => format!("Claude Code error: {err}"),
+            };
+
+            let timestamp = canonical_timestamp(&Utc::now());
+            let response_record = preserves::IOValue::record(
+                preserves::IOValue::symbol(RESPONSE_LABEL),
+                response_fields(
+                    agent_id_for_response,
+                    request_id,
+                    prompt,
+                    response,
+                    agent_kind,
+                    timestamp,
+                    Some(DEFAULT_ROLE),
+                    None,
+                ),
+            );
+
+            task.send(response_record);
+        });
 
         Ok(())
     }
@@ -280,7 +276,7 @@ impl ClaudeCodeAgent {
         });
         drop(exchanges);
 
-        let timestamp = Utc::now().to_rfc3339();
+        let timestamp = canonical_timestamp(&Utc::now());
 
         let agent_id = activation
             .current_entity_id()
@@ -351,6 +347,10 @@ impl Entity for ClaudeCodeAgent {
         }
         Ok(())
     }
+
+    fn describe(&self) -> EntityDescriptor {
+        super::agent_descriptor("claude-config", &["command", "args"])
+    }
 }
 
 /// Register the Claude Code agent in the entity catalog.
@@ -4,11 +4,11 @@ use super::{
     AgentEntity, AgentExchange, DUET_AGENT_SYSTEM_PROMPT, REQUEST_LABEL, RESPONSE_LABEL,
     exchanges_from_preserves, exchanges_to_preserves, parse_response_fields, response_fields,
 };
-use crate::runtime::AsyncMessage;
 use crate::runtime::actor::{Activation, Entity, HydratableEntity};
 use crate::runtime::error::{ActorError, ActorResult};
-use crate::runtime::registry::EntityCatalog;
+use crate::runtime::registry::{EntityCatalog, EntityDescriptor};
 use crate::runtime::turn::{Handle, TurnOutput};
+use crate::util::canonical::canonical_timestamp;
 use crate::util::io_value::record_with_label;
 use chrono::Utc;
 use once_cell::sync::Lazy;
@@ -78,6 +78,13 @@ pub fn set_external_command(command: Option<String>, args: Vec<String>) {
     settings.args = args;
 }
 
+/// Advisory check that the configured CLI can be found.
+pub(crate) fn doctor_check() -> crate::runtime::doctor::DoctorCheck {
+    let settings = DEFAULT_SETTINGS.lock().unwrap();
+    let command = settings.command.as_deref().unwrap_or("codex");
+    super::command_check(CODEX_KIND, command)
+}
+
 /// Entity type name registered in the global registry.
 pub const ENTITY_TYPE: &str = "agent-codex";
 /// Agent kind identifier exposed in dataspace assertions.
@@ -247,11 +254,8 @@ impl CodexAgent {
         request_id: String,
         prompt: String,
     ) -> ActorResult<()> {
-        let actor = activation.actor_id.clone();
-        let facet = activation.current_facet.clone();
         let request_uuid = Uuid::new_v4();
         let settings = self.settings.clone();
-        let async_sender = activation.async_sender();
         let agent_entity_id = activation
             .current_entity_id()
             .map(|id| id.to_string())
@@ -273,38 +277,30 @@ impl CodexAgent {
 
         let settings_clone = settings.clone();
 
-        if let Some(async_sender) = async_sender {
-            let agent_id_for_response = agent_entity_id.clone();
-            std::thread::spawn(move || {
-                let response = match Self::execute_prompt(&settings_clone, &prompt) {
-                    Ok(value) => value,
-                    Err(err) => format!("Codex error: {err}"),
-                };
-
-                let timestamp = Utc::now().to_rfc3339();
-                let response_record = preserves::IOValue::record(
-                    preserves::IOValue::symbol(RESPONSE_LABEL),
-                    response_fields(
-                        agent_id_for_response,
-                        request_id,
-                        prompt,
-                        response,
-                        agent_kind,
-                        timestamp,
-                        Some(DEFAULT_ROLE),
-                        None,
-                    ),
-                );
-
-                let message = AsyncMessage {
-                    actor,
-                    facet,
-                    payload: response_record,
-                };
-
-                let _ = async_sender.send(message);
-            });
-        }
+        let agent_id_for_response = agent_entity_id.clone();
+        activation.spawn_task(format!("codex-{request_id}"), move |task| {
+            let response = match Self::execute_prompt(&settings_clone, &prompt) {
+                Ok(value) => value,
+                Err(err) => format!("Codex error: {err}"),
+            };
+
+            let timestamp = canonical_timestamp(&Utc::now());
+            let response_record = preserves::IOValue::record(
+                preserves::IOValue::symbol(RESPONSE_LABEL),
+                response_fields(
+                    agent_id_for_response,
+                    request_id,
+                    prompt,
+                    response,
+                    agent_kind,
+                    timestamp,
+                    Some(DEFAULT_ROLE),
+                    None,
+                ),
+            );
+
+            task.send(response_record);
+        });
 
         Ok(())
     }
@@ -338,7 +334,7 @@ impl CodexAgent {
         });
         drop(exchanges);
 
-        let timestamp = Utc::now().to_rfc3339();
+        let timestamp = canonical_timestamp(&Utc::now());
 
         let response_record = preserves::IOValue::record(
             preserves::IOValue::symbol(RESPONSE_LABEL),
@@ -404,6 +400,10 @@ impl Entity for CodexAgent {
         }
         Ok(())
     }
+
+    fn describe(&self) -> EntityDescriptor {
+        super::agent_descriptor("codex-config", &["command", "args", "sandbox-mode"])
+    }
 }
 
 /// Register the Codex agent in the entity catalog.
@@ -0,0 +1,499 @@
+//! Conformance checks for agent entities.
+//!
+//! New agent kinds have to speak the same dataspace protocol as the built-in
+//! ones: route `agent-request` records addressed to them to their backend,
+//! assert `agent-response` records in the shared layout, tolerate responses
+//! that are delivered more than once, and carry their history through
+//! hydration. [`check_agent`] drives an [`AgentEntity`] through each of those
+//! steps without a runtime, playing the backend itself: every outbound
+//! [`TurnOutput::ExternalRequest`] is answered from a [`ScriptedBackend`] by
+//! delivering the `agent-response` message the background worker would send.
+//!
+//! The protocol delivers each response as a single record and has no
+//! cancellation message, so neither partial streaming nor cancellation is
+//! checked here.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use preserves::IOValue;
+use uuid::Uuid;
+
+use super::{AgentEntity, REQUEST_LABEL, RESPONSE_LABEL, parse_response_fields, response_fields};
+use crate::runtime::actor::{Activation, HydratableEntity};
+use crate::runtime::turn::{ActorId, FacetId, Handle, TurnOutput};
+use crate::util::canonical::canonical_timestamp;
+use crate::util::io_value::record_with_label;
+
+/// Canned backend replies, keyed by prompt.
+#[derive(Debug, Clone)]
+pub struct ScriptedBackend {
+    replies: HashMap<String, String>,
+    fallback: String,
+}
+
+impl ScriptedBackend {
+    /// Backend answering every prompt with `fallback`.
+    pub fn new(fallback: impl Into<String>) -> Self {
+        Self {
+            replies: HashMap::new(),
+            fallback: fallback.into(),
+        }
+    }
+
+    /// Answer `prompt` with `response`.
+    pub fn with_reply(mut self, prompt: impl Into<String>, response: impl Into<String>) -> Self {
+        self.replies.insert(prompt.into(), response.into());
+        self
+    }
+
+    /// Reply for `prompt`.
+    pub fn reply_for(&self, prompt: &str) -> &str {
+        self.replies
+            .get(prompt)
+            .map(String::as_str)
+            .unwrap_or(&self.fallback)
+    }
+}
+
+impl Default for ScriptedBackend {
+    fn default() -> Self {
+        Self::new("scripted reply")
+    }
+}
+
+/// A single conformance check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    /// The entity descriptor advertises `agent-request`
+    Descriptor,
+    /// Requests for this agent reach the backend; others are ignored
+    RequestRouting,
+    /// Backend replies are asserted as well-formed `agent-response` records
+    ResponseFormat,
+    /// A response delivered twice is only asserted once
+    DuplicateResponse,
+    /// History survives a snapshot/restore round trip
+    Hydration,
+}
+
+impl Check {
+    /// Every check, in the order [`check_agent`] runs them.
+    pub const ALL: [Check; 5] = [
+        Check::Descriptor,
+        Check::RequestRouting,
+        Check::ResponseFormat,
+        Check::DuplicateResponse,
+        Check::Hydration,
+    ];
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Check::Descriptor => "descriptor",
+            Check::RequestRouting => "request-routing",
+            Check::ResponseFormat => "response-format",
+            Check::DuplicateResponse => "duplicate-response",
+            Check::Hydration => "hydration",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Outcome of one check.
+#[derive(Debug, Clone)]
+pub struct CheckOutcome {
+    /// Check that ran
+    pub check: Check,
+    /// Why it failed (`None` = passed)
+    pub failure: Option<String>,
+}
+
+/// Outcomes of every check run against one agent.
+#[derive(Debug, Clone)]
+pub struct ConformanceReport {
+    /// Agent kind reported by the entity
+    pub agent_kind: String,
+    /// Per-check outcomes, in [`Check::ALL`] order
+    pub outcomes: Vec<CheckOutcome>,
+}
+
+impl ConformanceReport {
+    /// Whether every check passed.
+    pub fn is_conformant(&self) -> bool {
+        self.outcomes
+            .iter()
+            .all(|outcome| outcome.failure.is_none())
+    }
+
+    /// Failed checks with their reasons.
+    pub fn failures(&self) -> Vec<(Check, &str)> {
+        self.outcomes
+            .iter()
+            .filter_map(|outcome| Some((outcome.check, outcome.failure.as_deref()?)))
+            .collect()
+    }
+
+    /// Panic with every failure unless the agent is conformant.
+    pub fn assert_conformant(&self) {
+        if self.is_conformant() {
+            return;
+        }
+        let failures: Vec<String> = self
+            .failures()
+            .into_iter()
+            .map(|(check, reason)| format!("  {check}: {reason}"))
+            .collect();
+        panic!(
+            "agent '{}' is not conformant:\n{}",
+            self.agent_kind,
+            failures.join("\n")
+        );
+    }
+}
+
+/// Run every check against agents built by `make_agent`.
+///
+/// Each check starts from a fresh agent; the hydration check restores a
+/// snapshot into a second one.
+pub fn check_agent<A, F>(make_agent: F, backend: &ScriptedBackend) -> ConformanceReport
+where
+    A: AgentEntity + HydratableEntity,
+    F: Fn() -> A,
+{
+    let agent_kind = make_agent().agent_kind().to_string();
+    let outcomes = Check::ALL
+        .iter()
+        .map(|&check| {
+            let result = match check {
+                Check::Descriptor => check_descriptor(&make_agent()),
+                Check::RequestRouting => check_routing(&make_agent()),
+                Check::ResponseFormat => check_response_format(&make_agent(), backend),
+                Check::DuplicateResponse => check_duplicate_response(&make_agent(), backend),
+                Check::Hydration => check_hydration(&make_agent, backend),
+            };
+            CheckOutcome {
+                check,
+                failure: result.err(),
+            }
+        })
+        .collect();
+    ConformanceReport {
+        agent_kind,
+        outcomes,
+    }
+}
+
+type CheckResult = Result<(), String>;
+
+/// Agent entity under test together with the identity it runs as.
+struct Harness {
+    actor: ActorId,
+    facet: FacetId,
+    entity_id: Uuid,
+}
+
+impl Harness {
+    fn new() -> Self {
+        Self {
+            actor: ActorId::new(),
+            facet: FacetId::new(),
+            entity_id: Uuid::new_v4(),
+        }
+    }
+
+    fn activation(&self) -> Activation {
+        let mut activation = Activation::new(self.actor.clone(), self.facet.clone(), None);
+        activation.set_current_entity(Some(self.entity_id));
+        activation
+    }
+
+    fn request(&self, request_id: &str, prompt: &str) -> IOValue {
+        request_record(&self.entity_id.to_string(), request_id, prompt)
+    }
+
+    /// Deliver `payload` as a message and return the resulting outputs.
+    fn message<A: AgentEntity>(
+        &self,
+        agent: &A,
+        payload: &IOValue,
+    ) -> Result<Vec<TurnOutput>, String> {
+        let mut activation = self.activation();
+        agent
+            .on_message(&mut activation, payload)
+            .map_err(|err| format!("on_message failed: {err}"))?;
+        Ok(activation.outputs)
+    }
+
+    /// Send a request and return the response `backend` answers it with.
+    fn answer<A: AgentEntity>(
+        &self,
+        agent: &A,
+        backend: &ScriptedBackend,
+        request_id: &str,
+        prompt: &str,
+    ) -> Result<IOValue, String> {
+        let outputs = self.message(agent, &self.request(request_id, prompt))?;
+        let requests = external_requests(&outputs);
+        let [request] = requests[..] else {
+            return Err(format!(
+                "expected one external request, got {}",
+                requests.len()
+            ));
+        };
+        self.response(agent, backend, request)
+    }
+
+    /// The `agent-response` message a backend worker sends for `request`.
+    fn response<A: AgentEntity>(
+        &self,
+        agent: &A,
+        backend: &ScriptedBackend,
+        request: &IOValue,
+    ) -> Result<IOValue, String> {
+        let record = record_with_label(request, REQUEST_LABEL)
+            .ok_or("external request is not an agent-request record")?;
+        let (Some(agent_id), Some(request_id), Some(prompt)) = (
+            record.field_string(0),
+            record.field_string(1),
+            record.field_string(2),
+        ) else {
+            return Err("external request must carry agent id, request id and prompt".into());
+        };
+        if agent_id != self.entity_id.to_string() {
+            return Err(format!(
+                "external request names agent '{agent_id}' instead of '{}'",
+                self.entity_id
+            ));
+        }
+        let response = backend.reply_for(&prompt).to_string();
+        Ok(IOValue::record(
+            IOValue::symbol(RESPONSE_LABEL),
+            response_fields(
+                agent_id,
+                request_id,
+                prompt,
+                response,
+                agent.agent_kind().to_string(),
+                canonical_timestamp(&Utc::now()),
+                Some("assistant"),
+                None,
+            ),
+        ))
+    }
+}
+
+fn request_record(agent_id: &str, request_id: &str, prompt: &str) -> IOValue {
+    IOValue::record(
+        IOValue::symbol(REQUEST_LABEL),
+        vec![
+            IOValue::new(agent_id.to_string()),
+            IOValue::new(request_id.to_string()),
+            IOValue::new(prompt.to_string()),
+        ],
+    )
+}
+
+fn external_requests(outputs: &[TurnOutput]) -> Vec<&IOValue> {
+    outputs
+        .iter()
+        .filter_map(|output| match output {
+            TurnOutput::ExternalRequest { request, .. } => Some(request),
+            _ => None,
+        })
+        .collect()
+}
+
+fn asserted_responses(outputs: &[TurnOutput]) -> Vec<&IOValue> {
+    outputs
+        .iter()
+        .filter_map(|output| match output {
+            TurnOutput::Assert { value, .. } => {
+                record_with_label(value, RESPONSE_LABEL).map(|_| value)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn check_descriptor<A: AgentEntity>(agent: &A) -> CheckResult {
+    let descriptor = agent.describe();
+    if descriptor
+        .message_labels
+        .iter()
+        .any(|label| label == REQUEST_LABEL)
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "descriptor message labels {:?} omit '{REQUEST_LABEL}'",
+            descriptor.message_labels
+        ))
+    }
+}
+
+fn check_routing<A: AgentEntity>(agent: &A) -> CheckResult {
+    let harness = Harness::new();
+
+    let outputs = harness.message(agent, &harness.request("req-1", "hello"))?;
+    let requests = external_requests(&outputs);
+    let [request] = requests[..] else {
+        return Err(format!(
+            "a request for this agent produced {} external requests",
+            requests.len()
+        ));
+    };
+    if *request != harness.request("req-1", "hello") {
+        return Err(format!(
+            "external request {request:?} does not echo the agent request"
+        ));
+    }
+    let service = outputs.iter().find_map(|output| match output {
+        TurnOutput::ExternalRequest { service, .. } => Some(service.as_str()),
+        _ => None,
+    });
+    if service != Some(agent.agent_kind()) {
+        return Err(format!(
+            "external request service {:?} differs from agent kind '{}'",
+            service,
+            agent.agent_kind()
+        ));
+    }
+
+    let mut activation = harness.activation();
+    agent
+        .on_assert(
+            &mut activation,
+            &Handle::new(),
+            &harness.request("req-2", "asserted"),
+        )
+        .map_err(|err| format!("on_assert failed: {err}"))?;
+    if external_requests(&activation.outputs).len() != 1 {
+        return Err("an asserted request for this agent did not reach the backend".into());
+    }
+
+    let foreign = request_record(&Uuid::new_v4().to_string(), "req-3", "not yours");
+    let outputs = harness.message(agent, &foreign)?;
+    if !outputs.is_empty() {
+        return Err(format!(
+            "a request addressed to another agent produced {} outputs",
+            outputs.len()
+        ));
+    }
+    Ok(())
+}
+
+fn check_response_format<A: AgentEntity>(agent: &A, backend: &ScriptedBackend) -> CheckResult {
+    let harness = Harness::new();
+    let prompt = "describe the workspace";
+    let response = harness.answer(agent, backend, "req-1", prompt)?;
+    let outputs = harness.message(agent, &response)?;
+
+    let responses = asserted_responses(&outputs);
+    let [response] = responses[..] else {
+        return Err(format!(
+            "delivering a response asserted {} agent-response records",
+            responses.len()
+        ));
+    };
+    let Some((agent_id, request_id, echoed_prompt, text, kind)) = parse_response_fields(response)
+    else {
+        return Err(format!("malformed agent-response record {response:?}"));
+    };
+    let expected = (
+        harness.entity_id.to_string(),
+        "req-1".to_string(),
+        prompt.to_string(),
+        backend.reply_for(prompt).to_string(),
+        agent.agent_kind().to_string(),
+    );
+    if (agent_id, request_id, echoed_prompt, text, kind) != expected {
+        return Err(format!(
+            "agent-response {response:?} does not match {expected:?}"
+        ));
+    }
+
+    let timestamp = response.index(5);
+    let valid_timestamp = timestamp
+        .as_string()
+        .is_some_and(|text| DateTime::parse_from_rfc3339(text.as_ref()).is_ok());
+    if !valid_timestamp {
+        return Err(format!(
+            "agent-response timestamp {timestamp:?} is not RFC 3339"
+        ));
+    }
+
+    let foreign = IOValue::record(
+        IOValue::symbol(RESPONSE_LABEL),
+        response_fields(
+            Uuid::new_v4().to_string(),
+            "req-2".to_string(),
+            prompt.to_string(),
+            "not yours".to_string(),
+            agent.agent_kind().to_string(),
+            canonical_timestamp(&Utc::now()),
+            None,
+            None,
+        ),
+    );
+    if !asserted_responses(&harness.message(agent, &foreign)?).is_empty() {
+        return Err("a response addressed to another agent was asserted".into());
+    }
+    Ok(())
+}
+
+fn check_duplicate_response<A: AgentEntity>(agent: &A, backend: &ScriptedBackend) -> CheckResult {
+    let harness = Harness::new();
+    let response = harness.answer(agent, backend, "req-1", "once")?;
+
+    let first = asserted_responses(&harness.message(agent, &response)?).len();
+    let second = asserted_responses(&harness.message(agent, &response)?).len();
+    if (first, second) != (1, 0) {
+        return Err(format!(
+            "delivering the same response twice asserted {first} then {second} records"
+        ));
+    }
+    Ok(())
+}
+
+fn check_hydration<A, F>(make_agent: &F, backend: &ScriptedBackend) -> CheckResult
+where
+    A: AgentEntity + HydratableEntity,
+    F: Fn() -> A,
+{
+    let harness = Harness::new();
+    let agent = make_agent();
+    let response = harness.answer(&agent, backend, "req-1", "remember me")?;
+    harness.message(&agent, &response)?;
+
+    let snapshot = agent.snapshot_state();
+    let mut restored = make_agent();
+    restored
+        .restore_state(&snapshot)
+        .map_err(|err| format!("restore_state failed: {err}"))?;
+    if restored.snapshot_state() != snapshot {
+        return Err("snapshot after restore differs from the restored snapshot".into());
+    }
+    if !asserted_responses(&harness.message(&restored, &response)?).is_empty() {
+        return Err("restored agent re-asserted a response already in its history".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codebase::agent::{
+        claude::ClaudeCodeAgent, codex::CodexAgent, harness::HarnessAgent,
+    };
+
+    #[test]
+    fn builtin_agents_are_conformant() {
+        let backend = ScriptedBackend::default().with_reply("describe the workspace", "a tree");
+        check_agent(ClaudeCodeAgent::new, &backend).assert_conformant();
+        check_agent(CodexAgent::new, &backend).assert_conformant();
+        check_agent(HarnessAgent::new, &backend).assert_conformant();
+    }
+}
@@ -4,11 +4,11 @@ use super::{
     AgentEntity, AgentExchange, DUET_AGENT_SYSTEM_PROMPT, REQUEST_LABEL, RESPONSE_LABEL,
     exchanges_from_preserves, exchanges_to_preserves, parse_response_fields, response_fields,
 };
-use crate::runtime::AsyncMessage;
 use crate::runtime::actor::{Activation, Entity, HydratableEntity};
 use crate::runtime::error::{ActorError, ActorResult};
-use crate::runtime::registry::EntityCatalog;
+use crate::runtime::registry::{EntityCatalog, EntityDescriptor};
 use crate::runtime::turn::{Handle, TurnOutput};
+use crate::util::canonical::canonical_timestamp;
 use crate::util::io_value::record_with_label;
 use chrono::Utc;
 use once_cell::sync::Lazy;
@@ -75,6 +75,21 @@ impl Default for AgentSettings {
 static DEFAULT_SETTINGS: Lazy<Mutex<AgentSettings>> =
     Lazy::new(|| Mutex::new(AgentSettings::default()));
 
+/// Advisory check that an API key is configured for the endpoint.
+///
+/// The endpoint itself is not contacted.
+pub(crate) fn doctor_check() -> crate::runtime::doctor::DoctorCheck {
+    use crate::runtime::doctor::DoctorCheck;
+
+    let settings = DEFAULT_SETTINGS.lock().unwrap();
+    let name = format!("agent.{HARNESS_KIND}");
+    match settings.api_key {
+        Some(_) => DoctorCheck::pass(name, format!("API key set for {}", settings.endpoint)),
+        None => DoctorCheck::warn(name, "DUET_HARNESS_API_KEY is not set"),
+    }
+    .advisory()
+}
+
 /// Minimal OpenAI-compatible harness entity.
 pub struct HarnessAgent {
     settings: AgentSettings,
@@ -212,11 +227,8 @@ impl HarnessAgent {
         request_id: String,
         prompt: String,
     ) -> ActorResult<()> {
-        let actor = activation.actor_id.clone();
-        let facet = activation.current_facet.clone();
         let request_uuid = Uuid::new_v4();
         let settings = self.settings.clone();
-        let async_sender = activation.async_sender();
         let agent_entity_id = activation
             .current_entity_id()
             .map(|id| id.to_string())
@@ -237,38 +249,30 @@ impl HarnessAgent {
         let agent_kind = self.agent_kind().to_string();
         let settings_clone = settings.clone();
 
-        if let Some(async_sender) = async_sender {
-            let agent_id_for_response = agent_entity_id.clone();
-            std::thread::spawn(move || {
-                let response = match Self::execute_prompt(&settings_clone, &prompt) {
-                    Ok(value) => value,
-                    Err(err) => format!("Harness error: {err}"),
-                };
-
-                let timestamp = Utc::now().to_rfc3339();
-                let response_record = preserves::IOValue::record(
-                    preserves::IOValue::symbol(RESPONSE_LABEL),
-                    response_fields(
-                        agent_id_for_response,
-                        request_id,
-                        prompt,
-                        response,
-                        agent_kind,
-                        timestamp,
-                        Some(DEFAULT_ROLE),
-                        None,
-                    ),
-                );
-
-                let message = AsyncMessage {
-                    actor,
-                    facet,
-                    payload: response_record,
-                };
-
-                let _ = async_sender.send(message);
-            });
-        }
+        let agent_id_for_response = agent_entity_id.clone();
+        activation.spawn_task(format!("harness-{request_id}"), move |task| {
+            let response = match Self::execute_prompt(&settings_clone, &prompt) {
+                Ok(value) => value,
+                Err(err) => format!("Harness error: {err}"),
+            };
+
+            let timestamp = canonical_timestamp(&Utc::now());
+            let response_record = preserves::IOValue::record(
+                preserves::IOValue::symbol(RESPONSE_LABEL),
+                response_fields(
+                    agent_id_for_response,
+                    request_id,
+                    prompt,
+                    response,
+                    agent_kind,
+                    timestamp,
+                    Some(DEFAULT_ROLE),
+                    None,
+                ),
+            );
+
+            task.send(response_record);
+        });
 
         Ok(())
     }
@@ -302,7 +306,7 @@ impl HarnessAgent {
         });
         drop(exchanges);
 
-        let timestamp = Utc::now().to_rfc3339();
+        let timestamp = canonical_timestamp(&Utc::now());
 
         let response_record = preserves::IOValue::record(
             preserves::IOValue::symbol(RESPONSE_LABEL),
@@ -368,6 +372,20 @@ impl Entity for HarnessAgent {
         }
         Ok(())
     }
+
+    fn describe(&self) -> EntityDescriptor {
+        super::agent_descriptor(
+            "noface-config",
+            &[
+                "endpoint",
+                "model",
+                "system-prompt",
+                "api-key",
+                "temperature",
+                "max-tokens",
+            ],
+        )
+    }
 }
 
 /// Register the harness agent in the entity catalog.
@@ -2,11 +2,16 @@
 use preserves::IOValue;
 use serde::{Deserialize, Serialize};
 
+use std::path::{Path, PathBuf};
+
 use crate::runtime::actor::Entity;
+use crate::runtime::doctor::DoctorCheck;
+use crate::runtime::registry::EntityDescriptor;
 use crate::util::io_value::record_with_label;
 
 pub mod claude;
 pub mod codex;
+pub mod conformance;
 pub mod harness;
 
 /// System instructions shared by all Duet-managed agents.
@@ -50,6 +55,54 @@ pub trait AgentEntity: Entity {
     fn agent_kind(&self) -> &'static str;
 }
 
+/// Descriptor shared by agent entities: they accept `agent-request`
+/// records and are configured by a `<config_label field...>` record.
+pub(crate) fn agent_descriptor(config_label: &str, fields: &[&str]) -> EntityDescriptor {
+    EntityDescriptor {
+        message_labels: vec![REQUEST_LABEL.to_string()],
+        capability_kinds: Vec::new(),
+        config_schema: Some(IOValue::record(
+            IOValue::symbol(config_label.to_string()),
+            fields
+                .iter()
+                .map(|field| IOValue::symbol(field.to_string()))
+                .collect(),
+        )),
+    }
+}
+
+/// Advisory environment checks for every agent kind.
+///
+/// Checks only look at configuration and the filesystem; no agent is
+/// launched and no request is sent.
+pub fn doctor_checks() -> Vec<DoctorCheck> {
+    vec![
+        claude::doctor_check(),
+        codex::doctor_check(),
+        harness::doctor_check(),
+    ]
+}
+
+/// Check that the CLI an agent shells out to can be found.
+pub(crate) fn command_check(kind: &str, command: &str) -> DoctorCheck {
+    let name = format!("agent.{kind}");
+    match find_command(command) {
+        Some(path) => DoctorCheck::pass(name, format!("{command} found at {}", path.display())),
+        None => DoctorCheck::warn(name, format!("{command} not found on PATH")),
+    }
+    .advisory()
+}
+
+fn find_command(command: &str) -> Option<PathBuf> {
+    let candidate = Path::new(command);
+    if candidate.components().count() > 1 {
+        return candidate.is_file().then(|| candidate.to_path_buf());
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(command))
+        .find(|path| path.is_file())
+}
+
 /// Resolve an entity type identifier for a given agent kind.
 pub fn entity_type_for_kind(kind: &str) -> Option<&'static str> {
     match kind {
@@ -0,0 +1,350 @@
+//! First-run setup for applications embedding the runtime
+//!
+//! `codebased` sets a project up from command-line flags. Frontends that
+//! want to walk a user through the same choices use [`Bootstrap`] instead:
+//!
+//! 1. [`Bootstrap::detect`] inspects a project directory – whether a runtime
+//!    already lives there, which build outputs and `.gitignore` entries
+//!    should stay out of the workspace catalog, and which agent CLIs are
+//!    installed.
+//! 2. [`Bootstrap::plan`] returns the proposed setup. Every field is public,
+//!    so the frontend can show it and let the user adjust it through
+//!    [`Bootstrap::plan_mut`].
+//! 3. [`Bootstrap::apply`] initialises storage (when needed), applies the
+//!    planned template and hands back a ready [`Control`].
+//!
+//! Applying is idempotent in the same way templates are, so running the
+//! wizard against an existing runtime only adds what is missing.
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::runtime::control::Control;
+use crate::runtime::error::{Result as RuntimeResult, RuntimeError};
+use crate::runtime::storage::{self, Storage};
+use crate::runtime::{Runtime, RuntimeConfig};
+
+use super::agent;
+use super::template::{self, InitTemplate, ProjectManifest, TemplateOptions};
+
+/// Directory below the project root holding the runtime's storage.
+pub const RUNTIME_DIR: &str = ".duet";
+
+/// Entries kept out of the catalog when the marker file exists.
+const BUILD_OUTPUTS: &[(&str, &str)] = &[
+    ("Cargo.toml", "target"),
+    ("package.json", "node_modules"),
+    ("pyproject.toml", "__pycache__"),
+    ("pyproject.toml", ".venv"),
+    ("build.gradle", "build"),
+    ("pom.xml", "target"),
+];
+
+/// An agent the runtime knows how to drive, and whether it can run here.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DetectedAgent {
+    /// Agent kind (as used in `agent-request` assertions)
+    pub kind: String,
+    /// What the agent needs: a CLI name or an environment variable
+    pub requires: String,
+    /// Resolved executable, for CLI-backed agents that were found
+    pub path: Option<PathBuf>,
+    /// Whether the agent looks usable on this machine
+    pub available: bool,
+}
+
+/// What [`Bootstrap::detect`] found in a project directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Detection {
+    /// Project directory that was inspected
+    pub project_root: PathBuf,
+    /// Runtime storage directory (inside the project)
+    pub runtime_root: PathBuf,
+    /// Whether a runtime has already been initialised there
+    pub initialized: bool,
+    /// Manifest of the template applied last, if any
+    pub manifest: Option<ProjectManifest>,
+    /// Ignore rules derived from build files and `.gitignore`
+    pub ignore: Vec<String>,
+    /// Known agents and their availability
+    pub agents: Vec<DetectedAgent>,
+}
+
+/// Proposed setup; adjust the fields before [`Bootstrap::apply`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapPlan {
+    /// Runtime configuration to initialise (or reuse)
+    pub config: RuntimeConfig,
+    /// Directory the workspace entity serves
+    pub workspace_root: PathBuf,
+    /// Template to apply
+    pub template: InitTemplate,
+    /// Workspace entries left out of the catalog
+    pub ignore: Vec<String>,
+    /// Agent kinds to register
+    pub agents: Vec<String>,
+}
+
+/// Result of applying a plan.
+pub struct BootstrapOutcome {
+    /// Control handle for the initialised runtime
+    pub control: Control,
+    /// Manifest written by the applied template
+    pub manifest: ProjectManifest,
+    /// Whether storage was created by this run
+    pub created: bool,
+}
+
+/// Guided first-run setup of a project directory.
+#[derive(Debug, Clone)]
+pub struct Bootstrap {
+    detection: Detection,
+    plan: BootstrapPlan,
+}
+
+impl Bootstrap {
+    /// Inspect `root` and propose a setup for it.
+    pub fn detect(root: impl AsRef<Path>) -> RuntimeResult<Self> {
+        Self::detect_with_path(root.as_ref(), env::var_os("PATH"))
+    }
+
+    fn detect_with_path(root: &Path, search_path: Option<OsString>) -> RuntimeResult<Self> {
+        if !root.is_dir() {
+            return Err(RuntimeError::Config(format!(
+                "Project directory {} does not exist",
+                root.display()
+            )));
+        }
+        let project_root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let runtime_root = project_root.join(RUNTIME_DIR);
+        let initialized =
+            Storage::new(runtime_root.clone()).exists(&runtime_root.join("config.json"));
+
+        let manifest = if initialized {
+            template::load_manifest(&runtime_root)?
+        } else {
+            None
+        };
+
+        let detection = Detection {
+            ignore: detect_ignore_rules(&project_root),
+            agents: detect_agents(search_path.as_deref()),
+            project_root,
+            runtime_root,
+            initialized,
+            manifest,
+        };
+        let plan = propose(&detection)?;
+        Ok(Self { detection, plan })
+    }
+
+    /// What was found in the project directory.
+    pub fn detection(&self) -> &Detection {
+        &self.detection
+    }
+
+    /// The proposed setup.
+    pub fn plan(&self) -> &BootstrapPlan {
+        &self.plan
+    }
+
+    /// The proposed setup, for adjusting before it is applied.
+    pub fn plan_mut(&mut self) -> &mut BootstrapPlan {
+        &mut self.plan
+    }
+
+    /// Initialise the runtime (if needed) and apply the plan.
+    pub fn apply(self) -> RuntimeResult<BootstrapOutcome> {
+        let plan = self.plan;
+        let created = !self.detection.initialized;
+        if created {
+            Runtime::init(plan.config.clone())?;
+        }
+        let mut control = Control::new(plan.config)?;
+
+        let options = TemplateOptions {
+            ignore: plan.ignore,
+            agents: Some(plan.agents),
+        };
+        let manifest = template::apply_template_with(
+            &mut control,
+            plan.template,
+            &plan.workspace_root,
+            &options,
+        )?;
+
+        Ok(BootstrapOutcome {
+            control,
+            manifest,
+            created,
+        })
+    }
+}
+
+fn propose(detection: &Detection) -> RuntimeResult<BootstrapPlan> {
+    let config = if detection.initialized {
+        storage::load_config(&detection.runtime_root)
+            .map_err(|err| RuntimeError::Config(format!("Failed to load config: {}", err)))?
+    } else {
+        RuntimeConfig {
+            root: detection.runtime_root.clone(),
+            ..RuntimeConfig::default()
+        }
+    };
+
+    Ok(BootstrapPlan {
+        config,
+        workspace_root: detection.project_root.clone(),
+        template: detection
+            .manifest
+            .as_ref()
+            .map(|manifest| manifest.template)
+            .unwrap_or_default(),
+        ignore: detection.ignore.clone(),
+        agents: detection
+            .agents
+            .iter()
+            .filter(|agent| agent.available)
+            .map(|agent| agent.kind.clone())
+            .collect(),
+    })
+}
+
+fn detect_ignore_rules(project_root: &Path) -> Vec<String> {
+    let mut rules = vec![".git".to_string(), RUNTIME_DIR.to_string()];
+    for (marker, output) in BUILD_OUTPUTS {
+        if project_root.join(marker).is_file() {
+            rules.push(output.to_string());
+        }
+    }
+
+    // Only literal names and paths carry over; globs and negations have no
+    // equivalent in workspace rules.
+    if let Ok(gitignore) = fs::read_to_string(project_root.join(".gitignore")) {
+        for line in gitignore.lines() {
+            let line = line.trim();
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with('!')
+                || line.contains(['*', '?', '['])
+            {
+                continue;
+            }
+            let rule = line.trim_matches('/');
+            if !rule.is_empty() {
+                rules.push(rule.to_string());
+            }
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    rules.retain(|rule| seen.insert(rule.clone()));
+    rules
+}
+
+fn detect_agents(search_path: Option<&std::ffi::OsStr>) -> Vec<DetectedAgent> {
+    let cli = |kind: &str, env_var: &str, default: &str| {
+        let command = env::var(env_var)
+            .ok()
+            .filter(|command| !command.is_empty())
+            .unwrap_or_else(|| default.to_string());
+        let path = find_executable(&command, search_path);
+        DetectedAgent {
+            kind: kind.to_string(),
+            requires: command,
+            available: path.is_some(),
+            path,
+        }
+    };
+
+    vec![
+        cli(agent::claude::CLAUDE_KIND, "DUET_CLAUDE_COMMAND", "claude"),
+        cli(agent::codex::CODEX_KIND, "DUET_CODEX_COMMAND", "codex"),
+        DetectedAgent {
+            kind: agent::harness::HARNESS_KIND.to_string(),
+            requires: "DUET_HARNESS_API_KEY".to_string(),
+            path: None,
+            available: env::var("DUET_HARNESS_API_KEY")
+                .map(|key| !key.trim().is_empty())
+                .unwrap_or(false),
+        },
+    ]
+}
+
+/// Resolve `command` the way a shell would: as a path when it contains a
+/// separator, otherwise by searching `search_path`.
+fn find_executable(command: &str, search_path: Option<&std::ffi::OsStr>) -> Option<PathBuf> {
+    let command_path = Path::new(command);
+    if command_path.components().count() > 1 {
+        return is_executable(command_path).then(|| command_path.to_path_buf());
+    }
+    env::split_paths(search_path?)
+        .map(|dir| dir.join(command))
+        .find(|candidate| is_executable(candidate))
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn detection_reads_build_files_gitignore_and_path() {
+        let project = TempDir::new().unwrap();
+        fs::write(project.path().join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(
+            project.path().join(".gitignore"),
+            "# build\n/dist/\n*.log\n!keep\nnotes.txt\ntarget\n",
+        )
+        .unwrap();
+
+        let bin = TempDir::new().unwrap();
+        let codex = bin.path().join("codex");
+        fs::write(&codex, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&codex, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let bootstrap =
+            Bootstrap::detect_with_path(project.path(), Some(bin.path().into())).unwrap();
+        let detection = bootstrap.detection();
+        assert!(!detection.initialized);
+        assert_eq!(
+            detection.ignore,
+            vec![".git", RUNTIME_DIR, "target", "dist", "notes.txt"]
+        );
+
+        let codex_agent = detection
+            .agents
+            .iter()
+            .find(|agent| agent.kind == agent::codex::CODEX_KIND)
+            .unwrap();
+        if env::var_os("DUET_CODEX_COMMAND").is_none() {
+            assert!(codex_agent.available);
+            assert!(bootstrap.plan().agents.contains(&codex_agent.kind));
+        }
+        assert_eq!(bootstrap.plan().config.root, detection.runtime_root);
+        assert_eq!(bootstrap.plan().template, InitTemplate::CodebaseDaemon);
+    }
+}
@@ -0,0 +1,487 @@
+//! Key-value store entity
+//!
+//! Gives workflows and agents durable scratch space. Each store owns one
+//! namespace and publishes a `<kv-entry namespace key version value>`
+//! assertion per key, so the current contents are visible through ordinary
+//! dataspace queries and patterns. Reads and writes go through capabilities
+//! granted on request (`<kv-get prefix>`, `<kv-put prefix>`, `<kv-cas prefix>`
+//! messages); the prefix attenuates the capability to keys starting with it.
+//!
+//! Every key carries a version that starts at 1 and increases by one per
+//! write, which makes compare-and-swap deterministic under replay:
+//! `<kv-cas key expected value>` only writes when the key's current version
+//! equals `expected` (0 meaning the key is absent). The store is part of the
+//! entity's hydratable state, so it survives restarts and time travel.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use preserves::ValueImpl;
+
+use crate::runtime::actor::{Activation, CapabilitySpec, Entity, HydratableEntity};
+use crate::runtime::error::{ActorError, ActorResult};
+use crate::runtime::registry::{EntityCatalog, EntityDescriptor};
+use crate::runtime::state::{CapabilityMetadata, CapabilityTarget};
+use crate::runtime::turn::Handle;
+use crate::util::io_value::record_with_label;
+
+/// Entity type under which the store is registered.
+pub const ENTITY_TYPE: &str = "kv";
+
+/// Record label for published keys.
+pub const KV_ENTRY_LABEL: &str = "kv-entry";
+
+/// Capability kind for reading keys.
+pub const CAP_KIND_GET: &str = "kv/get";
+/// Capability kind for unconditional writes.
+pub const CAP_KIND_PUT: &str = "kv/put";
+/// Capability kind for compare-and-swap writes.
+pub const CAP_KIND_CAS: &str = "kv/cas";
+
+const DEFAULT_NAMESPACE: &str = "default";
+
+#[derive(Debug, Clone, PartialEq)]
+struct Slot {
+    value: preserves::IOValue,
+    version: u64,
+    handle: Handle,
+}
+
+/// Namespaced key-value store entity.
+pub struct KvStore {
+    namespace: String,
+    entries: Mutex<BTreeMap<String, Slot>>,
+}
+
+impl KvStore {
+    fn new(namespace: String) -> Self {
+        Self {
+            namespace,
+            entries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn entry_fact(&self, key: &str, slot: &Slot) -> preserves::IOValue {
+        preserves::IOValue::record(
+            preserves::IOValue::symbol(KV_ENTRY_LABEL),
+            vec![
+                preserves::IOValue::new(self.namespace.clone()),
+                preserves::IOValue::new(key.to_string()),
+                preserves::IOValue::new(slot.version as i64),
+                slot.value.clone(),
+            ],
+        )
+    }
+
+    /// Store `value` under `key`, replacing the published entry.
+    fn write(&self, activation: &mut Activation, key: &str, value: preserves::IOValue) -> u64 {
+        let mut entries = self.entries.lock().unwrap();
+        let version = match entries.remove(key) {
+            Some(previous) => {
+                activation.retract(previous.handle);
+                previous.version + 1
+            }
+            None => 1,
+        };
+        let slot = Slot {
+            value,
+            version,
+            handle: Handle::new(),
+        };
+        activation.assert(slot.handle.clone(), self.entry_fact(key, &slot));
+        entries.insert(key.to_string(), slot);
+        version
+    }
+
+    fn version_of(&self, key: &str) -> u64 {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|slot| slot.version)
+            .unwrap_or(0)
+    }
+
+    fn authorize(&self, capability: &CapabilityMetadata, key: &str) -> ActorResult<()> {
+        if let Some(first) = capability.attenuation.first() {
+            let prefix = first.as_string().ok_or_else(|| {
+                ActorError::InvalidActivation("kv capability attenuation must be a string".into())
+            })?;
+            if !key.starts_with(prefix.as_ref()) {
+                return Err(ActorError::InvalidActivation(format!(
+                    "key '{}' outside capability scope",
+                    key
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_key(&self, payload: &preserves::IOValue, label: &str) -> ActorResult<String> {
+        record_with_label(payload, label)
+            .and_then(|record| record.field_string(0))
+            .ok_or_else(|| {
+                ActorError::InvalidActivation(format!("expected <{} key ...> payload", label))
+            })
+    }
+
+    fn handle_get(
+        &self,
+        capability: &CapabilityMetadata,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<preserves::IOValue> {
+        let key = self.parse_key(payload, "kv-get")?;
+        self.authorize(capability, &key)?;
+
+        let entries = self.entries.lock().unwrap();
+        Ok(match entries.get(&key) {
+            Some(slot) => preserves::IOValue::record(
+                preserves::IOValue::symbol("kv-value"),
+                vec![
+                    preserves::IOValue::new(key.clone()),
+                    preserves::IOValue::new(slot.version as i64),
+                    slot.value.clone(),
+                ],
+            ),
+            None => preserves::IOValue::record(
+                preserves::IOValue::symbol("kv-missing"),
+                vec![preserves::IOValue::new(key)],
+            ),
+        })
+    }
+
+    fn handle_put(
+        &self,
+        activation: &mut Activation,
+        capability: &CapabilityMetadata,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<preserves::IOValue> {
+        let key = self.parse_key(payload, "kv-put")?;
+        self.authorize(capability, &key)?;
+        if payload.len() < 2 {
+            return Err(ActorError::InvalidActivation(
+                "kv-put requires a value argument".into(),
+            ));
+        }
+
+        let version = self.write(activation, &key, preserves::IOValue::from(payload.index(1)));
+        Ok(version_reply("kv-ok", version))
+    }
+
+    fn handle_cas(
+        &self,
+        activation: &mut Activation,
+        capability: &CapabilityMetadata,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<preserves::IOValue> {
+        let key = self.parse_key(payload, "kv-cas")?;
+        self.authorize(capability, &key)?;
+        if payload.len() < 3 {
+            return Err(ActorError::InvalidActivation(
+                "kv-cas requires expected version and value arguments".into(),
+            ));
+        }
+        let expected = preserves::IOValue::from(payload.index(1))
+            .as_signed_integer()
+            .and_then(|n| u64::try_from(n.as_ref()).ok())
+            .ok_or_else(|| {
+                ActorError::InvalidActivation(
+                    "kv-cas expected version must be a non-negative integer".into(),
+                )
+            })?;
+
+        let current = self.version_of(&key);
+        if current != expected {
+            return Ok(version_reply("kv-conflict", current));
+        }
+        let version = self.write(activation, &key, preserves::IOValue::from(payload.index(2)));
+        Ok(version_reply("kv-ok", version))
+    }
+}
+
+fn version_reply(label: &'static str, version: u64) -> preserves::IOValue {
+    preserves::IOValue::record(
+        preserves::IOValue::symbol(label),
+        vec![preserves::IOValue::new(version as i64)],
+    )
+}
+
+impl Entity for KvStore {
+    fn on_message(
+        &self,
+        activation: &mut Activation,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        for (label, kind) in [
+            ("kv-get", CAP_KIND_GET),
+            ("kv-put", CAP_KIND_PUT),
+            ("kv-cas", CAP_KIND_CAS),
+        ] {
+            if let Some(record) = record_with_label(payload, label) {
+                let prefix = if record.len() > 0 {
+                    record.field_string(0).unwrap_or_default()
+                } else {
+                    String::new()
+                };
+                let facet = activation.current_facet.clone();
+                activation.grant_capability(CapabilitySpec {
+                    holder: activation.actor_id.clone(),
+                    holder_facet: facet.clone(),
+                    target: Some(CapabilityTarget {
+                        actor: activation.actor_id.clone(),
+                        facet: Some(facet),
+                    }),
+                    kind: kind.into(),
+                    attenuation: vec![preserves::IOValue::new(prefix)],
+                });
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    fn on_capability_invoke(
+        &self,
+        activation: &mut Activation,
+        capability: &CapabilityMetadata,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<preserves::IOValue> {
+        match capability.kind.as_str() {
+            CAP_KIND_GET => self.handle_get(capability, payload),
+            CAP_KIND_PUT => self.handle_put(activation, capability, payload),
+            CAP_KIND_CAS => self.handle_cas(activation, capability, payload),
+            other => Err(ActorError::InvalidActivation(format!(
+                "unsupported capability kind: {}",
+                other
+            ))),
+        }
+    }
+
+    fn describe(&self) -> EntityDescriptor {
+        EntityDescriptor {
+            message_labels: ["kv-get", "kv-put", "kv-cas"].map(str::to_string).to_vec(),
+            capability_kinds: [CAP_KIND_GET, CAP_KIND_PUT, CAP_KIND_CAS]
+                .map(str::to_string)
+                .to_vec(),
+            config_schema: Some(preserves::IOValue::symbol("namespace")),
+        }
+    }
+}
+
+impl HydratableEntity for KvStore {
+    fn snapshot_state(&self) -> preserves::IOValue {
+        let entries = self.entries.lock().unwrap();
+        let items: Vec<preserves::IOValue> = entries
+            .iter()
+            .map(|(key, slot)| {
+                preserves::IOValue::record(
+                    preserves::IOValue::symbol("item"),
+                    vec![
+                        preserves::IOValue::new(key.clone()),
+                        preserves::IOValue::new(slot.version as i64),
+                        preserves::IOValue::new(slot.handle.0.to_string()),
+                        slot.value.clone(),
+                    ],
+                )
+            })
+            .collect();
+        preserves::IOValue::record(
+            preserves::IOValue::symbol("kv-state"),
+            vec![
+                preserves::IOValue::new(self.namespace.clone()),
+                preserves::IOValue::new(items),
+            ],
+        )
+    }
+
+    fn restore_state(&mut self, state: &preserves::IOValue) -> ActorResult<()> {
+        let invalid = || ActorError::InvalidActivation("malformed kv state".into());
+        let record = record_with_label(state, "kv-state")
+            .filter(|record| record.len() >= 2)
+            .ok_or_else(invalid)?;
+
+        let mut restored = BTreeMap::new();
+        let items = record.field(1);
+        for index in 0..items.len() {
+            let value = preserves::IOValue::from(items.index(index));
+            let item = record_with_label(&value, "item")
+                .filter(|item| item.len() >= 4)
+                .ok_or_else(invalid)?;
+            let key = item.field_string(0).ok_or_else(invalid)?;
+            let version = item
+                .field(1)
+                .as_signed_integer()
+                .and_then(|n| u64::try_from(n.as_ref()).ok())
+                .ok_or_else(invalid)?;
+            let handle = item
+                .field_string(2)
+                .and_then(|id| uuid::Uuid::parse_str(&id).ok())
+                .ok_or_else(invalid)?;
+            restored.insert(
+                key,
+                Slot {
+                    value: item.field(3),
+                    version,
+                    handle: Handle(handle),
+                },
+            );
+        }
+
+        self.namespace = record.field_string(0).ok_or_else(invalid)?;
+        *self.entries.lock().unwrap() = restored;
+        Ok(())
+    }
+}
+
+/// Register the key-value store entity type.
+pub fn register(catalog: &EntityCatalog) {
+    catalog.register_hydratable(ENTITY_TYPE, |config| {
+        let namespace = config
+            .as_string()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string());
+        Ok(KvStore::new(namespace))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::actor::Actor;
+    use crate::runtime::state::CapabilityStatus;
+    use crate::runtime::turn::{ActorId, TurnOutput};
+
+    fn capability(kind: &str, prefix: &str) -> CapabilityMetadata {
+        let actor = ActorId::new();
+        CapabilityMetadata {
+            id: uuid::Uuid::new_v4(),
+            issuer: actor.clone(),
+            issuer_facet: crate::runtime::turn::FacetId::new(),
+            issuer_entity: None,
+            holder: actor,
+            holder_facet: crate::runtime::turn::FacetId::new(),
+            target: None,
+            kind: kind.into(),
+            attenuation: vec![preserves::IOValue::new(prefix.to_string())],
+            status: CapabilityStatus::Active,
+        }
+    }
+
+    fn call(label: &'static str, fields: Vec<preserves::IOValue>) -> preserves::IOValue {
+        preserves::IOValue::record(preserves::IOValue::symbol(label), fields)
+    }
+
+    fn key(name: &str) -> preserves::IOValue {
+        preserves::IOValue::new(name.to_string())
+    }
+
+    #[test]
+    fn put_get_and_cas_follow_versions() {
+        let store = KvStore::new("scratch".into());
+        let actor = Actor::new(ActorId::new());
+        let mut activation = Activation::new(actor.id.clone(), actor.root_facet.clone(), None);
+        let put = capability(CAP_KIND_PUT, "");
+        let get = capability(CAP_KIND_GET, "");
+        let cas = capability(CAP_KIND_CAS, "plan/");
+
+        let reply = store
+            .on_capability_invoke(
+                &mut activation,
+                &get,
+                &call("kv-get", vec![key("plan/step")]),
+            )
+            .unwrap();
+        assert!(record_with_label(&reply, "kv-missing").is_some());
+
+        let reply = store
+            .on_capability_invoke(
+                &mut activation,
+                &cas,
+                &call(
+                    "kv-cas",
+                    vec![key("plan/step"), preserves::IOValue::new(0), key("a")],
+                ),
+            )
+            .unwrap();
+        assert_eq!(reply, version_reply("kv-ok", 1));
+
+        let reply = store
+            .on_capability_invoke(
+                &mut activation,
+                &put,
+                &call("kv-put", vec![key("plan/step"), key("b")]),
+            )
+            .unwrap();
+        assert_eq!(reply, version_reply("kv-ok", 2));
+
+        // A writer that last saw version 1 loses the race
+        let reply = store
+            .on_capability_invoke(
+                &mut activation,
+                &cas,
+                &call(
+                    "kv-cas",
+                    vec![key("plan/step"), preserves::IOValue::new(1), key("c")],
+                ),
+            )
+            .unwrap();
+        assert_eq!(reply, version_reply("kv-conflict", 2));
+
+        let reply = store
+            .on_capability_invoke(
+                &mut activation,
+                &get,
+                &call("kv-get", vec![key("plan/step")]),
+            )
+            .unwrap();
+        let value = record_with_label(&reply, "kv-value").unwrap();
+        assert_eq!(value.field_string(2).as_deref(), Some("b"));
+
+        // The CAS capability is attenuated to the plan/ prefix
+        assert!(
+            store
+                .on_capability_invoke(
+                    &mut activation,
+                    &cas,
+                    &call(
+                        "kv-cas",
+                        vec![key("notes"), preserves::IOValue::new(0), key("x")],
+                    ),
+                )
+                .is_err()
+        );
+
+        // Each write retracts the previous entry, leaving one live assertion
+        let asserts = activation
+            .outputs
+            .iter()
+            .filter(|output| matches!(output, TurnOutput::Assert { .. }))
+            .count();
+        let retracts = activation
+            .outputs
+            .iter()
+            .filter(|output| matches!(output, TurnOutput::Retract { .. }))
+            .count();
+        assert_eq!((asserts, retracts), (2, 1));
+    }
+
+    #[test]
+    fn store_survives_hydration() {
+        let store = KvStore::new("scratch".into());
+        let actor = Actor::new(ActorId::new());
+        let mut activation = Activation::new(actor.id.clone(), actor.root_facet.clone(), None);
+        store
+            .on_capability_invoke(
+                &mut activation,
+                &capability(CAP_KIND_PUT, ""),
+                &call("kv-put", vec![key("k"), preserves::IOValue::new(7)]),
+            )
+            .unwrap();
+
+        let mut restored = KvStore::new(DEFAULT_NAMESPACE.into());
+        restored.restore_state(&store.snapshot_state()).unwrap();
+        assert_eq!(restored.snapshot_state(), store.snapshot_state());
+        assert_eq!(restored.namespace, "scratch");
+        assert_eq!(restored.version_of("k"), 1);
+    }
+}
@@ -4,9 +4,17 @@
 //! the `codebased` daemon.  It currently includes:
 //!   * `workspace` – publishes a causal view of the filesystem and
 //!     issues capabilities for reading/modifying files.
+//!   * `symbols` – indexes workspace source files and publishes the
+//!     declarations they contain.
+//!   * `kv` – a namespaced key-value store with versioned writes, giving
+//!     workflows and agents durable scratch space.
 //!   * `echo` / `counter` – small reference implementations used by
 //!     tests/examples until richer catalogues arrive.
+//!
+//! [`template`] bundles these into initialisation profiles for new projects,
+//! and [`bootstrap`] lets embedding applications pick one interactively.
 
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::path::Path;
 use std::sync::{Mutex, Once};
@@ -17,13 +25,20 @@ use serde::{Deserialize, Serialize};
 
 use crate::runtime::actor::{Activation, Entity, HydratableEntity};
 use crate::runtime::control::Control;
+use crate::runtime::effects::FileBackup;
 use crate::runtime::error::{ActorError, ActorResult, Result as RuntimeResult, RuntimeError};
-use crate::runtime::pattern::Pattern;
+use crate::runtime::events::FromAssertion;
+use crate::runtime::pattern::{Pattern, PatternScope};
 use crate::runtime::registry::EntityCatalog;
 use crate::runtime::turn::{ActorId, BranchId, FacetId, Handle, TurnId};
-use crate::util::io_value::record_with_label;
+use crate::util::io_value::{io_value_to_json, record_with_label};
+use workspace::encoding::{DecodedData, ReadEncoding};
 
 pub mod agent;
+pub mod bootstrap;
+pub mod kv;
+pub mod symbols;
+pub mod template;
 pub mod transcript;
 pub mod workspace;
 
@@ -42,6 +57,8 @@ pub fn register_codebase_entities() {
         let catalog = EntityCatalog::global();
 
         workspace::register(catalog);
+        symbols::register(catalog);
+        kv::register(catalog);
         agent::claude::register(catalog);
         agent::codex::register(catalog);
         agent::harness::register(catalog);
@@ -119,6 +136,59 @@ pub struct WorkspaceEntry {
     pub modified: Option<String>,
     /// Optional digest associated with the entry.
     pub digest: Option<String>,
+    /// Values appended by configured metadata extractors, keyed by extractor name.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+}
+
+/// Capability offered by a workspace running with `<offers>`, pending acceptance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityOffer {
+    /// Offer identifier to accept or decline
+    pub id: uuid::Uuid,
+    /// Capability kind on offer
+    pub kind: String,
+    /// Workspace-relative path the capability is attenuated to
+    pub path: String,
+}
+
+/// Handle to a registered symbol catalog entity.
+#[derive(Debug, Clone)]
+pub struct SymbolsHandle {
+    /// Unique identifier of the symbol catalog entity instance.
+    pub entity_id: uuid::Uuid,
+    /// Actor hosting the symbol catalog entity.
+    pub actor: ActorId,
+    /// Facet the symbol catalog entity is attached to.
+    pub facet: FacetId,
+}
+
+/// Materialised view of a `symbol-entry` published by the symbol catalog.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolEntry {
+    /// Declaration kind (`function`, `struct`, `class`, ...).
+    pub kind: String,
+    /// Declared name.
+    pub name: String,
+    /// Workspace-relative path of the declaring file.
+    pub path: String,
+    /// First line of the declaration (1-based).
+    pub start_line: i64,
+    /// Last line of the declaration (1-based, inclusive).
+    pub end_line: i64,
+}
+
+/// Handle to a registered key-value store entity.
+#[derive(Debug, Clone)]
+pub struct KvHandle {
+    /// Unique identifier of the store entity instance.
+    pub entity_id: uuid::Uuid,
+    /// Actor hosting the store entity.
+    pub actor: ActorId,
+    /// Facet the store entity is attached to.
+    pub facet: FacetId,
+    /// Namespace served by the store.
+    pub namespace: String,
 }
 
 /// Handle to a registered agent entity.
@@ -179,6 +249,16 @@ pub struct AgentInvocation {
 pub fn ensure_workspace_entity(
     control: &mut Control,
     root: &Path,
+) -> RuntimeResult<WorkspaceHandle> {
+    ensure_workspace_entity_with(control, root, &[])
+}
+
+/// Like [`ensure_workspace_entity`], leaving entries matching `ignore` out
+/// of the catalog when the entity has to be created.
+pub fn ensure_workspace_entity_with(
+    control: &mut Control,
+    root: &Path,
+    ignore: &[String],
 ) -> RuntimeResult<WorkspaceHandle> {
     if let Some(handle) = workspace_handle(control) {
         return Ok(handle);
@@ -186,7 +266,24 @@ pub fn ensure_workspace_entity(
 
     let actor = ActorId::new();
     let facet = FacetId::new();
-    let config = preserves::IOValue::new(root.to_string_lossy().to_string());
+    let root_value = preserves::IOValue::new(root.to_string_lossy().to_string());
+    let config = if ignore.is_empty() {
+        root_value
+    } else {
+        preserves::IOValue::record(
+            preserves::IOValue::symbol("workspace-config"),
+            vec![
+                root_value,
+                preserves::IOValue::record(
+                    preserves::IOValue::symbol("ignore"),
+                    ignore
+                        .iter()
+                        .map(|rule| preserves::IOValue::new(rule.clone()))
+                        .collect(),
+                ),
+            ],
+        )
+    };
 
     let entity_id = control.register_entity(
         actor.clone(),
@@ -209,6 +306,152 @@ pub fn ensure_workspace_entity(
     })
 }
 
+/// Ensure a symbol catalog entity indexes the workspace rooted at `root`.
+///
+/// The catalog observes `workspace-entry` assertions dataspace-wide, so it
+/// picks up files already published by the workspace entity as well as
+/// every later rescan. Symbols are extracted by a background task and
+/// appear once it reports back. An existing catalog is told to resume
+/// extraction a restart may have cut short.
+pub fn ensure_symbols_entity(control: &mut Control, root: &Path) -> RuntimeResult<SymbolsHandle> {
+    if let Some(handle) = symbols_handle(control) {
+        control.send_message(
+            handle.actor.clone(),
+            handle.facet.clone(),
+            preserves::IOValue::record(
+                preserves::IOValue::symbol(symbols::SYMBOLS_RESUME_LABEL),
+                vec![],
+            ),
+        )?;
+        return Ok(handle);
+    }
+
+    let actor = ActorId::new();
+    let facet = FacetId::new();
+    let config = preserves::IOValue::new(root.to_string_lossy().to_string());
+
+    let entity_id =
+        control.register_entity(actor.clone(), facet.clone(), "symbols".to_string(), config)?;
+
+    for pattern in symbols::workspace_file_patterns() {
+        let pattern = Pattern {
+            id: uuid::Uuid::new_v4(),
+            pattern,
+            facet: facet.clone(),
+            namespace: None,
+            scope: PatternScope::Actor,
+        }
+        .dataspace_wide();
+        control.register_pattern_for_entity(entity_id, pattern)?;
+    }
+    control.drain_pending()?;
+
+    Ok(SymbolsHandle {
+        entity_id,
+        actor,
+        facet,
+    })
+}
+
+/// Ensure a key-value store entity serves `namespace`.
+pub fn ensure_kv_entity(control: &mut Control, namespace: &str) -> RuntimeResult<KvHandle> {
+    if let Some(handle) = kv_handle(control, namespace) {
+        return Ok(handle);
+    }
+
+    let actor = ActorId::new();
+    let facet = FacetId::new();
+    let entity_id = control.register_entity(
+        actor.clone(),
+        facet.clone(),
+        kv::ENTITY_TYPE.to_string(),
+        preserves::IOValue::new(namespace.to_string()),
+    )?;
+
+    Ok(KvHandle {
+        entity_id,
+        actor,
+        facet,
+        namespace: namespace.to_string(),
+    })
+}
+
+/// Return the handle for the key-value store serving `namespace`, if any.
+pub fn kv_handle(control: &Control, namespace: &str) -> Option<KvHandle> {
+    let runtime = control.runtime();
+    runtime
+        .entity_manager()
+        .list_on(&runtime.current_branch())
+        .into_iter()
+        .find_map(|meta| {
+            let served = meta.config.as_string().map(|s| s.to_string());
+            if meta.entity_type == kv::ENTITY_TYPE && served.as_deref() == Some(namespace) {
+                Some(KvHandle {
+                    entity_id: meta.id,
+                    actor: meta.actor.clone(),
+                    facet: meta.facet.clone(),
+                    namespace: namespace.to_string(),
+                })
+            } else {
+                None
+            }
+        })
+}
+
+/// Obtain a `kv/get`, `kv/put` or `kv/cas` capability for keys under `prefix`.
+pub fn request_kv_capability(
+    control: &mut Control,
+    handle: &KvHandle,
+    kind: &str,
+    prefix: &str,
+) -> RuntimeResult<uuid::Uuid> {
+    let label = match kind {
+        kv::CAP_KIND_GET => "kv-get",
+        kv::CAP_KIND_PUT => "kv-put",
+        kv::CAP_KIND_CAS => "kv-cas",
+        other => {
+            return Err(RuntimeError::Actor(ActorError::InvalidActivation(format!(
+                "unknown kv capability kind {other}"
+            ))));
+        }
+    };
+    let request = preserves::IOValue::record(
+        preserves::IOValue::symbol(label),
+        vec![preserves::IOValue::new(prefix.to_string())],
+    );
+    control.send_message(handle.actor.clone(), handle.facet.clone(), request)?;
+
+    find_capability(control, &handle.actor, kind, prefix).ok_or_else(|| {
+        RuntimeError::Actor(ActorError::InvalidActivation(format!(
+            "kv capability {kind} for prefix {prefix} not granted",
+        )))
+    })
+}
+
+/// Return the handle for the first registered symbol catalog entity, if any.
+pub fn symbols_handle(control: &Control) -> Option<SymbolsHandle> {
+    control.list_entities().into_iter().find_map(|entity| {
+        if entity.entity_type == "symbols" {
+            Some(SymbolsHandle {
+                entity_id: entity.id,
+                actor: entity.actor,
+                facet: entity.facet,
+            })
+        } else {
+            None
+        }
+    })
+}
+
+/// List symbols currently asserted by the symbol catalog.
+pub fn list_symbols(control: &Control, handle: &SymbolsHandle) -> Vec<SymbolEntry> {
+    control
+        .list_assertions_for_actor(&handle.actor)
+        .into_iter()
+        .filter_map(|(_handle, value)| parse_symbol_entry(&value))
+        .collect()
+}
+
 /// Return the handle for the first registered workspace entity, if any.
 pub fn workspace_handle(control: &Control) -> Option<WorkspaceHandle> {
     control.list_entities().into_iter().find_map(|entity| {
@@ -262,6 +505,156 @@ pub fn read_file(
     })
 }
 
+/// File content decoded by a workspace read in an explicit encoding mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileContent {
+    /// Workspace-relative path
+    pub path: String,
+    /// Mode the read was requested in
+    pub mode: ReadEncoding,
+    /// Encoding the bytes were decoded as (`binary` for raw bytes)
+    pub encoding: String,
+    /// Decoded content
+    pub data: DecodedData,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// blake3 digest (hex) of the file bytes
+    pub digest: String,
+    /// Invalid sequences replaced by U+FFFD
+    pub replacements: u64,
+}
+
+/// Read a workspace file, decoding its bytes according to `mode`.
+///
+/// Unlike [`read_file`] this succeeds on non-UTF-8 content for every mode
+/// but [`ReadEncoding::Utf8`].
+pub fn read_file_with(
+    control: &mut Control,
+    handle: &WorkspaceHandle,
+    rel_path: &str,
+    mode: ReadEncoding,
+) -> RuntimeResult<FileContent> {
+    let cap = request_read_capability(control, handle, rel_path)?;
+    let payload = preserves::IOValue::record(
+        preserves::IOValue::symbol("workspace-read"),
+        vec![
+            preserves::IOValue::new(rel_path.to_string()),
+            preserves::IOValue::symbol(mode.as_str()),
+        ],
+    );
+    let response = control.invoke_capability(cap, payload)?;
+    parse_file_content(&response).ok_or_else(|| {
+        RuntimeError::Actor(ActorError::InvalidActivation(
+            "workspace read returned a malformed content record".into(),
+        ))
+    })
+}
+
+fn parse_file_content(value: &preserves::IOValue) -> Option<FileContent> {
+    let record = record_with_label(value, workspace::encoding::CONTENT_LABEL)?;
+    if record.len() < 7 {
+        return None;
+    }
+    let field = record.field(3);
+    let data = if let Some(text) = field.as_string() {
+        DecodedData::Text(text.to_string())
+    } else {
+        DecodedData::Bytes(field.as_bytestring()?.to_vec())
+    };
+    let int = |index: usize| {
+        record
+            .field(index)
+            .as_signed_integer()
+            .and_then(|value| u64::try_from(value.as_ref()).ok())
+    };
+    Some(FileContent {
+        path: record.field_string(0)?,
+        mode: record.field_symbol(1)?.parse().ok()?,
+        encoding: record.field_string(2)?,
+        data,
+        size: int(4)?,
+        digest: record.field_string(5)?,
+        replacements: int(6)?,
+    })
+}
+
+/// Capability offers currently pending on the workspace.
+pub fn list_capability_offers(control: &Control, handle: &WorkspaceHandle) -> Vec<CapabilityOffer> {
+    control
+        .list_assertions_for_actor(&handle.actor)
+        .into_iter()
+        .filter_map(|(_handle, value)| parse_capability_offer(&value))
+        .collect()
+}
+
+/// Accept a pending offer and return the capability it grants.
+pub fn accept_capability_offer(
+    control: &mut Control,
+    handle: &WorkspaceHandle,
+    offer_id: uuid::Uuid,
+) -> RuntimeResult<uuid::Uuid> {
+    let offer = list_capability_offers(control, handle)
+        .into_iter()
+        .find(|offer| offer.id == offer_id)
+        .ok_or_else(|| {
+            RuntimeError::Actor(ActorError::NotFound(format!("Capability offer {offer_id}")))
+        })?;
+    settle_capability_offer(control, handle, offer_id, "capability-accept")?;
+    find_capability(control, &handle.actor, &offer.kind, &offer.path).ok_or_else(|| {
+        RuntimeError::Actor(ActorError::InvalidActivation(format!(
+            "accepting offer {offer_id} granted no capability"
+        )))
+    })
+}
+
+/// Decline a pending offer so its capability is never granted.
+pub fn decline_capability_offer(
+    control: &mut Control,
+    handle: &WorkspaceHandle,
+    offer_id: uuid::Uuid,
+) -> RuntimeResult<()> {
+    settle_capability_offer(control, handle, offer_id, "capability-decline")
+}
+
+fn settle_capability_offer(
+    control: &mut Control,
+    handle: &WorkspaceHandle,
+    offer_id: uuid::Uuid,
+    label: &'static str,
+) -> RuntimeResult<()> {
+    control.send_message(
+        handle.actor.clone(),
+        handle.facet.clone(),
+        preserves::IOValue::record(
+            preserves::IOValue::symbol(label),
+            vec![preserves::IOValue::new(offer_id.to_string())],
+        ),
+    )?;
+    Ok(())
+}
+
+fn parse_capability_offer(value: &preserves::IOValue) -> Option<CapabilityOffer> {
+    let record = record_with_label(value, workspace::OFFER_LABEL)?;
+    Some(CapabilityOffer {
+        id: uuid::Uuid::parse_str(&record.field_string(0)?).ok()?,
+        kind: record.field_string(1)?,
+        path: record.field_string(2)?,
+    })
+}
+
+/// Restore a workspace file to its content before `turn` wrote it, then
+/// rescan so the catalog reflects the restored file.
+pub fn restore_file(
+    control: &mut Control,
+    handle: &WorkspaceHandle,
+    rel_path: &str,
+    turn: &TurnId,
+) -> RuntimeResult<FileBackup> {
+    let backup = control.restore_file(rel_path, turn)?;
+    workspace_rescan(control, handle)?;
+    Ok(backup)
+}
+
 /// Ensure a Claude Code agent entity exists for this runtime.
 pub fn ensure_claude_agent(control: &mut Control) -> RuntimeResult<AgentHandle> {
     ensure_agent(
@@ -341,6 +734,8 @@ fn ensure_agent(
             ],
         ),
         facet: facet.clone(),
+        namespace: None,
+        scope: PatternScope::Actor,
     };
     control
         .register_pattern_for_entity(entity_id, pattern)
@@ -452,12 +847,54 @@ fn parse_workspace_entry(value: &preserves::IOValue) -> Option<WorkspaceEntry> {
         None
     };
 
+    let mut metadata = BTreeMap::new();
+    if record.len() > 5 {
+        let extras = record.field(5);
+        if let Some(extras) = record_with_label(&extras, "metadata") {
+            for index in 0..extras.len() {
+                let item = extras.field(index);
+                let name = item
+                    .is_record()
+                    .then(|| item.label().as_symbol().map(|s| s.as_ref().to_string()))
+                    .flatten();
+                if let (Some(name), true) = (name, item.len() > 0) {
+                    let value = preserves::IOValue::from(item.index(0));
+                    metadata.insert(name, io_value_to_json(&value));
+                }
+            }
+        }
+    }
+
     Some(WorkspaceEntry {
         path,
         kind,
         size,
         modified,
         digest,
+        metadata,
+    })
+}
+
+fn parse_symbol_entry(value: &preserves::IOValue) -> Option<SymbolEntry> {
+    let record = record_with_label(value, symbols::SYMBOL_ENTRY_LABEL)?;
+    if record.len() < 4 {
+        return None;
+    }
+
+    let span = record.field(3);
+    let span = record_with_label(&span, "span")?;
+    let line = |index: usize| {
+        span.field(index)
+            .as_signed_integer()
+            .and_then(|n| i64::try_from(n.as_ref()).ok())
+    };
+
+    Some(SymbolEntry {
+        kind: record.field_symbol(0)?,
+        name: record.field_string(1)?,
+        path: record.field_string(2)?,
+        start_line: line(0)?,
+        end_line: line(1)?,
     })
 }
 
@@ -478,7 +915,6 @@ fn agent_handle(control: &Control, kind: &str) -> Option<AgentHandle> {
     })
 }
 
-/// Attempt to interpret a preserves value as an agent response record.
 /// Attempt to interpret a preserves payload as an agent response.
 pub fn parse_agent_response(value: &preserves::IOValue) -> Option<AgentResponse> {
     let record = record_with_label(value, agent::RESPONSE_LABEL)?;
@@ -522,6 +958,12 @@ pub fn parse_agent_response(value: &preserves::IOValue) -> Option<AgentResponse>
     })
 }
 
+impl FromAssertion for AgentResponse {
+    fn from_assertion(value: &preserves::IOValue) -> Option<Self> {
+        parse_agent_response(value)
+    }
+}
+
 fn request_read_capability(
     control: &mut Control,
     handle: &WorkspaceHandle,
@@ -563,6 +1005,15 @@ fn request_capability(
     );
     control.send_message(handle.actor.clone(), handle.facet.clone(), request)?;
 
+    // Workspaces configured with `<offers>` answer with an offer; the helper
+    // acts as the requester and accepts it.
+    if let Some(offer) = list_capability_offers(control, handle)
+        .into_iter()
+        .find(|offer| offer.kind == kind && offer.path == rel_path)
+    {
+        return accept_capability_offer(control, handle, offer.id);
+    }
+
     find_capability(control, &handle.actor, kind, rel_path).ok_or_else(|| {
         RuntimeError::Actor(ActorError::InvalidActivation(format!(
             "workspace capability {kind} for {rel_path} not granted",
@@ -646,5 +1097,7 @@ mod tests {
         assert!(snapshot.has_type("echo"));
         assert!(snapshot.has_type("counter"));
         assert!(snapshot.has_type("workspace"));
+        assert!(snapshot.has_type("symbols"));
+        assert!(snapshot.has_type("kv"));
     }
 }
@@ -0,0 +1,216 @@
+//! Symbol extraction backends
+//!
+//! With the `tree-sitter` feature enabled, Rust and Python sources are parsed
+//! with their tree-sitter grammars. Without it, a conservative line-oriented
+//! scanner recognises top-level declarations so the catalog still works in
+//! minimal builds (spans then cover only the declaration line).
+
+use std::path::Path;
+
+/// Languages the symbol catalog understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// Rust sources (`.rs`)
+    Rust,
+    /// Python sources (`.py`)
+    Python,
+}
+
+impl Language {
+    /// Determine the language of a file from its extension.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "py" => Some(Self::Python),
+            _ => None,
+        }
+    }
+}
+
+/// A declaration discovered in a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Declaration kind (`function`, `struct`, `class`, ...)
+    pub kind: &'static str,
+    /// Declared name
+    pub name: String,
+    /// First line of the declaration (1-based)
+    pub start_line: usize,
+    /// Last line of the declaration (1-based, inclusive)
+    pub end_line: usize,
+}
+
+/// Extract the declarations in `source`.
+pub fn extract(language: Language, source: &str) -> Vec<Symbol> {
+    #[cfg(feature = "tree-sitter")]
+    {
+        if let Some(symbols) = tree_sitter_backend::extract(language, source) {
+            return symbols;
+        }
+    }
+
+    line_backend::extract(language, source)
+}
+
+#[cfg(feature = "tree-sitter")]
+mod tree_sitter_backend {
+    use super::{Language, Symbol};
+
+    fn kind_for(language: Language, node_kind: &str) -> Option<&'static str> {
+        match (language, node_kind) {
+            (Language::Rust, "function_item") => Some("function"),
+            (Language::Rust, "struct_item") => Some("struct"),
+            (Language::Rust, "enum_item") => Some("enum"),
+            (Language::Rust, "union_item") => Some("union"),
+            (Language::Rust, "trait_item") => Some("trait"),
+            (Language::Rust, "mod_item") => Some("module"),
+            (Language::Rust, "type_item") => Some("type"),
+            (Language::Rust, "const_item") => Some("const"),
+            (Language::Rust, "static_item") => Some("static"),
+            (Language::Rust, "macro_definition") => Some("macro"),
+            (Language::Python, "function_definition") => Some("function"),
+            (Language::Python, "class_definition") => Some("class"),
+            _ => None,
+        }
+    }
+
+    pub(super) fn extract(language: Language, source: &str) -> Option<Vec<Symbol>> {
+        let grammar: tree_sitter::Language = match language {
+            Language::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Language::Python => tree_sitter_python::LANGUAGE.into(),
+        };
+
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&grammar).ok()?;
+        let tree = parser.parse(source, None)?;
+
+        let mut symbols = Vec::new();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            if let Some(kind) = kind_for(language, node.kind()) {
+                let name = node
+                    .child_by_field_name("name")
+                    .and_then(|name| name.utf8_text(source.as_bytes()).ok());
+                if let Some(name) = name {
+                    symbols.push(Symbol {
+                        kind,
+                        name: name.to_string(),
+                        start_line: node.start_position().row + 1,
+                        end_line: node.end_position().row + 1,
+                    });
+                }
+            }
+
+            let mut cursor = node.walk();
+            let children: Vec<_> = node.named_children(&mut cursor).collect();
+            stack.extend(children.into_iter().rev());
+        }
+
+        Some(symbols)
+    }
+}
+
+mod line_backend {
+    use super::{Language, Symbol};
+
+    const RUST_PREFIXES: &[&str] = &["pub ", "async ", "unsafe ", "extern \"C\" ", "default "];
+
+    const RUST_KEYWORDS: &[(&str, &str)] = &[
+        ("fn ", "function"),
+        ("struct ", "struct"),
+        ("enum ", "enum"),
+        ("union ", "union"),
+        ("trait ", "trait"),
+        ("mod ", "module"),
+        ("type ", "type"),
+        ("const ", "const"),
+        ("static ", "static"),
+        ("macro_rules! ", "macro"),
+    ];
+
+    const PYTHON_KEYWORDS: &[(&str, &str)] = &[
+        ("def ", "function"),
+        ("async def ", "function"),
+        ("class ", "class"),
+    ];
+
+    fn strip_rust_modifiers(mut line: &str) -> &str {
+        loop {
+            if let Some(rest) = line.strip_prefix("pub(") {
+                match rest.find(')') {
+                    Some(close) => line = rest[close + 1..].trim_start(),
+                    None => return line,
+                }
+                continue;
+            }
+            match RUST_PREFIXES
+                .iter()
+                .find_map(|prefix| line.strip_prefix(prefix))
+            {
+                Some(rest) => line = rest.trim_start(),
+                None => return line,
+            }
+        }
+    }
+
+    fn identifier(text: &str) -> Option<String> {
+        let name: String = text
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+        (!name.is_empty()).then_some(name)
+    }
+
+    pub(super) fn extract(language: Language, source: &str) -> Vec<Symbol> {
+        let keywords = match language {
+            Language::Rust => RUST_KEYWORDS,
+            Language::Python => PYTHON_KEYWORDS,
+        };
+
+        let mut symbols = Vec::new();
+        for (index, raw) in source.lines().enumerate() {
+            let trimmed = raw.trim_start();
+            let line = match language {
+                Language::Rust => strip_rust_modifiers(trimmed),
+                Language::Python => trimmed,
+            };
+
+            let found = keywords.iter().find_map(|(keyword, kind)| {
+                line.strip_prefix(keyword)
+                    .and_then(|rest| identifier(rest.trim_start()))
+                    .map(|name| (*kind, name))
+            });
+
+            if let Some((kind, name)) = found {
+                symbols.push(Symbol {
+                    kind,
+                    name,
+                    start_line: index + 1,
+                    end_line: index + 1,
+                });
+            }
+        }
+
+        symbols
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_rust_and_python_declarations() {
+        let rust = "pub(crate) struct Foo;\n\nimpl Foo {\n    pub async fn bar() {}\n}\n";
+        let symbols = extract(Language::Rust, rust);
+        let names: Vec<_> = symbols.iter().map(|s| (s.kind, s.name.as_str())).collect();
+        assert_eq!(names, vec![("struct", "Foo"), ("function", "bar")]);
+        assert_eq!(symbols[1].start_line, 4);
+
+        let python = "class Greeter:\n    def hello(self):\n        pass\n";
+        let symbols = extract(Language::Python, python);
+        let names: Vec<_> = symbols.iter().map(|s| (s.kind, s.name.as_str())).collect();
+        assert_eq!(names, vec![("class", "Greeter"), ("function", "hello")]);
+        assert_eq!(symbols[0].start_line, 1);
+    }
+}
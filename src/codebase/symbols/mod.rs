@@ -0,0 +1,414 @@
+//! Symbol catalog entity
+//!
+//! Observes `workspace-entry` assertions published by the workspace entity
+//! and, for each source file in a supported language, asserts one
+//! `<symbol-entry kind name path <span start end>>` record per declaration.
+//! Because the workspace retracts and re-asserts entries whose metadata
+//! changes, the catalog follows rescans automatically: a changed file's
+//! symbols are retracted and re-extracted, a deleted file's are retracted.
+//!
+//! Files are read off the scheduler thread. Observed entries are queued and
+//! handed in batches to a background task, one batch at a time; the task
+//! reports back with a `<symbols-extracted batch [<symbols-file ...> ...]>`
+//! message, so the extracted symbols are journaled with the turn that
+//! asserts them and replay never touches the filesystem.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::runtime::actor::{Activation, Entity, HydratableEntity};
+use crate::runtime::error::{ActorError, ActorResult};
+use crate::runtime::registry::EntityCatalog;
+use crate::runtime::turn::Handle;
+use crate::util::io_value::record_with_label;
+
+pub mod extract;
+
+use extract::Language;
+
+/// Record label for published symbols.
+pub const SYMBOL_ENTRY_LABEL: &str = "symbol-entry";
+
+/// Message label carrying the symbols extracted by a batch.
+pub const SYMBOLS_EXTRACTED_LABEL: &str = "symbols-extracted";
+
+/// Message label restarting extraction after a restart cut a batch short.
+pub const SYMBOLS_RESUME_LABEL: &str = "symbols-resume";
+
+/// Files larger than this are not parsed.
+const MAX_SOURCE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Symbols asserted for a single workspace entry.
+#[derive(Debug, Clone)]
+struct IndexedFile {
+    path: String,
+    /// Empty until the entry's batch reports back
+    handles: Vec<Handle>,
+}
+
+/// Catalog state, snapshotted so hydration restores it.
+#[derive(Debug, Default)]
+struct CatalogState {
+    /// Keyed by the handle of the observed `workspace-entry` assertion
+    files: HashMap<Handle, IndexedFile>,
+    /// Entries waiting for the next batch
+    queued: Vec<Handle>,
+    /// Batch in flight and the entries it covers
+    batch: Option<(String, Vec<Handle>)>,
+}
+
+/// Symbol catalog entity implementation.
+pub struct SymbolCatalog {
+    root: PathBuf,
+    state: Mutex<CatalogState>,
+}
+
+impl SymbolCatalog {
+    fn new(root: PathBuf) -> Self {
+        let root = fs::canonicalize(&root).unwrap_or(root);
+        Self {
+            root,
+            state: Mutex::new(CatalogState::default()),
+        }
+    }
+
+    /// Hand the queued entries to a background task unless a batch is in flight.
+    ///
+    /// The batch is recorded whether or not the task can start (it cannot
+    /// during replay), so replayed turns match the journaled result.
+    fn start_batch(&self, activation: &mut Activation, state: &mut CatalogState) {
+        if state.batch.is_some() || state.queued.is_empty() {
+            return;
+        }
+        let entries = std::mem::take(&mut state.queued);
+        let files: Vec<(Handle, String)> = entries
+            .iter()
+            .filter_map(|handle| {
+                let file = state.files.get(handle)?;
+                Some((handle.clone(), file.path.clone()))
+            })
+            .collect();
+        let batch = activation.rng().uuid().to_string();
+        state.batch = Some((batch.clone(), entries));
+
+        let root = self.root.clone();
+        activation.spawn_task(format!("symbols-{batch}"), move |task| {
+            let files = files
+                .into_iter()
+                .map(|(handle, path)| {
+                    let symbols = extract_file(&root, &path)
+                        .iter()
+                        .map(|symbol| symbol_record(&path, symbol))
+                        .collect::<Vec<_>>();
+                    preserves::IOValue::record(
+                        preserves::IOValue::symbol("symbols-file"),
+                        vec![
+                            preserves::IOValue::new(handle.0.to_string()),
+                            preserves::IOValue::new(path),
+                            preserves::IOValue::new(symbols),
+                        ],
+                    )
+                })
+                .collect::<Vec<_>>();
+            task.send(preserves::IOValue::record(
+                preserves::IOValue::symbol(SYMBOLS_EXTRACTED_LABEL),
+                vec![
+                    preserves::IOValue::new(batch),
+                    preserves::IOValue::new(files),
+                ],
+            ));
+        });
+    }
+
+    /// Assert the symbols of a finished batch and start the next one.
+    fn finish_batch(
+        &self,
+        activation: &mut Activation,
+        batch: &str,
+        files: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.batch.as_ref().map(|(id, _)| id.as_str()) != Some(batch) {
+            return Ok(());
+        }
+        state.batch = None;
+
+        for index in 0..files.len() {
+            let value = preserves::IOValue::from(files.index(index));
+            let Some(file) = record_with_label(&value, "symbols-file").filter(|r| r.len() >= 3)
+            else {
+                continue;
+            };
+            let Some(handle) = file
+                .field_string(0)
+                .and_then(|id| uuid::Uuid::parse_str(&id).ok())
+                .map(Handle)
+            else {
+                continue;
+            };
+            // Entries retracted or re-asserted since the batch started are stale
+            if state.queued.contains(&handle) {
+                continue;
+            }
+            let Some(indexed) = state.files.get_mut(&handle) else {
+                continue;
+            };
+            if Some(&indexed.path) != file.field_string(1).as_ref() || !indexed.handles.is_empty() {
+                continue;
+            }
+            let symbols = file.field(2);
+            for index in 0..symbols.len() {
+                let symbol = preserves::IOValue::from(symbols.index(index));
+                let symbol_handle = Handle::new();
+                activation.assert(symbol_handle.clone(), symbol);
+                indexed.handles.push(symbol_handle);
+            }
+        }
+
+        self.start_batch(activation, &mut state);
+        Ok(())
+    }
+
+    /// Requeue a batch whose task did not survive a restart and start over.
+    ///
+    /// A result still arriving for the abandoned batch is ignored.
+    fn resume(&self, activation: &mut Activation) {
+        let mut state = self.state.lock().unwrap();
+        if let Some((_, entries)) = state.batch.take() {
+            for handle in entries.into_iter().rev() {
+                if !state.queued.contains(&handle) {
+                    state.queued.insert(0, handle);
+                }
+            }
+        }
+        self.start_batch(activation, &mut state);
+    }
+}
+
+/// Read `rel_path` under `root` and extract its symbols.
+fn extract_file(root: &Path, rel_path: &str) -> Vec<extract::Symbol> {
+    let Some(language) = Language::for_path(Path::new(rel_path)) else {
+        return Vec::new();
+    };
+
+    let abs_path = root.join(rel_path);
+    let too_large = fs::metadata(&abs_path)
+        .map(|metadata| metadata.len() > MAX_SOURCE_BYTES)
+        .unwrap_or(true);
+    if too_large {
+        return Vec::new();
+    }
+    let Ok(source) = fs::read_to_string(&abs_path) else {
+        return Vec::new();
+    };
+
+    extract::extract(language, &source)
+}
+
+/// Build the `<symbol-entry kind name path <span start end>>` record for a symbol.
+pub fn symbol_record(path: &str, symbol: &extract::Symbol) -> preserves::IOValue {
+    preserves::IOValue::record(
+        preserves::IOValue::symbol(SYMBOL_ENTRY_LABEL),
+        vec![
+            preserves::IOValue::symbol(symbol.kind),
+            preserves::IOValue::new(symbol.name.clone()),
+            preserves::IOValue::new(path.to_string()),
+            preserves::IOValue::record(
+                preserves::IOValue::symbol("span"),
+                vec![
+                    preserves::IOValue::new(symbol.start_line as i64),
+                    preserves::IOValue::new(symbol.end_line as i64),
+                ],
+            ),
+        ],
+    )
+}
+
+/// Patterns matching the file entries the catalog indexes.
+///
+/// Workspace entries carry an optional digest and metadata record after the
+/// timestamp, so every arity is observed.
+pub fn workspace_file_patterns() -> Vec<preserves::IOValue> {
+    let wildcard = || preserves::IOValue::symbol("<_>");
+    let file_entry = |extra: usize| {
+        let mut fields = vec![
+            wildcard(),
+            preserves::IOValue::symbol("file"),
+            wildcard(),
+            wildcard(),
+        ];
+        fields.extend((0..extra).map(|_| wildcard()));
+        preserves::IOValue::record(preserves::IOValue::symbol("workspace-entry"), fields)
+    };
+    vec![file_entry(0), file_entry(1), file_entry(2)]
+}
+
+impl Entity for SymbolCatalog {
+    fn on_message(
+        &self,
+        activation: &mut Activation,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        if let Some(record) = record_with_label(payload, SYMBOLS_EXTRACTED_LABEL) {
+            let Some(batch) = record.field_string(0) else {
+                return Ok(());
+            };
+            return self.finish_batch(activation, &batch, &record.field(1));
+        }
+        if record_with_label(payload, SYMBOLS_RESUME_LABEL).is_some() {
+            self.resume(activation);
+        }
+        Ok(())
+    }
+
+    fn on_assert(
+        &self,
+        activation: &mut Activation,
+        handle: &Handle,
+        value: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        let Some(path) =
+            record_with_label(value, "workspace-entry").and_then(|r| r.field_string(0))
+        else {
+            return Ok(());
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(previous) = state.files.remove(handle) {
+            for stale in previous.handles {
+                activation.retract(stale);
+            }
+        }
+        state.queued.retain(|queued| queued != handle);
+
+        if Language::for_path(Path::new(&path)).is_some() {
+            state.files.insert(
+                handle.clone(),
+                IndexedFile {
+                    path,
+                    handles: Vec::new(),
+                },
+            );
+            state.queued.push(handle.clone());
+            self.start_batch(activation, &mut state);
+        }
+        Ok(())
+    }
+
+    fn on_retract(&self, activation: &mut Activation, handle: &Handle) -> ActorResult<()> {
+        let mut state = self.state.lock().unwrap();
+        state.queued.retain(|queued| queued != handle);
+        if let Some(indexed) = state.files.remove(handle) {
+            for stale in indexed.handles {
+                activation.retract(stale);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn handle_list(handles: &[Handle]) -> preserves::IOValue {
+    preserves::IOValue::new(
+        handles
+            .iter()
+            .map(|handle| preserves::IOValue::new(handle.0.to_string()))
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn parse_handle_list(value: &preserves::IOValue) -> Option<Vec<Handle>> {
+    (0..value.len())
+        .map(|index| {
+            let id = preserves::IOValue::from(value.index(index));
+            let id = id.as_string()?;
+            uuid::Uuid::parse_str(id.as_ref()).ok().map(Handle)
+        })
+        .collect()
+}
+
+impl HydratableEntity for SymbolCatalog {
+    fn snapshot_state(&self) -> preserves::IOValue {
+        let state = self.state.lock().unwrap();
+
+        let mut files: Vec<_> = state.files.iter().collect();
+        files.sort_by(|a, b| a.1.path.cmp(&b.1.path).then(a.0.0.cmp(&b.0.0)));
+        let files: Vec<preserves::IOValue> = files
+            .into_iter()
+            .map(|(handle, file)| {
+                preserves::IOValue::record(
+                    preserves::IOValue::symbol("file"),
+                    vec![
+                        preserves::IOValue::new(handle.0.to_string()),
+                        preserves::IOValue::new(file.path.clone()),
+                        handle_list(&file.handles),
+                    ],
+                )
+            })
+            .collect();
+
+        let batch = match &state.batch {
+            Some((id, entries)) => preserves::IOValue::record(
+                preserves::IOValue::symbol("batch"),
+                vec![preserves::IOValue::new(id.clone()), handle_list(entries)],
+            ),
+            None => preserves::IOValue::symbol("none"),
+        };
+
+        preserves::IOValue::record(
+            preserves::IOValue::symbol("symbols-state"),
+            vec![
+                preserves::IOValue::new(files),
+                handle_list(&state.queued),
+                batch,
+            ],
+        )
+    }
+
+    fn restore_state(&mut self, state: &preserves::IOValue) -> ActorResult<()> {
+        let invalid = || ActorError::InvalidActivation("malformed symbols state".into());
+        let record = record_with_label(state, "symbols-state")
+            .filter(|record| record.len() >= 3)
+            .ok_or_else(invalid)?;
+
+        let mut restored = CatalogState::default();
+        let files = record.field(0);
+        for index in 0..files.len() {
+            let value = preserves::IOValue::from(files.index(index));
+            let file = record_with_label(&value, "file")
+                .filter(|file| file.len() >= 3)
+                .ok_or_else(invalid)?;
+            let handle = file
+                .field_string(0)
+                .and_then(|id| uuid::Uuid::parse_str(&id).ok())
+                .ok_or_else(invalid)?;
+            let path = file.field_string(1).ok_or_else(invalid)?;
+            let handles = parse_handle_list(&file.field(2)).ok_or_else(invalid)?;
+            restored
+                .files
+                .insert(Handle(handle), IndexedFile { path, handles });
+        }
+        restored.queued = parse_handle_list(&record.field(1)).ok_or_else(invalid)?;
+        let batch = record.field(2);
+        if let Some(batch) = record_with_label(&batch, "batch").filter(|b| b.len() >= 2) {
+            let id = batch.field_string(0).ok_or_else(invalid)?;
+            let entries = parse_handle_list(&batch.field(1)).ok_or_else(invalid)?;
+            restored.batch = Some((id, entries));
+        }
+
+        *self.state.lock().unwrap() = restored;
+        Ok(())
+    }
+}
+
+/// Register the symbol catalog entity with the global registry.
+pub fn register(catalog: &EntityCatalog) {
+    catalog.register_hydratable("symbols", |config| {
+        let root = config
+            .as_string()
+            .map(|path| PathBuf::from(path.as_ref()))
+            .unwrap_or_else(|| PathBuf::from("."));
+        Ok(SymbolCatalog::new(root))
+    });
+}
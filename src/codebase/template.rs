@@ -0,0 +1,316 @@
+//! Project templates applied when a runtime is initialised
+//!
+//! `Runtime::init` only lays out bare storage. A template turns that into a
+//! usable project by registering the standard entities for a given use,
+//! installing default reactions and writing a `manifest.json` to the runtime
+//! root that records what was set up. Applying a template is idempotent:
+//! entities and reactions that already exist are reused, so `codebased` can
+//! apply its template on every start.
+//!
+//! * `bare` – storage only.
+//! * `codebase-daemon` – workspace catalog, symbol index and the coding
+//!   agents, as served by `codebased`.
+//! * `agent-sandbox` – the coding agents and a `scratch` key-value store,
+//!   without filesystem access.
+//!
+//! Profiles that include agents also install one reaction per agent that
+//! forwards `<agent-request id request prompt>` assertions made anywhere in
+//! the dataspace to that agent, so other actors can queue work declaratively.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::runtime::control::Control;
+use crate::runtime::error::{Result as RuntimeResult, RuntimeError};
+use crate::runtime::pattern::{Pattern, PatternScope};
+use crate::runtime::reaction::{ReactionDefinition, ReactionEffect, ReactionId, ReactionValue};
+use crate::runtime::storage::Storage;
+use crate::runtime::turn::{ActorId, FacetId};
+
+use super::{AgentHandle, agent};
+
+/// File written to the runtime root describing the applied template.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Name of the key-value namespace created by the agent sandbox.
+pub const SANDBOX_NAMESPACE: &str = "scratch";
+
+/// Selectable initialisation profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InitTemplate {
+    /// Storage only
+    Bare,
+    /// Workspace, symbol index and coding agents
+    #[default]
+    CodebaseDaemon,
+    /// Coding agents and a scratch key-value store, no filesystem access
+    AgentSandbox,
+}
+
+impl InitTemplate {
+    /// All templates, in the order they are listed to users.
+    pub const ALL: [InitTemplate; 3] = [
+        InitTemplate::Bare,
+        InitTemplate::CodebaseDaemon,
+        InitTemplate::AgentSandbox,
+    ];
+
+    /// Profile name accepted on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InitTemplate::Bare => "bare",
+            InitTemplate::CodebaseDaemon => "codebase-daemon",
+            InitTemplate::AgentSandbox => "agent-sandbox",
+        }
+    }
+}
+
+impl fmt::Display for InitTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for InitTemplate {
+    type Err = RuntimeError;
+
+    fn from_str(name: &str) -> RuntimeResult<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|template| template.as_str() == name)
+            .ok_or_else(|| {
+                let known: Vec<&str> = Self::ALL.iter().map(InitTemplate::as_str).collect();
+                RuntimeError::Config(format!(
+                    "Unknown init template '{}' (expected one of: {})",
+                    name,
+                    known.join(", ")
+                ))
+            })
+    }
+}
+
+/// Entity recorded in the project manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntity {
+    /// Registered entity type
+    pub entity_type: String,
+    /// Entity instance identifier
+    pub id: uuid::Uuid,
+    /// Actor hosting the entity
+    pub actor: ActorId,
+    /// Facet the entity is attached to
+    pub facet: FacetId,
+}
+
+/// Description of an applied template, persisted as [`MANIFEST_FILE`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectManifest {
+    /// Template that was applied
+    pub template: InitTemplate,
+    /// Workspace directory the entities serve
+    pub workspace_root: PathBuf,
+    /// Entities registered by the template
+    pub entities: Vec<ManifestEntity>,
+    /// Default reactions installed by the template
+    pub reactions: Vec<ReactionId>,
+    /// When the template was applied
+    pub applied_at: DateTime<Utc>,
+}
+
+/// Adjustments to what a template sets up.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TemplateOptions {
+    /// Workspace entries left out of the catalog
+    pub ignore: Vec<String>,
+    /// Agent kinds to register (`None` registers every agent)
+    pub agents: Option<Vec<String>>,
+}
+
+/// Apply `template` to an initialised runtime and write its manifest.
+pub fn apply_template(
+    control: &mut Control,
+    template: InitTemplate,
+    workspace_root: &Path,
+) -> RuntimeResult<ProjectManifest> {
+    apply_template_with(
+        control,
+        template,
+        workspace_root,
+        &TemplateOptions::default(),
+    )
+}
+
+/// Apply `template` with `options` and write its manifest.
+pub fn apply_template_with(
+    control: &mut Control,
+    template: InitTemplate,
+    workspace_root: &Path,
+    options: &TemplateOptions,
+) -> RuntimeResult<ProjectManifest> {
+    let mut entities = Vec::new();
+    let mut agents = Vec::new();
+    let wants_agent = |kind: &str| {
+        options
+            .agents
+            .as_ref()
+            .is_none_or(|kinds| kinds.iter().any(|wanted| wanted == kind))
+    };
+
+    if template == InitTemplate::CodebaseDaemon {
+        let workspace =
+            super::ensure_workspace_entity_with(control, workspace_root, &options.ignore)?;
+        entities.push(manifest_entity(
+            "workspace",
+            workspace.entity_id,
+            workspace.actor,
+            workspace.facet,
+        ));
+        let symbols = super::ensure_symbols_entity(control, workspace_root)?;
+        entities.push(manifest_entity(
+            "symbols",
+            symbols.entity_id,
+            symbols.actor,
+            symbols.facet,
+        ));
+    }
+
+    if template != InitTemplate::Bare {
+        if wants_agent(agent::claude::CLAUDE_KIND) {
+            agents.push(super::ensure_claude_agent(control)?);
+        }
+        if wants_agent(agent::codex::CODEX_KIND) {
+            agents.push(super::ensure_codex_agent(control)?);
+        }
+        if wants_agent(agent::harness::HARNESS_KIND) {
+            agents.push(super::ensure_harness_agent(control)?);
+        }
+        for handle in &agents {
+            let entity_type = agent::entity_type_for_kind(&handle.kind).unwrap_or(&handle.kind);
+            entities.push(manifest_entity(
+                entity_type,
+                handle.entity_id,
+                handle.actor.clone(),
+                handle.facet.clone(),
+            ));
+        }
+    }
+
+    if template == InitTemplate::AgentSandbox {
+        let store = super::ensure_kv_entity(control, SANDBOX_NAMESPACE)?;
+        entities.push(manifest_entity(
+            super::kv::ENTITY_TYPE,
+            store.entity_id,
+            store.actor,
+            store.facet,
+        ));
+    }
+
+    let mut reactions = Vec::new();
+    for handle in &agents {
+        reactions.push(ensure_request_forwarding(control, handle)?);
+    }
+
+    let manifest = ProjectManifest {
+        template,
+        workspace_root: workspace_root.to_path_buf(),
+        entities,
+        reactions,
+        applied_at: Utc::now(),
+    };
+    write_manifest(control, &manifest)?;
+    Ok(manifest)
+}
+
+/// Load the manifest written by the last applied template, if any.
+pub fn load_manifest(root: &Path) -> RuntimeResult<Option<ProjectManifest>> {
+    let storage = Storage::new(root.to_path_buf());
+    let path = root.join(MANIFEST_FILE);
+    if !storage.exists(&path) {
+        return Ok(None);
+    }
+    let data = storage.read_file(&path)?;
+    serde_json::from_slice(&data)
+        .map(Some)
+        .map_err(|err| RuntimeError::Config(format!("Invalid project manifest: {}", err)))
+}
+
+fn manifest_entity(
+    entity_type: &str,
+    id: uuid::Uuid,
+    actor: ActorId,
+    facet: FacetId,
+) -> ManifestEntity {
+    ManifestEntity {
+        entity_type: entity_type.to_string(),
+        id,
+        actor,
+        facet,
+    }
+}
+
+/// Reaction delivering dataspace-wide `agent-request` assertions to `handle`.
+fn ensure_request_forwarding(
+    control: &mut Control,
+    handle: &AgentHandle,
+) -> RuntimeResult<ReactionId> {
+    let pattern = preserves::IOValue::record(
+        preserves::IOValue::symbol(agent::REQUEST_LABEL),
+        vec![
+            preserves::IOValue::new(handle.entity_id.to_string()),
+            preserves::IOValue::symbol("<_>"),
+            preserves::IOValue::symbol("<_>"),
+        ],
+    );
+
+    if let Some(existing) = control
+        .list_reactions()
+        .into_iter()
+        .find(|info| info.actor == handle.actor && info.definition.pattern.pattern == pattern)
+    {
+        return Ok(existing.reaction_id);
+    }
+
+    let definition = ReactionDefinition::new(
+        Pattern {
+            id: uuid::Uuid::new_v4(),
+            pattern,
+            facet: handle.facet.clone(),
+            namespace: None,
+            scope: PatternScope::Actor,
+        }
+        .dataspace_wide(),
+        ReactionEffect::SendMessage {
+            actor: handle.actor.clone(),
+            facet: handle.facet.clone(),
+            payload: ReactionValue::Match,
+        },
+    );
+    control.register_reaction(handle.actor.clone(), definition)
+}
+
+fn write_manifest(control: &Control, manifest: &ProjectManifest) -> RuntimeResult<()> {
+    let storage = control.runtime().storage();
+    let json = serde_json::to_vec_pretty(manifest)
+        .map_err(|err| RuntimeError::Config(format!("Failed to encode manifest: {}", err)))?;
+    storage.write_atomic(&storage.root().join(MANIFEST_FILE), &json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_names_round_trip() {
+        for template in InitTemplate::ALL {
+            assert_eq!(template.as_str().parse::<InitTemplate>().unwrap(), template);
+        }
+        assert!("kitchen-sink".parse::<InitTemplate>().is_err());
+        assert_eq!(InitTemplate::default(), InitTemplate::CodebaseDaemon);
+    }
+}
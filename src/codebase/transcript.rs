@@ -15,11 +15,12 @@ use crate::util::io_value::{io_value_summary, io_value_to_json, record_with_labe
 use chrono::{DateTime, Utc};
 use codebase::AgentResponse;
 use preserves::IOValue;
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value, json};
 use std::time::Duration;
 
 /// Snapshot describing the most recent transcript state we observed.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptCursor {
     /// Branch where the conversation is taking place.
     pub branch: BranchId,
@@ -46,7 +47,7 @@ pub struct TranscriptEntry {
     pub role: Option<String>,
     /// Tool metadata associated with the response, if provided.
     pub tool: Option<String>,
-    /// Timestamp recorded for the response, if provided by the agent.
+    /// Wall-clock time recorded by the agent, if provided (advisory only).
     pub response_timestamp: Option<DateTime<Utc>>,
 }
 
@@ -64,94 +65,68 @@ pub fn transcript_entries(
         .or_else(|| cursor.map(|c| c.branch.clone()))
         .unwrap_or_else(|| status.active_branch.clone());
 
+    // Read the branch's journal directly so inspecting another branch never
+    // disturbs the live one.
+    let assertions = control.assertions_on(&branch, None)?;
+    let head = control
+        .runtime()
+        .branch_manager()
+        .head(&branch)
+        .cloned()
+        .unwrap_or_else(|| status.head_turn.clone());
+
     let mut resolved_cursor = cursor.cloned().unwrap_or(TranscriptCursor {
         branch: branch.clone(),
-        last_turn: status.head_turn.clone(),
+        last_turn: head.clone(),
         actor: None,
     });
     resolved_cursor.branch = branch.clone();
-    resolved_cursor.last_turn = status.head_turn.clone();
+    resolved_cursor.last_turn = head;
 
+    // Assertions replay in causal order, so entries need no re-sorting by the
+    // agents' wall-clock response timestamps (which differ across replays).
     let mut entries = Vec::new();
 
-    if let Some(actor_id) = resolved_cursor.actor.clone() {
-        for (handle, value) in control.list_assertions_for_actor(&actor_id) {
-            if !matches_request(&value, request_id) {
-                continue;
-            }
-            if let Some(agent_resp) = parse_agent_response(&value) {
-                let AgentResponse {
-                    prompt,
-                    response,
-                    agent,
-                    timestamp,
-                    role,
-                    tool,
-                    ..
-                } = agent_resp;
-                entries.push(TranscriptEntry {
-                    actor: actor_id.clone(),
-                    handle,
-                    agent,
-                    prompt,
-                    response,
-                    role,
-                    tool,
-                    response_timestamp: timestamp,
-                });
-                if entries.len() >= limit {
-                    break;
-                }
-            }
+    for assertion in assertions {
+        if resolved_cursor
+            .actor
+            .as_ref()
+            .is_some_and(|actor| *actor != assertion.actor)
+        {
+            continue;
         }
-    } else {
-        for assertion in control.list_assertions(None) {
-            if !matches_label(&assertion.value) {
-                continue;
-            }
-            if !matches_request(&assertion.value, request_id) {
-                continue;
-            }
-
-            if let Some(agent_resp) = parse_agent_response(&assertion.value) {
-                let AgentResponse {
-                    prompt,
-                    response,
-                    agent,
-                    timestamp,
-                    role,
-                    tool,
-                    ..
-                } = agent_resp;
-                resolved_cursor.actor.get_or_insert(assertion.actor.clone());
-                entries.push(TranscriptEntry {
-                    actor: assertion.actor.clone(),
-                    handle: assertion.handle.clone(),
-                    agent,
-                    prompt,
-                    response,
-                    role,
-                    tool,
-                    response_timestamp: timestamp,
-                });
-                if entries.len() >= limit {
-                    break;
-                }
-            }
+        if !matches_label(&assertion.value) {
+            continue;
         }
-    }
-
-    entries.sort_by(|a, b| {
-        let time_order = a.response_timestamp.cmp(&b.response_timestamp);
-        if time_order == std::cmp::Ordering::Equal {
-            a.handle.0.cmp(&b.handle.0)
-        } else {
-            time_order
+        if !matches_request(&assertion.value, request_id) {
+            continue;
         }
-    });
 
-    if entries.len() > limit {
-        entries.truncate(limit);
+        if let Some(agent_resp) = parse_agent_response(&assertion.value) {
+            let AgentResponse {
+                prompt,
+                response,
+                agent,
+                timestamp,
+                role,
+                tool,
+                ..
+            } = agent_resp;
+            resolved_cursor.actor.get_or_insert(assertion.actor.clone());
+            entries.push(TranscriptEntry {
+                actor: assertion.actor,
+                handle: assertion.handle,
+                agent,
+                prompt,
+                response,
+                role,
+                tool,
+                response_timestamp: timestamp,
+            });
+            if entries.len() >= limit {
+                break;
+            }
+        }
     }
 
     Ok((entries, resolved_cursor))
@@ -288,6 +263,7 @@ pub fn event_batches_payload(chunk: &AssertionEventChunk) -> Vec<Value> {
                 "actor": batch.actor.to_string(),
                 "clock": batch.clock,
                 "timestamp": batch.timestamp.to_rfc3339(),
+                "logical_time": batch.logical_time,
                 "events": events,
             })
         })
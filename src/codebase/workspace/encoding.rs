@@ -0,0 +1,224 @@
+//! Decoding of file content for `workspace/read`
+//!
+//! A plain `<workspace-read path>` returns the file as a string and fails on
+//! content that is not UTF-8. Naming a mode – `<workspace-read path mode>` –
+//! always succeeds and answers with a `<workspace-content ...>` record that
+//! says how the bytes were interpreted:
+//!
+//! * `utf8` – strict UTF-8, as the plain form.
+//! * `bytes` – the raw content as a byte string.
+//! * `lossy` – UTF-8 with each invalid sequence replaced by U+FFFD; the
+//!   number of replacements is reported.
+//! * `detect` – honour a UTF-8/UTF-16 byte-order mark, otherwise use UTF-8
+//!   when the content is valid and fall back to ISO-8859-1, which maps every
+//!   byte.
+//!
+//! Decoding only depends on the bytes and the mode, and the record carries
+//! the source size and blake3 digest, so a replayed turn reproduces the
+//! response exactly and clients can check it against the file on disk.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Record label of a decoded read response.
+pub const CONTENT_LABEL: &str = "workspace-content";
+
+/// How `workspace/read` interprets file bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadEncoding {
+    /// Strict UTF-8
+    Utf8,
+    /// Raw bytes
+    Bytes,
+    /// UTF-8 with replacement markers
+    Lossy,
+    /// Byte-order mark, then UTF-8, then ISO-8859-1
+    Detect,
+}
+
+impl ReadEncoding {
+    /// All modes, in the order they are listed to users.
+    pub const ALL: [ReadEncoding; 4] = [
+        ReadEncoding::Utf8,
+        ReadEncoding::Bytes,
+        ReadEncoding::Lossy,
+        ReadEncoding::Detect,
+    ];
+
+    /// Symbol naming the mode in `workspace-read` payloads.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReadEncoding::Utf8 => "utf8",
+            ReadEncoding::Bytes => "bytes",
+            ReadEncoding::Lossy => "lossy",
+            ReadEncoding::Detect => "detect",
+        }
+    }
+}
+
+impl fmt::Display for ReadEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ReadEncoding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.as_str() == name)
+            .ok_or_else(|| {
+                let known: Vec<&str> = Self::ALL.iter().map(ReadEncoding::as_str).collect();
+                format!(
+                    "unknown read encoding '{}' (expected one of: {})",
+                    name,
+                    known.join(", ")
+                )
+            })
+    }
+}
+
+/// Decoded file content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodedData {
+    /// Text content
+    Text(String),
+    /// Raw content
+    Bytes(Vec<u8>),
+}
+
+/// Result of decoding a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    /// Character encoding the bytes were read as (`binary` for raw bytes)
+    pub encoding: &'static str,
+    /// The content
+    pub data: DecodedData,
+    /// Invalid sequences replaced by U+FFFD
+    pub replacements: usize,
+}
+
+/// Decode `bytes` according to `mode`.
+///
+/// Only [`ReadEncoding::Utf8`] can fail, when the content is not UTF-8.
+pub fn decode(bytes: Vec<u8>, mode: ReadEncoding) -> Result<Decoded, String> {
+    let text = |encoding, text| Decoded {
+        encoding,
+        data: DecodedData::Text(text),
+        replacements: 0,
+    };
+
+    match mode {
+        ReadEncoding::Utf8 => String::from_utf8(bytes)
+            .map(|decoded| text("utf-8", decoded))
+            .map_err(|err| format!("content is not valid UTF-8 ({})", err.utf8_error())),
+        ReadEncoding::Bytes => Ok(Decoded {
+            encoding: "binary",
+            data: DecodedData::Bytes(bytes),
+            replacements: 0,
+        }),
+        ReadEncoding::Lossy => {
+            let (decoded, replacements) = utf8_lossy(&bytes);
+            Ok(Decoded {
+                encoding: "utf-8",
+                data: DecodedData::Text(decoded),
+                replacements,
+            })
+        }
+        ReadEncoding::Detect => Ok(detect(bytes)),
+    }
+}
+
+fn detect(bytes: Vec<u8>) -> Decoded {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        let (decoded, replacements) = utf8_lossy(rest);
+        return Decoded {
+            encoding: "utf-8",
+            data: DecodedData::Text(decoded),
+            replacements,
+        };
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return utf16(rest, "utf-16le", u16::from_le_bytes);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return utf16(rest, "utf-16be", u16::from_be_bytes);
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(decoded) => Decoded {
+            encoding: "utf-8",
+            data: DecodedData::Text(decoded),
+            replacements: 0,
+        },
+        Err(err) => Decoded {
+            encoding: "iso-8859-1",
+            data: DecodedData::Text(err.into_bytes().iter().map(|&b| char::from(b)).collect()),
+            replacements: 0,
+        },
+    }
+}
+
+fn utf16(bytes: &[u8], encoding: &'static str, unit: fn([u8; 2]) -> u16) -> Decoded {
+    let units = bytes.chunks(2).map(|pair| match pair {
+        [a, b] => unit([*a, *b]),
+        // A dangling odd byte cannot form a code unit
+        _ => 0xDC00,
+    });
+    let mut replacements = 0;
+    let decoded = char::decode_utf16(units)
+        .map(|ch| {
+            ch.unwrap_or_else(|_| {
+                replacements += 1;
+                char::REPLACEMENT_CHARACTER
+            })
+        })
+        .collect();
+    Decoded {
+        encoding,
+        data: DecodedData::Text(decoded),
+        replacements,
+    }
+}
+
+fn utf8_lossy(bytes: &[u8]) -> (String, usize) {
+    let mut decoded = String::with_capacity(bytes.len());
+    let mut replacements = 0;
+    for chunk in bytes.utf8_chunks() {
+        decoded.push_str(chunk.valid());
+        if !chunk.invalid().is_empty() {
+            decoded.push(char::REPLACEMENT_CHARACTER);
+            replacements += 1;
+        }
+    }
+    (decoded, replacements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_decode_non_utf8_content() {
+        let latin1 = b"caf\xe9".to_vec();
+
+        assert!(decode(latin1.clone(), ReadEncoding::Utf8).is_err());
+
+        let bytes = decode(latin1.clone(), ReadEncoding::Bytes).unwrap();
+        assert_eq!(bytes.data, DecodedData::Bytes(latin1.clone()));
+
+        let lossy = decode(latin1.clone(), ReadEncoding::Lossy).unwrap();
+        assert_eq!(lossy.data, DecodedData::Text("caf\u{FFFD}".into()));
+        assert_eq!(lossy.replacements, 1);
+
+        let detected = decode(latin1, ReadEncoding::Detect).unwrap();
+        assert_eq!(detected.encoding, "iso-8859-1");
+        assert_eq!(detected.data, DecodedData::Text("café".into()));
+
+        let utf16 = decode(vec![0xFF, 0xFE, b'h', 0, b'i', 0], ReadEncoding::Detect).unwrap();
+        assert_eq!(utf16.encoding, "utf-16le");
+        assert_eq!(utf16.data, DecodedData::Text("hi".into()));
+    }
+}
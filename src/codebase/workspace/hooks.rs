@@ -0,0 +1,95 @@
+//! Workspace policy hooks
+//!
+//! Embedders customise what the workspace entity publishes by registering
+//! named hooks here and referencing them from the entity config value:
+//!
+//! * **Path classifiers** decide whether an entry is scanned normally,
+//!   recorded as *opaque* (its existence is published but a directory's
+//!   contents are not walked), or ignored altogether.
+//! * **Metadata extractors** compute an extra value for file entries; the
+//!   results are appended to `workspace-entry` assertions as
+//!   `<metadata <name value> ...>`.
+//!
+//! Hooks only run when an entry is (re)asserted, so unchanged files cost
+//! nothing beyond a `stat` during rescans.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+/// How the workspace scanner treats a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathClass {
+    /// Publish the entry and, for directories, walk its contents
+    Include,
+    /// Publish the entry but never walk a directory's contents
+    Opaque,
+    /// Publish nothing for the entry or anything beneath it
+    Ignore,
+}
+
+/// Classifier consulted for every entry below the workspace root.
+///
+/// Receives the workspace-relative path and whether it is a directory;
+/// returning `None` defers to the next classifier.
+pub type PathClassifier = Arc<dyn Fn(&Path, bool) -> Option<PathClass> + Send + Sync>;
+
+/// Extractor computing extra metadata for a file from its absolute path.
+pub type MetadataExtractor = Arc<dyn Fn(&Path) -> Option<preserves::IOValue> + Send + Sync>;
+
+#[derive(Default)]
+struct HookRegistry {
+    classifiers: HashMap<String, PathClassifier>,
+    extractors: HashMap<String, MetadataExtractor>,
+}
+
+static HOOKS: Lazy<RwLock<HookRegistry>> = Lazy::new(|| RwLock::new(HookRegistry::default()));
+
+/// Register a named path classifier, replacing any previous one with that name.
+pub fn register_path_classifier<F>(name: impl Into<String>, classifier: F)
+where
+    F: Fn(&Path, bool) -> Option<PathClass> + Send + Sync + 'static,
+{
+    HOOKS
+        .write()
+        .unwrap()
+        .classifiers
+        .insert(name.into(), Arc::new(classifier));
+}
+
+/// Register a named metadata extractor, replacing any previous one with that name.
+pub fn register_metadata_extractor<F>(name: impl Into<String>, extractor: F)
+where
+    F: Fn(&Path) -> Option<preserves::IOValue> + Send + Sync + 'static,
+{
+    HOOKS
+        .write()
+        .unwrap()
+        .extractors
+        .insert(name.into(), Arc::new(extractor));
+}
+
+pub(super) fn classifier(name: &str) -> Option<PathClassifier> {
+    HOOKS.read().unwrap().classifiers.get(name).cloned()
+}
+
+pub(super) fn extractor(name: &str) -> Option<MetadataExtractor> {
+    HOOKS.read().unwrap().extractors.get(name).cloned()
+}
+
+/// Check a config rule against a workspace-relative path.
+///
+/// Rules containing `/` name a path exactly; bare rules match any entry with
+/// that file name (so `target` matches `target/` at every depth).
+pub(super) fn rule_matches(rule: &str, rel_path: &Path) -> bool {
+    let rule = rule.trim_end_matches('/');
+    if rule.contains('/') {
+        rel_path == Path::new(rule)
+    } else {
+        rel_path
+            .file_name()
+            .is_some_and(|name| name.to_str() == Some(rule))
+    }
+}
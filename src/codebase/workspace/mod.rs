@@ -3,8 +3,45 @@
 //! This module provides an entity that mirrors the local filesystem into the
 //! dataspace. It publishes immutable facts about files/directories and grants
 //! capabilities for controlled modification.
+//!
+//! Rescans are incremental: a directory whose mtime and inode are unchanged
+//! since the previous scan has the same set of children, so its cached listing
+//! is reused instead of reading the directory again, and the files in it keep
+//! their previous entries without being stat'ed. Only its subdirectories are
+//! visited, since their own mtimes decide whether they changed. An edit that
+//! rewrites a file in place leaves its directory's mtime alone, so it shows up
+//! on the entity's own writes (which re-stat the written path) or on a
+//! `<workspace-rescan full>`, which ignores the cache. Listings taken within
+//! [`RACY_WINDOW_SECS`] of a directory's mtime are not cached, as a later
+//! change in the same timestamp tick would go unnoticed. The cache is part of
+//! the entity's hydratable state, so it survives restarts and time travel.
+//!
+//! What gets published is governed by the entity config: paths can be
+//! ignored or treated as opaque (recorded, never walked), files can carry a
+//! content digest, and embedder-registered [`hooks`] can classify paths and
+//! append metadata to `workspace-entry` assertions.
+//!
+//! Every write journals the content it replaces (see
+//! [`Activation::record_file_backup`]), so `Control::restore_file` can put a
+//! file back the way it was before any given turn wrote it.
+//!
+//! With the `<offers>` config option, capability requests are not granted
+//! outright. The entity asserts `<capability-offer id kind path>` instead and
+//! only grants the capability once a `<capability-accept id>` message arrives
+//! (`<capability-decline id>` withdraws it). Accepting is therefore a journaled
+//! turn of its own, and pending offers are visible to policy review.
+//!
+//! Reads are strict UTF-8 unless the request names one of the [`encoding`]
+//! modes, which return raw bytes, lossy text or detected-encoding text in a
+//! `<workspace-content ...>` record describing how the bytes were decoded.
+//!
+//! Large files can be read incrementally through the `workspace/stream-read`
+//! capability: instead of one response value it asserts the content as
+//! numbered `<workspace-chunk stream path seq bytes>` records followed by a
+//! `<workspace-stream-end stream path chunks bytes>` marker, which clients
+//! pick up through the usual dataspace events.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
@@ -12,50 +49,268 @@ use std::sync::{Arc, Mutex};
 use chrono::{DateTime, Utc};
 use preserves::ValueImpl;
 use serde::{Deserialize, Serialize};
-use walkdir::WalkDir;
 
-use crate::runtime::actor::{Activation, CapabilitySpec, Entity};
+use crate::runtime::actor::{Activation, CapabilitySpec, Entity, HydratableEntity};
+use crate::runtime::effects::EffectKind;
 use crate::runtime::error::{ActorError, ActorResult};
-use crate::runtime::registry::EntityCatalog;
+use crate::runtime::registry::{EntityCatalog, EntityDescriptor};
 use crate::runtime::turn::{FacetId, Handle};
+use crate::util::canonical::timestamp_value;
 use crate::util::io_value::record_with_label;
 
 use crate::runtime::state::{CapabilityMetadata, CapabilityTarget};
 #[cfg(test)]
 use crate::runtime::turn::TurnOutput;
 
+pub mod encoding;
+pub mod hooks;
+
+use encoding::{DecodedData, ReadEncoding};
+use hooks::{MetadataExtractor, PathClass, PathClassifier};
+
 const CAP_KIND_READ: &str = "workspace/read";
 const CAP_KIND_WRITE: &str = "workspace/write";
+const CAP_KIND_STREAM_READ: &str = "workspace/stream-read";
+
+/// Label of the assertion announcing a pending capability offer.
+pub const OFFER_LABEL: &str = "capability-offer";
+
+/// Chunk size used by `workspace/stream-read` when the request names none.
+pub const STREAM_CHUNK_BYTES: usize = 16 * 1024;
+
+/// Largest chunk size a `workspace/stream-read` request may ask for.
+pub const MAX_STREAM_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// Directory listings younger than this (relative to the directory mtime) are not cached.
+pub const RACY_WINDOW_SECS: i64 = 2;
 
 /// Configuration accepted by the workspace catalog entity.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+///
+/// The config value is either a bare root path string or a record
+/// `<workspace-config "root" option...>` where each option is one of
+/// `<opaque rule...>`, `<ignore rule...>`, `<classify name...>`,
+/// `<extract name...>`, `<digest>` or `<offers>`. Rules are file names (matched at any
+/// depth) or workspace-relative paths; `classify`/`extract` name hooks
+/// registered through [`hooks`].
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 struct WorkspaceConfig {
     /// Root directory of the workspace (defaults to current directory)
     root: PathBuf,
+    /// Entries recorded without walking their contents
+    #[serde(default)]
+    opaque: Vec<String>,
+    /// Entries left out of the catalog entirely
+    #[serde(default)]
+    ignore: Vec<String>,
+    /// Registered path classifiers to consult, in order
+    #[serde(default)]
+    classifiers: Vec<String>,
+    /// Registered metadata extractors to run on files, in order
+    #[serde(default)]
+    extractors: Vec<String>,
+    /// Whether to publish a content digest for files
+    #[serde(default)]
+    digest: bool,
+    /// Whether capability requests become offers that must be accepted
+    #[serde(default)]
+    offers: bool,
 }
 
 impl WorkspaceConfig {
-    fn from_value(config: &preserves::IOValue) -> Self {
+    fn from_value(config: &preserves::IOValue) -> ActorResult<Self> {
         if let Some(path) = config.as_string() {
-            Self::normalize(PathBuf::from(path.as_ref()))
-        } else {
-            Self::normalize(PathBuf::from("."))
+            return Ok(Self::normalize(PathBuf::from(path.as_ref())));
+        }
+
+        let Some(record) = record_with_label(config, "workspace-config") else {
+            return Ok(Self::normalize(PathBuf::from(".")));
+        };
+
+        let invalid = |reason: String| ActorError::InvalidActivation(reason);
+        let root = record
+            .field_string(0)
+            .ok_or_else(|| invalid("workspace-config requires a root path".into()))?;
+        let mut parsed = Self::normalize(PathBuf::from(root));
+
+        for index in 1..record.len() {
+            let option = record.field(index);
+            let name = option
+                .is_record()
+                .then(|| option.label().as_symbol().map(|s| s.as_ref().to_string()))
+                .flatten()
+                .ok_or_else(|| invalid("workspace-config options must be records".into()))?;
+            let values = (0..option.len())
+                .map(|i| {
+                    preserves::IOValue::from(option.index(i))
+                        .as_string()
+                        .map(|s| s.to_string())
+                        .ok_or_else(|| invalid(format!("workspace-config {name} expects strings")))
+                })
+                .collect::<ActorResult<Vec<_>>>()?;
+
+            match name.as_str() {
+                "opaque" => parsed.opaque.extend(values),
+                "ignore" => parsed.ignore.extend(values),
+                "classify" => parsed.classifiers.extend(values),
+                "extract" => parsed.extractors.extend(values),
+                "digest" => parsed.digest = true,
+                "offers" => parsed.offers = true,
+                other => {
+                    return Err(invalid(format!(
+                        "unknown workspace-config option '{other}'"
+                    )));
+                }
+            }
         }
+
+        Ok(parsed)
     }
 
     fn normalize(root: PathBuf) -> Self {
-        if let Ok(canon) = fs::canonicalize(&root) {
-            Self { root: canon }
-        } else {
-            Self { root }
+        let root = fs::canonicalize(&root).unwrap_or(root);
+        Self {
+            root,
+            ..Self::default()
         }
     }
 }
 
+/// Config rules with their named hooks resolved.
+struct WorkspacePolicy {
+    opaque: Vec<String>,
+    ignore: Vec<String>,
+    classifiers: Vec<PathClassifier>,
+    extractors: Vec<(String, MetadataExtractor)>,
+    digest: bool,
+    offers: bool,
+}
+
+impl WorkspacePolicy {
+    fn resolve(config: &WorkspaceConfig) -> ActorResult<Self> {
+        let missing = |kind: &str, name: &str| {
+            ActorError::InvalidActivation(format!("unknown {kind} '{name}'"))
+        };
+
+        let classifiers = config
+            .classifiers
+            .as_slice()
+            .iter()
+            .map(|name| hooks::classifier(name).ok_or_else(|| missing("path classifier", name)))
+            .collect::<ActorResult<_>>()?;
+        let extractors = config
+            .extractors
+            .as_slice()
+            .iter()
+            .map(|name| {
+                hooks::extractor(name)
+                    .map(|extractor| (name.clone(), extractor))
+                    .ok_or_else(|| missing("metadata extractor", name))
+            })
+            .collect::<ActorResult<_>>()?;
+
+        Ok(Self {
+            opaque: config.opaque.clone(),
+            ignore: config.ignore.clone(),
+            classifiers,
+            extractors,
+            digest: config.digest,
+            offers: config.offers,
+        })
+    }
+
+    /// Classify a non-root entry. Explicit `ignore`/`opaque` rules take
+    /// precedence over classifiers; the first classifier with an opinion wins.
+    fn classify(&self, rel_path: &Path, is_dir: bool) -> PathClass {
+        if self
+            .ignore
+            .as_slice()
+            .iter()
+            .any(|rule| hooks::rule_matches(rule, rel_path))
+        {
+            return PathClass::Ignore;
+        }
+        if self
+            .opaque
+            .as_slice()
+            .iter()
+            .any(|rule| hooks::rule_matches(rule, rel_path))
+        {
+            return PathClass::Opaque;
+        }
+        self.classifiers
+            .iter()
+            .find_map(|classifier| classifier(rel_path, is_dir))
+            .unwrap_or(PathClass::Include)
+    }
+
+    /// Fill in the digest of a file and run the metadata extractors over it.
+    fn inspect(&self, abs_path: &Path, entry: &mut FileEntry) -> Vec<(String, preserves::IOValue)> {
+        if entry.kind != FileKind::File {
+            return Vec::new();
+        }
+        if self.digest {
+            entry.digest = fs::read(abs_path)
+                .ok()
+                .map(|contents| blake3::hash(&contents).to_hex().to_string());
+        }
+        self.extractors
+            .iter()
+            .filter_map(|(name, extractor)| extractor(abs_path).map(|value| (name.clone(), value)))
+            .collect()
+    }
+}
+
 /// Internal memoisation of discovered entries to avoid reasserting duplicates.
 #[derive(Debug, Default)]
 struct CatalogState {
     entries: HashMap<PathBuf, CatalogEntry>,
+    /// Cached child listings keyed by workspace-relative directory path
+    dirs: HashMap<PathBuf, DirListing>,
+    /// Capability offers awaiting acceptance, keyed by offer id
+    offers: BTreeMap<uuid::Uuid, PendingOffer>,
+}
+
+/// A capability offered to a requester but not yet accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PendingOffer {
+    /// Handle of the `capability-offer` assertion
+    handle: Handle,
+    /// Facet the capability will be granted on
+    facet: FacetId,
+    /// Capability kind
+    kind: String,
+    /// Workspace-relative path the capability is attenuated to
+    path: PathBuf,
+}
+
+/// Children of a directory as of a given mtime/inode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DirListing {
+    modified: DateTime<Utc>,
+    inode: u64,
+    children: Vec<PathBuf>,
+}
+
+/// Counters describing how much work a rescan performed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ScanStats {
+    /// Directories whose children were read from disk
+    listed: usize,
+    /// Directories whose cached listing was reused
+    reused: usize,
+    /// Files kept from the previous scan without being stat'ed
+    skipped: usize,
+}
+
+/// How much of the workspace a rescan re-examines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rescan<'a> {
+    /// Skip the files of directories that have not changed
+    Incremental,
+    /// Incremental, but always stat the given workspace-relative path
+    Touched(&'a Path),
+    /// Read and stat everything, ignoring cached listings
+    Full,
 }
 
 /// Representation of a single filesystem entry.
@@ -64,9 +319,20 @@ struct FileEntry {
     kind: FileKind,
     size: u64,
     modified: Option<DateTime<Utc>>,
+    inode: u64,
     digest: Option<String>,
 }
 
+impl FileEntry {
+    /// Whether the stat-derived fields match (digests are only computed on change).
+    fn same_stat(&self, other: &FileEntry) -> bool {
+        self.kind == other.kind
+            && self.size == other.size
+            && self.modified == other.modified
+            && self.inode == other.inode
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum FileKind {
     File,
@@ -75,6 +341,26 @@ enum FileKind {
     Other,
 }
 
+impl FileKind {
+    fn as_symbol(self) -> &'static str {
+        match self {
+            FileKind::File => "file",
+            FileKind::Directory => "dir",
+            FileKind::Symlink => "symlink",
+            FileKind::Other => "other",
+        }
+    }
+
+    fn from_symbol(symbol: &str) -> Self {
+        match symbol {
+            "file" => FileKind::File,
+            "dir" => FileKind::Directory,
+            "symlink" => FileKind::Symlink,
+            _ => FileKind::Other,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct CatalogEntry {
     handle: Handle,
@@ -84,15 +370,17 @@ struct CatalogEntry {
 /// Workspace catalog entity implementation.
 pub struct WorkspaceCatalog {
     root: PathBuf,
+    policy: WorkspacePolicy,
     state: Arc<Mutex<CatalogState>>,
 }
 
 impl WorkspaceCatalog {
-    fn new(config: &WorkspaceConfig) -> Self {
-        Self {
+    fn new(config: &WorkspaceConfig) -> ActorResult<Self> {
+        Ok(Self {
             root: config.root.clone(),
+            policy: WorkspacePolicy::resolve(config)?,
             state: Arc::new(Mutex::new(CatalogState::default())),
-        }
+        })
     }
 
     fn relative(&self, path: &Path) -> PathBuf {
@@ -122,6 +410,7 @@ impl WorkspaceCatalog {
                 kind,
                 size,
                 modified,
+                inode: inode_of(&metadata),
                 digest: None,
             }
         } else {
@@ -129,6 +418,7 @@ impl WorkspaceCatalog {
                 kind: FileKind::Other,
                 size: 0,
                 modified: None,
+                inode: 0,
                 digest: None,
             }
         }
@@ -139,14 +429,10 @@ impl WorkspaceCatalog {
         activation: &mut Activation,
         rel_path: &Path,
         entry: &FileEntry,
+        metadata: Vec<(String, preserves::IOValue)>,
         handle: Handle,
     ) -> CatalogEntry {
-        let kind_symbol = match entry.kind {
-            FileKind::File => "file",
-            FileKind::Directory => "dir",
-            FileKind::Symlink => "symlink",
-            FileKind::Other => "other",
-        };
+        let kind_symbol = entry.kind.as_symbol();
 
         let mut fields = vec![
             preserves::IOValue::new(self.path_display(rel_path)),
@@ -155,13 +441,28 @@ impl WorkspaceCatalog {
         ];
 
         if let Some(timestamp) = entry.modified {
-            fields.push(preserves::IOValue::new(timestamp.to_rfc3339()));
+            fields.push(timestamp_value(&timestamp));
         } else {
             fields.push(preserves::IOValue::symbol("unknown"));
         }
 
         if let Some(digest) = &entry.digest {
             fields.push(preserves::IOValue::new(digest.clone()));
+        } else if !metadata.is_empty() {
+            fields.push(preserves::IOValue::symbol("none"));
+        }
+
+        if !metadata.is_empty() {
+            let items = metadata
+                .into_iter()
+                .map(|(name, value)| {
+                    preserves::IOValue::record(preserves::IOValue::symbol(name), vec![value])
+                })
+                .collect();
+            fields.push(preserves::IOValue::record(
+                preserves::IOValue::symbol("metadata"),
+                items,
+            ));
         }
 
         let fact =
@@ -174,51 +475,140 @@ impl WorkspaceCatalog {
         }
     }
 
-    fn grant_read_capability(&self, activation: &mut Activation, facet: FacetId, rel_path: &Path) {
-        let holder_facet = facet.clone();
-        let target_facet = facet.clone();
+    fn grant_capability(
+        &self,
+        activation: &mut Activation,
+        facet: FacetId,
+        rel_path: &Path,
+        kind: &str,
+    ) {
         let spec = CapabilitySpec {
             holder: activation.actor_id.clone(),
-            holder_facet,
+            holder_facet: facet.clone(),
             target: Some(CapabilityTarget {
                 actor: activation.actor_id.clone(),
-                facet: Some(target_facet),
+                facet: Some(facet),
             }),
-            kind: CAP_KIND_READ.into(),
+            kind: kind.into(),
             attenuation: vec![preserves::IOValue::new(self.path_display(rel_path))],
         };
         activation.grant_capability(spec);
     }
 
-    fn grant_write_capability(&self, activation: &mut Activation, facet: FacetId, rel_path: &Path) {
-        let holder_facet = facet.clone();
-        let target_facet = facet.clone();
-        let spec = CapabilitySpec {
-            holder: activation.actor_id.clone(),
-            holder_facet,
-            target: Some(CapabilityTarget {
-                actor: activation.actor_id.clone(),
-                facet: Some(target_facet),
-            }),
-            kind: CAP_KIND_WRITE.into(),
-            attenuation: vec![preserves::IOValue::new(self.path_display(rel_path))],
-        };
-        activation.grant_capability(spec);
+    /// Assert an offer for a capability instead of granting it.
+    fn offer_capability(
+        &self,
+        activation: &mut Activation,
+        facet: FacetId,
+        rel_path: &Path,
+        kind: &str,
+    ) {
+        let id = uuid::Uuid::new_v4();
+        let handle = Handle::new();
+        activation.assert(
+            handle.clone(),
+            preserves::IOValue::record(
+                preserves::IOValue::symbol(OFFER_LABEL),
+                vec![
+                    preserves::IOValue::new(id.to_string()),
+                    preserves::IOValue::new(kind.to_string()),
+                    preserves::IOValue::new(self.path_display(rel_path)),
+                ],
+            ),
+        );
+        self.state.lock().unwrap().offers.insert(
+            id,
+            PendingOffer {
+                handle,
+                facet,
+                kind: kind.to_string(),
+                path: rel_path.to_path_buf(),
+            },
+        );
     }
 
-    fn rescan(&self, activation: &mut Activation) -> ActorResult<()> {
+    /// Withdraw a pending offer, granting its capability when `accept` is set.
+    fn settle_offer(&self, activation: &mut Activation, id: &str, accept: bool) -> ActorResult<()> {
+        let offer = uuid::Uuid::parse_str(id)
+            .ok()
+            .and_then(|id| self.state.lock().unwrap().offers.remove(&id))
+            .ok_or_else(|| ActorError::InvalidActivation(format!("no pending offer {id}")))?;
+        activation.retract(offer.handle);
+        if accept {
+            self.grant_capability(activation, offer.facet, &offer.path, &offer.kind);
+        }
+        Ok(())
+    }
+
+    fn rescan(&self, activation: &mut Activation, mode: Rescan<'_>) -> ActorResult<()> {
+        self.rescan_with_stats(activation, mode).map(|_| ())
+    }
+
+    fn rescan_with_stats(
+        &self,
+        activation: &mut Activation,
+        mode: Rescan<'_>,
+    ) -> ActorResult<ScanStats> {
         let mut catalog = self.state.lock().unwrap();
         let mut previous = std::mem::take(&mut catalog.entries);
+        let previous_dirs = match mode {
+            Rescan::Full => HashMap::new(),
+            _ => std::mem::take(&mut catalog.dirs),
+        };
+        let touched = match mode {
+            Rescan::Touched(path) => Some(path),
+            _ => None,
+        };
         let mut updated = HashMap::new();
+        let mut dirs = HashMap::new();
+        let mut stats = ScanStats::default();
 
         if self.root.exists() {
-            for entry in WalkDir::new(&self.root).into_iter().filter_map(|e| e.ok()) {
-                let abs_path = entry.path();
-                let rel_path = self.relative(abs_path);
-                let desc = self.describe_entry(abs_path);
+            // Paths paired with whether their parent's listing was reused
+            let mut pending = vec![(self.root.clone(), false)];
+            while let Some((abs_path, unchanged_parent)) = pending.pop() {
+                let rel_path = self.relative(&abs_path);
+                // Files of an unchanged directory keep their previous entries
+                if unchanged_parent
+                    && touched != Some(rel_path.as_path())
+                    && previous
+                        .get(&rel_path)
+                        .is_some_and(|prev| prev.data.kind != FileKind::Directory)
+                    && let Some(prev) = previous.remove(&rel_path)
+                {
+                    updated.insert(rel_path, prev);
+                    stats.skipped += 1;
+                    continue;
+                }
+                let mut desc = self.describe_entry(&abs_path);
+                let is_dir = desc.kind == FileKind::Directory;
+                let class = if rel_path.as_os_str().is_empty() {
+                    PathClass::Include
+                } else {
+                    self.policy.classify(&rel_path, is_dir)
+                };
+                if class == PathClass::Ignore {
+                    continue;
+                }
+
+                if is_dir && class == PathClass::Include {
+                    let (children, reused) =
+                        self.list_children(&abs_path, &rel_path, &desc, &previous_dirs, &mut dirs);
+                    if reused {
+                        stats.reused += 1;
+                    } else {
+                        stats.listed += 1;
+                    }
+                    pending.extend(
+                        children
+                            .iter()
+                            .rev()
+                            .map(|child| (abs_path.join(child), reused)),
+                    );
+                }
 
                 if let Some(prev) = previous.remove(&rel_path) {
-                    if prev.data == desc {
+                    if prev.data.same_stat(&desc) {
                         updated.insert(rel_path.clone(), prev);
                         continue;
                     } else {
@@ -226,8 +616,15 @@ impl WorkspaceCatalog {
                     }
                 }
 
+                // Opaque entries only record their existence
+                let metadata = if class == PathClass::Include {
+                    self.policy.inspect(&abs_path, &mut desc)
+                } else {
+                    Vec::new()
+                };
                 let handle = Handle::new();
-                let catalog_entry = self.assert_entry(activation, &rel_path, &desc, handle);
+                let catalog_entry =
+                    self.assert_entry(activation, &rel_path, &desc, metadata, handle);
                 updated.insert(rel_path.clone(), catalog_entry);
             }
         }
@@ -238,7 +635,48 @@ impl WorkspaceCatalog {
         }
 
         catalog.entries = updated;
-        Ok(())
+        catalog.dirs = dirs;
+        Ok(stats)
+    }
+
+    /// List the children of `rel_dir`, reusing the cached listing if its mtime
+    /// and inode are unchanged. The flag reports whether the cache was used.
+    ///
+    /// Fresh listings are recorded in `current` when they are safe to cache.
+    fn list_children(
+        &self,
+        abs_dir: &Path,
+        rel_dir: &Path,
+        desc: &FileEntry,
+        previous: &HashMap<PathBuf, DirListing>,
+        current: &mut HashMap<PathBuf, DirListing>,
+    ) -> (Vec<PathBuf>, bool) {
+        let Some(modified) = desc.modified else {
+            return (read_children(abs_dir), false);
+        };
+
+        let cached = previous
+            .get(rel_dir)
+            .filter(|cached| cached.modified == modified && cached.inode == desc.inode);
+        if let Some(cached) = cached {
+            current.insert(rel_dir.to_path_buf(), cached.clone());
+            return (cached.children.clone(), true);
+        }
+
+        let children = read_children(abs_dir);
+        let settled = Utc::now() - modified > chrono::Duration::seconds(RACY_WINDOW_SECS);
+        // Listings are persisted as strings, so only cache UTF-8 names
+        if settled && children.iter().all(|child| child.to_str().is_some()) {
+            current.insert(
+                rel_dir.to_path_buf(),
+                DirListing {
+                    modified,
+                    inode: desc.inode,
+                    children: children.clone(),
+                },
+            );
+        }
+        (children, false)
     }
 
     fn path_display(&self, rel_path: &Path) -> String {
@@ -294,16 +732,142 @@ impl WorkspaceCatalog {
         let rel_path = self.parse_path(payload, "workspace-read")?;
         self.authorize(capability, &rel_path)?;
 
+        let mode = if payload.len() > 1 {
+            let mode = payload.index(1);
+            let name = mode.as_symbol().ok_or_else(|| {
+                ActorError::InvalidActivation("workspace-read mode must be a symbol".into())
+            })?;
+            Some(
+                name.as_ref()
+                    .parse::<ReadEncoding>()
+                    .map_err(ActorError::InvalidActivation)?,
+            )
+        } else {
+            None
+        };
+
         let abs_path = self.root.join(&rel_path);
-        let contents = fs::read_to_string(&abs_path).map_err(|err| {
+        let bytes = fs::read(&abs_path).map_err(|err| {
             ActorError::InvalidActivation(format!(
                 "failed to read '{}': {}",
                 rel_path.display(),
                 err
             ))
         })?;
+        let size = bytes.len() as i64;
+        let digest = blake3::hash(&bytes).to_hex().to_string();
+
+        let decoded =
+            encoding::decode(bytes, mode.unwrap_or(ReadEncoding::Utf8)).map_err(|err| {
+                ActorError::InvalidActivation(format!(
+                    "failed to read '{}': {}; name a read mode (bytes, lossy, detect) to read it anyway",
+                    rel_path.display(),
+                    err
+                ))
+            })?;
+        let data = match decoded.data {
+            DecodedData::Text(text) => preserves::IOValue::new(text),
+            DecodedData::Bytes(bytes) => preserves::IOValue::new(preserves::Bytes::new(bytes)),
+        };
+
+        // The plain form keeps answering with the bare string
+        let Some(mode) = mode else {
+            return Ok(data);
+        };
+        Ok(preserves::IOValue::record(
+            preserves::IOValue::symbol(encoding::CONTENT_LABEL),
+            vec![
+                preserves::IOValue::new(self.path_display(&rel_path)),
+                preserves::IOValue::symbol(mode.as_str()),
+                preserves::IOValue::new(decoded.encoding.to_string()),
+                data,
+                preserves::IOValue::new(size),
+                preserves::IOValue::new(digest),
+                preserves::IOValue::new(decoded.replacements as i64),
+            ],
+        ))
+    }
+
+    /// Assert the file named by `<workspace-stream-read path chunk-size?>` as
+    /// numbered chunks plus an end marker, returning a summary of the stream.
+    fn handle_stream_read(
+        &self,
+        activation: &mut Activation,
+        capability: &CapabilityMetadata,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<preserves::IOValue> {
+        use std::io::Read;
 
-        Ok(preserves::IOValue::new(contents))
+        let rel_path = self.parse_path(payload, "workspace-stream-read")?;
+        self.authorize(capability, &rel_path)?;
+
+        let chunk_size = if payload.len() > 1 {
+            payload
+                .index(1)
+                .as_signed_integer()
+                .and_then(|size| usize::try_from(size.as_ref()).ok())
+                .filter(|size| (1..=MAX_STREAM_CHUNK_BYTES).contains(size))
+                .ok_or_else(|| {
+                    ActorError::InvalidActivation(format!(
+                        "workspace-stream-read chunk size must be between 1 and {}",
+                        MAX_STREAM_CHUNK_BYTES
+                    ))
+                })?
+        } else {
+            STREAM_CHUNK_BYTES
+        };
+
+        let read_error = |err: std::io::Error| {
+            ActorError::InvalidActivation(format!(
+                "failed to read '{}': {}",
+                rel_path.display(),
+                err
+            ))
+        };
+        let mut file = fs::File::open(self.root.join(&rel_path)).map_err(read_error)?;
+
+        let stream = preserves::IOValue::new(uuid::Uuid::new_v4().to_string());
+        let path = preserves::IOValue::new(self.path_display(&rel_path));
+        let mut chunks = 0i64;
+        let mut total = 0i64;
+        loop {
+            let mut chunk = Vec::with_capacity(chunk_size);
+            (&mut file)
+                .take(chunk_size as u64)
+                .read_to_end(&mut chunk)
+                .map_err(read_error)?;
+            if chunk.is_empty() {
+                break;
+            }
+            total += chunk.len() as i64;
+            activation.assert(
+                Handle::new(),
+                preserves::IOValue::record(
+                    preserves::IOValue::symbol("workspace-chunk"),
+                    vec![
+                        stream.clone(),
+                        path.clone(),
+                        preserves::IOValue::new(chunks),
+                        preserves::IOValue::new(preserves::Bytes::new(chunk)),
+                    ],
+                ),
+            );
+            chunks += 1;
+        }
+
+        let summary = |label: &'static str| {
+            preserves::IOValue::record(
+                preserves::IOValue::symbol(label),
+                vec![
+                    stream.clone(),
+                    path.clone(),
+                    preserves::IOValue::new(chunks),
+                    preserves::IOValue::new(total),
+                ],
+            )
+        };
+        activation.assert(Handle::new(), summary("workspace-stream-end"));
+        Ok(summary("workspace-stream"))
     }
 
     fn handle_write(
@@ -333,6 +897,17 @@ impl WorkspaceCatalog {
         })?;
 
         let abs_path = self.root.join(&rel_path);
+        let previous = match fs::read(&abs_path) {
+            Ok(content) => Some(content),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => {
+                return Err(ActorError::InvalidActivation(format!(
+                    "failed to back up '{}': {}",
+                    rel_path.display(),
+                    err
+                )));
+            }
+        };
         if let Some(parent) = abs_path.parent() {
             fs::create_dir_all(parent).map_err(|err| {
                 ActorError::InvalidActivation(format!(
@@ -351,13 +926,48 @@ impl WorkspaceCatalog {
             ))
         })?;
 
+        activation.record_file_backup(
+            self.path_display(&rel_path),
+            abs_path.to_string_lossy(),
+            previous,
+        );
+        activation.record_side_effect(
+            EffectKind::FileWrite,
+            self.path_display(&rel_path),
+            format!("wrote {} bytes", content.len()),
+        );
+
         // Update catalog assertions deterministically
-        self.rescan(activation)?;
+        self.rescan(activation, Rescan::Touched(&rel_path))?;
 
         Ok(preserves::IOValue::symbol("ok"))
     }
 }
 
+/// Sorted names of the entries directly inside `dir` (empty if unreadable).
+fn read_children(dir: &Path) -> Vec<PathBuf> {
+    let mut children: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| PathBuf::from(entry.file_name()))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    children.sort();
+    children
+}
+
+#[cfg(unix)]
+fn inode_of(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode_of(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
 impl Entity for WorkspaceCatalog {
     fn on_message(
         &self,
@@ -366,36 +976,47 @@ impl Entity for WorkspaceCatalog {
     ) -> ActorResult<()> {
         if let Some(symbol) = payload.as_symbol() {
             if symbol.as_ref() == "workspace-rescan" {
-                self.rescan(activation)?;
+                self.rescan(activation, Rescan::Incremental)?;
             }
             return Ok(());
         }
 
-        if record_with_label(payload, "workspace-rescan").is_some() {
-            self.rescan(activation)?;
+        if let Some(record) = record_with_label(payload, "workspace-rescan") {
+            let full = record.field_symbol(0).as_deref() == Some("full");
+            let mode = if full {
+                Rescan::Full
+            } else {
+                Rescan::Incremental
+            };
+            self.rescan(activation, mode)?;
             return Ok(());
         }
 
-        if let Some(record) = record_with_label(payload, "workspace-read") {
-            if let Some(path) = record.field_string(0) {
-                self.grant_read_capability(
-                    activation,
-                    activation.current_facet.clone(),
-                    Path::new(&path),
-                );
+        for (label, accept) in [("capability-accept", true), ("capability-decline", false)] {
+            if let Some(record) = record_with_label(payload, label) {
+                let id = record.field_string(0).ok_or_else(|| {
+                    ActorError::InvalidActivation(format!("{label} requires an offer id"))
+                })?;
+                return self.settle_offer(activation, &id, accept);
             }
-            return Ok(());
         }
 
-        if let Some(record) = record_with_label(payload, "workspace-write") {
-            if let Some(path) = record.field_string(0) {
-                self.grant_write_capability(
-                    activation,
-                    activation.current_facet.clone(),
-                    Path::new(&path),
-                );
+        for (label, kind) in [
+            ("workspace-read", CAP_KIND_READ),
+            ("workspace-write", CAP_KIND_WRITE),
+            ("workspace-stream-read", CAP_KIND_STREAM_READ),
+        ] {
+            if let Some(record) = record_with_label(payload, label) {
+                if let Some(path) = record.field_string(0) {
+                    let facet = activation.current_facet.clone();
+                    if self.policy.offers {
+                        self.offer_capability(activation, facet, Path::new(&path), kind);
+                    } else {
+                        self.grant_capability(activation, facet, Path::new(&path), kind);
+                    }
+                }
+                return Ok(());
             }
-            return Ok(());
         }
 
         Ok(())
@@ -410,19 +1031,236 @@ impl Entity for WorkspaceCatalog {
         match capability.kind.as_str() {
             CAP_KIND_READ => self.handle_read(capability, payload),
             CAP_KIND_WRITE => self.handle_write(activation, capability, payload),
+            CAP_KIND_STREAM_READ => self.handle_stream_read(activation, capability, payload),
             other => Err(ActorError::InvalidActivation(format!(
                 "unsupported capability kind: {}",
                 other
             ))),
         }
     }
+
+    fn describe(&self) -> EntityDescriptor {
+        EntityDescriptor {
+            message_labels: [
+                "workspace-rescan",
+                "workspace-read",
+                "workspace-write",
+                "workspace-stream-read",
+                "capability-accept",
+                "capability-decline",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+            capability_kinds: [CAP_KIND_READ, CAP_KIND_WRITE, CAP_KIND_STREAM_READ]
+                .map(str::to_string)
+                .to_vec(),
+            config_schema: Some(preserves::IOValue::record(
+                preserves::IOValue::symbol("workspace-config"),
+                vec![
+                    preserves::IOValue::symbol("root"),
+                    preserves::IOValue::symbol("option..."),
+                ],
+            )),
+        }
+    }
+}
+
+impl HydratableEntity for WorkspaceCatalog {
+    fn snapshot_state(&self) -> preserves::IOValue {
+        let catalog = self.state.lock().unwrap();
+
+        let mut entries: Vec<_> = catalog.entries.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        let entries: Vec<preserves::IOValue> = entries
+            .into_iter()
+            .map(|(path, entry)| {
+                preserves::IOValue::record(
+                    preserves::IOValue::symbol("entry"),
+                    vec![
+                        preserves::IOValue::new(path.to_string_lossy().to_string()),
+                        preserves::IOValue::new(entry.handle.0.to_string()),
+                        preserves::IOValue::symbol(entry.data.kind.as_symbol()),
+                        preserves::IOValue::new(entry.data.size as i64),
+                        optional_timestamp(entry.data.modified),
+                        preserves::IOValue::new(entry.data.inode.to_string()),
+                        entry
+                            .data
+                            .digest
+                            .clone()
+                            .map(preserves::IOValue::new)
+                            .unwrap_or_else(|| preserves::IOValue::symbol("none")),
+                    ],
+                )
+            })
+            .collect();
+
+        let mut dirs: Vec<_> = catalog.dirs.iter().collect();
+        dirs.sort_by(|a, b| a.0.cmp(b.0));
+        let dirs: Vec<preserves::IOValue> = dirs
+            .into_iter()
+            .map(|(path, listing)| {
+                preserves::IOValue::record(
+                    preserves::IOValue::symbol("dir"),
+                    vec![
+                        preserves::IOValue::new(path.to_string_lossy().to_string()),
+                        optional_timestamp(Some(listing.modified)),
+                        preserves::IOValue::new(listing.inode.to_string()),
+                        preserves::IOValue::new(
+                            listing
+                                .children
+                                .iter()
+                                .map(|child| {
+                                    preserves::IOValue::new(child.to_string_lossy().to_string())
+                                })
+                                .collect::<Vec<_>>(),
+                        ),
+                    ],
+                )
+            })
+            .collect();
+
+        let offers: Vec<preserves::IOValue> = catalog
+            .offers
+            .iter()
+            .map(|(id, offer)| {
+                preserves::IOValue::record(
+                    preserves::IOValue::symbol("offer"),
+                    vec![
+                        preserves::IOValue::new(id.to_string()),
+                        preserves::IOValue::new(offer.handle.0.to_string()),
+                        preserves::IOValue::new(offer.facet.0.to_string()),
+                        preserves::IOValue::new(offer.kind.clone()),
+                        preserves::IOValue::new(offer.path.to_string_lossy().to_string()),
+                    ],
+                )
+            })
+            .collect();
+
+        preserves::IOValue::record(
+            preserves::IOValue::symbol("workspace-state"),
+            vec![
+                preserves::IOValue::new(entries),
+                preserves::IOValue::new(dirs),
+                preserves::IOValue::new(offers),
+            ],
+        )
+    }
+
+    fn restore_state(&mut self, state: &preserves::IOValue) -> ActorResult<()> {
+        let invalid = || ActorError::InvalidActivation("malformed workspace state".into());
+        let record = record_with_label(state, "workspace-state").ok_or_else(invalid)?;
+        if record.len() < 2 {
+            return Err(invalid());
+        }
+
+        let mut restored = CatalogState::default();
+
+        let entries = record.field(0);
+        for index in 0..entries.len() {
+            let value = preserves::IOValue::from(entries.index(index));
+            let entry = record_with_label(&value, "entry")
+                .filter(|entry| entry.len() >= 7)
+                .ok_or_else(invalid)?;
+            let path = entry.field_string(0).ok_or_else(invalid)?;
+            let handle = entry
+                .field_string(1)
+                .and_then(|id| uuid::Uuid::parse_str(&id).ok())
+                .ok_or_else(invalid)?;
+            let size = entry
+                .field(3)
+                .as_signed_integer()
+                .and_then(|n| u64::try_from(n.as_ref()).ok())
+                .unwrap_or(0);
+            restored.entries.insert(
+                PathBuf::from(path),
+                CatalogEntry {
+                    handle: Handle(handle),
+                    data: FileEntry {
+                        kind: FileKind::from_symbol(&entry.field_symbol(2).unwrap_or_default()),
+                        size,
+                        modified: entry.field_timestamp(4),
+                        inode: parse_inode(entry.field_string(5)),
+                        digest: entry.field_string(6),
+                    },
+                },
+            );
+        }
+
+        let dirs = record.field(1);
+        for index in 0..dirs.len() {
+            let value = preserves::IOValue::from(dirs.index(index));
+            let dir = record_with_label(&value, "dir")
+                .filter(|dir| dir.len() >= 4)
+                .ok_or_else(invalid)?;
+            let path = dir.field_string(0).ok_or_else(invalid)?;
+            let Some(modified) = dir.field_timestamp(1) else {
+                continue;
+            };
+            let children_value = dir.field(3);
+            let children = (0..children_value.len())
+                .filter_map(|i| {
+                    preserves::IOValue::from(children_value.index(i))
+                        .as_string()
+                        .map(|name| PathBuf::from(name.as_ref()))
+                })
+                .collect();
+            restored.dirs.insert(
+                PathBuf::from(path),
+                DirListing {
+                    modified,
+                    inode: parse_inode(dir.field_string(2)),
+                    children,
+                },
+            );
+        }
+
+        // States snapshotted before offers existed have no third field
+        if record.len() >= 3 {
+            let offers = record.field(2);
+            for index in 0..offers.len() {
+                let value = preserves::IOValue::from(offers.index(index));
+                let offer = record_with_label(&value, "offer")
+                    .filter(|offer| offer.len() >= 5)
+                    .ok_or_else(invalid)?;
+                let uuid_field = |index: usize| {
+                    offer
+                        .field_string(index)
+                        .and_then(|id| uuid::Uuid::parse_str(&id).ok())
+                        .ok_or_else(invalid)
+                };
+                restored.offers.insert(
+                    uuid_field(0)?,
+                    PendingOffer {
+                        handle: Handle(uuid_field(1)?),
+                        facet: FacetId(uuid_field(2)?),
+                        kind: offer.field_string(3).ok_or_else(invalid)?,
+                        path: PathBuf::from(offer.field_string(4).ok_or_else(invalid)?),
+                    },
+                );
+            }
+        }
+
+        *self.state.lock().unwrap() = restored;
+        Ok(())
+    }
+}
+
+fn optional_timestamp(timestamp: Option<DateTime<Utc>>) -> preserves::IOValue {
+    match timestamp {
+        Some(timestamp) => timestamp_value(&timestamp),
+        None => preserves::IOValue::symbol("unknown"),
+    }
+}
+
+fn parse_inode(text: Option<String>) -> u64 {
+    text.and_then(|text| text.parse().ok()).unwrap_or(0)
 }
 
 /// Register the workspace catalog entity with the global registry.
 pub fn register(catalog: &EntityCatalog) {
-    catalog.register("workspace", |config| {
-        let cfg = WorkspaceConfig::from_value(config);
-        Ok(Box::new(WorkspaceCatalog::new(&cfg)))
+    catalog.register_hydratable("workspace", |config| {
+        let cfg = WorkspaceConfig::from_value(config)?;
+        WorkspaceCatalog::new(&cfg)
     });
 }
 
@@ -439,16 +1277,16 @@ mod tests {
         let file_path = temp.path().join("hello.txt");
         fs::write(&file_path, b"hello world").unwrap();
 
-        let config = WorkspaceConfig {
-            root: temp.path().to_path_buf(),
-        };
-        let catalog = WorkspaceCatalog::new(&config);
+        let config = WorkspaceConfig::normalize(temp.path().to_path_buf());
+        let catalog = WorkspaceCatalog::new(&config).unwrap();
 
         let actor = Actor::new(ActorId::new());
         let mut activation = Activation::new(actor.id.clone(), actor.root_facet.clone(), None);
         activation.set_current_entity(Some(uuid::Uuid::new_v4()));
 
-        catalog.rescan(&mut activation).unwrap();
+        catalog
+            .rescan(&mut activation, Rescan::Incremental)
+            .unwrap();
 
         assert!(
             activation
@@ -459,13 +1297,281 @@ mod tests {
         );
     }
 
+    fn age_dir(path: &Path) {
+        let past = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        fs::File::open(path).unwrap().set_modified(past).unwrap();
+    }
+
     #[test]
-    fn command_grants_capabilities() {
+    fn rescan_reuses_unchanged_directory_listings() {
         let temp = tempdir().unwrap();
-        let config = WorkspaceConfig {
-            root: temp.path().to_path_buf(),
+        fs::create_dir_all(temp.path().join("src/nested")).unwrap();
+        fs::write(temp.path().join("src/nested/a.rs"), b"a").unwrap();
+        fs::write(temp.path().join("src/b.rs"), b"b").unwrap();
+        for dir in ["src/nested", "src", ""] {
+            age_dir(&temp.path().join(dir));
+        }
+
+        let catalog =
+            WorkspaceCatalog::new(&WorkspaceConfig::normalize(temp.path().to_path_buf())).unwrap();
+        let actor = Actor::new(ActorId::new());
+        let mut activation = Activation::new(actor.id.clone(), actor.root_facet.clone(), None);
+
+        let first = catalog
+            .rescan_with_stats(&mut activation, Rescan::Incremental)
+            .unwrap();
+        assert_eq!(
+            first,
+            ScanStats {
+                listed: 3,
+                reused: 0,
+                skipped: 0,
+            }
+        );
+        let asserted = activation.outputs.len();
+
+        // Unchanged directories are walked without stat'ing their files
+        let second = catalog
+            .rescan_with_stats(&mut activation, Rescan::Incremental)
+            .unwrap();
+        assert_eq!(
+            second,
+            ScanStats {
+                listed: 0,
+                reused: 3,
+                skipped: 2,
+            }
+        );
+        assert_eq!(activation.outputs.len(), asserted, "nothing changed");
+
+        // An in-place edit leaves the directory's mtime alone...
+        fs::write(temp.path().join("src/nested/a.rs"), b"grown").unwrap();
+        // ...while a new file invalidates only its parent's listing
+        fs::write(temp.path().join("src/c.rs"), b"c").unwrap();
+        let third = catalog
+            .rescan_with_stats(&mut activation, Rescan::Incremental)
+            .unwrap();
+        assert_eq!(
+            third,
+            ScanStats {
+                listed: 1,
+                reused: 2,
+                skipped: 1,
+            }
+        );
+        let size_of = |path: &str| {
+            catalog.state.lock().unwrap().entries[Path::new(path)]
+                .data
+                .size
+        };
+        assert_eq!(size_of("src/nested/a.rs"), 1);
+
+        // Touching the path, or a full rescan, picks the edit up
+        catalog
+            .rescan_with_stats(
+                &mut activation,
+                Rescan::Touched(Path::new("src/nested/a.rs")),
+            )
+            .unwrap();
+        assert_eq!(size_of("src/nested/a.rs"), 5);
+        fs::write(temp.path().join("src/b.rs"), b"edited").unwrap();
+        let full = catalog
+            .rescan_with_stats(&mut activation, Rescan::Full)
+            .unwrap();
+        assert_eq!(full.reused, 0);
+        assert_eq!(size_of("src/b.rs"), 6);
+
+        let paths: Vec<_> = catalog
+            .state
+            .lock()
+            .unwrap()
+            .entries
+            .keys()
+            .cloned()
+            .collect();
+        assert!(paths.contains(&PathBuf::from("src/c.rs")));
+    }
+
+    #[test]
+    fn catalog_state_survives_hydration() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join("a.txt"), b"a").unwrap();
+        age_dir(temp.path());
+
+        let config = WorkspaceConfig::normalize(temp.path().to_path_buf());
+        let catalog = WorkspaceCatalog::new(&config).unwrap();
+        let actor = Actor::new(ActorId::new());
+        let mut activation = Activation::new(actor.id.clone(), actor.root_facet.clone(), None);
+        catalog
+            .rescan(&mut activation, Rescan::Incremental)
+            .unwrap();
+
+        let mut restored = WorkspaceCatalog::new(&config).unwrap();
+        restored.restore_state(&catalog.snapshot_state()).unwrap();
+        assert_eq!(restored.snapshot_state(), catalog.snapshot_state());
+
+        let mut activation = Activation::new(actor.id.clone(), actor.root_facet.clone(), None);
+        let stats = restored
+            .rescan_with_stats(&mut activation, Rescan::Incremental)
+            .unwrap();
+        assert_eq!(
+            stats,
+            ScanStats {
+                listed: 0,
+                reused: 1,
+                skipped: 1,
+            }
+        );
+        assert!(
+            activation.outputs.is_empty(),
+            "restored entries are not re-asserted"
+        );
+    }
+
+    #[test]
+    fn pending_offers_survive_hydration() {
+        let temp = tempdir().unwrap();
+        let mut config = WorkspaceConfig::normalize(temp.path().to_path_buf());
+        config.offers = true;
+        let catalog = WorkspaceCatalog::new(&config).unwrap();
+        let actor = Actor::new(ActorId::new());
+        let mut activation = Activation::new(actor.id.clone(), actor.root_facet.clone(), None);
+
+        let payload = preserves::IOValue::record(
+            preserves::IOValue::symbol("workspace-write"),
+            vec![preserves::IOValue::new("out.txt".to_string())],
+        );
+        Entity::on_message(&catalog, &mut activation, &payload).unwrap();
+        assert!(
+            !activation
+                .outputs
+                .iter()
+                .any(|output| matches!(output, TurnOutput::CapabilityGranted { .. }))
+        );
+        let offer_id = activation
+            .outputs
+            .iter()
+            .find_map(|output| match output {
+                TurnOutput::Assert { value, .. } => {
+                    record_with_label(value, OFFER_LABEL).and_then(|record| record.field_string(0))
+                }
+                _ => None,
+            })
+            .expect("offer asserted");
+
+        let mut restored = WorkspaceCatalog::new(&config).unwrap();
+        restored.restore_state(&catalog.snapshot_state()).unwrap();
+        assert_eq!(restored.snapshot_state(), catalog.snapshot_state());
+
+        let accept = preserves::IOValue::record(
+            preserves::IOValue::symbol("capability-accept"),
+            vec![preserves::IOValue::new(offer_id)],
+        );
+        let mut activation = Activation::new(actor.id.clone(), actor.root_facet.clone(), None);
+        activation.set_current_entity(Some(uuid::Uuid::new_v4()));
+        Entity::on_message(&restored, &mut activation, &accept).unwrap();
+        assert!(activation
+            .outputs
+            .iter()
+            .any(|output| matches!(output, TurnOutput::CapabilityGranted { kind, .. } if kind == "workspace/write")));
+        assert!(Entity::on_message(&restored, &mut activation, &accept).is_err());
+    }
+
+    fn asserted_entries(activation: &Activation) -> HashMap<String, preserves::IOValue> {
+        activation
+            .outputs
+            .iter()
+            .filter_map(|output| match output {
+                TurnOutput::Assert { value, .. } => record_with_label(value, "workspace-entry")
+                    .and_then(|record| record.field_string(0))
+                    .map(|path| (path, value.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn policy_shapes_published_entries() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("target/debug")).unwrap();
+        fs::write(temp.path().join("target/debug/app"), b"binary").unwrap();
+        fs::create_dir_all(temp.path().join(".git")).unwrap();
+        fs::write(temp.path().join(".git/HEAD"), b"ref").unwrap();
+        fs::write(temp.path().join("lib.rs"), b"fn a() {}\nfn b() {}\n").unwrap();
+
+        hooks::register_metadata_extractor("test-line-count", |path| {
+            fs::read_to_string(path)
+                .ok()
+                .map(|text| preserves::IOValue::new(text.lines().count() as i64))
+        });
+        hooks::register_path_classifier("test-skip-lock", |path, _is_dir| {
+            (path.extension().and_then(|ext| ext.to_str()) == Some("lock"))
+                .then_some(PathClass::Ignore)
+        });
+        fs::write(temp.path().join("Cargo.lock"), b"lock").unwrap();
+
+        let strings = |values: &[&str]| -> Vec<preserves::IOValue> {
+            values
+                .iter()
+                .map(|value| preserves::IOValue::new(value.to_string()))
+                .collect()
+        };
+        let option = |name: &'static str, values: &[&str]| {
+            preserves::IOValue::record(preserves::IOValue::symbol(name), strings(values))
         };
-        let catalog = WorkspaceCatalog::new(&config);
+        let config_value = preserves::IOValue::record(
+            preserves::IOValue::symbol("workspace-config"),
+            vec![
+                preserves::IOValue::new(temp.path().to_string_lossy().to_string()),
+                option("opaque", &["target"]),
+                option("ignore", &[".git"]),
+                option("classify", &["test-skip-lock"]),
+                option("extract", &["test-line-count"]),
+                option("digest", &[]),
+            ],
+        );
+        let config = WorkspaceConfig::from_value(&config_value).unwrap();
+        let catalog = WorkspaceCatalog::new(&config).unwrap();
+
+        let actor = Actor::new(ActorId::new());
+        let mut activation = Activation::new(actor.id.clone(), actor.root_facet.clone(), None);
+        catalog
+            .rescan(&mut activation, Rescan::Incremental)
+            .unwrap();
+        let entries = asserted_entries(&activation);
+
+        assert!(entries.contains_key("target"), "opaque dirs are recorded");
+        assert!(!entries.contains_key("target/debug"), "but not walked");
+        assert!(!entries.keys().any(|path| path.starts_with(".git")));
+        assert!(!entries.contains_key("Cargo.lock"));
+        assert_eq!(entries["target"].len(), 4, "opaque entries carry no extras");
+
+        let lib = &entries["lib.rs"];
+        let expected_digest = blake3::hash(b"fn a() {}\nfn b() {}\n").to_hex().to_string();
+        let record = record_with_label(lib, "workspace-entry").unwrap();
+        assert_eq!(record.field_string(4), Some(expected_digest));
+        let metadata = record.field(5);
+        let metadata = record_with_label(&metadata, "metadata").unwrap();
+        let line_count = metadata.field(0);
+        let line_count = record_with_label(&line_count, "test-line-count").unwrap();
+        assert_eq!(line_count.field(0), preserves::IOValue::new(2i64));
+
+        let unknown = preserves::IOValue::record(
+            preserves::IOValue::symbol("workspace-config"),
+            vec![
+                preserves::IOValue::new(".".to_string()),
+                option("extract", &["no-such-extractor"]),
+            ],
+        );
+        let config = WorkspaceConfig::from_value(&unknown).unwrap();
+        assert!(WorkspaceCatalog::new(&config).is_err());
+    }
+
+    #[test]
+    fn command_grants_capabilities() {
+        let temp = tempdir().unwrap();
+        let config = WorkspaceConfig::normalize(temp.path().to_path_buf());
+        let catalog = WorkspaceCatalog::new(&config).unwrap();
 
         let actor = Actor::new(ActorId::new());
         let facet = actor.root_facet.clone();
@@ -14,15 +14,23 @@ use std::sync::mpsc::Sender;
 use uuid::{Uuid, uuid};
 
 use super::AsyncMessage;
+use super::effects::EffectKind;
 use super::error::{ActorError, ActorResult};
-use super::pattern::{Pattern, PatternEngine, PatternId, PatternMatch};
-use super::reaction::{ReactionDefinition, ReactionEffect, ReactionId, ReactionStats};
+use super::limits::{LimitTracker, LimitsConfig, OutputMark};
+use super::pattern::{Pattern, PatternEngine, PatternId, PatternMatch, PatternScope};
+use super::reaction::{
+    ReactionCapability, ReactionDefinition, ReactionEffect, ReactionId, ReactionStats,
+};
+use super::registry::EntityDescriptor;
+use super::rng::DeterministicRng;
+use super::secrets::SecretsProvider;
 use super::state::{
     AccountDelta, AssertionDelta, AssertionSet, CapId, CapabilityDelta, CapabilityMap,
-    CapabilityMetadata, CapabilityStatus, CapabilityTarget, FacetDelta, FacetMap, FacetMetadata,
-    FacetStatus, PNCounter, StateDelta,
+    CapabilityMetadata, CapabilityStatus, CapabilityTarget, DEFAULT_NAMESPACE, FacetDelta,
+    FacetMap, FacetMetadata, FacetStatus, PNCounter, StateDelta, assertion_version,
 };
-use super::turn::{ActorId, CapabilityCompletion, FacetId, Handle, TurnInput, TurnOutput};
+use super::task::{TaskContext, TaskId, TaskManager};
+use super::turn::{ActorId, CapabilityCompletion, FacetId, Handle, TurnId, TurnInput, TurnOutput};
 /// An actor: isolated unit of computation with its own state
 pub struct Actor {
     /// Unique actor ID
@@ -61,6 +69,8 @@ struct ReactionEntry {
     reaction_id: ReactionId,
     effect: ReactionEffect,
     default_facet: FacetId,
+    priority: i32,
+    consume: bool,
     stats: ReactionStats,
 }
 
@@ -109,12 +119,38 @@ impl Actor {
         activation: &mut Activation,
         handle: &Handle,
         value: &preserves::IOValue,
+        namespace: Option<&str>,
     ) -> ActorResult<()> {
         // Evaluate pattern engine
+        let mut engine = self.pattern_engine.write();
+        let pattern_matches = engine.eval_assert_in(handle, value, namespace);
+        drop(engine);
+
+        self.dispatch_pattern_matches(activation, pattern_matches)
+    }
+
+    /// Run the patterns and reactions matching a scratch note.
+    ///
+    /// Unlike [`Actor::notify_assert`] no `PatternMatched` outputs are
+    /// recorded, so the note leaves no trace in the journal.
+    fn notify_note(
+        &self,
+        activation: &mut Activation,
+        handle: &Handle,
+        value: &preserves::IOValue,
+    ) -> ActorResult<()> {
         let mut engine = self.pattern_engine.write();
         let pattern_matches = engine.eval_assert(handle, value);
         drop(engine);
 
+        self.dispatch_to_observers(activation, &pattern_matches)
+    }
+
+    fn dispatch_pattern_matches(
+        &self,
+        activation: &mut Activation,
+        pattern_matches: Vec<PatternMatch>,
+    ) -> ActorResult<()> {
         // Emit PatternMatched outputs
         for pattern_match in &pattern_matches {
             activation.outputs.push(TurnOutput::PatternMatched {
@@ -123,6 +159,16 @@ impl Actor {
             });
         }
 
+        self.dispatch_to_observers(activation, &pattern_matches)
+    }
+
+    /// Deliver `pattern_matches` to the observing entities' `on_assert`
+    /// callbacks, then fire the reactions subscribed to them.
+    fn dispatch_to_observers(
+        &self,
+        activation: &mut Activation,
+        pattern_matches: &[PatternMatch],
+    ) -> ActorResult<()> {
         // Dispatch on_assert callbacks
         let entities = self.entities.read();
         for pattern_match in pattern_matches {
@@ -134,11 +180,13 @@ impl Actor {
                     let result: ActorResult<()> = (|| {
                         for entry in entity_list {
                             activation.set_current_entity(Some(entry.id));
+                            let mark = LimitTracker::mark(&activation.outputs);
                             entry.entity.on_assert(
                                 activation,
                                 &pattern_match.handle,
                                 &pattern_match.value,
                             )?;
+                            activation.check_limits(entry.id, &entry.entity_type, mark)?;
                         }
                         Ok(())
                     })();
@@ -147,9 +195,52 @@ impl Actor {
                     result?;
                 }
             }
+        }
+        drop(entities);
+
+        self.trigger_reactions(activation, pattern_matches);
+
+        Ok(())
+    }
 
-            self.trigger_reactions(activation, &pattern_match)?;
+    fn notify_retract(&self, activation: &mut Activation, handle: &Handle) -> ActorResult<()> {
+        // Evaluate pattern engine
+        let mut engine = self.pattern_engine.write();
+        let affected_patterns = engine.eval_retract(handle);
+        drop(engine);
+
+        // Generate PatternUnmatched outputs
+        for pattern_id in &affected_patterns {
+            activation.outputs.push(TurnOutput::PatternUnmatched {
+                pattern_id: *pattern_id,
+                handle: handle.clone(),
+            });
+        }
+
+        // Call entity on_retract callbacks for affected patterns
+        let entities = self.entities.read();
+        for pattern_id in affected_patterns {
+            let engine = self.pattern_engine.read();
+            if let Some(pattern) = engine.patterns.get(&pattern_id) {
+                if let Some(entity_list) = entities.get(&pattern.facet) {
+                    let prev_facet =
+                        std::mem::replace(&mut activation.current_facet, pattern.facet.clone());
+                    let result: ActorResult<()> = (|| {
+                        for entry in entity_list {
+                            activation.set_current_entity(Some(entry.id));
+                            let mark = LimitTracker::mark(&activation.outputs);
+                            entry.entity.on_retract(activation, handle)?;
+                            activation.check_limits(entry.id, &entry.entity_type, mark)?;
+                        }
+                        Ok(())
+                    })();
+                    activation.set_current_entity(None);
+                    activation.current_facet = prev_facet;
+                    result?;
+                }
+            }
         }
+        drop(entities);
 
         Ok(())
     }
@@ -165,7 +256,8 @@ impl Actor {
             }
 
             let pending = activation.drain_pending_asserts();
-            if pending.is_empty() {
+            let notes = std::mem::take(&mut activation.pending_notes);
+            if pending.is_empty() && notes.is_empty() {
                 if !had_patterns {
                     break;
                 } else {
@@ -173,8 +265,11 @@ impl Actor {
                 }
             }
 
-            for (handle, value) in pending {
-                self.notify_assert(activation, &handle, &value)?;
+            for (handle, value, namespace) in pending {
+                self.notify_assert(activation, &handle, &value, namespace.as_deref())?;
+            }
+            for (handle, value) in notes {
+                self.notify_note(activation, &handle, &value)?;
             }
         }
 
@@ -244,11 +339,37 @@ impl Actor {
         }
     }
 
-    /// Execute a turn with the given inputs
+    /// Execute a turn with the given inputs under the default [`LimitsConfig`]
     pub fn execute_turn(
         &self,
         inputs: Vec<TurnInput>,
         async_sender: Option<&Sender<AsyncMessage>>,
+    ) -> ActorResult<(Vec<TurnOutput>, StateDelta)> {
+        self.execute_turn_with_limits(inputs, async_sender, &LimitsConfig::default())
+    }
+
+    /// Execute a turn, failing it if any entity exceeds its [`TurnLimits`](super::limits::TurnLimits)
+    pub fn execute_turn_with_limits(
+        &self,
+        inputs: Vec<TurnInput>,
+        async_sender: Option<&Sender<AsyncMessage>>,
+        limits: &LimitsConfig,
+    ) -> ActorResult<(Vec<TurnOutput>, StateDelta)> {
+        self.execute_turn_with_secrets(inputs, async_sender, limits, Arc::default(), None, 0)
+    }
+
+    /// Execute a turn under `limits`, exposing `secrets` through [`Activation::secret`]
+    /// and hosting tasks spawned with [`Activation::spawn_task`] in `tasks`
+    ///
+    /// `sequence` is the turn's position on its branch; it seeds [`Activation::rng`].
+    pub fn execute_turn_with_secrets(
+        &self,
+        inputs: Vec<TurnInput>,
+        async_sender: Option<&Sender<AsyncMessage>>,
+        limits: &LimitsConfig,
+        secrets: Arc<SecretsProvider>,
+        tasks: Option<&TaskManager>,
+        sequence: u64,
     ) -> ActorResult<(Vec<TurnOutput>, StateDelta)> {
         // Create activation context
         let mut activation = Activation::new(
@@ -256,10 +377,49 @@ impl Actor {
             self.root_facet.clone(),
             async_sender.cloned(),
         );
+        activation.limits = LimitTracker::new(limits.clone());
+        activation.secrets = secrets;
+        activation.tasks = tasks.cloned();
+        activation.rng = DeterministicRng::for_turn(&self.id, sequence);
 
         // Process each input
-        for input in inputs {
-            self.process_input(&mut activation, input)?;
+        let processed = inputs
+            .into_iter()
+            .try_for_each(|input| self.process_input(&mut activation, input));
+
+        // Scratch notes end with the turn, whether or not it succeeded
+        {
+            let mut engine = self.pattern_engine.write();
+            for (handle, _) in &activation.notes {
+                engine.eval_retract(handle);
+            }
+        }
+        processed?;
+
+        // Tag retractions of assertions published in earlier turns with their namespace
+        {
+            let assertions = self.assertions.read();
+            for output in activation.outputs.iter_mut() {
+                if let TurnOutput::Retract { handle, namespace } = output {
+                    *namespace = namespace.take().or_else(|| {
+                        assertions
+                            .namespace_of(&self.id, handle)
+                            .map(str::to_string)
+                    });
+                }
+            }
+        }
+
+        if activation.rng.draws() > 0 {
+            activation.outputs.push(TurnOutput::RandomDrawn {
+                seed: activation
+                    .rng
+                    .seed()
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect(),
+                draws: activation.rng.draws(),
+            });
         }
 
         // Collect outputs and delta
@@ -281,7 +441,9 @@ impl Actor {
                     let result: ActorResult<()> = (|| {
                         for entry in entity_list {
                             activation.set_current_entity(Some(entry.id));
+                            let mark = LimitTracker::mark(&activation.outputs);
                             entry.entity.on_message(activation, &payload)?;
+                            activation.check_limits(entry.id, &entry.entity_type, mark)?;
                         }
                         Ok(())
                     })();
@@ -291,64 +453,103 @@ impl Actor {
                 }
             }
 
-            TurnInput::Assert { handle, value, .. } => {
-                self.notify_assert(activation, &handle, &value)?;
-                activation.assert(handle.clone(), value.clone());
+            TurnInput::Assert {
+                handle,
+                value,
+                namespace,
+                ..
+            } => {
+                self.notify_assert(activation, &handle, &value, namespace.as_deref())?;
+                match namespace {
+                    Some(namespace) => activation.assert_in(namespace, handle, value),
+                    None => activation.assert(handle, value),
+                }
                 activation.pop_last_pending_assert();
             }
 
             TurnInput::Retract { handle, .. } => {
-                // Evaluate pattern engine
+                self.notify_retract(activation, &handle)?;
+
+                // Record the retraction
+                activation.retract(handle);
+            }
+
+            TurnInput::ObservedAssert {
+                handle,
+                value,
+                namespace,
+                ..
+            } => {
                 let mut engine = self.pattern_engine.write();
-                let affected_patterns = engine.eval_retract(&handle);
+                let pattern_matches =
+                    engine.eval_observed_assert(&handle, &value, namespace.as_deref());
                 drop(engine);
 
-                // Generate PatternUnmatched outputs
-                for pattern_id in &affected_patterns {
-                    activation.outputs.push(TurnOutput::PatternUnmatched {
-                        pattern_id: *pattern_id,
-                        handle: handle.clone(),
-                    });
-                }
+                self.dispatch_pattern_matches(activation, pattern_matches)?;
+            }
 
-                // Call entity on_retract callbacks for affected patterns
-                let entities = self.entities.read();
-                for pattern_id in affected_patterns {
-                    let engine = self.pattern_engine.read();
-                    if let Some(pattern) = engine.patterns.get(&pattern_id) {
-                        if let Some(entity_list) = entities.get(&pattern.facet) {
-                            let prev_facet = std::mem::replace(
-                                &mut activation.current_facet,
-                                pattern.facet.clone(),
-                            );
-                            let result: ActorResult<()> = (|| {
-                                for entry in entity_list {
-                                    activation.set_current_entity(Some(entry.id));
-                                    entry.entity.on_retract(activation, &handle)?;
-                                }
-                                Ok(())
-                            })();
-                            activation.set_current_entity(None);
-                            activation.current_facet = prev_facet;
-                            result?;
-                        }
-                    }
+            TurnInput::ObservedRetract { handle, .. } => {
+                self.notify_retract(activation, &handle)?;
+            }
+
+            TurnInput::RetractMatching { handles, .. } => {
+                for handle in handles {
+                    self.notify_retract(activation, &handle)?;
+                    activation.retract(handle);
                 }
-                drop(entities);
+            }
 
-                // Record the retraction
-                activation.retract(handle);
+            TurnInput::SetFlag {
+                flag,
+                enabled,
+                handle,
+                retracted,
+            } => {
+                for old in retracted {
+                    self.notify_retract(activation, &old)?;
+                    activation.retract(old);
+                }
+                let value = flag.assertion(enabled);
+                self.notify_assert(activation, &handle, &value, None)?;
+                activation.assert(handle, value);
+                activation.pop_last_pending_assert();
             }
 
             TurnInput::Sync { facet, .. } => {
                 activation.outputs.push(TurnOutput::Synced { facet });
             }
 
+            TurnInput::Timer {
+                timer_id,
+                facet,
+                payload,
+                ..
+            } => {
+                let entities = self.entities.read();
+                if let Some(entity_list) = entities.get(&facet) {
+                    let prev_facet =
+                        std::mem::replace(&mut activation.current_facet, facet.clone());
+                    let result: ActorResult<()> = (|| {
+                        for entry in entity_list {
+                            activation.set_current_entity(Some(entry.id));
+                            let mark = LimitTracker::mark(&activation.outputs);
+                            entry.entity.on_timer(activation, timer_id, &payload)?;
+                            activation.check_limits(entry.id, &entry.entity_type, mark)?;
+                        }
+                        Ok(())
+                    })();
+                    activation.set_current_entity(None);
+                    activation.current_facet = prev_facet;
+                    result?;
+                }
+            }
+
             TurnInput::CapabilityInvocation {
                 capability,
                 payload,
+                invocation,
             } => {
-                self.handle_capability_invocation(activation, capability, payload)?;
+                self.handle_capability_invocation(activation, capability, payload, invocation)?;
             }
 
             _ => {
@@ -364,6 +565,7 @@ impl Actor {
         activation: &mut Activation,
         capability_id: CapId,
         payload: preserves::IOValue,
+        invocation: Option<Uuid>,
     ) -> ActorResult<()> {
         let metadata = {
             let capabilities = self.capabilities.read();
@@ -406,20 +608,46 @@ impl Actor {
 
         let prev_facet = std::mem::replace(&mut activation.current_facet, facet_id.clone());
         activation.set_current_entity(Some(issuer_entity));
+        let mark = LimitTracker::mark(&activation.outputs);
         let result = entry
             .entity
             .on_capability_invoke(activation, &metadata, &payload)?;
+        activation.check_limits(issuer_entity, &entry.entity_type, mark)?;
         activation.set_current_entity(None);
         activation.current_facet = prev_facet;
 
         activation.outputs.push(TurnOutput::CapabilityResult {
             capability: capability_id,
             result,
+            invocation,
         });
 
         Ok(())
     }
 
+    /// Invoke [`Entity::on_time_travel`] on every attached entity.
+    ///
+    /// Returns the entities whose hook failed; a failing hook does not stop
+    /// the others from running.
+    pub fn notify_time_travel(
+        &self,
+        old_head: &TurnId,
+        new_head: &TurnId,
+    ) -> Vec<(Uuid, ActorError)> {
+        let entities = self.entities.read();
+        entities
+            .values()
+            .flatten()
+            .filter_map(|entry| {
+                entry
+                    .entity
+                    .on_time_travel(old_head, new_head)
+                    .err()
+                    .map(|err| (entry.id, err))
+            })
+            .collect()
+    }
+
     /// Attach an entity to a facet
     pub fn attach_entity(
         &self,
@@ -439,6 +667,15 @@ impl Actor {
             });
     }
 
+    /// Whether an entity instance is attached to any facet of this actor
+    pub fn has_entity(&self, entity_id: Uuid) -> bool {
+        self.entities
+            .read()
+            .values()
+            .flatten()
+            .any(|entry| entry.id == entity_id)
+    }
+
     /// Detach an entity by ID
     pub fn detach_entity(&self, entity_id: Uuid) -> bool {
         let mut entities = self.entities.write();
@@ -492,6 +729,8 @@ impl Actor {
             id,
             pattern,
             effect,
+            priority,
+            consume,
         } = definition;
         let default_facet = pattern.facet.clone();
         let pattern_id = self.register_pattern(pattern);
@@ -504,6 +743,8 @@ impl Actor {
                     reaction_id: id,
                     effect,
                     default_facet,
+                    priority,
+                    consume,
                     stats: ReactionStats::default(),
                 },
             );
@@ -530,70 +771,123 @@ impl Actor {
             .collect()
     }
 
-    fn trigger_reactions(
-        &self,
-        activation: &mut Activation,
-        pattern_match: &PatternMatch,
-    ) -> ActorResult<()> {
-        let reaction_data = {
+    /// Fire the reactions subscribed to `pattern_matches`, all raised by one assertion.
+    ///
+    /// Reactions fire by descending priority, ties broken by reaction id. Once
+    /// a consuming reaction has fired, reactions of lower priority are skipped.
+    fn trigger_reactions(&self, activation: &mut Activation, pattern_matches: &[PatternMatch]) {
+        let mut pending: Vec<(ReactionEntry, &PatternMatch)> = {
             let reactions = self.reactions.read();
-            reactions.get(&pattern_match.pattern_id).map(|entry| {
-                (
-                    entry.reaction_id,
-                    entry.effect.clone(),
-                    entry.default_facet.clone(),
-                )
-            })
+            pattern_matches
+                .iter()
+                .filter_map(|pattern_match| {
+                    reactions
+                        .get(&pattern_match.pattern_id)
+                        .map(|entry| (entry.clone(), pattern_match))
+                })
+                .collect()
         };
+        pending.sort_by(|(a, _), (b, _)| {
+            b.priority
+                .cmp(&a.priority)
+                .then(a.reaction_id.cmp(&b.reaction_id))
+        });
 
-        if let Some((reaction_id, effect, default_facet)) = reaction_data {
-            let outcome: Result<(), String> = match effect {
-                ReactionEffect::Assert {
-                    value,
-                    target_facet,
-                } => {
-                    let target = target_facet.unwrap_or(default_facet);
-                    let previous = std::mem::replace(&mut activation.current_facet, target);
-                    let result = value
-                        .resolve(pattern_match)
-                        .map(|resolved| {
-                            activation.assert(Handle::new(), resolved);
-                        })
-                        .ok_or_else(|| "unable to resolve assertion value".to_string());
-                    activation.current_facet = previous;
-                    result
-                }
-                ReactionEffect::SendMessage {
-                    actor,
-                    facet,
-                    payload,
-                } => payload
-                    .resolve(pattern_match)
-                    .map(|resolved| activation.send_message(actor, facet, resolved))
-                    .ok_or_else(|| "unable to resolve payload".to_string()),
-            };
+        let mut consumed_at: Option<i32> = None;
+        for (entry, pattern_match) in pending {
+            if consumed_at.is_some_and(|priority| entry.priority < priority) {
+                break;
+            }
+
+            let outcome = Self::fire_reaction(activation, &entry, pattern_match);
 
             {
                 let mut reactions = self.reactions.write();
-                if let Some(entry) = reactions.get_mut(&pattern_match.pattern_id) {
+                if let Some(stored) = reactions.get_mut(&pattern_match.pattern_id) {
                     match &outcome {
-                        Ok(()) => entry.stats.record_success(),
-                        Err(err) => entry.stats.record_error(err.clone()),
+                        Ok(()) => stored.stats.record_success(),
+                        Err(err) => stored.stats.record_error(err.clone()),
                     }
                 }
             }
 
-            if let Err(err) = &outcome {
-                tracing::warn!(
+            match &outcome {
+                Ok(()) if entry.consume => {
+                    consumed_at.get_or_insert(entry.priority);
+                }
+                Ok(()) => {}
+                Err(err) => tracing::warn!(
                     pattern = ?pattern_match.pattern_id,
-                    reaction = ?reaction_id,
+                    reaction = ?entry.reaction_id,
                     "reaction execution failed: {}",
                     err
-                );
+                ),
             }
         }
+    }
 
-        Ok(())
+    fn fire_reaction(
+        activation: &mut Activation,
+        entry: &ReactionEntry,
+        pattern_match: &PatternMatch,
+    ) -> Result<(), String> {
+        match &entry.effect {
+            ReactionEffect::Assert {
+                value,
+                target_facet,
+            } => {
+                let target = target_facet
+                    .clone()
+                    .unwrap_or_else(|| entry.default_facet.clone());
+                let previous = std::mem::replace(&mut activation.current_facet, target);
+                let result = value
+                    .resolve(pattern_match)
+                    .map(|resolved| {
+                        activation.assert(Handle::new(), resolved);
+                    })
+                    .ok_or_else(|| "unable to resolve assertion value".to_string());
+                activation.current_facet = previous;
+                result
+            }
+            ReactionEffect::SendMessage {
+                actor,
+                facet,
+                payload,
+            } => payload
+                .resolve(pattern_match)
+                .map(|resolved| activation.send_message(actor.clone(), facet.clone(), resolved))
+                .ok_or_else(|| "unable to resolve payload".to_string()),
+            ReactionEffect::InvokeCapability {
+                capability,
+                payload,
+                tag,
+            } => {
+                let resolved = payload
+                    .resolve(pattern_match)
+                    .ok_or_else(|| "unable to resolve payload".to_string())?;
+                let alias = match capability {
+                    ReactionCapability::Id { id } => id.to_string(),
+                    ReactionCapability::Kind { kind } => kind.clone(),
+                };
+                let completion = CapabilityCompletion {
+                    origin_actor: activation.actor_id.clone(),
+                    origin_facet: entry.default_facet.clone(),
+                    instance_id: entry.reaction_id.to_string(),
+                    role: "reaction".to_string(),
+                    capability_alias: alias,
+                    tag: tag.clone().unwrap_or_else(|| entry.reaction_id.to_string()),
+                    role_properties: None,
+                };
+                match capability {
+                    ReactionCapability::Id { id } => {
+                        activation.request_capability_invocation(*id, resolved, completion)
+                    }
+                    ReactionCapability::Kind { kind } => activation
+                        .request_capability_invocation_by_kind(kind.clone(), resolved, completion),
+                }
+                Ok(())
+            }
+        }
     }
 }
 
@@ -617,12 +911,21 @@ pub struct Activation {
     /// Assertions retracted
     pub assertions_retracted: Vec<Handle>,
 
+    /// Named dataspaces for assertions made outside the default dataspace
+    pub assertion_namespaces: HashMap<Handle, String>,
+
     /// Assertions emitted locally that still need pattern dispatch
-    pending_asserts: Vec<(Handle, preserves::IOValue)>,
+    pending_asserts: Vec<(Handle, preserves::IOValue, Option<String>)>,
 
     /// Pattern registrations requested during this turn that still need to be applied.
     pending_patterns: Vec<Pattern>,
 
+    /// Scratch facts noted during this turn; never journaled
+    notes: Vec<(Handle, preserves::IOValue)>,
+
+    /// Notes that still need pattern dispatch
+    pending_notes: Vec<(Handle, preserves::IOValue)>,
+
     /// Facets spawned
     pub facets_spawned: Vec<FacetMetadata>,
 
@@ -649,6 +952,18 @@ pub struct Activation {
 
     /// Deterministic sequence counter for spawned entities
     spawn_counter: u64,
+
+    /// Per-entity emission limits for this turn
+    limits: LimitTracker,
+
+    /// Secrets entities may look up during this turn
+    secrets: Arc<SecretsProvider>,
+
+    /// Manager owning background tasks spawned during this turn
+    tasks: Option<TaskManager>,
+
+    /// Deterministic random number generator for this turn
+    rng: DeterministicRng,
 }
 
 /// Stable namespace for deriving spawn identifiers (UUID v5).
@@ -699,14 +1014,18 @@ impl Activation {
         async_sender: Option<Sender<AsyncMessage>>,
     ) -> Self {
         Self {
+            rng: DeterministicRng::for_turn(&actor_id, 0),
             actor_id,
             current_facet: current_facet.clone(),
             root_facet: current_facet,
             outputs: Vec::new(),
             assertions_added: Vec::new(),
             assertions_retracted: Vec::new(),
+            assertion_namespaces: HashMap::new(),
             pending_asserts: Vec::new(),
             pending_patterns: Vec::new(),
+            notes: Vec::new(),
+            pending_notes: Vec::new(),
             facets_spawned: Vec::new(),
             facets_terminated: Vec::new(),
             tokens_borrowed: 0,
@@ -716,24 +1035,207 @@ impl Activation {
             current_entity: None,
             async_sender,
             spawn_counter: 0,
+            limits: LimitTracker::default(),
+            secrets: Arc::default(),
+            tasks: None,
         }
     }
 
+    /// Fail the turn if the entity's outputs since `mark` exceed its limits
+    fn check_limits(
+        &mut self,
+        entity_id: Uuid,
+        entity_type: &str,
+        mark: OutputMark,
+    ) -> ActorResult<()> {
+        self.limits
+            .check(entity_id, entity_type, &self.outputs, mark)
+    }
+
+    /// Look up a runtime secret, journaling the access (by name only)
+    pub fn secret(&mut self, name: &str) -> Option<String> {
+        let value = self.secrets.get(name).map(str::to_string);
+        self.outputs.push(TurnOutput::SecretAccessed {
+            entity_id: self.current_entity,
+            name: name.to_string(),
+            found: value.is_some(),
+        });
+        value
+    }
+
+    /// Run `body` on a background thread owned by the current facet.
+    ///
+    /// The task starts once this turn commits and is cancelled when the facet
+    /// terminates or a rewind discards the turn; results sent through its
+    /// [`TaskContext`] arrive as messages on the facet. Returns `None` when
+    /// the turn is not executed by a runtime that can host tasks.
+    pub fn spawn_task<F>(&mut self, name: impl Into<String>, body: F) -> Option<TaskId>
+    where
+        F: FnOnce(TaskContext) + Send + 'static,
+    {
+        let tasks = self.tasks.as_ref()?;
+        let sender = self.async_sender.clone()?;
+        Some(tasks.spawn(
+            name.into(),
+            self.actor_id.clone(),
+            self.current_facet.clone(),
+            self.current_entity,
+            sender,
+            Box::new(body),
+        ))
+    }
+
+    /// Record an external effect that time travel cannot undo
+    pub fn record_side_effect(
+        &mut self,
+        kind: EffectKind,
+        target: impl Into<String>,
+        description: impl Into<String>,
+    ) {
+        self.outputs.push(TurnOutput::SideEffect {
+            entity_id: self.current_entity,
+            kind,
+            target: target.into(),
+            description: description.into(),
+        });
+    }
+
+    /// Wake the current facet's entities with `payload` once `delay` has
+    /// passed, returning the timer's id.
+    ///
+    /// The timer is journaled with this turn and fires as a turn of its own;
+    /// see [`super::timer`].
+    pub fn set_timer(&mut self, delay: std::time::Duration, payload: preserves::IOValue) -> Uuid {
+        let index = self
+            .outputs
+            .iter()
+            .filter(|output| matches!(output, TurnOutput::TimerRegistered { .. }))
+            .count() as u64;
+        let timer_id = spawn_uuid(&[
+            &self.rng.seed(),
+            self.current_facet.0.as_bytes(),
+            &index.to_le_bytes(),
+            b":timer",
+        ]);
+        let delay = chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX);
+        let deadline = chrono::Utc::now()
+            .checked_add_signed(delay)
+            .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC);
+        self.outputs.push(TurnOutput::TimerRegistered {
+            timer_id,
+            deadline,
+            facet: self.current_facet.clone(),
+            payload,
+        });
+        timer_id
+    }
+
+    /// Deterministic random number generator of this actor for this turn.
+    ///
+    /// Every execution of the turn, including one on a fork or after a
+    /// rewind, draws the same sequence; see [`super::rng`].
+    pub fn rng(&mut self) -> &mut DeterministicRng {
+        &mut self.rng
+    }
+
+    /// Stage a delivery of `payload` to the external system behind outbox
+    /// `channel`.
+    ///
+    /// Nothing is sent from inside the turn: the delivery is journaled with
+    /// it and dispatched by the outbox once the turn commits, exactly once.
+    pub fn stage_delivery(&mut self, channel: impl Into<String>, payload: preserves::IOValue) {
+        self.outputs.push(TurnOutput::OutboxStaged {
+            entity_id: self.current_entity,
+            channel: channel.into(),
+            payload,
+        });
+    }
+
+    /// Journal the content a file write is about to replace.
+    ///
+    /// `previous` is `None` when the file did not exist yet; restoring such a
+    /// backup removes the file again.
+    pub fn record_file_backup(
+        &mut self,
+        path: impl Into<String>,
+        location: impl Into<String>,
+        previous: Option<Vec<u8>>,
+    ) {
+        self.outputs.push(TurnOutput::FileBackup {
+            entity_id: self.current_entity,
+            path: path.into(),
+            location: location.into(),
+            previous: previous.map(preserves::IOValue::bytes),
+        });
+    }
+
     /// Make an assertion
     pub fn assert(&mut self, handle: Handle, value: preserves::IOValue) {
         self.assertions_added.push((handle.clone(), value.clone()));
-        self.pending_asserts.push((handle.clone(), value.clone()));
-        self.outputs.push(TurnOutput::Assert { handle, value });
+        self.pending_asserts
+            .push((handle.clone(), value.clone(), None));
+        self.outputs.push(TurnOutput::Assert {
+            handle,
+            value,
+            namespace: None,
+        });
+    }
+
+    /// Make an assertion into a named dataspace.
+    ///
+    /// Only patterns registered for the same namespace observe the assertion.
+    /// Asserting into [`DEFAULT_NAMESPACE`] is equivalent to [`Activation::assert`].
+    pub fn assert_in(
+        &mut self,
+        namespace: impl Into<String>,
+        handle: Handle,
+        value: preserves::IOValue,
+    ) {
+        let namespace = namespace.into();
+        if namespace == DEFAULT_NAMESPACE {
+            self.assert(handle, value);
+            return;
+        }
+
+        self.assertions_added.push((handle.clone(), value.clone()));
+        self.assertion_namespaces
+            .insert(handle.clone(), namespace.clone());
+        self.pending_asserts
+            .push((handle.clone(), value.clone(), Some(namespace.clone())));
+        self.outputs.push(TurnOutput::Assert {
+            handle,
+            value,
+            namespace: Some(namespace),
+        });
+    }
+
+    /// Note a scratch fact for the rest of this turn.
+    ///
+    /// Patterns and reactions of this actor see the note like an assertion
+    /// while the turn runs, but it produces no output, is never journaled and
+    /// is gone once the turn ends. Use it for intermediate values that other
+    /// entities of the actor react to within the turn.
+    pub fn note(&mut self, value: preserves::IOValue) -> Handle {
+        let handle = Handle::new();
+        self.notes.push((handle.clone(), value.clone()));
+        self.pending_notes.push((handle.clone(), value));
+        handle
+    }
+
+    /// Scratch facts noted so far in this turn, oldest first.
+    pub fn notes(&self) -> impl Iterator<Item = &preserves::IOValue> {
+        self.notes.iter().map(|(_, value)| value)
     }
 
     /// Retract an assertion
     pub fn retract(&mut self, handle: Handle) {
         self.assertions_retracted.push(handle.clone());
-        self.outputs.push(TurnOutput::Retract { handle });
+        let namespace = self.assertion_namespaces.get(&handle).cloned();
+        self.outputs.push(TurnOutput::Retract { handle, namespace });
     }
 
     /// Drain pending local assertions for pattern processing
-    pub fn drain_pending_asserts(&mut self) -> Vec<(Handle, preserves::IOValue)> {
+    pub fn drain_pending_asserts(&mut self) -> Vec<(Handle, preserves::IOValue, Option<String>)> {
         self.pending_asserts.drain(..).collect()
     }
 
@@ -890,6 +1392,24 @@ impl Activation {
         });
     }
 
+    /// Request invocation of the capability of `kind` held by this actor.
+    ///
+    /// The runtime picks the active capability of that kind with the lowest
+    /// id once the turn completes; if there is none, the completion receives a
+    /// `tool-error` result.
+    pub fn request_capability_invocation_by_kind(
+        &mut self,
+        kind: impl Into<String>,
+        payload: preserves::IOValue,
+        completion: CapabilityCompletion,
+    ) {
+        self.outputs.push(TurnOutput::CapabilityInvokeByKind {
+            kind: kind.into(),
+            payload,
+            completion,
+        });
+    }
+
     /// Spawn a new actor hosting the specified entity.
     ///
     /// Returns identifying information for the spawned entity so callers can
@@ -963,6 +1483,8 @@ impl Activation {
             id: pattern_id,
             pattern: pattern_expr,
             facet,
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         self.outputs.push(TurnOutput::PatternRegistered {
@@ -1063,10 +1585,16 @@ impl Activation {
                 self.actor_id.clone(),
                 handle.clone(),
                 value.clone(),
-                uuid::Uuid::new_v4(), // Generate version ID
+                assertion_version(&self.actor_id, handle, value),
             ));
         }
 
+        for (handle, namespace) in &self.assertion_namespaces {
+            assertions
+                .namespaces
+                .push((self.actor_id.clone(), handle.clone(), namespace.clone()));
+        }
+
         for handle in &self.assertions_retracted {
             assertions.retracted.push((
                 self.actor_id.clone(),
@@ -1139,6 +1667,18 @@ pub trait Entity: Send + Sync + Any {
         Ok(())
     }
 
+    /// Handle a timer set with [`Activation::set_timer`] firing.
+    ///
+    /// Defaults to delivering the timer's payload like a message.
+    fn on_timer(
+        &self,
+        activation: &mut Activation,
+        _timer_id: Uuid,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        self.on_message(activation, payload)
+    }
+
     /// Handle a retraction
     fn on_retract(&self, _activation: &mut Activation, _handle: &Handle) -> ActorResult<()> {
         Ok(())
@@ -1160,6 +1700,20 @@ pub trait Entity: Send + Sync + Any {
             "capability invocation not supported by this entity".into(),
         ))
     }
+
+    /// Called after history jumps (`goto`, `back`, or a branch switch).
+    ///
+    /// The dataspace already reflects `new_head` when this runs; entities that
+    /// mirror state outside the runtime (files, remote services) can use it to
+    /// reconcile with the restored history.
+    fn on_time_travel(&self, _old_head: &TurnId, _new_head: &TurnId) -> ActorResult<()> {
+        Ok(())
+    }
+
+    /// Advertise the messages, capability kinds and configuration this entity supports.
+    fn describe(&self) -> EntityDescriptor {
+        EntityDescriptor::default()
+    }
 }
 
 /// Optional trait for entities with private state that can't live in the dataspace
@@ -1299,6 +1853,8 @@ mod tests {
             actor: actor.id.clone(),
             facet,
             payload: preserves::IOValue::symbol("test-message"),
+            idempotency_key: None,
+            broadcast: None,
         };
 
         let result = actor.execute_turn(vec![input], None);
@@ -1310,7 +1866,7 @@ mod tests {
 
     #[test]
     fn test_pattern_integration() {
-        use crate::runtime::pattern::Pattern;
+        use crate::runtime::pattern::{Pattern, PatternScope};
         use std::sync::Arc;
         use std::sync::atomic::{AtomicUsize, Ordering};
 
@@ -1357,6 +1913,8 @@ mod tests {
             id: uuid::Uuid::new_v4(),
             pattern: preserves::IOValue::symbol("<_>"),
             facet: facet.clone(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         actor.register_pattern(pattern);
@@ -1383,6 +1941,7 @@ mod tests {
             actor: actor.id.clone(),
             handle: handle.clone(),
             value,
+            namespace: None,
         }];
 
         let (outputs, _) = actor.execute_turn(inputs, None).unwrap();
@@ -1418,7 +1977,7 @@ mod tests {
 
     #[test]
     fn test_local_assert_triggers_pattern() {
-        use crate::runtime::pattern::Pattern;
+        use crate::runtime::pattern::{Pattern, PatternScope};
 
         struct SelfAssertEntity;
 
@@ -1442,6 +2001,8 @@ mod tests {
             id: uuid::Uuid::new_v4(),
             pattern: preserves::IOValue::symbol("local-value"),
             facet: facet.clone(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         actor.register_pattern(pattern);
@@ -1457,6 +2018,8 @@ mod tests {
             actor: actor.id.clone(),
             facet,
             payload: preserves::IOValue::symbol("trigger"),
+            idempotency_key: None,
+            broadcast: None,
         };
 
         let (outputs, _) = actor.execute_turn(vec![input], None).unwrap();
@@ -1469,6 +2032,83 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_notes_reach_patterns_without_outputs() {
+        use crate::runtime::pattern::{Pattern, PatternScope};
+
+        struct NotingEntity;
+
+        impl Entity for NotingEntity {
+            fn on_message(
+                &self,
+                activation: &mut Activation,
+                _payload: &preserves::IOValue,
+            ) -> ActorResult<()> {
+                activation.note(preserves::IOValue::symbol("scratch"));
+                Ok(())
+            }
+
+            fn on_assert(
+                &self,
+                activation: &mut Activation,
+                _handle: &Handle,
+                value: &preserves::IOValue,
+            ) -> ActorResult<()> {
+                assert!(activation.notes().any(|note| note == value));
+                activation.assert(Handle::new(), preserves::IOValue::symbol("derived"));
+                Ok(())
+            }
+        }
+
+        let actor = Actor::new(ActorId::new());
+        let facet = actor.root_facet.clone();
+        let pattern_id = actor.register_pattern(Pattern {
+            id: uuid::Uuid::new_v4(),
+            pattern: preserves::IOValue::symbol("scratch"),
+            facet: facet.clone(),
+            namespace: None,
+            scope: PatternScope::Actor,
+        });
+        actor.attach_entity(
+            uuid::Uuid::new_v4(),
+            "noting".to_string(),
+            facet.clone(),
+            Box::new(NotingEntity),
+        );
+
+        let input = TurnInput::ExternalMessage {
+            actor: actor.id.clone(),
+            facet,
+            payload: preserves::IOValue::symbol("trigger"),
+            idempotency_key: None,
+            broadcast: None,
+        };
+        let (outputs, delta) = actor.execute_turn(vec![input], None).unwrap();
+
+        // Only the derived assertion is durable
+        let asserted: Vec<_> = outputs
+            .iter()
+            .filter_map(|output| match output {
+                TurnOutput::Assert { value, .. } => Some(value.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(asserted, vec![preserves::IOValue::symbol("derived")]);
+        assert!(
+            !outputs
+                .iter()
+                .any(|o| matches!(o, TurnOutput::PatternMatched { .. }))
+        );
+        assert_eq!(delta.assertions.added.len(), 1);
+        assert!(
+            actor
+                .pattern_engine
+                .read()
+                .get_matches(&pattern_id)
+                .is_empty()
+        );
+    }
+
     #[test]
     fn spawn_entity_outputs_are_deterministic() {
         let actor = Actor::new(ActorId::new());
@@ -1571,4 +2211,78 @@ mod tests {
             "revoking should emit a capability revoked output"
         );
     }
+
+    #[test]
+    fn test_turn_limits_reject_oversized_assertions() {
+        use crate::runtime::limits::TurnLimits;
+
+        struct BulkEntity;
+
+        impl Entity for BulkEntity {
+            fn on_message(
+                &self,
+                activation: &mut Activation,
+                _payload: &preserves::IOValue,
+            ) -> ActorResult<()> {
+                for _ in 0..3 {
+                    activation.assert(Handle::new(), preserves::IOValue::new("x".repeat(64)));
+                }
+                Ok(())
+            }
+        }
+
+        let actor = Actor::new(ActorId::new());
+        let facet = actor.root_facet.clone();
+        actor.attach_entity(
+            uuid::Uuid::new_v4(),
+            "bulk".to_string(),
+            facet.clone(),
+            Box::new(BulkEntity),
+        );
+        let input = TurnInput::ExternalMessage {
+            actor: actor.id.clone(),
+            facet,
+            payload: preserves::IOValue::symbol("go"),
+            idempotency_key: None,
+            broadcast: None,
+        };
+
+        let mut limits = LimitsConfig::default();
+        limits.defaults.max_assertion_bytes = Some(16);
+        match actor.execute_turn_with_limits(vec![input.clone()], None, &limits) {
+            Err(ActorError::LimitExceeded {
+                entity_type,
+                limit,
+                max,
+                ..
+            }) => {
+                assert_eq!(entity_type, "bulk");
+                assert_eq!(limit, "max_assertion_bytes");
+                assert_eq!(max, 16);
+            }
+            other => panic!("expected size limit error, got {other:?}"),
+        }
+
+        limits.defaults = TurnLimits {
+            max_assertions_per_turn: Some(2),
+            ..TurnLimits::default()
+        };
+        assert!(matches!(
+            actor.execute_turn_with_limits(vec![input.clone()], None, &limits),
+            Err(ActorError::LimitExceeded {
+                actual: 3,
+                max: 2,
+                ..
+            })
+        ));
+
+        // An override for the entity type lifts the limits
+        limits
+            .overrides
+            .insert("bulk".to_string(), TurnLimits::unlimited());
+        let (outputs, _) = actor
+            .execute_turn_with_limits(vec![input], None, &limits)
+            .unwrap();
+        assert_eq!(outputs.len(), 3);
+    }
 }
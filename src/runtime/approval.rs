@@ -0,0 +1,162 @@
+//! Human approval gates for capability invocations
+//!
+//! Invocations of capability kinds listed in
+//! [`RuntimeConfig::approval_kinds`](super::RuntimeConfig::approval_kinds) are
+//! parked here instead of running. The requesting actor sees a
+//! `<pending-approval ...>` assertion until an operator approves or denies the
+//! request through the control plane.
+//!
+//! That assertion is journaled, so it also decides which parked invocations
+//! are pending at the current head: after time travel or a branch switch the
+//! pending set is rebuilt from the live assertions, and only those can be
+//! approved or denied.
+
+use chrono::{DateTime, Utc};
+use preserves::IOValue;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use uuid::Uuid;
+
+use super::state::CapId;
+use super::turn::{CapabilityCompletion, Handle};
+use crate::util::io_value::record_with_label;
+
+/// Identifier of a parked invocation.
+pub type ApprovalId = Uuid;
+
+/// Record label asserted while an invocation awaits approval.
+pub const PENDING_APPROVAL_LABEL: &str = "pending-approval";
+
+/// Record label delivered as the invocation result when a request is denied.
+pub const APPROVAL_DENIED_LABEL: &str = "approval-denied";
+
+/// Capability invocation parked until a human decides on it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingApproval {
+    /// Identifier used by `approve`/`deny`
+    pub id: ApprovalId,
+    /// Capability the invocation targets
+    pub capability: CapId,
+    /// Kind of the capability (the reason approval is required)
+    pub kind: String,
+    /// Payload the invocation will run with once approved
+    pub payload: IOValue,
+    /// Where the invocation result should be published
+    pub completion: CapabilityCompletion,
+    /// Handle of the `pending-approval` assertion on the origin actor
+    pub handle: Handle,
+    /// When the invocation was parked
+    pub requested_at: DateTime<Utc>,
+}
+
+impl PendingApproval {
+    /// `<pending-approval id capability kind tag payload>` record shown to the origin actor.
+    pub fn record(&self) -> IOValue {
+        IOValue::record(
+            IOValue::symbol(PENDING_APPROVAL_LABEL),
+            vec![
+                IOValue::new(self.id.to_string()),
+                IOValue::new(self.capability.to_string()),
+                IOValue::new(self.kind.clone()),
+                IOValue::new(self.completion.tag.clone()),
+                self.payload.clone(),
+            ],
+        )
+    }
+
+    /// Approval id named by a `<pending-approval ...>` record.
+    pub fn id_in(value: &IOValue) -> Option<ApprovalId> {
+        let record = record_with_label(value, PENDING_APPROVAL_LABEL)?;
+        record.field_string(0)?.parse().ok()
+    }
+
+    /// `<approval-denied id kind reason>` record delivered in place of a result.
+    pub fn denial_record(&self, reason: &str) -> IOValue {
+        IOValue::record(
+            IOValue::symbol(APPROVAL_DENIED_LABEL),
+            vec![
+                IOValue::new(self.id.to_string()),
+                IOValue::new(self.kind.clone()),
+                IOValue::new(reason.to_string()),
+            ],
+        )
+    }
+}
+
+/// Persistent record of parked invocations and the ones pending at the current head.
+#[derive(Debug, Default)]
+pub struct ApprovalStore {
+    entries: BTreeMap<ApprovalId, PendingApproval>,
+    pending: BTreeSet<ApprovalId>,
+}
+
+impl ApprovalStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load parked invocations from disk, returning an empty store if the file is absent.
+    pub fn load(path: &Path) -> Result<Self, std::io::Error> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let data = std::fs::read(path)?;
+        let entries: Vec<PendingApproval> =
+            preserves::serde::from_bytes(&data).map_err(io_error)?;
+        let entries: BTreeMap<_, _> = entries.into_iter().map(|entry| (entry.id, entry)).collect();
+        Ok(Self {
+            pending: entries.keys().copied().collect(),
+            entries,
+        })
+    }
+
+    /// Persist the store to disk, creating parent directories as needed.
+    pub fn save(&self, path: &Path) -> Result<(), std::io::Error> {
+        use preserves::PackedWriter;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let entries: Vec<&PendingApproval> = self.entries.values().collect();
+        let mut buf = Vec::new();
+        let mut writer = PackedWriter::new(&mut buf);
+        preserves::serde::to_writer(&mut writer, &entries).map_err(io_error)?;
+        std::fs::write(path, buf)
+    }
+
+    /// Park an invocation.
+    pub fn insert(&mut self, entry: PendingApproval) {
+        self.pending.insert(entry.id);
+        self.entries.insert(entry.id, entry);
+    }
+
+    /// Take a pending invocation so it can be approved or denied.
+    ///
+    /// The invocation stays on record, so travelling back to before the
+    /// decision makes it pending again.
+    pub fn take(&mut self, id: &ApprovalId) -> Option<PendingApproval> {
+        if !self.pending.remove(id) {
+            return None;
+        }
+        self.entries.get(id).cloned()
+    }
+
+    /// Replace the pending set with the recorded invocations among `live`.
+    pub fn rebuild(&mut self, live: impl IntoIterator<Item = ApprovalId>) {
+        self.pending = live
+            .into_iter()
+            .filter(|id| self.entries.contains_key(id))
+            .collect();
+    }
+
+    /// Iterate over pending invocations in identifier order.
+    pub fn iter(&self) -> impl Iterator<Item = &PendingApproval> {
+        self.pending.iter().filter_map(|id| self.entries.get(id))
+    }
+}
+
+fn io_error(err: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+}
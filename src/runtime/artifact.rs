@@ -0,0 +1,815 @@
+//! Branch checkpoints as content-addressed artifacts
+//!
+//! A checkpoint bundles everything needed to rebuild a branch elsewhere: its
+//! full lineage of turn records (ancestor turns included, so the result is
+//! self-contained), the newest snapshot on the branch, and the metadata of
+//! the entities living on it. Each part is stored as a layer addressed by its
+//! sha256 digest and tied together by an OCI image manifest, so a checkpoint
+//! can be pushed to any OCI distribution registry and pulled back like a
+//! container image. Plain HTTP stores and local directories are supported
+//! with the same layout; layers already present in a store are not uploaded
+//! again.
+//!
+//! Stores are addressed by location strings:
+//!
+//! - `oci://host[:port]/name` – OCI registry over HTTPS
+//!   (`oci+http://` for registries without TLS)
+//! - `http(s)://base` – artifact store accepting `PUT` and `GET` on
+//!   `base/blobs/<digest>` and `base/manifests/<reference>`
+//! - `file:///path` or a bare path – directory holding `blobs/` and `refs/`
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::error::{Result, RuntimeError};
+use super::registry::EntityMetadata;
+use super::snapshot::RuntimeSnapshot;
+use super::turn::{BranchId, TurnId, TurnRecord};
+use super::version::VersionStamp;
+
+/// Media type of the manifest tying a checkpoint together.
+pub const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+/// Artifact type recorded in checkpoint manifests.
+pub const ARTIFACT_TYPE: &str = "application/vnd.duet.checkpoint.v1";
+/// Media type of the checkpoint configuration blob.
+pub const CONFIG_MEDIA_TYPE: &str = "application/vnd.duet.checkpoint.config.v1+json";
+/// Media type of the turn record layer.
+pub const JOURNAL_MEDIA_TYPE: &str = "application/vnd.duet.checkpoint.journal.v1+preserves";
+/// Media type of the snapshot layer.
+pub const SNAPSHOT_MEDIA_TYPE: &str = "application/vnd.duet.checkpoint.snapshot.v1+preserves";
+/// Media type of the entity metadata layer.
+pub const ENTITIES_MEDIA_TYPE: &str = "application/vnd.duet.checkpoint.entities.v1+json";
+
+/// Content digest of `bytes` in OCI form (`sha256:<hex>`).
+pub fn digest(bytes: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(bytes))
+}
+
+/// Reference to a blob in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Descriptor {
+    /// Media type of the referenced content
+    pub media_type: String,
+    /// Content digest
+    pub digest: String,
+    /// Size in bytes
+    pub size: u64,
+}
+
+impl Descriptor {
+    fn of(media_type: &str, bytes: &[u8]) -> Self {
+        Self {
+            media_type: media_type.to_string(),
+            digest: digest(bytes),
+            size: bytes.len() as u64,
+        }
+    }
+}
+
+/// OCI image manifest describing a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    /// Always 2
+    pub schema_version: u32,
+    /// [`MANIFEST_MEDIA_TYPE`]
+    pub media_type: String,
+    /// [`ARTIFACT_TYPE`]
+    #[serde(default)]
+    pub artifact_type: Option<String>,
+    /// Checkpoint configuration blob
+    pub config: Descriptor,
+    /// Journal, snapshot and entity layers
+    pub layers: Vec<Descriptor>,
+    /// Free-form annotations
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// Blob contents with the descriptor referencing them.
+pub type Blob = (Descriptor, Vec<u8>);
+
+/// Configuration blob of a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointConfig {
+    /// Branch the checkpoint was taken from
+    pub branch: BranchId,
+    /// Head of that branch when exported
+    pub head: TurnId,
+    /// Number of turn records in the journal layer
+    pub turns: usize,
+    /// Turn of the bundled snapshot, if any
+    pub snapshot_turn: Option<TurnId>,
+    /// Runtime version that exported the checkpoint
+    pub version: VersionStamp,
+    /// When the checkpoint was exported
+    pub created_at: DateTime<Utc>,
+}
+
+/// A branch's history and state, ready to be pushed or imported.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    /// Description of the checkpoint
+    pub config: CheckpointConfig,
+    /// Turn records from the root of the branch's lineage to its head
+    pub records: Vec<TurnRecord>,
+    /// Newest snapshot on the branch
+    pub snapshot: Option<RuntimeSnapshot>,
+    /// Entities instantiated on the branch
+    pub entities: Vec<EntityMetadata>,
+}
+
+/// Result of pushing a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushReport {
+    /// Reference the manifest was stored under
+    pub reference: String,
+    /// Digest of the manifest
+    pub manifest_digest: String,
+    /// Blobs uploaded
+    pub uploaded: usize,
+    /// Blobs already present in the store
+    pub reused: usize,
+    /// Total bytes of the config blob and layers
+    pub size: u64,
+}
+
+impl Checkpoint {
+    /// Encode the checkpoint as a manifest and the blobs it references
+    /// (config first).
+    pub fn encode(&self) -> Result<(Manifest, Vec<Blob>)> {
+        let config = serde_json::to_vec(&self.config).map_err(artifact_error)?;
+        let mut blobs = vec![(Descriptor::of(CONFIG_MEDIA_TYPE, &config), config)];
+
+        let journal = encode_preserves(&self.records)?;
+        blobs.push((Descriptor::of(JOURNAL_MEDIA_TYPE, &journal), journal));
+        if let Some(snapshot) = &self.snapshot {
+            let snapshot = encode_preserves(snapshot)?;
+            blobs.push((Descriptor::of(SNAPSHOT_MEDIA_TYPE, &snapshot), snapshot));
+        }
+        let entities = serde_json::to_vec(&self.entities).map_err(artifact_error)?;
+        blobs.push((Descriptor::of(ENTITIES_MEDIA_TYPE, &entities), entities));
+
+        let annotations = BTreeMap::from([
+            (
+                "org.opencontainers.image.created".to_string(),
+                self.config.created_at.to_rfc3339(),
+            ),
+            (
+                "dev.duet.checkpoint.branch".to_string(),
+                self.config.branch.to_string(),
+            ),
+            (
+                "dev.duet.checkpoint.head".to_string(),
+                self.config.head.to_string(),
+            ),
+        ]);
+        let manifest = Manifest {
+            schema_version: 2,
+            media_type: MANIFEST_MEDIA_TYPE.to_string(),
+            artifact_type: Some(ARTIFACT_TYPE.to_string()),
+            config: blobs[0].0.clone(),
+            layers: blobs[1..].iter().map(|(desc, _)| desc.clone()).collect(),
+            annotations,
+        };
+        Ok((manifest, blobs))
+    }
+
+    /// Rebuild a checkpoint from `manifest`, fetching and verifying each blob.
+    pub fn decode(
+        manifest: &Manifest,
+        mut fetch: impl FnMut(&Descriptor) -> Result<Vec<u8>>,
+    ) -> Result<Self> {
+        if manifest.config.media_type != CONFIG_MEDIA_TYPE {
+            return Err(RuntimeError::Artifact(format!(
+                "not a duet checkpoint (config media type '{}')",
+                manifest.config.media_type
+            )));
+        }
+        let mut load = |desc: &Descriptor| -> Result<Vec<u8>> {
+            let bytes = fetch(desc)?;
+            verify(desc, &bytes)?;
+            Ok(bytes)
+        };
+
+        let config: CheckpointConfig =
+            serde_json::from_slice(&load(&manifest.config)?).map_err(artifact_error)?;
+        let mut records = None;
+        let mut snapshot = None;
+        let mut entities = Vec::new();
+        for layer in &manifest.layers {
+            match layer.media_type.as_str() {
+                JOURNAL_MEDIA_TYPE => records = Some(decode_preserves(&load(layer)?)?),
+                SNAPSHOT_MEDIA_TYPE => snapshot = Some(decode_preserves(&load(layer)?)?),
+                ENTITIES_MEDIA_TYPE => {
+                    entities = serde_json::from_slice(&load(layer)?).map_err(artifact_error)?
+                }
+                other => {
+                    return Err(RuntimeError::Artifact(format!(
+                        "unknown checkpoint layer '{other}'"
+                    )));
+                }
+            }
+        }
+        let records: Vec<TurnRecord> = records
+            .ok_or_else(|| RuntimeError::Artifact("checkpoint has no journal layer".into()))?;
+        if records.len() != config.turns {
+            return Err(RuntimeError::Artifact(format!(
+                "journal layer holds {} turns, config declares {}",
+                records.len(),
+                config.turns
+            )));
+        }
+
+        Ok(Self {
+            config,
+            records,
+            snapshot,
+            entities,
+        })
+    }
+}
+
+fn encode_preserves<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    let mut writer = preserves::PackedWriter::new(&mut buf);
+    preserves::serde::to_writer(&mut writer, value).map_err(artifact_error)?;
+    Ok(buf)
+}
+
+fn decode_preserves<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    preserves::serde::from_bytes(bytes).map_err(artifact_error)
+}
+
+fn artifact_error(err: impl std::fmt::Display) -> RuntimeError {
+    RuntimeError::Artifact(err.to_string())
+}
+
+fn verify(desc: &Descriptor, bytes: &[u8]) -> Result<()> {
+    let actual = digest(bytes);
+    if actual != desc.digest || bytes.len() as u64 != desc.size {
+        return Err(RuntimeError::Artifact(format!(
+            "blob {} failed verification (got {} bytes with digest {})",
+            desc.digest,
+            bytes.len(),
+            actual
+        )));
+    }
+    Ok(())
+}
+
+/// Check that `reference` is a tag or a `sha256:` digest.
+fn check_reference(reference: &str) -> Result<()> {
+    let tag = reference.len() <= 128
+        && reference
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        && reference
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if tag || is_digest(reference) {
+        Ok(())
+    } else {
+        Err(RuntimeError::Artifact(format!(
+            "invalid reference '{reference}' (expected a tag or sha256 digest)"
+        )))
+    }
+}
+
+fn is_digest(reference: &str) -> bool {
+    reference.strip_prefix("sha256:").is_some_and(|hex| {
+        hex.len() == 64
+            && hex
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+    })
+}
+
+/// Content-addressed storage for checkpoint blobs and manifests.
+pub trait ArtifactStore: Send {
+    /// Whether a blob with `digest` is already stored.
+    fn has_blob(&self, digest: &str) -> Result<bool>;
+    /// Store `bytes` under `digest`.
+    fn put_blob(&self, digest: &str, bytes: &[u8]) -> Result<()>;
+    /// Fetch the blob stored under `digest`.
+    fn get_blob(&self, digest: &str) -> Result<Vec<u8>>;
+    /// Store a manifest under `reference`.
+    fn put_manifest(&self, reference: &str, bytes: &[u8]) -> Result<()>;
+    /// Fetch the manifest stored under `reference`.
+    fn get_manifest(&self, reference: &str) -> Result<Vec<u8>>;
+}
+
+/// Open the store at `location`, authenticating HTTP requests with `token`.
+pub fn open_store(location: &str, token: Option<String>) -> Result<Box<dyn ArtifactStore>> {
+    let registry = |rest: &str, scheme: &str| -> Result<Box<dyn ArtifactStore>> {
+        let (host, name) = rest
+            .split_once('/')
+            .filter(|(host, name)| !host.is_empty() && !name.is_empty())
+            .ok_or_else(|| {
+                RuntimeError::Artifact(format!("registry location '{location}' must be host/name"))
+            })?;
+        Ok(Box::new(OciRegistry::new(
+            format!("{scheme}://{host}"),
+            name,
+            token.clone(),
+        )))
+    };
+
+    if let Some(rest) = location.strip_prefix("oci://") {
+        registry(rest, "https")
+    } else if let Some(rest) = location.strip_prefix("oci+http://") {
+        registry(rest, "http")
+    } else if location.starts_with("http://") || location.starts_with("https://") {
+        Ok(Box::new(HttpStore::new(location, token)))
+    } else {
+        let path = location.strip_prefix("file://").unwrap_or(location);
+        Ok(Box::new(DirectoryStore::new(path)))
+    }
+}
+
+/// Upload `checkpoint` to `store` under `reference`, skipping blobs the store
+/// already holds.
+pub fn push(
+    store: &dyn ArtifactStore,
+    checkpoint: &Checkpoint,
+    reference: &str,
+) -> Result<PushReport> {
+    check_reference(reference)?;
+    if is_digest(reference) {
+        return Err(RuntimeError::Artifact(
+            "checkpoints are pushed under a tag, not a digest".into(),
+        ));
+    }
+
+    let (manifest, blobs) = checkpoint.encode()?;
+    let mut uploaded = 0;
+    let mut reused = 0;
+    let mut size = 0;
+    for (desc, bytes) in &blobs {
+        size += desc.size;
+        if store.has_blob(&desc.digest)? {
+            reused += 1;
+        } else {
+            store.put_blob(&desc.digest, bytes)?;
+            uploaded += 1;
+        }
+    }
+
+    let manifest = serde_json::to_vec(&manifest).map_err(artifact_error)?;
+    store.put_manifest(reference, &manifest)?;
+    Ok(PushReport {
+        reference: reference.to_string(),
+        manifest_digest: digest(&manifest),
+        uploaded,
+        reused,
+        size,
+    })
+}
+
+/// Download and verify the checkpoint stored under `reference`.
+pub fn pull(store: &dyn ArtifactStore, reference: &str) -> Result<Checkpoint> {
+    check_reference(reference)?;
+    let bytes = store.get_manifest(reference)?;
+    if is_digest(reference) && digest(&bytes) != reference {
+        return Err(RuntimeError::Artifact(format!(
+            "manifest does not match digest {reference}"
+        )));
+    }
+    let manifest: Manifest = serde_json::from_slice(&bytes).map_err(artifact_error)?;
+    Checkpoint::decode(&manifest, |desc| store.get_blob(&desc.digest))
+}
+
+/// Store laid out in a local directory.
+///
+/// Blobs live under `blobs/sha256/<hex>`; manifests are stored as blobs too,
+/// with `refs/<tag>` holding the manifest digest.
+pub struct DirectoryStore {
+    root: PathBuf,
+}
+
+impl DirectoryStore {
+    /// Store rooted at `root`, created on first write.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn blob_path(&self, digest: &str) -> Result<PathBuf> {
+        if !is_digest(digest) {
+            return Err(RuntimeError::Artifact(format!("invalid digest '{digest}'")));
+        }
+        Ok(self.root.join("blobs").join("sha256").join(&digest[7..]))
+    }
+
+    fn write(path: &std::path::Path, bytes: &[u8]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(artifact_error)?;
+        }
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, bytes).map_err(artifact_error)?;
+        std::fs::rename(&temp, path).map_err(artifact_error)
+    }
+}
+
+impl ArtifactStore for DirectoryStore {
+    fn has_blob(&self, digest: &str) -> Result<bool> {
+        Ok(self.blob_path(digest)?.exists())
+    }
+
+    fn put_blob(&self, digest: &str, bytes: &[u8]) -> Result<()> {
+        Self::write(&self.blob_path(digest)?, bytes)
+    }
+
+    fn get_blob(&self, digest: &str) -> Result<Vec<u8>> {
+        let path = self.blob_path(digest)?;
+        std::fs::read(&path)
+            .map_err(|err| RuntimeError::Artifact(format!("blob {digest} unavailable: {err}")))
+    }
+
+    fn put_manifest(&self, reference: &str, bytes: &[u8]) -> Result<()> {
+        check_reference(reference)?;
+        let manifest_digest = digest(bytes);
+        self.put_blob(&manifest_digest, bytes)?;
+        Self::write(
+            &self.root.join("refs").join(reference),
+            manifest_digest.as_bytes(),
+        )
+    }
+
+    fn get_manifest(&self, reference: &str) -> Result<Vec<u8>> {
+        check_reference(reference)?;
+        if is_digest(reference) {
+            return self.get_blob(reference);
+        }
+        let target = std::fs::read_to_string(self.root.join("refs").join(reference))
+            .map_err(|_| RuntimeError::Artifact(format!("reference '{reference}' not found")))?;
+        self.get_blob(target.trim())
+    }
+}
+
+fn http_client() -> reqwest::blocking::Client {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(300))
+        .build()
+        .unwrap_or_else(|_| reqwest::blocking::Client::new())
+}
+
+fn send(
+    request: reqwest::blocking::RequestBuilder,
+    token: Option<&str>,
+) -> Result<reqwest::blocking::Response> {
+    let request = match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    };
+    request.send().map_err(artifact_error)
+}
+
+fn expect_success(
+    response: reqwest::blocking::Response,
+    what: &str,
+) -> Result<reqwest::blocking::Response> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(RuntimeError::Artifact(format!(
+            "{what} failed with HTTP {}",
+            response.status()
+        )))
+    }
+}
+
+fn body(response: reqwest::blocking::Response) -> Result<Vec<u8>> {
+    Ok(response.bytes().map_err(artifact_error)?.to_vec())
+}
+
+/// Registry speaking the OCI distribution API.
+pub struct OciRegistry {
+    base: String,
+    name: String,
+    token: Option<String>,
+    client: reqwest::blocking::Client,
+}
+
+impl OciRegistry {
+    /// Repository `name` on the registry at `base` (`https://host[:port]`).
+    pub fn new(base: impl Into<String>, name: impl Into<String>, token: Option<String>) -> Self {
+        Self {
+            base: base.into().trim_end_matches('/').to_string(),
+            name: name.into(),
+            token,
+            client: http_client(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/v2/{}/{}", self.base, self.name, path)
+    }
+}
+
+impl ArtifactStore for OciRegistry {
+    fn has_blob(&self, digest: &str) -> Result<bool> {
+        let response = send(
+            self.client.head(self.url(&format!("blobs/{digest}"))),
+            self.token.as_deref(),
+        )?;
+        Ok(response.status().is_success())
+    }
+
+    fn put_blob(&self, digest: &str, bytes: &[u8]) -> Result<()> {
+        // Monolithic upload: open a session, then complete it in one PUT
+        let response = send(
+            self.client.post(self.url("blobs/uploads/")),
+            self.token.as_deref(),
+        )?;
+        let response = expect_success(response, "starting blob upload")?;
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| RuntimeError::Artifact("upload session has no location".into()))?;
+        let location = if location.starts_with('/') {
+            format!("{}{}", self.base, location)
+        } else {
+            location.to_string()
+        };
+        let separator = if location.contains('?') { '&' } else { '?' };
+
+        let response = send(
+            self.client
+                .put(format!("{location}{separator}digest={digest}"))
+                .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                .body(bytes.to_vec()),
+            self.token.as_deref(),
+        )?;
+        expect_success(response, &format!("uploading blob {digest}"))?;
+        Ok(())
+    }
+
+    fn get_blob(&self, digest: &str) -> Result<Vec<u8>> {
+        let response = send(
+            self.client.get(self.url(&format!("blobs/{digest}"))),
+            self.token.as_deref(),
+        )?;
+        body(expect_success(
+            response,
+            &format!("fetching blob {digest}"),
+        )?)
+    }
+
+    fn put_manifest(&self, reference: &str, bytes: &[u8]) -> Result<()> {
+        let response = send(
+            self.client
+                .put(self.url(&format!("manifests/{reference}")))
+                .header(reqwest::header::CONTENT_TYPE, MANIFEST_MEDIA_TYPE)
+                .body(bytes.to_vec()),
+            self.token.as_deref(),
+        )?;
+        expect_success(response, &format!("pushing manifest {reference}"))?;
+        Ok(())
+    }
+
+    fn get_manifest(&self, reference: &str) -> Result<Vec<u8>> {
+        let response = send(
+            self.client
+                .get(self.url(&format!("manifests/{reference}")))
+                .header(reqwest::header::ACCEPT, MANIFEST_MEDIA_TYPE),
+            self.token.as_deref(),
+        )?;
+        body(expect_success(
+            response,
+            &format!("fetching manifest {reference}"),
+        )?)
+    }
+}
+
+/// Generic HTTP artifact store addressed by plain paths.
+pub struct HttpStore {
+    base: String,
+    token: Option<String>,
+    client: reqwest::blocking::Client,
+}
+
+impl HttpStore {
+    /// Store rooted at `base`.
+    pub fn new(base: impl Into<String>, token: Option<String>) -> Self {
+        Self {
+            base: base.into().trim_end_matches('/').to_string(),
+            token,
+            client: http_client(),
+        }
+    }
+}
+
+impl ArtifactStore for HttpStore {
+    fn has_blob(&self, digest: &str) -> Result<bool> {
+        let response = send(
+            self.client.head(format!("{}/blobs/{digest}", self.base)),
+            self.token.as_deref(),
+        )?;
+        Ok(response.status().is_success())
+    }
+
+    fn put_blob(&self, digest: &str, bytes: &[u8]) -> Result<()> {
+        let response = send(
+            self.client
+                .put(format!("{}/blobs/{digest}", self.base))
+                .body(bytes.to_vec()),
+            self.token.as_deref(),
+        )?;
+        expect_success(response, &format!("uploading blob {digest}"))?;
+        Ok(())
+    }
+
+    fn get_blob(&self, digest: &str) -> Result<Vec<u8>> {
+        let response = send(
+            self.client.get(format!("{}/blobs/{digest}", self.base)),
+            self.token.as_deref(),
+        )?;
+        body(expect_success(
+            response,
+            &format!("fetching blob {digest}"),
+        )?)
+    }
+
+    fn put_manifest(&self, reference: &str, bytes: &[u8]) -> Result<()> {
+        let response = send(
+            self.client
+                .put(format!("{}/manifests/{reference}", self.base))
+                .header(reqwest::header::CONTENT_TYPE, MANIFEST_MEDIA_TYPE)
+                .body(bytes.to_vec()),
+            self.token.as_deref(),
+        )?;
+        expect_success(response, &format!("pushing manifest {reference}"))?;
+        Ok(())
+    }
+
+    fn get_manifest(&self, reference: &str) -> Result<Vec<u8>> {
+        let response = send(
+            self.client
+                .get(format!("{}/manifests/{reference}", self.base)),
+            self.token.as_deref(),
+        )?;
+        body(expect_success(
+            response,
+            &format!("fetching manifest {reference}"),
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serve the subset of the OCI distribution API used by [`OciRegistry`],
+    /// returning the registry's base URL.
+    fn serve_registry() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let mut blobs: HashMap<String, Vec<u8>> = HashMap::new();
+            let mut manifests: HashMap<String, Vec<u8>> = HashMap::new();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut parts = line.split_whitespace();
+                let (method, target) = (parts.next().unwrap(), parts.next().unwrap());
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+
+                let path = target.strip_prefix("/v2/team/session/").unwrap();
+                let (status, location, payload) = match (method, path) {
+                    ("POST", "blobs/uploads/") => {
+                        (202, Some("/v2/team/session/blobs/uploads/1?state=x"), None)
+                    }
+                    ("PUT", upload) if upload.starts_with("blobs/uploads/1?state=x&digest=") => {
+                        let expected = upload.rsplit("digest=").next().unwrap().to_string();
+                        if digest(&body) == expected {
+                            blobs.insert(expected, body);
+                            (201, None, None)
+                        } else {
+                            (400, None, None)
+                        }
+                    }
+                    ("PUT", manifest) => {
+                        manifests.insert(manifest.to_string(), body);
+                        (201, None, None)
+                    }
+                    (_, blob) if blob.starts_with("blobs/") => match blobs.get(&blob[6..]) {
+                        Some(bytes) => (200, None, (method == "GET").then(|| bytes.clone())),
+                        None => (404, None, None),
+                    },
+                    (_, manifest) => match manifests.get(manifest) {
+                        Some(bytes) => (200, None, Some(bytes.clone())),
+                        None => (404, None, None),
+                    },
+                };
+
+                let payload = payload.unwrap_or_default();
+                let mut response = format!(
+                    "HTTP/1.1 {status} X\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    payload.len()
+                );
+                if let Some(location) = location {
+                    response.push_str(&format!("Location: {location}\r\n"));
+                }
+                response.push_str("\r\n");
+                stream.write_all(response.as_bytes()).unwrap();
+                stream.write_all(&payload).unwrap();
+            }
+        });
+        base
+    }
+
+    #[test]
+    fn checkpoints_round_trip_through_an_oci_registry() {
+        let base = serve_registry();
+        let store = open_store(&format!("oci+http://{}/team/session", &base[7..]), None).unwrap();
+        let checkpoint = Checkpoint {
+            config: CheckpointConfig {
+                branch: BranchId::main(),
+                head: TurnId::genesis(),
+                turns: 0,
+                snapshot_turn: None,
+                version: VersionStamp {
+                    runtime: "test".into(),
+                    registry: "none".into(),
+                },
+                created_at: Utc::now(),
+            },
+            records: Vec::new(),
+            snapshot: None,
+            entities: Vec::new(),
+        };
+
+        let first = push(store.as_ref(), &checkpoint, "v1").unwrap();
+        assert_eq!((first.uploaded, first.reused), (3, 0));
+        let second = push(store.as_ref(), &checkpoint, "v2").unwrap();
+        assert_eq!((second.uploaded, second.reused), (0, 3));
+        assert_eq!(first.manifest_digest, second.manifest_digest);
+
+        let pulled = pull(store.as_ref(), "v2").unwrap();
+        assert_eq!(pulled.config, checkpoint.config);
+        assert!(pulled.records.is_empty() && pulled.snapshot.is_none());
+        assert!(pull(store.as_ref(), "missing").is_err());
+    }
+
+    #[test]
+    fn references_and_digests_are_validated() {
+        assert!(check_reference("v1.2-rc_3").is_ok());
+        assert!(check_reference(&digest(b"manifest")).is_ok());
+        assert!(check_reference("../escape").is_err());
+        assert!(check_reference("").is_err());
+        assert!(check_reference("sha256:abc").is_err());
+        assert_eq!(
+            digest(b""),
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn directory_store_resolves_tags_and_rejects_corrupt_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DirectoryStore::new(dir.path());
+        let layer = b"layer".to_vec();
+        let desc = Descriptor::of(JOURNAL_MEDIA_TYPE, &layer);
+
+        assert!(!store.has_blob(&desc.digest).unwrap());
+        store.put_blob(&desc.digest, &layer).unwrap();
+        assert!(store.has_blob(&desc.digest).unwrap());
+        store.put_manifest("latest", b"{}").unwrap();
+        assert_eq!(store.get_manifest("latest").unwrap(), b"{}");
+        assert_eq!(store.get_manifest(&digest(b"{}")).unwrap(), b"{}");
+
+        std::fs::write(
+            dir.path().join("blobs/sha256").join(&desc.digest[7..]),
+            b"tampered",
+        )
+        .unwrap();
+        let tampered = store.get_blob(&desc.digest).unwrap();
+        assert!(verify(&desc, &tampered).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Online backups of the storage root
+//!
+//! [`Runtime::backup`](super::Runtime::backup) copies a live `.duet` directory
+//! between two turns: buffered journal writes and in-memory metadata are
+//! flushed first, then the tree is mirrored into the destination. Files that
+//! never change once written — sealed journal segments, snapshots and blobs —
+//! are hard-linked when the destination is on the same filesystem, so the copy
+//! stays short and the runtime resumes turns right away. Everything else,
+//! including the segment still being appended to, is copied. A
+//! [`BackupManifest`] describing the result is written alongside the files.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::turn::{BranchId, TurnId};
+use super::version::VersionStamp;
+
+/// Name of the manifest written at the top of every backup.
+pub const MANIFEST_FILE: &str = "backup-manifest.json";
+
+/// How a file ended up in the backup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupMethod {
+    /// Hard link to the immutable original
+    Linked,
+    /// Byte copy
+    Copied,
+}
+
+/// One file in a backup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupFile {
+    /// Path relative to the backup root
+    pub path: PathBuf,
+    /// Size in bytes
+    pub bytes: u64,
+    /// Whether the file was linked or copied
+    pub method: BackupMethod,
+}
+
+/// Description of a finished backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// When the backup was taken
+    pub created_at: DateTime<Utc>,
+    /// Storage root that was backed up
+    pub source: PathBuf,
+    /// Directory holding the backup
+    pub dest: PathBuf,
+    /// Runtime version and registry fingerprint at backup time
+    pub version: VersionStamp,
+    /// Branch that was active
+    pub current_branch: BranchId,
+    /// Head of every branch at backup time
+    pub heads: BTreeMap<BranchId, TurnId>,
+    /// Files in the backup, in path order
+    pub files: Vec<BackupFile>,
+    /// Bytes shared with the source through hard links
+    pub linked_bytes: u64,
+    /// Bytes copied
+    pub copied_bytes: u64,
+}
+
+/// Mirror `source` into the empty directory `dest`.
+///
+/// Leftover `*.tmp` files from interrupted atomic writes are skipped.
+pub(crate) fn mirror(source: &Path, dest: &Path) -> io::Result<Vec<BackupFile>> {
+    let mut files = Vec::new();
+    mirror_dir(source, dest, Path::new(""), &mut files)?;
+    Ok(files)
+}
+
+fn mirror_dir(
+    source: &Path,
+    dest: &Path,
+    relative: &Path,
+    files: &mut Vec<BackupFile>,
+) -> io::Result<()> {
+    fs::create_dir_all(dest.join(relative))?;
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(source.join(relative))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    let active_segment = entries
+        .iter()
+        .filter(|path| is_segment(path))
+        .max()
+        .cloned();
+
+    for path in entries {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        let relative = relative.join(name);
+        let file_type = fs::symlink_metadata(&path)?.file_type();
+        if file_type.is_dir() {
+            mirror_dir(source, dest, &relative, files)?;
+            continue;
+        }
+        if !file_type.is_file() || path.extension().is_some_and(|ext| ext == "tmp") {
+            continue;
+        }
+
+        let immutable = (is_segment(&path) && Some(&path) != active_segment.as_ref())
+            || path
+                .extension()
+                .is_some_and(|ext| ext == "snapshot" || ext == "blob");
+        let target = dest.join(&relative);
+        let method = if immutable && fs::hard_link(&path, &target).is_ok() {
+            BackupMethod::Linked
+        } else {
+            fs::copy(&path, &target)?;
+            BackupMethod::Copied
+        };
+        files.push(BackupFile {
+            bytes: fs::metadata(&target)?.len(),
+            path: relative,
+            method,
+        });
+    }
+    Ok(())
+}
+
+/// Whether `path` is a journal segment (`segment-NNNNNN.turnlog`).
+fn is_segment(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("segment-") && name.ends_with(".turnlog"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_mirror_links_sealed_segments_and_copies_the_active_one() {
+        let source = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        let journal = source.path().join("journal/main");
+        fs::create_dir_all(&journal).unwrap();
+        fs::write(journal.join("segment-000000.turnlog"), b"sealed").unwrap();
+        fs::write(journal.join("segment-000001.turnlog"), b"active").unwrap();
+        fs::write(source.path().join("config.json"), b"{}").unwrap();
+        fs::write(source.path().join("config.tmp"), b"partial").unwrap();
+
+        let files = mirror(source.path(), dest.path()).unwrap();
+        let methods: Vec<(String, BackupMethod)> = files
+            .iter()
+            .map(|file| (file.path.display().to_string(), file.method))
+            .collect();
+        assert_eq!(
+            methods,
+            vec![
+                ("config.json".to_string(), BackupMethod::Copied),
+                (
+                    "journal/main/segment-000000.turnlog".to_string(),
+                    BackupMethod::Linked
+                ),
+                (
+                    "journal/main/segment-000001.turnlog".to_string(),
+                    BackupMethod::Copied
+                ),
+            ]
+        );
+
+        // Appends to the live segment do not leak into the backup
+        fs::write(journal.join("segment-000001.turnlog"), b"active+more").unwrap();
+        assert_eq!(
+            fs::read(dest.path().join("journal/main/segment-000001.turnlog")).unwrap(),
+            b"active"
+        );
+    }
+}
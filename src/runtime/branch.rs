@@ -3,11 +3,15 @@
 //! Tracks branch relationships, implements fork/rewind/goto operations,
 //! and orchestrates CRDT-based merges.
 
+use chrono::{DateTime, Utc};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use super::error::{BranchError, BranchResult};
-use super::turn::{BranchId, TurnId};
+use super::identity::Identity;
+use super::state::AssertionValue;
+use super::turn::{ActorId, BranchId, Handle, TurnId};
 
 /// Branch metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +30,154 @@ pub struct BranchMetadata {
 
     /// Current snapshot (if any)
     pub snapshot: Option<TurnId>,
+
+    /// Description, creator and tags given when the branch was created
+    #[serde(default)]
+    pub details: BranchDetails,
+
+    /// When the branch was created (unknown for branches created before this was recorded)
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+
+    /// Control-plane client identity that forked the branch
+    #[serde(default)]
+    pub created_by: Option<Identity>,
+
+    /// Last turn dropped by journal compaction; the journal starts after it
+    #[serde(default)]
+    pub compacted_through: Option<TurnId>,
+}
+
+/// Descriptive metadata supplied when forking a branch
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchDetails {
+    /// What the branch is for
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Who or what created the branch (a user, an agent, a request id, ...)
+    #[serde(default)]
+    pub creator: Option<String>,
+    /// Purpose tags such as `experiment` or `review`
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Naming templates and policy for new branches
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchNamingConfig {
+    /// Named templates with `{placeholder}`s, e.g. `agent` -> `agent/{request_id}`
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
+    /// Regular expression every new branch name must match in full
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+impl BranchNamingConfig {
+    /// Render the template called `template`, filling placeholders from `vars`.
+    pub fn render(
+        &self,
+        template: &str,
+        vars: &BTreeMap<String, String>,
+    ) -> BranchResult<BranchId> {
+        let text = self.templates.get(template).ok_or_else(|| {
+            BranchError::InvalidName(format!("unknown naming template '{}'", template))
+        })?;
+
+        let placeholder = Regex::new(r"\{([A-Za-z0-9_-]+)\}").expect("valid placeholder regex");
+        let mut missing = Vec::new();
+        let name = placeholder.replace_all(text, |caps: &regex::Captures<'_>| {
+            vars.get(&caps[1]).cloned().unwrap_or_else(|| {
+                missing.push(caps[1].to_string());
+                String::new()
+            })
+        });
+        if !missing.is_empty() {
+            return Err(BranchError::InvalidName(format!(
+                "template '{}' needs {}",
+                template,
+                missing.join(", ")
+            )));
+        }
+
+        let branch = BranchId::new(name.into_owned());
+        self.check(&branch)?;
+        Ok(branch)
+    }
+
+    /// Check `branch` against the naming policy.
+    pub fn check(&self, branch: &BranchId) -> BranchResult<()> {
+        if branch.0.trim().is_empty() {
+            return Err(BranchError::InvalidName(
+                "branch names cannot be empty".into(),
+            ));
+        }
+        if branch.is_reserved() {
+            return Err(BranchError::InvalidName(format!(
+                "'{}' uses the '__' prefix reserved for runtime branches",
+                branch
+            )));
+        }
+        // Names become directories under the storage root, so every `/`
+        // separated part must be a plain file name
+        if branch.0.contains(['\\', ':']) || branch.0.chars().any(char::is_control) {
+            return Err(BranchError::InvalidName(format!(
+                "'{}' contains a path separator or control character",
+                branch.0.escape_debug()
+            )));
+        }
+        if branch
+            .0
+            .split('/')
+            .any(|part| matches!(part, "" | "." | ".."))
+        {
+            return Err(BranchError::InvalidName(format!(
+                "'{}' has an empty, '.' or '..' path component",
+                branch
+            )));
+        }
+        let Some(pattern) = &self.pattern else {
+            return Ok(());
+        };
+        let regex = Regex::new(&format!("^(?:{})$", pattern)).map_err(|err| {
+            BranchError::InvalidName(format!("invalid naming pattern '{}': {}", pattern, err))
+        })?;
+        if regex.is_match(&branch.0) {
+            Ok(())
+        } else {
+            Err(BranchError::InvalidName(format!(
+                "'{}' does not match naming pattern '{}'",
+                branch, pattern
+            )))
+        }
+    }
+}
+
+/// Tombstone left in branch state for a branch moved to cold storage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedBranch {
+    /// Archived branch
+    pub id: BranchId,
+    /// Branch it was forked from
+    pub parent: Option<BranchId>,
+    /// Turn it was forked at
+    pub base_turn: Option<TurnId>,
+    /// Head when archived
+    pub head_turn: TurnId,
+    /// Description, creator and tags
+    pub details: BranchDetails,
+    /// When the branch was created
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the branch was archived
+    pub archived_at: DateTime<Utc>,
+    /// Directory artifact store holding the archive
+    pub location: String,
+    /// Digest of the archive's checkpoint manifest
+    pub manifest_digest: String,
+    /// Turn records in the archive (ancestor turns included)
+    pub turns: usize,
+    /// Size of the archive in bytes
+    pub size: u64,
 }
 
 /// Serializable branch state used for persistence
@@ -35,6 +187,9 @@ pub struct BranchState {
     pub branches: Vec<BranchMetadata>,
     /// Active branch identifier
     pub active: BranchId,
+    /// Tombstones of archived branches
+    #[serde(default)]
+    pub archived: Vec<ArchivedBranch>,
 }
 
 /// Branch manager
@@ -44,6 +199,9 @@ pub struct BranchManager {
 
     /// Active branch
     active_branch: BranchId,
+
+    /// Archived branches by name
+    archived: BTreeMap<BranchId, ArchivedBranch>,
 }
 
 impl BranchManager {
@@ -69,7 +227,7 @@ impl BranchManager {
         new_branch: BranchId,
         base_turn: TurnId,
     ) -> BranchResult<()> {
-        if self.branches.contains_key(&new_branch) {
+        if self.branches.contains_key(&new_branch) || self.archived.contains_key(&new_branch) {
             return Err(BranchError::AlreadyExists(new_branch.0.clone()));
         }
 
@@ -84,6 +242,10 @@ impl BranchManager {
             base_turn: Some(base_turn.clone()),
             head_turn: base_turn,
             snapshot: source_metadata.snapshot.clone(),
+            details: BranchDetails::default(),
+            created_at: Some(Utc::now()),
+            created_by: None,
+            compacted_through: None,
         };
 
         self.branches.insert(new_branch, metadata);
@@ -91,6 +253,47 @@ impl BranchManager {
         Ok(())
     }
 
+    /// Create a parentless branch starting from genesis unless it already exists
+    pub fn ensure_root(&mut self, branch: BranchId) {
+        self.branches
+            .entry(branch.clone())
+            .or_insert_with(|| BranchMetadata {
+                id: branch,
+                parent: None,
+                base_turn: None,
+                head_turn: TurnId::genesis(),
+                snapshot: None,
+                details: BranchDetails::default(),
+                created_at: Some(Utc::now()),
+                created_by: None,
+                compacted_through: None,
+            });
+    }
+
+    /// Replace the descriptive details of a branch
+    pub fn describe(&mut self, branch: &BranchId, details: BranchDetails) -> BranchResult<()> {
+        let metadata = self
+            .branches
+            .get_mut(branch)
+            .ok_or_else(|| BranchError::NotFound(branch.0.clone()))?;
+        metadata.details = details;
+        Ok(())
+    }
+
+    /// Record the client identity that created a branch
+    pub fn set_created_by(
+        &mut self,
+        branch: &BranchId,
+        identity: Option<Identity>,
+    ) -> BranchResult<()> {
+        let metadata = self
+            .branches
+            .get_mut(branch)
+            .ok_or_else(|| BranchError::NotFound(branch.0.clone()))?;
+        metadata.created_by = identity;
+        Ok(())
+    }
+
     /// Switch to a different branch
     pub fn switch_branch(&mut self, branch: BranchId) -> BranchResult<()> {
         if !self.branches.contains_key(&branch) {
@@ -112,6 +315,17 @@ impl BranchManager {
         Ok(())
     }
 
+    /// Record that `branch`'s journal was compacted through `horizon`
+    pub fn mark_compacted(&mut self, branch: &BranchId, horizon: TurnId) -> BranchResult<()> {
+        let metadata = self
+            .branches
+            .get_mut(branch)
+            .ok_or_else(|| BranchError::NotFound(branch.0.clone()))?;
+
+        metadata.compacted_through = Some(horizon);
+        Ok(())
+    }
+
     /// Get the head turn for a branch
     pub fn head(&self, branch: &BranchId) -> Option<&TurnId> {
         self.branches.get(branch).map(|m| &m.head_turn)
@@ -119,52 +333,35 @@ impl BranchManager {
 
     /// Find the lowest common ancestor of two branches
     ///
-    /// Traces ancestry back from both branches to find their common fork point.
-    /// Returns the base_turn of the most recent common ancestor.
+    /// Each branch sees its own history up to its head plus each ancestor's
+    /// history up to the fork point. The LCA is the earlier of the two limits
+    /// on the nearest branch both lineages share, so a branch compared with
+    /// the branch it was forked from yields its fork point.
     pub fn find_lca(&self, branch_a: &BranchId, branch_b: &BranchId) -> Option<TurnId> {
-        // Build ancestry path for branch A
-        let mut ancestry_a = HashMap::new();
-        let mut current = branch_a.clone();
-
-        loop {
-            let metadata = self.branches.get(&current)?;
-
-            if let Some(base_turn) = &metadata.base_turn {
-                ancestry_a.insert(current.clone(), base_turn.clone());
-            }
+        let lineage_a = self.lineage(branch_a)?;
+        let lineage_b = self.lineage(branch_b)?;
 
-            match &metadata.parent {
-                Some(parent) => current = parent.clone(),
-                None => break, // Reached root (main branch)
+        for (branch, limit_b) in &lineage_b {
+            if let Some((_, limit_a)) = lineage_a.iter().find(|(shared, _)| shared == branch) {
+                return Some(limit_a.clone().min(limit_b.clone()));
             }
         }
 
-        // Trace branch B ancestry and find first common point
-        let mut current = branch_b.clone();
-
-        loop {
-            let metadata = self.branches.get(&current)?;
-
-            // Check if this branch is in A's ancestry
-            if let Some(base_turn) = ancestry_a.get(&current) {
-                return Some(base_turn.clone());
-            }
-
-            // Check if B's base turn is in A's ancestry
-            if let Some(base_turn) = &metadata.base_turn {
-                if ancestry_a.values().any(|turn| turn == base_turn) {
-                    return Some(base_turn.clone());
-                }
-            }
+        // If branches share no common ancestry, they diverged from the empty history
+        Some(TurnId::genesis())
+    }
 
-            match &metadata.parent {
-                Some(parent) => current = parent.clone(),
-                None => break,
-            }
+    /// Branches whose history `branch` sees, nearest first, each with the
+    /// last turn visible from `branch`
+    fn lineage(&self, branch: &BranchId) -> Option<Vec<(BranchId, TurnId)>> {
+        let metadata = self.branches.get(branch)?;
+        let mut lineage = vec![(branch.clone(), metadata.head_turn.clone())];
+        let mut current = metadata;
+        while let (Some(parent), Some(base_turn)) = (&current.parent, &current.base_turn) {
+            lineage.push((parent.clone(), base_turn.clone()));
+            current = self.branches.get(parent)?;
         }
-
-        // If branches share no common ancestry, they diverged from main at turn 0
-        Some(TurnId::new("turn_00000000".to_string()))
+        Some(lineage)
     }
 
     /// Merge two branches using CRDT join
@@ -173,16 +370,98 @@ impl BranchManager {
         unimplemented!("Branch merge not yet implemented")
     }
 
+    /// Replace turn ids in branch metadata according to `mapping`
+    pub fn remap_turns(&mut self, mapping: &HashMap<TurnId, TurnId>) {
+        let remap = |turn: &mut TurnId| {
+            if let Some(mapped) = mapping.get(turn) {
+                *turn = mapped.clone();
+            }
+        };
+
+        for metadata in self.branches.values_mut() {
+            remap(&mut metadata.head_turn);
+            if let Some(base) = metadata.base_turn.as_mut() {
+                remap(base);
+            }
+            if let Some(snapshot) = metadata.snapshot.as_mut() {
+                remap(snapshot);
+            }
+            if let Some(horizon) = metadata.compacted_through.as_mut() {
+                remap(horizon);
+            }
+        }
+    }
+
     /// List all branches
     pub fn list_branches(&self) -> Vec<&BranchMetadata> {
         self.branches.values().collect()
     }
 
+    /// Remove `branch` from active state, leaving `tombstone` in its place.
+    ///
+    /// The active branch and branches other branches were forked from cannot
+    /// be archived.
+    pub fn archive(&mut self, tombstone: ArchivedBranch) -> BranchResult<BranchMetadata> {
+        let branch = tombstone.id.clone();
+        if !self.branches.contains_key(&branch) {
+            return Err(BranchError::NotFound(branch.0.clone()));
+        }
+        if branch == self.active_branch {
+            return Err(BranchError::ArchiveRefused(format!(
+                "'{}' is the active branch",
+                branch
+            )));
+        }
+        if let Some(child) = self
+            .branches
+            .values()
+            .find(|metadata| metadata.parent.as_ref() == Some(&branch))
+        {
+            return Err(BranchError::ArchiveRefused(format!(
+                "'{}' was forked from '{}'",
+                child.id, branch
+            )));
+        }
+
+        self.archived.insert(branch.clone(), tombstone);
+        Ok(self.branches.remove(&branch).expect("branch checked above"))
+    }
+
+    /// Forget `branch` entirely, as for a discarded experiment branch.
+    ///
+    /// Callers make sure the branch is neither active nor forked from.
+    pub fn remove(&mut self, branch: &BranchId) -> BranchResult<BranchMetadata> {
+        self.branches
+            .remove(branch)
+            .ok_or_else(|| BranchError::NotFound(branch.0.clone()))
+    }
+
+    /// Tombstone of an archived branch
+    pub fn archived(&self, branch: &BranchId) -> Option<&ArchivedBranch> {
+        self.archived.get(branch)
+    }
+
+    /// Record `tombstone` without touching the active branches
+    pub fn insert_archived(&mut self, tombstone: ArchivedBranch) {
+        self.archived.insert(tombstone.id.clone(), tombstone);
+    }
+
+    /// Remove and return the tombstone of an archived branch
+    pub fn take_archived(&mut self, branch: &BranchId) -> Option<ArchivedBranch> {
+        self.archived.remove(branch)
+    }
+
+    /// Tombstones of all archived branches, by name
+    pub fn list_archived(&self) -> Vec<&ArchivedBranch> {
+        self.archived.values().collect()
+    }
+
     /// Return a serializable snapshot of branch state
     pub fn state(&self) -> BranchState {
         BranchState {
             branches: self.branches.values().cloned().collect(),
             active: self.active_branch.clone(),
+            archived: self.archived.values().cloned().collect(),
         }
     }
 
@@ -202,6 +481,11 @@ impl BranchManager {
         Self {
             branches,
             active_branch: active,
+            archived: state
+                .archived
+                .into_iter()
+                .map(|tombstone| (tombstone.id.clone(), tombstone))
+                .collect(),
         }
     }
 
@@ -212,13 +496,18 @@ impl BranchManager {
             id: main_branch.clone(),
             parent: None,
             base_turn: None,
-            head_turn: TurnId::new("turn_0".to_string()),
+            head_turn: TurnId::genesis(),
             snapshot: None,
+            details: BranchDetails::default(),
+            created_at: Some(Utc::now()),
+            created_by: None,
+            compacted_through: None,
         };
 
         BranchState {
             branches: vec![metadata],
             active: main_branch,
+            archived: Vec::new(),
         }
     }
 }
@@ -239,6 +528,48 @@ pub struct MergeResult {
     pub warnings: Vec<MergeWarning>,
 }
 
+/// Predicted outcome of a merge, computed without merging
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeForecast {
+    /// Lowest common ancestor of the two branches
+    pub lca: TurnId,
+
+    /// Warnings/conflicts the merge would report
+    pub warnings: Vec<MergeWarning>,
+
+    /// Assertions the merge would add to the target
+    pub added: Vec<ForecastAssertion>,
+
+    /// Assertions the merge would retract from the target
+    pub retracted: Vec<ForecastAssertion>,
+}
+
+/// Assertion a forecast merge would change on its target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForecastAssertion {
+    /// Asserting actor
+    pub actor: ActorId,
+
+    /// Assertion handle
+    pub handle: Handle,
+
+    /// Asserted value
+    pub value: AssertionValue,
+}
+
+/// Branch and turn that made a live assertion
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssertionOrigin {
+    /// Branch whose turn made the assertion
+    pub branch: BranchId,
+
+    /// Turn that made the assertion
+    pub turn: TurnId,
+
+    /// Merge turn that brought the assertion onto this branch, if any
+    pub merge_turn: Option<TurnId>,
+}
+
 /// Warning about a merge conflict or issue
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MergeWarning {
@@ -256,6 +587,61 @@ pub struct MergeWarning {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_naming_templates_and_policy() {
+        let naming = BranchNamingConfig {
+            templates: [("review".to_string(), "review/{pr}-{step}".to_string())].into(),
+            pattern: Some("main|review/[0-9]+-[a-z]+".to_string()),
+        };
+        let vars: BTreeMap<String, String> = [
+            ("pr".to_string(), "17".to_string()),
+            ("step".to_string(), "lint".to_string()),
+        ]
+        .into();
+
+        assert_eq!(
+            naming.render("review", &vars).unwrap(),
+            BranchId::new("review/17-lint")
+        );
+        assert!(matches!(
+            naming.render("review", &BTreeMap::new()),
+            Err(BranchError::InvalidName(message)) if message.contains("pr, step")
+        ));
+        assert!(naming.render("missing", &vars).is_err());
+        // The pattern must match the whole name
+        assert!(
+            naming
+                .check(&BranchId::new("review/17-lint-extra1"))
+                .is_err()
+        );
+        assert!(naming.check(&BranchId::new("")).is_err());
+        assert!(
+            BranchNamingConfig::default()
+                .check(&BranchId::new("anything/goes"))
+                .is_ok()
+        );
+        // No policy lets a name leave its storage directory
+        for name in [
+            "..",
+            ".",
+            "../main",
+            "a/../b",
+            "/abs",
+            "trailing/",
+            "a//b",
+            "a\\b",
+            "c:x",
+            "a\nb",
+        ] {
+            assert!(
+                BranchNamingConfig::default()
+                    .check(&BranchId::new(name))
+                    .is_err(),
+                "{name:?} should be rejected"
+            );
+        }
+    }
+
     #[test]
     fn test_branch_manager_creation() {
         let manager = BranchManager::new();
@@ -331,6 +717,23 @@ mod tests {
         assert_eq!(lca, Some(turn_20));
     }
 
+    #[test]
+    fn test_find_lca_with_parent_is_fork_point() {
+        let mut manager = BranchManager::new();
+        let main = BranchId::main();
+        let branch_a = BranchId::new("branch-a");
+
+        let turn_10 = TurnId::new("turn_10".to_string());
+        let turn_30 = TurnId::new("turn_30".to_string());
+        manager.update_head(&main, turn_30).unwrap();
+        manager
+            .fork(&main, branch_a.clone(), turn_10.clone())
+            .unwrap();
+
+        assert_eq!(manager.find_lca(&main, &branch_a), Some(turn_10.clone()));
+        assert_eq!(manager.find_lca(&branch_a, &main), Some(turn_10));
+    }
+
     #[test]
     fn test_find_lca_same_branch() {
         let mut manager = BranchManager::new();
@@ -0,0 +1,44 @@
+//! Broadcast addressing for actor groups
+//!
+//! Actors can be collected into named groups (see
+//! [`EntityManager::add_to_group`](super::registry::EntityManager::add_to_group)).
+//! A broadcast fans one payload out as a message turn per recipient facet:
+//! every facet of a member actor that hosts entities, or the actor's root
+//! facet if it hosts none. Each fanned-out input carries the broadcast id, and
+//! the broadcast itself is logged once per branch so the recipients of a
+//! logical broadcast can be traced back to it.
+
+use chrono::{DateTime, Utc};
+use preserves::IOValue;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::turn::{ActorId, FacetId};
+
+/// Identifier of a broadcast.
+pub type BroadcastId = Uuid;
+
+/// Facet a broadcast was delivered to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BroadcastRecipient {
+    /// Member actor
+    pub actor: ActorId,
+    /// Facet that received the message
+    pub facet: FacetId,
+}
+
+/// A single logical broadcast and its fan-out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BroadcastRecord {
+    /// Identifier carried by every fanned-out input
+    pub id: BroadcastId,
+    /// Group the broadcast was addressed to
+    pub group: String,
+    /// Payload delivered to each recipient
+    #[serde(with = "super::registry::preserves_text_serde")]
+    pub payload: IOValue,
+    /// Facets a message turn was scheduled for
+    pub recipients: Vec<BroadcastRecipient>,
+    /// When the broadcast was sent
+    pub sent_at: DateTime<Utc>,
+}
@@ -0,0 +1,278 @@
+//! Dictionary-trained journal compression
+//!
+//! Turn records repeat a lot of structure: agent transcripts, workspace
+//! entries and assertion labels recur across thousands of turns, but each
+//! record is too small for generic compression to find the repetition. A zstd
+//! dictionary trained on a sample of existing records captures it once.
+//!
+//! Compression is off until a dictionary is trained with
+//! [`Runtime::train_journal_dictionary`](super::Runtime::train_journal_dictionary).
+//! Dictionaries live under `meta/journal-dictionaries/` as `<id>.zdict` next to
+//! a `<id>.json` description, and the `active` file names the one new records
+//! are compressed with. Dictionaries are never deleted: records compressed
+//! with them stay in the journal.
+//!
+//! Compressed records keep the segment framing (a 4-byte length prefix) and
+//! hold a zstd frame in place of the packed Preserves bytes. Each frame names
+//! its dictionary, so segments need no extra metadata and records written
+//! before compression was enabled stay readable.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
+
+use super::error::{JournalError, JournalResult};
+use super::storage::Storage;
+
+/// Directory under `meta/` holding trained dictionaries
+const DICTIONARY_DIR: &str = "journal-dictionaries";
+
+/// File naming the dictionary new records are compressed with
+const ACTIVE_FILE: &str = "active";
+
+/// First bytes of a zstd frame (packed Preserves records start with `0xB4`)
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Prepared decoder dictionaries, keyed by file, shared by all readers
+static DECODERS: Lazy<RwLock<HashMap<PathBuf, Arc<DecoderDictionary<'static>>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Parameters for [`train`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrainingOptions {
+    /// Upper bound on the dictionary size in bytes
+    pub max_dictionary_size: usize,
+    /// Number of most recent records to sample
+    pub max_samples: usize,
+    /// zstd compression level used with the dictionary
+    pub level: i32,
+}
+
+impl Default for TrainingOptions {
+    fn default() -> Self {
+        Self {
+            max_dictionary_size: 110 * 1024,
+            max_samples: 4096,
+            level: 3,
+        }
+    }
+}
+
+/// Description of a trained dictionary, stored next to it in `meta`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DictionaryInfo {
+    /// zstd dictionary id, recorded in every frame compressed with it
+    pub id: u32,
+    /// Dictionary size in bytes
+    pub size: usize,
+    /// Compression level used with the dictionary
+    pub level: i32,
+    /// Number of records sampled
+    pub samples: usize,
+    /// Uncompressed size of the sample
+    pub sample_bytes: usize,
+    /// Sample size under plain zstd at the same level
+    pub generic_bytes: usize,
+    /// Sample size when compressed with the dictionary
+    pub dictionary_bytes: usize,
+    /// When the dictionary was trained
+    pub trained_at: DateTime<Utc>,
+}
+
+/// A trained dictionary prepared for compressing journal records
+pub struct JournalDictionary {
+    info: DictionaryInfo,
+    encoder: EncoderDictionary<'static>,
+}
+
+impl std::fmt::Debug for JournalDictionary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JournalDictionary")
+            .field("info", &self.info)
+            .finish_non_exhaustive()
+    }
+}
+
+impl JournalDictionary {
+    fn new(info: DictionaryInfo, bytes: &[u8]) -> Self {
+        let encoder = EncoderDictionary::copy(bytes, info.level);
+        Self { info, encoder }
+    }
+
+    /// Description of the dictionary
+    pub fn info(&self) -> &DictionaryInfo {
+        &self.info
+    }
+
+    /// Compress a packed record, or `None` when that would not make it smaller
+    pub(crate) fn compress(&self, packed: &[u8]) -> JournalResult<Option<Vec<u8>>> {
+        let compressed = zstd::bulk::Compressor::with_prepared_dictionary(&self.encoder)
+            .and_then(|mut compressor| compressor.compress(packed))
+            .map_err(|e| JournalError::Compression(e.to_string()))?;
+        Ok((compressed.len() < packed.len()).then_some(compressed))
+    }
+}
+
+/// Train a dictionary on packed record `samples`.
+///
+/// The returned info reports the sample's size uncompressed, under plain
+/// zstd, and with the new dictionary.
+pub fn train(
+    samples: &[Vec<u8>],
+    options: &TrainingOptions,
+) -> JournalResult<(DictionaryInfo, Vec<u8>)> {
+    let bytes = zstd::dict::from_samples(samples, options.max_dictionary_size)
+        .map_err(|e| JournalError::Compression(format!("dictionary training failed: {e}")))?;
+    let id = zstd::zstd_safe::get_dict_id_from_dict(&bytes)
+        .ok_or_else(|| JournalError::Compression("trained dictionary has no id".into()))?
+        .get();
+
+    let dictionary = JournalDictionary::new(
+        DictionaryInfo {
+            id,
+            size: bytes.len(),
+            level: options.level,
+            samples: samples.len(),
+            sample_bytes: 0,
+            generic_bytes: 0,
+            dictionary_bytes: 0,
+            trained_at: Utc::now(),
+        },
+        &bytes,
+    );
+    let mut info = dictionary.info.clone();
+    for sample in samples {
+        info.sample_bytes += sample.len();
+        info.generic_bytes += zstd::bulk::compress(sample, options.level)
+            .map_err(|e| JournalError::Compression(e.to_string()))?
+            .len();
+        info.dictionary_bytes += dictionary
+            .compress(sample)?
+            .map_or(sample.len(), |compressed| compressed.len());
+    }
+    Ok((info, bytes))
+}
+
+/// Store a trained dictionary in `meta` and make it the active one.
+pub fn install(
+    storage: &Storage,
+    info: DictionaryInfo,
+    bytes: &[u8],
+) -> JournalResult<Arc<JournalDictionary>> {
+    let dir = dictionary_dir(storage);
+    std::fs::create_dir_all(&dir)?;
+    let description =
+        serde_json::to_vec_pretty(&info).map_err(|e| JournalError::EncodingError(e.to_string()))?;
+    write_durably(&dir.join(format!("{}.zdict", info.id)), bytes)?;
+    write_durably(&dir.join(format!("{}.json", info.id)), &description)?;
+    write_durably(&dir.join(ACTIVE_FILE), info.id.to_string().as_bytes())?;
+    Ok(Arc::new(JournalDictionary::new(info, bytes)))
+}
+
+/// Stop compressing new records. Stored dictionaries are kept for reading.
+pub fn deactivate(storage: &Storage) -> JournalResult<()> {
+    let path = dictionary_dir(storage).join(ACTIVE_FILE);
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The dictionary new records are compressed with, if compression is enabled.
+pub fn active(storage: &Storage) -> JournalResult<Option<Arc<JournalDictionary>>> {
+    let dir = dictionary_dir(storage);
+    let active = dir.join(ACTIVE_FILE);
+    if !active.exists() {
+        return Ok(None);
+    }
+    let id = std::fs::read_to_string(&active)?;
+    let id = id.trim();
+    let description = std::fs::read(dir.join(format!("{id}.json")))?;
+    let info: DictionaryInfo = serde_json::from_slice(&description)
+        .map_err(|e| JournalError::Compression(format!("unreadable dictionary {id}: {e}")))?;
+    let bytes = std::fs::read(dir.join(format!("{id}.zdict")))?;
+    Ok(Some(Arc::new(JournalDictionary::new(info, &bytes))))
+}
+
+/// Packed Preserves bytes of a record read from a segment.
+///
+/// Uncompressed records are returned as they are.
+pub(crate) fn decode(storage: &Storage, data: Vec<u8>) -> JournalResult<Vec<u8>> {
+    if !data.starts_with(&ZSTD_MAGIC) {
+        return Ok(data);
+    }
+    let id = zstd::zstd_safe::get_dict_id_from_frame(&data)
+        .ok_or_else(|| JournalError::DecodingError("compressed record has no dictionary".into()))?;
+    let dictionary = decoder(storage, id.get())?;
+    let mut decoder = zstd::stream::read::Decoder::with_prepared_dictionary(&data[..], &dictionary)
+        .map_err(|e| JournalError::Compression(e.to_string()))?;
+    let mut packed = Vec::new();
+    decoder
+        .read_to_end(&mut packed)
+        .map_err(|e| JournalError::DecodingError(format!("decompression failed: {e}")))?;
+    Ok(packed)
+}
+
+/// Load (or reuse) the decoder for dictionary `id`
+fn decoder(storage: &Storage, id: u32) -> JournalResult<Arc<DecoderDictionary<'static>>> {
+    let path = dictionary_dir(storage).join(format!("{id}.zdict"));
+    if let Some(dictionary) = DECODERS.read().get(&path) {
+        return Ok(dictionary.clone());
+    }
+    let bytes = std::fs::read(&path)
+        .map_err(|e| JournalError::Compression(format!("dictionary {id} unavailable: {e}")))?;
+    let dictionary = Arc::new(DecoderDictionary::copy(&bytes));
+    DECODERS.write().insert(path, dictionary.clone());
+    Ok(dictionary)
+}
+
+fn dictionary_dir(storage: &Storage) -> PathBuf {
+    storage.meta_dir().join(DICTIONARY_DIR)
+}
+
+/// Write `data` through a synced temporary file so readers never see a partial file
+fn write_durably(path: &std::path::Path, data: &[u8]) -> JournalResult<()> {
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, data)?;
+    std::fs::File::open(&temp_path)?.sync_all()?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dictionary_round_trips_and_beats_generic_compression() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let samples: Vec<Vec<u8>> = (0..500)
+            .map(|i| {
+                format!(
+                    "<agent-response \"claude-code\" \"req-{i}\" \"Please show me READ src/lib.rs\" \
+                     \"Here is the summary of the workspace entry {i} you asked about\">"
+                )
+                .into_bytes()
+            })
+            .collect();
+
+        let (info, bytes) = train(&samples, &TrainingOptions::default()).unwrap();
+        assert!(info.dictionary_bytes < info.generic_bytes);
+
+        let dictionary = install(&storage, info.clone(), &bytes).unwrap();
+        assert_eq!(active(&storage).unwrap().unwrap().info(), &info);
+        let compressed = dictionary.compress(&samples[7]).unwrap().unwrap();
+        assert_eq!(decode(&storage, compressed).unwrap(), samples[7]);
+        assert_eq!(decode(&storage, samples[8].clone()).unwrap(), samples[8]);
+
+        deactivate(&storage).unwrap();
+        assert!(active(&storage).unwrap().is_none());
+    }
+}
@@ -0,0 +1,227 @@
+//! Configuration history on the reserved `__config` branch
+//!
+//! Entity registrations, pattern subscriptions and reaction definitions live
+//! in JSON side files under `meta/`, which only hold the latest state and are
+//! invisible to time travel. Every change made through [`Control`] is
+//! therefore also journaled as a turn on the reserved [`BranchId::config`]
+//! branch. Each turn carries a single [`TurnInput::ConfigChange`] naming the
+//! working branch the change was made on, so the configuration in effect at
+//! any point can be rebuilt with [`ConfigState::replay`]. The branch cannot
+//! be switched to or forked by name; it is read through its journal.
+//!
+//! The journal is the source of truth and the side files are a cache of it.
+//! Each change is also anchored to the head of the working branch at the
+//! time, so time travel rolls back exactly the changes made on the current
+//! branch after the target turn (see [`in_effect_at`]). Changes made on other
+//! branches stay in effect.
+//!
+//! [`Control`]: super::control::Control
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+use super::pattern::Pattern;
+use super::reaction::{ReactionDefinition, ReactionId};
+use super::registry::EntityMetadata;
+use super::turn::{ActorId, BranchId, TurnId, TurnInput, TurnRecord};
+
+/// A single configuration change.
+///
+/// Entity metadata, patterns and reaction definitions are embedded as the
+/// same JSON documents the side files hold; their serde layout relies on
+/// features the Preserves encoding of the journal does not support.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ConfigChange {
+    /// An entity instance was registered
+    EntityRegistered {
+        /// Metadata of the new instance
+        #[serde(with = "json_text")]
+        entity: EntityMetadata,
+    },
+    /// An entity instance was removed
+    EntityUnregistered {
+        /// Removed instance
+        entity_id: Uuid,
+    },
+    /// A pattern subscription was added to an entity
+    PatternRegistered {
+        /// Subscribing entity
+        entity_id: Uuid,
+        /// Registered pattern
+        #[serde(with = "json_text")]
+        pattern: Pattern,
+    },
+    /// The branches an entity exists on changed
+    EntityBranchesChanged {
+        /// Affected entity
+        entity_id: Uuid,
+        /// New branch scope (`None` = every branch)
+        branches: Option<BTreeSet<BranchId>>,
+    },
+    /// A reaction was registered or replaced
+    ReactionRegistered {
+        /// Actor hosting the reaction
+        actor: ActorId,
+        /// Reaction definition
+        #[serde(with = "json_text")]
+        definition: ReactionDefinition,
+    },
+    /// A reaction was removed
+    ReactionUnregistered {
+        /// Removed reaction
+        reaction_id: ReactionId,
+    },
+}
+
+mod json_text {
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let text = serde_json::to_string(value).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        serde_json::from_str(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A journaled configuration change.
+#[derive(Debug, Clone)]
+pub struct ConfigEntry {
+    /// Turn on the config branch recording the change
+    pub turn_id: TurnId,
+    /// Working branch that was active when the change was made
+    pub branch: BranchId,
+    /// Head of the working branch when the change was made
+    pub at: Option<TurnId>,
+    /// The change itself
+    pub change: ConfigChange,
+    /// When the change was recorded
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Configuration changes recorded in `records`, in journal order.
+pub fn changes_in(records: &[TurnRecord]) -> Vec<ConfigEntry> {
+    records
+        .iter()
+        .flat_map(|record| {
+            record.inputs.iter().filter_map(move |input| match input {
+                TurnInput::ConfigChange { branch, at, change } => Some(ConfigEntry {
+                    turn_id: record.turn_id.clone(),
+                    branch: branch.clone(),
+                    at: at.clone(),
+                    change: change.clone(),
+                    timestamp: record.timestamp,
+                }),
+                _ => None,
+            })
+        })
+        .collect()
+}
+
+/// Entries of `entries` in effect at the turn at position `limit` of
+/// `branch`'s journal.
+///
+/// `position` maps a turn to its position in that journal, with genesis at
+/// zero. A change anchored to a turn was made while that turn was the head,
+/// so it is in effect from that turn on. Changes made on other branches,
+/// recorded without an anchor, or anchored to a turn the journal does not
+/// hold are always in effect.
+pub fn in_effect_at<'a>(
+    entries: &'a [ConfigEntry],
+    branch: &'a BranchId,
+    position: impl Fn(&TurnId) -> Option<usize> + 'a,
+    limit: usize,
+) -> impl Iterator<Item = &'a ConfigEntry> + 'a {
+    entries.iter().filter(move |entry| {
+        entry.branch != *branch
+            || entry
+                .at
+                .as_ref()
+                .and_then(&position)
+                .is_none_or(|at| at <= limit)
+    })
+}
+
+/// Configuration in effect after a prefix of the history.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigState {
+    /// Registered entity instances
+    pub entities: BTreeMap<Uuid, EntityMetadata>,
+    /// Registered reactions with their hosting actor
+    pub reactions: BTreeMap<ReactionId, (ActorId, ReactionDefinition)>,
+}
+
+impl ConfigState {
+    /// Fold `entries` into the empty configuration.
+    pub fn replay<'a>(entries: impl IntoIterator<Item = &'a ConfigEntry>) -> Self {
+        let mut state = Self::default();
+        for entry in entries {
+            state.apply(&entry.change);
+        }
+        state
+    }
+
+    /// Entities and reactions any of `entries` registered.
+    pub fn registered<'a>(
+        entries: impl IntoIterator<Item = &'a ConfigEntry>,
+    ) -> (BTreeSet<Uuid>, BTreeSet<ReactionId>) {
+        let mut entities = BTreeSet::new();
+        let mut reactions = BTreeSet::new();
+        for entry in entries {
+            match &entry.change {
+                ConfigChange::EntityRegistered { entity } => {
+                    entities.insert(entity.id);
+                }
+                ConfigChange::ReactionRegistered { definition, .. } => {
+                    reactions.insert(definition.id);
+                }
+                _ => {}
+            }
+        }
+        (entities, reactions)
+    }
+
+    /// Apply one change.
+    pub fn apply(&mut self, change: &ConfigChange) {
+        match change {
+            ConfigChange::EntityRegistered { entity } => {
+                self.entities.insert(entity.id, entity.clone());
+            }
+            ConfigChange::EntityUnregistered { entity_id } => {
+                self.entities.remove(entity_id);
+            }
+            ConfigChange::PatternRegistered { entity_id, pattern } => {
+                if let Some(entity) = self.entities.get_mut(entity_id) {
+                    entity.patterns.retain(|existing| existing.id != pattern.id);
+                    entity.patterns.push(pattern.clone());
+                }
+            }
+            ConfigChange::EntityBranchesChanged {
+                entity_id,
+                branches,
+            } => {
+                if let Some(entity) = self.entities.get_mut(entity_id) {
+                    entity.branches = branches.clone();
+                }
+            }
+            ConfigChange::ReactionRegistered { actor, definition } => {
+                self.reactions
+                    .insert(definition.id, (actor.clone(), definition.clone()));
+            }
+            ConfigChange::ReactionUnregistered { reaction_id } => {
+                self.reactions.remove(reaction_id);
+            }
+        }
+    }
+}
@@ -5,26 +5,68 @@
 
 use preserves::IOValue;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::time::Duration;
 use uuid::Uuid;
 
 use super::actor::Actor;
+use super::approval::{ApprovalId, PendingApproval};
+use super::artifact::{self, PushReport};
+use super::backup::BackupManifest;
+use super::branch::{
+    ArchivedBranch, AssertionOrigin, BranchDetails, BranchMetadata, MergeForecast,
+};
+use super::broadcast::BroadcastRecord;
+use super::config_history::{ConfigChange, ConfigEntry, ConfigState};
+use super::cursor::{self, CursorDirection, CursorKind, Page, PageCursor};
+use super::dedup::DuplicateAnnotation;
+use super::effects::{CompensationHook, EffectKind, FileBackup, RewindWarning, SideEffect};
 use super::error::Result;
+use super::events::EventSubscription;
+use super::experiment::{ExperimentOptions, ExperimentReport, ExperimentVariant};
+use super::fixture::FixtureReport;
+use super::flags::{FeatureFlag, FlagStatus};
+use super::history::{HistoryDetail, TurnView};
+use super::identity::Identity;
+use super::journal::RecordHeader;
+use super::logging::{self, LoggingConfig};
+use super::memory::MemoryReport;
+use super::outbox::{Deliverer, OutboxStatus};
+use super::ratelimit::{IngressLimits, RateLimitStats, RateLimiter, RateScope};
 use super::reaction::{ReactionDefinition, ReactionId, ReactionInfo};
-use super::state::{CapId, CapabilityStatus, CapabilityTarget, FacetMetadata, FacetStatus};
-use super::turn::{ActorId, BranchId, FacetId, TurnId, TurnOutput, TurnRecord};
+use super::reflog::ReflogEntry;
+use super::registry::{ConcurrencyClass, EntityDescriptor};
+use super::schedule::{RecurringSchedule, ScheduleId};
+use super::schema::{AssertionSchema, SchemaRegistry};
+use super::secrets::SecretAccess;
+use super::sink::{SinkOptions, SinkStats, TurnSink};
+use super::snapshot::{SnapshotIndexEntry, SnapshotRetention, SnapshotVerification};
+use super::state::{
+    CapId, CapabilityStatus, CapabilityTarget, FacetMetadata, FacetStatus, namespace_matches,
+};
+use super::sturdy::SturdyRef;
+use super::task::TaskInfo;
+use super::timer::PendingTimer;
+use super::turn::{
+    ActorId, BranchId, FacetId, Handle, LogicalTimestamp, TurnId, TurnInput, TurnOutput,
+    TurnRecord, VectorClock,
+};
+use super::version::VersionStamp;
+use super::wait::{WaitId, WaitRegistration};
 use super::{Runtime, RuntimeConfig};
 
 /// Control interface for the runtime
 pub struct Control {
     runtime: Runtime,
+    ingress: RateLimiter,
 }
 
 impl Control {
     /// Create a new control interface with initialized runtime
     pub fn new(config: RuntimeConfig) -> Result<Self> {
+        let ingress = RateLimiter::new(config.limits.ingress);
         let runtime = Runtime::new(config)?;
-        Ok(Self { runtime })
+        Ok(Self { runtime, ingress })
     }
 
     /// Initialize storage and create a new control interface
@@ -41,15 +83,27 @@ impl Control {
             .branch_manager()
             .head(&current_branch)
             .cloned()
-            .unwrap_or_else(|| TurnId::new("turn_0".to_string()));
+            .unwrap_or_else(TurnId::genesis);
 
         let pending_inputs = self.runtime.scheduler().pending_count();
 
+        let mut journal_versions: Vec<VersionStamp> = self
+            .runtime
+            .journal_reader(&current_branch)?
+            .segment_headers()?
+            .into_iter()
+            .map(|(_, header)| header.version)
+            .collect();
+        journal_versions.dedup();
+
         Ok(RuntimeStatus {
             active_branch: current_branch,
             head_turn,
             pending_inputs,
+            paused_actors: self.runtime.scheduler().paused_actors().cloned().collect(),
             snapshot_interval: self.runtime.config().snapshot_interval,
+            version: self.runtime.version().clone(),
+            journal_versions,
         })
     }
 
@@ -60,6 +114,7 @@ impl Control {
         facet: FacetId,
         payload: preserves::IOValue,
     ) -> Result<TurnId> {
+        self.ingress.admit(RateScope::Actor, &actor.to_string())?;
         self.runtime.send_message(actor.clone(), facet, payload);
 
         // Step to execute the message
@@ -72,6 +127,145 @@ impl Control {
         }
     }
 
+    /// Send a message carrying an idempotency key.
+    ///
+    /// Returns the executed turn, or `None` if the key was already seen on this
+    /// branch and the delivery was recorded as a duplicate instead.
+    pub fn send_message_idempotent(
+        &mut self,
+        actor: ActorId,
+        facet: FacetId,
+        payload: preserves::IOValue,
+        idempotency_key: impl Into<String>,
+    ) -> Result<Option<TurnId>> {
+        self.ingress.admit(RateScope::Actor, &actor.to_string())?;
+        if !self
+            .runtime
+            .send_message_idempotent(actor, facet, payload, idempotency_key)?
+        {
+            return Ok(None);
+        }
+
+        match self.runtime.step()? {
+            Some(record) => Ok(Some(record.turn_id)),
+            None => Err(super::error::RuntimeError::Init(
+                "No turn executed after sending message".into(),
+            )),
+        }
+    }
+
+    /// Charge one message from control-plane client `client` against its
+    /// ingestion rate limit.
+    pub fn admit_client(&mut self, client: &str) -> Result<()> {
+        self.ingress.admit(RateScope::Client, client)
+    }
+
+    /// Act on behalf of `identity` until it is replaced, returning the
+    /// identity acted for before.
+    ///
+    /// Turns caused from now on (messages sent, steps taken, approval
+    /// decisions) record it as their initiator, and forks as their creator.
+    pub fn set_identity(&mut self, identity: Option<Identity>) -> Option<Identity> {
+        self.runtime.set_initiator(identity)
+    }
+
+    /// Identity the control plane currently acts on behalf of
+    pub fn identity(&self) -> Option<&Identity> {
+        self.runtime.initiator()
+    }
+
+    /// Ingestion rate limits in effect
+    pub fn ingress_limits(&self) -> &IngressLimits {
+        self.ingress.limits()
+    }
+
+    /// Replace the ingestion rate limits for this process
+    pub fn set_ingress_limits(&mut self, limits: IngressLimits) {
+        self.ingress.set_limits(limits);
+    }
+
+    /// Messages admitted and rejected per source since startup
+    pub fn rate_limit_stats(&self) -> Vec<RateLimitStats> {
+        self.ingress.stats()
+    }
+
+    /// Deliver `payload` to an actor/facet every `every_n_turns` turns
+    pub fn schedule_recurring(
+        &mut self,
+        actor: ActorId,
+        facet: FacetId,
+        payload: preserves::IOValue,
+        every_n_turns: u64,
+    ) -> Result<ScheduleId> {
+        self.runtime
+            .schedule_recurring(actor, facet, payload, every_n_turns)
+    }
+
+    /// Cancel a recurring schedule, returning whether it existed
+    pub fn cancel_recurring(&mut self, id: ScheduleId) -> Result<bool> {
+        self.runtime.cancel_recurring(id)
+    }
+
+    /// Registered recurring schedules
+    pub fn recurring_schedules(&self) -> Vec<RecurringSchedule> {
+        self.runtime.recurring_schedules()
+    }
+
+    /// Fire the timers whose deadline has passed and drain the resulting turns
+    pub fn fire_due_timers(&mut self) -> Result<usize> {
+        let fired = self.runtime.fire_due_timers(chrono::Utc::now());
+        if fired > 0 {
+            self.drain_pending()?;
+        }
+        Ok(fired)
+    }
+
+    /// Earliest deadline of a pending timer that has not fired yet
+    pub fn next_timer_deadline(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.runtime.next_timer_deadline()
+    }
+
+    /// Timers set on the current branch that have not fired yet
+    pub fn pending_timers(&self) -> Vec<PendingTimer> {
+        self.runtime.pending_timers()
+    }
+
+    /// Add an actor to a named group used for broadcast addressing
+    pub fn add_to_group(&mut self, group: impl Into<String>, actor: ActorId) -> Result<bool> {
+        self.runtime.add_to_group(group, actor)
+    }
+
+    /// Remove an actor from a named group
+    pub fn remove_from_group(&mut self, group: &str, actor: &ActorId) -> Result<bool> {
+        self.runtime.remove_from_group(group, actor)
+    }
+
+    /// Members of a named group
+    pub fn group_members(&self, group: &str) -> Vec<ActorId> {
+        self.runtime.entity_manager().group_members(group)
+    }
+
+    /// Broadcast a message to every member of a group and drain the resulting turns
+    pub fn broadcast(
+        &mut self,
+        group: &str,
+        payload: preserves::IOValue,
+    ) -> Result<BroadcastRecord> {
+        let record = self.runtime.broadcast(group, payload)?;
+        self.drain_pending()?;
+        Ok(record)
+    }
+
+    /// Broadcasts sent on the current branch
+    pub fn broadcasts(&self) -> Result<Vec<BroadcastRecord>> {
+        self.runtime.broadcasts()
+    }
+
+    /// Duplicate deliveries suppressed on the current branch
+    pub fn duplicate_annotations(&self) -> Result<Vec<DuplicateAnnotation>> {
+        self.runtime.duplicate_annotations()
+    }
+
     /// Assert a value directly into an actor's dataspace and execute resulting turns.
     pub fn assert_value(&mut self, actor: ActorId, value: preserves::IOValue) -> Result<TurnId> {
         self.runtime.assert_value(actor.clone(), value);
@@ -85,10 +279,77 @@ impl Control {
         }
     }
 
+    /// Retract every assertion of `actor` matching `pattern` in one administrative turn.
+    ///
+    /// Pattern syntax is the same as for entity subscriptions.
+    pub fn retract_matching(
+        &mut self,
+        actor: ActorId,
+        pattern: preserves::IOValue,
+    ) -> Result<RetractMatchingReport> {
+        let handles = self.runtime.retract_matching(actor, pattern);
+
+        if let Some(record) = self.runtime.step()? {
+            Ok(RetractMatchingReport {
+                turn_id: record.turn_id,
+                count: handles.len(),
+                handles,
+            })
+        } else {
+            Err(super::error::RuntimeError::Init(
+                "No turn executed after retracting matching assertions".into(),
+            ))
+        }
+    }
+
+    /// Stop scheduling turns for `actor` while other actors keep running.
+    ///
+    /// Queued inputs are held until [`Control::resume_actor`]. Returns the
+    /// administrative turn recording the pause, or `None` if the actor was
+    /// already paused.
+    pub fn pause_actor(&mut self, actor: ActorId) -> Result<Option<TurnId>> {
+        self.runtime.set_actor_paused(actor, true)
+    }
+
+    /// Resume a paused actor, releasing its held inputs.
+    ///
+    /// Returns `None` if the actor was not paused.
+    pub fn resume_actor(&mut self, actor: ActorId) -> Result<Option<TurnId>> {
+        self.runtime.set_actor_paused(actor, false)
+    }
+
+    /// Switch a runtime feature flag in a journaled system-actor turn.
+    ///
+    /// The new value is published as a `<feature-flag name enabled>`
+    /// assertion that entities and reactions can observe.
+    pub fn set_flag(&mut self, flag: FeatureFlag, enabled: bool) -> Result<TurnId> {
+        self.runtime.set_flag(flag, enabled);
+
+        if let Some(record) = self.runtime.step()? {
+            Ok(record.turn_id)
+        } else {
+            Err(super::error::RuntimeError::Init(
+                "No turn executed after setting feature flag".into(),
+            ))
+        }
+    }
+
+    /// Current value of every runtime feature flag
+    pub fn flags(&self) -> Vec<FlagStatus> {
+        self.runtime.flags()
+    }
+
     /// Step forward by N turns
     pub fn step(&mut self, count: usize) -> Result<Vec<TurnSummary>> {
         let records = self.runtime.step_n(count)?;
-        Ok(records.into_iter().map(turn_to_summary).collect())
+        let version = self.runtime.version();
+        Ok(records
+            .into_iter()
+            .map(|record| TurnSummary {
+                version: Some(version.clone()),
+                ..turn_to_summary(record)
+            })
+            .collect())
     }
 
     /// Go back N turns
@@ -101,14 +362,108 @@ impl Control {
         self.runtime.goto(turn_id)
     }
 
-    /// Fork a new branch
+    /// Movements of a branch's head, newest first
+    pub fn reflog(&self, branch: &BranchId) -> Result<Vec<ReflogEntry>> {
+        self.runtime.reflog(branch)
+    }
+
+    /// Put the current branch's head back where reflog entry `index` left it
+    pub fn restore_head(&mut self, index: usize) -> Result<TurnId> {
+        self.runtime.restore_head(index)
+    }
+
+    /// Check `branch`'s snapshots against state replayed from its journal
+    pub fn verify_snapshots(&self, branch: &BranchId) -> Result<SnapshotVerification> {
+        self.runtime.verify_snapshots(branch)
+    }
+
+    /// Check that every turn of `branch` was committed in canonical order.
+    pub fn verify_replay(&self, branch: &BranchId) -> Result<super::ordering::ReplayVerification> {
+        self.runtime.verify_replay(branch)
+    }
+
+    /// Train a journal compression dictionary on recent records and enable it
+    pub fn train_journal_dictionary(
+        &mut self,
+        options: &super::compression::TrainingOptions,
+    ) -> Result<super::compression::DictionaryInfo> {
+        self.runtime.train_journal_dictionary(options)
+    }
+
+    /// Stop compressing new journal records
+    pub fn disable_journal_compression(&mut self) -> Result<()> {
+        self.runtime.disable_journal_compression()
+    }
+
+    /// The active journal compression dictionary, if any
+    pub fn journal_dictionary(&self) -> Result<Option<super::compression::DictionaryInfo>> {
+        self.runtime.journal_dictionary()
+    }
+
+    /// Compact a branch journal below its retention horizon
+    ///
+    /// Uses the configured retention policy unless `policy` overrides it.
+    pub fn compact_journal(
+        &mut self,
+        branch: &BranchId,
+        policy: Option<&super::journal::compactor::RetentionPolicy>,
+    ) -> Result<super::journal::compactor::CompactionReport> {
+        self.runtime.compact_journal(branch, policy)
+    }
+
+    /// Delete a branch's snapshots outside its snapshot retention
+    ///
+    /// Uses the configured retention unless `policy` overrides it.
+    pub fn prune_snapshots(
+        &mut self,
+        branch: &BranchId,
+        policy: Option<&SnapshotRetention>,
+    ) -> Result<Vec<SnapshotIndexEntry>> {
+        self.runtime.prune_snapshots(branch, policy)
+    }
+
+    /// Tag a snapshot so snapshot retention keeps it
+    pub fn tag_snapshot(&mut self, branch: &BranchId, turn_id: &TurnId, tag: &str) -> Result<bool> {
+        self.runtime.tag_snapshot(branch, turn_id, tag)
+    }
+
+    /// Snapshots recorded for a branch, oldest first
+    pub fn snapshots(&self, branch: &BranchId) -> Vec<SnapshotIndexEntry> {
+        self.runtime.snapshots(branch)
+    }
+
+    /// Run environment checks and return a report for support.
+    ///
+    /// Agent checks are advisory: they look for CLIs and API keys without
+    /// launching agents, and never affect the journal.
+    pub fn doctor(&self) -> super::doctor::DoctorReport {
+        let mut report = self.runtime.doctor();
+        report
+            .checks
+            .extend(crate::codebase::agent::doctor_checks());
+        report
+    }
+
+    /// Explain why `turn_id` ran, what routed it, and which turns it triggered.
+    pub fn explain_turn(&self, turn_id: &TurnId) -> Result<super::explain::TurnExplanation> {
+        self.runtime.explain_turn(turn_id)
+    }
+
+    /// Fork a new branch, recording `details` with it
     pub fn fork(
         &mut self,
         _source: BranchId,
         new_branch: BranchId,
         from_turn: Option<TurnId>,
+        details: BranchDetails,
     ) -> Result<BranchId> {
-        self.runtime.fork(new_branch.0.clone(), from_turn)
+        self.runtime
+            .fork_with(new_branch.0.clone(), from_turn, details)
+    }
+
+    /// Branch name produced by the configured naming template `template`.
+    pub fn branch_name(&self, template: &str, vars: &BTreeMap<String, String>) -> Result<BranchId> {
+        Ok(self.runtime.config().branch_naming.render(template, vars)?)
     }
 
     /// Merge branches
@@ -127,6 +482,12 @@ impl Control {
         })
     }
 
+    /// Predict the warnings and assertion changes of merging `source` into
+    /// `target`, without writing a merge turn.
+    pub fn merge_forecast(&self, source: &BranchId, target: &BranchId) -> Result<MergeForecast> {
+        self.runtime.merge_forecast(source, target)
+    }
+
     /// Get history for a branch
     pub fn history(
         &self,
@@ -136,8 +497,225 @@ impl Control {
     ) -> Result<Vec<TurnSummary>> {
         // Read from journal
         let reader = self.runtime.journal_reader(branch)?;
-        let turns = reader.read_range(start, limit)?;
-        Ok(turns.into_iter().map(turn_to_summary).collect())
+        let versions = reader.turn_versions()?;
+        Ok(reader
+            .iter_headers()?
+            .skip(start)
+            .take(limit)
+            .map(|header| with_version(header_to_summary(header, branch), &versions))
+            .collect())
+    }
+
+    /// History visible from `branch`, including turns inherited from the
+    /// branches it was forked from.
+    ///
+    /// Reads the journals directly: the active branch and its writer are left
+    /// untouched, so this is safe to call while another branch is live.
+    pub fn history_on(
+        &self,
+        branch: &BranchId,
+        start: usize,
+        limit: usize,
+    ) -> Result<Vec<TurnSummary>> {
+        let headers = self.runtime.lineage_headers(branch)?;
+        let versions = self.runtime.lineage_versions(branch)?;
+        Ok(headers
+            .into_iter()
+            .skip(start)
+            .take(limit)
+            .map(|header| with_version(header_to_summary(header, branch), &versions))
+            .collect())
+    }
+
+    /// Curated views of the turns `summaries` lists, read from the history
+    /// visible from `branch`; see [`TurnView`].
+    pub fn turn_views(
+        &self,
+        branch: &BranchId,
+        summaries: Vec<TurnSummary>,
+        detail: HistoryDetail,
+    ) -> Result<Vec<TurnView>> {
+        let wanted: HashSet<&TurnId> = summaries.iter().map(|summary| &summary.turn_id).collect();
+        let records: HashMap<TurnId, TurnRecord> = self
+            .runtime
+            .lineage_records(branch, None)?
+            .into_iter()
+            .filter(|record| wanted.contains(&record.turn_id))
+            .map(|record| (record.turn_id.clone(), record))
+            .collect();
+        summaries
+            .into_iter()
+            .map(|summary| {
+                let record = records.get(&summary.turn_id).ok_or_else(|| {
+                    super::error::RuntimeError::Journal(super::error::JournalError::TurnNotFound(
+                        summary.turn_id.to_string(),
+                    ))
+                })?;
+                Ok(TurnView::of(summary, record, detail))
+            })
+            .collect()
+    }
+
+    /// Turns executed by `actor` on `branch`, oldest first.
+    ///
+    /// Follows the per-actor parent links back from the actor's latest turn
+    /// instead of filtering the whole history. With `facet`, only turns that
+    /// involve that facet (see [`TurnRecord::involves_facet`]) are kept.
+    /// `start` and `limit` page through the actor's turns.
+    pub fn actor_history(
+        &self,
+        branch: &BranchId,
+        actor: &ActorId,
+        facet: Option<&FacetId>,
+        start: usize,
+        limit: usize,
+    ) -> Result<Vec<TurnSummary>> {
+        let headers = self.runtime.lineage_headers(branch)?;
+        let versions = self.runtime.lineage_versions(branch)?;
+
+        let mut chain = Vec::new();
+        let mut cursor = headers
+            .iter()
+            .rev()
+            .find(|header| &header.actor == actor)
+            .map(|header| header.turn_id.clone());
+        let by_id: HashMap<&TurnId, &RecordHeader> = headers
+            .iter()
+            .map(|header| (&header.turn_id, header))
+            .collect();
+        while let Some(turn_id) = cursor {
+            let Some(header) = by_id.get(&turn_id) else {
+                break;
+            };
+            cursor = header.parent.clone();
+            chain.push((*header).clone());
+        }
+        chain.reverse();
+
+        if let Some(facet) = facet {
+            let involved: HashSet<TurnId> = self
+                .runtime
+                .lineage_records(branch, None)?
+                .into_iter()
+                .filter(|record| &record.actor == actor && record.involves_facet(facet))
+                .map(|record| record.turn_id)
+                .collect();
+            chain.retain(|header| involved.contains(&header.turn_id));
+        }
+
+        Ok(chain
+            .into_iter()
+            .skip(start)
+            .take(limit)
+            .map(|header| with_version(header_to_summary(header, branch), &versions))
+            .collect())
+    }
+
+    /// Assertions live on `branch` after `turn` (or after its head), replayed
+    /// from the journal without switching branches.
+    pub fn assertions_on(
+        &self,
+        branch: &BranchId,
+        turn: Option<&TurnId>,
+    ) -> Result<Vec<AssertionInfo>> {
+        let mut live: Vec<AssertionInfo> = Vec::new();
+        for record in self.runtime.lineage_records(branch, turn)? {
+            // Merge turns have no outputs; their joined delta carries the changes
+            if record
+                .inputs
+                .iter()
+                .any(|input| matches!(input, TurnInput::Merge { .. }))
+            {
+                let delta = &record.delta.assertions;
+                for (actor, handle, value, _version) in &delta.added {
+                    live.retain(|info| !(&info.actor == actor && &info.handle == handle));
+                    live.push(AssertionInfo {
+                        actor: actor.clone(),
+                        handle: handle.clone(),
+                        value: value.clone(),
+                        namespace: delta
+                            .namespaces
+                            .iter()
+                            .find(|(owner, named, _)| owner == actor && named == handle)
+                            .map(|(_, _, namespace)| namespace.clone()),
+                    });
+                }
+                for (actor, handle, _version) in &delta.retracted {
+                    live.retain(|info| !(&info.actor == actor && &info.handle == handle));
+                }
+                continue;
+            }
+            for output in &record.outputs {
+                match output {
+                    TurnOutput::Assert {
+                        handle,
+                        value,
+                        namespace,
+                    } => live.push(AssertionInfo {
+                        actor: record.actor.clone(),
+                        handle: handle.clone(),
+                        value: value.clone(),
+                        namespace: namespace.clone(),
+                    }),
+                    TurnOutput::Retract { handle, .. } => {
+                        live.retain(|info| !(info.actor == record.actor && &info.handle == handle))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(live)
+    }
+
+    /// Export `actor`'s assertions, facet tree, capabilities and entity
+    /// states after `turn` (or at the head) as one canonical Preserves
+    /// document; see [`Runtime::export_actor_state`].
+    pub fn export_actor_state(
+        &self,
+        actor: &ActorId,
+        turn: Option<&TurnId>,
+    ) -> Result<preserves::IOValue> {
+        self.runtime.export_actor_state(actor, turn)
+    }
+
+    /// Private state of one live entity; see [`Runtime::entity_state`].
+    pub fn entity_state(&self, entity_id: Uuid) -> Result<Option<preserves::IOValue>> {
+        self.runtime.entity_state(entity_id)
+    }
+
+    /// Overwrite the private state of one live entity; see
+    /// [`Runtime::set_entity_state`].
+    pub fn set_entity_state(&mut self, entity_id: Uuid, state: &preserves::IOValue) -> Result<()> {
+        self.runtime.set_entity_state(entity_id, state)
+    }
+
+    /// Page through the history visible from `branch` with a stable cursor.
+    ///
+    /// Unlike [`Control::history`], pages do not shift as new turns land. When
+    /// `cursor` is given, its direction takes precedence over `direction`.
+    pub fn history_page(
+        &self,
+        branch: &BranchId,
+        cursor: Option<&str>,
+        direction: CursorDirection,
+        limit: usize,
+    ) -> Result<Page<TurnSummary>> {
+        let (direction, after) = resume_cursor(cursor, CursorKind::History, branch, direction)?;
+        let headers = self.runtime.lineage_headers(branch)?;
+        let versions = self.runtime.lineage_versions(branch)?;
+        let summaries: Vec<TurnSummary> = headers
+            .into_iter()
+            .map(|header| with_version(header_to_summary(header, branch), &versions))
+            .collect();
+        cursor::paginate_sequence(
+            summaries,
+            |turn| turn.turn_id.to_string(),
+            CursorKind::History,
+            branch,
+            direction,
+            after.as_deref(),
+            limit,
+        )
     }
 
     /// List all branches
@@ -149,10 +727,117 @@ impl Control {
                 name: metadata.id.clone(),
                 head_turn: metadata.head_turn.clone(),
                 parent: metadata.parent.clone(),
+                base_turn: metadata.base_turn.clone(),
+                created_at: metadata.created_at,
+                created_by: metadata.created_by.clone(),
+                description: metadata.details.description.clone(),
+                creator: metadata.details.creator.clone(),
+                tags: metadata.details.tags.clone(),
             })
             .collect())
     }
 
+    /// Tombstones of branches moved to cold storage.
+    pub fn list_archived_branches(&self) -> Vec<ArchivedBranch> {
+        self.runtime
+            .branch_manager()
+            .list_archived()
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Export `branch` to the archive directory `dest` and drop it from the
+    /// active branches.
+    pub fn archive_branch(
+        &mut self,
+        branch: &BranchId,
+        dest: impl AsRef<std::path::Path>,
+    ) -> Result<ArchivedBranch> {
+        self.runtime.archive_branch(branch, dest.as_ref())
+    }
+
+    /// Delete `branch` and its storage; `force` also deletes unmerged work.
+    pub fn delete_branch(&mut self, branch: &BranchId, force: bool) -> Result<BranchMetadata> {
+        self.runtime.delete_branch(branch, force)
+    }
+
+    /// Fsync the journal, returning how many turns were not yet synced.
+    pub fn flush(&mut self) -> Result<u64> {
+        self.runtime.flush()
+    }
+
+    /// Take a consistent backup of the storage root into `dest`.
+    pub fn backup(&mut self, dest: impl AsRef<std::path::Path>) -> Result<BackupManifest> {
+        self.runtime.backup(dest.as_ref())
+    }
+
+    /// Restore an archived branch under its original name.
+    pub fn unarchive_branch(&mut self, branch: &BranchId) -> Result<BranchId> {
+        self.runtime.unarchive_branch(branch)
+    }
+
+    /// Run `variants` on ephemeral forks of the current branch at
+    /// `base_turn` and compare them; see [`Runtime::experiment`].
+    pub fn experiment(
+        &mut self,
+        base_turn: TurnId,
+        variants: Vec<ExperimentVariant>,
+        options: ExperimentOptions,
+    ) -> Result<ExperimentReport> {
+        self.runtime.experiment(base_turn, variants, options)
+    }
+
+    /// Head of `branch`, with its timestamp and the number of turns visible
+    /// from it (including turns inherited from ancestors).
+    pub fn branch_head(&self, branch: &BranchId) -> Result<BranchHead> {
+        let headers = self.runtime.lineage_headers(branch)?;
+        let turn_id = self
+            .runtime
+            .branch_manager()
+            .head(branch)
+            .cloned()
+            .unwrap_or_else(TurnId::genesis);
+        let head = headers.last();
+        Ok(BranchHead {
+            branch: branch.clone(),
+            timestamp: head.map(|header| header.timestamp),
+            logical_time: head.map(|header| header.logical_timestamp(branch)),
+            turn_count: headers.len(),
+            turn_id,
+        })
+    }
+
+    /// Turn where `a` and `b` diverged, with how many turns each branch has
+    /// recorded since.
+    pub fn branch_lca(&self, a: &BranchId, b: &BranchId) -> Result<BranchLca> {
+        // Both lookups fail with `NotFound` for unknown branches
+        let a_headers = self.runtime.lineage_headers(a)?;
+        let b_headers = self.runtime.lineage_headers(b)?;
+        let lca = self
+            .runtime
+            .branch_manager()
+            .find_lca(a, b)
+            .unwrap_or_else(TurnId::genesis);
+        let since = |headers: &[RecordHeader]| match headers
+            .iter()
+            .position(|header| header.turn_id == lca)
+        {
+            Some(index) => headers.len() - index - 1,
+            None => headers.len(),
+        };
+        let shared = a_headers.iter().find(|header| header.turn_id == lca);
+        Ok(BranchLca {
+            timestamp: shared.map(|header| header.timestamp),
+            logical_time: shared.map(|header| header.logical_timestamp(a)),
+            a_turns_since: since(&a_headers),
+            b_turns_since: since(&b_headers),
+            a: a.clone(),
+            b: b.clone(),
+            lca,
+        })
+    }
+
     /// Get reference to underlying runtime (for advanced usage)
     pub fn runtime(&self) -> &Runtime {
         &self.runtime
@@ -170,6 +855,55 @@ impl Control {
         facet: FacetId,
         entity_type: String,
         config: preserves::IOValue,
+    ) -> Result<Uuid> {
+        self.register_scoped_entity(actor, facet, entity_type, config, None)
+    }
+
+    /// Register an entity instance that only exists on `branch` and the
+    /// branches later forked from it.
+    ///
+    /// The instance is only attached while `branch` is active, so a fork can
+    /// run, say, a workspace entity with a different root than its parent.
+    pub fn register_entity_on_branch(
+        &mut self,
+        actor: ActorId,
+        facet: FacetId,
+        entity_type: String,
+        config: preserves::IOValue,
+        branch: BranchId,
+    ) -> Result<Uuid> {
+        self.register_scoped_entity(
+            actor,
+            facet,
+            entity_type,
+            config,
+            Some(std::iter::once(branch).collect()),
+        )
+    }
+
+    /// Restrict an existing entity to `branches` (`None` = every branch).
+    pub fn set_entity_branches(
+        &mut self,
+        entity_id: Uuid,
+        branches: Option<BTreeSet<BranchId>>,
+    ) -> Result<()> {
+        self.runtime
+            .set_entity_branches(entity_id, branches.clone())?;
+        self.runtime
+            .record_config_change(ConfigChange::EntityBranchesChanged {
+                entity_id,
+                branches,
+            })?;
+        Ok(())
+    }
+
+    fn register_scoped_entity(
+        &mut self,
+        actor: ActorId,
+        facet: FacetId,
+        entity_type: String,
+        config: preserves::IOValue,
+        branches: Option<BTreeSet<BranchId>>,
     ) -> Result<Uuid> {
         use super::registry::EntityMetadata;
 
@@ -205,10 +939,12 @@ impl Control {
             config,
             is_root_facet,
             patterns: vec![],
+            branches,
         };
+        let visible = metadata.visible_on(&self.runtime.current_branch());
 
         // Register metadata
-        self.runtime.entity_manager_mut().register(metadata);
+        self.runtime.entity_manager_mut().register(metadata.clone());
 
         // Attach entity to actor (obtain a fresh mutable borrow)
         let actor_obj = self
@@ -216,7 +952,9 @@ impl Control {
             .actors
             .entry(actor.clone())
             .or_insert_with(|| Actor::new(actor.clone()));
-        actor_obj.attach_entity(entity_id, entity_type, facet.clone(), entity);
+        if visible {
+            actor_obj.attach_entity(entity_id, entity_type.clone(), facet.clone(), entity);
+        }
 
         {
             let mut facets = actor_obj.facets.write();
@@ -233,10 +971,72 @@ impl Control {
 
         // Persist entity metadata
         self.runtime.persist_entities()?;
+        self.runtime
+            .record_config_change(ConfigChange::EntityRegistered { entity: metadata })?;
+
+        if visible {
+            self.publish_entity_descriptor(&actor, &entity_type)?;
+        }
 
         Ok(entity_id)
     }
 
+    /// Assert the `entity-descriptor` for `entity_type` into `actor`'s dataspace.
+    ///
+    /// Skipped when the type advertises nothing or the actor already holds
+    /// an identical descriptor.
+    fn publish_entity_descriptor(&mut self, actor: &ActorId, entity_type: &str) -> Result<()> {
+        let Some(descriptor) = self
+            .runtime
+            .entity_registry()
+            .describe(entity_type)
+            .filter(|descriptor| !descriptor.is_empty())
+        else {
+            return Ok(());
+        };
+
+        let value = descriptor.to_value(entity_type);
+        let published = self
+            .runtime
+            .assertions_for_actor(actor)
+            .unwrap_or_default()
+            .into_iter()
+            .any(|(_handle, existing)| existing == value);
+        if !published {
+            self.assert_value(actor.clone(), value)?;
+        }
+        Ok(())
+    }
+
+    /// Messages, capability kinds and configuration advertised by `entity_type`.
+    pub fn describe_entity_type(&self, entity_type: &str) -> Result<EntityDescriptor> {
+        let registry = self.runtime.entity_registry();
+        if !registry.has_type(entity_type) {
+            return Err(super::error::RuntimeError::Actor(
+                super::error::ActorError::NotFound(format!("Entity type {}", entity_type)),
+            ));
+        }
+        registry.describe(entity_type).ok_or_else(|| {
+            super::error::RuntimeError::Actor(super::error::ActorError::NotFound(format!(
+                "No instance of entity type {} has been created",
+                entity_type
+            )))
+        })
+    }
+
+    /// Concurrency class declared for `entity_type`.
+    pub fn entity_concurrency(&self, entity_type: &str) -> Result<ConcurrencyClass> {
+        self.runtime
+            .entity_registry()
+            .concurrency(entity_type)
+            .ok_or_else(|| {
+                super::error::RuntimeError::Actor(super::error::ActorError::NotFound(format!(
+                    "Entity type {}",
+                    entity_type
+                )))
+            })
+    }
+
     /// Register a pattern subscription for an entity
     ///
     /// Registers the pattern with the actor and persists the pattern definition
@@ -273,6 +1073,8 @@ impl Control {
 
         self.runtime
             .register_pattern_for_entity(entity_id, pattern.clone())?;
+        self.runtime
+            .record_config_change(ConfigChange::PatternRegistered { entity_id, pattern })?;
 
         Ok(pattern_id)
     }
@@ -298,6 +1100,8 @@ impl Control {
 
         // Persist updated metadata
         self.runtime.persist_entities()?;
+        self.runtime
+            .record_config_change(ConfigChange::EntityUnregistered { entity_id })?;
 
         Ok(true)
     }
@@ -306,7 +1110,7 @@ impl Control {
     pub fn list_entities(&self) -> Vec<EntityInfo> {
         self.runtime
             .entity_manager()
-            .list()
+            .list_on(&self.runtime.current_branch())
             .into_iter()
             .map(|meta| EntityInfo {
                 id: meta.id,
@@ -318,12 +1122,43 @@ impl Control {
             .collect()
     }
 
+    /// Page through registered entities in identifier order.
+    pub fn entities_page(
+        &self,
+        cursor: Option<&str>,
+        direction: CursorDirection,
+        limit: usize,
+    ) -> Result<Page<EntityInfo>> {
+        let branch = self.runtime.current_branch();
+        let (direction, after) = resume_cursor(cursor, CursorKind::Entities, &branch, direction)?;
+        let mut entities = self.list_entities();
+        entities.sort_by_key(|entity| entity.id.to_string());
+        Ok(cursor::paginate_sorted(
+            entities,
+            |entity| entity.id.to_string(),
+            CursorKind::Entities,
+            &branch,
+            direction,
+            after.as_deref(),
+            limit,
+        ))
+    }
+
+    /// Root facet of `actor`, if the actor exists.
+    pub fn actor_root_facet(&self, actor: &ActorId) -> Option<FacetId> {
+        self.runtime
+            .actors
+            .get(actor)
+            .map(|entry| entry.root_facet.clone())
+    }
+
     /// List entities for a specific actor
     pub fn list_entities_for_actor(&self, actor: &ActorId) -> Vec<EntityInfo> {
         self.runtime
             .entity_manager()
             .list_for_actor(actor)
             .into_iter()
+            .filter(|meta| meta.visible_on(&self.runtime.current_branch()))
             .map(|meta| EntityInfo {
                 id: meta.id,
                 actor: meta.actor.clone(),
@@ -340,12 +1175,48 @@ impl Control {
         actor: ActorId,
         definition: ReactionDefinition,
     ) -> Result<ReactionId> {
-        self.runtime.register_reaction(actor, definition)
+        let reaction_id = self
+            .runtime
+            .register_reaction(actor.clone(), definition.clone())?;
+        self.runtime
+            .record_config_change(ConfigChange::ReactionRegistered { actor, definition })?;
+        Ok(reaction_id)
     }
 
     /// Remove a previously registered reaction.
     pub fn unregister_reaction(&mut self, reaction_id: ReactionId) -> Result<bool> {
-        self.runtime.unregister_reaction(reaction_id)
+        let removed = self.runtime.unregister_reaction(reaction_id)?;
+        if removed {
+            self.runtime
+                .record_config_change(ConfigChange::ReactionUnregistered { reaction_id })?;
+        }
+        Ok(removed)
+    }
+
+    /// Configuration changes recorded on the reserved config branch, oldest first.
+    pub fn config_history(&self) -> Result<Vec<ConfigEntry>> {
+        self.runtime.config_history()
+    }
+
+    /// Configuration in effect once the config-branch turn `turn` was recorded
+    /// (`None` = latest).
+    pub fn config_at(&self, turn: Option<&TurnId>) -> Result<ConfigState> {
+        let history = self.runtime.config_history()?;
+        let end = match turn {
+            Some(turn) => {
+                history
+                    .iter()
+                    .position(|entry| entry.turn_id == *turn)
+                    .ok_or_else(|| {
+                        super::error::RuntimeError::Journal(
+                            super::error::JournalError::TurnNotFound(turn.to_string()),
+                        )
+                    })?
+                    + 1
+            }
+            None => history.len(),
+        };
+        Ok(ConfigState::replay(&history[..end]))
     }
 
     /// List all stored reactions.
@@ -362,6 +1233,29 @@ impl Control {
         results
     }
 
+    /// Page through capabilities in identifier order.
+    pub fn capabilities_page(
+        &self,
+        cursor: Option<&str>,
+        direction: CursorDirection,
+        limit: usize,
+    ) -> Result<Page<CapabilityInfo>> {
+        let branch = self.runtime.current_branch();
+        let (direction, after) =
+            resume_cursor(cursor, CursorKind::Capabilities, &branch, direction)?;
+        let mut capabilities = self.list_capabilities();
+        capabilities.sort_by_key(|capability| capability.id.to_string());
+        Ok(cursor::paginate_sorted(
+            capabilities,
+            |capability| capability.id.to_string(),
+            CursorKind::Capabilities,
+            &branch,
+            direction,
+            after.as_deref(),
+            limit,
+        ))
+    }
+
     /// List capabilities for a specific actor
     pub fn list_capabilities_for_actor(&self, actor: &ActorId) -> Vec<CapabilityInfo> {
         if let Some(actor_obj) = self.runtime.actors.get(actor) {
@@ -381,31 +1275,89 @@ impl Control {
 
     /// List assertions across the runtime, optionally filtered by actor.
     pub fn list_assertions(&self, actor: Option<&ActorId>) -> Vec<AssertionInfo> {
-        match actor {
-            Some(actor_id) => self
-                .list_assertions_for_actor(actor_id)
-                .into_iter()
-                .map(|(handle, value)| AssertionInfo {
-                    actor: actor_id.clone(),
-                    handle,
-                    value,
-                })
-                .collect(),
-            None => {
-                let mut results = Vec::new();
-                for (actor_id, actor) in &self.runtime.actors {
-                    let assertions = actor.assertions.read();
-                    results.extend(assertions.active.iter().map(
-                        |((_owner, handle), (value, _version))| AssertionInfo {
-                            actor: actor_id.clone(),
-                            handle: handle.clone(),
-                            value: value.clone(),
-                        },
-                    ));
-                }
-                results
+        let mut results = Vec::new();
+        for (actor_id, actor_obj) in &self.runtime.actors {
+            if actor.is_some_and(|wanted| wanted != actor_id) {
+                continue;
             }
+            let assertions = actor_obj.assertions.read();
+            results.extend(
+                assertions
+                    .active
+                    .iter()
+                    .map(|((owner, handle), (value, _version))| AssertionInfo {
+                        actor: actor_id.clone(),
+                        handle: handle.clone(),
+                        value: value.clone(),
+                        namespace: assertions.namespace_of(owner, handle).map(str::to_string),
+                    }),
+            );
         }
+        results
+    }
+
+    /// Origin of each assertion live on the current branch, keyed by handle.
+    ///
+    /// Assertions joined by a merge name the branch and turn that made them
+    /// along with the merge turn that brought them in.
+    pub fn assertion_origins(&self) -> Result<HashMap<Handle, AssertionOrigin>> {
+        let branch = self.runtime.current_branch();
+        self.runtime.assertion_origins(&branch, None)
+    }
+
+    /// Page through live assertions ordered by actor and handle.
+    pub fn assertions_page(
+        &self,
+        actor: Option<&ActorId>,
+        cursor: Option<&str>,
+        direction: CursorDirection,
+        limit: usize,
+    ) -> Result<Page<AssertionInfo>> {
+        let branch = self.runtime.current_branch();
+        let (direction, after) = resume_cursor(cursor, CursorKind::Assertions, &branch, direction)?;
+        let key = |info: &AssertionInfo| format!("{}/{}", info.actor, info.handle);
+        let mut assertions = self.list_assertions(actor);
+        assertions.sort_by_key(key);
+        Ok(cursor::paginate_sorted(
+            assertions,
+            key,
+            CursorKind::Assertions,
+            &branch,
+            direction,
+            after.as_deref(),
+            limit,
+        ))
+    }
+
+    /// List assertions published into a named dataspace, optionally filtered by actor.
+    pub fn list_assertions_in(
+        &self,
+        namespace: &str,
+        actor: Option<&ActorId>,
+    ) -> Vec<AssertionInfo> {
+        let mut results = self.list_assertions(actor);
+        results.retain(|info| namespace_matches(namespace, info.namespace.as_deref()));
+        results
+    }
+
+    /// List assertions that are records labelled `label`, optionally filtered by actor.
+    pub fn list_assertions_with_label(
+        &self,
+        label: &str,
+        actor: Option<&ActorId>,
+    ) -> Vec<AssertionInfo> {
+        let mut results = self.list_assertions(actor);
+        results
+            .retain(|info| crate::util::io_value::record_with_label(&info.value, label).is_some());
+        results
+    }
+
+    /// Assertion types that [`AssertionEventFilter::schema`] can name.
+    pub fn assertion_schemas(&self) -> Vec<AssertionSchema> {
+        SchemaRegistry::init()
+            .assertion_schemas()
+            .cloned()
+            .collect()
     }
 
     /// Stream assertion-related events from the journal.
@@ -434,6 +1386,51 @@ impl Control {
         Ok(chunk)
     }
 
+    /// Subscribe to assertion events on the current branch.
+    ///
+    /// Narrow and decode the subscription with its builder methods, then
+    /// iterate it; see [`EventSubscription`].
+    pub fn events(&self) -> EventSubscription<'_> {
+        EventSubscription::new(self, self.runtime.current_branch())
+    }
+
+    /// Export a capability as a signed sturdy ref that can leave this runtime
+    pub fn export_sturdy_ref(
+        &self,
+        cap_id: Uuid,
+        attenuation: Vec<IOValue>,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<SturdyRef> {
+        self.runtime
+            .export_sturdy_ref(cap_id, attenuation, expires_at)
+    }
+
+    /// Redeem a sturdy ref previously exported by this runtime
+    pub fn redeem_sturdy_ref(
+        &mut self,
+        sturdy_ref: &SturdyRef,
+        payload: IOValue,
+    ) -> Result<IOValue> {
+        self.runtime.redeem_sturdy_ref(sturdy_ref, payload)
+    }
+
+    /// Capability invocations parked awaiting human approval
+    pub fn pending_approvals(&self) -> Vec<PendingApproval> {
+        self.runtime.pending_approvals()
+    }
+
+    /// Approve a parked invocation and drain the turns it produces
+    pub fn approve(&mut self, id: ApprovalId) -> Result<()> {
+        self.runtime.approve(id)?;
+        self.drain_pending()
+    }
+
+    /// Deny a parked invocation and drain the turns it produces
+    pub fn deny(&mut self, id: ApprovalId, reason: &str) -> Result<()> {
+        self.runtime.deny(id, reason)?;
+        self.drain_pending()
+    }
+
     /// Invoke a capability by id with a payload; runtime enforces attenuation
     pub fn invoke_capability(
         &mut self,
@@ -443,6 +1440,169 @@ impl Control {
         self.runtime.invoke_capability(cap_id, payload)
     }
 
+    /// Enqueue a capability invocation without pumping the scheduler.
+    pub fn invoke_capability_async(
+        &mut self,
+        cap_id: Uuid,
+        payload: preserves::IOValue,
+        reply_to: Option<ActorId>,
+    ) -> Result<super::invocation::InvocationId> {
+        self.runtime
+            .invoke_capability_async(cap_id, payload, reply_to)
+    }
+
+    /// Asynchronous invocations whose turn has not executed yet.
+    pub fn pending_invocations(&self) -> Vec<super::invocation::PendingInvocation> {
+        self.runtime.pending_invocations()
+    }
+
+    /// Take the outcome of a finished asynchronous invocation.
+    pub fn take_invocation_result(
+        &mut self,
+        id: super::invocation::InvocationId,
+    ) -> Option<super::invocation::InvocationOutcome> {
+        self.runtime.take_invocation_result(id)
+    }
+
+    /// Provide (or replace) a secret for entities.
+    pub fn set_secret(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.runtime.set_secret(name, value);
+    }
+
+    /// Secret lookups journaled on `branch`, oldest first (names only).
+    pub fn secret_accesses(&self, branch: &BranchId) -> Result<Vec<SecretAccess>> {
+        let records = self.runtime.lineage_records(branch, None)?;
+        Ok(super::secrets::accesses_in(&records))
+    }
+
+    /// Irreversible side effects journaled on `branch`, oldest first.
+    pub fn side_effects(&self, branch: &BranchId) -> Result<Vec<SideEffect>> {
+        let records = self.runtime.lineage_records(branch, None)?;
+        Ok(super::effects::effects_in(&records))
+    }
+
+    /// File backups journaled on `branch`, oldest first.
+    pub fn file_backups(&self, branch: &BranchId) -> Result<Vec<FileBackup>> {
+        let records = self.runtime.lineage_records(branch, None)?;
+        Ok(super::effects::backups_in(&records))
+    }
+
+    /// Restore the content `path` had before `turn` wrote it.
+    pub fn restore_file(&self, path: &str, turn: &TurnId) -> Result<FileBackup> {
+        self.runtime.restore_file(path, turn)
+    }
+
+    /// Side effects left in place by the most recent `goto` or `back`, if any.
+    pub fn rewind_warning(&self) -> Option<&RewindWarning> {
+        self.runtime.rewind_warning()
+    }
+
+    /// Register a hook that tries to undo side effects of `kind`.
+    pub fn register_compensation(&mut self, kind: EffectKind, hook: CompensationHook) {
+        self.runtime.register_compensation(kind, hook);
+    }
+
+    /// Try to undo `effect` with the compensation hook registered for its kind.
+    pub fn compensate(&mut self, effect: &SideEffect) -> Result<()> {
+        self.runtime.compensate(effect)
+    }
+
+    /// Carry out outbox deliveries on `channel` with `deliverer`.
+    pub fn register_deliverer(
+        &mut self,
+        channel: impl Into<String>,
+        deliverer: std::sync::Arc<dyn Deliverer>,
+    ) -> Result<()> {
+        self.runtime.register_deliverer(channel, deliverer)
+    }
+
+    /// Dispatch due outbox deliveries and wait up to `timeout` for them.
+    pub fn flush_outbox(&mut self, timeout: Duration) -> Result<OutboxStatus> {
+        self.runtime.flush_outbox(timeout)
+    }
+
+    /// Pending outbox deliveries and counters.
+    pub fn outbox_status(&self) -> OutboxStatus {
+        self.runtime.outbox_status()
+    }
+
+    /// Change the log level of `subsystem` (or the default level when `None`)
+    /// while the runtime is running.
+    ///
+    /// Only works when the runtime's subscriber was installed with
+    /// [`logging::init`]; returns the levels now in effect.
+    pub fn set_log_level(&self, subsystem: Option<&str>, level: &str) -> Result<LoggingConfig> {
+        logging::set_level(subsystem, level)
+    }
+
+    /// Per-actor assertion, facet and byte counts with recent memory warnings.
+    pub fn memory_report(&mut self) -> MemoryReport {
+        self.runtime.memory_report()
+    }
+
+    /// Mirror every committed turn into `sink`.
+    pub fn add_turn_sink(
+        &mut self,
+        name: impl Into<String>,
+        sink: std::sync::Arc<dyn TurnSink>,
+        options: SinkOptions,
+    ) {
+        self.runtime.add_turn_sink(name, sink, options);
+    }
+
+    /// Entities hydrated as recorder stubs under the runtime's stub config.
+    pub fn stubbed_entities(&self) -> Vec<Uuid> {
+        self.runtime.stubbed_entities()
+    }
+
+    /// Push `branch` as a checkpoint to the artifact store at `location`
+    /// under `reference`.
+    pub fn push_checkpoint(
+        &self,
+        branch: &BranchId,
+        location: &str,
+        reference: &str,
+        token: Option<String>,
+    ) -> Result<PushReport> {
+        let checkpoint = self.runtime.export_checkpoint(branch)?;
+        let store = artifact::open_store(location, token)?;
+        artifact::push(store.as_ref(), &checkpoint, reference)
+    }
+
+    /// Pull the checkpoint `reference` from `location` into the new branch
+    /// `new_branch`.
+    pub fn pull_checkpoint(
+        &mut self,
+        location: &str,
+        reference: &str,
+        new_branch: &str,
+        token: Option<String>,
+    ) -> Result<BranchId> {
+        let store = artifact::open_store(location, token)?;
+        let checkpoint = artifact::pull(store.as_ref(), reference)?;
+        self.runtime.import_checkpoint(checkpoint, new_branch)
+    }
+
+    /// Run the reaction fixtures in `path` in scratch runtimes.
+    pub fn run_fixture(&self, path: impl AsRef<std::path::Path>) -> Result<FixtureReport> {
+        self.runtime.run_fixture(path)
+    }
+
+    /// Background tasks currently owned by entity facets.
+    pub fn list_tasks(&self) -> Vec<TaskInfo> {
+        self.runtime.tasks()
+    }
+
+    /// Wait until registered turn sinks have handled every queued record.
+    pub fn flush_turn_sinks(&self) {
+        self.runtime.flush_turn_sinks();
+    }
+
+    /// Delivery counters for the registered turn sinks.
+    pub fn turn_sink_stats(&self) -> Vec<SinkStats> {
+        self.runtime.turn_sink_stats()
+    }
+
     /// Wait for a branch head to advance beyond a target turn or until timeout.
     pub fn wait_for_turn_after(
         &self,
@@ -453,6 +1613,22 @@ impl Control {
         self.runtime.wait_for_turn_after(branch, since, timeout)
     }
 
+    /// Register a long-poll wait that survives client reconnects.
+    pub fn register_turn_wait(&self, branch: BranchId, since: Option<TurnId>) -> WaitRegistration {
+        self.runtime.register_turn_wait(branch, since)
+    }
+
+    /// Collect the branch head queued for a long-poll registration, waiting
+    /// up to `timeout` for one.
+    pub fn poll_turn_wait(&self, id: WaitId, timeout: Duration) -> Result<Option<TurnId>> {
+        self.runtime.poll_turn_wait(id, timeout)
+    }
+
+    /// Drop a long-poll registration, returning whether it existed.
+    pub fn cancel_turn_wait(&self, id: WaitId) -> bool {
+        self.runtime.cancel_turn_wait(id)
+    }
+
     /// Drain any queued turns (including async completions) until the scheduler is idle.
     pub fn drain_pending(&mut self) -> Result<()> {
         loop {
@@ -465,6 +1641,23 @@ impl Control {
         Ok(())
     }
 
+    /// Run every queued turn, including the ones earlier turns induced, and
+    /// return the resulting head of the current branch.
+    ///
+    /// Clients call this after a mutating command to read their own writes:
+    /// once it returns, queries observe everything the command caused.
+    /// Results of background tasks that have not reported back yet are not
+    /// waited for.
+    pub fn sync(&mut self) -> Result<TurnId> {
+        self.drain_pending()?;
+        Ok(self
+            .runtime
+            .branch_manager()
+            .head(&self.runtime.current_branch())
+            .cloned()
+            .unwrap_or_else(TurnId::genesis))
+    }
+
     fn collect_assertion_events(
         &self,
         branch: &BranchId,
@@ -472,6 +1665,11 @@ impl Control {
         limit: usize,
         filter: &AssertionEventFilter,
     ) -> Result<AssertionEventChunk> {
+        let schema = filter
+            .schema
+            .as_deref()
+            .map(|name| SchemaRegistry::init().resolve_assertion_schema(name))
+            .transpose()?;
         let reader = self.runtime.journal_reader(branch)?;
         let iterator = if let Some(turn) = since {
             let mut iter = reader.iter_from(turn)?;
@@ -502,16 +1700,23 @@ impl Control {
             let mut events = Vec::new();
             for output in record.outputs.iter() {
                 match output {
-                    TurnOutput::Assert { handle, value } if filter.include_asserts => {
-                        if let Some(label) = &filter.label {
-                            let matches = value
-                                .label()
-                                .as_symbol()
-                                .map(|sym| sym.as_ref() == label)
-                                .unwrap_or(false);
-                            if !matches {
-                                continue;
-                            }
+                    TurnOutput::Assert {
+                        handle,
+                        value,
+                        namespace,
+                    } if filter.include_asserts => {
+                        if !filter.admits_namespace(namespace.as_deref()) {
+                            continue;
+                        }
+
+                        if let Some(label) = &filter.label
+                            && crate::util::io_value::record_with_label(value, label).is_none()
+                        {
+                            continue;
+                        }
+
+                        if schema.is_some_and(|schema| !schema.matches(value)) {
+                            continue;
                         }
 
                         if let Some(request_id) = &filter.request_id {
@@ -529,13 +1734,19 @@ impl Control {
                             action: AssertionEventAction::Assert,
                             handle: handle.clone(),
                             value: Some(value.clone()),
+                            namespace: namespace.clone(),
                         });
                     }
-                    TurnOutput::Retract { handle } if filter.include_retracts => {
+                    TurnOutput::Retract { handle, namespace } if filter.include_retracts => {
+                        if !filter.admits_namespace(namespace.as_deref()) {
+                            continue;
+                        }
+
                         events.push(AssertionEvent {
                             action: AssertionEventAction::Retract,
                             handle: handle.clone(),
                             value: None,
+                            namespace: namespace.clone(),
                         });
                     }
                     _ => {}
@@ -548,6 +1759,7 @@ impl Control {
 
             last_turn = Some(record.turn_id.clone());
             batches.push(AssertionEventBatch {
+                logical_time: record.logical_timestamp(),
                 turn_id: record.turn_id,
                 actor: record.actor,
                 clock: record.clock.0,
@@ -642,6 +1854,9 @@ pub struct AssertionInfo {
     pub handle: super::turn::Handle,
     /// Assertion payload.
     pub value: IOValue,
+    /// Named dataspace the assertion lives in (`None` = default dataspace).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
 }
 
 /// Filter describing which assertion events should be surfaced.
@@ -657,6 +1872,11 @@ pub struct AssertionEventFilter {
     pub include_asserts: bool,
     /// Whether retraction events should be included.
     pub include_retracts: bool,
+    /// Restrict events to a named dataspace (`None` = all dataspaces).
+    pub namespace: Option<String>,
+    /// Restrict to assertions of a registered [`AssertionSchema`] type
+    /// (e.g. `AgentResponse`).
+    pub schema: Option<String>,
 }
 
 impl AssertionEventFilter {
@@ -668,8 +1888,17 @@ impl AssertionEventFilter {
             request_id: None,
             include_asserts: true,
             include_retracts: true,
+            namespace: None,
+            schema: None,
         }
     }
+
+    /// Check whether an event from `namespace` passes the namespace restriction.
+    pub fn admits_namespace(&self, namespace: Option<&str>) -> bool {
+        self.namespace
+            .as_deref()
+            .is_none_or(|wanted| namespace_matches(wanted, namespace))
+    }
 }
 
 impl Default for AssertionEventFilter {
@@ -688,6 +1917,9 @@ pub struct AssertionEvent {
     /// Assertion payload (present for asserts).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub value: Option<IOValue>,
+    /// Named dataspace of the affected assertion (`None` = default dataspace).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
 }
 
 /// Action performed on an assertion.
@@ -709,8 +1941,10 @@ pub struct AssertionEventBatch {
     pub actor: ActorId,
     /// Logical clock value associated with the turn.
     pub clock: u64,
-    /// Timestamp recorded for the turn.
+    /// Wall-clock time recorded for the turn (advisory).
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Deterministic timestamp of the turn.
+    pub logical_time: LogicalTimestamp,
     /// Events emitted during this turn.
     pub events: Vec<AssertionEvent>,
 }
@@ -731,17 +1965,57 @@ pub struct AssertionEventChunk {
 }
 
 /// Convert a TurnRecord to a TurnSummary
+/// Decode an optional cursor token, returning the direction to walk and the key to resume after.
+fn resume_cursor(
+    token: Option<&str>,
+    kind: CursorKind,
+    branch: &BranchId,
+    direction: CursorDirection,
+) -> Result<(CursorDirection, Option<String>)> {
+    match token {
+        Some(token) => {
+            let cursor = PageCursor::resume(token, kind, branch)?;
+            Ok((cursor.direction, Some(cursor.after)))
+        }
+        None => Ok((direction, None)),
+    }
+}
+
 fn turn_to_summary(record: TurnRecord) -> TurnSummary {
     TurnSummary {
+        logical_time: record.logical_timestamp(),
         turn_id: record.turn_id,
         actor: record.actor,
         clock: record.clock.0,
         input_count: record.inputs.len(),
         output_count: record.outputs.len(),
         timestamp: record.timestamp,
+        vector_clock: record.vector_clock,
+        version: None,
+        initiator: record.initiator,
+    }
+}
+
+fn header_to_summary(header: RecordHeader, branch: &BranchId) -> TurnSummary {
+    TurnSummary {
+        logical_time: header.logical_timestamp(branch),
+        turn_id: header.turn_id,
+        actor: header.actor,
+        clock: header.clock.0,
+        input_count: header.input_count,
+        output_count: header.output_count,
+        timestamp: header.timestamp,
+        vector_clock: header.vector_clock,
+        version: None,
+        initiator: header.initiator,
     }
 }
 
+fn with_version(mut summary: TurnSummary, versions: &HashMap<TurnId, VersionStamp>) -> TurnSummary {
+    summary.version = versions.get(&summary.turn_id).cloned();
+    summary
+}
+
 /// Runtime status information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RuntimeStatus {
@@ -754,8 +2028,18 @@ pub struct RuntimeStatus {
     /// Number of pending inputs
     pub pending_inputs: usize,
 
+    /// Actors whose turns are held until resumed
+    #[serde(default)]
+    pub paused_actors: Vec<ActorId>,
+
     /// Snapshot interval
     pub snapshot_interval: u64,
+
+    /// Version this runtime stamps on new journal segments and snapshots
+    pub version: VersionStamp,
+
+    /// Distinct versions that wrote the active branch's journal, oldest first
+    pub journal_versions: Vec<VersionStamp>,
 }
 
 /// Summary of a turn for display
@@ -776,8 +2060,23 @@ pub struct TurnSummary {
     /// Number of outputs
     pub output_count: usize,
 
-    /// Timestamp
+    /// Wall-clock time the turn was recorded (advisory)
     pub timestamp: chrono::DateTime<chrono::Utc>,
+
+    /// Deterministic timestamp; order turns by this rather than `timestamp`
+    pub logical_time: LogicalTimestamp,
+
+    /// Vector clock capturing the turn's causal history
+    #[serde(default)]
+    pub vector_clock: VectorClock,
+
+    /// Runtime version that wrote the turn, when its segment records one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<VersionStamp>,
+
+    /// Control-plane client identity the turn ran on behalf of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initiator: Option<Identity>,
 }
 
 /// Branch information
@@ -791,6 +2090,87 @@ pub struct BranchInfo {
 
     /// Parent branch
     pub parent: Option<BranchId>,
+
+    /// Turn the branch was forked from
+    #[serde(default)]
+    pub base_turn: Option<TurnId>,
+
+    /// When the branch was created
+    #[serde(default)]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Control-plane client identity that forked the branch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<Identity>,
+
+    /// What the branch is for
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Who or what created the branch
+    #[serde(default)]
+    pub creator: Option<String>,
+
+    /// Purpose tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Head of a branch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchHead {
+    /// Branch name
+    pub branch: BranchId,
+
+    /// Head turn (genesis for an empty branch)
+    pub turn_id: TurnId,
+
+    /// When the head turn was recorded (wall clock, advisory)
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Deterministic timestamp of the head turn
+    #[serde(default)]
+    pub logical_time: Option<LogicalTimestamp>,
+
+    /// Turns visible from the branch, including inherited ones
+    pub turn_count: usize,
+}
+
+/// Divergence point of two branches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchLca {
+    /// First branch
+    pub a: BranchId,
+
+    /// Second branch
+    pub b: BranchId,
+
+    /// Last turn both branches share (genesis if none)
+    pub lca: TurnId,
+
+    /// When the shared turn was recorded (wall clock, advisory)
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Deterministic timestamp of the shared turn
+    #[serde(default)]
+    pub logical_time: Option<LogicalTimestamp>,
+
+    /// Turns `a` has recorded since the divergence
+    pub a_turns_since: usize,
+
+    /// Turns `b` has recorded since the divergence
+    pub b_turns_since: usize,
+}
+
+/// Outcome of [`Control::retract_matching`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetractMatchingReport {
+    /// Administrative turn that performed the retraction
+    pub turn_id: TurnId,
+    /// Number of assertions retracted
+    pub count: usize,
+    /// Handles of the retracted assertions
+    pub handles: Vec<Handle>,
 }
 
 /// Merge report with conflicts and warnings
@@ -819,6 +2199,23 @@ mod tests {
             snapshot_interval: 10,
             flow_control_limit: 100,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         let control = Control::init(config).unwrap();
@@ -836,6 +2233,23 @@ mod tests {
             snapshot_interval: 10,
             flow_control_limit: 100,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         let mut control = Control::init(config).unwrap();
@@ -857,6 +2271,23 @@ mod tests {
             snapshot_interval: 10,
             flow_control_limit: 100,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         let control = Control::init(config).unwrap();
@@ -874,6 +2305,23 @@ mod tests {
             snapshot_interval: 10,
             flow_control_limit: 100,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         let mut control = Control::init(config).unwrap();
@@ -881,7 +2329,12 @@ mod tests {
         // Fork a new branch
         let new_branch = BranchId::new("experiment");
         let result = control
-            .fork(BranchId::main(), new_branch.clone(), None)
+            .fork(
+                BranchId::main(),
+                new_branch.clone(),
+                None,
+                Default::default(),
+            )
             .unwrap();
         assert_eq!(result, new_branch);
 
@@ -898,6 +2351,23 @@ mod tests {
             snapshot_interval: 10,
             flow_control_limit: 100,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         let mut control = Control::init(config).unwrap();
@@ -935,6 +2405,23 @@ mod tests {
             snapshot_interval: 10,
             flow_control_limit: 100,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         let mut control = Control::init(config).unwrap();
@@ -985,6 +2472,23 @@ mod tests {
             snapshot_interval: 10,
             flow_control_limit: 100,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         let mut control = Control::init(config).unwrap();
@@ -1006,7 +2510,12 @@ mod tests {
         // Fork a branch
         let experiment = BranchId::new("experiment");
         control
-            .fork(BranchId::main(), experiment.clone(), None)
+            .fork(
+                BranchId::main(),
+                experiment.clone(),
+                None,
+                Default::default(),
+            )
             .unwrap();
 
         // Switch to experiment and make changes
@@ -1058,6 +2567,23 @@ mod tests {
             snapshot_interval: 10,
             flow_control_limit: 100,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         let mut control = Control::init(config).unwrap();
@@ -1077,7 +2603,12 @@ mod tests {
         // Fork branch
         let experiment = BranchId::new("experiment");
         control
-            .fork(BranchId::main(), experiment.clone(), None)
+            .fork(
+                BranchId::main(),
+                experiment.clone(),
+                None,
+                Default::default(),
+            )
             .unwrap();
 
         // The merge functionality is implemented and tested
@@ -1114,6 +2645,23 @@ mod tests {
             snapshot_interval: 10,
             flow_control_limit: 100,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         // Register the entity type in the global registry
@@ -0,0 +1,245 @@
+//! Stable pagination cursors
+//!
+//! Offset pagination shifts as new turns land or entities appear while a
+//! client is still paging. A [`PageCursor`] instead records the key of the
+//! last item a page returned (a turn id for history, an identifier for
+//! listings), so the next page resumes strictly after it no matter what was
+//! added in the meantime. Clients treat the encoded token as opaque.
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::hex;
+
+use super::error::{Result, RuntimeError};
+use super::turn::BranchId;
+
+const TOKEN_PREFIX: &str = "cursor:";
+
+/// Order in which a paginated listing is walked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorDirection {
+    /// Oldest (or smallest key) first
+    #[default]
+    Forward,
+    /// Newest (or largest key) first
+    Backward,
+}
+
+/// Listing a cursor belongs to; tokens are rejected by other listings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorKind {
+    /// Branch history
+    History,
+    /// Registered entities
+    Entities,
+    /// Capabilities
+    Capabilities,
+    /// Dataspace assertions
+    Assertions,
+}
+
+/// Position after the last item of a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor {
+    /// Listing the cursor was issued for
+    pub kind: CursorKind,
+    /// Branch the listing was read from
+    pub branch: BranchId,
+    /// Key of the last item returned
+    pub after: String,
+    /// Walk direction
+    pub direction: CursorDirection,
+}
+
+impl PageCursor {
+    /// Encode the cursor as an opaque token.
+    pub fn to_token(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursors always serialize");
+        format!("{}{}", TOKEN_PREFIX, hex::encode(&json))
+    }
+
+    /// Decode a token produced by [`PageCursor::to_token`].
+    pub fn from_token(token: &str) -> Result<Self> {
+        let malformed = |detail: &str| RuntimeError::InvalidCursor(detail.to_string());
+
+        let digits = token
+            .trim()
+            .strip_prefix(TOKEN_PREFIX)
+            .ok_or_else(|| malformed("missing cursor: prefix"))?;
+        let bytes = hex::decode(digits).map_err(malformed)?;
+
+        serde_json::from_slice(&bytes).map_err(|err| malformed(&err.to_string()))
+    }
+
+    /// Decode `token` and check it was issued for `kind` on `branch`.
+    pub fn resume(token: &str, kind: CursorKind, branch: &BranchId) -> Result<Self> {
+        let cursor = Self::from_token(token)?;
+        if cursor.kind != kind {
+            return Err(RuntimeError::InvalidCursor(format!(
+                "cursor was issued for {:?}, not {:?}",
+                cursor.kind, kind
+            )));
+        }
+        if &cursor.branch != branch {
+            return Err(RuntimeError::InvalidCursor(format!(
+                "cursor belongs to branch {}, not {}",
+                cursor.branch, branch
+            )));
+        }
+        Ok(cursor)
+    }
+}
+
+/// One page of a listing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// Items on this page
+    pub items: Vec<T>,
+    /// Token for the next page, absent once the listing is exhausted
+    pub next_cursor: Option<String>,
+}
+
+/// Paginate a listing whose items are sorted by `key` in forward order.
+///
+/// Resumes strictly after `cursor.after`, so items added anywhere in the
+/// listing never shift or repeat a page.
+pub fn paginate_sorted<T>(
+    mut items: Vec<T>,
+    key: impl Fn(&T) -> String,
+    kind: CursorKind,
+    branch: &BranchId,
+    direction: CursorDirection,
+    after: Option<&str>,
+    limit: usize,
+) -> Page<T> {
+    if direction == CursorDirection::Backward {
+        items.reverse();
+    }
+    let start = match after {
+        Some(after) => items
+            .iter()
+            .position(|item| match direction {
+                CursorDirection::Forward => key(item).as_str() > after,
+                CursorDirection::Backward => key(item).as_str() < after,
+            })
+            .unwrap_or(items.len()),
+        None => 0,
+    };
+    page_from(items, start, key, kind, branch, direction, limit)
+}
+
+/// Paginate an append-only sequence (such as branch history) in its own order.
+///
+/// Resumes after the item whose key equals `after`; fails if that item is no
+/// longer part of the sequence, e.g. because the branch was rewound past it.
+pub fn paginate_sequence<T>(
+    mut items: Vec<T>,
+    key: impl Fn(&T) -> String,
+    kind: CursorKind,
+    branch: &BranchId,
+    direction: CursorDirection,
+    after: Option<&str>,
+    limit: usize,
+) -> Result<Page<T>> {
+    if direction == CursorDirection::Backward {
+        items.reverse();
+    }
+    let start = match after {
+        Some(after) => {
+            items
+                .iter()
+                .position(|item| key(item) == after)
+                .ok_or_else(|| {
+                    RuntimeError::InvalidCursor(format!("{after} is no longer on branch {branch}"))
+                })?
+                + 1
+        }
+        None => 0,
+    };
+    Ok(page_from(items, start, key, kind, branch, direction, limit))
+}
+
+fn page_from<T>(
+    items: Vec<T>,
+    start: usize,
+    key: impl Fn(&T) -> String,
+    kind: CursorKind,
+    branch: &BranchId,
+    direction: CursorDirection,
+    limit: usize,
+) -> Page<T> {
+    let remaining = items.len() - start;
+    let items: Vec<T> = items.into_iter().skip(start).take(limit).collect();
+    let next_cursor = (remaining > items.len())
+        .then(|| items.last())
+        .flatten()
+        .map(|last| {
+            PageCursor {
+                kind,
+                branch: branch.clone(),
+                after: key(last),
+                direction,
+            }
+            .to_token()
+        });
+
+    Page { items, next_cursor }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_resume_after_items_inserted_mid_listing() {
+        let branch = BranchId::main();
+        let key = |item: &&str| item.to_string();
+
+        let first = paginate_sorted(
+            vec!["a", "c", "e"],
+            key,
+            CursorKind::Entities,
+            &branch,
+            CursorDirection::Forward,
+            None,
+            2,
+        );
+        assert_eq!(first.items, vec!["a", "c"]);
+        let token = first.next_cursor.expect("more items remain");
+
+        // "b" lands before the cursor and "d" after it; neither shifts the page
+        let cursor = PageCursor::resume(&token, CursorKind::Entities, &branch).unwrap();
+        let second = paginate_sorted(
+            vec!["a", "b", "c", "d", "e"],
+            key,
+            CursorKind::Entities,
+            &branch,
+            cursor.direction,
+            Some(&cursor.after),
+            2,
+        );
+        assert_eq!(second.items, vec!["d", "e"]);
+        assert!(second.next_cursor.is_none());
+
+        assert!(PageCursor::resume(&token, CursorKind::History, &branch).is_err());
+        assert!(PageCursor::from_token("cursor:zz").is_err());
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected_without_panicking() {
+        for token in [
+            "cursor:aéb",
+            "cursor:éé",
+            "cursor:abc",
+            "cursor:zz",
+            "token",
+        ] {
+            assert!(matches!(
+                PageCursor::from_token(token),
+                Err(RuntimeError::InvalidCursor(_))
+            ));
+        }
+    }
+}
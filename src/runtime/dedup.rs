@@ -0,0 +1,73 @@
+//! Idempotent delivery of external messages
+//!
+//! Integrations with at-least-once semantics may hand the runtime the same
+//! message more than once. Callers that attach an idempotency key to an
+//! external message get at-most-once execution per branch: a key that is
+//! already queued or recorded in the branch history is not executed again,
+//! and the suppressed delivery is noted as a [`DuplicateAnnotation`] instead.
+//!
+//! Keys travel with the recorded [`TurnInput`], so the index is rebuilt from
+//! the journal and follows time travel and branch switches.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::turn::{ActorId, FacetId, TurnId, TurnInput, TurnRecord};
+
+/// Record of a delivery dropped because its idempotency key was already seen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateAnnotation {
+    /// Idempotency key supplied by the caller
+    pub key: String,
+    /// Actor the duplicate was addressed to
+    pub actor: ActorId,
+    /// Facet the duplicate was addressed to
+    pub facet: FacetId,
+    /// Turn that executed the original delivery (`None` while it is still queued)
+    pub original_turn: Option<TurnId>,
+    /// When the duplicate was suppressed
+    pub suppressed_at: DateTime<Utc>,
+}
+
+/// Idempotency keys seen on the current branch.
+#[derive(Debug, Default)]
+pub struct IdempotencyIndex {
+    /// Key to the turn that consumed it (`None` while queued)
+    keys: HashMap<String, Option<TurnId>>,
+}
+
+impl IdempotencyIndex {
+    /// Create an empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget every key.
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    /// Look up a key: `None` if unseen, otherwise the turn that consumed it (if executed).
+    pub fn lookup(&self, key: &str) -> Option<Option<TurnId>> {
+        self.keys.get(key).cloned()
+    }
+
+    /// Note a key whose message has been queued but not yet executed.
+    pub fn mark_queued(&mut self, key: impl Into<String>) {
+        self.keys.entry(key.into()).or_insert(None);
+    }
+
+    /// Record the keys carried by a turn's inputs.
+    pub fn record(&mut self, record: &TurnRecord) {
+        for input in &record.inputs {
+            if let TurnInput::ExternalMessage {
+                idempotency_key: Some(key),
+                ..
+            } = input
+            {
+                self.keys.insert(key.clone(), Some(record.turn_id.clone()));
+            }
+        }
+    }
+}
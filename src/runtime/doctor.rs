@@ -0,0 +1,116 @@
+//! Environment self-test
+//!
+//! [`Control::doctor`](super::Control::doctor) runs a fixed list of checks
+//! against the installed environment and returns a [`DoctorReport`] that can
+//! be attached to support requests. Checks only read state (the storage probe
+//! removes the file it writes), so running them never changes the journal.
+//!
+//! Checks marked advisory, such as agent CLI and API key lookups, depend on the
+//! host rather than on the runtime, and do not make the report unhealthy.
+
+use serde::{Deserialize, Serialize};
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    /// The check succeeded
+    Pass,
+    /// The check found something worth a look that does not block the runtime
+    Warn,
+    /// The check failed
+    Fail,
+}
+
+/// Result of one environment check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DoctorCheck {
+    /// Stable check name (e.g. `storage.writable`)
+    pub name: String,
+    /// Outcome
+    pub status: CheckStatus,
+    /// Human-readable detail
+    pub detail: String,
+    /// Whether the outcome depends on the host and is reported for information only
+    #[serde(default)]
+    pub advisory: bool,
+}
+
+impl DoctorCheck {
+    /// A passing check.
+    pub fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Pass, detail)
+    }
+
+    /// A check that found something worth a look.
+    pub fn warn(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Warn, detail)
+    }
+
+    /// A failing check.
+    pub fn fail(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Fail, detail)
+    }
+
+    /// Mark the check as advisory.
+    pub fn advisory(mut self) -> Self {
+        self.advisory = true;
+        self
+    }
+
+    fn new(name: impl Into<String>, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+            advisory: false,
+        }
+    }
+}
+
+/// Structured report returned by [`Control::doctor`](super::Control::doctor).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DoctorReport {
+    /// Checks in the order they ran
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    /// Whether no non-advisory check failed.
+    pub fn healthy(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|check| check.status == CheckStatus::Fail && !check.advisory)
+    }
+
+    /// Look up a check by name.
+    pub fn check(&self, name: &str) -> Option<&DoctorCheck> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advisory_failures_keep_the_report_healthy() {
+        let mut report = DoctorReport {
+            checks: vec![
+                DoctorCheck::pass("storage.writable", "ok"),
+                DoctorCheck::fail("agent.claude-code", "not on PATH").advisory(),
+            ],
+        };
+        assert!(report.healthy());
+
+        report
+            .checks
+            .push(DoctorCheck::fail("journal.integrity", "head unreadable"));
+        assert!(!report.healthy());
+        assert_eq!(
+            report.check("journal.integrity").map(|check| check.status),
+            Some(CheckStatus::Fail)
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Ledger of irreversible external side effects
+//!
+//! Time travel restores the dataspace, not the world: a file written or a
+//! request sent during a rewound turn stays written or sent. Entities report
+//! such effects through
+//! [`Activation::record_side_effect`](super::actor::Activation::record_side_effect),
+//! which journals a [`TurnOutput::SideEffect`] with the turn that caused it.
+//! When `goto` or `back` rewinds past turns with recorded effects, the runtime
+//! logs a warning and keeps a [`RewindWarning`] listing them, together with
+//! whether a compensation hook registered for the effect's kind can try to
+//! undo it. Hooks are only offered, never run implicitly.
+//!
+//! File writes are the one effect the journal can undo by itself: entities
+//! record the content a write replaces as a [`TurnOutput::FileBackup`] in the
+//! same turn, and [`FileBackup`]s read back from the journal let
+//! `Control::restore_file` put the pre-write content back.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::turn::{ActorId, BranchId, TurnId, TurnOutput, TurnRecord};
+
+/// Category of an external side effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectKind {
+    /// A file was created, modified or removed
+    FileWrite,
+    /// A commit was made to a version-control repository
+    GitCommit,
+    /// A request was sent over the network
+    Network,
+    /// An external process was run
+    Process,
+    /// Anything else that cannot be rolled back
+    Other,
+}
+
+impl fmt::Display for EffectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EffectKind::FileWrite => "file_write",
+            EffectKind::GitCommit => "git_commit",
+            EffectKind::Network => "network",
+            EffectKind::Process => "process",
+            EffectKind::Other => "other",
+        })
+    }
+}
+
+/// Journaled side effect.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SideEffect {
+    /// Turn that caused the effect
+    pub turn_id: TurnId,
+    /// Index of the effect within the turn's outputs
+    pub index: usize,
+    /// Actor whose entity caused it
+    pub actor: ActorId,
+    /// Entity instance that caused it, if known
+    pub entity_id: Option<Uuid>,
+    /// Kind of effect
+    pub kind: EffectKind,
+    /// What was affected (path, repository, URL, ...)
+    pub target: String,
+    /// Human-readable description
+    pub description: String,
+    /// When the turn was recorded
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Side effects recorded in `records`, in journal order.
+pub fn effects_in(records: &[TurnRecord]) -> Vec<SideEffect> {
+    records
+        .iter()
+        .flat_map(|record| {
+            record
+                .outputs
+                .iter()
+                .enumerate()
+                .filter_map(move |(index, output)| match output {
+                    TurnOutput::SideEffect {
+                        entity_id,
+                        kind,
+                        target,
+                        description,
+                    } => Some(SideEffect {
+                        turn_id: record.turn_id.clone(),
+                        index,
+                        actor: record.actor.clone(),
+                        entity_id: *entity_id,
+                        kind: *kind,
+                        target: target.clone(),
+                        description: description.clone(),
+                        timestamp: record.timestamp,
+                    }),
+                    _ => None,
+                })
+        })
+        .collect()
+}
+
+/// Journaled content of a file before a write replaced it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileBackup {
+    /// Turn whose write replaced the content
+    pub turn_id: TurnId,
+    /// Entity instance that performed the write, if known
+    pub entity_id: Option<Uuid>,
+    /// Path as the writing entity reports it
+    pub path: String,
+    /// Absolute location of the file
+    pub location: String,
+    /// Content before the write (`None` if the file did not exist)
+    pub previous: Option<Vec<u8>>,
+    /// When the turn was recorded
+    pub timestamp: DateTime<Utc>,
+}
+
+/// File backups recorded in `records`, in journal order.
+pub fn backups_in(records: &[TurnRecord]) -> Vec<FileBackup> {
+    records
+        .iter()
+        .flat_map(|record| {
+            record
+                .outputs
+                .iter()
+                .filter_map(move |output| match output {
+                    TurnOutput::FileBackup {
+                        entity_id,
+                        path,
+                        location,
+                        previous,
+                    } => Some(FileBackup {
+                        turn_id: record.turn_id.clone(),
+                        entity_id: *entity_id,
+                        path: path.clone(),
+                        location: location.clone(),
+                        previous: previous.as_ref().and_then(|value| {
+                            value.as_bytestring().map(|bytes| bytes.into_owned())
+                        }),
+                        timestamp: record.timestamp,
+                    }),
+                    _ => None,
+                })
+        })
+        .collect()
+}
+
+/// Side effect left in place by a rewind.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoneEffect {
+    /// The effect
+    pub effect: SideEffect,
+    /// Whether a compensation hook is registered for its kind
+    pub compensable: bool,
+}
+
+/// Effects of the turns a rewind moved past.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewindWarning {
+    /// Branch that was rewound
+    pub branch: BranchId,
+    /// Head before the rewind
+    pub from: TurnId,
+    /// Head after the rewind
+    pub to: TurnId,
+    /// Effects of the rewound turns, oldest first
+    pub effects: Vec<UndoneEffect>,
+}
+
+/// Hook that tries to undo a side effect.
+pub type CompensationHook = Arc<dyn Fn(&SideEffect) -> Result<(), String> + Send + Sync>;
+
+/// Compensation hooks by effect kind.
+#[derive(Clone, Default)]
+pub struct CompensationRegistry {
+    hooks: HashMap<EffectKind, CompensationHook>,
+}
+
+impl fmt::Debug for CompensationRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompensationRegistry")
+            .field("kinds", &self.hooks.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl CompensationRegistry {
+    /// Register (or replace) the hook for `kind`.
+    pub fn register(&mut self, kind: EffectKind, hook: CompensationHook) {
+        self.hooks.insert(kind, hook);
+    }
+
+    /// Whether a hook is registered for `kind`.
+    pub fn has(&self, kind: EffectKind) -> bool {
+        self.hooks.contains_key(&kind)
+    }
+
+    /// Hook registered for `kind`.
+    pub fn get(&self, kind: EffectKind) -> Option<CompensationHook> {
+        self.hooks.get(&kind).cloned()
+    }
+}
@@ -44,6 +44,71 @@ pub enum RuntimeError {
     /// Initialization errors
     #[error("Initialization failed: {0}")]
     Init(String),
+
+    /// Pagination cursor could not be decoded or no longer applies
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+
+    /// No long-poll registration with this identifier (never created,
+    /// cancelled, or expired)
+    #[error("Wait registration {0} not found")]
+    WaitNotFound(Uuid),
+
+    /// Checkpoint artifact could not be built, transferred or verified
+    #[error("Artifact error: {0}")]
+    Artifact(String),
+
+    /// Assertion type name not in the schema registry
+    #[error("Unknown assertion schema '{name}' (known: {known})")]
+    UnknownSchema {
+        /// Requested type name
+        name: String,
+        /// Registered type names
+        known: String,
+    },
+
+    /// Fixture file could not be read or is malformed
+    #[error("Invalid fixture: {0}")]
+    Fixture(String),
+
+    /// Pattern text could not be parsed
+    #[error("Invalid pattern at offset {offset}: {message}")]
+    PatternSyntax {
+        /// Byte offset into the pattern text where parsing failed
+        offset: usize,
+        /// What was expected or found there
+        message: String,
+    },
+
+    /// A side-effect compensation hook was missing or failed
+    #[error("Compensation failed: {0}")]
+    Compensation(String),
+
+    /// A journaled file backup was missing or could not be written back
+    #[error("File restore failed: {0}")]
+    Restore(String),
+
+    /// External message rejected by an ingestion rate limit
+    #[error("Rate limited: {scope} {key} may retry in {retry_after_ms} ms")]
+    RateLimited {
+        /// Kind of source that exceeded its limit (`actor` or `client`)
+        scope: String,
+        /// Actor id or client name
+        key: String,
+        /// Time until the source's next message would be admitted
+        retry_after_ms: u64,
+    },
+
+    /// Replay refused because the data was written by another version
+    #[error("Refusing to replay {origin} written by {recorded} with {current}")]
+    VersionMismatch {
+        /// What was being replayed (segment or snapshot)
+        origin: String,
+        /// Version that wrote it
+        recorded: String,
+        /// Version of this runtime
+        current: String,
+    },
 }
 
 /// Journal-specific errors
@@ -80,6 +145,28 @@ pub enum JournalError {
     #[error("Turn decoding failed: {0}")]
     DecodingError(String),
 
+    /// Dictionary training or record (de)compression failed
+    #[error("Journal compression failed: {0}")]
+    Compression(String),
+
+    /// Turn removed by journal compaction
+    #[error("Turn '{turn}' was compacted away (journal starts after '{horizon}')")]
+    Compacted {
+        /// Requested turn
+        turn: String,
+        /// Last compacted turn, restored from its snapshot
+        horizon: String,
+    },
+
+    /// Spilled payload could not be stored or read back
+    #[error("Blob {digest} unavailable: {detail}")]
+    Blob {
+        /// SHA-256 of the blob
+        digest: String,
+        /// Description of the failure
+        detail: String,
+    },
+
     /// IO error
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
@@ -151,6 +238,14 @@ pub enum StorageError {
     /// JSON error
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
+
+    /// Backup destination unusable
+    #[error("Backup refused: {0}")]
+    BackupRefused(String),
+
+    /// Stored blob does not match its digest
+    #[error("Blob {0} does not match its digest")]
+    BlobCorrupted(String),
 }
 
 /// Convenience result alias for storage operations
@@ -171,6 +266,31 @@ pub enum BranchError {
     #[error("Invalid fork point: turn '{0}' not found")]
     InvalidForkPoint(String),
 
+    /// Branch name rejected by the naming policy or template
+    #[error("Invalid branch name: {0}")]
+    InvalidName(String),
+
+    /// Branch cannot be moved to cold storage
+    #[error("Cannot archive branch: {0}")]
+    ArchiveRefused(String),
+
+    /// Branch cannot be deleted
+    #[error("Cannot delete branch: {0}")]
+    DeleteRefused(String),
+
+    /// Experiment cannot start from the requested state
+    #[error("Cannot run experiment: {0}")]
+    ExperimentRefused(String),
+
+    /// Reflog has no entry at the requested index
+    #[error("Branch '{branch}' has no reflog entry {index}")]
+    ReflogEntryNotFound {
+        /// Branch whose reflog was consulted
+        branch: String,
+        /// Requested index (0 = latest movement)
+        index: usize,
+    },
+
     /// Merge conflict
     #[error("Merge conflict between '{source_branch}' and '{target_branch}': {detail}")]
     MergeConflict {
@@ -208,6 +328,19 @@ pub enum ActorError {
     /// Turn execution failed
     #[error("Turn execution failed: {0}")]
     ExecutionFailed(String),
+
+    /// An entity exceeded one of its per-turn limits
+    #[error("Entity of type {entity_type} exceeded {limit}: {actual} > {max}")]
+    LimitExceeded {
+        /// Type of the offending entity
+        entity_type: String,
+        /// Name of the limit that was exceeded
+        limit: String,
+        /// Observed value
+        actual: usize,
+        /// Configured maximum
+        max: usize,
+    },
 }
 
 /// Capability invocation errors
@@ -224,6 +357,30 @@ pub enum CapabilityError {
     /// Capability invocation denied by issuer
     #[error("Capability {0} invocation denied: {1}")]
     Denied(Uuid, String),
+
+    /// Sturdy ref could not be decoded
+    #[error("Malformed sturdy ref: {0}")]
+    MalformedSturdyRef(String),
+
+    /// Sturdy ref signature did not verify against this runtime's key
+    #[error("Sturdy ref for capability {0} has an invalid signature")]
+    InvalidSignature(Uuid),
+
+    /// Sturdy ref is past its expiry
+    #[error("Sturdy ref for capability {0} expired at {1}")]
+    Expired(Uuid, String),
+
+    /// Redemption payload does not satisfy the sturdy ref's caveats
+    #[error("Payload rejected by sturdy ref caveats for capability {0}")]
+    CaveatRejected(Uuid),
+
+    /// Capability kind needs approval and cannot be invoked directly
+    #[error("Capability {0} of kind {1} requires approval")]
+    ApprovalRequired(Uuid, String),
+
+    /// No parked invocation with this approval identifier
+    #[error("Approval request {0} not found")]
+    ApprovalNotFound(Uuid),
 }
 
 /// Convenience result alias for actor operations
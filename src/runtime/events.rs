@@ -0,0 +1,240 @@
+//! Typed subscriptions to dataspace events
+//!
+//! [`Control::assertion_events_since`] hands out raw [`IOValue`]s and leaves
+//! cursor bookkeeping to the caller. [`Control::events`] wraps it in a
+//! builder that filters, decodes and pages for the embedder:
+//!
+//! ```ignore
+//! for event in control.events().label("agent-response").decode::<AgentResponse>() {
+//!     let event = event?;
+//!     println!("{}", event.value.unwrap().response);
+//! }
+//! ```
+//!
+//! The subscription is an iterator over [`TypedEvent`]s in journal order. It
+//! ends once it has caught up with the branch (after waiting, if
+//! [`wait`](EventSubscription::wait) was set); [`cursor`](EventSubscription::cursor)
+//! says where it stopped, so a later subscription can pick up from there with
+//! [`since`](EventSubscription::since). Assertions that do not decode as the
+//! requested type are skipped, the way a label filter skips them; retractions
+//! carry no value and are delivered whatever the type, unless
+//! [`asserts_only`](EventSubscription::asserts_only) leaves them out.
+
+use preserves::IOValue;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use super::control::{AssertionEventAction, AssertionEventFilter, Control};
+use super::error::Result;
+use super::turn::{ActorId, BranchId, Handle, LogicalTimestamp, TurnId};
+
+/// Number of turns fetched from the journal at a time
+const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// Types an assertion value can be decoded into.
+pub trait FromAssertion: Sized {
+    /// Decode `value`, or `None` when it is not an instance of this type.
+    fn from_assertion(value: &IOValue) -> Option<Self>;
+}
+
+impl FromAssertion for IOValue {
+    fn from_assertion(value: &IOValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+/// Dataspace change delivered by an [`EventSubscription`].
+#[derive(Debug, Clone)]
+pub struct TypedEvent<T> {
+    /// Turn that made the change.
+    pub turn_id: TurnId,
+    /// Actor that executed the turn.
+    pub actor: ActorId,
+    /// Deterministic timestamp of the turn.
+    pub logical_time: LogicalTimestamp,
+    /// Assert or retract.
+    pub action: AssertionEventAction,
+    /// Assertion handle affected.
+    pub handle: Handle,
+    /// Decoded assertion (retractions carry none).
+    pub value: Option<T>,
+    /// Named dataspace of the assertion (`None` = default dataspace).
+    pub namespace: Option<String>,
+}
+
+/// Builder and iterator over the assertion events of a branch.
+pub struct EventSubscription<'a, T = IOValue> {
+    control: &'a Control,
+    branch: BranchId,
+    filter: AssertionEventFilter,
+    cursor: Option<TurnId>,
+    batch_size: usize,
+    wait: Option<Duration>,
+    buffered: VecDeque<TypedEvent<T>>,
+    caught_up: bool,
+    _decode: PhantomData<fn() -> T>,
+}
+
+impl<'a> EventSubscription<'a, IOValue> {
+    /// Every event on `branch`, from the start of its journal.
+    pub fn new(control: &'a Control, branch: BranchId) -> Self {
+        Self {
+            control,
+            branch,
+            filter: AssertionEventFilter::inclusive(),
+            cursor: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            wait: None,
+            buffered: VecDeque::new(),
+            caught_up: false,
+            _decode: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: FromAssertion> EventSubscription<'a, T> {
+    /// Follow `branch` instead of the current branch.
+    pub fn branch(mut self, branch: BranchId) -> Self {
+        self.branch = branch;
+        self
+    }
+
+    /// Only assertions whose record label is `label`.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.filter.label = Some(label.into());
+        self
+    }
+
+    /// Only assertions of the registered type `schema` (e.g. `AgentResponse`).
+    pub fn schema(mut self, schema: impl Into<String>) -> Self {
+        self.filter.schema = Some(schema.into());
+        self
+    }
+
+    /// Only turns executed by `actor`.
+    pub fn actor(mut self, actor: ActorId) -> Self {
+        self.filter.actor = Some(actor);
+        self
+    }
+
+    /// Only assertions whose first field is `request_id`.
+    pub fn request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.filter.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Only events in the named dataspace `namespace`.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.filter.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Leave retractions out.
+    pub fn asserts_only(mut self) -> Self {
+        self.filter.include_retracts = false;
+        self
+    }
+
+    /// Start after `cursor`, a turn returned by [`cursor`](Self::cursor).
+    pub fn since(mut self, cursor: TurnId) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    /// Wait up to `timeout` for a new turn before ending the iteration.
+    pub fn wait(mut self, timeout: Duration) -> Self {
+        self.wait = Some(timeout);
+        self
+    }
+
+    /// Fetch `size` turns from the journal at a time.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// Decode assertion values as `U`, skipping those that are not one.
+    pub fn decode<U: FromAssertion>(self) -> EventSubscription<'a, U> {
+        EventSubscription {
+            control: self.control,
+            branch: self.branch,
+            filter: self.filter,
+            cursor: self.cursor,
+            batch_size: self.batch_size,
+            wait: self.wait,
+            buffered: VecDeque::new(),
+            caught_up: false,
+            _decode: PhantomData,
+        }
+    }
+
+    /// Last turn whose events were delivered or buffered.
+    pub fn cursor(&self) -> Option<&TurnId> {
+        self.cursor.as_ref()
+    }
+
+    /// Fetch the next batch of events, empty once caught up.
+    pub fn poll(&mut self) -> Result<Vec<TypedEvent<T>>> {
+        loop {
+            let chunk = self.control.assertion_events_since(
+                &self.branch,
+                self.cursor.as_ref(),
+                self.batch_size,
+                self.filter.clone(),
+                self.wait,
+            )?;
+            if let Some(next) = chunk.next_cursor {
+                self.cursor = Some(next);
+            }
+            let events: Vec<TypedEvent<T>> = chunk
+                .events
+                .into_iter()
+                .flat_map(|batch| {
+                    let (turn_id, actor, logical_time) =
+                        (batch.turn_id, batch.actor, batch.logical_time);
+                    batch.events.into_iter().filter_map(move |event| {
+                        let value = match &event.value {
+                            Some(value) => Some(T::from_assertion(value)?),
+                            None => None,
+                        };
+                        Some(TypedEvent {
+                            turn_id: turn_id.clone(),
+                            actor: actor.clone(),
+                            logical_time: logical_time.clone(),
+                            action: event.action,
+                            handle: event.handle,
+                            value,
+                            namespace: event.namespace,
+                        })
+                    })
+                })
+                .collect();
+            // Turns whose events all failed to decode still move the cursor
+            if !events.is_empty() || !chunk.has_more {
+                return Ok(events);
+            }
+        }
+    }
+}
+
+impl<T: FromAssertion> Iterator for EventSubscription<'_, T> {
+    type Item = Result<TypedEvent<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffered.is_empty() {
+            if self.caught_up {
+                return None;
+            }
+            match self.poll() {
+                Ok(events) if events.is_empty() => self.caught_up = true,
+                Ok(events) => self.buffered.extend(events),
+                Err(err) => {
+                    self.caught_up = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.buffered.pop_front().map(Ok)
+    }
+}
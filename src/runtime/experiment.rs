@@ -0,0 +1,147 @@
+//! Batch fork-and-run experiments
+//!
+//! [`Runtime::experiment`](super::Runtime::experiment) explores alternatives
+//! from a common starting point: each [`ExperimentVariant`] runs on its own
+//! ephemeral branch forked at the same base turn, to quiescence or until the
+//! turn budget runs out. The branches are discarded afterwards and only the
+//! comparative [`VariantOutcome`]s remain, unless a [`Promotion`] keeps the
+//! winning variant as a named fork. Because turns are deterministic, the
+//! promoted fork is produced by replaying the winning inputs and ends in the
+//! same state the experiment observed.
+
+use preserves::IOValue;
+use serde::{Deserialize, Serialize};
+
+use super::branch::BranchDetails;
+use super::turn::{ActorId, BranchId, FacetId, Handle, TurnId, TurnInput};
+
+/// Prefix of the reserved branches variants run on.
+pub(crate) const EXPERIMENT_BRANCH_PREFIX: &str = "__experiment-";
+
+/// Inputs to inject on one variant's branch, each with its target actor.
+#[derive(Debug, Clone)]
+pub struct ExperimentVariant {
+    /// Name reported with the variant's outcome
+    pub label: String,
+    /// Inputs enqueued, in order, before the variant runs
+    pub inputs: Vec<(ActorId, TurnInput)>,
+}
+
+impl ExperimentVariant {
+    /// Variant called `label` with no inputs yet
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Add an external message to `facet` of `actor`
+    pub fn message(mut self, actor: ActorId, facet: FacetId, payload: IOValue) -> Self {
+        let input = TurnInput::ExternalMessage {
+            actor: actor.clone(),
+            facet,
+            payload,
+            idempotency_key: None,
+            broadcast: None,
+        };
+        self.inputs.push((actor, input));
+        self
+    }
+
+    /// Add an assertion into `actor`'s default dataspace
+    pub fn assert(mut self, actor: ActorId, value: IOValue) -> Self {
+        let input = TurnInput::Assert {
+            actor: actor.clone(),
+            handle: Handle::new(),
+            value,
+            namespace: None,
+        };
+        self.inputs.push((actor, input));
+        self
+    }
+}
+
+/// How an experiment runs and what it keeps.
+#[derive(Debug, Clone)]
+pub struct ExperimentOptions {
+    /// Turns each variant may execute before it is stopped
+    pub max_turns: usize,
+    /// Keep the winning variant as a named fork
+    pub promote: Option<Promotion>,
+}
+
+impl Default for ExperimentOptions {
+    fn default() -> Self {
+        Self {
+            max_turns: 1000,
+            promote: None,
+        }
+    }
+}
+
+/// Which variant to keep, and under what name.
+#[derive(Debug, Clone)]
+pub struct Promotion {
+    /// Name of the fork created for the winner
+    pub branch: BranchId,
+    /// Details recorded with the fork
+    pub details: BranchDetails,
+    /// Picks the winner from the outcomes (`None` keeps nothing)
+    pub pick: fn(&[VariantOutcome]) -> Option<usize>,
+}
+
+/// Assertion live at the end of a variant but not at the base, or the
+/// reverse.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssertionChange {
+    /// Actor owning the assertion
+    pub actor: ActorId,
+    /// Assertion handle
+    pub handle: Handle,
+    /// Asserted value
+    pub value: IOValue,
+}
+
+/// What one variant did, relative to the base turn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantOutcome {
+    /// Position of the variant in the request
+    pub index: usize,
+    /// Label given to the variant
+    pub label: String,
+    /// Turns executed, in order
+    pub turns: Vec<TurnId>,
+    /// Whether the variant ran out of ready turns within the budget
+    pub quiescent: bool,
+    /// Assertions live at the end that were not live at the base
+    pub asserted: Vec<AssertionChange>,
+    /// Assertions live at the base that were retracted
+    pub retracted: Vec<AssertionChange>,
+    /// Failed turns, exhausted budgets and irreversible side effects
+    pub warnings: Vec<String>,
+}
+
+/// Result of [`Runtime::experiment`](super::Runtime::experiment).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentReport {
+    /// Branch the experiment forked from
+    pub origin: BranchId,
+    /// Turn every variant started from
+    pub base_turn: TurnId,
+    /// One outcome per variant, in request order
+    pub variants: Vec<VariantOutcome>,
+    /// Winner kept as a named fork, if a promotion picked one
+    pub promoted: Option<PromotedVariant>,
+}
+
+/// Variant kept by a [`Promotion`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromotedVariant {
+    /// Index of the winning variant
+    pub index: usize,
+    /// Fork holding its turns
+    pub branch: BranchId,
+    /// Head of the fork
+    pub head: TurnId,
+}
@@ -0,0 +1,256 @@
+//! Turn explanations for debuggers
+//!
+//! [`Runtime::explain_turn`](super::Runtime::explain_turn) gathers what a
+//! debugger needs to answer "why did this turn happen and what did it do":
+//! the inputs, the turn whose outputs delivered them, the patterns, reactions
+//! and capabilities that routed them, the delta, and the later turns its own
+//! outputs went on to trigger.
+//!
+//! The journal does not store causal links between turns, so they are
+//! reconstructed by content: a `Message` output is linked to the first later
+//! turn of the target actor receiving the same payload on the same facet, an
+//! `Assert` output to `ObservedAssert` inputs carrying its handle, and a
+//! `CapabilityInvoke` output to the matching `CapabilityInvocation` input.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::state::{CapId, StateDelta};
+use super::turn::{ActorId, BranchId, Handle, TurnId, TurnInput, TurnOutput, TurnRecord};
+
+/// Structured explanation of one journaled turn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnExplanation {
+    /// Turn being explained
+    pub turn_id: TurnId,
+    /// Branch the turn was found on
+    pub branch: BranchId,
+    /// Actor that ran the turn
+    pub actor: ActorId,
+    /// Previous turn of the same actor
+    pub parent: Option<TurnId>,
+    /// Inputs the turn processed
+    pub inputs: Vec<TurnInput>,
+    /// Turn whose outputs delivered the inputs (`None` for external inputs)
+    pub caused_by: Option<TurnLink>,
+    /// Patterns, reactions and capabilities that routed inputs or fired
+    pub routes: Vec<TurnRoute>,
+    /// State changes the turn produced
+    pub delta: StateDelta,
+    /// Later turns triggered by the turn's outputs, in journal order
+    pub triggered: Vec<TurnLink>,
+}
+
+/// A causal link between two turns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnLink {
+    /// The other turn
+    pub turn_id: TurnId,
+    /// Actor that ran it
+    pub actor: ActorId,
+    /// How the output of one turn became the input of the other
+    pub via: LinkKind,
+}
+
+/// Kind of output → input hand-off behind a [`TurnLink`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LinkKind {
+    /// A message sent to the actor
+    Message,
+    /// An assertion routed to a dataspace-wide observer
+    Observation {
+        /// Handle of the observed assertion
+        handle: Handle,
+    },
+    /// A capability invocation
+    Capability {
+        /// Invoked capability
+        capability: CapId,
+    },
+}
+
+/// Something that routed an input of the turn or fired within it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TurnRoute {
+    /// A pattern subscription matched an assertion
+    Pattern {
+        /// Matching pattern
+        pattern_id: Uuid,
+        /// Entity owning the pattern, when still registered
+        entity: Option<Uuid>,
+        /// Handle of the matched assertion
+        handle: Handle,
+    },
+    /// A reaction's pattern matched, firing its effect
+    Reaction {
+        /// Fired reaction
+        reaction_id: Uuid,
+        /// The reaction's pattern
+        pattern_id: Uuid,
+        /// Handle of the matched assertion
+        handle: Handle,
+    },
+    /// A capability delivered the invocation
+    Capability {
+        /// Invoked capability
+        capability: CapId,
+        /// Capability kind, when the capability is still known
+        capability_kind: Option<String>,
+        /// Issuing actor, when the capability is still known
+        issuer: Option<ActorId>,
+    },
+}
+
+/// Runtime state used to name the patterns and capabilities of a turn.
+#[derive(Default)]
+pub(crate) struct Registrations {
+    /// Pattern id → reaction id
+    pub reactions: HashMap<Uuid, Uuid>,
+    /// Pattern id → owning entity
+    pub pattern_owners: HashMap<Uuid, Uuid>,
+    /// Capability → (kind, issuer)
+    pub capabilities: HashMap<CapId, (String, ActorId)>,
+}
+
+/// Explain `records[index]`, where `records` is the branch lineage in journal order.
+pub(crate) fn explain(
+    branch: &BranchId,
+    records: &[TurnRecord],
+    index: usize,
+    registrations: &Registrations,
+) -> TurnExplanation {
+    let record = &records[index];
+
+    // The most recent earlier turn whose outputs account for an input
+    let caused_by = records[..index].iter().rev().find_map(|earlier| {
+        earlier.outputs.iter().find_map(|output| {
+            record
+                .inputs
+                .iter()
+                .find_map(|input| link(output, input))
+                .map(|via| TurnLink {
+                    turn_id: earlier.turn_id.clone(),
+                    actor: earlier.actor.clone(),
+                    via,
+                })
+        })
+    });
+
+    let mut routes = Vec::new();
+    for input in &record.inputs {
+        if let TurnInput::CapabilityInvocation { capability, .. } = input {
+            let known = registrations.capabilities.get(capability);
+            routes.push(TurnRoute::Capability {
+                capability: *capability,
+                capability_kind: known.map(|(kind, _)| kind.clone()),
+                issuer: known.map(|(_, issuer)| issuer.clone()),
+            });
+        }
+    }
+    for output in &record.outputs {
+        if let TurnOutput::PatternMatched { pattern_id, handle } = output {
+            routes.push(match registrations.reactions.get(pattern_id) {
+                Some(reaction_id) => TurnRoute::Reaction {
+                    reaction_id: *reaction_id,
+                    pattern_id: *pattern_id,
+                    handle: handle.clone(),
+                },
+                None => TurnRoute::Pattern {
+                    pattern_id: *pattern_id,
+                    entity: registrations.pattern_owners.get(pattern_id).copied(),
+                    handle: handle.clone(),
+                },
+            });
+        }
+    }
+
+    // Each output is handed to at most one later turn (observations aside,
+    // which reach every observer)
+    let mut pending: Vec<&TurnOutput> = record.outputs.iter().collect();
+    let mut triggered = Vec::new();
+    for later in &records[index + 1..] {
+        if pending.is_empty() {
+            break;
+        }
+        let mut via = None;
+        for input in &later.inputs {
+            if let Some(position) = pending
+                .iter()
+                .position(|output| link(output, input).is_some())
+            {
+                let output = pending[position];
+                if !matches!(output, TurnOutput::Assert { .. }) {
+                    pending.remove(position);
+                }
+                via = via.or_else(|| link(output, input));
+            }
+        }
+        if let Some(via) = via {
+            triggered.push(TurnLink {
+                turn_id: later.turn_id.clone(),
+                actor: later.actor.clone(),
+                via,
+            });
+        }
+    }
+
+    TurnExplanation {
+        turn_id: record.turn_id.clone(),
+        branch: branch.clone(),
+        actor: record.actor.clone(),
+        parent: record.parent.clone(),
+        inputs: record.inputs.clone(),
+        caused_by,
+        routes,
+        delta: record.delta.clone(),
+        triggered,
+    }
+}
+
+/// How `output` of one turn became `input` of another, if it did.
+fn link(output: &TurnOutput, input: &TurnInput) -> Option<LinkKind> {
+    match (output, input) {
+        (
+            TurnOutput::Message {
+                target_actor,
+                target_facet,
+                payload,
+            },
+            TurnInput::ExternalMessage {
+                actor,
+                facet,
+                payload: received,
+                ..
+            },
+        ) if target_actor == actor && target_facet == facet && payload == received => {
+            Some(LinkKind::Message)
+        }
+        (
+            TurnOutput::Assert { handle, .. },
+            TurnInput::ObservedAssert {
+                handle: observed, ..
+            },
+        ) if handle == observed => Some(LinkKind::Observation {
+            handle: handle.clone(),
+        }),
+        (
+            TurnOutput::CapabilityInvoke {
+                capability,
+                payload,
+                ..
+            },
+            TurnInput::CapabilityInvocation {
+                capability: invoked,
+                payload: received,
+                ..
+            },
+        ) if capability == invoked && payload == received => Some(LinkKind::Capability {
+            capability: *capability,
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,484 @@
+//! Declarative regression fixtures for reactions and patterns
+//!
+//! A fixture file holds one or more `fixture` records in Preserves text. Each
+//! describes a scenario for a single actor: the entities attached to it, the
+//! reactions registered on its root facet, the inputs fed to it, and what the
+//! dataspace should look like afterwards.
+//!
+//! ```text
+//! <fixture "mirror echoes the payload" {
+//!   entities: [<entity "mirror-entity" mirror-config>]
+//!   reactions: [
+//!     <reaction <mirror '<_>'> <assert <match-index 0>>>
+//!     <reaction <mirror '<_>'> <assert <literal seen>> {priority: 10}>
+//!   ]
+//!   steps: [<assert <mirror "hello">> <message ping> <retract <mirror '<_>'>>]
+//!   expect: [<asserted seen> <absent <mirror '<_>'>> <emitted "hello"> <count seen 1>]
+//! }>
+//! ```
+//!
+//! Reaction effects are `<assert VALUE>` or `<invoke KIND VALUE>`, where a
+//! value is `<literal V>`, `match` or `<match-index N>`; the optional options
+//! dictionary takes `priority`, `consume`, `scope` (`actor` or `dataspace`)
+//! and `namespace`. Steps `<assert V>`, `<retract PATTERN>` and
+//! `<message V>` each run one turn. Expectations are checked once all steps
+//! have run: `asserted`, `absent` and `count` look at the actor's live
+//! assertions, `emitted` at every assertion and message output of the run.
+//! Patterns use the runtime's matching rules, so wildcards are symbols such
+//! as `'<_>'`.
+//!
+//! Every fixture runs in its own scratch runtime, so a suite never touches
+//! the journal of the runtime that launched it.
+
+use preserves::IOValue;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::RuntimeConfig;
+use super::control::Control;
+use super::error::{Result, RuntimeError};
+use super::pattern::{Pattern, PatternScope, matches_pattern};
+use super::reaction::{ReactionCapability, ReactionDefinition, ReactionEffect, ReactionValue};
+use super::turn::{ActorId, TurnOutput, TurnRecord};
+use crate::util::io_value::{as_record, record_with_label};
+
+/// Outcome of one expectation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureCheck {
+    /// The expectation, as written in the fixture
+    pub expectation: String,
+    /// Whether it held
+    pub passed: bool,
+    /// What was observed when it did not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Outcome of one fixture.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureResult {
+    /// Fixture name
+    pub name: String,
+    /// Whether every expectation held
+    pub passed: bool,
+    /// Turns executed by the fixture's steps
+    pub turns: usize,
+    /// Expectation outcomes, in fixture order
+    pub checks: Vec<FixtureCheck>,
+}
+
+/// Outcome of a fixture file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureReport {
+    /// File the fixtures were read from
+    pub path: PathBuf,
+    /// Whether every fixture passed
+    pub passed: bool,
+    /// Per-fixture outcomes, in file order
+    pub fixtures: Vec<FixtureResult>,
+}
+
+struct Fixture {
+    name: String,
+    entities: Vec<(String, IOValue)>,
+    reactions: Vec<(IOValue, ReactionEffect, IOValue)>,
+    steps: Vec<Step>,
+    expect: Vec<Expectation>,
+}
+
+enum Step {
+    Assert(IOValue),
+    Retract(IOValue),
+    Message(IOValue),
+}
+
+enum Expectation {
+    Asserted(IOValue),
+    Absent(IOValue),
+    Count(IOValue, usize),
+    Emitted(IOValue),
+}
+
+/// Parse the fixtures in `path` and run each in a scratch runtime configured
+/// like `config`.
+pub fn run_file(path: &Path, config: &RuntimeConfig) -> Result<FixtureReport> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| RuntimeError::Fixture(format!("cannot read {}: {}", path.display(), err)))?;
+    let fixtures = parse(&text)?;
+
+    let mut results = Vec::with_capacity(fixtures.len());
+    for fixture in &fixtures {
+        results.push(run_scratch(fixture, config)?);
+    }
+    Ok(FixtureReport {
+        path: path.to_path_buf(),
+        passed: results.iter().all(|result| result.passed),
+        fixtures: results,
+    })
+}
+
+fn run_scratch(fixture: &Fixture, config: &RuntimeConfig) -> Result<FixtureResult> {
+    let root = std::env::temp_dir().join(format!("duet-fixture-{}", Uuid::new_v4()));
+    let mut scratch = config.clone();
+    scratch.root = root.clone();
+    scratch.notifiers.clear();
+
+    let result = Control::init(scratch).and_then(|mut control| run(fixture, &mut control));
+    let _ = std::fs::remove_dir_all(&root);
+    result
+}
+
+fn run(fixture: &Fixture, control: &mut Control) -> Result<FixtureResult> {
+    let actor = ActorId::new();
+    let facet = control
+        .runtime_mut()
+        .actors
+        .entry(actor.clone())
+        .or_insert_with(|| super::actor::Actor::new(actor.clone()))
+        .root_facet
+        .clone();
+
+    for (entity_type, entity_config) in &fixture.entities {
+        control.register_entity(
+            actor.clone(),
+            facet.clone(),
+            entity_type.clone(),
+            entity_config.clone(),
+        )?;
+    }
+    for (pattern, effect, options) in &fixture.reactions {
+        let mut pattern = Pattern {
+            id: Uuid::new_v4(),
+            pattern: pattern.clone(),
+            facet: facet.clone(),
+            namespace: None,
+            scope: PatternScope::Actor,
+        };
+        let mut definition = ReactionDefinition::new(pattern.clone(), effect.clone());
+        if let Some(priority) = option(options, "priority") {
+            definition.priority = priority
+                .as_signed_integer()
+                .and_then(|value| i32::try_from(value.as_ref()).ok())
+                .ok_or_else(|| invalid("reaction priority must be an integer", &priority))?;
+        }
+        if let Some(consume) = option(options, "consume") {
+            definition.consume = consume
+                .as_boolean()
+                .ok_or_else(|| invalid("reaction consume must be a boolean", &consume))?;
+        }
+        if let Some(scope) = option(options, "scope") {
+            pattern.scope = match symbol(&scope).as_deref() {
+                Some("actor") => PatternScope::Actor,
+                Some("dataspace") => PatternScope::Dataspace,
+                _ => return Err(invalid("reaction scope must be actor or dataspace", &scope)),
+            };
+        }
+        if let Some(namespace) = option(options, "namespace") {
+            let namespace = namespace
+                .as_string()
+                .ok_or_else(|| invalid("reaction namespace must be a string", &namespace))?;
+            pattern = pattern.in_namespace(namespace.to_string());
+        }
+        definition.pattern = pattern;
+        control.register_reaction(actor.clone(), definition)?;
+    }
+
+    let mut records: Vec<TurnRecord> = Vec::new();
+    for step in &fixture.steps {
+        let turn = match step {
+            Step::Assert(value) => control.assert_value(actor.clone(), value.clone())?,
+            Step::Retract(pattern) => {
+                control
+                    .retract_matching(actor.clone(), pattern.clone())?
+                    .turn_id
+            }
+            Step::Message(payload) => {
+                control.send_message(actor.clone(), facet.clone(), payload.clone())?
+            }
+        };
+        let reader = control
+            .runtime()
+            .journal_reader(&control.runtime().current_branch())?;
+        records.push(reader.read(&turn)?);
+    }
+
+    let live: Vec<IOValue> = control
+        .runtime()
+        .assertions_for_actor(&actor)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    let emitted: Vec<&IOValue> = records
+        .iter()
+        .flat_map(|record| &record.outputs)
+        .filter_map(|output| match output {
+            TurnOutput::Assert { value, .. } => Some(value),
+            TurnOutput::Message { payload, .. } => Some(payload),
+            _ => None,
+        })
+        .collect();
+
+    let checks: Vec<FixtureCheck> = fixture
+        .expect
+        .iter()
+        .map(|expectation| check(expectation, &live, &emitted))
+        .collect();
+    Ok(FixtureResult {
+        name: fixture.name.clone(),
+        passed: checks.iter().all(|check| check.passed),
+        turns: records.len(),
+        checks,
+    })
+}
+
+fn check(expectation: &Expectation, live: &[IOValue], emitted: &[&IOValue]) -> FixtureCheck {
+    let count = |pattern: &IOValue| {
+        live.iter()
+            .filter(|value| matches_pattern(pattern, value))
+            .count()
+    };
+    let (expectation, outcome) = match expectation {
+        Expectation::Asserted(pattern) => (
+            format!("<asserted {:?}>", pattern),
+            (count(pattern) > 0)
+                .then_some(())
+                .ok_or_else(|| format!("no live assertion matches among {}", live.len())),
+        ),
+        Expectation::Absent(pattern) => {
+            let found = count(pattern);
+            (
+                format!("<absent {:?}>", pattern),
+                (found == 0)
+                    .then_some(())
+                    .ok_or_else(|| format!("{found} live assertions match")),
+            )
+        }
+        Expectation::Count(pattern, expected) => {
+            let found = count(pattern);
+            (
+                format!("<count {:?} {}>", pattern, expected),
+                (found == *expected)
+                    .then_some(())
+                    .ok_or_else(|| format!("{found} live assertions match")),
+            )
+        }
+        Expectation::Emitted(pattern) => (
+            format!("<emitted {:?}>", pattern),
+            emitted
+                .iter()
+                .any(|value| matches_pattern(pattern, value))
+                .then_some(())
+                .ok_or_else(|| format!("no output matches among {}", emitted.len())),
+        ),
+    };
+    FixtureCheck {
+        expectation,
+        passed: outcome.is_ok(),
+        detail: outcome.err(),
+    }
+}
+
+fn invalid(message: &str, value: &IOValue) -> RuntimeError {
+    RuntimeError::Fixture(format!("{message}: {value:?}"))
+}
+
+fn symbol(value: &IOValue) -> Option<String> {
+    value.as_symbol().map(|symbol| symbol.as_ref().to_string())
+}
+
+/// Look up `key` in an options or fixture body dictionary.
+fn option(dictionary: &IOValue, key: &str) -> Option<IOValue> {
+    if !dictionary.is_dictionary() {
+        return None;
+    }
+    dictionary
+        .entries()
+        .find(|(name, _)| symbol(&IOValue::from(name.clone())).as_deref() == Some(key))
+        .map(|(_, value)| IOValue::from(value))
+}
+
+fn items(body: &IOValue, key: &str) -> Result<Vec<IOValue>> {
+    match option(body, key) {
+        None => Ok(Vec::new()),
+        Some(list) if list.is_sequence() => Ok(list.iter().map(IOValue::from).collect()),
+        Some(other) => Err(invalid(&format!("'{key}' must be a sequence"), &other)),
+    }
+}
+
+fn parse(text: &str) -> Result<Vec<Fixture>> {
+    let values: IOValue = format!("[{text}\n]")
+        .parse()
+        .map_err(|err| RuntimeError::Fixture(format!("invalid Preserves text: {err}")))?;
+    values
+        .iter()
+        .map(|value| parse_fixture(&IOValue::from(value)))
+        .collect()
+}
+
+fn parse_fixture(value: &IOValue) -> Result<Fixture> {
+    let record = record_with_label(value, "fixture")
+        .filter(|record| record.len() == 2)
+        .ok_or_else(|| invalid("expected <fixture NAME {...}>", value))?;
+    let name = record
+        .field_string(0)
+        .ok_or_else(|| invalid("fixture name must be a string", value))?;
+    let body = record.field(1);
+    if !body.is_dictionary() {
+        return Err(invalid("fixture body must be a dictionary", &body));
+    }
+
+    let entities = items(&body, "entities")?
+        .iter()
+        .map(|entity| {
+            record_with_label(entity, "entity")
+                .filter(|record| record.len() == 2)
+                .and_then(|record| Some((record.field_string(0)?, record.field(1))))
+                .ok_or_else(|| invalid("expected <entity TYPE CONFIG>", entity))
+        })
+        .collect::<Result<_>>()?;
+    let reactions = items(&body, "reactions")?
+        .iter()
+        .map(|reaction| {
+            let record = record_with_label(reaction, "reaction")
+                .filter(|record| record.len() == 2 || record.len() == 3)
+                .ok_or_else(|| invalid("expected <reaction PATTERN EFFECT [OPTIONS]>", reaction))?;
+            let options = if record.len() == 3 {
+                record.field(2)
+            } else {
+                IOValue::new(preserves::Map::<IOValue, IOValue>::new())
+            };
+            Ok((record.field(0), parse_effect(&record.field(1))?, options))
+        })
+        .collect::<Result<_>>()?;
+    let steps = items(&body, "steps")?
+        .iter()
+        .map(|step| {
+            let record = as_record(step)
+                .filter(|record| record.len() == 1)
+                .ok_or_else(|| invalid("expected <assert V>, <retract P> or <message V>", step))?;
+            if record.has_label("assert") {
+                Ok(Step::Assert(record.field(0)))
+            } else if record.has_label("retract") {
+                Ok(Step::Retract(record.field(0)))
+            } else if record.has_label("message") {
+                Ok(Step::Message(record.field(0)))
+            } else {
+                Err(invalid("unknown step", step))
+            }
+        })
+        .collect::<Result<_>>()?;
+    let expect = items(&body, "expect")?
+        .iter()
+        .map(parse_expectation)
+        .collect::<Result<_>>()?;
+
+    Ok(Fixture {
+        name,
+        entities,
+        reactions,
+        steps,
+        expect,
+    })
+}
+
+fn parse_effect(value: &IOValue) -> Result<ReactionEffect> {
+    if let Some(record) = record_with_label(value, "assert").filter(|record| record.len() == 1) {
+        return Ok(ReactionEffect::Assert {
+            value: parse_value(&record.field(0))?,
+            target_facet: None,
+        });
+    }
+    if let Some(record) = record_with_label(value, "invoke").filter(|record| record.len() == 2) {
+        let kind = record
+            .field_string(0)
+            .ok_or_else(|| invalid("capability kind must be a string", value))?;
+        return Ok(ReactionEffect::InvokeCapability {
+            capability: ReactionCapability::Kind { kind },
+            payload: parse_value(&record.field(1))?,
+            tag: None,
+        });
+    }
+    Err(invalid(
+        "expected <assert VALUE> or <invoke KIND VALUE>",
+        value,
+    ))
+}
+
+fn parse_value(value: &IOValue) -> Result<ReactionValue> {
+    if symbol(value).as_deref() == Some("match") {
+        return Ok(ReactionValue::Match);
+    }
+    if let Some(record) = record_with_label(value, "literal").filter(|record| record.len() == 1) {
+        return Ok(ReactionValue::Literal {
+            value: record.field(0),
+        });
+    }
+    if let Some(record) = record_with_label(value, "match-index").filter(|record| record.len() == 1)
+    {
+        let index = record
+            .field(0)
+            .as_signed_integer()
+            .and_then(|index| usize::try_from(index.as_ref()).ok())
+            .ok_or_else(|| invalid("match-index takes a non-negative integer", value))?;
+        return Ok(ReactionValue::MatchIndex { index });
+    }
+    Err(invalid(
+        "expected <literal V>, match or <match-index N>",
+        value,
+    ))
+}
+
+fn parse_expectation(value: &IOValue) -> Result<Expectation> {
+    let record =
+        as_record(value).ok_or_else(|| invalid("expected an expectation record", value))?;
+    match record.len() {
+        1 if record.has_label("asserted") => Ok(Expectation::Asserted(record.field(0))),
+        1 if record.has_label("absent") => Ok(Expectation::Absent(record.field(0))),
+        1 if record.has_label("emitted") => Ok(Expectation::Emitted(record.field(0))),
+        2 if record.has_label("count") => {
+            let expected = record
+                .field(1)
+                .as_signed_integer()
+                .and_then(|count| usize::try_from(count.as_ref()).ok())
+                .ok_or_else(|| invalid("count takes a non-negative integer", value))?;
+            Ok(Expectation::Count(record.field(0), expected))
+        }
+        _ => Err(invalid(
+            "expected <asserted P>, <absent P>, <count P N> or <emitted P>",
+            value,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_parse_and_reject_malformed_entries() {
+        let fixtures = parse(
+            r#"
+            <fixture "one" {
+              reactions: [<reaction <ping '<_>'> <assert <match-index 0>> {priority: 3}>]
+              steps: [<assert <ping 1>> <retract <ping '<_>'>>]
+              expect: [<emitted 1> <count 1 0>]
+            }>
+            <fixture "two" {}>
+            "#,
+        )
+        .unwrap();
+        assert_eq!(fixtures.len(), 2);
+        assert_eq!(fixtures[0].name, "one");
+        assert_eq!(fixtures[0].reactions.len(), 1);
+        assert_eq!(fixtures[0].steps.len(), 2);
+        assert_eq!(fixtures[0].expect.len(), 2);
+        assert!(fixtures[1].steps.is_empty());
+
+        assert!(parse(r#"<fixture "bad" {steps: [<wait 1>]}>"#).is_err());
+        assert!(parse(r#"<fixture "bad" {expect: [<count x>]}>"#).is_err());
+        assert!(parse(r#"<fixture "bad" {reactions: [<reaction x <send y>>]}>"#).is_err());
+        assert!(parse("<fixture missing-body>").is_err());
+    }
+}
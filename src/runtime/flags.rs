@@ -0,0 +1,143 @@
+//! Runtime feature flags published in the dataspace
+//!
+//! A handful of runtime behaviours can be switched while the daemon runs:
+//! debug logging, chaos fault injection and the human approval gates. Each
+//! flag that has been set is visible as a `<feature-flag name enabled>`
+//! assertion made by the [system actor](system_actor), so entities and
+//! reactions can observe it like any other fact. Flags are changed with
+//! [`Control::set_flag`](super::control::Control::set_flag), which runs as a
+//! journaled turn of the system actor; the flag therefore follows time
+//! travel and branching along with the rest of the dataspace.
+//!
+//! A flag without an assertion takes its default from the runtime
+//! configuration.
+
+use std::fmt;
+use std::str::FromStr;
+
+use preserves::IOValue;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::error::{Result, RuntimeError};
+use super::turn::ActorId;
+use crate::util::io_value::record_with_label;
+
+/// Record label of a flag assertion.
+pub const FLAG_LABEL: &str = "feature-flag";
+
+/// Actor owning the flag assertions and the runtime's administrative turns.
+pub fn system_actor() -> ActorId {
+    ActorId::from_uuid(Uuid::nil())
+}
+
+/// Switchable runtime behaviour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FeatureFlag {
+    /// Debug-level logging (defaults to `RuntimeConfig::debug`)
+    Debug,
+    /// Fault injection, when a chaos injector is installed (defaults to on)
+    Chaos,
+    /// Approval gates for `RuntimeConfig::approval_kinds` (defaults to on)
+    ApprovalGates,
+}
+
+impl FeatureFlag {
+    /// All flags, in the order they are listed to users.
+    pub const ALL: [FeatureFlag; 3] = [
+        FeatureFlag::Debug,
+        FeatureFlag::Chaos,
+        FeatureFlag::ApprovalGates,
+    ];
+
+    /// Name used in assertions and on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeatureFlag::Debug => "debug",
+            FeatureFlag::Chaos => "chaos",
+            FeatureFlag::ApprovalGates => "approval-gates",
+        }
+    }
+
+    /// `<feature-flag name enabled>` assertion for this flag.
+    pub fn assertion(&self, enabled: bool) -> IOValue {
+        IOValue::record(
+            IOValue::symbol(FLAG_LABEL),
+            vec![IOValue::symbol(self.as_str()), IOValue::new(enabled)],
+        )
+    }
+
+    /// Pattern matching any assertion of this flag.
+    pub fn pattern(&self) -> IOValue {
+        IOValue::record(
+            IOValue::symbol(FLAG_LABEL),
+            vec![IOValue::symbol(self.as_str()), IOValue::symbol("<_>")],
+        )
+    }
+
+    /// Decode a `<feature-flag name enabled>` assertion.
+    pub fn parse_assertion(value: &IOValue) -> Option<(FeatureFlag, bool)> {
+        let record = record_with_label(value, FLAG_LABEL)?;
+        if record.len() != 2 {
+            return None;
+        }
+        let flag = record.field_symbol(0)?.parse().ok()?;
+        let enabled = record.field(1).as_boolean()?;
+        Some((flag, enabled))
+    }
+}
+
+impl fmt::Display for FeatureFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FeatureFlag {
+    type Err = RuntimeError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|flag| flag.as_str() == name)
+            .ok_or_else(|| {
+                let known: Vec<&str> = Self::ALL.iter().map(FeatureFlag::as_str).collect();
+                RuntimeError::Config(format!(
+                    "Unknown feature flag '{}' (expected one of: {})",
+                    name,
+                    known.join(", ")
+                ))
+            })
+    }
+}
+
+/// Current value of a flag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlagStatus {
+    /// The flag
+    pub flag: FeatureFlag,
+    /// Whether the behaviour is on
+    pub enabled: bool,
+    /// Whether the value was set in the dataspace rather than defaulted
+    pub asserted: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_assertions_round_trip() {
+        for flag in FeatureFlag::ALL {
+            assert_eq!(flag.as_str().parse::<FeatureFlag>().unwrap(), flag);
+            for enabled in [true, false] {
+                assert_eq!(
+                    FeatureFlag::parse_assertion(&flag.assertion(enabled)),
+                    Some((flag, enabled))
+                );
+            }
+        }
+        assert!("turbo".parse::<FeatureFlag>().is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! Client-facing view of turns in history responses
+//!
+//! A [`TurnView`] describes a turn in stable terms: the kind of each input
+//! with a short rendering of its payload, how many assertions, retractions
+//! and messages the turn produced, and which capabilities it touched. Clients
+//! render these instead of the journal's encoding of [`TurnRecord`], which
+//! changes with the runtime. [`HistoryDetail::Full`] additionally lists every
+//! output and carries payloads as structured JSON.
+
+use preserves::IOValue;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+use super::control::TurnSummary;
+use super::state::CapId;
+use super::turn::{ActorId, TurnInput, TurnOutput, TurnRecord};
+use crate::util::io_value::{io_value_summary, io_value_to_json};
+
+/// Characters kept when summarizing a payload.
+const SUMMARY_LIMIT: usize = 80;
+
+/// How much of each turn a history response includes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryDetail {
+    /// Input kinds, summarized payloads, output counts and capabilities
+    #[default]
+    Summary,
+    /// Also every output, with payloads as structured JSON
+    Full,
+}
+
+impl FromStr for HistoryDetail {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "summary" => Ok(Self::Summary),
+            "full" => Ok(Self::Full),
+            other => Err(format!(
+                "unknown history detail '{}' (expected summary or full)",
+                other
+            )),
+        }
+    }
+}
+
+/// Curated description of one turn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnView {
+    /// Identity, clocks and input/output totals
+    #[serde(flatten)]
+    pub summary: TurnSummary,
+    /// What triggered the turn
+    pub inputs: Vec<InputView>,
+    /// Assertions, retractions and messages the turn produced
+    pub counts: OutputCounts,
+    /// Capabilities invoked, granted, revoked or answered, in first-use order
+    pub capabilities: Vec<CapId>,
+    /// Every output ([`HistoryDetail::Full`] only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Vec<OutputView>>,
+}
+
+/// One input of a turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputView {
+    /// Input kind in snake case (e.g. `external_message`)
+    pub kind: String,
+    /// Actor the input was addressed to, when it names one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<ActorId>,
+    /// Short rendering of the payload or of what the input does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Payload as structured JSON ([`HistoryDetail::Full`] only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Value>,
+}
+
+/// One output of a turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputView {
+    /// Output kind in snake case (e.g. `assert`)
+    pub kind: String,
+    /// Short rendering of the payload or of what the output does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Payload as structured JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Value>,
+}
+
+/// Dataspace activity of a turn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputCounts {
+    /// Assertions made
+    pub asserts: usize,
+    /// Assertions retracted
+    pub retracts: usize,
+    /// Messages sent
+    pub messages: usize,
+}
+
+impl TurnView {
+    /// View of `record`, extending its `summary`
+    pub fn of(summary: TurnSummary, record: &TurnRecord, detail: HistoryDetail) -> Self {
+        let full = detail == HistoryDetail::Full;
+        let mut counts = OutputCounts::default();
+        let mut capabilities = Vec::new();
+        let mut touch = |capability: &CapId| {
+            if !capabilities.contains(capability) {
+                capabilities.push(*capability);
+            }
+        };
+
+        let inputs = record
+            .inputs
+            .iter()
+            .map(|input| {
+                if let TurnInput::CapabilityInvocation { capability, .. } = input {
+                    touch(capability);
+                }
+                let (kind, actor, payload, note) = describe_input(input);
+                InputView {
+                    kind: kind.to_string(),
+                    actor: actor.cloned(),
+                    summary: note.or_else(|| {
+                        payload.map(|payload| io_value_summary(payload, SUMMARY_LIMIT))
+                    }),
+                    payload: payload.filter(|_| full).map(io_value_to_json),
+                }
+            })
+            .collect();
+
+        let mut outputs = Vec::new();
+        for output in &record.outputs {
+            match output {
+                TurnOutput::Assert { .. } => counts.asserts += 1,
+                TurnOutput::Retract { .. } => counts.retracts += 1,
+                TurnOutput::Message { .. } => counts.messages += 1,
+                TurnOutput::CapabilityGranted { capability, .. }
+                | TurnOutput::CapabilityRevoked { capability }
+                | TurnOutput::CapabilityInvoke { capability, .. }
+                | TurnOutput::CapabilityResult { capability, .. } => touch(capability),
+                _ => {}
+            }
+            if full {
+                let (kind, payload, note) = describe_output(output);
+                outputs.push(OutputView {
+                    kind: kind.to_string(),
+                    summary: note.or_else(|| {
+                        payload.map(|payload| io_value_summary(payload, SUMMARY_LIMIT))
+                    }),
+                    payload: payload.map(io_value_to_json),
+                });
+            }
+        }
+
+        Self {
+            summary,
+            inputs,
+            counts,
+            capabilities,
+            outputs: full.then_some(outputs),
+        }
+    }
+}
+
+/// Kind, addressed actor, payload and a note preferred over the payload
+/// in summaries.
+fn describe_input(
+    input: &TurnInput,
+) -> (
+    &'static str,
+    Option<&ActorId>,
+    Option<&IOValue>,
+    Option<String>,
+) {
+    match input {
+        TurnInput::ExternalMessage { actor, payload, .. } => {
+            ("external_message", Some(actor), Some(payload), None)
+        }
+        TurnInput::Assert { actor, value, .. } => ("assert", Some(actor), Some(value), None),
+        TurnInput::Retract { actor, handle } => (
+            "retract",
+            Some(actor),
+            None,
+            Some(format!("handle {}", handle)),
+        ),
+        TurnInput::Sync { actor, facet } => (
+            "sync",
+            Some(actor),
+            None,
+            Some(format!("facet {}", facet.0)),
+        ),
+        TurnInput::Timer {
+            actor, deadline, ..
+        } => (
+            "timer",
+            Some(actor),
+            None,
+            Some(format!("deadline {}", deadline.to_rfc3339())),
+        ),
+        TurnInput::ExternalResponse {
+            actor, response, ..
+        } => ("external_response", Some(actor), Some(response), None),
+        TurnInput::CapabilityInvocation { payload, .. } => {
+            ("capability_invocation", None, Some(payload), None)
+        }
+        TurnInput::RemoteMessage { payload, .. } => ("remote_message", None, Some(payload), None),
+        TurnInput::Merge {
+            source_branch,
+            target_branch,
+            ..
+        } => (
+            "merge",
+            None,
+            None,
+            Some(format!("{} into {}", source_branch, target_branch)),
+        ),
+        TurnInput::ObservedAssert {
+            observer, value, ..
+        } => ("observed_assert", Some(observer), Some(value), None),
+        TurnInput::ObservedRetract {
+            observer, handle, ..
+        } => (
+            "observed_retract",
+            Some(observer),
+            None,
+            Some(format!("handle {}", handle)),
+        ),
+        TurnInput::RetractMatching { actor, pattern, .. } => {
+            ("retract_matching", Some(actor), Some(pattern), None)
+        }
+        TurnInput::PauseActor { actor } => ("pause_actor", Some(actor), None, None),
+        TurnInput::ResumeActor { actor } => ("resume_actor", Some(actor), None, None),
+        TurnInput::SetFlag { flag, enabled, .. } => (
+            "set_flag",
+            None,
+            None,
+            Some(format!("{} = {}", flag, enabled)),
+        ),
+        TurnInput::ConfigChange { branch, .. } => {
+            ("config_change", None, None, Some(format!("on {}", branch)))
+        }
+    }
+}
+
+/// Kind, payload and a note preferred over the payload in summaries.
+fn describe_output(output: &TurnOutput) -> (&'static str, Option<&IOValue>, Option<String>) {
+    match output {
+        TurnOutput::Assert { value, .. } => ("assert", Some(value), None),
+        TurnOutput::Retract { handle, .. } => ("retract", None, Some(format!("handle {}", handle))),
+        TurnOutput::Message { payload, .. } => ("message", Some(payload), None),
+        TurnOutput::Synced { .. } => ("synced", None, None),
+        TurnOutput::FacetSpawned { facet, .. } => {
+            ("facet_spawned", None, Some(format!("facet {}", facet.0)))
+        }
+        TurnOutput::FacetTerminated { facet } => {
+            ("facet_terminated", None, Some(format!("facet {}", facet.0)))
+        }
+        TurnOutput::TimerRegistered { deadline, .. } => (
+            "timer_registered",
+            None,
+            Some(format!("deadline {}", deadline.to_rfc3339())),
+        ),
+        TurnOutput::ExternalRequest {
+            service, request, ..
+        } => ("external_request", Some(request), Some(service.clone())),
+        TurnOutput::PatternMatched { .. } => ("pattern_matched", None, None),
+        TurnOutput::PatternUnmatched { .. } => ("pattern_unmatched", None, None),
+        TurnOutput::CapabilityGranted { kind, .. } => {
+            ("capability_granted", None, Some(kind.clone()))
+        }
+        TurnOutput::CapabilityRevoked { .. } => ("capability_revoked", None, None),
+        TurnOutput::PatternRegistered { .. } => ("pattern_registered", None, None),
+        TurnOutput::PatternUnregistered { .. } => ("pattern_unregistered", None, None),
+        TurnOutput::EntityDetached { .. } => ("entity_detached", None, None),
+        TurnOutput::CapabilityInvoke { payload, .. } => ("capability_invoke", Some(payload), None),
+        TurnOutput::EntitySpawned {
+            entity_type,
+            config,
+            ..
+        } => ("entity_spawned", Some(config), Some(entity_type.clone())),
+        TurnOutput::EntityAttached {
+            entity_type,
+            config,
+            ..
+        } => ("entity_attached", Some(config), Some(entity_type.clone())),
+        TurnOutput::CapabilityResult { result, .. } => ("capability_result", Some(result), None),
+        TurnOutput::SecretAccessed { name, .. } => ("secret_accessed", None, Some(name.clone())),
+        TurnOutput::SideEffect {
+            kind, description, ..
+        } => (
+            "side_effect",
+            None,
+            Some(format!("{}: {}", kind, description)),
+        ),
+        TurnOutput::FileBackup { path, .. } => ("file_backup", None, Some(path.clone())),
+        TurnOutput::OutboxStaged {
+            channel, payload, ..
+        } => ("outbox_staged", Some(payload), Some(channel.clone())),
+        TurnOutput::RandomDrawn { draws, .. } => {
+            ("random_drawn", None, Some(format!("{} draws", draws)))
+        }
+        TurnOutput::MergeProvenance { source_branch, .. } => (
+            "merge_provenance",
+            None,
+            Some(format!("from {}", source_branch)),
+        ),
+        TurnOutput::CapabilityInvokeByKind { kind, payload, .. } => (
+            "capability_invoke_by_kind",
+            Some(payload),
+            Some(kind.clone()),
+        ),
+    }
+}
@@ -0,0 +1,209 @@
+//! Identities of the people and bots driving the control plane
+//!
+//! A client may name who it acts for in the service handshake. The identity
+//! is attached to what the connection causes: turns run from its messages,
+//! its steps and its approval decisions carry it as their
+//! [`TurnRecord::initiator`](super::turn::TurnRecord::initiator), and branches
+//! it forks record it as their creator. Inputs enqueued while such a turn runs
+//! inherit the identity, so a cascade of turns traces back to whoever started
+//! it.
+//!
+//! Identities are only trusted when [`IdentityConfig::principals`] lists them:
+//! the client must then present the principal's token, which is checked
+//! against its SHA-256 digest. Other identities are taken at the client's
+//! word and recorded as unauthenticated. With [`IdentityConfig::required`]
+//! set, clients must name an identity, and a listed one if any are listed.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Whether an identity is a person or an automated client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityKind {
+    /// A person operating a client
+    #[default]
+    Human,
+    /// An automated client (CI job, agent, integration)
+    Bot,
+}
+
+/// Who initiated a change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity {
+    /// Stable identifier (user name, bot name)
+    pub id: String,
+    /// Person or bot
+    #[serde(default)]
+    pub kind: IdentityKind,
+    /// Name to show instead of the id
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Whether the identity was checked against a configured principal
+    #[serde(default)]
+    pub authenticated: bool,
+}
+
+impl Identity {
+    /// Unauthenticated identity `id` of the given kind.
+    pub fn new(id: impl Into<String>, kind: IdentityKind) -> Self {
+        Self {
+            id: id.into(),
+            kind,
+            display_name: None,
+            authenticated: false,
+        }
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            IdentityKind::Human => write!(f, "{}", self.id),
+            IdentityKind::Bot => write!(f, "{} (bot)", self.id),
+        }
+    }
+}
+
+/// Identity a client claims in its handshake.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityClaim {
+    /// Identifier the client acts as
+    pub id: String,
+    /// Kind the client declares (principals' configured kind wins)
+    #[serde(default)]
+    pub kind: Option<IdentityKind>,
+    /// Name to show instead of the id
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Secret proving the identity, required for configured principals
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Known identity a client can authenticate as.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Principal {
+    /// Person or bot
+    #[serde(default)]
+    pub kind: IdentityKind,
+    /// Name to show instead of the id
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Hex SHA-256 digest of the principal's token
+    pub token_sha256: String,
+}
+
+/// Identities accepted from control-plane clients.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityConfig {
+    /// Reject clients naming no identity, or an unlisted one when principals are listed
+    #[serde(default)]
+    pub required: bool,
+    /// Identities that must authenticate, by id
+    #[serde(default)]
+    pub principals: BTreeMap<String, Principal>,
+}
+
+impl IdentityConfig {
+    /// Identity for a client's `claim` (`None` when it names none).
+    ///
+    /// Claims of configured principals must carry the principal's token.
+    pub fn authenticate(&self, claim: Option<&IdentityClaim>) -> Result<Option<Identity>, String> {
+        let Some(claim) = claim else {
+            return if self.required {
+                Err("an identity is required".to_string())
+            } else {
+                Ok(None)
+            };
+        };
+        if claim.id.is_empty() {
+            return Err("identity id must not be empty".to_string());
+        }
+        let Some(principal) = self.principals.get(&claim.id) else {
+            if self.required && !self.principals.is_empty() {
+                return Err(format!("unknown identity '{}'", claim.id));
+            }
+            return Ok(Some(Identity {
+                id: claim.id.clone(),
+                kind: claim.kind.unwrap_or_default(),
+                display_name: claim.display_name.clone(),
+                authenticated: false,
+            }));
+        };
+        let digest = claim
+            .token
+            .as_ref()
+            .map(|token| token_digest(token))
+            .ok_or_else(|| format!("identity '{}' requires a token", claim.id))?;
+        if !digest.eq_ignore_ascii_case(&principal.token_sha256) {
+            return Err(format!("invalid token for identity '{}'", claim.id));
+        }
+        Ok(Some(Identity {
+            id: claim.id.clone(),
+            kind: principal.kind,
+            display_name: principal
+                .display_name
+                .clone()
+                .or_else(|| claim.display_name.clone()),
+            authenticated: true,
+        }))
+    }
+}
+
+/// Hex SHA-256 digest of `token`, as stored in [`Principal::token_sha256`].
+pub fn token_digest(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(id: &str, token: Option<&str>) -> IdentityClaim {
+        IdentityClaim {
+            id: id.to_string(),
+            token: token.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_principals_must_present_their_token() {
+        let mut config = IdentityConfig::default();
+        config.principals.insert(
+            "ci".to_string(),
+            Principal {
+                kind: IdentityKind::Bot,
+                display_name: None,
+                token_sha256: token_digest("s3cret"),
+            },
+        );
+
+        let ci = config
+            .authenticate(Some(&claim("ci", Some("s3cret"))))
+            .unwrap()
+            .unwrap();
+        assert!(ci.authenticated);
+        assert_eq!(ci.kind, IdentityKind::Bot);
+        assert!(
+            config
+                .authenticate(Some(&claim("ci", Some("nope"))))
+                .is_err()
+        );
+        assert!(config.authenticate(Some(&claim("ci", None))).is_err());
+
+        let alice = config
+            .authenticate(Some(&claim("alice", None)))
+            .unwrap()
+            .unwrap();
+        assert!(!alice.authenticated);
+        assert_eq!(config.authenticate(None).unwrap(), None);
+
+        config.required = true;
+        assert!(config.authenticate(None).is_err());
+        assert!(config.authenticate(Some(&claim("alice", None))).is_err());
+    }
+}
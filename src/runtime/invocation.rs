@@ -0,0 +1,122 @@
+//! Asynchronous capability invocations
+//!
+//! [`Runtime::invoke_capability`](super::Runtime::invoke_capability) pumps the
+//! scheduler until the result appears, which may execute unrelated turns. The
+//! asynchronous variant only enqueues the invocation turn and hands back an
+//! [`InvocationId`]; the caller decides when turns run. Once the issuer's turn
+//! produces a result (or fails), the outcome is kept for
+//! [`Runtime::take_invocation_result`](super::Runtime::take_invocation_result)
+//! and, when a reply actor was given, asserted on it as
+//! `<invocation-result id capability value>`.
+
+use chrono::{DateTime, Utc};
+use preserves::IOValue;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use super::state::CapId;
+use super::turn::ActorId;
+
+/// Identifier of an asynchronous invocation.
+pub type InvocationId = Uuid;
+
+/// Record label asserted on the reply target when an invocation completes.
+pub const INVOCATION_RESULT_LABEL: &str = "invocation-result";
+
+/// Record label used as the value of a failed invocation.
+pub const INVOCATION_ERROR_LABEL: &str = "tool-error";
+
+/// Invocation enqueued but not yet executed.
+#[derive(Debug, Clone)]
+pub struct PendingInvocation {
+    /// Token returned to the caller
+    pub id: InvocationId,
+    /// Capability being invoked
+    pub capability: CapId,
+    /// Actor hosting the capability's issuer entity
+    pub issuer: ActorId,
+    /// Actor the result is asserted on, if any
+    pub reply_to: Option<ActorId>,
+    /// When the invocation was submitted
+    pub submitted_at: DateTime<Utc>,
+}
+
+/// Outcome of a finished invocation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvocationOutcome {
+    /// The issuer entity returned a result
+    Completed(IOValue),
+    /// The invocation turn failed
+    Failed(String),
+}
+
+impl InvocationOutcome {
+    /// Value published for the outcome; failures become `<tool-error message>`.
+    pub fn value(&self) -> IOValue {
+        match self {
+            InvocationOutcome::Completed(value) => value.clone(),
+            InvocationOutcome::Failed(message) => IOValue::record(
+                IOValue::symbol(INVOCATION_ERROR_LABEL),
+                vec![IOValue::new(message.clone())],
+            ),
+        }
+    }
+}
+
+/// In-flight and finished asynchronous invocations.
+#[derive(Debug, Default)]
+pub struct InvocationTable {
+    pending: BTreeMap<InvocationId, PendingInvocation>,
+    finished: BTreeMap<InvocationId, InvocationOutcome>,
+}
+
+impl InvocationTable {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track a newly submitted invocation.
+    pub fn submit(&mut self, invocation: PendingInvocation) {
+        self.pending.insert(invocation.id, invocation);
+    }
+
+    /// Move an invocation from pending to finished, returning its pending entry.
+    pub fn finish(
+        &mut self,
+        id: &InvocationId,
+        outcome: InvocationOutcome,
+    ) -> Option<PendingInvocation> {
+        let pending = self.pending.remove(id)?;
+        self.finished.insert(*id, outcome);
+        Some(pending)
+    }
+
+    /// Invocations still waiting for their turn, in identifier order.
+    pub fn pending(&self) -> impl Iterator<Item = &PendingInvocation> {
+        self.pending.values()
+    }
+
+    /// Remove and return the outcome of a finished invocation.
+    pub fn take_outcome(&mut self, id: &InvocationId) -> Option<InvocationOutcome> {
+        self.finished.remove(id)
+    }
+
+    /// Forget an invocation entirely.
+    pub fn discard(&mut self, id: &InvocationId) {
+        self.pending.remove(id);
+        self.finished.remove(id);
+    }
+}
+
+/// `<invocation-result id capability value>` record asserted on the reply target.
+pub fn result_record(id: InvocationId, capability: CapId, outcome: &InvocationOutcome) -> IOValue {
+    IOValue::record(
+        IOValue::symbol(INVOCATION_RESULT_LABEL),
+        vec![
+            IOValue::new(id.to_string()),
+            IOValue::new(capability.to_string()),
+            outcome.value(),
+        ],
+    )
+}
@@ -1,747 +0,0 @@
-//! Append-only turn log writer and reader
-//!
-//! Manages journal segments, provides read iterators, and handles
-//! crash recovery with partial write detection.
-
-use super::error::{JournalError, JournalResult};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::{File, OpenOptions};
-use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
-use std::path::{Path, PathBuf};
-
-use super::storage::Storage;
-use super::turn::{BranchId, TurnId, TurnRecord};
-
-/// Maximum segment size in bytes (10MB)
-const MAX_SEGMENT_SIZE: u64 = 10 * 1024 * 1024;
-
-/// Journal index mapping turn IDs to (segment, offset)
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct JournalIndex {
-    /// Map from turn ID to (segment number, byte offset)
-    pub(crate) entries: HashMap<String, (u64, u64)>,
-}
-
-impl JournalIndex {
-    /// Add an entry to the index
-    pub(crate) fn add(&mut self, turn_id: &TurnId, segment: u64, offset: u64) {
-        self.entries
-            .insert(turn_id.as_str().to_string(), (segment, offset));
-    }
-
-    /// Get location for a turn ID
-    pub(crate) fn get(&self, turn_id: &TurnId) -> Option<(u64, u64)> {
-        self.entries.get(turn_id.as_str()).copied()
-    }
-
-    /// Save index to disk atomically
-    pub(crate) fn save(&self, path: &Path) -> JournalResult<()> {
-        let data = serde_json::to_vec_pretty(self)
-            .map_err(|e| JournalError::IndexCorrupted(e.to_string()))?;
-
-        // Write to temp file first
-        let temp_path = path.with_extension("tmp");
-        std::fs::write(&temp_path, &data)?;
-
-        // Fsync the temp file
-        let file = std::fs::File::open(&temp_path)?;
-        file.sync_all()?;
-        drop(file);
-
-        // Atomic rename
-        std::fs::rename(&temp_path, path)?;
-
-        // Fsync parent directory to ensure rename is durable
-        if let Some(parent) = path.parent() {
-            let dir = std::fs::File::open(parent)?;
-            dir.sync_all()?;
-        }
-
-        Ok(())
-    }
-
-    /// Load index from disk
-    pub(crate) fn load(path: &Path) -> JournalResult<Self> {
-        if !path.exists() {
-            return Ok(Self::default());
-        }
-        let data = std::fs::read(path)?;
-        let index = serde_json::from_slice(&data)
-            .map_err(|e| JournalError::IndexCorrupted(e.to_string()))?;
-        Ok(index)
-    }
-}
-
-fn read_record_from<R: Read>(reader: &mut R) -> JournalResult<Option<TurnRecord>> {
-    let mut len_buf = [0u8; 4];
-    match reader.read_exact(&mut len_buf) {
-        Ok(()) => {}
-        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
-            return Ok(None);
-        }
-        Err(e) => return Err(JournalError::Io(e)),
-    }
-
-    let len = u32::from_le_bytes(len_buf) as usize;
-    let mut buf = vec![0u8; len];
-    reader.read_exact(&mut buf)?;
-
-    // Deserialize directly from the data buffer (without length prefix)
-    // since we already read the length prefix separately above
-    let record = preserves::serde::from_bytes(&buf)
-        .map_err(|e| JournalError::DecodingError(e.to_string()))?;
-
-    Ok(Some(record))
-}
-
-/// Journal writer for appending turn records
-pub struct JournalWriter {
-    storage: Storage,
-    branch: BranchId,
-    current_segment: u64,
-    current_segment_size: u64,
-    writer: Option<BufWriter<File>>,
-    index: JournalIndex,
-}
-
-impl JournalWriter {
-    /// Create a new journal writer
-    ///
-    /// IMPORTANT: Caller must ensure validate_and_repair() has been run
-    /// and the index has been rebuilt if needed before calling this.
-    pub fn new(storage: Storage, branch: BranchId) -> JournalResult<Self> {
-        // Ensure journal directory exists
-        let journal_dir = storage.branch_journal_dir(&branch);
-        std::fs::create_dir_all(&journal_dir)?;
-
-        // Load index (should be clean after repair)
-        let index_path = storage.branch_meta_dir(&branch).join("journal.index");
-        let index = JournalIndex::load(&index_path).unwrap_or_default();
-
-        // Find the latest segment
-        let (current_segment, current_segment_size) = Self::find_latest_segment(&journal_dir)?;
-
-        Ok(Self {
-            storage,
-            branch,
-            current_segment,
-            current_segment_size,
-            writer: None,
-            index,
-        })
-    }
-
-    /// Create a new journal writer after recovery with a fresh index
-    pub fn new_with_index(
-        storage: Storage,
-        branch: BranchId,
-        index: JournalIndex,
-    ) -> JournalResult<Self> {
-        // Ensure journal directory exists
-        let journal_dir = storage.branch_journal_dir(&branch);
-        std::fs::create_dir_all(&journal_dir)?;
-
-        // Find the latest segment
-        let (current_segment, current_segment_size) = Self::find_latest_segment(&journal_dir)?;
-
-        Ok(Self {
-            storage,
-            branch,
-            current_segment,
-            current_segment_size,
-            writer: None,
-            index,
-        })
-    }
-
-    /// Find the latest segment number and its size
-    fn find_latest_segment(journal_dir: &Path) -> JournalResult<(u64, u64)> {
-        let mut max_segment = 0u64;
-        let mut size = 0u64;
-
-        if let Ok(entries) = std::fs::read_dir(journal_dir) {
-            for entry in entries.flatten() {
-                let file_name = entry.file_name();
-                let name = file_name.to_string_lossy();
-
-                if name.starts_with("segment-") && name.ends_with(".turnlog") {
-                    if let Some(num_str) = name
-                        .strip_prefix("segment-")
-                        .and_then(|s| s.strip_suffix(".turnlog"))
-                    {
-                        if let Ok(num) = num_str.parse::<u64>() {
-                            if num > max_segment {
-                                max_segment = num;
-                                size = entry.metadata()?.len();
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok((max_segment, size))
-    }
-
-    /// Append a turn record to the journal
-    ///
-    /// CRITICAL DURABILITY ORDERING:
-    /// 1. Write record to segment
-    /// 2. Flush and fsync segment to disk
-    /// 3. Update in-memory index
-    /// 4. Save and fsync index to disk
-    ///
-    /// This ensures the index never points to uncommitted data.
-    pub fn append(&mut self, record: &TurnRecord) -> JournalResult<()> {
-        let encoded = record
-            .encode()
-            .map_err(|e| JournalError::EncodingError(e.to_string()))?;
-        let record_size = encoded.len() as u64;
-
-        // Check if we need to rotate to a new segment
-        if self.current_segment_size + record_size > MAX_SEGMENT_SIZE {
-            self.rotate_segment()?;
-        }
-
-        // Ensure writer is open
-        if self.writer.is_none() {
-            self.open_segment()?;
-        }
-
-        // Record current offset before writing
-        let offset = self.current_segment_size;
-
-        // Write the record to the segment
-        let writer = self.writer.as_mut().unwrap();
-        writer.write_all(&encoded)?;
-
-        // Flush buffered writes
-        writer.flush()?;
-
-        // CRITICAL: Fsync the segment to disk BEFORE updating the index
-        // This ensures durability - the index will never point to uncommitted data
-        writer.get_mut().sync_all()?;
-
-        // Now it's safe to update the index
-        self.index
-            .add(&record.turn_id, self.current_segment, offset);
-        self.current_segment_size += record_size;
-
-        // Periodically save index (already has its own fsync)
-        self.save_index()?;
-
-        Ok(())
-    }
-
-    /// Open the current segment for writing
-    fn open_segment(&mut self) -> JournalResult<()> {
-        let segment_path = self.segment_path(self.current_segment);
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(segment_path)?;
-        self.writer = Some(BufWriter::new(file));
-        Ok(())
-    }
-
-    /// Rotate to a new segment
-    fn rotate_segment(&mut self) -> JournalResult<()> {
-        // Flush, fsync, and close current writer
-        if let Some(mut writer) = self.writer.take() {
-            writer.flush()?;
-            writer.get_mut().sync_all()?; // Ensure segment is durable
-        }
-
-        // Move to next segment
-        self.current_segment += 1;
-        self.current_segment_size = 0;
-        self.open_segment()?;
-
-        Ok(())
-    }
-
-    /// Save the index to disk
-    fn save_index(&self) -> JournalResult<()> {
-        let index_path = self
-            .storage
-            .branch_meta_dir(&self.branch)
-            .join("journal.index");
-        std::fs::create_dir_all(self.storage.branch_meta_dir(&self.branch))?;
-        self.index.save(&index_path)
-    }
-
-    /// Get the path for a segment
-    fn segment_path(&self, segment: u64) -> PathBuf {
-        self.storage
-            .branch_journal_dir(&self.branch)
-            .join(format!("segment-{:06}.turnlog", segment))
-    }
-
-    /// Flush any buffered writes and ensure durability
-    pub fn flush(&mut self) -> JournalResult<()> {
-        if let Some(ref mut writer) = self.writer {
-            writer.flush()?;
-            writer.get_mut().sync_all()?; // Ensure durability
-        }
-        self.save_index()?;
-        Ok(())
-    }
-}
-
-/// Journal reader for iterating over turn records
-pub struct JournalReader {
-    storage: Storage,
-    branch: BranchId,
-    index: JournalIndex,
-}
-
-impl JournalReader {
-    /// Create a new journal reader
-    pub fn new(storage: Storage, branch: BranchId) -> JournalResult<Self> {
-        // Load index
-        let index_path = storage.branch_meta_dir(&branch).join("journal.index");
-        let index = JournalIndex::load(&index_path)?;
-
-        Ok(Self {
-            storage,
-            branch,
-            index,
-        })
-    }
-
-    /// Create a new journal reader with an empty index
-    ///
-    /// Used during crash recovery when the index file doesn't exist yet
-    pub fn new_empty(storage: Storage, branch: BranchId) -> Self {
-        Self {
-            storage,
-            branch,
-            index: JournalIndex::default(),
-        }
-    }
-
-    /// Read a specific turn record
-    pub fn read(&self, turn_id: &TurnId) -> JournalResult<TurnRecord> {
-        let (segment, offset) = self
-            .index
-            .get(turn_id)
-            .ok_or_else(|| JournalError::TurnNotFound(turn_id.as_str().to_string()))?;
-
-        let segment_path = self.segment_path(segment);
-        let mut file = File::open(&segment_path)?;
-
-        // Seek to the offset
-        file.seek(io::SeekFrom::Start(offset))?;
-
-        // Read the record
-        let mut reader = BufReader::new(file);
-        let record = match read_record_from(&mut reader)? {
-            Some(record) => record,
-            None => return Err(JournalError::DecodingError("unexpected EOF".to_string())),
-        };
-
-        Ok(record)
-    }
-
-    /// Iterate from a specific turn
-    pub fn iter_from(&self, turn_id: &TurnId) -> JournalResult<JournalIterator> {
-        let (segment, offset) = self
-            .index
-            .get(turn_id)
-            .ok_or_else(|| JournalError::TurnNotFound(turn_id.as_str().to_string()))?;
-
-        JournalIterator::new(self.storage.clone(), self.branch.clone(), segment, offset)
-    }
-
-    /// Iterate over all turns in the journal
-    pub fn iter_all(&self) -> JournalResult<JournalIterator> {
-        JournalIterator::new(self.storage.clone(), self.branch.clone(), 0, 0)
-    }
-
-    /// Read a range of turn records
-    pub fn read_range(&self, start: usize, limit: usize) -> JournalResult<Vec<TurnRecord>> {
-        let mut records = Vec::new();
-        let mut iter = self.iter_all()?;
-
-        // Skip to start position
-        for _ in 0..start {
-            if iter.next().is_none() {
-                return Ok(records);
-            }
-        }
-
-        // Collect up to limit records
-        for _ in 0..limit {
-            match iter.next() {
-                Some(Ok(record)) => records.push(record),
-                Some(Err(e)) => return Err(e),
-                None => break,
-            }
-        }
-
-        Ok(records)
-    }
-
-    /// Get the path for a segment
-    fn segment_path(&self, segment: u64) -> PathBuf {
-        self.storage
-            .branch_journal_dir(&self.branch)
-            .join(format!("segment-{:06}.turnlog", segment))
-    }
-
-    /// Rebuild index by scanning all segments
-    pub fn rebuild_index(&self) -> JournalResult<JournalIndex> {
-        let mut new_index = JournalIndex::default();
-        let journal_dir = self.storage.branch_journal_dir(&self.branch);
-
-        // Find all segments
-        let mut segments = Vec::new();
-        if let Ok(entries) = std::fs::read_dir(&journal_dir) {
-            for entry in entries.flatten() {
-                let file_name = entry.file_name();
-                let name = file_name.to_string_lossy();
-
-                if name.starts_with("segment-") && name.ends_with(".turnlog") {
-                    if let Some(num_str) = name
-                        .strip_prefix("segment-")
-                        .and_then(|s| s.strip_suffix(".turnlog"))
-                    {
-                        if let Ok(num) = num_str.parse::<u64>() {
-                            segments.push(num);
-                        }
-                    }
-                }
-            }
-        }
-
-        segments.sort_unstable();
-
-        // Scan each segment
-        for segment_num in segments {
-            let segment_path = self.segment_path(segment_num);
-            let file = File::open(&segment_path)?;
-            let mut reader = BufReader::new(file);
-            let mut offset = 0u64;
-
-            loop {
-                let start_offset = offset;
-
-                match read_record_from(&mut reader)? {
-                    Some(record) => {
-                        new_index.add(&record.turn_id, segment_num, start_offset);
-                        offset = reader.stream_position()?;
-                    }
-                    None => break,
-                }
-            }
-        }
-
-        Ok(new_index)
-    }
-
-    /// Validate journal integrity and truncate if needed
-    pub fn validate_and_repair(&self) -> JournalResult<()> {
-        let journal_dir = self.storage.branch_journal_dir(&self.branch);
-
-        // Find all segments
-        let mut segments = Vec::new();
-        if let Ok(entries) = std::fs::read_dir(&journal_dir) {
-            for entry in entries.flatten() {
-                let file_name = entry.file_name();
-                let name = file_name.to_string_lossy();
-
-                if name.starts_with("segment-") && name.ends_with(".turnlog") {
-                    if let Some(num_str) = name
-                        .strip_prefix("segment-")
-                        .and_then(|s| s.strip_suffix(".turnlog"))
-                    {
-                        if let Ok(num) = num_str.parse::<u64>() {
-                            segments.push((num, entry.path()));
-                        }
-                    }
-                }
-            }
-        }
-
-        segments.sort_by_key(|(num, _)| *num);
-
-        // Validate each segment
-        for (segment_num, segment_path) in segments {
-            let file = File::open(&segment_path)?;
-            let mut reader = BufReader::new(file);
-            let mut last_valid_offset = 0u64;
-
-            loop {
-                let current_offset = reader.stream_position()?;
-
-                match read_record_from(&mut reader) {
-                    Ok(Some(_)) => {
-                        last_valid_offset = reader.stream_position()?;
-                    }
-                    Ok(None) => break,
-                    Err(e) => {
-                        tracing::warn!(
-                            "Corrupted record found in segment {} at offset {}: {}",
-                            segment_num,
-                            current_offset,
-                            e
-                        );
-
-                        if last_valid_offset < current_offset {
-                            let file = OpenOptions::new().write(true).open(&segment_path)?;
-                            file.set_len(last_valid_offset)?;
-                            tracing::info!(
-                                "Truncated segment {} to {} bytes",
-                                segment_num,
-                                last_valid_offset
-                            );
-                        }
-                        break;
-                    }
-                }
-            }
-        }
-
-        Ok(())
-    }
-}
-
-/// Iterator over journal entries
-pub struct JournalIterator {
-    storage: Storage,
-    branch: BranchId,
-    current_segment: u64,
-    reader: Option<BufReader<File>>,
-}
-
-impl JournalIterator {
-    /// Create a new iterator starting at the given segment and offset
-    fn new(storage: Storage, branch: BranchId, segment: u64, offset: u64) -> JournalResult<Self> {
-        let mut iter = Self {
-            storage,
-            branch,
-            current_segment: segment,
-            reader: None,
-        };
-
-        // Open the initial segment
-        iter.open_segment(segment, offset)?;
-
-        Ok(iter)
-    }
-
-    /// Open a segment file
-    fn open_segment(&mut self, segment: u64, offset: u64) -> JournalResult<()> {
-        let segment_path = self.segment_path(segment);
-
-        if !segment_path.exists() {
-            self.reader = None;
-            return Ok(());
-        }
-
-        let mut file = File::open(&segment_path)?;
-
-        if offset > 0 {
-            file.seek(io::SeekFrom::Start(offset))?;
-        }
-
-        self.reader = Some(BufReader::new(file));
-        Ok(())
-    }
-
-    /// Get the path for a segment
-    fn segment_path(&self, segment: u64) -> PathBuf {
-        self.storage
-            .branch_journal_dir(&self.branch)
-            .join(format!("segment-{:06}.turnlog", segment))
-    }
-}
-
-impl Iterator for JournalIterator {
-    type Item = JournalResult<TurnRecord>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            let reader = self.reader.as_mut()?;
-
-            match read_record_from(reader) {
-                Ok(Some(record)) => return Some(Ok(record)),
-                Ok(None) => {
-                    // End of segment - advance to next segment
-                    self.current_segment += 1;
-                    match self.open_segment(self.current_segment, 0) {
-                        Ok(()) => {
-                            if self.reader.is_none() {
-                                return None;
-                            }
-                        }
-                        Err(e) => return Some(Err(e)),
-                    }
-                }
-                Err(e) => return Some(Err(e)),
-            }
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::super::state::StateDelta;
-    use super::super::turn::{ActorId, FacetId, LogicalClock, compute_turn_id};
-    use super::*;
-    use tempfile::TempDir;
-
-    #[test]
-    fn test_journal_writer_creation() {
-        let temp = TempDir::new().unwrap();
-        let storage = Storage::new(temp.path().to_path_buf());
-        let branch = BranchId::main();
-
-        let _writer = JournalWriter::new(storage, branch).unwrap();
-        // Basic smoke test
-    }
-
-    #[test]
-    fn test_journal_append_and_read() {
-        let temp = TempDir::new().unwrap();
-        let storage = Storage::new(temp.path().to_path_buf());
-        let branch = BranchId::main();
-
-        // Create a writer and append some records
-        let mut writer = JournalWriter::new(storage.clone(), branch.clone()).unwrap();
-
-        let actor = ActorId::new();
-        let _facet = FacetId::new();
-        let clock = LogicalClock::zero();
-
-        let record = TurnRecord {
-            turn_id: compute_turn_id(&actor, &clock, &[]),
-            actor: actor.clone(),
-            branch: branch.clone(),
-            clock,
-            parent: None,
-            inputs: vec![],
-            outputs: vec![],
-            delta: StateDelta::empty(),
-            timestamp: chrono::Utc::now(),
-        };
-
-        writer.append(&record).unwrap();
-        writer.flush().unwrap();
-
-        // Create a reader and read the record back
-        let reader = JournalReader::new(storage, branch).unwrap();
-        let read_record = reader.read(&record.turn_id).unwrap();
-
-        assert_eq!(read_record.turn_id, record.turn_id);
-        assert_eq!(read_record.actor, record.actor);
-    }
-
-    #[test]
-    fn test_journal_iteration() {
-        let temp = TempDir::new().unwrap();
-        let storage = Storage::new(temp.path().to_path_buf());
-        let branch = BranchId::main();
-
-        // Write multiple records
-        let mut writer = JournalWriter::new(storage.clone(), branch.clone()).unwrap();
-
-        let actor = ActorId::new();
-        let _facet = FacetId::new();
-
-        for i in 0..5 {
-            let clock = LogicalClock(i);
-            let record = TurnRecord {
-                turn_id: compute_turn_id(&actor, &clock, &[]),
-                actor: actor.clone(),
-                branch: branch.clone(),
-                clock,
-                parent: None,
-                inputs: vec![],
-                outputs: vec![],
-                delta: StateDelta::empty(),
-                timestamp: chrono::Utc::now(),
-            };
-            writer.append(&record).unwrap();
-        }
-        writer.flush().unwrap();
-
-        // Read all records
-        let reader = JournalReader::new(storage, branch).unwrap();
-        let records: Vec<_> = reader.iter_all().unwrap().collect();
-
-        assert_eq!(records.len(), 5);
-        for (i, result) in records.iter().enumerate() {
-            let record = result.as_ref().unwrap();
-            assert_eq!(record.clock.0, i as u64);
-        }
-    }
-
-    #[test]
-    fn test_journal_segment_rotation() {
-        // This test is skipped for now since creating realistic large deltas
-        // requires more complex CRDT state. The segment rotation logic is
-        // correct and will be tested in integration tests with real workloads.
-        //
-        // The rotation mechanism works correctly - it checks if
-        // current_segment_size + record_size > MAX_SEGMENT_SIZE
-        // and rotates when needed.
-    }
-
-    #[test]
-    fn test_journal_index_rebuild() {
-        let temp = TempDir::new().unwrap();
-        let storage = Storage::new(temp.path().to_path_buf());
-        let branch = BranchId::main();
-
-        // Write some records
-        let mut writer = JournalWriter::new(storage.clone(), branch.clone()).unwrap();
-
-        let actor = ActorId::new();
-        let mut turn_ids = Vec::new();
-
-        for i in 0..5 {
-            let clock = LogicalClock(i);
-            let record = TurnRecord {
-                turn_id: compute_turn_id(&actor, &clock, &[]),
-                actor: actor.clone(),
-                branch: branch.clone(),
-                clock,
-                parent: None,
-                inputs: vec![],
-                outputs: vec![],
-                delta: StateDelta::empty(),
-                timestamp: chrono::Utc::now(),
-            };
-            turn_ids.push(record.turn_id.clone());
-            writer.append(&record).unwrap();
-        }
-        writer.flush().unwrap();
-
-        // Delete the index
-        let index_path = storage.branch_meta_dir(&branch).join("journal.index");
-        std::fs::remove_file(&index_path).ok();
-
-        // Rebuild index
-        let reader = JournalReader::new(storage.clone(), branch.clone()).unwrap_or_else(|_| {
-            // If reader fails due to missing index, create it manually
-            JournalReader {
-                storage: storage.clone(),
-                branch: branch.clone(),
-                index: JournalIndex::default(),
-            }
-        });
-
-        let rebuilt_index = reader.rebuild_index().unwrap();
-
-        // Verify all turn IDs are in the rebuilt index
-        for turn_id in &turn_ids {
-            assert!(
-                rebuilt_index.get(turn_id).is_some(),
-                "Turn ID {:?} not found in rebuilt index",
-                turn_id
-            );
-        }
-    }
-}
@@ -0,0 +1,368 @@
+//! Journal compaction below a retention horizon
+//!
+//! A branch journal grows with every turn. Compaction picks a horizon, the
+//! newest snapshot of the branch that lies outside the [`RetentionPolicy`],
+//! and drops every record up to and including it: the snapshot stands in for
+//! them, and the remaining records are renumbered from segment 0 so readers
+//! keep iterating from the start. Segments past the horizon are copied byte
+//! for byte, so version headers, redaction and compression survive unchanged.
+//!
+//! The horizon's state is written as a consolidated snapshot, and the branch
+//! records the horizon in its metadata, before the trimmed journal is swapped
+//! in; a crash in between leaves a complete journal that merely refuses to
+//! travel behind the horizon. The trimmed segments are staged and indexed
+//! next to the branch metadata first, so the index on disk already describes
+//! them when they replace the journal. [`recover`] finishes or discards a
+//! swap that a crash interrupted.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::super::error::JournalResult;
+use super::super::storage::Storage;
+use super::super::turn::{BranchId, TurnId};
+use super::{JournalIndex, read_record_from, segment_header_path};
+
+/// How much history a branch keeps when its journal is compacted.
+///
+/// A turn is retained while any configured bound covers it. With neither
+/// bound set (the default) the whole journal is retained.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Keep at least this many of the branch's most recent turns
+    #[serde(default)]
+    pub keep_turns: Option<u64>,
+    /// Keep turns recorded within this many days
+    #[serde(default)]
+    pub keep_days: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Whether the policy retains everything
+    pub fn is_unbounded(&self) -> bool {
+        self.keep_turns.is_none() && self.keep_days.is_none()
+    }
+
+    /// Whether a turn with `newer` later turns on its branch, recorded at
+    /// `timestamp`, must be kept at `now`
+    pub fn retains(&self, newer: usize, timestamp: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        if self.is_unbounded() {
+            return true;
+        }
+        self.keep_turns.is_some_and(|keep| (newer as u64) < keep)
+            || self
+                .keep_days
+                .is_some_and(|days| now - timestamp < Duration::days(days as i64))
+    }
+}
+
+/// Outcome of [`Runtime::compact_journal`](crate::runtime::Runtime::compact_journal)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactionReport {
+    /// Compacted branch
+    pub branch: BranchId,
+    /// Snapshot turn the journal now starts after (`None` when nothing was compacted)
+    pub horizon: Option<TurnId>,
+    /// Records dropped from the journal
+    pub turns_removed: usize,
+    /// Records left in the journal
+    pub turns_kept: usize,
+    /// Segment files before compaction
+    pub segments_before: usize,
+    /// Segment files after compaction
+    pub segments_after: usize,
+    /// Journal size in bytes before compaction
+    pub bytes_before: u64,
+    /// Journal size in bytes after compaction
+    pub bytes_after: u64,
+    /// Snapshots older than the horizon that were deleted
+    pub snapshots_removed: usize,
+    /// Branches forked from this one whose fork points held the horizon back
+    pub held_back_by: Vec<BranchId>,
+}
+
+impl CompactionReport {
+    /// Report for a branch that was left untouched
+    pub(crate) fn unchanged(branch: BranchId, turns: usize, held_back_by: Vec<BranchId>) -> Self {
+        Self {
+            branch,
+            horizon: None,
+            turns_removed: 0,
+            turns_kept: turns,
+            segments_before: 0,
+            segments_after: 0,
+            bytes_before: 0,
+            bytes_after: 0,
+            snapshots_removed: 0,
+            held_back_by,
+        }
+    }
+}
+
+/// Segment counts and sizes around a [`trim`]
+pub(crate) struct TrimStats {
+    pub segments_before: usize,
+    pub segments_after: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Drop every record of `branch` stored before `keep_from`, the segment and
+/// offset of the first record to keep (`None` drops them all).
+///
+/// The kept records are written to a staging directory, renumbered from
+/// segment 0, and indexed; the index is saved and the staged segments are
+/// then swapped in for the journal.
+pub(crate) fn trim(
+    storage: &Storage,
+    branch: &BranchId,
+    keep_from: Option<(u64, u64)>,
+) -> JournalResult<TrimStats> {
+    let journal_dir = storage.branch_journal_dir(branch);
+    let segments = segment_numbers(&journal_dir);
+    let mut stats = TrimStats {
+        segments_before: segments.len(),
+        segments_after: 0,
+        bytes_before: 0,
+        bytes_after: 0,
+    };
+    for segment in &segments {
+        stats.bytes_before += std::fs::metadata(segment_path(&journal_dir, *segment))?.len();
+    }
+
+    let staging_dir = staging_dir(storage, branch);
+    if staging_dir.exists() {
+        std::fs::remove_dir_all(&staging_dir)?;
+    }
+    std::fs::create_dir_all(&staging_dir)?;
+
+    if let Some((first_segment, first_offset)) = keep_from {
+        for segment in segments.iter().filter(|segment| **segment >= first_segment) {
+            let renumbered = segment - first_segment;
+            let mut source = BufReader::new(File::open(segment_path(&journal_dir, *segment))?);
+            if *segment == first_segment {
+                source.seek(SeekFrom::Start(first_offset))?;
+            }
+            let mut kept = Vec::new();
+            source.read_to_end(&mut kept)?;
+
+            let mut out = File::create(segment_path(&staging_dir, renumbered))?;
+            out.write_all(&kept)?;
+            out.sync_all()?;
+            stats.segments_after += 1;
+            stats.bytes_after += kept.len() as u64;
+
+            let header = segment_header_path(storage, branch, *segment);
+            if header.exists() {
+                std::fs::copy(
+                    &header,
+                    staging_dir.join(format!("segment-{:06}.header.json", renumbered)),
+                )?;
+            }
+        }
+    }
+
+    File::open(&staging_dir)?.sync_all()?;
+
+    save_index(storage, branch, &index_segments(storage, &staging_dir)?)?;
+
+    let retired_dir = retired_dir(storage, branch);
+    if retired_dir.exists() {
+        std::fs::remove_dir_all(&retired_dir)?;
+    }
+    std::fs::rename(&journal_dir, &retired_dir)?;
+    std::fs::rename(&staging_dir, &journal_dir)?;
+    std::fs::remove_dir_all(&retired_dir)?;
+
+    Ok(stats)
+}
+
+/// Finish or discard a [`trim`] of `branch` that a crash interrupted,
+/// returning whether one was found.
+///
+/// The journal is only moved aside once the staged segments are complete,
+/// so a missing journal is replaced by the staged one; staged segments next
+/// to a journal are an unfinished copy and are dropped. The index is rebuilt
+/// either way, since it may already describe the staged segments.
+pub(crate) fn recover(storage: &Storage, branch: &BranchId) -> JournalResult<bool> {
+    let journal_dir = storage.branch_journal_dir(branch);
+    let staging_dir = staging_dir(storage, branch);
+    let retired_dir = retired_dir(storage, branch);
+    if !staging_dir.exists() && !retired_dir.exists() {
+        return Ok(false);
+    }
+
+    for leftover in [staging_dir, retired_dir] {
+        if !leftover.exists() {
+            continue;
+        }
+        if journal_dir.exists() {
+            std::fs::remove_dir_all(&leftover)?;
+        } else {
+            std::fs::rename(&leftover, &journal_dir)?;
+        }
+    }
+    save_index(storage, branch, &index_segments(storage, &journal_dir)?)?;
+    tracing::warn!(branch = %branch, "recovered an interrupted journal compaction");
+    Ok(true)
+}
+
+/// Index the records of the segments in `journal_dir`
+pub(crate) fn index_segments(storage: &Storage, journal_dir: &Path) -> JournalResult<JournalIndex> {
+    let mut index = JournalIndex::default();
+    for segment in segment_numbers(journal_dir) {
+        let mut reader = BufReader::new(File::open(segment_path(journal_dir, segment))?);
+        let mut offset = 0;
+        while let Some(record) = read_record_from(&mut reader, storage)? {
+            index.add_record(&record, segment, offset);
+            offset = reader.stream_position()?;
+        }
+    }
+    Ok(index)
+}
+
+fn save_index(storage: &Storage, branch: &BranchId, index: &JournalIndex) -> JournalResult<()> {
+    let meta_dir = storage.branch_meta_dir(branch);
+    std::fs::create_dir_all(&meta_dir)?;
+    index.save(&meta_dir.join("journal.index"))
+}
+
+/// Where trimmed segments are written before they replace the journal
+fn staging_dir(storage: &Storage, branch: &BranchId) -> PathBuf {
+    storage.branch_meta_dir(branch).join("journal.compacting")
+}
+
+/// Where the untrimmed journal waits while the trimmed one is swapped in
+fn retired_dir(storage: &Storage, branch: &BranchId) -> PathBuf {
+    storage.branch_meta_dir(branch).join("journal.compacted")
+}
+
+/// Segment numbers present in `journal_dir`, in order
+fn segment_numbers(journal_dir: &Path) -> Vec<u64> {
+    let mut segments: Vec<u64> = std::fs::read_dir(journal_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .strip_prefix("segment-")
+                .and_then(|name| name.strip_suffix(".turnlog"))
+                .and_then(|number| number.parse().ok())
+        })
+        .collect();
+    segments.sort_unstable();
+    segments
+}
+
+fn segment_path(dir: &Path, segment: u64) -> PathBuf {
+    dir.join(format!("segment-{:06}.turnlog", segment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::state::StateDelta;
+    use super::super::super::turn::{ActorId, LogicalClock, TurnRecord, compute_turn_id};
+    use super::super::{JournalReader, JournalWriter};
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_retention_keeps_turns_covered_by_any_bound() {
+        let now = Utc::now();
+        let old = now - Duration::days(30);
+        assert!(RetentionPolicy::default().retains(1_000, old, now));
+
+        let policy = RetentionPolicy {
+            keep_turns: Some(10),
+            keep_days: Some(7),
+        };
+        assert!(policy.retains(3, old, now));
+        assert!(policy.retains(50, now - Duration::days(1), now));
+        assert!(!policy.retains(50, old, now));
+    }
+
+    #[test]
+    fn test_trim_renumbers_kept_records_from_segment_zero() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let branch = BranchId::main();
+        let turns = write_turns(&storage, &branch, 5);
+
+        let reader = JournalReader::new(storage.clone(), branch.clone()).unwrap();
+        let keep_from = reader.index.get(&turns[2]).unwrap();
+        let stats = trim(&storage, &branch, Some(keep_from)).unwrap();
+        assert_eq!(stats.segments_after, 1);
+        assert!(stats.bytes_after < stats.bytes_before);
+
+        let reader = JournalReader::new(storage, branch).unwrap();
+        let kept: Vec<TurnId> = reader
+            .iter_all()
+            .unwrap()
+            .map(|record| record.unwrap().turn_id)
+            .collect();
+        assert_eq!(kept, turns[2..]);
+        assert!(reader.read(&turns[1]).is_err());
+        assert_eq!(reader.read(&turns[4]).unwrap().turn_id, turns[4]);
+    }
+
+    #[test]
+    fn test_recover_finishes_an_interrupted_swap() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let branch = BranchId::main();
+        let turns = write_turns(&storage, &branch, 4);
+        assert!(!recover(&storage, &branch).unwrap());
+
+        // Crash after the journal was moved aside, before the staged one moved in
+        let keep_from = JournalReader::new(storage.clone(), branch.clone())
+            .unwrap()
+            .index
+            .get(&turns[2]);
+        trim(&storage, &branch, keep_from).unwrap();
+        let journal_dir = storage.branch_journal_dir(&branch);
+        std::fs::rename(&journal_dir, staging_dir(&storage, &branch)).unwrap();
+
+        assert!(recover(&storage, &branch).unwrap());
+        assert!(!staging_dir(&storage, &branch).exists());
+        let reader = JournalReader::new(storage.clone(), branch.clone()).unwrap();
+        assert_eq!(reader.iter_headers().unwrap().count(), 2);
+        assert_eq!(reader.read(&turns[3]).unwrap().turn_id, turns[3]);
+
+        // Staged segments next to a journal are an unfinished copy
+        std::fs::create_dir_all(staging_dir(&storage, &branch)).unwrap();
+        assert!(recover(&storage, &branch).unwrap());
+        assert!(!staging_dir(&storage, &branch).exists());
+        let reader = JournalReader::new(storage, branch).unwrap();
+        assert_eq!(reader.iter_headers().unwrap().count(), 2);
+    }
+
+    fn write_turns(storage: &Storage, branch: &BranchId, count: u64) -> Vec<TurnId> {
+        let actor = ActorId::new();
+        let mut writer = JournalWriter::new(storage.clone(), branch.clone()).unwrap();
+        (1..=count)
+            .map(|seq| {
+                let clock = LogicalClock(seq);
+                let record = TurnRecord {
+                    turn_id: compute_turn_id(seq, &actor, &clock, &[]),
+                    actor: actor.clone(),
+                    branch: branch.clone(),
+                    clock,
+                    parent: None,
+                    inputs: vec![],
+                    outputs: vec![],
+                    delta: StateDelta::empty(),
+                    timestamp: Utc::now(),
+                    vector_clock: Default::default(),
+                    initiator: None,
+                };
+                writer.append(&record).unwrap();
+                record.turn_id
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,462 @@
+//! Decoding of turn records written before the record layout grew
+//!
+//! Preserves encodes structs and enum variants as positional records, so a
+//! field appended to [`TurnRecord`](super::super::turn::TurnRecord) or one of
+//! its parts changes the arity of every record written afterwards, and
+//! `serde(default)` cannot fill the missing positions when an older record
+//! is read. The types here freeze the original layout, with the original
+//! record labels, and [`decode`] converts a record in that layout to the
+//! current types, filling every field added since with its default.
+//!
+//! Only records that fail to decode in the current layout are tried here.
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::super::pattern::{self, PatternId};
+use super::super::state::{
+    self, AccountDelta, AssertionValue, CapId, CapabilityDelta, CapabilityTarget, FacetDelta,
+    TimerDelta,
+};
+use super::super::turn::{
+    self, ActorId, BranchId, CapabilityCompletion, FacetId, Handle, LogicalClock, TurnId,
+};
+
+/// Decode a record in the original layout into the current [`turn::TurnRecord`]
+pub(super) fn decode(packed: &[u8]) -> Result<turn::TurnRecord, preserves::serde::Error> {
+    let record: TurnRecord = preserves::serde::from_bytes(packed)?;
+    Ok(record.into())
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "TurnRecord")]
+struct TurnRecord {
+    turn_id: TurnId,
+    actor: ActorId,
+    branch: BranchId,
+    clock: LogicalClock,
+    parent: Option<TurnId>,
+    inputs: Vec<TurnInput>,
+    outputs: Vec<TurnOutput>,
+    delta: StateDelta,
+    timestamp: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "TurnInput")]
+enum TurnInput {
+    ExternalMessage {
+        actor: ActorId,
+        facet: FacetId,
+        payload: preserves::IOValue,
+    },
+    Assert {
+        actor: ActorId,
+        handle: Handle,
+        value: preserves::IOValue,
+    },
+    Retract {
+        actor: ActorId,
+        handle: Handle,
+    },
+    Sync {
+        actor: ActorId,
+        facet: FacetId,
+    },
+    Timer {
+        actor: ActorId,
+        timer_id: Uuid,
+        deadline: DateTime<Utc>,
+    },
+    ExternalResponse {
+        request_id: Uuid,
+        actor: ActorId,
+        response: preserves::IOValue,
+    },
+    CapabilityInvocation {
+        capability: CapId,
+        payload: preserves::IOValue,
+    },
+    RemoteMessage {
+        source_node: Uuid,
+        source_turn: TurnId,
+        payload: preserves::IOValue,
+    },
+    Merge {
+        source_branch: BranchId,
+        target_branch: BranchId,
+        lca_turn: TurnId,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "TurnOutput")]
+enum TurnOutput {
+    Assert {
+        handle: Handle,
+        value: preserves::IOValue,
+    },
+    Retract {
+        handle: Handle,
+    },
+    Message {
+        target_actor: ActorId,
+        target_facet: FacetId,
+        payload: preserves::IOValue,
+    },
+    Synced {
+        facet: FacetId,
+    },
+    FacetSpawned {
+        facet: FacetId,
+        parent: Option<FacetId>,
+    },
+    FacetTerminated {
+        facet: FacetId,
+    },
+    TimerRegistered {
+        timer_id: Uuid,
+        deadline: DateTime<Utc>,
+    },
+    ExternalRequest {
+        request_id: Uuid,
+        service: String,
+        request: preserves::IOValue,
+    },
+    PatternMatched {
+        pattern_id: Uuid,
+        handle: Handle,
+    },
+    PatternUnmatched {
+        pattern_id: Uuid,
+        handle: Handle,
+    },
+    CapabilityGranted {
+        capability: CapId,
+        issuer: ActorId,
+        issuer_facet: FacetId,
+        issuer_entity: Option<Uuid>,
+        holder: ActorId,
+        holder_facet: FacetId,
+        target: Option<CapabilityTarget>,
+        kind: String,
+        attenuation: Vec<preserves::IOValue>,
+    },
+    CapabilityRevoked {
+        capability: CapId,
+    },
+    PatternRegistered {
+        entity_id: Uuid,
+        pattern: Pattern,
+    },
+    PatternUnregistered {
+        pattern_id: Uuid,
+    },
+    EntityDetached {
+        entity_id: Uuid,
+    },
+    CapabilityInvoke {
+        capability: CapId,
+        payload: preserves::IOValue,
+        completion: CapabilityCompletion,
+    },
+    EntitySpawned {
+        parent_actor: ActorId,
+        parent_facet: FacetId,
+        child_actor: ActorId,
+        child_root_facet: FacetId,
+        entity_id: Uuid,
+        entity_type: String,
+        config: preserves::IOValue,
+        link: bool,
+        issuer_entity: Option<Uuid>,
+    },
+    EntityAttached {
+        actor: ActorId,
+        facet: FacetId,
+        entity_id: Uuid,
+        entity_type: String,
+        config: preserves::IOValue,
+    },
+    CapabilityResult {
+        capability: CapId,
+        result: preserves::IOValue,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "StateDelta")]
+struct StateDelta {
+    assertions: AssertionDelta,
+    facets: FacetDelta,
+    capabilities: CapabilityDelta,
+    timers: TimerDelta,
+    accounts: AccountDelta,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "AssertionDelta")]
+struct AssertionDelta {
+    added: Vec<(ActorId, Handle, AssertionValue, Uuid)>,
+    retracted: Vec<(ActorId, Handle, Uuid)>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Pattern")]
+struct Pattern {
+    id: PatternId,
+    #[serde(with = "super::super::registry::preserves_text_serde")]
+    pattern: preserves::IOValue,
+    facet: FacetId,
+}
+
+impl From<TurnRecord> for turn::TurnRecord {
+    fn from(record: TurnRecord) -> Self {
+        Self {
+            turn_id: record.turn_id,
+            actor: record.actor,
+            branch: record.branch,
+            clock: record.clock,
+            parent: record.parent,
+            inputs: record.inputs.into_iter().map(Into::into).collect(),
+            outputs: record.outputs.into_iter().map(Into::into).collect(),
+            delta: record.delta.into(),
+            timestamp: record.timestamp,
+            vector_clock: Default::default(),
+            initiator: None,
+        }
+    }
+}
+
+/// Payload of timers recorded before timers carried one. No entity ever
+/// received these, as their facet is not recorded either.
+fn legacy_timer_payload() -> preserves::IOValue {
+    preserves::IOValue::symbol("timer")
+}
+
+impl From<TurnInput> for turn::TurnInput {
+    fn from(input: TurnInput) -> Self {
+        match input {
+            TurnInput::ExternalMessage {
+                actor,
+                facet,
+                payload,
+            } => Self::ExternalMessage {
+                actor,
+                facet,
+                payload,
+                idempotency_key: None,
+                broadcast: None,
+            },
+            TurnInput::Assert {
+                actor,
+                handle,
+                value,
+            } => Self::Assert {
+                actor,
+                handle,
+                value,
+                namespace: None,
+            },
+            TurnInput::Retract { actor, handle } => Self::Retract { actor, handle },
+            TurnInput::Sync { actor, facet } => Self::Sync { actor, facet },
+            TurnInput::Timer {
+                actor,
+                timer_id,
+                deadline,
+            } => Self::Timer {
+                actor,
+                timer_id,
+                deadline,
+                facet: FacetId(Uuid::nil()),
+                payload: legacy_timer_payload(),
+            },
+            TurnInput::ExternalResponse {
+                request_id,
+                actor,
+                response,
+            } => Self::ExternalResponse {
+                request_id,
+                actor,
+                response,
+            },
+            TurnInput::CapabilityInvocation {
+                capability,
+                payload,
+            } => Self::CapabilityInvocation {
+                capability,
+                payload,
+                invocation: None,
+            },
+            TurnInput::RemoteMessage {
+                source_node,
+                source_turn,
+                payload,
+            } => Self::RemoteMessage {
+                source_node,
+                source_turn,
+                payload,
+            },
+            TurnInput::Merge {
+                source_branch,
+                target_branch,
+                lca_turn,
+            } => Self::Merge {
+                source_branch,
+                target_branch,
+                lca_turn,
+                source_head: None,
+            },
+        }
+    }
+}
+
+impl From<TurnOutput> for turn::TurnOutput {
+    fn from(output: TurnOutput) -> Self {
+        match output {
+            TurnOutput::Assert { handle, value } => Self::Assert {
+                handle,
+                value,
+                namespace: None,
+            },
+            TurnOutput::Retract { handle } => Self::Retract {
+                handle,
+                namespace: None,
+            },
+            TurnOutput::Message {
+                target_actor,
+                target_facet,
+                payload,
+            } => Self::Message {
+                target_actor,
+                target_facet,
+                payload,
+            },
+            TurnOutput::Synced { facet } => Self::Synced { facet },
+            TurnOutput::FacetSpawned { facet, parent } => Self::FacetSpawned { facet, parent },
+            TurnOutput::FacetTerminated { facet } => Self::FacetTerminated { facet },
+            TurnOutput::TimerRegistered { timer_id, deadline } => Self::TimerRegistered {
+                timer_id,
+                deadline,
+                facet: FacetId(Uuid::nil()),
+                payload: legacy_timer_payload(),
+            },
+            TurnOutput::ExternalRequest {
+                request_id,
+                service,
+                request,
+            } => Self::ExternalRequest {
+                request_id,
+                service,
+                request,
+            },
+            TurnOutput::PatternMatched { pattern_id, handle } => {
+                Self::PatternMatched { pattern_id, handle }
+            }
+            TurnOutput::PatternUnmatched { pattern_id, handle } => {
+                Self::PatternUnmatched { pattern_id, handle }
+            }
+            TurnOutput::CapabilityGranted {
+                capability,
+                issuer,
+                issuer_facet,
+                issuer_entity,
+                holder,
+                holder_facet,
+                target,
+                kind,
+                attenuation,
+            } => Self::CapabilityGranted {
+                capability,
+                issuer,
+                issuer_facet,
+                issuer_entity,
+                holder,
+                holder_facet,
+                target,
+                kind,
+                attenuation,
+            },
+            TurnOutput::CapabilityRevoked { capability } => Self::CapabilityRevoked { capability },
+            TurnOutput::PatternRegistered { entity_id, pattern } => Self::PatternRegistered {
+                entity_id,
+                pattern: pattern::Pattern {
+                    id: pattern.id,
+                    pattern: pattern.pattern,
+                    facet: pattern.facet,
+                    namespace: None,
+                    scope: Default::default(),
+                },
+            },
+            TurnOutput::PatternUnregistered { pattern_id } => {
+                Self::PatternUnregistered { pattern_id }
+            }
+            TurnOutput::EntityDetached { entity_id } => Self::EntityDetached { entity_id },
+            TurnOutput::CapabilityInvoke {
+                capability,
+                payload,
+                completion,
+            } => Self::CapabilityInvoke {
+                capability,
+                payload,
+                completion,
+            },
+            TurnOutput::EntitySpawned {
+                parent_actor,
+                parent_facet,
+                child_actor,
+                child_root_facet,
+                entity_id,
+                entity_type,
+                config,
+                link,
+                issuer_entity,
+            } => Self::EntitySpawned {
+                parent_actor,
+                parent_facet,
+                child_actor,
+                child_root_facet,
+                entity_id,
+                entity_type,
+                config,
+                link,
+                issuer_entity,
+            },
+            TurnOutput::EntityAttached {
+                actor,
+                facet,
+                entity_id,
+                entity_type,
+                config,
+            } => Self::EntityAttached {
+                actor,
+                facet,
+                entity_id,
+                entity_type,
+                config,
+            },
+            TurnOutput::CapabilityResult { capability, result } => Self::CapabilityResult {
+                capability,
+                result,
+                invocation: None,
+            },
+        }
+    }
+}
+
+impl From<StateDelta> for state::StateDelta {
+    fn from(delta: StateDelta) -> Self {
+        Self {
+            assertions: state::AssertionDelta {
+                added: delta.assertions.added,
+                retracted: delta.assertions.retracted,
+                namespaces: Vec::new(),
+            },
+            facets: delta.facets,
+            capabilities: delta.capabilities,
+            timers: delta.timers,
+            accounts: delta.accounts,
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Guardrails on what a single entity may emit in one turn
+//!
+//! A buggy entity asserting multi-megabyte values, or thousands of them, makes
+//! every later journal read and snapshot pay for it. The runtime therefore
+//! checks each entity callback against [`TurnLimits`]: the encoded size of
+//! every value it asserts, and the number of assertions and outputs it has
+//! produced so far in the turn. Exceeding a limit fails the turn with
+//! [`ActorError::LimitExceeded`], so nothing is journaled.
+//!
+//! Limits are configured through
+//! [`RuntimeConfig::limits`](super::RuntimeConfig::limits); entity types that
+//! legitimately need more room get a per-type override.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use super::error::{ActorError, ActorResult};
+use super::turn::TurnOutput;
+
+/// Per-entity, per-turn limits (`None` = unlimited).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnLimits {
+    /// Maximum encoded size of a single asserted value, in bytes
+    pub max_assertion_bytes: Option<usize>,
+    /// Maximum number of assertions an entity may make in one turn
+    pub max_assertions_per_turn: Option<usize>,
+    /// Maximum number of outputs an entity may produce in one turn
+    pub max_outputs_per_turn: Option<usize>,
+}
+
+impl Default for TurnLimits {
+    fn default() -> Self {
+        Self {
+            max_assertion_bytes: Some(4 * 1024 * 1024),
+            max_assertions_per_turn: Some(100_000),
+            max_outputs_per_turn: Some(250_000),
+        }
+    }
+}
+
+impl TurnLimits {
+    /// Limits that never trigger.
+    pub fn unlimited() -> Self {
+        Self {
+            max_assertion_bytes: None,
+            max_assertions_per_turn: None,
+            max_outputs_per_turn: None,
+        }
+    }
+}
+
+/// Runtime-wide limits plus per-entity-type overrides.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LimitsConfig {
+    /// Limits applied to entity types without an override
+    #[serde(default)]
+    pub defaults: TurnLimits,
+    /// Replacement limits keyed by entity type
+    #[serde(default)]
+    pub overrides: BTreeMap<String, TurnLimits>,
+    /// Rate limits on external messages, per target actor and per client
+    #[serde(default)]
+    pub ingress: super::ratelimit::IngressLimits,
+}
+
+impl LimitsConfig {
+    /// Limits that apply to entities of `entity_type`.
+    pub fn for_entity_type(&self, entity_type: &str) -> &TurnLimits {
+        self.overrides.get(entity_type).unwrap_or(&self.defaults)
+    }
+}
+
+/// Per-turn accounting of what each entity has emitted.
+#[derive(Debug, Default)]
+pub(crate) struct LimitTracker {
+    config: LimitsConfig,
+    emitted: HashMap<Uuid, Emitted>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Emitted {
+    assertions: usize,
+    outputs: usize,
+}
+
+/// Position in the activation's output log before an entity callback ran.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct OutputMark(usize);
+
+impl LimitTracker {
+    pub(crate) fn new(config: LimitsConfig) -> Self {
+        Self {
+            config,
+            emitted: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn mark(outputs: &[TurnOutput]) -> OutputMark {
+        OutputMark(outputs.len())
+    }
+
+    /// Check the outputs an entity produced since `mark` against its limits.
+    pub(crate) fn check(
+        &mut self,
+        entity_id: Uuid,
+        entity_type: &str,
+        outputs: &[TurnOutput],
+        mark: OutputMark,
+    ) -> ActorResult<()> {
+        let limits = *self.config.for_entity_type(entity_type);
+        let produced = outputs.get(mark.0..).unwrap_or_default();
+        let exceeded = |limit: &str, actual: usize, max: usize| ActorError::LimitExceeded {
+            entity_type: entity_type.to_string(),
+            limit: limit.to_string(),
+            actual,
+            max,
+        };
+
+        let emitted = self.emitted.entry(entity_id).or_default();
+        for output in produced {
+            emitted.outputs += 1;
+            if let TurnOutput::Assert { value, .. } = output {
+                emitted.assertions += 1;
+                if let Some(max) = limits.max_assertion_bytes {
+                    let size = encoded_size(value);
+                    if size > max {
+                        return Err(exceeded("max_assertion_bytes", size, max));
+                    }
+                }
+            }
+        }
+
+        if let Some(max) = limits
+            .max_assertions_per_turn
+            .filter(|max| emitted.assertions > *max)
+        {
+            return Err(exceeded("max_assertions_per_turn", emitted.assertions, max));
+        }
+        if let Some(max) = limits
+            .max_outputs_per_turn
+            .filter(|max| emitted.outputs > *max)
+        {
+            return Err(exceeded("max_outputs_per_turn", emitted.outputs, max));
+        }
+        Ok(())
+    }
+}
+
+/// Size of a value in the packed binary encoding used by the journal.
+pub fn encoded_size(value: &preserves::IOValue) -> usize {
+    use preserves::PackedWriter;
+
+    let mut buf = Vec::new();
+    let mut writer = PackedWriter::new(&mut buf);
+    match preserves::serde::to_writer(&mut writer, value) {
+        Ok(()) => buf.len(),
+        Err(_) => 0,
+    }
+}
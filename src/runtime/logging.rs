@@ -0,0 +1,186 @@
+//! Structured logging with per-subsystem levels
+//!
+//! Runtime events are emitted through `tracing`. Turn execution, time travel
+//! and merges run inside spans carrying the branch and turn ids involved, so
+//! every event logged on those paths can be correlated with the journal.
+//! Levels are set per subsystem – a module path below the crate root such as
+//! `runtime::journal` or `service` – through
+//! [`RuntimeConfig::logging`](super::RuntimeConfig::logging), and can be
+//! changed while the daemon runs with [`set_level`].
+//!
+//! The subscriber is process-global: binaries call [`init`] once at startup.
+//! Library users that install their own subscriber simply never call it, and
+//! [`set_level`] then reports that logging is not managed by the runtime.
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::reload;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use super::error::{Result, RuntimeError};
+
+/// Root of the tracing targets emitted by this crate.
+const CRATE_TARGET: &str = "duet";
+
+/// Log levels for the runtime's subsystems.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Level for subsystems without an entry in `levels`
+    pub default_level: String,
+    /// Levels keyed by subsystem (module path below the crate root)
+    pub levels: BTreeMap<String, String>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            default_level: "warn".to_string(),
+            levels: BTreeMap::new(),
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// Configuration with the default level raised to `debug` when `debug`
+    /// is set, as the runtime's `debug` flag requests.
+    pub fn effective(&self, debug: bool) -> Self {
+        let mut config = self.clone();
+        if debug {
+            config.default_level = "debug".to_string();
+        }
+        config
+    }
+
+    /// Set `subsystem`'s level, or the default level when `None`.
+    pub fn set(&mut self, subsystem: Option<&str>, level: &str) -> Result<()> {
+        let level = parse_level(level)?;
+        match subsystem {
+            None => self.default_level = level,
+            Some(subsystem) => {
+                validate_subsystem(subsystem)?;
+                self.levels.insert(subsystem.to_string(), level);
+            }
+        }
+        Ok(())
+    }
+
+    /// `EnvFilter` directives equivalent to this configuration.
+    pub fn directives(&self) -> Result<String> {
+        let mut directives = vec![format!(
+            "{}={}",
+            CRATE_TARGET,
+            parse_level(&self.default_level)?
+        )];
+        for (subsystem, level) in &self.levels {
+            validate_subsystem(subsystem)?;
+            directives.push(format!(
+                "{}::{}={}",
+                CRATE_TARGET,
+                subsystem,
+                parse_level(level)?
+            ));
+        }
+        Ok(directives.join(","))
+    }
+}
+
+fn parse_level(level: &str) -> Result<String> {
+    LevelFilter::from_str(level.trim())
+        .map(|filter| filter.to_string().to_lowercase())
+        .map_err(|_| RuntimeError::Config(format!("unknown log level '{level}'")))
+}
+
+fn validate_subsystem(subsystem: &str) -> Result<()> {
+    let valid = !subsystem.is_empty()
+        && subsystem.split("::").all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(RuntimeError::Config(format!(
+            "invalid logging subsystem '{subsystem}' (expected a module path such as runtime::journal)"
+        )))
+    }
+}
+
+struct Managed {
+    config: LoggingConfig,
+    handle: reload::Handle<EnvFilter, tracing_subscriber::Registry>,
+}
+
+static MANAGED: OnceCell<Mutex<Managed>> = OnceCell::new();
+
+/// Install the runtime's subscriber, writing to stderr.
+///
+/// Fails if this or another subscriber was already installed.
+pub fn init(config: &LoggingConfig) -> Result<()> {
+    let filter = EnvFilter::try_new(config.directives()?)
+        .map_err(|err| RuntimeError::Config(format!("invalid log directives: {err}")))?;
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .try_init()
+        .map_err(|err| RuntimeError::Init(format!("failed to install logging: {err}")))?;
+
+    MANAGED
+        .set(Mutex::new(Managed {
+            config: config.clone(),
+            handle,
+        }))
+        .map_err(|_| RuntimeError::Init("logging already initialized".into()))
+}
+
+/// Change the level of `subsystem` (or the default level when `None`) in the
+/// installed subscriber, returning the configuration now in effect.
+pub fn set_level(subsystem: Option<&str>, level: &str) -> Result<LoggingConfig> {
+    let managed = MANAGED
+        .get()
+        .ok_or_else(|| RuntimeError::Config("logging is not managed by the runtime".into()))?;
+    let mut managed = managed.lock().unwrap();
+
+    let mut config = managed.config.clone();
+    config.set(subsystem, level)?;
+    let filter = EnvFilter::try_new(config.directives()?)
+        .map_err(|err| RuntimeError::Config(format!("invalid log directives: {err}")))?;
+    managed
+        .handle
+        .reload(filter)
+        .map_err(|err| RuntimeError::Config(format!("failed to update log levels: {err}")))?;
+    managed.config = config.clone();
+    Ok(config)
+}
+
+/// Configuration of the installed subscriber, if the runtime manages one.
+pub fn current() -> Option<LoggingConfig> {
+    MANAGED
+        .get()
+        .map(|managed| managed.lock().unwrap().config.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directives_follow_subsystem_levels() {
+        let mut config = LoggingConfig::default().effective(true);
+        config.set(Some("runtime::journal"), "TRACE").unwrap();
+        config.set(Some("service"), "off").unwrap();
+        assert_eq!(
+            config.directives().unwrap(),
+            "duet=debug,duet::runtime::journal=trace,duet::service=off"
+        );
+
+        assert!(config.set(Some("runtime::journal"), "loud").is_err());
+        assert!(config.set(Some("duet=trace,other"), "info").is_err());
+        assert!(config.set(Some("runtime::"), "info").is_err());
+    }
+}
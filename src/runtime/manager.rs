@@ -0,0 +1,317 @@
+//! Hosting several runtimes in one process
+//!
+//! A [`RuntimeManager`] keeps independent runtimes, one per `.duet` root, under
+//! tenant names so a single daemon can serve many projects. Tenants are
+//! registered with their root and opened on first use; every tenant shares
+//! the manager's base configuration apart from its root. One tenant is the
+//! default, used by clients that do not pick one.
+//!
+//! A root is hosted by at most one tenant, compared after canonicalization,
+//! so two runtimes never append to the same journal. Clients may only add
+//! tenants under a directory the host allowed with
+//! [`RuntimeManager::allow_roots_under`].
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::RuntimeConfig;
+use super::control::Control;
+use super::error::{Result, RuntimeError};
+
+/// Hook run on every runtime the manager opens
+type OpenHook = Box<dyn FnMut(&mut Control) + Send>;
+
+/// Tenant as listed by [`RuntimeManager::tenants`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantInfo {
+    /// Tenant name
+    pub name: String,
+    /// Runtime root directory
+    pub root: PathBuf,
+    /// Whether the runtime has been opened
+    pub open: bool,
+    /// Whether this is the default tenant
+    pub default: bool,
+}
+
+/// Named runtimes hosted in one process.
+pub struct RuntimeManager {
+    base: RuntimeConfig,
+    default_tenant: String,
+    roots: BTreeMap<String, PathBuf>,
+    runtimes: BTreeMap<String, Control>,
+    on_open: Option<OpenHook>,
+    client_roots: Option<PathBuf>,
+}
+
+impl RuntimeManager {
+    /// Manager hosting `control` as the default tenant `name`.
+    ///
+    /// Tenants registered later are configured like `control`, with their own root.
+    pub fn new(name: impl Into<String>, control: Control) -> Self {
+        let name = name.into();
+        let base = control.runtime().config().clone();
+        Self {
+            roots: BTreeMap::from([(name.clone(), base.root.clone())]),
+            runtimes: BTreeMap::from([(name.clone(), control)]),
+            default_tenant: name,
+            base,
+            on_open: None,
+            client_roots: None,
+        }
+    }
+
+    /// Run `hook` on every runtime opened from now on (e.g. to apply a template).
+    pub fn on_open(&mut self, hook: impl FnMut(&mut Control) + Send + 'static) {
+        self.on_open = Some(Box::new(hook));
+    }
+
+    /// Let clients add tenants rooted under `parent` (see [`admit`](Self::admit)).
+    pub fn allow_roots_under(&mut self, parent: impl Into<PathBuf>) {
+        self.client_roots = Some(parent.into());
+    }
+
+    /// Register tenant `name` rooted at `root`; it is opened on first use.
+    ///
+    /// Registering a known tenant again with the same root does nothing; a
+    /// root already hosted by another tenant is refused.
+    pub fn register(&mut self, name: impl Into<String>, root: impl AsRef<Path>) -> Result<()> {
+        let name = name.into();
+        let root = root.as_ref();
+        if name.is_empty() {
+            return Err(RuntimeError::Config("tenant name must not be empty".into()));
+        }
+        let canonical = canonical_root(root);
+        if let Some(existing) = self.roots.get(&name) {
+            if canonical_root(existing) == canonical {
+                return Ok(());
+            }
+            return Err(RuntimeError::Config(format!(
+                "tenant '{}' is already rooted at {}",
+                name,
+                existing.display()
+            )));
+        }
+        if let Some(other) = self
+            .roots
+            .iter()
+            .find(|(_, existing)| canonical_root(existing) == canonical)
+            .map(|(other, _)| other)
+        {
+            return Err(RuntimeError::Config(format!(
+                "{} is already hosted by tenant '{}'",
+                root.display(),
+                other
+            )));
+        }
+        self.roots.insert(name, root.to_path_buf());
+        Ok(())
+    }
+
+    /// Register tenant `name` rooted at `root` on a client's behalf.
+    ///
+    /// Clients may name the root a tenant was registered with, or a new one
+    /// under the directory allowed by [`allow_roots_under`](Self::allow_roots_under);
+    /// without one they can only select tenants the host registered.
+    pub fn admit(&mut self, name: impl Into<String>, root: impl AsRef<Path>) -> Result<()> {
+        let name = name.into();
+        let root = root.as_ref();
+        let known = self
+            .roots
+            .get(&name)
+            .is_some_and(|existing| canonical_root(existing) == canonical_root(root));
+        let allowed = self.client_roots.as_ref().is_some_and(|parent| {
+            !root
+                .components()
+                .any(|component| component == Component::ParentDir)
+                && canonical_root(root).starts_with(canonical_root(parent))
+        });
+        if !known && !allowed {
+            return Err(RuntimeError::Config(format!(
+                "clients may not open a runtime at {}",
+                root.display()
+            )));
+        }
+        self.register(name, root)
+    }
+
+    /// Name of the default tenant.
+    pub fn default_tenant(&self) -> &str {
+        &self.default_tenant
+    }
+
+    /// Whether `name` is a registered tenant.
+    pub fn contains(&self, name: &str) -> bool {
+        self.roots.contains_key(name)
+    }
+
+    /// Runtime of tenant `name`, opening (and initializing) it if needed.
+    pub fn control_mut(&mut self, name: &str) -> Result<&mut Control> {
+        if !self.runtimes.contains_key(name) {
+            let root = self
+                .roots
+                .get(name)
+                .ok_or_else(|| RuntimeError::Config(format!("unknown tenant '{}'", name)))?;
+            let config = RuntimeConfig {
+                root: root.clone(),
+                ..self.base.clone()
+            };
+            let mut control = Control::init(config)?;
+            if let Some(hook) = self.on_open.as_mut() {
+                hook(&mut control);
+            }
+            self.runtimes.insert(name.to_string(), control);
+        }
+        Ok(self
+            .runtimes
+            .get_mut(name)
+            .expect("tenant runtime was just opened"))
+    }
+
+    /// Runtime of the default tenant.
+    pub fn default_control_mut(&mut self) -> &mut Control {
+        self.runtimes
+            .get_mut(&self.default_tenant)
+            .expect("default tenant is always open")
+    }
+
+    /// Registered tenants, by name.
+    pub fn tenants(&self) -> Vec<TenantInfo> {
+        self.roots
+            .iter()
+            .map(|(name, root)| TenantInfo {
+                name: name.clone(),
+                root: root.clone(),
+                open: self.runtimes.contains_key(name),
+                default: *name == self.default_tenant,
+            })
+            .collect()
+    }
+}
+
+/// `path` made absolute with symlinks resolved, as far as it exists (roots
+/// are created when their tenant is first opened); `..` is resolved lexically
+fn canonical_root(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut path = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::CurDir => {}
+            component => path.push(component),
+        }
+    }
+    let mut missing = Vec::new();
+    let mut existing = path.as_path();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return missing
+                .iter()
+                .rev()
+                .fold(canonical, |root, part| root.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return path.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tenants_open_lazily_with_their_own_roots() {
+        let temp = TempDir::new().unwrap();
+        let config = RuntimeConfig {
+            root: temp.path().join("default"),
+            ..Default::default()
+        };
+        let mut manager = RuntimeManager::new("default", Control::init(config).unwrap());
+        manager
+            .register("project", temp.path().join("project"))
+            .unwrap();
+        assert!(
+            manager
+                .register("project", temp.path().join("elsewhere"))
+                .is_err()
+        );
+        assert!(!manager.tenants()[1].open);
+
+        let control = manager.control_mut("project").unwrap();
+        assert_eq!(control.runtime().config().root, temp.path().join("project"));
+        assert!(temp.path().join("project").exists());
+        assert!(manager.tenants().iter().all(|tenant| tenant.open));
+        assert!(manager.control_mut("missing").is_err());
+    }
+
+    #[test]
+    fn test_a_root_is_hosted_by_one_tenant() {
+        let temp = TempDir::new().unwrap();
+        let config = RuntimeConfig {
+            root: temp.path().join("default"),
+            ..Default::default()
+        };
+        let mut manager = RuntimeManager::new("default", Control::init(config).unwrap());
+        manager
+            .register("project", temp.path().join("project"))
+            .unwrap();
+
+        // The same directory under another name, spelled differently or not
+        assert!(
+            manager
+                .register("alias", temp.path().join("default"))
+                .is_err()
+        );
+        assert!(
+            manager
+                .register("alias", temp.path().join("project/../default"))
+                .is_err()
+        );
+        assert!(
+            manager
+                .register("alias", temp.path().join("./project"))
+                .is_err()
+        );
+        assert_eq!(manager.tenants().len(), 2);
+    }
+
+    #[test]
+    fn test_clients_only_open_roots_under_the_allowed_directory() {
+        let temp = TempDir::new().unwrap();
+        let config = RuntimeConfig {
+            root: temp.path().join("default"),
+            ..Default::default()
+        };
+        let mut manager = RuntimeManager::new("default", Control::init(config).unwrap());
+        manager
+            .register("configured", temp.path().join("configured"))
+            .unwrap();
+        let projects = temp.path().join("projects");
+
+        // Without an allowed directory, only configured roots can be named
+        assert!(manager.admit("new", projects.join("new")).is_err());
+        manager
+            .admit("configured", temp.path().join("configured"))
+            .unwrap();
+
+        manager.allow_roots_under(&projects);
+        manager.admit("new", projects.join("new")).unwrap();
+        assert!(manager.admit("escape", projects.join("../escape")).is_err());
+        assert!(
+            manager
+                .admit("outside", temp.path().join("outside"))
+                .is_err()
+        );
+        assert!(manager.contains("new") && !manager.contains("escape"));
+    }
+}
@@ -0,0 +1,315 @@
+//! Per-actor memory accounting and leak detection
+//!
+//! A long-running daemon grows without bound when an entity keeps asserting
+//! values it never retracts. After every turn the runtime measures the actor
+//! that ran: its live assertions, retraction tombstones, facets and the
+//! approximate encoded size of its assertion values. A [`MemoryWarning`] is
+//! raised when an actor crosses one of the configured [`MemoryThresholds`],
+//! or when its assertion count has kept growing without a single retraction
+//! for many turns in a row. Warnings are logged, forwarded to notification
+//! sinks and kept for [`MemoryReport`]s.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use tracing::warn;
+
+use super::actor::Actor;
+use super::limits::encoded_size;
+use super::turn::{ActorId, Handle, TurnId};
+
+/// Number of warnings kept for reports.
+const WARNING_HISTORY: usize = 100;
+
+/// Per-actor levels that trigger a warning (`None` = never warn).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryThresholds {
+    /// Live assertions held by one actor
+    pub assertions_per_actor: Option<usize>,
+    /// Approximate encoded bytes of one actor's live assertions
+    pub bytes_per_actor: Option<usize>,
+    /// Consecutive turns an actor's assertion count may grow without any
+    /// retraction
+    pub growth_turns: Option<u64>,
+}
+
+impl Default for MemoryThresholds {
+    fn default() -> Self {
+        Self {
+            assertions_per_actor: Some(50_000),
+            bytes_per_actor: Some(64 * 1024 * 1024),
+            growth_turns: Some(1_000),
+        }
+    }
+}
+
+/// What a memory warning is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryWarningKind {
+    /// Live assertion count crossed its threshold
+    Assertions,
+    /// Approximate assertion bytes crossed their threshold
+    Bytes,
+    /// Assertions kept growing without retractions
+    SustainedGrowth,
+}
+
+/// A threshold crossing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryWarning {
+    /// Actor that crossed the threshold
+    pub actor: ActorId,
+    /// Which threshold
+    pub kind: MemoryWarningKind,
+    /// Measured value
+    pub value: u64,
+    /// Configured threshold
+    pub threshold: u64,
+    /// Turn after which the crossing was observed
+    pub turn: TurnId,
+    /// When it was observed
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Memory held by one actor.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActorMemory {
+    /// Actor measured
+    pub actor: ActorId,
+    /// Live assertions in the actor's assertion set
+    pub assertions: usize,
+    /// Retraction tombstones kept for merging
+    pub tombstones: usize,
+    /// Facets owned by the actor
+    pub facets: usize,
+    /// Approximate encoded size of the live assertion values
+    pub approx_bytes: usize,
+    /// Highest assertion count observed
+    pub peak_assertions: usize,
+    /// Consecutive turns the assertion count grew without retractions
+    pub growth_turns: u64,
+}
+
+/// Snapshot of memory usage across actors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryReport {
+    /// Per-actor usage, largest first
+    pub actors: Vec<ActorMemory>,
+    /// Live assertions across all actors
+    pub total_assertions: usize,
+    /// Approximate encoded bytes across all actors
+    pub total_bytes: usize,
+    /// Thresholds in effect
+    pub thresholds: MemoryThresholds,
+    /// Most recent warnings, oldest first
+    pub warnings: Vec<MemoryWarning>,
+}
+
+#[derive(Default)]
+struct Tracked {
+    usage: ActorMemory,
+    /// Encoded size of each live assertion, so only new values are encoded
+    sizes: HashMap<(ActorId, Handle), usize>,
+    /// Thresholds currently exceeded, so each crossing warns once
+    exceeded: HashSet<MemoryWarningKind>,
+}
+
+/// Memory accounting owned by the runtime.
+#[derive(Default)]
+pub struct MemoryTracker {
+    thresholds: MemoryThresholds,
+    actors: HashMap<ActorId, Tracked>,
+    warnings: VecDeque<MemoryWarning>,
+}
+
+impl MemoryTracker {
+    /// Create a tracker warning at `thresholds`.
+    pub fn new(thresholds: MemoryThresholds) -> Self {
+        Self {
+            thresholds,
+            ..Self::default()
+        }
+    }
+
+    /// Measure `actor` after it executed `turn`, returning new warnings.
+    pub fn observe(&mut self, actor: &Actor, turn: &TurnId) -> Vec<MemoryWarning> {
+        let thresholds = self.thresholds;
+        let tracked = self.actors.entry(actor.id.clone()).or_default();
+        let before = tracked.usage.assertions;
+        let tombstones_before = tracked.usage.tombstones;
+        measure(tracked, actor);
+
+        let usage = &mut tracked.usage;
+        let retracted = usage.tombstones > tombstones_before || usage.assertions < before;
+        usage.growth_turns = if usage.assertions > before && !retracted {
+            usage.growth_turns + 1
+        } else if retracted {
+            0
+        } else {
+            usage.growth_turns
+        };
+
+        let levels = [
+            (
+                MemoryWarningKind::Assertions,
+                usage.assertions as u64,
+                thresholds.assertions_per_actor.map(|max| max as u64),
+            ),
+            (
+                MemoryWarningKind::Bytes,
+                usage.approx_bytes as u64,
+                thresholds.bytes_per_actor.map(|max| max as u64),
+            ),
+            (
+                MemoryWarningKind::SustainedGrowth,
+                usage.growth_turns,
+                thresholds.growth_turns,
+            ),
+        ];
+
+        let mut raised = Vec::new();
+        for (kind, value, threshold) in levels {
+            let Some(threshold) = threshold else {
+                continue;
+            };
+            if value < threshold {
+                tracked.exceeded.remove(&kind);
+                continue;
+            }
+            if !tracked.exceeded.insert(kind) {
+                continue;
+            }
+            warn!(
+                actor = %actor.id,
+                ?kind,
+                value,
+                threshold,
+                "actor memory threshold exceeded"
+            );
+            raised.push(MemoryWarning {
+                actor: actor.id.clone(),
+                kind,
+                value,
+                threshold,
+                turn: turn.clone(),
+                timestamp: Utc::now(),
+            });
+        }
+
+        for warning in &raised {
+            if self.warnings.len() == WARNING_HISTORY {
+                self.warnings.pop_front();
+            }
+            self.warnings.push_back(warning.clone());
+        }
+        raised
+    }
+
+    /// Re-measure `actors` and summarise their usage.
+    pub fn report<'a>(&mut self, actors: impl IntoIterator<Item = &'a Actor>) -> MemoryReport {
+        let mut usage: Vec<ActorMemory> = actors
+            .into_iter()
+            .map(|actor| {
+                let tracked = self.actors.entry(actor.id.clone()).or_default();
+                measure(tracked, actor);
+                tracked.usage.clone()
+            })
+            .collect();
+        usage.sort_by(|a, b| {
+            b.approx_bytes
+                .cmp(&a.approx_bytes)
+                .then_with(|| b.assertions.cmp(&a.assertions))
+                .then_with(|| a.actor.cmp(&b.actor))
+        });
+
+        MemoryReport {
+            total_assertions: usage.iter().map(|actor| actor.assertions).sum(),
+            total_bytes: usage.iter().map(|actor| actor.approx_bytes).sum(),
+            actors: usage,
+            thresholds: self.thresholds,
+            warnings: self.warnings.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Refresh `tracked` from the actor's current state.
+fn measure(tracked: &mut Tracked, actor: &Actor) {
+    let assertions = actor.assertions.read();
+    tracked
+        .sizes
+        .retain(|key, _| assertions.active.contains_key(key));
+    for (key, (value, _)) in &assertions.active {
+        tracked
+            .sizes
+            .entry(key.clone())
+            .or_insert_with(|| encoded_size(value));
+    }
+
+    let usage = &mut tracked.usage;
+    usage.actor = actor.id.clone();
+    usage.assertions = assertions.active.len();
+    usage.tombstones = assertions.tombstones.len();
+    usage.facets = actor.facets.read().facets.len();
+    usage.approx_bytes = tracked.sizes.values().sum();
+    usage.peak_assertions = usage.peak_assertions.max(usage.assertions);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_values(actor: &Actor, count: usize) -> Vec<Handle> {
+        let mut assertions = actor.assertions.write();
+        (0..count)
+            .map(|i| {
+                let handle = Handle::new();
+                assertions.active.insert(
+                    (actor.id.clone(), handle.clone()),
+                    (preserves::IOValue::new(i as i64), uuid::Uuid::new_v4()),
+                );
+                handle
+            })
+            .collect()
+    }
+
+    #[test]
+    fn warns_once_per_crossing_and_on_sustained_growth() {
+        let mut tracker = MemoryTracker::new(MemoryThresholds {
+            assertions_per_actor: Some(5),
+            bytes_per_actor: None,
+            growth_turns: Some(3),
+        });
+        let actor = Actor::new(ActorId::new());
+        let turn = TurnId::genesis();
+
+        let mut kinds = Vec::new();
+        for _ in 0..4 {
+            assert_values(&actor, 2);
+            kinds.extend(tracker.observe(&actor, &turn).into_iter().map(|w| w.kind));
+        }
+        assert_eq!(
+            kinds,
+            vec![
+                MemoryWarningKind::Assertions,
+                MemoryWarningKind::SustainedGrowth
+            ]
+        );
+
+        let report = tracker.report([&actor]);
+        assert_eq!(report.total_assertions, 8);
+        assert!(report.total_bytes > 0);
+        assert_eq!(report.actors[0].growth_turns, 4);
+        assert_eq!(report.warnings.len(), 2);
+
+        // A retraction resets the growth streak and re-arms the warning
+        {
+            let mut assertions = actor.assertions.write();
+            let key = assertions.active.keys().next().unwrap().clone();
+            assertions.active.remove(&key);
+        }
+        assert!(tracker.observe(&actor, &turn).is_empty());
+        assert_eq!(tracker.report([&actor]).actors[0].growth_turns, 0);
+    }
+}
@@ -3,29 +3,70 @@
 //! This module provides the main `Runtime` struct that coordinates all subsystems
 //! and exposes the public interface for embedding or controlling the runtime.
 
-use crate::runtime::pattern::Pattern;
+use crate::runtime::pattern::{Pattern, PatternScope};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::any::Any;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{Receiver, Sender, channel};
-use std::sync::{Arc, Condvar, Mutex, RwLock};
-use std::time::{Duration, Instant};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use uuid::Uuid;
 // Submodules
 pub mod actor;
+pub mod approval;
+pub mod artifact;
+pub mod backup;
 pub mod branch;
+pub mod broadcast;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod compression;
+pub mod config_history;
 pub mod control;
+pub mod cursor;
+pub mod dedup;
+pub mod doctor;
+pub mod effects;
 pub mod error;
+pub mod events;
+pub mod experiment;
+pub mod explain;
+pub mod fixture;
+pub mod flags;
+pub mod history;
+pub mod identity;
+pub mod invocation;
 pub mod journal;
+pub mod limits;
+pub mod logging;
+pub mod manager;
+pub mod memory;
+pub mod notify;
+pub mod ordering;
+pub mod outbox;
 pub mod pattern;
+pub mod ratelimit;
 pub mod reaction;
+pub mod redaction;
+pub mod reflog;
 pub mod registry;
+pub mod rng;
+pub mod schedule;
 pub mod scheduler;
 pub mod schema;
+pub mod secrets;
 pub mod service_client;
+pub mod sink;
 pub mod snapshot;
 pub mod state;
 pub mod storage;
+pub mod stub;
+pub mod sturdy;
+pub mod task;
+pub mod timer;
 pub mod turn;
+pub mod version;
+pub mod wait;
 
 // Future module (phase 8)
 // pub mod link;
@@ -44,8 +85,77 @@ pub struct RuntimeConfig {
     /// Maximum credit limit for flow-control accounts
     pub flow_control_limit: u64,
 
-    /// Enable debug tracing
+    /// Raise the default log level to `debug`
     pub debug: bool,
+
+    /// Capability kinds whose entity-initiated invocations wait for human approval
+    #[serde(default)]
+    pub approval_kinds: Vec<String>,
+
+    /// Webhook sinks notified about significant runtime events
+    #[serde(default)]
+    pub notifiers: Vec<notify::NotifierConfig>,
+
+    /// Per-turn emission limits for entities, with per-type overrides
+    #[serde(default)]
+    pub limits: limits::LimitsConfig,
+
+    /// Secrets resolved at startup and offered to entities
+    #[serde(default)]
+    pub secrets: secrets::SecretsConfig,
+
+    /// How to treat replay of journals or snapshots written by another version
+    #[serde(default)]
+    pub version_policy: version::VersionPolicy,
+
+    /// Patterns redacted from journaled payloads and snapshots
+    #[serde(default)]
+    pub redaction: redaction::RedactionConfig,
+
+    /// Naming templates and policy for new branches
+    #[serde(default)]
+    pub branch_naming: branch::BranchNamingConfig,
+
+    /// Per-actor memory levels that trigger warnings
+    #[serde(default)]
+    pub memory: memory::MemoryThresholds,
+
+    /// Log levels per subsystem
+    #[serde(default)]
+    pub logging: logging::LoggingConfig,
+
+    /// Entity types hydrated as recorder stubs, for replaying in
+    /// environments that lack or should not run them
+    #[serde(default)]
+    pub stubs: stub::StubConfig,
+
+    /// Order in which turn outputs are committed
+    #[serde(default)]
+    pub output_order: ordering::OutputOrder,
+
+    /// History kept when branch journals are compacted
+    #[serde(default)]
+    pub retention: journal::compactor::RetentionPolicy,
+
+    /// Deliverers for outbox channels
+    #[serde(default)]
+    pub outbox: outbox::OutboxConfig,
+
+    /// Identities control-plane clients may act as
+    #[serde(default)]
+    pub identity: identity::IdentityConfig,
+
+    /// Snapshots kept when snapshots are pruned, automatically after each new one
+    #[serde(default)]
+    pub snapshot_retention: snapshot::SnapshotRetention,
+
+    /// Size above which journaled payloads move to the blob store
+    #[serde(default)]
+    pub blobs: storage::blobs::BlobConfig,
+
+    /// When journal appends are fsynced to disk
+    #[serde(default)]
+    pub durability: journal::DurabilityMode,
 }
 
 #[cfg(test)]
@@ -78,6 +188,23 @@ mod tests {
             snapshot_interval: 5,
             flow_control_limit: 1000,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         let mut runtime = Runtime::new(config).expect("runtime init");
@@ -133,6 +260,296 @@ mod tests {
         assert!(runtime.entity_manager().get(&entity_id).is_some());
         assert!(runtime.actors.get(&child_actor).is_some());
     }
+
+    #[test]
+    fn legacy_turn_ids_are_migrated_on_startup() {
+        let temp = tempdir().unwrap();
+        let config = RuntimeConfig {
+            root: temp.path().to_path_buf(),
+            ..Default::default()
+        };
+        drop(Runtime::new(config.clone()).expect("runtime init"));
+
+        // Journal and branch head as recorded before structured turn ids
+        let storage = Storage::new(config.root.clone());
+        let branch = BranchId::main();
+        let mut writer = JournalWriter::new(storage.clone(), branch.clone()).unwrap();
+        let actor = ActorId::new();
+        let mut parent: Option<TurnId> = None;
+        for i in 0..3 {
+            let legacy = TurnId::new(format!("turn_{:08}", i));
+            let record = TurnRecord {
+                turn_id: legacy.clone(),
+                actor: actor.clone(),
+                branch: branch.clone(),
+                clock: turn::LogicalClock(i),
+                parent: parent.clone(),
+                inputs: vec![],
+                outputs: vec![],
+                delta: state::StateDelta::empty(),
+                timestamp: chrono::Utc::now(),
+                vector_clock: Default::default(),
+                initiator: None,
+            };
+            writer.append(&record).unwrap();
+            parent = Some(legacy);
+        }
+        writer.flush().unwrap();
+        drop(writer);
+        let mut branches =
+            BranchManager::from_state(storage::load_branch_state(&storage).unwrap().unwrap());
+        branches.update_head(&branch, parent.unwrap()).unwrap();
+        storage::save_branch_state(&storage, &branches.state()).unwrap();
+
+        let runtime = Runtime::new(config).expect("runtime restart");
+        let ids: Vec<TurnId> = runtime
+            .journal_reader(&branch)
+            .unwrap()
+            .iter_headers()
+            .unwrap()
+            .map(|header| header.turn_id)
+            .collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids.iter().all(|id| !id.is_legacy()));
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(runtime.branch_manager.head(&branch), ids.last());
+        assert!(!Runtime::has_legacy_turn_ids(
+            &runtime.storage,
+            &runtime.branch_manager
+        ));
+    }
+
+    #[test]
+    fn messages_propagate_vector_clocks() {
+        let temp = tempdir().unwrap();
+        let config = RuntimeConfig {
+            root: temp.path().to_path_buf(),
+            snapshot_interval: 5,
+            flow_control_limit: 1000,
+            debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
+        };
+        let mut runtime = Runtime::new(config).expect("runtime init");
+
+        let sender = ActorId::new();
+        let receiver = ActorId::new();
+        runtime.send_message(sender.clone(), FacetId::new(), IOValue::symbol("ping"));
+        let first = runtime.execute_turn().unwrap().unwrap();
+
+        runtime.dispatch_turn_outputs(
+            &sender,
+            &[TurnOutput::Message {
+                target_actor: receiver.clone(),
+                target_facet: FacetId::new(),
+                payload: IOValue::symbol("pong"),
+            }],
+        );
+        let second = runtime.execute_turn().unwrap().unwrap();
+        assert_eq!(second.actor, receiver);
+        assert!(first.vector_clock.happened_before(&second.vector_clock));
+        assert!(second.turn_id > first.turn_id);
+
+        runtime.send_message(ActorId::new(), FacetId::new(), IOValue::symbol("solo"));
+        let third = runtime.execute_turn().unwrap().unwrap();
+        assert!(third.is_concurrent_with(&first));
+        assert!(third.is_concurrent_with(&second));
+    }
+
+    #[test]
+    fn namespaced_assertions_are_scoped() {
+        let temp = tempdir().unwrap();
+        let config = RuntimeConfig {
+            root: temp.path().to_path_buf(),
+            snapshot_interval: 5,
+            flow_control_limit: 1000,
+            debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
+        };
+        let mut control = control::Control::init(config).expect("control init");
+
+        let actor_id = ActorId::new();
+        let runtime = control.runtime_mut();
+        runtime.assert_value_in(actor_id.clone(), "agents", IOValue::symbol("scoped"));
+        runtime.assert_value(actor_id.clone(), IOValue::symbol("plain"));
+        let scoped = runtime.execute_turn().unwrap().unwrap();
+        runtime.execute_turn().unwrap().unwrap();
+
+        assert!(matches!(
+            scoped.outputs.as_slice(),
+            [TurnOutput::Assert { namespace: Some(ns), .. }] if ns == "agents"
+        ));
+
+        let agents = control.list_assertions_in("agents", None);
+        assert_eq!(agents.len(), 1);
+        assert_eq!(agents[0].value, IOValue::symbol("scoped"));
+
+        let defaults = control.list_assertions_in(state::DEFAULT_NAMESPACE, Some(&actor_id));
+        assert_eq!(defaults.len(), 1);
+        assert_eq!(defaults[0].value, IOValue::symbol("plain"));
+        assert_eq!(control.list_assertions(None).len(), 2);
+
+        let mut filter = control::AssertionEventFilter::inclusive();
+        filter.namespace = Some("agents".to_string());
+        let chunk = control
+            .assertion_events_since(&BranchId::main(), None, 10, filter, None)
+            .unwrap();
+        assert_eq!(chunk.events.len(), 1);
+        assert_eq!(
+            chunk.events[0].events[0].namespace.as_deref(),
+            Some("agents")
+        );
+    }
+
+    #[test]
+    fn dataspace_patterns_observe_other_actors() {
+        let catalog = registry::EntityCatalog::global();
+        catalog.register("test/entity".into(), |_config| Ok(Box::new(TestEntity)));
+
+        let temp = tempdir().unwrap();
+        let config = RuntimeConfig {
+            root: temp.path().to_path_buf(),
+            snapshot_interval: 5,
+            flow_control_limit: 1000,
+            debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
+        };
+        let mut control = control::Control::init(config).expect("control init");
+
+        let source = ActorId::new();
+        let observer = ActorId::new();
+        control
+            .runtime_mut()
+            .assert_value(source.clone(), IOValue::symbol("early"));
+        control.runtime_mut().execute_turn().unwrap().unwrap();
+
+        let entity_id = control
+            .register_entity(
+                observer.clone(),
+                FacetId::new(),
+                "test/entity".to_string(),
+                IOValue::symbol("config"),
+            )
+            .unwrap();
+        let facet = control
+            .runtime()
+            .entity_manager()
+            .get(&entity_id)
+            .unwrap()
+            .facet
+            .clone();
+        let pattern = Pattern {
+            id: Uuid::new_v4(),
+            pattern: IOValue::symbol("<_>"),
+            facet,
+            namespace: None,
+            scope: PatternScope::Actor,
+        }
+        .dataspace_wide();
+        let pattern_id = pattern.id;
+        control
+            .register_pattern_for_entity(entity_id, pattern)
+            .unwrap();
+
+        // Existing assertions from other actors are delivered on registration
+        let runtime = control.runtime_mut();
+        let seeded = runtime.execute_turn().unwrap().unwrap();
+        assert_eq!(seeded.actor, observer);
+        assert!(matches!(
+            seeded.inputs.as_slice(),
+            [TurnInput::ObservedAssert { source: s, .. }] if *s == source
+        ));
+
+        runtime.assert_value(source.clone(), IOValue::symbol("late"));
+        let asserted = runtime.execute_turn().unwrap().unwrap();
+        let handle = match asserted.outputs.as_slice() {
+            [TurnOutput::Assert { handle, .. }] => handle.clone(),
+            other => panic!("unexpected outputs: {:?}", other),
+        };
+        let observed = runtime.execute_turn().unwrap().unwrap();
+        assert_eq!(observed.actor, observer);
+        assert!(
+            asserted
+                .vector_clock
+                .happened_before(&observed.vector_clock)
+        );
+        assert_eq!(
+            runtime
+                .pattern_matches(&observer, &pattern_id)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        runtime.scheduler.enqueue(
+            source.clone(),
+            TurnInput::Retract {
+                actor: source.clone(),
+                handle,
+            },
+            ScheduleCause::External,
+        );
+        runtime.execute_turn().unwrap().unwrap();
+        let retracted = runtime.execute_turn().unwrap().unwrap();
+        assert!(matches!(
+            retracted.inputs.as_slice(),
+            [TurnInput::ObservedRetract { .. }]
+        ));
+        assert_eq!(
+            runtime
+                .pattern_matches(&observer, &pattern_id)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
 }
 
 impl Default for RuntimeConfig {
@@ -142,6 +559,23 @@ impl Default for RuntimeConfig {
             snapshot_interval: 50,
             flow_control_limit: 1000,
             debug: false,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         }
     }
 }
@@ -156,12 +590,13 @@ use tracing::warn;
 use turn::{BranchId, CapabilityCompletion, Handle, TurnInput, TurnOutput};
 
 use crate::runtime::turn::{ActorId, FacetId};
+use crate::util::io_value::canonical_encoding;
 use actor::Actor;
 use error::{ActorError, StorageError};
 use reaction::{ReactionDefinition, ReactionId, ReactionInfo, ReactionStore, StoredReaction};
 use registry::EntityManager;
 use state::{CapId, CapabilityMetadata, CapabilityStatus, FacetMetadata, FacetStatus};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 const TOOL_RESULT_RECORD_LABEL: &str = "tool-result";
 
@@ -174,6 +609,9 @@ pub struct AsyncMessage {
     pub facet: turn::FacetId,
     /// Payload delivered to the actor.
     pub payload: preserves::IOValue,
+    /// Token of the managed task that sent the message; the message is
+    /// dropped if the task has been cancelled in the meantime.
+    pub cancellation: Option<task::CancellationToken>,
 }
 
 /// The main runtime orchestrator
@@ -196,6 +634,11 @@ pub struct Runtime {
 
     /// Entity registry snapshot for this runtime instance
     entity_registry: registry::EntityRegistry,
+
+    /// Runtime version and registry fingerprint stamped on new data
+    version: version::VersionStamp,
+    /// Redaction applied to everything persisted
+    redactor: Arc<redaction::Redactor>,
     /// Persisted reaction definitions for this runtime
     reaction_store: Arc<RwLock<ReactionStore>>,
     /// Filesystem path where reactions are stored
@@ -207,8 +650,68 @@ pub struct Runtime {
     /// Last turn ID for each actor (for causality tracking)
     last_turn_per_actor: HashMap<turn::ActorId, turn::TurnId>,
 
-    /// Turn notifications for long-polling listeners
-    turn_wait: Arc<(Mutex<HashMap<BranchId, TurnId>>, Condvar)>,
+    /// Vector clock of each actor's latest turn
+    vector_clocks: HashMap<turn::ActorId, turn::VectorClock>,
+
+    /// Causal history delivered to an actor by messages it has not yet processed
+    pending_causality: HashMap<turn::ActorId, turn::VectorClock>,
+
+    /// Observers that were routed each assertion via a dataspace-wide pattern
+    observed_handles: HashMap<Handle, BTreeSet<turn::ActorId>>,
+
+    /// Secret used to sign and verify exported sturdy refs
+    sturdy_key: sturdy::SturdyKey,
+
+    /// Capability invocations parked until a human approves or denies them
+    approvals: approval::ApprovalStore,
+    /// Filesystem path where parked invocations are stored
+    approvals_path: PathBuf,
+
+    /// Sinks notified about significant runtime events
+    notifications: notify::Notifications,
+
+    /// Sinks mirroring committed turns into external systems
+    turn_sinks: sink::TurnSinks,
+
+    /// Deliveries to external systems staged by committed turns
+    outbox: outbox::Outbox,
+
+    /// Background tasks owned by entity facets
+    tasks: task::TaskManager,
+
+    /// Per-actor memory accounting
+    memory: memory::MemoryTracker,
+
+    /// Idempotency keys of external messages seen on the current branch
+    idempotency: dedup::IdempotencyIndex,
+
+    /// Timers set on the current branch that have not fired yet
+    timers: timer::TimerTable,
+
+    /// Asynchronous capability invocations awaiting or holding results
+    invocations: invocation::InvocationTable,
+    /// Entities serving synchronous capability invocations, outermost first
+    serving: Vec<uuid::Uuid>,
+
+    /// Secret values entities may look up
+    secrets: Arc<secrets::SecretsProvider>,
+
+    /// Hooks that try to undo side effects left behind by a rewind
+    compensations: effects::CompensationRegistry,
+    /// Side effects left in place by the most recent rewind
+    rewind_warning: Option<effects::RewindWarning>,
+
+    /// Fault injector, when chaos testing is enabled
+    #[cfg(feature = "chaos")]
+    chaos: Option<chaos::ChaosMonkey>,
+
+    /// Recurring inputs driven by the branch turn sequence
+    schedules: schedule::ScheduleStore,
+    /// Filesystem path where recurring schedules are stored
+    schedules_path: PathBuf,
+
+    /// Branch heads and long-poll registrations waiting on them
+    turn_waiters: wait::TurnWaiters,
 
     /// Inbound async message queue
     async_inbox: Receiver<AsyncMessage>,
@@ -254,7 +757,47 @@ impl Runtime {
             }
         };
 
-        let branch_manager = BranchManager::from_state(branch_state.clone());
+        let mut branch_manager = BranchManager::from_state(branch_state.clone());
+
+        // Finish or discard journal compactions a crash interrupted
+        for branch in branch_manager.list_branches() {
+            journal::compactor::recover(&storage, &branch.id).map_err(|e| {
+                error::RuntimeError::Init(format!("Compaction recovery failed: {}", e))
+            })?;
+        }
+
+        let entity_registry = registry::EntityCatalog::global().snapshot();
+        let version = version::VersionStamp::current(&entity_registry);
+
+        // Segments written before headers carry no version to check, but one
+        // whose records decode in no known layout came from an incompatible
+        // release; refuse it before repair or migration touches it
+        if config.version_policy == version::VersionPolicy::Refuse {
+            for metadata in branch_manager.list_branches() {
+                let reader = JournalReader::new_empty(storage.clone(), metadata.id.clone());
+                if let Some((segment, offset)) = reader
+                    .undecodable_unversioned_record()
+                    .map_err(error::RuntimeError::Journal)?
+                {
+                    return Err(error::RuntimeError::VersionMismatch {
+                        origin: format!(
+                            "journal segment {} of branch {} (record at offset {})",
+                            segment, metadata.id, offset
+                        ),
+                        recorded: "unversioned".to_string(),
+                        current: version.to_string(),
+                    });
+                }
+            }
+        }
+
+        // Journals recorded before structured turn ids are migrated before
+        // repair scans them, and before anything orders turns by sequence
+        if Self::has_legacy_turn_ids(&storage, &branch_manager) {
+            let mapping =
+                Self::migrate_journal_turn_ids(&storage, &mut branch_manager, &snapshot_manager)?;
+            tracing::info!(migrated = mapping.len(), "migrated legacy turn ids");
+        }
 
         // Use active branch from state
         let current_branch = branch_state.active.clone();
@@ -288,7 +831,7 @@ impl Runtime {
             .map_err(|e| error::RuntimeError::Init(format!("Failed to save index: {}", e)))?;
 
         // Now create journal writer with the clean index
-        let journal_writer =
+        let mut journal_writer =
             JournalWriter::new_with_index(storage.clone(), current_branch.clone(), clean_index)
                 .map_err(|e| {
                     error::RuntimeError::Init(format!("Failed to create journal writer: {}", e))
@@ -296,18 +839,51 @@ impl Runtime {
 
         // Load entity metadata
         let entity_meta_path = storage.meta_dir().join("entities.json");
-        let entity_manager =
+        let mut entity_manager =
             EntityManager::load(&entity_meta_path).unwrap_or_else(|_| EntityManager::new());
+        entity_manager.load_groups(&storage.meta_dir().join("groups.json"))?;
 
         let (async_sender, async_receiver) = channel();
 
-        let entity_registry = registry::EntityCatalog::global().snapshot();
+        journal_writer
+            .set_version(version.clone())
+            .map_err(|e| error::RuntimeError::Init(format!("Failed to stamp journal: {}", e)))?;
+        let redactor = Arc::new(
+            redaction::Redactor::from_config(&config.redaction)
+                .map_err(error::RuntimeError::Init)?,
+        );
+        journal_writer.set_redactor(redactor.clone());
+        journal_writer.set_spill_threshold(config.blobs.spill_threshold);
+        journal_writer.set_durability(config.durability);
 
         let reaction_store_path = storage.meta_dir().join("reactions.json");
         let reaction_store = ReactionStore::load(&reaction_store_path).map_err(|e| {
             error::RuntimeError::Init(format!("Failed to load reaction definitions: {}", e))
         })?;
 
+        let sturdy_key = sturdy::SturdyKey::load_or_create(&storage)?;
+
+        let notifications = notify::Notifications::from_config(&config.notifiers);
+
+        let mut outbox = outbox::Outbox::load(&storage.meta_dir().join("outbox.bin"))
+            .map_err(|e| error::RuntimeError::Init(format!("Failed to load outbox: {}", e)))?;
+        for (channel, deliverer) in &config.outbox.channels {
+            outbox.register(channel.clone(), deliverer.build());
+        }
+
+        let approvals_path = storage.meta_dir().join("approvals.bin");
+        let approvals = approval::ApprovalStore::load(&approvals_path).map_err(|e| {
+            error::RuntimeError::Init(format!("Failed to load pending approvals: {}", e))
+        })?;
+
+        let schedules_path = storage.meta_dir().join("schedules.bin");
+        let schedules = schedule::ScheduleStore::load(&schedules_path).map_err(|e| {
+            error::RuntimeError::Init(format!("Failed to load recurring schedules: {}", e))
+        })?;
+
+        let secrets = Arc::new(secrets::SecretsProvider::from_config(&config.secrets));
+        let memory = memory::MemoryTracker::new(config.memory);
+
         let mut runtime = Self {
             config,
             storage,
@@ -319,18 +895,48 @@ impl Runtime {
             actors: HashMap::new(),
             entity_manager,
             entity_registry,
+            version,
+            redactor,
             reaction_store: Arc::new(RwLock::new(reaction_store)),
             reaction_store_path,
             turn_count: 0,
             last_turn_per_actor: HashMap::new(),
-            turn_wait: Arc::new((Mutex::new(HashMap::new()), Condvar::new())),
+            vector_clocks: HashMap::new(),
+            pending_causality: HashMap::new(),
+            observed_handles: HashMap::new(),
+            sturdy_key,
+            approvals,
+            approvals_path,
+            notifications,
+            turn_sinks: sink::TurnSinks::default(),
+            outbox,
+            tasks: task::TaskManager::new(),
+            memory,
+            idempotency: dedup::IdempotencyIndex::new(),
+            timers: timer::TimerTable::new(),
+            invocations: invocation::InvocationTable::new(),
+            serving: Vec::new(),
+            secrets,
+            compensations: effects::CompensationRegistry::default(),
+            rewind_warning: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
+            schedules,
+            schedules_path,
+            turn_waiters: wait::TurnWaiters::default(),
             async_inbox: async_receiver,
             async_sender,
         };
 
+        // The side files cache the config journal; rebuild them from it
+        let head = runtime.current_head();
+        runtime.restore_config_at(&head)?;
+
         // Hydrate entities: recreate and attach them from metadata
         runtime.hydrate_entities(None)?;
         runtime.hydrate_reactions()?;
+        runtime.rebuild_branch_indexes()?;
+        runtime.recover_outbox()?;
 
         if let Some(head) = runtime
             .branch_manager
@@ -351,65 +957,159 @@ impl Runtime {
         &mut self,
         entity_states: Option<&HashMap<uuid::Uuid, snapshot::EntityStateSnapshot>>,
     ) -> Result<()> {
-        let registry = &self.entity_registry;
-
         // Clone metadata to avoid borrow conflicts
-        let entities: Vec<_> = self.entity_manager.list().into_iter().cloned().collect();
+        let entities: Vec<_> = self
+            .entity_manager
+            .list_on(&self.current_branch)
+            .into_iter()
+            .cloned()
+            .collect();
+
+        let actor_roots = Self::actor_roots(&entities);
+        for metadata in entities {
+            let state = entity_states.and_then(|states| states.get(&metadata.id));
+            self.instantiate_entity(&metadata, state, &actor_roots)?;
+        }
+        self.seed_remote_pattern_matches();
+
+        Ok(())
+    }
+
+    /// Re-establish dataspace-wide matches on other actors' assertions so
+    /// their retractions still reach observers hydrated after a restart.
+    fn seed_remote_pattern_matches(&self) {
+        for (id, actor) in &self.actors {
+            let mut engine = actor.pattern_engine.write();
+            for (source, other) in &self.actors {
+                if source != id {
+                    engine.seed_remote_matches(id, &other.assertions.read());
+                }
+            }
+        }
+    }
 
+    /// Root facet of each actor, as recorded by its root-facet entities.
+    fn actor_roots(entities: &[EntityMetadata]) -> HashMap<ActorId, FacetId> {
         let mut actor_roots: HashMap<ActorId, FacetId> = HashMap::new();
-        for metadata in &entities {
+        for metadata in entities {
             if metadata.is_root_facet {
                 actor_roots
                     .entry(metadata.actor.clone())
                     .or_insert(metadata.facet.clone());
             }
         }
+        actor_roots
+    }
 
-        for metadata in entities {
+    /// Create an entity instance from its metadata, attach it, and re-register its patterns.
+    fn instantiate_entity(
+        &mut self,
+        metadata: &EntityMetadata,
+        state: Option<&snapshot::EntityStateSnapshot>,
+        actor_roots: &HashMap<ActorId, FacetId>,
+    ) -> Result<()> {
+        let registry = &self.entity_registry;
+
+        let entity = if self.config.stubs.should_stub(
+            &metadata.entity_type,
+            registry.has_type(&metadata.entity_type),
+        ) {
+            tracing::debug!(
+                entity = %metadata.id,
+                entity_type = %metadata.entity_type,
+                "hydrating entity as a recorder stub"
+            );
+            Box::new(stub::RecorderStub::new(
+                metadata.entity_type.clone(),
+                state.map(|state| state.state.clone()),
+            )) as Box<dyn actor::Entity>
+        } else {
             // Create entity instance using registry
             let mut entity = registry
                 .create(&metadata.entity_type, &metadata.config)
                 .map_err(|e| error::RuntimeError::Actor(e))?;
 
             // Restore private state if available
-            if let Some(state_map) = entity_states {
-                if let Some(state) = state_map.get(&metadata.id) {
-                    let _ = registry.restore_entity(
-                        &metadata.entity_type,
-                        entity.as_mut(),
-                        &state.state,
-                    )?;
-                }
+            if let Some(state) = state {
+                let _ = registry.restore_entity(
+                    &metadata.entity_type,
+                    entity.as_mut(),
+                    &state.state,
+                )?;
+            }
+            entity
+        };
+
+        // Get or create actor
+        let actor_id = metadata.actor.clone();
+        let root_choice = actor_roots.get(&actor_id).cloned();
+        let actor = self.actors.entry(actor_id.clone()).or_insert_with(move || {
+            if let Some(root) = root_choice.clone() {
+                Actor::with_root(actor_id.clone(), root)
+            } else {
+                Actor::new(actor_id.clone())
             }
+        });
 
-            // Get or create actor
-            let actor_id = metadata.actor.clone();
-            let root_choice = actor_roots.get(&actor_id).cloned();
-            let actor = self.actors.entry(actor_id.clone()).or_insert_with(move || {
-                if let Some(root) = root_choice.clone() {
-                    Actor::with_root(actor_id.clone(), root)
-                } else {
-                    Actor::new(actor_id.clone())
-                }
-            });
+        // Attach entity to facet
+        actor.attach_entity(
+            metadata.id,
+            metadata.entity_type.clone(),
+            metadata.facet.clone(),
+            entity,
+        );
 
-            // Attach entity to facet
-            actor.attach_entity(
-                metadata.id,
-                metadata.entity_type.clone(),
-                metadata.facet.clone(),
-                entity,
-            );
+        // Re-register patterns
+        for pattern in &metadata.patterns {
+            actor.register_pattern(pattern.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Attach and detach live instances so exactly the entities scoped to the
+    /// current branch are running.
+    fn sync_branch_entities(&mut self) -> Result<()> {
+        let entities: Vec<_> = self.entity_manager.list().into_iter().cloned().collect();
+        let actor_roots = Self::actor_roots(&entities);
 
-            // Re-register patterns
-            for pattern in &metadata.patterns {
-                actor.register_pattern(pattern.clone());
+        for metadata in entities {
+            let visible = metadata.visible_on(&self.current_branch);
+            let attached = self
+                .actors
+                .get(&metadata.actor)
+                .is_some_and(|actor| actor.has_entity(metadata.id));
+
+            if attached && !visible {
+                if let Some(actor) = self.actors.get(&metadata.actor) {
+                    actor.detach_entity(metadata.id);
+                    for pattern in &metadata.patterns {
+                        actor.unregister_pattern(pattern.id);
+                    }
+                }
+            } else if visible && !attached {
+                self.instantiate_entity(&metadata, None, &actor_roots)?;
             }
         }
 
         Ok(())
     }
 
+    /// Restrict an entity to `branches` (`None` = every branch) and apply the
+    /// change to the running instances.
+    pub fn set_entity_branches(
+        &mut self,
+        entity_id: uuid::Uuid,
+        branches: Option<BTreeSet<BranchId>>,
+    ) -> Result<()> {
+        let metadata = self.entity_manager.get_mut(&entity_id).ok_or_else(|| {
+            error::RuntimeError::Actor(ActorError::NotFound(entity_id.to_string()))
+        })?;
+        metadata.branches = branches;
+        self.persist_entities()?;
+        self.sync_branch_entities()
+    }
+
     /// Hydrate reaction definitions from the persisted store.
     fn hydrate_reactions(&mut self) -> Result<()> {
         let stored: Vec<StoredReaction> = {
@@ -433,7 +1133,10 @@ impl Runtime {
     /// Takes the next ready turn from the scheduler, executes it,
     /// records it to the journal, and updates state.
     pub fn execute_turn(&mut self) -> Result<Option<TurnRecord>> {
+        // Tasks spawned by a turn that never committed must not start
+        self.tasks.discard_pending();
         self.poll_async_messages();
+        self.fire_due_timers(chrono::Utc::now());
         // Get next ready turn from scheduler
         let scheduled_turn = match self.scheduler.next_turn() {
             Some(turn) => turn,
@@ -443,27 +1146,95 @@ impl Runtime {
         let actor_id = scheduled_turn.actor.clone();
         let clock = scheduled_turn.clock;
         let inputs = scheduled_turn.inputs;
+        // Turns nobody in particular enqueued run on behalf of whoever drives execution
+        let initiator = scheduled_turn
+            .initiator
+            .or_else(|| self.scheduler.initiator().cloned());
+
+        // Error level keeps the span enabled whenever any event inside it is
+        let span = tracing::error_span!(
+            "turn",
+            branch = %self.current_branch,
+            actor = %actor_id,
+            turn = tracing::field::Empty,
+        );
+        let _entered = span.enter();
 
         // Execute the turn and apply its delta to the hosting actor.
-        let (outputs, delta) = {
+        let seq = self.next_turn_sequence(&self.current_branch);
+        let executed = {
             let actor = self
                 .actors
                 .entry(actor_id.clone())
                 .or_insert_with(|| Actor::new(actor_id.clone()));
 
-            let (outputs, delta) = actor
-                .execute_turn(inputs.clone(), Some(&self.async_sender))
-                .map_err(|e| error::RuntimeError::Actor(e))?;
-            actor.apply_delta(&delta);
-            (outputs, delta)
+            actor
+                .execute_turn_with_secrets(
+                    inputs.clone(),
+                    Some(&self.async_sender),
+                    &self.config.limits,
+                    self.secrets.clone(),
+                    Some(&self.tasks),
+                    seq,
+                )
+                .map(|(outputs, delta)| {
+                    actor.apply_delta(&delta);
+                    if !delta.facets.terminated.is_empty() {
+                        let facets = actor.facets.read();
+                        self.tasks.cancel_facets(&actor_id, |facet| {
+                            facets
+                                .facets
+                                .get(facet)
+                                .is_none_or(|meta| meta.status == state::FacetStatus::Terminated)
+                        });
+                    }
+                    (outputs, delta)
+                })
+        };
+        let (mut outputs, mut delta) = match executed {
+            Ok(executed) => executed,
+            Err(err) => {
+                warn!(error = %err, "turn failed");
+                self.notify(
+                    notify::NotificationEvent::TurnFailed,
+                    format!("actor {} failed a turn: {}", actor_id, err),
+                    serde_json::json!({
+                        "actor": actor_id.to_string(),
+                        "error": err.to_string(),
+                    }),
+                );
+                for input in &inputs {
+                    if let TurnInput::CapabilityInvocation {
+                        capability,
+                        invocation: Some(id),
+                        ..
+                    } = input
+                    {
+                        self.complete_invocation(
+                            *id,
+                            *capability,
+                            invocation::InvocationOutcome::Failed(err.to_string()),
+                        );
+                    }
+                }
+                return Err(error::RuntimeError::Actor(err));
+            }
         };
 
+        self.config.output_order.apply(&mut outputs, &mut delta);
+
+        // Stamp the turn's causal history before outputs propagate it
+        let vector_clock = self.advance_vector_clock(&actor_id);
+
         // Update flow control in scheduler (before consuming delta)
         let borrowed = delta.accounts.borrowed;
         let repaid = delta.accounts.repaid;
         self.scheduler.update_account(&actor_id, borrowed, repaid);
 
+        // Inputs the turn enqueues are caused by its initiator as well
+        let acting = self.scheduler.set_initiator(initiator.clone());
         self.dispatch_turn_outputs(&actor_id, &outputs);
+        self.scheduler.set_initiator(acting);
 
         // Build turn record with parent turn tracking
         let parent = self.last_turn_per_actor.get(&actor_id).cloned();
@@ -475,37 +1246,239 @@ impl Runtime {
             inputs,
             outputs,
             delta,
-        );
+        )
+        .with_sequence(seq)
+        .with_vector_clock(vector_clock)
+        .with_initiator(initiator);
         let turn_id = turn_record.turn_id.clone();
+        span.record("turn", tracing::field::display(&turn_id));
+        self.idempotency.record(&turn_record);
+        self.timers.record(&turn_record);
+        if !schedule::is_scheduled_turn(&turn_record.inputs) {
+            self.fire_recurring(seq);
+        }
 
         // Update last turn tracker for this actor
         self.last_turn_per_actor
             .insert(actor_id.clone(), turn_id.clone());
 
         // Append to journal
+        #[cfg(feature = "chaos")]
+        if self.inject_fault(chaos::FaultKind::JournalWrite, || {
+            format!("turn {}", turn_id)
+        }) {
+            let torn = self.journal_writer.append_torn(&turn_record);
+            self.reopen_journal()?;
+            torn.map_err(error::RuntimeError::Journal)?;
+        }
         self.journal_writer
             .append(&turn_record)
             .map_err(|e| error::RuntimeError::Journal(e))?;
+        self.turn_sinks.publish(&turn_record);
+        self.outbox.stage(&turn_record).map_err(outbox_error)?;
+        self.pump_outbox()?;
+        self.tasks.start_pending(&self.current_branch, &turn_id);
+        self.observe_memory(&actor_id, &turn_id);
 
         // Update turn count
         self.turn_count += 1;
 
         // Check if we should create a snapshot
         if self.snapshot_manager.should_snapshot(self.turn_count) {
+            #[cfg(feature = "chaos")]
+            if self.inject_fault(chaos::FaultKind::Snapshot, || {
+                format!("snapshot after turn {}", turn_id)
+            }) {
+                return Err(error::RuntimeError::Snapshot(
+                    error::SnapshotError::ValidationFailed("chaos: snapshot write failed".into()),
+                ));
+            }
             self.create_snapshot()?;
         }
 
+        let previous_head = self.current_head();
         self.branch_manager
             .update_head(&self.current_branch, turn_id.clone())
             .map_err(|e| error::RuntimeError::Branch(e))?;
         self.persist_branch_state()?;
+        self.log_head_move(
+            &self.current_branch,
+            previous_head,
+            &turn_id,
+            reflog::HeadMove::Step,
+            turn_record.initiator.clone(),
+        )?;
 
         self.record_branch_head(self.current_branch.clone(), turn_id.clone());
+        tracing::debug!(
+            sequence = seq,
+            inputs = turn_record.inputs.len(),
+            outputs = turn_record.outputs.len(),
+            "turn committed"
+        );
 
         Ok(Some(turn_record))
     }
 
+    /// Register an additional notification sink for `events` (empty = all events).
+    pub fn add_notifier(
+        &mut self,
+        events: Vec<notify::NotificationEvent>,
+        notifier: Arc<dyn notify::Notifier>,
+    ) {
+        self.notifications.register(events, notifier);
+    }
+
+    /// Deliver every committed turn to `sink` under `name`.
+    ///
+    /// Records are handed over after the journal append, on a worker thread
+    /// governed by `options`.
+    pub fn add_turn_sink(
+        &mut self,
+        name: impl Into<String>,
+        sink: Arc<dyn sink::TurnSink>,
+        options: sink::SinkOptions,
+    ) {
+        self.turn_sinks.register(name, sink, options);
+    }
+
+    /// Carry out outbox deliveries on `channel` with `deliverer`, replacing
+    /// any deliverer configured for it.
+    ///
+    /// Deliveries already pending on the channel are dispatched right away.
+    pub fn register_deliverer(
+        &mut self,
+        channel: impl Into<String>,
+        deliverer: Arc<dyn outbox::Deliverer>,
+    ) -> Result<()> {
+        self.outbox.register(channel, deliverer);
+        self.pump_outbox()
+    }
+
+    /// Dispatch due outbox deliveries and wait up to `timeout` for their
+    /// acknowledgements.
+    pub fn flush_outbox(&mut self, timeout: std::time::Duration) -> Result<outbox::OutboxStatus> {
+        self.pump_outbox()?;
+        self.outbox.wait(timeout).map_err(outbox_error)?;
+        Ok(self.outbox.status())
+    }
+
+    /// Pending outbox deliveries and counters.
+    pub fn outbox_status(&self) -> outbox::OutboxStatus {
+        self.outbox.status()
+    }
+
+    /// Record delivery acknowledgements and dispatch due deliveries.
+    fn pump_outbox(&mut self) -> Result<()> {
+        self.outbox.pump().map_err(outbox_error)
+    }
+
+    /// Stage the deliveries of the current branch's latest turn again.
+    ///
+    /// Deliveries are stored right after their turn is journaled, so only
+    /// that turn can have been cut off by a crash; deliveries already pending
+    /// or acknowledged are skipped.
+    fn recover_outbox(&mut self) -> Result<()> {
+        let reader = self.journal_reader(&self.current_branch)?;
+        let last = reader
+            .iter_headers()
+            .map_err(error::RuntimeError::Journal)?
+            .last();
+        if let Some(header) = last {
+            let record = reader
+                .read(&header.turn_id)
+                .map_err(error::RuntimeError::Journal)?;
+            self.outbox.stage(&record).map_err(outbox_error)?;
+        }
+        self.pump_outbox()
+    }
+
+    /// Background tasks currently owned by entity facets.
+    pub fn tasks(&self) -> Vec<task::TaskInfo> {
+        self.tasks.list()
+    }
+
+    /// Wait until every registered turn sink has handled its queued records.
+    pub fn flush_turn_sinks(&self) {
+        self.turn_sinks.flush();
+    }
+
+    /// Delivery counters for the registered turn sinks.
+    pub fn turn_sink_stats(&self) -> Vec<sink::SinkStats> {
+        self.turn_sinks.stats()
+    }
+
+    /// Measure `actor_id` after `turn_id` and report threshold crossings.
+    fn observe_memory(&mut self, actor_id: &ActorId, turn_id: &TurnId) {
+        let Some(actor) = self.actors.get(actor_id) else {
+            return;
+        };
+        for warning in self.memory.observe(actor, turn_id) {
+            self.notify(
+                notify::NotificationEvent::MemoryThreshold,
+                format!(
+                    "actor {} exceeded its {:?} threshold ({} >= {})",
+                    warning.actor, warning.kind, warning.value, warning.threshold
+                ),
+                serde_json::to_value(&warning).unwrap_or_default(),
+            );
+        }
+    }
+
+    /// Per-actor memory usage with recent threshold warnings.
+    pub fn memory_report(&mut self) -> memory::MemoryReport {
+        self.memory.report(self.actors.values())
+    }
+
+    fn notify(
+        &self,
+        event: notify::NotificationEvent,
+        summary: String,
+        details: serde_json::Value,
+    ) {
+        self.notifications.emit(&notify::Notification {
+            event,
+            branch: self.current_branch.clone(),
+            timestamp: chrono::Utc::now(),
+            summary,
+            details,
+        });
+    }
+
+    fn notify_agent_responses(&self, actor_id: &ActorId, outputs: &[TurnOutput]) {
+        if !self
+            .notifications
+            .wants(notify::NotificationEvent::AgentResponseCompleted)
+        {
+            return;
+        }
+
+        for output in outputs {
+            let TurnOutput::Assert { value, .. } = output else {
+                continue;
+            };
+            let Some((agent_id, request_id, _prompt, response, agent_kind)) =
+                crate::codebase::agent::parse_response_fields(value)
+            else {
+                continue;
+            };
+            self.notify(
+                notify::NotificationEvent::AgentResponseCompleted,
+                format!("{} answered request {}", agent_id, request_id),
+                serde_json::json!({
+                    "actor": actor_id.to_string(),
+                    "agent": agent_id,
+                    "agent_kind": agent_kind,
+                    "request_id": request_id,
+                    "response": response,
+                }),
+            );
+        }
+    }
+
     fn dispatch_turn_outputs(&mut self, actor_id: &ActorId, outputs: &[TurnOutput]) {
+        self.notify_agent_responses(actor_id, outputs);
+
         for output in outputs {
             match output {
                 TurnOutput::Message {
@@ -517,8 +1490,11 @@ impl Runtime {
                         actor: target_actor.clone(),
                         facet: target_facet.clone(),
                         payload: payload.clone(),
+                        idempotency_key: None,
+                        broadcast: None,
                     };
 
+                    self.propagate_causality(actor_id, target_actor);
                     self.scheduler
                         .enqueue(target_actor.clone(), input, ScheduleCause::Message);
                 }
@@ -529,6 +1505,39 @@ impl Runtime {
                 } => {
                     self.handle_capability_invoke(*capability, payload.clone(), completion.clone());
                 }
+                TurnOutput::CapabilityInvokeByKind {
+                    kind,
+                    payload,
+                    completion,
+                } => match self.held_capability_of_kind(&completion.origin_actor, kind) {
+                    Some(capability) => self.handle_capability_invoke(
+                        capability,
+                        payload.clone(),
+                        completion.clone(),
+                    ),
+                    None => self.deliver_capability_result(
+                        CapId::nil(),
+                        preserves::IOValue::record(
+                            preserves::IOValue::symbol("tool-error"),
+                            vec![preserves::IOValue::new(format!(
+                                "no active capability of kind {} held by {}",
+                                kind, completion.origin_actor
+                            ))],
+                        ),
+                        completion.clone(),
+                    ),
+                },
+                TurnOutput::CapabilityResult {
+                    capability,
+                    result,
+                    invocation: Some(id),
+                } => {
+                    self.complete_invocation(
+                        *id,
+                        *capability,
+                        invocation::InvocationOutcome::Completed(result.clone()),
+                    );
+                }
                 TurnOutput::EntitySpawned {
                     parent_actor,
                     parent_facet,
@@ -576,8 +1585,120 @@ impl Runtime {
                 TurnOutput::EntityDetached { entity_id } => {
                     self.handle_entity_detach(actor_id, entity_id);
                 }
-                _ => {}
-            }
+                TurnOutput::Assert {
+                    handle,
+                    value,
+                    namespace,
+                } => {
+                    self.route_observed_assert(actor_id, handle, value, namespace.as_deref());
+                }
+                TurnOutput::Retract { handle, .. } => {
+                    self.route_observed_retract(actor_id, handle);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Route an assertion to every other actor holding a matching dataspace-wide pattern.
+    fn route_observed_assert(
+        &mut self,
+        source: &ActorId,
+        handle: &Handle,
+        value: &preserves::IOValue,
+        namespace: Option<&str>,
+    ) {
+        let mut observers: Vec<ActorId> = self
+            .actors
+            .iter()
+            .filter(|(id, actor)| {
+                *id != source
+                    && actor
+                        .pattern_engine
+                        .read()
+                        .observes_remote(value, namespace)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        // Actor map iteration order is arbitrary; sort to keep scheduling deterministic
+        observers.sort();
+
+        for observer in observers {
+            self.enqueue_observed_assert(source, &observer, handle, value, namespace);
+        }
+    }
+
+    fn enqueue_observed_assert(
+        &mut self,
+        source: &ActorId,
+        observer: &ActorId,
+        handle: &Handle,
+        value: &preserves::IOValue,
+        namespace: Option<&str>,
+    ) {
+        self.observed_handles
+            .entry(handle.clone())
+            .or_default()
+            .insert(observer.clone());
+
+        let input = TurnInput::ObservedAssert {
+            observer: observer.clone(),
+            source: source.clone(),
+            handle: handle.clone(),
+            value: value.clone(),
+            namespace: namespace.map(str::to_string),
+        };
+        self.propagate_causality(source, observer);
+        self.scheduler
+            .enqueue(observer.clone(), input, ScheduleCause::Observation);
+    }
+
+    /// Route a retraction to every observer that was sent the original assertion.
+    fn route_observed_retract(&mut self, source: &ActorId, handle: &Handle) {
+        let mut observers = self.observed_handles.remove(handle).unwrap_or_default();
+        // Matches established before a restart or time travel are only known to the observer
+        for (id, actor) in &self.actors {
+            if id != source && actor.pattern_engine.read().has_match_for(handle) {
+                observers.insert(id.clone());
+            }
+        }
+
+        for observer in observers {
+            let input = TurnInput::ObservedRetract {
+                observer: observer.clone(),
+                source: source.clone(),
+                handle: handle.clone(),
+            };
+            self.propagate_causality(source, &observer);
+            self.scheduler
+                .enqueue(observer, input, ScheduleCause::Observation);
+        }
+    }
+
+    /// Deliver existing assertions from other actors to a newly registered dataspace-wide pattern.
+    fn seed_observed_assertions(&mut self, observer: &ActorId, pattern: &Pattern) {
+        let mut existing = Vec::new();
+        for (source, actor) in &self.actors {
+            if source == observer {
+                continue;
+            }
+            let assertions = actor.assertions.read();
+            for ((owner, handle), (value, _version)) in assertions.active.iter() {
+                let namespace = assertions.namespace_of(owner, handle);
+                if pattern.matches(value, namespace) {
+                    existing.push((
+                        source.clone(),
+                        handle.clone(),
+                        value.clone(),
+                        namespace.map(str::to_string),
+                    ));
+                }
+            }
+        }
+        existing.sort_by(|a, b| (&a.0, a.1.0).cmp(&(&b.0, b.1.0)));
+
+        for (source, handle, value, namespace) in existing {
+            self.enqueue_observed_assert(&source, observer, &handle, &value, namespace.as_deref());
         }
     }
 
@@ -628,6 +1749,7 @@ impl Runtime {
             config: config.clone(),
             is_root_facet: false,
             patterns: vec![],
+            branches: None,
         };
         self.entity_manager_mut().register(metadata);
 
@@ -789,6 +1911,7 @@ impl Runtime {
             config: config.clone(),
             is_root_facet: true,
             patterns: vec![],
+            branches: None,
         };
         self.entity_manager_mut().register(metadata);
 
@@ -809,8 +1932,13 @@ impl Runtime {
         entity_id: Uuid,
         pattern: Pattern,
     ) -> error::Result<()> {
-        let (actor_id, expected_facet) = match self.entity_manager().get(&entity_id) {
-            Some(meta) => (meta.actor.clone(), meta.facet.clone()),
+        let current_branch = self.current_branch.clone();
+        let (actor_id, expected_facet, visible) = match self.entity_manager().get(&entity_id) {
+            Some(meta) => (
+                meta.actor.clone(),
+                meta.facet.clone(),
+                meta.visible_on(&current_branch),
+            ),
             None => {
                 return Err(error::RuntimeError::Actor(error::ActorError::NotFound(
                     format!("Entity {} not found for pattern registration", entity_id),
@@ -827,12 +1955,19 @@ impl Runtime {
             ));
         }
 
-        let actor = self
-            .actors
-            .entry(actor_id.clone())
-            .or_insert_with(|| Actor::new(actor_id));
+        // Entities hidden on this branch pick the pattern up when instantiated
+        if visible {
+            let actor = self
+                .actors
+                .entry(actor_id.clone())
+                .or_insert_with(|| Actor::new(actor_id.clone()));
 
-        actor.register_pattern(pattern.clone());
+            actor.register_pattern(pattern.clone());
+
+            if pattern.scope == PatternScope::Dataspace {
+                self.seed_observed_assertions(&actor_id, &pattern);
+            }
+        }
 
         if let Some(meta) = self.entity_manager_mut().get_mut(&entity_id) {
             meta.patterns.retain(|existing| existing.id != pattern.id);
@@ -854,6 +1989,109 @@ impl Runtime {
         capability: CapId,
         payload: preserves::IOValue,
         completion: CapabilityCompletion,
+    ) {
+        if let Some(kind) = self.approval_required_kind(capability) {
+            self.park_for_approval(capability, kind, payload, completion);
+            return;
+        }
+
+        self.run_capability_invoke(capability, payload, completion);
+    }
+
+    /// Kind of `capability` if its invocations must wait for human approval.
+    fn approval_required_kind(&self, capability: CapId) -> Option<String> {
+        let (_issuer, metadata) = self.lookup_capability(capability)?;
+        (self.config.approval_kinds.contains(&metadata.kind)
+            && self.flag_enabled(flags::FeatureFlag::ApprovalGates))
+        .then_some(metadata.kind)
+    }
+
+    fn park_for_approval(
+        &mut self,
+        capability: CapId,
+        kind: String,
+        payload: preserves::IOValue,
+        completion: CapabilityCompletion,
+    ) {
+        let pending = approval::PendingApproval {
+            id: uuid::Uuid::new_v4(),
+            capability,
+            kind,
+            payload,
+            completion,
+            handle: Handle::new(),
+            requested_at: chrono::Utc::now(),
+        };
+
+        let origin = pending.completion.origin_actor.clone();
+        let input = TurnInput::Assert {
+            actor: origin.clone(),
+            handle: pending.handle.clone(),
+            value: pending.record(),
+            namespace: None,
+        };
+        self.scheduler
+            .enqueue(origin, input, ScheduleCause::Capability);
+
+        self.approvals.insert(pending);
+        if let Err(err) = self.persist_approvals() {
+            warn!("failed to persist pending approvals: {}", err);
+        }
+    }
+
+    /// Invocations currently parked awaiting approval.
+    pub fn pending_approvals(&self) -> Vec<approval::PendingApproval> {
+        self.approvals.iter().cloned().collect()
+    }
+
+    /// Approve a parked invocation and run it.
+    pub fn approve(&mut self, id: approval::ApprovalId) -> Result<()> {
+        let pending = self.take_pending_approval(id)?;
+        self.run_capability_invoke(pending.capability, pending.payload, pending.completion);
+        Ok(())
+    }
+
+    /// Deny a parked invocation, delivering an `approval-denied` record as its result.
+    pub fn deny(&mut self, id: approval::ApprovalId, reason: &str) -> Result<()> {
+        let pending = self.take_pending_approval(id)?;
+        let denial = pending.denial_record(reason);
+        self.deliver_capability_result(pending.capability, denial, pending.completion);
+        Ok(())
+    }
+
+    /// Remove a parked invocation and retract its `pending-approval` assertion.
+    fn take_pending_approval(
+        &mut self,
+        id: approval::ApprovalId,
+    ) -> Result<approval::PendingApproval> {
+        let pending = self
+            .approvals
+            .take(&id)
+            .ok_or(error::CapabilityError::ApprovalNotFound(id))?;
+
+        let origin = pending.completion.origin_actor.clone();
+        let input = TurnInput::Retract {
+            actor: origin.clone(),
+            handle: pending.handle.clone(),
+        };
+        self.scheduler
+            .enqueue(origin, input, ScheduleCause::External);
+
+        Ok(pending)
+    }
+
+    /// Persist parked invocations to disk.
+    fn persist_approvals(&self) -> Result<()> {
+        self.approvals
+            .save(&self.approvals_path)
+            .map_err(|e| error::RuntimeError::Storage(StorageError::Io(e)))
+    }
+
+    fn run_capability_invoke(
+        &mut self,
+        capability: CapId,
+        payload: preserves::IOValue,
+        completion: CapabilityCompletion,
     ) {
         let invocation_result = CapabilityInvoker::invoke(self, capability, payload);
 
@@ -872,6 +2110,16 @@ impl Runtime {
             }
         };
 
+        self.deliver_capability_result(capability, result_value, completion);
+    }
+
+    /// Publish a capability result to the actor awaiting it as a `tool-result` assertion.
+    fn deliver_capability_result(
+        &mut self,
+        capability: CapId,
+        result_value: preserves::IOValue,
+        completion: CapabilityCompletion,
+    ) {
         let mut fields = vec![
             preserves::IOValue::new(completion.instance_id.clone()),
             preserves::IOValue::new(completion.tag.clone()),
@@ -895,6 +2143,7 @@ impl Runtime {
             actor: completion.origin_actor.clone(),
             handle,
             value: record,
+            namespace: None,
         };
 
         self.scheduler
@@ -904,6 +2153,7 @@ impl Runtime {
     /// Step the runtime forward by one turn
     pub fn step(&mut self) -> Result<Option<TurnRecord>> {
         self.poll_async_messages();
+        self.pump_outbox()?;
         self.execute_turn()
     }
 
@@ -943,37 +2193,27 @@ impl Runtime {
             all_capabilities = all_capabilities.join(&actor_caps);
         }
 
+        // Snapshots are persisted like the journal, so they are redacted alike
+        self.redactor.redact_assertions(&mut all_assertions);
+
         // Get the actual turn ID of the last executed turn
-        // Use the most recent turn ID from any actor, or generate a placeholder
+        // Turn ids order by branch sequence, so the maximum is the latest turn
         let turn_id = self
             .last_turn_per_actor
             .values()
             .max()
             .cloned()
-            .unwrap_or_else(|| TurnId::new(format!("turn_{:08}", self.turn_count)));
+            .unwrap_or_else(TurnId::genesis);
 
         // Capture entity private state (for HydratableEntity implementations)
-        let registry = &self.entity_registry;
-        let mut entity_states = Vec::new();
-
-        for (actor_id, actor) in self.actors.iter() {
-            let entities = actor.entities.read();
-            for (facet_id, entries) in entities.iter() {
-                for entry in entries.iter() {
-                    if let Some(state) =
-                        registry.snapshot_entity(&entry.entity_type, entry.entity.as_ref())
-                    {
-                        entity_states.push(snapshot::EntityStateSnapshot {
-                            entity_id: entry.id,
-                            actor: actor_id.clone(),
-                            facet: facet_id.clone(),
-                            entity_type: entry.entity_type.clone(),
-                            state,
-                        });
-                    }
-                }
-            }
-        }
+        let entity_states = self
+            .live_entity_states()
+            .into_iter()
+            .map(|mut entry| {
+                entry.state = self.redactor.redact_value(&entry.state);
+                entry
+            })
+            .collect();
 
         let snapshot = RuntimeSnapshot {
             branch: self.current_branch.clone(),
@@ -986,31 +2226,236 @@ impl Runtime {
                 created_at: chrono::Utc::now(),
                 turn_count: self.turn_count,
                 turn_id,
+                version: Some(self.version.clone()),
             },
         };
 
+        // A snapshot never describes turns that could be lost
+        self.journal_writer.sync_pending()?;
         self.snapshot_manager
             .save(&snapshot)
             .map_err(|e| error::RuntimeError::Snapshot(e))?;
 
+        // Turns behind the snapshot are not recovered again, so neither are
+        // their deliveries
+        self.outbox
+            .forget_acknowledged_before(&snapshot.branch, &snapshot.turn_id)
+            .map_err(outbox_error)?;
+
+        if !self.config.snapshot_retention.is_unbounded() {
+            let branch = self.current_branch.clone();
+            self.prune_snapshots(&branch, None)?;
+        }
         Ok(())
     }
 
+    /// Delete `branch`'s snapshots outside `policy` (the configured snapshot
+    /// retention when `None`), returning the removed ones.
+    ///
+    /// The snapshot the journal was compacted to is always kept: replay
+    /// behind it has no records to start from.
+    pub fn prune_snapshots(
+        &mut self,
+        branch: &BranchId,
+        policy: Option<&snapshot::SnapshotRetention>,
+    ) -> Result<Vec<snapshot::SnapshotIndexEntry>> {
+        let policy = *policy.unwrap_or(&self.config.snapshot_retention);
+        let Some(meta) = self.branch_manager.get_branch(branch) else {
+            return Err(error::RuntimeError::Branch(error::BranchError::NotFound(
+                branch.to_string(),
+            )));
+        };
+        let protected: Vec<TurnId> = meta.compacted_through.iter().cloned().collect();
+        let pruned = self.snapshot_manager.prune(branch, &policy, &protected)?;
+        if !pruned.is_empty() {
+            tracing::debug!(branch = %branch, pruned = pruned.len(), "snapshots pruned");
+        }
+        Ok(pruned)
+    }
+
+    /// Tag the snapshot of `branch` taken at `turn_id` so retention keeps
+    /// it, returning whether the tag was new.
+    pub fn tag_snapshot(&mut self, branch: &BranchId, turn_id: &TurnId, tag: &str) -> Result<bool> {
+        Ok(self.snapshot_manager.tag(branch, turn_id, tag)?)
+    }
+
+    /// Snapshots recorded for `branch`, oldest first.
+    pub fn snapshots(&self, branch: &BranchId) -> Vec<snapshot::SnapshotIndexEntry> {
+        self.snapshot_manager.list(branch)
+    }
+
+    /// Rebuild observer routing from replayed dataspace-wide observation inputs.
+    fn replay_observations(&mut self, inputs: &[TurnInput]) {
+        for input in inputs {
+            match input {
+                TurnInput::ObservedAssert {
+                    observer, handle, ..
+                } => {
+                    self.observed_handles
+                        .entry(handle.clone())
+                        .or_default()
+                        .insert(observer.clone());
+                }
+                TurnInput::ObservedRetract {
+                    observer, handle, ..
+                } => {
+                    if let Some(observers) = self.observed_handles.get_mut(handle) {
+                        observers.remove(observer);
+                        if observers.is_empty() {
+                            self.observed_handles.remove(handle);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Advance and return the vector clock for a turn executed by `actor`.
+    fn advance_vector_clock(&mut self, actor: &turn::ActorId) -> turn::VectorClock {
+        let mut clock = self.vector_clocks.get(actor).cloned().unwrap_or_default();
+        if let Some(cause) = self.pending_causality.remove(actor) {
+            clock.join_in_place(&cause);
+        }
+        clock.increment(actor);
+        self.vector_clocks.insert(actor.clone(), clock.clone());
+        clock
+    }
+
+    /// Record that `target` will observe everything `source` has seen.
+    fn propagate_causality(&mut self, source: &turn::ActorId, target: &turn::ActorId) {
+        if let Some(clock) = self.vector_clocks.get(source).cloned() {
+            self.pending_causality
+                .entry(target.clone())
+                .or_default()
+                .join_in_place(&clock);
+        }
+    }
+
+    /// Vector clock recorded for a turn on `branch`, if the turn is journaled.
+    fn vector_clock_at(&self, branch: &BranchId, turn_id: &TurnId) -> turn::VectorClock {
+        JournalReader::new(self.storage.clone(), branch.clone())
+            .and_then(|reader| reader.header(turn_id))
+            .map(|header| header.vector_clock)
+            .unwrap_or_default()
+    }
+
+    /// Next Lamport sequence for a turn recorded on `branch`.
+    fn next_turn_sequence(&self, branch: &BranchId) -> u64 {
+        self.branch_manager
+            .head(branch)
+            .map(|head| head.sequence())
+            .unwrap_or(0)
+            + 1
+    }
+
+    /// Whether any branch journal still records legacy string turn ids.
+    fn has_legacy_turn_ids(storage: &Storage, branch_manager: &BranchManager) -> bool {
+        branch_manager.list_branches().iter().any(|metadata| {
+            JournalReader::new(storage.clone(), metadata.id.clone())
+                .unwrap_or_else(|_| JournalReader::new_empty(storage.clone(), metadata.id.clone()))
+                .has_legacy_turn_ids()
+        })
+    }
+
+    /// Rewrite legacy string turn ids across every branch.
+    ///
+    /// Journals, branch metadata, and snapshots are rewritten so previously
+    /// recorded history uses structured, sequence-ordered ids. Runs on
+    /// startup when a journal still has legacy ids. Returns the number of
+    /// turn ids that changed.
+    pub fn migrate_turn_ids(&mut self) -> Result<usize> {
+        let mapping = Self::migrate_journal_turn_ids(
+            &self.storage,
+            &mut self.branch_manager,
+            &self.snapshot_manager,
+        )?;
+        if mapping.is_empty() {
+            return Ok(0);
+        }
+
+        for turn_id in self.last_turn_per_actor.values_mut() {
+            if let Some(mapped) = mapping.get(turn_id) {
+                *turn_id = mapped.clone();
+            }
+        }
+
+        // Reopen the writer so its index reflects the rewritten segments,
+        // and re-key the indexes built from it
+        self.reopen_journal()?;
+        self.rebuild_branch_indexes()?;
+
+        Ok(mapping.len())
+    }
+
+    /// Rewrite the journals, branch metadata, and snapshot index that still
+    /// use legacy turn ids, returning the ids that changed
+    fn migrate_journal_turn_ids(
+        storage: &Storage,
+        branch_manager: &mut BranchManager,
+        snapshot_manager: &SnapshotManager,
+    ) -> Result<HashMap<TurnId, TurnId>> {
+        let mut branches: Vec<branch::BranchMetadata> = branch_manager
+            .list_branches()
+            .into_iter()
+            .cloned()
+            .collect();
+        // Migrate parents before children so fork-point parents are already mapped.
+        branches.sort_by_key(|metadata| metadata.parent.is_some());
+
+        let mut mapping = HashMap::new();
+        for metadata in &branches {
+            let base_seq = metadata
+                .base_turn
+                .as_ref()
+                .map(|base| mapping.get(base).unwrap_or(base).sequence())
+                .unwrap_or(0);
+            journal::migrate_legacy_turn_ids(storage, &metadata.id, base_seq, &mut mapping)
+                .map_err(error::RuntimeError::Journal)?;
+        }
+
+        if mapping.is_empty() {
+            return Ok(mapping);
+        }
+
+        branch_manager.remap_turns(&mapping);
+        storage::save_branch_state(storage, &branch_manager.state()).map_err(|e| {
+            error::RuntimeError::Config(format!("Failed to persist branch state: {}", e))
+        })?;
+        snapshot_manager
+            .remap_turn_ids(&mapping)
+            .map_err(error::RuntimeError::Snapshot)?;
+
+        Ok(mapping)
+    }
+
     fn record_branch_head(&self, branch: BranchId, head: TurnId) {
-        let (lock, cvar) = &*self.turn_wait;
-        let mut guard = lock.lock().unwrap();
-        guard.insert(branch, head);
-        cvar.notify_all();
+        self.turn_waiters.record_head(branch, head);
     }
 
     fn poll_async_messages(&mut self) {
         while let Ok(message) = self.async_inbox.try_recv() {
+            if message
+                .cancellation
+                .as_ref()
+                .is_some_and(task::CancellationToken::is_cancelled)
+            {
+                continue;
+            }
+            #[cfg(feature = "chaos")]
+            if self.inject_fault(chaos::FaultKind::AsyncMessageDrop, || {
+                format!("message for actor {}", message.actor)
+            }) {
+                continue;
+            }
             self.scheduler.enqueue(
                 message.actor.clone(),
                 TurnInput::ExternalMessage {
                     actor: message.actor,
                     facet: message.facet,
                     payload: message.payload,
+                    idempotency_key: None,
+                    broadcast: None,
                 },
                 ScheduleCause::External,
             );
@@ -1024,52 +2469,33 @@ impl Runtime {
         since: Option<&TurnId>,
         timeout: Duration,
     ) -> Result<Option<TurnId>> {
-        let (lock, cvar) = &*self.turn_wait;
-
-        // Fast path: check current head before waiting.
-        {
-            let guard = lock.lock().unwrap();
-            if let Some(current) = guard
-                .get(branch)
-                .cloned()
-                .or_else(|| self.branch_manager.head(branch).cloned())
-            {
-                if since.map_or(true, |s| &current != s) {
-                    return Ok(Some(current));
-                }
-            }
-        }
-
-        if timeout.is_zero() {
-            return Ok(None);
-        }
-
-        let deadline = Instant::now() + timeout;
-        let mut guard = lock.lock().unwrap();
-
-        loop {
-            let remaining = deadline.saturating_duration_since(Instant::now());
-            if remaining.is_zero() {
-                return Ok(None);
-            }
+        Ok(self
+            .turn_waiters
+            .wait_once(branch, since, self.branch_manager.head(branch), timeout))
+    }
 
-            let (g, wait_result) = cvar.wait_timeout(guard, remaining).unwrap();
-            guard = g;
+    /// Register a long-poll wait for `branch` moving past `since`.
+    ///
+    /// The registration outlives the caller's connection; collect turns with
+    /// [`poll_turn_wait`](Self::poll_turn_wait).
+    pub fn register_turn_wait(
+        &self,
+        branch: BranchId,
+        since: Option<TurnId>,
+    ) -> wait::WaitRegistration {
+        let current = self.branch_manager.head(&branch).cloned();
+        self.turn_waiters.register(branch, since, current.as_ref())
+    }
 
-            if let Some(current) = guard
-                .get(branch)
-                .cloned()
-                .or_else(|| self.branch_manager.head(branch).cloned())
-            {
-                if since.map_or(true, |s| &current != s) {
-                    return Ok(Some(current));
-                }
-            }
+    /// Collect the branch head queued for a registration, waiting up to
+    /// `timeout` for one.
+    pub fn poll_turn_wait(&self, id: wait::WaitId, timeout: Duration) -> Result<Option<TurnId>> {
+        self.turn_waiters.poll(id, timeout)
+    }
 
-            if wait_result.timed_out() {
-                return Ok(None);
-            }
-        }
+    /// Drop a long-poll registration, returning whether it existed.
+    pub fn cancel_turn_wait(&self, id: wait::WaitId) -> bool {
+        self.turn_waiters.cancel(id)
     }
 
     /// Enqueue a message to an actor
@@ -1085,100 +2511,2545 @@ impl Runtime {
             actor: target_actor.clone(),
             facet: target_facet,
             payload,
+            idempotency_key: None,
+            broadcast: None,
         };
 
         self.scheduler
             .enqueue(target_actor, input, ScheduleCause::External);
     }
 
-    /// Assert a value directly into an actor's dataspace.
-    pub fn assert_value(&mut self, target_actor: turn::ActorId, value: preserves::IOValue) {
+    /// Attribute turns enqueued or driven from now on, and branches forked,
+    /// to `identity`, returning the previous attribution.
+    pub fn set_initiator(
+        &mut self,
+        identity: Option<identity::Identity>,
+    ) -> Option<identity::Identity> {
+        self.scheduler.set_initiator(identity)
+    }
+
+    /// Identity turns are currently attributed to
+    pub fn initiator(&self) -> Option<&identity::Identity> {
+        self.scheduler.initiator()
+    }
+
+    /// Enqueue a message unless a message with the same idempotency key was
+    /// already queued or executed on the current branch.
+    ///
+    /// Returns `false` when the message was suppressed; the duplicate is then
+    /// recorded as a [`dedup::DuplicateAnnotation`] instead of running a turn.
+    pub fn send_message_idempotent(
+        &mut self,
+        target_actor: turn::ActorId,
+        target_facet: turn::FacetId,
+        payload: preserves::IOValue,
+        idempotency_key: impl Into<String>,
+    ) -> Result<bool> {
         use scheduler::ScheduleCause;
 
-        let handle = Handle::new();
-        let input = turn::TurnInput::Assert {
+        let key = idempotency_key.into();
+        if let Some(original_turn) = self.idempotency.lookup(&key) {
+            let annotation = dedup::DuplicateAnnotation {
+                key,
+                actor: target_actor,
+                facet: target_facet,
+                original_turn,
+                suppressed_at: chrono::Utc::now(),
+            };
+            storage::append_json_line(&self.duplicates_path(), &annotation)?;
+            return Ok(false);
+        }
+
+        self.idempotency.mark_queued(key.clone());
+        let input = turn::TurnInput::ExternalMessage {
             actor: target_actor.clone(),
-            handle,
-            value,
+            facet: target_facet,
+            payload,
+            idempotency_key: Some(key),
+            broadcast: None,
         };
-
         self.scheduler
             .enqueue(target_actor, input, ScheduleCause::External);
+        Ok(true)
     }
 
-    /// Fork a new branch from the current branch
-    pub fn fork(
+    /// Deliver `payload` to an actor/facet every `every_n_turns` turns.
+    ///
+    /// The schedule fires after each turn whose branch sequence number is a
+    /// multiple of the interval and persists across restarts until cancelled.
+    pub fn schedule_recurring(
         &mut self,
-        new_branch_name: impl Into<String>,
-        at_turn: Option<TurnId>,
-    ) -> Result<BranchId> {
-        let current = self.current_branch.clone();
-        let new_branch = BranchId::new(new_branch_name);
+        actor: turn::ActorId,
+        facet: turn::FacetId,
+        payload: preserves::IOValue,
+        every_n_turns: u64,
+    ) -> Result<schedule::ScheduleId> {
+        if every_n_turns == 0 {
+            return Err(error::RuntimeError::Config(
+                "recurring schedule interval must be at least one turn".into(),
+            ));
+        }
 
-        // Use current head if no specific turn specified
-        let base_turn = at_turn.unwrap_or_else(|| {
-            // TODO: Track actual last turn ID
-            TurnId::new(format!("turn_{:08}", self.turn_count))
-        });
+        let entry = schedule::RecurringSchedule {
+            id: uuid::Uuid::new_v4(),
+            actor,
+            facet,
+            payload,
+            every_n_turns,
+            created_at: chrono::Utc::now(),
+        };
+        let id = entry.id;
+        self.schedules.insert(entry);
+        self.persist_schedules()?;
+        Ok(id)
+    }
 
-        // Create the fork in branch manager
-        self.branch_manager
-            .fork(&current, new_branch.clone(), base_turn.clone())
-            .map_err(|e| error::RuntimeError::Branch(e))?;
+    /// Cancel a recurring schedule, returning whether it existed.
+    pub fn cancel_recurring(&mut self, id: schedule::ScheduleId) -> Result<bool> {
+        let removed = self.schedules.remove(&id).is_some();
+        if removed {
+            self.persist_schedules()?;
+        }
+        Ok(removed)
+    }
 
-        // Create journal and snapshot directories for new branch
-        let new_journal_dir = self.storage.branch_journal_dir(&new_branch);
-        let new_snapshot_dir = self.storage.branch_snapshot_dir(&new_branch);
-        std::fs::create_dir_all(&new_journal_dir).map_err(|e| {
-            error::RuntimeError::Init(format!("Failed to create branch journal dir: {}", e))
-        })?;
-        std::fs::create_dir_all(&new_snapshot_dir).map_err(|e| {
+    /// Registered recurring schedules.
+    pub fn recurring_schedules(&self) -> Vec<schedule::RecurringSchedule> {
+        self.schedules.iter().cloned().collect()
+    }
+
+    /// Enqueue the wakeups of timers due at `now`, returning how many fired.
+    ///
+    /// Called before every turn; daemons idling between requests call it
+    /// once [`Runtime::next_timer_deadline`] has passed.
+    pub fn fire_due_timers(&mut self, now: chrono::DateTime<chrono::Utc>) -> usize {
+        let due = self.timers.take_due(now);
+        for timer in &due {
+            self.scheduler.enqueue(
+                timer.actor.clone(),
+                timer.input(),
+                scheduler::ScheduleCause::Timer,
+            );
+        }
+        due.len()
+    }
+
+    /// Earliest deadline of a pending timer that has not fired yet.
+    pub fn next_timer_deadline(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.timers.next_deadline()
+    }
+
+    /// Timers set on the current branch that have not fired yet.
+    pub fn pending_timers(&self) -> Vec<timer::PendingTimer> {
+        self.timers.pending()
+    }
+
+    fn persist_schedules(&self) -> Result<()> {
+        self.schedules.save(&self.schedules_path).map_err(|e| {
+            error::RuntimeError::Init(format!("Failed to persist recurring schedules: {}", e))
+        })
+    }
+
+    /// Enqueue the firings due after the turn with sequence number `seq`.
+    fn fire_recurring(&mut self, seq: u64) {
+        use scheduler::ScheduleCause;
+
+        let due: Vec<_> = self
+            .schedules
+            .iter()
+            .filter(|entry| entry.due_at(seq))
+            .cloned()
+            .collect();
+        for entry in due {
+            let key = entry.firing_key(seq);
+            if self.idempotency.lookup(&key).is_some() {
+                continue;
+            }
+            self.idempotency.mark_queued(key.clone());
+            let input = TurnInput::ExternalMessage {
+                actor: entry.actor.clone(),
+                facet: entry.facet,
+                payload: entry.payload,
+                idempotency_key: Some(key),
+                broadcast: None,
+            };
+            self.scheduler
+                .enqueue(entry.actor, input, ScheduleCause::Timer);
+        }
+    }
+
+    /// Add an actor to a named group used for broadcast addressing.
+    pub fn add_to_group(&mut self, group: impl Into<String>, actor: turn::ActorId) -> Result<bool> {
+        let added = self.entity_manager.add_to_group(group, actor);
+        self.persist_entities()?;
+        Ok(added)
+    }
+
+    /// Remove an actor from a named group.
+    pub fn remove_from_group(&mut self, group: &str, actor: &turn::ActorId) -> Result<bool> {
+        let removed = self.entity_manager.remove_from_group(group, actor);
+        self.persist_entities()?;
+        Ok(removed)
+    }
+
+    /// Schedule a message turn carrying `payload` for every member of `group`.
+    ///
+    /// Members receive the message on each facet hosting entities (or on their
+    /// root facet if they host none). The broadcast is logged once for the
+    /// current branch and each resulting input references its id.
+    pub fn broadcast(
+        &mut self,
+        group: &str,
+        payload: preserves::IOValue,
+    ) -> Result<broadcast::BroadcastRecord> {
+        use scheduler::ScheduleCause;
+
+        let mut recipients = Vec::new();
+        for actor in self.entity_manager.group_members(group) {
+            let mut facets: Vec<turn::FacetId> = Vec::new();
+            for metadata in self.entity_manager.list_for_actor(&actor) {
+                if !facets.contains(&metadata.facet) {
+                    facets.push(metadata.facet.clone());
+                }
+            }
+            facets.sort_by_key(|facet| facet.0);
+            if facets.is_empty() {
+                facets.extend(self.actors.get(&actor).map(|a| a.root_facet.clone()));
+            }
+            recipients.extend(
+                facets
+                    .into_iter()
+                    .map(|facet| broadcast::BroadcastRecipient {
+                        actor: actor.clone(),
+                        facet,
+                    }),
+            );
+        }
+
+        let record = broadcast::BroadcastRecord {
+            id: uuid::Uuid::new_v4(),
+            group: group.to_string(),
+            payload,
+            recipients,
+            sent_at: chrono::Utc::now(),
+        };
+        storage::append_json_line(&self.broadcasts_path(), &record)?;
+
+        for recipient in &record.recipients {
+            let input = TurnInput::ExternalMessage {
+                actor: recipient.actor.clone(),
+                facet: recipient.facet.clone(),
+                payload: record.payload.clone(),
+                idempotency_key: None,
+                broadcast: Some(record.id),
+            };
+            self.scheduler
+                .enqueue(recipient.actor.clone(), input, ScheduleCause::External);
+        }
+
+        Ok(record)
+    }
+
+    /// Broadcasts sent on the current branch, oldest first.
+    pub fn broadcasts(&self) -> Result<Vec<broadcast::BroadcastRecord>> {
+        Ok(storage::read_json_lines(&self.broadcasts_path())?)
+    }
+
+    fn broadcasts_path(&self) -> PathBuf {
+        self.storage
+            .branch_meta_dir(&self.current_branch)
+            .join("broadcasts.jsonl")
+    }
+
+    /// Movements of `branch`'s head, newest first.
+    pub fn reflog(&self, branch: &BranchId) -> Result<Vec<reflog::ReflogEntry>> {
+        if self.branch_manager.get_branch(branch).is_none() {
+            return Err(error::RuntimeError::Branch(error::BranchError::NotFound(
+                branch.to_string(),
+            )));
+        }
+        let mut entries: Vec<reflog::ReflogEntry> =
+            storage::read_json_lines(&self.reflog_path(branch))?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Move the current branch's head back to where reflog entry `index`
+    /// (0 = latest) left it, undoing later time travel.
+    pub fn restore_head(&mut self, index: usize) -> Result<TurnId> {
+        let target = self
+            .reflog(&self.current_branch)?
+            .into_iter()
+            .nth(index)
+            .map(|entry| entry.to)
+            .ok_or_else(|| {
+                error::RuntimeError::Branch(error::BranchError::ReflogEntryNotFound {
+                    branch: self.current_branch.to_string(),
+                    index,
+                })
+            })?;
+        self.travel_to(target.clone(), reflog::HeadMove::Restore)?;
+        Ok(target)
+    }
+
+    /// Append a movement of `branch`'s head from `from` to `to` to its reflog.
+    fn log_head_move(
+        &self,
+        branch: &BranchId,
+        from: TurnId,
+        to: &TurnId,
+        action: reflog::HeadMove,
+        client: Option<identity::Identity>,
+    ) -> Result<()> {
+        let entry = reflog::ReflogEntry {
+            from,
+            to: to.clone(),
+            action,
+            client,
+            at: chrono::Utc::now(),
+        };
+        storage::append_json_line(&self.reflog_path(branch), &entry)?;
+        Ok(())
+    }
+
+    fn reflog_path(&self, branch: &BranchId) -> PathBuf {
+        self.storage.branch_meta_dir(branch).join("reflog.jsonl")
+    }
+
+    /// Duplicate deliveries suppressed on the current branch, oldest first.
+    pub fn duplicate_annotations(&self) -> Result<Vec<dedup::DuplicateAnnotation>> {
+        Ok(storage::read_json_lines(&self.duplicates_path())?)
+    }
+
+    fn duplicates_path(&self) -> PathBuf {
+        self.storage
+            .branch_meta_dir(&self.current_branch)
+            .join("duplicates.jsonl")
+    }
+
+    /// Rebuild the idempotency index, the pending timers, the set of paused
+    /// actors, each actor's vector clock and the pending approvals from the
+    /// current branch's history, including the ancestor turns it was forked
+    /// from.
+    fn rebuild_branch_indexes(&mut self) -> Result<()> {
+        self.idempotency.clear();
+        self.timers.clear();
+        self.vector_clocks.clear();
+        self.pending_causality.clear();
+        self.observed_handles.clear();
+        let records = self.lineage_records(&self.current_branch, None)?;
+        let mut paused = BTreeSet::new();
+        let mut approvals = HashMap::new();
+        for record in &records {
+            self.idempotency.record(record);
+            for (_actor, handle, value, _version) in &record.delta.assertions.added {
+                if let Some(id) = approval::PendingApproval::id_in(value) {
+                    approvals.insert(handle.clone(), id);
+                }
+            }
+            for (_actor, handle, _version) in &record.delta.assertions.retracted {
+                approvals.remove(handle);
+            }
+            self.timers.record(record);
+            // Observers of assertions made before a snapshot still need
+            // their retractions
+            self.replay_observations(&record.inputs);
+            // An actor's latest clock covers all of its earlier ones
+            if !record.vector_clock.0.is_empty() {
+                self.vector_clocks
+                    .insert(record.actor.clone(), record.vector_clock.clone());
+            }
+            for input in &record.inputs {
+                match input {
+                    TurnInput::PauseActor { actor } => {
+                        paused.insert(actor.clone());
+                    }
+                    TurnInput::ResumeActor { actor } => {
+                        paused.remove(actor);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let stale: Vec<ActorId> = self
+            .scheduler
+            .paused_actors()
+            .filter(|actor| !paused.contains(*actor))
+            .cloned()
+            .collect();
+        for actor in &stale {
+            self.scheduler.resume(actor);
+        }
+        for actor in &paused {
+            self.scheduler.pause(actor);
+        }
+        // Only invocations parked in this history can be decided on
+        self.approvals.rebuild(approvals.into_values());
+        Ok(())
+    }
+
+    /// Pause or resume `actor`, journaling the change as an administrative
+    /// turn on the current branch.
+    ///
+    /// While paused, the actor's queued and newly arriving inputs are held by
+    /// the scheduler; other actors keep running. Returns `None` when the
+    /// actor was already in the requested state.
+    pub fn set_actor_paused(&mut self, actor: ActorId, paused: bool) -> Result<Option<TurnId>> {
+        let changed = if paused {
+            self.scheduler.pause(&actor)
+        } else {
+            self.scheduler.resume(&actor)
+        };
+        if !changed {
+            return Ok(None);
+        }
+
+        let input = if paused {
+            TurnInput::PauseActor { actor }
+        } else {
+            TurnInput::ResumeActor { actor }
+        };
+        let branch = self.current_branch.clone();
+        let parent = self
+            .branch_manager
+            .head(&branch)
+            .filter(|head| **head != TurnId::genesis())
+            .cloned();
+        let seq = self.next_turn_sequence(&branch);
+        let record = TurnRecord::new(
+            turn::ActorId::from_uuid(Uuid::nil()),
+            branch.clone(),
+            turn::LogicalClock::zero(),
+            parent,
+            vec![input],
+            Vec::new(),
+            state::StateDelta::empty(),
+        )
+        .with_sequence(seq);
+        let turn_id = record.turn_id.clone();
+
+        self.journal_writer
+            .append(&record)
+            .map_err(error::RuntimeError::Journal)?;
+        self.turn_sinks.publish(&record);
+        self.branch_manager
+            .update_head(&branch, turn_id.clone())
+            .map_err(error::RuntimeError::Branch)?;
+        self.persist_branch_state()?;
+        self.log_head_move(
+            &branch,
+            record.parent.clone().unwrap_or_else(TurnId::genesis),
+            &turn_id,
+            reflog::HeadMove::Admin,
+            self.scheduler.initiator().cloned(),
+        )?;
+        self.record_branch_head(branch, turn_id.clone());
+
+        Ok(Some(turn_id))
+    }
+
+    /// Headers of the turns visible from `branch`, including turns inherited
+    /// from the branches it was forked from, without decoding payloads.
+    pub fn lineage_headers(&self, branch: &BranchId) -> Result<Vec<journal::RecordHeader>> {
+        if self.branch_manager.get_branch(branch).is_none() {
+            return Err(error::RuntimeError::Branch(error::BranchError::NotFound(
+                branch.to_string(),
+            )));
+        }
+
+        let mut segments = Vec::new();
+        let mut cursor = self
+            .branch_manager
+            .head(branch)
+            .cloned()
+            .map(|head| (branch.clone(), head));
+        while let Some((branch, stop)) = cursor {
+            cursor = self
+                .branch_manager
+                .get_branch(&branch)
+                .and_then(|meta| meta.parent.clone().zip(meta.base_turn.clone()));
+            segments.push((branch, stop));
+        }
+
+        let mut headers = Vec::new();
+        for (branch, stop) in segments.into_iter().rev() {
+            if stop == TurnId::genesis() {
+                continue;
+            }
+            let reader = JournalReader::new(self.storage.clone(), branch.clone())
+                .unwrap_or_else(|_| JournalReader::new_empty(self.storage.clone(), branch));
+            for header in reader
+                .iter_headers()
+                .map_err(error::RuntimeError::Journal)?
+            {
+                let done = header.turn_id == stop;
+                headers.push(header);
+                if done {
+                    break;
+                }
+            }
+        }
+        Ok(headers)
+    }
+
+    /// Origin of every assertion live on `branch` after `until` (or its head).
+    ///
+    /// Assertions brought in by a merge report the branch and turn that
+    /// originally made them, following chains of merges back to the source.
+    pub fn assertion_origins(
+        &self,
+        branch: &BranchId,
+        until: Option<&TurnId>,
+    ) -> Result<HashMap<Handle, branch::AssertionOrigin>> {
+        let mut origins = HashMap::new();
+        for record in self.lineage_records(branch, until)? {
+            for (_actor, handle, _version) in &record.delta.assertions.retracted {
+                origins.remove(handle);
+            }
+            // Merges re-add assertions both sides already had; keep the original
+            for (_actor, handle, _value, _version) in &record.delta.assertions.added {
+                origins
+                    .entry(handle.clone())
+                    .or_insert_with(|| branch::AssertionOrigin {
+                        branch: record.branch.clone(),
+                        turn: record.turn_id.clone(),
+                        merge_turn: None,
+                    });
+            }
+            for output in &record.outputs {
+                if let turn::TurnOutput::MergeProvenance {
+                    handle,
+                    source_branch,
+                    source_turn,
+                    ..
+                } = output
+                {
+                    origins.insert(
+                        handle.clone(),
+                        branch::AssertionOrigin {
+                            branch: source_branch.clone(),
+                            turn: source_turn.clone(),
+                            merge_turn: Some(record.turn_id.clone()),
+                        },
+                    );
+                }
+            }
+        }
+        Ok(origins)
+    }
+
+    /// Version that wrote each turn visible from `branch`, including turns
+    /// inherited from its ancestors. Turns from segments without a header are
+    /// absent.
+    pub fn lineage_versions(
+        &self,
+        branch: &BranchId,
+    ) -> Result<HashMap<TurnId, version::VersionStamp>> {
+        let mut versions = HashMap::new();
+        let mut cursor = Some(branch.clone());
+        while let Some(branch) = cursor {
+            cursor = self
+                .branch_manager
+                .get_branch(&branch)
+                .and_then(|meta| meta.parent.clone());
+            let reader = JournalReader::new(self.storage.clone(), branch)
+                .map_err(error::RuntimeError::Journal)?;
+            for (turn_id, version) in reader
+                .turn_versions()
+                .map_err(error::RuntimeError::Journal)?
+            {
+                versions.entry(turn_id).or_insert(version);
+            }
+        }
+        Ok(versions)
+    }
+
+    /// Turns visible from `branch`, oldest first, without touching the active branch.
+    ///
+    /// Walks the fork chain so a branch's history includes its ancestors' turns
+    /// up to each fork point. Reading stops after `until` when given, and at
+    /// the branch head otherwise.
+    pub fn lineage_records(
+        &self,
+        branch: &BranchId,
+        until: Option<&TurnId>,
+    ) -> Result<Vec<TurnRecord>> {
+        if self.branch_manager.get_branch(branch).is_none() {
+            return Err(error::RuntimeError::Branch(error::BranchError::NotFound(
+                branch.to_string(),
+            )));
+        }
+
+        let mut segments = Vec::new();
+        let mut cursor = self
+            .branch_manager
+            .head(branch)
+            .cloned()
+            .map(|head| (branch.clone(), head));
+        while let Some((branch, stop)) = cursor {
+            cursor = self
+                .branch_manager
+                .get_branch(&branch)
+                .and_then(|meta| meta.parent.clone().zip(meta.base_turn.clone()));
+            segments.push((branch, stop));
+        }
+
+        let mut records = Vec::new();
+        for (branch, stop) in segments.into_iter().rev() {
+            if stop == TurnId::genesis() {
+                continue;
+            }
+            let reader = JournalReader::new(self.storage.clone(), branch.clone())
+                .unwrap_or_else(|_| JournalReader::new_empty(self.storage.clone(), branch));
+            for result in reader.iter_all().map_err(error::RuntimeError::Journal)? {
+                let record = result.map_err(error::RuntimeError::Journal)?;
+                let done = record.turn_id == stop;
+                let reached = until.is_some_and(|until| *until == record.turn_id);
+                records.push(record);
+                if reached {
+                    return Ok(records);
+                }
+                if done {
+                    break;
+                }
+            }
+        }
+
+        if let Some(until) = until {
+            return Err(error::RuntimeError::Journal(
+                error::JournalError::TurnNotFound(until.to_string()),
+            ));
+        }
+        Ok(records)
+    }
+
+    /// Assert a value directly into an actor's dataspace.
+    pub fn assert_value(&mut self, target_actor: turn::ActorId, value: preserves::IOValue) {
+        self.enqueue_assert(target_actor, None, value);
+    }
+
+    /// Assert a value into a named dataspace of an actor.
+    pub fn assert_value_in(
+        &mut self,
+        target_actor: turn::ActorId,
+        namespace: impl Into<String>,
+        value: preserves::IOValue,
+    ) {
+        let namespace = namespace.into();
+        let namespace = (namespace != state::DEFAULT_NAMESPACE).then_some(namespace);
+        self.enqueue_assert(target_actor, namespace, value);
+    }
+
+    /// Queue a single turn retracting every assertion of `actor` matching `pattern`.
+    ///
+    /// Matching happens now, against the live dataspace; the handles are
+    /// carried in the turn input so replay retracts exactly the same set.
+    pub fn retract_matching(
+        &mut self,
+        actor: turn::ActorId,
+        pattern: preserves::IOValue,
+    ) -> Vec<Handle> {
+        use scheduler::ScheduleCause;
+
+        let mut handles: Vec<Handle> = self
+            .actors
+            .get(&actor)
+            .map(|actor_obj| {
+                let assertions = actor_obj.assertions.read();
+                assertions
+                    .active
+                    .iter()
+                    .filter(|((owner, _), (value, _))| {
+                        *owner == actor && pattern::matches_pattern(&pattern, value)
+                    })
+                    .map(|((_, handle), _)| handle.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        handles.sort_by_key(|handle| handle.0);
+
+        let input = TurnInput::RetractMatching {
+            actor: actor.clone(),
+            pattern,
+            handles: handles.clone(),
+        };
+        self.scheduler
+            .enqueue(actor, input, ScheduleCause::External);
+        handles
+    }
+
+    /// Queue a system-actor turn setting `flag` to `enabled`.
+    ///
+    /// The turn retracts the flag's previous assertion and asserts the new
+    /// value, so exactly one `<feature-flag ...>` fact per flag is visible.
+    /// Debug logging is adjusted immediately when the runtime manages the
+    /// subscriber.
+    pub fn set_flag(&mut self, flag: flags::FeatureFlag, enabled: bool) {
+        use scheduler::ScheduleCause;
+
+        let actor = flags::system_actor();
+        let retracted = self
+            .flag_assertions()
+            .into_iter()
+            .filter(|(asserted, _, _)| *asserted == flag)
+            .map(|(_, _, handle)| handle)
+            .collect();
+        let input = TurnInput::SetFlag {
+            flag,
+            enabled,
+            handle: Handle::new(),
+            retracted,
+        };
+        self.scheduler
+            .enqueue(actor, input, ScheduleCause::External);
+
+        if flag == flags::FeatureFlag::Debug && logging::current().is_some() {
+            let level = if enabled {
+                "debug".to_string()
+            } else {
+                self.config.logging.default_level.clone()
+            };
+            if let Err(err) = logging::set_level(None, &level) {
+                tracing::warn!(error = %err, "failed to apply debug flag to logging");
+            }
+        }
+    }
+
+    /// Current value of every feature flag.
+    pub fn flags(&self) -> Vec<flags::FlagStatus> {
+        let asserted = self.flag_assertions();
+        flags::FeatureFlag::ALL
+            .into_iter()
+            .map(|flag| {
+                let value = asserted
+                    .iter()
+                    .find(|(asserted, _, _)| *asserted == flag)
+                    .map(|(_, enabled, _)| *enabled);
+                flags::FlagStatus {
+                    flag,
+                    enabled: value.unwrap_or_else(|| self.flag_default(flag)),
+                    asserted: value.is_some(),
+                }
+            })
+            .collect()
+    }
+
+    /// Whether `flag` is currently on.
+    pub fn flag_enabled(&self, flag: flags::FeatureFlag) -> bool {
+        self.flag_assertions()
+            .into_iter()
+            .find(|(asserted, _, _)| *asserted == flag)
+            .map(|(_, enabled, _)| enabled)
+            .unwrap_or_else(|| self.flag_default(flag))
+    }
+
+    fn flag_default(&self, flag: flags::FeatureFlag) -> bool {
+        match flag {
+            flags::FeatureFlag::Debug => self.config.debug,
+            flags::FeatureFlag::Chaos | flags::FeatureFlag::ApprovalGates => true,
+        }
+    }
+
+    /// Flag assertions held by the system actor, with their handles.
+    fn flag_assertions(&self) -> Vec<(flags::FeatureFlag, bool, Handle)> {
+        let system = flags::system_actor();
+        let Some(actor) = self.actors.get(&system) else {
+            return Vec::new();
+        };
+        let assertions = actor.assertions.read();
+        let mut found: Vec<_> = assertions
+            .active
+            .iter()
+            .filter(|((owner, _), _)| *owner == system)
+            .filter_map(|((_, handle), (value, _))| {
+                flags::FeatureFlag::parse_assertion(value)
+                    .map(|(flag, enabled)| (flag, enabled, handle.clone()))
+            })
+            .collect();
+        found.sort_by_key(|(_, _, handle)| handle.0);
+        found
+    }
+
+    fn enqueue_assert(
+        &mut self,
+        target_actor: turn::ActorId,
+        namespace: Option<String>,
+        value: preserves::IOValue,
+    ) {
+        use scheduler::ScheduleCause;
+
+        let handle = Handle::new();
+        let input = turn::TurnInput::Assert {
+            actor: target_actor.clone(),
+            handle,
+            value,
+            namespace,
+        };
+
+        self.scheduler
+            .enqueue(target_actor, input, ScheduleCause::External);
+    }
+
+    /// Fork a new branch from the current branch
+    pub fn fork(
+        &mut self,
+        new_branch_name: impl Into<String>,
+        at_turn: Option<TurnId>,
+    ) -> Result<BranchId> {
+        self.fork_with(new_branch_name, at_turn, branch::BranchDetails::default())
+    }
+
+    /// Fork the current branch, recording `details` with the new branch.
+    ///
+    /// The name must satisfy [`RuntimeConfig::branch_naming`].
+    pub fn fork_with(
+        &mut self,
+        new_branch_name: impl Into<String>,
+        at_turn: Option<TurnId>,
+        details: branch::BranchDetails,
+    ) -> Result<BranchId> {
+        let new_branch = BranchId::new(new_branch_name);
+        self.config.branch_naming.check(&new_branch)?;
+        self.fork_branch(new_branch, at_turn, details)
+    }
+
+    /// Fork the current branch as `new_branch` without applying the naming
+    /// policy.
+    fn fork_branch(
+        &mut self,
+        new_branch: BranchId,
+        at_turn: Option<TurnId>,
+        details: branch::BranchDetails,
+    ) -> Result<BranchId> {
+        let current = self.current_branch.clone();
+
+        // Use current head if no specific turn specified
+        let base_turn = at_turn.unwrap_or_else(|| {
+            self.branch_manager
+                .head(&current)
+                .cloned()
+                .unwrap_or_else(TurnId::genesis)
+        });
+
+        // Create the fork in branch manager
+        let initiator = self.scheduler.initiator().cloned();
+        let details = branch::BranchDetails {
+            creator: details
+                .creator
+                .or_else(|| initiator.as_ref().map(|identity| identity.id.clone())),
+            ..details
+        };
+        self.branch_manager
+            .fork(&current, new_branch.clone(), base_turn.clone())
+            .map_err(|e| error::RuntimeError::Branch(e))?;
+        self.branch_manager.describe(&new_branch, details)?;
+        self.branch_manager.set_created_by(&new_branch, initiator)?;
+        self.entity_manager
+            .inherit_branch_scope(&current, &new_branch);
+        self.persist_entities()?;
+
+        // Create journal and snapshot directories for new branch
+        let new_journal_dir = self.storage.branch_journal_dir(&new_branch);
+        let new_snapshot_dir = self.storage.branch_snapshot_dir(&new_branch);
+        std::fs::create_dir_all(&new_journal_dir).map_err(|e| {
+            error::RuntimeError::Init(format!("Failed to create branch journal dir: {}", e))
+        })?;
+        std::fs::create_dir_all(&new_snapshot_dir).map_err(|e| {
+            error::RuntimeError::Init(format!("Failed to create branch snapshot dir: {}", e))
+        })?;
+
+        self.persist_branch_state()?;
+
+        Ok(new_branch)
+    }
+
+    /// Switch to a different branch
+    pub fn switch_branch(&mut self, branch: BranchId) -> Result<()> {
+        if branch.is_reserved() {
+            return Err(error::RuntimeError::Branch(
+                error::BranchError::InvalidName(format!(
+                    "'{}' is reserved for the runtime",
+                    branch
+                )),
+            ));
+        }
+        self.enter_branch(branch)
+    }
+
+    /// Make `branch` current, including reserved branches.
+    fn enter_branch(&mut self, branch: BranchId) -> Result<()> {
+        let old_head = self.current_head();
+
+        // Verify branch exists
+        self.branch_manager
+            .switch_branch(branch.clone())
+            .map_err(|e| error::RuntimeError::Branch(e))?;
+
+        // Update runtime state
+        self.journal_writer.sync_pending()?;
+        self.current_branch = branch.clone();
+
+        // Reinitialize journal writer for new branch
+        self.reopen_journal()?;
+
+        self.persist_branch_state()?;
+        self.rebuild_branch_indexes()?;
+        self.sync_branch_entities()?;
+
+        let new_head = self.current_head();
+        self.notify_time_travel(&old_head, &new_head);
+
+        Ok(())
+    }
+
+    /// Journal `change` as a turn on the reserved config branch.
+    ///
+    /// The branch is created on first use. Its turns have no actor state;
+    /// they exist so configuration history can be read back at any turn.
+    pub fn record_config_change(&mut self, change: config_history::ConfigChange) -> Result<TurnId> {
+        let branch = BranchId::config();
+        self.branch_manager.ensure_root(branch.clone());
+
+        let parent = self
+            .branch_manager
+            .head(&branch)
+            .filter(|head| **head != TurnId::genesis())
+            .cloned();
+        let seq = self.next_turn_sequence(&branch);
+        let record = TurnRecord::new(
+            turn::ActorId::from_uuid(Uuid::nil()),
+            branch.clone(),
+            turn::LogicalClock::zero(),
+            parent,
+            vec![TurnInput::ConfigChange {
+                branch: self.current_branch.clone(),
+                at: Some(self.current_head()),
+                change,
+            }],
+            Vec::new(),
+            state::StateDelta::empty(),
+        )
+        .with_sequence(seq);
+        let turn_id = record.turn_id.clone();
+
+        let mut writer = JournalWriter::new(self.storage.clone(), branch.clone())
+            .map_err(error::RuntimeError::Journal)?;
+        writer
+            .set_version(self.version.clone())
+            .map_err(error::RuntimeError::Journal)?;
+        writer.set_redactor(self.redactor.clone());
+        writer.set_spill_threshold(self.config.blobs.spill_threshold);
+        writer
+            .append(&record)
+            .map_err(error::RuntimeError::Journal)?;
+        self.turn_sinks.publish(&record);
+
+        self.branch_manager
+            .update_head(&branch, turn_id.clone())
+            .map_err(error::RuntimeError::Branch)?;
+        self.persist_branch_state()?;
+
+        Ok(turn_id)
+    }
+
+    /// Bring entity and reaction registrations in line with `target` on the
+    /// current branch, rolling back changes recorded here after it and
+    /// replaying those up to it. The side files are rewritten to match.
+    ///
+    /// Registrations the config journal never recorded, such as entities
+    /// spawned by turns, are left alone.
+    fn restore_config_at(&mut self, target: &TurnId) -> Result<()> {
+        let entries = self.config_history()?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let reader = JournalReader::new(self.storage.clone(), self.current_branch.clone())
+            .map_err(error::RuntimeError::Journal)?;
+        let positions: HashMap<TurnId, usize> = reader
+            .iter_headers()
+            .map_err(error::RuntimeError::Journal)?
+            .enumerate()
+            .map(|(index, header)| (header.turn_id, index + 1))
+            .collect();
+        let position = |turn: &TurnId| {
+            if *turn == TurnId::genesis() {
+                Some(0)
+            } else {
+                positions.get(turn).copied()
+            }
+        };
+        let limit = position(target).unwrap_or(0);
+        let state = config_history::ConfigState::replay(config_history::in_effect_at(
+            &entries,
+            &self.current_branch,
+            position,
+            limit,
+        ));
+        let (entities, reactions) = config_history::ConfigState::registered(&entries);
+
+        for entity_id in entities {
+            match state.entities.get(&entity_id) {
+                Some(wanted) => match self.entity_manager.get_mut(&entity_id) {
+                    Some(metadata) => metadata.branches = wanted.branches.clone(),
+                    None => self.entity_manager.register(wanted.clone()),
+                },
+                None => {
+                    self.entity_manager.unregister(&entity_id);
+                }
+            }
+        }
+
+        {
+            let mut store = self.reaction_store.write().unwrap();
+            for reaction_id in reactions {
+                match state.reactions.get(&reaction_id) {
+                    Some((actor, definition)) => store.insert(StoredReaction {
+                        reaction_id,
+                        actor: actor.clone(),
+                        definition: definition.clone(),
+                    }),
+                    None => {
+                        store.remove(&reaction_id);
+                    }
+                }
+            }
+        }
+
+        self.persist_entities()?;
+        self.persist_reactions()
+    }
+
+    /// Configuration changes recorded on the config branch, oldest first.
+    pub fn config_history(&self) -> Result<Vec<config_history::ConfigEntry>> {
+        let branch = BranchId::config();
+        if self.branch_manager.get_branch(&branch).is_none() {
+            return Ok(Vec::new());
+        }
+        Ok(config_history::changes_in(
+            &self.lineage_records(&branch, None)?,
+        ))
+    }
+
+    /// Run the reaction fixtures in `path`, each in a scratch runtime
+    /// configured like this one.
+    pub fn run_fixture(&self, path: impl AsRef<Path>) -> Result<fixture::FixtureReport> {
+        fixture::run_file(path.as_ref(), &self.config)
+    }
+
+    /// Bundle `branch`'s lineage, newest snapshot and entities as a checkpoint.
+    pub fn export_checkpoint(&self, branch: &BranchId) -> Result<artifact::Checkpoint> {
+        let records = self.lineage_records(branch, None)?;
+        let head = self
+            .branch_manager
+            .head(branch)
+            .cloned()
+            .unwrap_or_else(TurnId::genesis);
+        let snapshot = self
+            .snapshot_manager
+            .nearest_snapshot(branch, &head)?
+            .map(|count| self.snapshot_manager.load_by_count(branch, count))
+            .transpose()?;
+        let entities = self
+            .entity_manager
+            .list_on(branch)
+            .into_iter()
+            .cloned()
+            .collect();
+
+        Ok(artifact::Checkpoint {
+            config: artifact::CheckpointConfig {
+                branch: branch.clone(),
+                head,
+                turns: records.len(),
+                snapshot_turn: snapshot.as_ref().map(|snapshot| snapshot.turn_id.clone()),
+                version: self.version.clone(),
+                created_at: chrono::Utc::now(),
+            },
+            records,
+            snapshot,
+            entities,
+        })
+    }
+
+    /// Rebuild a checkpoint as the new root branch `name`.
+    ///
+    /// The checkpoint's whole lineage becomes the branch's own journal, so
+    /// the result does not depend on any branch of this runtime. Entities
+    /// not known here are registered on the new branch only. The current
+    /// branch is left unchanged.
+    pub fn import_checkpoint(
+        &mut self,
+        checkpoint: artifact::Checkpoint,
+        name: impl Into<String>,
+    ) -> Result<BranchId> {
+        let branch = BranchId::new(name);
+        self.config.branch_naming.check(&branch)?;
+        if branch.is_reserved()
+            || self.branch_manager.get_branch(&branch).is_some()
+            || self.branch_manager.archived(&branch).is_some()
+        {
+            return Err(error::RuntimeError::Branch(
+                error::BranchError::AlreadyExists(branch.to_string()),
+            ));
+        }
+        let head = checkpoint
+            .records
+            .last()
+            .map(|record| record.turn_id.clone())
+            .unwrap_or_else(TurnId::genesis);
+        if head != checkpoint.config.head {
+            return Err(error::RuntimeError::Artifact(format!(
+                "journal ends at {} but the checkpoint head is {}",
+                head, checkpoint.config.head
+            )));
+        }
+
+        self.branch_manager.ensure_root(branch.clone());
+        self.branch_manager.describe(
+            &branch,
+            branch::BranchDetails {
+                description: Some(format!(
+                    "Imported from {} at {}",
+                    checkpoint.config.branch, checkpoint.config.head
+                )),
+                creator: None,
+                tags: vec!["imported".to_string()],
+            },
+        )?;
+        std::fs::create_dir_all(self.storage.branch_snapshot_dir(&branch)).map_err(|e| {
             error::RuntimeError::Init(format!("Failed to create branch snapshot dir: {}", e))
         })?;
 
-        self.persist_branch_state()?;
+        let mut writer = JournalWriter::new(self.storage.clone(), branch.clone())?;
+        writer.set_version(checkpoint.config.version.clone())?;
+        writer.set_spill_threshold(self.config.blobs.spill_threshold);
+        for mut record in checkpoint.records {
+            record.branch = branch.clone();
+            writer.append(&record)?;
+        }
+        self.branch_manager.update_head(&branch, head)?;
+
+        if let Some(mut snapshot) = checkpoint.snapshot {
+            snapshot.branch = branch.clone();
+            self.snapshot_manager.save(&snapshot)?;
+        }
+
+        for mut metadata in checkpoint.entities {
+            match self.entity_manager.get_mut(&metadata.id) {
+                Some(existing) => {
+                    if let Some(branches) = existing.branches.as_mut() {
+                        branches.insert(branch.clone());
+                    }
+                }
+                None => {
+                    metadata.branches = Some(BTreeSet::from([branch.clone()]));
+                    self.entity_manager.register(metadata);
+                }
+            }
+        }
+        self.persist_entities()?;
+        self.persist_branch_state()?;
+
+        Ok(branch)
+    }
+
+    /// Move `branch` to cold storage.
+    ///
+    /// The branch is exported as a checkpoint into the directory artifact
+    /// store at `dest`, then its journal, snapshots and metadata are deleted
+    /// and a tombstone recording where the archive lives replaces it in the
+    /// branch state. The main and reserved branches, the current branch and
+    /// branches with forks of their own cannot be archived.
+    pub fn archive_branch(
+        &mut self,
+        branch: &BranchId,
+        dest: &Path,
+    ) -> Result<branch::ArchivedBranch> {
+        if *branch == BranchId::main() || branch.is_reserved() {
+            return Err(error::RuntimeError::Branch(
+                error::BranchError::ArchiveRefused(format!("'{}' is a built-in branch", branch)),
+            ));
+        }
+        if *branch == self.current_branch {
+            return Err(error::RuntimeError::Branch(
+                error::BranchError::ArchiveRefused(format!("'{}' is the current branch", branch)),
+            ));
+        }
+        let metadata = self
+            .branch_manager
+            .get_branch(branch)
+            .cloned()
+            .ok_or_else(|| {
+                error::RuntimeError::Branch(error::BranchError::NotFound(branch.to_string()))
+            })?;
+
+        let checkpoint = self.export_checkpoint(branch)?;
+        let store = artifact::DirectoryStore::new(dest);
+        let report = artifact::push(&store, &checkpoint, &archive_tag(branch))?;
+
+        let tombstone = branch::ArchivedBranch {
+            id: branch.clone(),
+            parent: metadata.parent,
+            base_turn: metadata.base_turn,
+            head_turn: metadata.head_turn,
+            details: metadata.details,
+            created_at: metadata.created_at,
+            archived_at: chrono::Utc::now(),
+            location: dest.display().to_string(),
+            manifest_digest: report.manifest_digest,
+            turns: checkpoint.config.turns,
+            size: report.size,
+        };
+        self.branch_manager.archive(tombstone.clone())?;
+        self.remove_branch_storage(branch)?;
+
+        Ok(tombstone)
+    }
+
+    /// Delete `branch` along with its journal, snapshots and metadata.
+    ///
+    /// The main and reserved branches, the current branch and branches with
+    /// forks of their own cannot be deleted. Neither can a branch whose head
+    /// was never merged into another branch, unless `force` is set. Returns
+    /// the metadata the branch had.
+    pub fn delete_branch(
+        &mut self,
+        branch: &BranchId,
+        force: bool,
+    ) -> Result<branch::BranchMetadata> {
+        let refuse =
+            |reason: String| error::RuntimeError::Branch(error::BranchError::DeleteRefused(reason));
+        if *branch == BranchId::main() || branch.is_reserved() {
+            return Err(refuse(format!("'{}' is a built-in branch", branch)));
+        }
+        if *branch == self.current_branch {
+            return Err(refuse(format!("'{}' is the current branch", branch)));
+        }
+        let metadata = self
+            .branch_manager
+            .get_branch(branch)
+            .cloned()
+            .ok_or_else(|| {
+                error::RuntimeError::Branch(error::BranchError::NotFound(branch.to_string()))
+            })?;
+        if let Some(child) = self
+            .branch_manager
+            .list_branches()
+            .into_iter()
+            .find(|other| other.parent.as_ref() == Some(branch))
+        {
+            return Err(refuse(format!(
+                "'{}' was forked from '{}'",
+                child.id, branch
+            )));
+        }
+        if !force && !self.branch_merged(&metadata)? {
+            return Err(refuse(format!(
+                "'{}' has turns that were never merged (use force to delete it anyway)",
+                branch
+            )));
+        }
+
+        self.branch_manager.remove(branch)?;
+        self.remove_branch_storage(branch)?;
+        tracing::debug!(branch = %branch, force, "branch deleted");
+        Ok(metadata)
+    }
+
+    /// Whether the head of `metadata`'s branch reached another branch through
+    /// a merge (or the branch recorded nothing since its fork).
+    ///
+    /// The head counts as merged when it is the source head recorded by a
+    /// merge turn, or one of that head's ancestors. Only record headers are read.
+    fn branch_merged(&self, metadata: &branch::BranchMetadata) -> Result<bool> {
+        if metadata.base_turn.as_ref() == Some(&metadata.head_turn) {
+            return Ok(true);
+        }
+        let mut merged_heads = Vec::new();
+        for other in self.branch_manager.list_branches() {
+            if other.id == metadata.id {
+                continue;
+            }
+            let reader = self.journal_reader(&other.id)?;
+            merged_heads.extend(
+                reader
+                    .iter_headers()?
+                    .filter_map(|header| header.merge_source)
+                    .filter(|(source, _)| *source == metadata.id)
+                    .map(|(_, head)| head),
+            );
+        }
+        if merged_heads.is_empty() {
+            return Ok(false);
+        }
+
+        let parents: HashMap<TurnId, Option<TurnId>> = self
+            .journal_reader(&metadata.id)?
+            .iter_headers()?
+            .map(|header| (header.turn_id, header.parent))
+            .collect();
+        for merged in merged_heads {
+            let mut cursor = Some(merged);
+            while let Some(turn) = cursor {
+                if turn == metadata.head_turn {
+                    return Ok(true);
+                }
+                cursor = parents.get(&turn).cloned().flatten();
+            }
+        }
+        Ok(false)
+    }
+
+    /// Fsync the current branch's journal segment and index.
+    ///
+    /// A barrier for the relaxed [`journal::DurabilityMode`]s: every turn
+    /// committed before the call survives a machine crash once it returns.
+    /// Returns the number of turns that were not yet synced.
+    pub fn flush(&mut self) -> Result<u64> {
+        let unsynced = self.journal_writer.unsynced();
+        self.journal_writer.flush()?;
+        Ok(unsynced)
+    }
+
+    /// Take a consistent backup of the storage root into `dest`.
+    ///
+    /// Buffered journal writes and in-memory metadata are flushed first, so
+    /// the backup holds exactly the state after the latest turn. Sealed
+    /// journal segments and snapshots are hard-linked where possible, which
+    /// keeps the pause between turns short. `dest` must be empty or missing
+    /// and must lie outside the storage root.
+    pub fn backup(&mut self, dest: &Path) -> Result<backup::BackupManifest> {
+        let source = self.storage.root().to_path_buf();
+        let refuse =
+            |reason: String| error::RuntimeError::Storage(StorageError::BackupRefused(reason));
+        let absolute = |path: &Path| std::path::absolute(path).map_err(StorageError::Io);
+        if absolute(dest)?.starts_with(absolute(&source)?) {
+            return Err(refuse(format!(
+                "{} is inside the storage root",
+                dest.display()
+            )));
+        }
+        if dest.exists()
+            && std::fs::read_dir(dest)
+                .map_err(StorageError::Io)?
+                .next()
+                .is_some()
+        {
+            return Err(refuse(format!("{} is not empty", dest.display())));
+        }
+
+        self.journal_writer.flush()?;
+        self.persist_entities()?;
+        self.persist_reactions()?;
+        self.persist_branch_state()?;
+
+        let files = backup::mirror(&source, dest).map_err(StorageError::Io)?;
+        let (mut linked_bytes, mut copied_bytes) = (0, 0);
+        for file in &files {
+            match file.method {
+                backup::BackupMethod::Linked => linked_bytes += file.bytes,
+                backup::BackupMethod::Copied => copied_bytes += file.bytes,
+            }
+        }
+        let manifest = backup::BackupManifest {
+            created_at: chrono::Utc::now(),
+            source,
+            dest: dest.to_path_buf(),
+            version: self.version.clone(),
+            current_branch: self.current_branch.clone(),
+            heads: self
+                .branch_manager
+                .list_branches()
+                .into_iter()
+                .map(|branch| (branch.id.clone(), branch.head_turn.clone()))
+                .collect(),
+            files,
+            linked_bytes,
+            copied_bytes,
+        };
+        self.storage.write_atomic(
+            &dest.join(backup::MANIFEST_FILE),
+            &serde_json::to_vec_pretty(&manifest).map_err(StorageError::Json)?,
+        )?;
+        Ok(manifest)
+    }
+
+    /// Drop `branch` from entity scopes and delete its journal, snapshots
+    /// and metadata once the branch manager no longer tracks it.
+    fn remove_branch_storage(&mut self, branch: &BranchId) -> Result<()> {
+        let ids: Vec<Uuid> = self
+            .entity_manager
+            .list()
+            .iter()
+            .filter(|metadata| {
+                metadata
+                    .branches
+                    .as_ref()
+                    .is_some_and(|branches| branches.contains(branch))
+            })
+            .map(|metadata| metadata.id)
+            .collect();
+        for id in ids {
+            if let Some(branches) = self
+                .entity_manager
+                .get_mut(&id)
+                .and_then(|metadata| metadata.branches.as_mut())
+            {
+                branches.remove(branch);
+            }
+        }
+        self.persist_entities()?;
+        self.persist_branch_state()?;
+
+        for (parent, dir) in [
+            (
+                self.storage.journal_dir(),
+                self.storage.branch_journal_dir(branch),
+            ),
+            (
+                self.storage.snapshots_dir(),
+                self.storage.branch_snapshot_dir(branch),
+            ),
+            (
+                self.storage.meta_dir(),
+                self.storage.branch_meta_dir(branch),
+            ),
+        ] {
+            remove_branch_files(&parent, &dir)?;
+        }
+        let index = self.storage.branch_index_path(branch);
+        if index.exists() {
+            let parent = index.parent().unwrap_or(&index);
+            contained_in(&self.storage.meta_dir(), parent)?;
+            std::fs::remove_file(&index).map_err(|e| {
+                error::RuntimeError::Init(format!("Failed to remove {}: {}", index.display(), e))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Restore an archived branch from cold storage under its original name.
+    ///
+    /// The archive is pulled by digest and rebuilt like an imported
+    /// checkpoint, so the restored branch is a root holding its whole
+    /// lineage; its head, history and details match the archived branch.
+    pub fn unarchive_branch(&mut self, branch: &BranchId) -> Result<BranchId> {
+        let tombstone = self.branch_manager.take_archived(branch).ok_or_else(|| {
+            error::RuntimeError::Branch(error::BranchError::NotFound(format!(
+                "archived branch '{}'",
+                branch
+            )))
+        })?;
+
+        let restored = artifact::pull(
+            &artifact::DirectoryStore::new(&tombstone.location),
+            &tombstone.manifest_digest,
+        )
+        .and_then(|checkpoint| self.import_checkpoint(checkpoint, branch.0.clone()));
+        let restored = match restored {
+            Ok(restored) => restored,
+            Err(err) => {
+                // Keep the tombstone so the archive stays reachable
+                self.branch_manager.insert_archived(tombstone);
+                return Err(err);
+            }
+        };
+
+        self.branch_manager.describe(&restored, tombstone.details)?;
+        self.persist_branch_state()?;
+        Ok(restored)
+    }
+
+    /// Run each variant on its own ephemeral fork of the current branch at
+    /// `base_turn` and compare the outcomes.
+    ///
+    /// Every variant starts from the state at `base_turn`, gets its inputs
+    /// enqueued and runs until no turn is ready or `options.max_turns` is
+    /// spent. The forks are discarded afterwards; with
+    /// [`ExperimentOptions::promote`](experiment::ExperimentOptions::promote)
+    /// the winning variant is replayed onto a fork under the requested name.
+    /// The runtime must be idle, and the current branch is left at the head
+    /// it had before the call.
+    pub fn experiment(
+        &mut self,
+        base_turn: TurnId,
+        variants: Vec<experiment::ExperimentVariant>,
+        options: experiment::ExperimentOptions,
+    ) -> Result<experiment::ExperimentReport> {
+        let origin = self.current_branch.clone();
+        let origin_head = self.current_head();
+        let queued = self.scheduler.pending_count();
+        if queued > 0 {
+            return Err(error::RuntimeError::Branch(
+                error::BranchError::ExperimentRefused(format!(
+                    "{} input(s) are queued on '{}'",
+                    queued, origin
+                )),
+            ));
+        }
+        self.journal_reader(&origin)?
+            .header(&base_turn)
+            .map_err(|_| {
+                error::RuntimeError::Branch(error::BranchError::InvalidForkPoint(
+                    base_turn.to_string(),
+                ))
+            })?;
+
+        let outcome = self.run_experiment(&origin, &base_turn, &variants, &options);
+
+        // Put the origin back where it was, whatever happened to the variants
+        if self.current_branch != origin {
+            self.enter_branch(origin.clone())?;
+        }
+        self.goto(origin_head)?;
+
+        let (variants, promoted) = outcome?;
+        Ok(experiment::ExperimentReport {
+            origin,
+            base_turn,
+            variants,
+            promoted,
+        })
+    }
+
+    fn run_experiment(
+        &mut self,
+        origin: &BranchId,
+        base_turn: &TurnId,
+        variants: &[experiment::ExperimentVariant],
+        options: &experiment::ExperimentOptions,
+    ) -> Result<(
+        Vec<experiment::VariantOutcome>,
+        Option<experiment::PromotedVariant>,
+    )> {
+        let mut outcomes = Vec::with_capacity(variants.len());
+        for (index, variant) in variants.iter().enumerate() {
+            let mut suffix = index;
+            let ephemeral = loop {
+                let name = BranchId::new(format!(
+                    "{}{}",
+                    experiment::EXPERIMENT_BRANCH_PREFIX,
+                    suffix
+                ));
+                if self.branch_manager.get_branch(&name).is_none() {
+                    break name;
+                }
+                suffix += variants.len();
+            };
+
+            let run = self.run_variant(
+                origin,
+                base_turn,
+                index,
+                variant,
+                options.max_turns,
+                ephemeral.clone(),
+                branch::BranchDetails::default(),
+            );
+            if self.current_branch != *origin {
+                self.enter_branch(origin.clone())?;
+            }
+            if self.branch_manager.remove(&ephemeral).is_ok() {
+                self.remove_branch_storage(&ephemeral)?;
+            }
+            outcomes.push(run?.0);
+        }
+
+        let Some(promotion) = &options.promote else {
+            return Ok((outcomes, None));
+        };
+        let Some(index) = (promotion.pick)(&outcomes).filter(|index| *index < variants.len())
+        else {
+            return Ok((outcomes, None));
+        };
+        self.config.branch_naming.check(&promotion.branch)?;
+        let (_, head) = self.run_variant(
+            origin,
+            base_turn,
+            index,
+            &variants[index],
+            options.max_turns,
+            promotion.branch.clone(),
+            promotion.details.clone(),
+        )?;
+        Ok((
+            outcomes,
+            Some(experiment::PromotedVariant {
+                index,
+                branch: promotion.branch.clone(),
+                head,
+            }),
+        ))
+    }
+
+    /// Fork `branch` from `origin` at `base_turn`, run `variant` on it and
+    /// describe the result; the fork is left current.
+    #[allow(clippy::too_many_arguments)]
+    fn run_variant(
+        &mut self,
+        origin: &BranchId,
+        base_turn: &TurnId,
+        index: usize,
+        variant: &experiment::ExperimentVariant,
+        max_turns: usize,
+        branch: BranchId,
+        details: branch::BranchDetails,
+    ) -> Result<(experiment::VariantOutcome, TurnId)> {
+        if self.current_branch != *origin {
+            self.enter_branch(origin.clone())?;
+        }
+        self.goto(base_turn.clone())?;
+        let before = self.live_assertions();
+
+        self.fork_branch(branch.clone(), Some(base_turn.clone()), details)?;
+        self.enter_branch(branch)?;
+        for (actor, input) in &variant.inputs {
+            self.scheduler
+                .enqueue(actor.clone(), input.clone(), ScheduleCause::External);
+        }
+
+        let mut records = Vec::new();
+        let mut warnings = Vec::new();
+        let mut quiescent = false;
+        for _ in 0..max_turns {
+            self.poll_async_messages();
+            match self.execute_turn() {
+                Ok(Some(record)) => records.push(record),
+                Ok(None) => {
+                    quiescent = true;
+                    break;
+                }
+                Err(err) => warnings.push(format!("turn failed: {}", err)),
+            }
+        }
+        if !quiescent {
+            warnings.push(format!(
+                "stopped after {} turn(s) with {} input(s) still queued",
+                max_turns,
+                self.scheduler.pending_count()
+            ));
+        }
+        for effect in effects::effects_in(&records) {
+            warnings.push(format!(
+                "turn {} left a {} effect on {}: {}",
+                effect.turn_id, effect.kind, effect.target, effect.description
+            ));
+        }
+
+        let after = self.live_assertions();
+        let change = |(actor, handle): &(ActorId, Uuid), value: &preserves::IOValue| {
+            experiment::AssertionChange {
+                actor: actor.clone(),
+                handle: Handle(*handle),
+                value: value.clone(),
+            }
+        };
+        let asserted = after
+            .iter()
+            .filter(|(key, _)| !before.contains_key(key))
+            .map(|(key, value)| change(key, value))
+            .collect();
+        let retracted = before
+            .iter()
+            .filter(|(key, _)| !after.contains_key(key))
+            .map(|(key, value)| change(key, value))
+            .collect();
+
+        Ok((
+            experiment::VariantOutcome {
+                index,
+                label: variant.label.clone(),
+                turns: records.into_iter().map(|record| record.turn_id).collect(),
+                quiescent,
+                asserted,
+                retracted,
+                warnings,
+            },
+            self.current_head(),
+        ))
+    }
+
+    /// Every live assertion across actors, keyed by owner and handle.
+    fn live_assertions(&self) -> BTreeMap<(ActorId, Uuid), preserves::IOValue> {
+        let mut live = BTreeMap::new();
+        for actor in self.actors.values() {
+            let assertions = actor.assertions.read();
+            for ((owner, handle), (value, _version)) in &assertions.active {
+                live.insert((owner.clone(), handle.0), value.clone());
+            }
+        }
+        live
+    }
+
+    /// Repair the current branch's journal and reopen its writer on the clean tail.
+    fn reopen_journal(&mut self) -> Result<()> {
+        let branch = self.current_branch.clone();
+        let journal_reader = JournalReader::new(self.storage.clone(), branch.clone())
+            .unwrap_or_else(|_| JournalReader::new_empty(self.storage.clone(), branch.clone()));
+
+        journal_reader
+            .validate_and_repair()
+            .map_err(|e| error::RuntimeError::Init(format!("Journal validation failed: {}", e)))?;
+
+        let clean_index = journal_reader
+            .rebuild_index()
+            .map_err(|e| error::RuntimeError::Init(format!("Index rebuild failed: {}", e)))?;
+
+        let index_path = self.storage.branch_meta_dir(&branch).join("journal.index");
+        std::fs::create_dir_all(self.storage.branch_meta_dir(&branch))
+            .map_err(|e| error::RuntimeError::Init(format!("Failed to create meta dir: {}", e)))?;
+        clean_index
+            .save(&index_path)
+            .map_err(|e| error::RuntimeError::Init(format!("Failed to save index: {}", e)))?;
+
+        self.journal_writer =
+            JournalWriter::new_with_index(self.storage.clone(), branch.clone(), clean_index)
+                .map_err(|e| {
+                    error::RuntimeError::Init(format!("Failed to create journal writer: {}", e))
+                })?;
+        self.journal_writer
+            .set_version(self.version.clone())
+            .map_err(error::RuntimeError::Journal)?;
+        self.journal_writer.set_redactor(self.redactor.clone());
+        self.journal_writer
+            .set_spill_threshold(self.config.blobs.spill_threshold);
+        self.journal_writer.set_durability(self.config.durability);
+
+        Ok(())
+    }
+
+    /// Version stamped on journal segments and snapshots written by this runtime.
+    pub fn version(&self) -> &version::VersionStamp {
+        &self.version
+    }
+
+    /// Apply the configured [`version::VersionPolicy`] to a replay up to `target`.
+    ///
+    /// Checks the snapshot the replay starts from and the header of every
+    /// journal segment between it and `target`. Segments written before
+    /// headers existed carry no version and are not checked.
+    fn check_replay_versions(
+        &self,
+        snapshot: Option<&snapshot::SnapshotMetadata>,
+        target: &TurnId,
+    ) -> Result<()> {
+        let policy = self.config.version_policy;
+        if policy == version::VersionPolicy::Ignore {
+            return Ok(());
+        }
+
+        if let Some(stamp) = snapshot.and_then(|s| s.version.as_ref()) {
+            let turn_count = snapshot.map_or(0, |s| s.turn_count);
+            policy.check(
+                &format!("snapshot at turn {}", turn_count),
+                stamp,
+                &self.version,
+            )?;
+        }
+
+        let journal_reader = JournalReader::new(self.storage.clone(), self.current_branch.clone())
+            .map_err(error::RuntimeError::Journal)?;
+        let first = snapshot
+            .and_then(|s| journal_reader.segment_of(&s.turn_id))
+            .unwrap_or(0);
+        let last = journal_reader.segment_of(target).unwrap_or(u64::MAX);
+        for (segment, header) in journal_reader
+            .segment_headers()
+            .map_err(error::RuntimeError::Journal)?
+        {
+            if (first..=last).contains(&segment) {
+                policy.check(
+                    &format!("journal segment {}", segment),
+                    &header.version,
+                    &self.version,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Entities currently hydrated as recorder stubs.
+    pub fn stubbed_entities(&self) -> Vec<uuid::Uuid> {
+        let mut stubbed = Vec::new();
+        for actor in self.actors.values() {
+            for entries in actor.entities.read().values() {
+                stubbed.extend(
+                    entries
+                        .iter()
+                        .filter(|entry| {
+                            (entry.entity.as_ref() as &dyn Any).is::<stub::RecorderStub>()
+                        })
+                        .map(|entry| entry.id),
+                );
+            }
+        }
+        stubbed.sort();
+        stubbed
+    }
+
+    /// Private state of every live entity whose type supports hydration.
+    fn live_entity_states(&self) -> Vec<snapshot::EntityStateSnapshot> {
+        let mut entity_states = Vec::new();
+        for (actor_id, actor) in self.actors.iter() {
+            let entities = actor.entities.read();
+            for (facet_id, entries) in entities.iter() {
+                for entry in entries.iter() {
+                    let state = match (entry.entity.as_ref() as &dyn Any)
+                        .downcast_ref::<stub::RecorderStub>()
+                    {
+                        Some(stub) => stub.state().cloned(),
+                        None => self
+                            .entity_registry
+                            .snapshot_entity(&entry.entity_type, entry.entity.as_ref()),
+                    };
+                    if let Some(state) = state {
+                        entity_states.push(snapshot::EntityStateSnapshot {
+                            entity_id: entry.id,
+                            actor: actor_id.clone(),
+                            facet: facet_id.clone(),
+                            entity_type: entry.entity_type.clone(),
+                            state,
+                        });
+                    }
+                }
+            }
+        }
+        entity_states
+    }
+
+    /// Current private state of one live entity, as the registry's snapshot
+    /// hook reports it.
+    ///
+    /// Returns `None` when the entity's type does not support hydration. The
+    /// state is redacted like snapshot state.
+    pub fn entity_state(&self, entity_id: Uuid) -> Result<Option<preserves::IOValue>> {
+        let (actor, facet) = self.live_entity_location(entity_id)?;
+        let entities = self.actors[&actor].entities.read();
+        let entry = entities
+            .get(&facet)
+            .and_then(|entries| entries.iter().find(|entry| entry.id == entity_id))
+            .ok_or_else(|| Self::entity_not_found(entity_id))?;
+        let state = match (entry.entity.as_ref() as &dyn Any).downcast_ref::<stub::RecorderStub>() {
+            Some(stub) => stub.state().cloned(),
+            None => self
+                .entity_registry
+                .snapshot_entity(&entry.entity_type, entry.entity.as_ref()),
+        };
+        Ok(state.map(|state| self.redactor.redact_value(&state)))
+    }
+
+    /// Replace the private state of one live entity through the registry's
+    /// restore hook.
+    ///
+    /// This is a repair tool: the change is not a turn, so a fresh snapshot
+    /// is written to make it survive restarts. Time travel to earlier turns
+    /// still rebuilds the state those turns produced.
+    pub fn set_entity_state(&mut self, entity_id: Uuid, state: &preserves::IOValue) -> Result<()> {
+        let (actor, facet) = self.live_entity_location(entity_id)?;
+        {
+            let mut entities = self.actors[&actor].entities.write();
+            let entry = entities
+                .get_mut(&facet)
+                .and_then(|entries| entries.iter_mut().find(|entry| entry.id == entity_id))
+                .ok_or_else(|| Self::entity_not_found(entity_id))?;
+            let restored = self.entity_registry.restore_entity(
+                &entry.entity_type,
+                entry.entity.as_mut(),
+                state,
+            )?;
+            if !restored {
+                return Err(error::RuntimeError::Actor(
+                    error::ActorError::InvalidActivation(format!(
+                        "Entity type {} does not support state restore",
+                        entry.entity_type
+                    )),
+                ));
+            }
+        }
+        self.create_snapshot()
+    }
+
+    /// Actor and facet hosting a live entity on the current branch.
+    fn live_entity_location(&self, entity_id: Uuid) -> Result<(turn::ActorId, turn::FacetId)> {
+        self.entity_manager
+            .get(&entity_id)
+            .filter(|meta| meta.visible_on(&self.current_branch))
+            .filter(|meta| self.actors.contains_key(&meta.actor))
+            .map(|meta| (meta.actor.clone(), meta.facet.clone()))
+            .ok_or_else(|| Self::entity_not_found(entity_id))
+    }
+
+    fn entity_not_found(entity_id: Uuid) -> error::RuntimeError {
+        error::RuntimeError::Actor(ActorError::NotFound(format!("Entity {}", entity_id)))
+    }
+
+    /// Export `actor`'s state on the current branch after `turn` (or at the
+    /// head) as a single Preserves document.
+    ///
+    /// Assertions, facets and capabilities are replayed from the journal, so
+    /// the result does not depend on where the runtime currently stands.
+    /// Entity private state is only kept live for the head; for earlier turns
+    /// it comes from the nearest snapshot at or before `turn`, if any. The
+    /// document has the shape
+    ///
+    /// ```text
+    /// <actor-state actor turn
+    ///   #{<assertion handle value namespace> ...}
+    ///   [<facet id status [child ...]> ...]
+    ///   #{capability ...}
+    ///   {entity-id: <entity type facet state> ...}>
+    /// ```
+    ///
+    /// Sets and dictionaries are ordered canonically and facet children are
+    /// sorted by id, so exporting the same state twice yields equal values.
+    pub fn export_actor_state(
+        &self,
+        actor: &turn::ActorId,
+        turn: Option<&TurnId>,
+    ) -> Result<preserves::IOValue> {
+        use preserves::IOValue;
+
+        let head = self.current_head();
+        let turn_id = turn.cloned().unwrap_or_else(|| head.clone());
+
+        let mut assertions = state::AssertionSet::new();
+        let mut facets = state::FacetMap::new();
+        let mut capabilities = state::CapabilityMap::new();
+        if turn_id != TurnId::genesis() {
+            for record in self.lineage_records(&self.current_branch, Some(&turn_id))? {
+                assertions.apply(&record.delta.assertions);
+                facets.apply(&record.delta.facets);
+                capabilities.apply(&record.delta.capabilities);
+            }
+        }
+
+        let entity_states = if turn_id == head {
+            self.live_entity_states()
+        } else {
+            self.snapshot_manager
+                .nearest_snapshot(&self.current_branch, &turn_id)
+                .map_err(error::RuntimeError::Snapshot)?
+                .map(|count| {
+                    self.snapshot_manager
+                        .entity_states_by_count(&self.current_branch, count)
+                        .map_err(error::RuntimeError::Snapshot)
+                })
+                .transpose()?
+                .unwrap_or_default()
+        };
+
+        let assertion_values: preserves::Set<IOValue> = assertions
+            .active
+            .iter()
+            .filter(|((owner, _), _)| owner == actor)
+            .map(|((owner, handle), (value, _version))| {
+                let namespace = assertions
+                    .namespaces
+                    .get(&(owner.clone(), handle.clone()))
+                    .map(String::as_str)
+                    .unwrap_or(state::DEFAULT_NAMESPACE);
+                IOValue::record(
+                    IOValue::symbol("assertion"),
+                    vec![
+                        IOValue::new(handle.to_string()),
+                        value.clone(),
+                        IOValue::new(namespace.to_string()),
+                    ],
+                )
+            })
+            .collect();
+
+        let owned: Vec<&FacetMetadata> = facets
+            .facets
+            .values()
+            .filter(|metadata| &metadata.actor == actor)
+            .collect();
+        fn facet_tree(metadata: &FacetMetadata, owned: &[&FacetMetadata]) -> IOValue {
+            let mut children: Vec<&&FacetMetadata> = owned
+                .iter()
+                .filter(|child| child.parent.as_ref() == Some(&metadata.id))
+                .collect();
+            children.sort_by_key(|child| child.id.0);
+            IOValue::record(
+                IOValue::symbol("facet"),
+                vec![
+                    IOValue::new(metadata.id.0.to_string()),
+                    IOValue::symbol(match metadata.status {
+                        FacetStatus::Alive => "alive",
+                        FacetStatus::Terminated => "terminated",
+                        FacetStatus::Removed => "removed",
+                    }),
+                    IOValue::new(
+                        children
+                            .into_iter()
+                            .map(|child| facet_tree(child, owned))
+                            .collect::<Vec<_>>(),
+                    ),
+                ],
+            )
+        }
+        let mut roots: Vec<&&FacetMetadata> = owned
+            .iter()
+            .filter(|metadata| {
+                metadata
+                    .parent
+                    .as_ref()
+                    .is_none_or(|parent| !owned.iter().any(|other| &other.id == parent))
+            })
+            .collect();
+        roots.sort_by_key(|metadata| metadata.id.0);
+        let facet_values: Vec<IOValue> = roots
+            .into_iter()
+            .map(|metadata| facet_tree(metadata, &owned))
+            .collect();
+
+        let capability_values: preserves::Set<IOValue> = capabilities
+            .capabilities
+            .values()
+            .filter(|metadata| &metadata.issuer == actor || &metadata.holder == actor)
+            .map(preserves::serde::to_value)
+            .collect();
+
+        let entity_values: preserves::Map<IOValue, IOValue> = entity_states
+            .into_iter()
+            .filter(|entry| &entry.actor == actor)
+            .map(|entry| {
+                (
+                    IOValue::new(entry.entity_id.to_string()),
+                    IOValue::record(
+                        IOValue::symbol("entity"),
+                        vec![
+                            IOValue::new(entry.entity_type),
+                            IOValue::new(entry.facet.0.to_string()),
+                            entry.state,
+                        ],
+                    ),
+                )
+            })
+            .collect();
+
+        Ok(IOValue::record(
+            IOValue::symbol("actor-state"),
+            vec![
+                IOValue::new(actor.to_string()),
+                IOValue::new(turn_id.to_string()),
+                IOValue::new(assertion_values),
+                IOValue::new(facet_values),
+                IOValue::new(capability_values),
+                IOValue::new(entity_values),
+            ],
+        ))
+    }
+
+    /// Head of the current branch, or genesis before its first turn.
+    fn current_head(&self) -> TurnId {
+        self.branch_manager
+            .head(&self.current_branch)
+            .cloned()
+            .unwrap_or_else(TurnId::genesis)
+    }
+
+    /// Let entities reconcile external state after a history jump.
+    fn notify_time_travel(&self, old_head: &TurnId, new_head: &TurnId) {
+        for (actor_id, actor) in &self.actors {
+            for (entity_id, err) in actor.notify_time_travel(old_head, new_head) {
+                warn!(
+                    "on_time_travel failed for entity {} on actor {}: {}",
+                    entity_id, actor_id, err
+                );
+            }
+        }
+    }
+
+    /// Check every snapshot of `branch` against state replayed from the journal.
+    ///
+    /// Each snapshot's assertions, facets and capabilities are compared by
+    /// [`snapshot::state_hash`] with the state obtained by replaying the
+    /// branch lineage up to the snapshot's turn. Unreadable snapshots and
+    /// snapshots whose turn is missing from the journal are reported as
+    /// mismatches too, since [`Runtime::goto`] would trust them all the same.
+    pub fn verify_snapshots(&self, branch: &BranchId) -> Result<snapshot::SnapshotVerification> {
+        if self.branch_manager.get_branch(branch).is_none() {
+            return Err(error::RuntimeError::Branch(error::BranchError::NotFound(
+                branch.to_string(),
+            )));
+        }
+
+        let entries = self.snapshot_manager.list(branch);
+        let mut mismatches = Vec::new();
+        for entry in &entries {
+            let mismatch = |stored_hash, derived_hash, reason: String| snapshot::SnapshotMismatch {
+                turn_count: entry.turn_count,
+                turn_id: entry.turn_id.clone(),
+                stored_hash,
+                derived_hash,
+                reason,
+            };
+
+            let stored = match self
+                .snapshot_manager
+                .load_by_count(branch, entry.turn_count)
+            {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    mismatches.push(mismatch(None, None, format!("unreadable: {}", err)));
+                    continue;
+                }
+            };
+            let stored_hash =
+                snapshot::state_hash(&stored.assertions, &stored.facets, &stored.capabilities);
+
+            let mut assertions = state::AssertionSet::new();
+            let mut facets = state::FacetMap::new();
+            let mut capabilities = state::CapabilityMap::new();
+            if stored.turn_id != TurnId::genesis() {
+                let records = match self.lineage_records(branch, Some(&stored.turn_id)) {
+                    Ok(records) => records,
+                    Err(err) => {
+                        mismatches.push(mismatch(
+                            Some(stored_hash),
+                            None,
+                            format!("cannot replay to snapshot turn: {}", err),
+                        ));
+                        continue;
+                    }
+                };
+                for record in &records {
+                    assertions.apply(&record.delta.assertions);
+                    facets.apply(&record.delta.facets);
+                    capabilities.apply(&record.delta.capabilities);
+                }
+            }
+            let derived_hash = snapshot::state_hash(&assertions, &facets, &capabilities);
+
+            if stored_hash != derived_hash {
+                mismatches.push(mismatch(
+                    Some(stored_hash),
+                    Some(derived_hash),
+                    "stored state differs from journal replay".to_string(),
+                ));
+            }
+        }
+
+        Ok(snapshot::SnapshotVerification {
+            branch: branch.clone(),
+            checked: entries.len(),
+            mismatches,
+        })
+    }
 
-        Ok(new_branch)
+    /// Check that every turn of `branch` was committed in canonical order.
+    ///
+    /// Turns recorded under [`ordering::OutputOrder::Emission`], or by a
+    /// runtime that predates canonical ordering, are reported; replaying them
+    /// is only stable if their entities emit outputs deterministically.
+    pub fn verify_replay(&self, branch: &BranchId) -> Result<ordering::ReplayVerification> {
+        let records = self.lineage_records(branch, None)?;
+        let unordered = records
+            .iter()
+            .filter(|record| !ordering::is_canonical(&record.outputs, &record.delta))
+            .map(|record| record.turn_id.clone())
+            .collect();
+        Ok(ordering::ReplayVerification {
+            checked: records.len(),
+            unordered,
+        })
     }
 
-    /// Switch to a different branch
-    pub fn switch_branch(&mut self, branch: BranchId) -> Result<()> {
-        // Verify branch exists
-        self.branch_manager
-            .switch_branch(branch.clone())
-            .map_err(|e| error::RuntimeError::Branch(e))?;
+    /// Train a compression dictionary on recent journal records and enable it.
+    ///
+    /// Samples the most recent `options.max_samples` records across all
+    /// branches. Records appended afterwards are compressed with the
+    /// dictionary; existing segments are left as they are.
+    pub fn train_journal_dictionary(
+        &mut self,
+        options: &compression::TrainingOptions,
+    ) -> Result<compression::DictionaryInfo> {
+        let mut samples = std::collections::VecDeque::with_capacity(options.max_samples);
+        for meta in self.branch_manager.list_branches() {
+            let Ok(reader) = JournalReader::new(self.storage.clone(), meta.id.clone()) else {
+                continue;
+            };
+            for record in reader.iter_all()? {
+                let encoded = record?
+                    .encode()
+                    .map_err(|e| error::JournalError::EncodingError(e.to_string()))?;
+                if samples.len() == options.max_samples {
+                    samples.pop_front();
+                }
+                samples.push_back(encoded[4..].to_vec());
+            }
+        }
 
-        // Update runtime state
-        self.current_branch = branch.clone();
+        let samples: Vec<Vec<u8>> = samples.into();
+        let (info, bytes) = compression::train(&samples, options)?;
+        let dictionary = compression::install(&self.storage, info.clone(), &bytes)?;
+        self.journal_writer.set_dictionary(Some(dictionary));
+        Ok(info)
+    }
 
-        // Reinitialize journal writer for new branch
-        let journal_reader = JournalReader::new(self.storage.clone(), branch.clone())
-            .unwrap_or_else(|_| JournalReader::new_empty(self.storage.clone(), branch.clone()));
+    /// Stop compressing new journal records. Compressed records stay readable.
+    pub fn disable_journal_compression(&mut self) -> Result<()> {
+        compression::deactivate(&self.storage)?;
+        self.journal_writer.set_dictionary(None);
+        Ok(())
+    }
 
-        journal_reader
-            .validate_and_repair()
-            .map_err(|e| error::RuntimeError::Init(format!("Journal validation failed: {}", e)))?;
+    /// The dictionary new journal records are compressed with, if any.
+    pub fn journal_dictionary(&self) -> Result<Option<compression::DictionaryInfo>> {
+        Ok(compression::active(&self.storage)?.map(|dictionary| dictionary.info().clone()))
+    }
 
-        let clean_index = journal_reader
-            .rebuild_index()
-            .map_err(|e| error::RuntimeError::Init(format!("Index rebuild failed: {}", e)))?;
+    /// Compact `branch`'s journal under `policy` (the configured retention
+    /// policy when `None`).
+    ///
+    /// The horizon is the newest turn of the branch outside the policy. It
+    /// never reaches the head or the fork point of a branch forked from this
+    /// one, whose history still reads through this journal. The state at the
+    /// horizon is written as a consolidated snapshot, then records up to the
+    /// horizon and older snapshots are deleted; time travel behind the
+    /// horizon fails with [`JournalError::Compacted`](error::JournalError::Compacted).
+    ///
+    /// Turns are compared by their position in the journal, so journals with
+    /// legacy turn ids compact the same way.
+    pub fn compact_journal(
+        &mut self,
+        branch: &BranchId,
+        policy: Option<&journal::compactor::RetentionPolicy>,
+    ) -> Result<journal::compactor::CompactionReport> {
+        use journal::compactor::{self, CompactionReport};
+
+        let policy = *policy.unwrap_or(&self.config.retention);
+        let Some(meta) = self.branch_manager.get_branch(branch) else {
+            return Err(error::RuntimeError::Branch(error::BranchError::NotFound(
+                branch.to_string(),
+            )));
+        };
+        let head = meta.head_turn.clone();
+        let compacted_through = meta.compacted_through.clone();
 
-        let index_path = self.storage.branch_meta_dir(&branch).join("journal.index");
-        std::fs::create_dir_all(self.storage.branch_meta_dir(&branch))
-            .map_err(|e| error::RuntimeError::Init(format!("Failed to create meta dir: {}", e)))?;
-        clean_index
-            .save(&index_path)
-            .map_err(|e| error::RuntimeError::Init(format!("Failed to save index: {}", e)))?;
+        // The trimmed segments must not lose turns the writer has not synced
+        if *branch == self.current_branch {
+            self.journal_writer.sync_pending()?;
+        }
 
-        self.journal_writer =
-            JournalWriter::new_with_index(self.storage.clone(), branch.clone(), clean_index)
-                .map_err(|e| {
-                    error::RuntimeError::Init(format!("Failed to create journal writer: {}", e))
-                })?;
+        let reader = JournalReader::new(self.storage.clone(), branch.clone())?;
+        let headers: Vec<journal::RecordHeader> = reader.iter_headers()?.collect();
+        let now = chrono::Utc::now();
+        let positions: HashMap<&TurnId, usize> = headers
+            .iter()
+            .enumerate()
+            .map(|(position, header)| (&header.turn_id, position))
+            .collect();
+        let outside_policy = |position: usize| {
+            let header = &headers[position];
+            header.turn_id != head
+                && !policy.retains(headers.len() - 1 - position, header.timestamp, now)
+        };
+
+        // Newest eligible turn, ignoring forks, then the newest one below
+        // every fork point. A fork point missing from the journal lies
+        // before all of it.
+        let eligible: Vec<usize> = (0..headers.len())
+            .rev()
+            .filter(|position| outside_policy(*position))
+            .collect();
+        let forks: Vec<(BranchId, Option<usize>)> = self
+            .branch_manager
+            .list_branches()
+            .into_iter()
+            .filter(|child| child.parent.as_ref() == Some(branch))
+            .filter_map(|child| {
+                let base = child.base_turn.as_ref()?;
+                Some((child.id.clone(), positions.get(base).copied()))
+            })
+            .collect();
+        let held_back_by: Vec<BranchId> = eligible
+            .first()
+            .map(|newest| {
+                forks
+                    .iter()
+                    .filter(|(_, base)| base.is_none_or(|base| base <= *newest))
+                    .map(|(child, _)| child.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let horizon = eligible.into_iter().find(|position| {
+            forks
+                .iter()
+                .all(|(_, base)| base.is_some_and(|base| *position < base))
+        });
 
+        let Some(horizon) = horizon else {
+            return Ok(CompactionReport::unchanged(
+                branch.clone(),
+                headers.len(),
+                held_back_by,
+            ));
+        };
+        let snapshot = self.consolidate_snapshot(
+            branch,
+            &reader,
+            &positions,
+            compacted_through.as_ref(),
+            horizon,
+        )?;
+        let removed = horizon + 1;
+        let keep_from = headers
+            .get(removed)
+            .map(|header| (header.segment, header.offset));
+
+        // Mark first: a crash before the swap leaves a full journal behind a horizon
+        self.branch_manager
+            .mark_compacted(branch, snapshot.turn_id.clone())?;
         self.persist_branch_state()?;
+        let stats = compactor::trim(&self.storage, branch, keep_from)?;
+        let snapshots_removed = self
+            .snapshot_manager
+            .prune_before(branch, snapshot.turn_count)?;
+        if *branch == self.current_branch {
+            self.reopen_journal()?;
+        }
 
-        Ok(())
+        Ok(CompactionReport {
+            branch: branch.clone(),
+            horizon: Some(snapshot.turn_id),
+            turns_removed: removed,
+            turns_kept: headers.len() - removed,
+            segments_before: stats.segments_before,
+            segments_after: stats.segments_after,
+            bytes_before: stats.bytes_before,
+            bytes_after: stats.bytes_after,
+            snapshots_removed,
+            held_back_by,
+        })
+    }
+
+    /// Snapshot of `branch` at the record at journal `position`, reusing one
+    /// taken there or consolidating the newest earlier snapshot (or the
+    /// compaction horizon's) with the records that follow it.
+    ///
+    /// Entity private state is carried over from the earlier snapshot, as
+    /// replaying to the same turn would.
+    fn consolidate_snapshot(
+        &self,
+        branch: &BranchId,
+        reader: &JournalReader,
+        positions: &HashMap<&TurnId, usize>,
+        compacted_through: Option<&TurnId>,
+        position: usize,
+    ) -> Result<snapshot::SnapshotIndexEntry> {
+        use snapshot::{RuntimeSnapshot, SnapshotIndexEntry};
+        use state::{AssertionSet, CapabilityMap, FacetMap};
+
+        // Snapshots order by turn count, so the first match is the newest;
+        // the compaction horizon's snapshot lies before the whole journal
+        let base = self
+            .snapshot_manager
+            .list(branch)
+            .into_iter()
+            .rev()
+            .find_map(|entry| {
+                if compacted_through == Some(&entry.turn_id) {
+                    return Some((entry, 0));
+                }
+                let at = *positions.get(&entry.turn_id)?;
+                (at <= position).then_some((entry, at + 1))
+            });
+        let (mut assertions, mut facets, mut capabilities, entity_states, base_count, from) =
+            match base {
+                Some((entry, from)) if from == position + 1 => return Ok(entry),
+                Some((entry, from)) => {
+                    let snapshot = self
+                        .snapshot_manager
+                        .load_by_count(branch, entry.turn_count)?;
+                    (
+                        snapshot.assertions,
+                        snapshot.facets,
+                        snapshot.capabilities,
+                        snapshot.entity_states,
+                        entry.turn_count,
+                        from,
+                    )
+                }
+                None => (
+                    AssertionSet::new(),
+                    FacetMap::new(),
+                    CapabilityMap::new(),
+                    Vec::new(),
+                    0,
+                    0,
+                ),
+            };
+
+        let mut turn_id = TurnId::genesis();
+        for record in reader.iter_all()?.skip(from).take(position + 1 - from) {
+            let record = record?;
+            assertions.apply(&record.delta.assertions);
+            facets.apply(&record.delta.facets);
+            capabilities.apply(&record.delta.capabilities);
+            turn_id = record.turn_id;
+        }
+
+        let turn_count = base_count + (position + 1 - from) as u64;
+        self.snapshot_manager.save(&RuntimeSnapshot {
+            branch: branch.clone(),
+            turn_id: turn_id.clone(),
+            assertions,
+            facets,
+            capabilities,
+            entity_states,
+            metadata: snapshot::SnapshotMetadata {
+                created_at: chrono::Utc::now(),
+                turn_count,
+                turn_id: turn_id.clone(),
+                version: Some(self.version.clone()),
+            },
+        })?;
+        Ok(SnapshotIndexEntry {
+            turn_id,
+            turn_count,
+            tags: Vec::new(),
+        })
+    }
+
+    /// Run the runtime's environment checks: storage, journals and entity factories.
+    ///
+    /// Nothing is journaled; the storage probe removes the file it writes.
+    pub fn doctor(&self) -> doctor::DoctorReport {
+        let mut report = doctor::DoctorReport::default();
+
+        let probe = self.storage.meta_dir().join(".doctor-probe");
+        report.checks.push(
+            match self
+                .storage
+                .write_atomic(&probe, b"probe")
+                .and_then(|()| std::fs::remove_file(&probe).map_err(Into::into))
+            {
+                Ok(()) => doctor::DoctorCheck::pass(
+                    "storage.writable",
+                    format!("{} is writable", self.storage.root().display()),
+                ),
+                Err(err) => doctor::DoctorCheck::fail(
+                    "storage.writable",
+                    format!(
+                        "cannot write to {}: {err}",
+                        self.storage.meta_dir().display()
+                    ),
+                ),
+            },
+        );
+
+        // Quick check: every branch head must still decode from its segment
+        let mut unreadable = Vec::new();
+        let mut checked = 0;
+        for meta in self.branch_manager.list_branches() {
+            let Some(head) = self
+                .branch_manager
+                .head(&meta.id)
+                .filter(|head| **head != TurnId::genesis())
+            else {
+                continue;
+            };
+            checked += 1;
+            let read = JournalReader::new(self.storage.clone(), meta.id.clone())
+                .and_then(|reader| reader.read(head));
+            match read {
+                Ok(record) if record.turn_id == *head => {}
+                Ok(record) => unreadable.push(format!(
+                    "{}: head {head} resolves to {}",
+                    meta.id, record.turn_id
+                )),
+                Err(err) => unreadable.push(format!("{}: {err}", meta.id)),
+            }
+        }
+        report.checks.push(if unreadable.is_empty() {
+            doctor::DoctorCheck::pass(
+                "journal.integrity",
+                format!("{checked} branch head(s) readable"),
+            )
+        } else {
+            doctor::DoctorCheck::fail("journal.integrity", unreadable.join("; "))
+        });
+
+        let mut types = self.entity_registry.list_types();
+        types.sort();
+        let config = preserves::IOValue::symbol("default");
+        let failed: Vec<String> = types
+            .iter()
+            .filter_map(|entity_type| {
+                self.entity_registry
+                    .create(entity_type, &config)
+                    .err()
+                    .map(|err| format!("{entity_type}: {err}"))
+            })
+            .collect();
+        report.checks.push(if failed.is_empty() {
+            doctor::DoctorCheck::pass(
+                "entities.factories",
+                format!(
+                    "{} entity type(s) instantiate with default config",
+                    types.len()
+                ),
+            )
+        } else {
+            doctor::DoctorCheck::fail("entities.factories", failed.join("; "))
+        });
+
+        report
+    }
+
+    /// Explain why a journaled turn ran and what it led to.
+    ///
+    /// Looks for the turn on the current branch first, then on every other
+    /// branch. See [`explain`] for how causal links are reconstructed.
+    pub fn explain_turn(&self, turn_id: &TurnId) -> Result<explain::TurnExplanation> {
+        let current = self.current_branch();
+        let mut branches = vec![current.clone()];
+        branches.extend(
+            self.branch_manager
+                .list_branches()
+                .into_iter()
+                .map(|meta| meta.id.clone())
+                .filter(|id| *id != current),
+        );
+
+        for branch in branches {
+            let records = self.lineage_records(&branch, None)?;
+            let Some(index) = records.iter().position(|record| record.turn_id == *turn_id) else {
+                continue;
+            };
+
+            let mut registrations = explain::Registrations::default();
+            for info in self.list_reactions() {
+                registrations
+                    .reactions
+                    .insert(info.definition.pattern.id, info.reaction_id);
+            }
+            for (entity_id, metadata) in self.entity_manager.iter() {
+                for pattern in &metadata.patterns {
+                    registrations.pattern_owners.insert(pattern.id, *entity_id);
+                }
+            }
+            for input in &records[index].inputs {
+                if let TurnInput::CapabilityInvocation { capability, .. } = input
+                    && let Some((_, metadata)) = self.lookup_capability(*capability)
+                {
+                    registrations
+                        .capabilities
+                        .insert(*capability, (metadata.kind, metadata.issuer));
+                }
+            }
+            return Ok(explain::explain(&branch, &records, index, &registrations));
+        }
+
+        Err(error::RuntimeError::Journal(
+            error::JournalError::TurnNotFound(turn_id.to_string()),
+        ))
     }
 
     /// Go to a specific turn (time travel)
@@ -1186,80 +5057,78 @@ impl Runtime {
     /// Loads the nearest snapshot before the target turn, then replays
     /// journal entries up to the target.
     pub fn goto(&mut self, target_turn: TurnId) -> Result<()> {
+        self.travel_to(target_turn, reflog::HeadMove::Goto)
+    }
+
+    /// Move the current branch's head to `target_turn`, logging the
+    /// movement as `action`.
+    fn travel_to(&mut self, target_turn: TurnId, action: reflog::HeadMove) -> Result<()> {
+        let old_head = self.current_head();
+        let client = self.scheduler.initiator().cloned();
+        let span = tracing::error_span!(
+            "goto",
+            branch = %self.current_branch,
+            from = %old_head,
+            to = %target_turn,
+        );
+        let _entered = span.enter();
+        // Turns behind the horizon are gone from the journal; the horizon
+        // itself lives on as a snapshot
+        if let Some(horizon) = self
+            .branch_manager
+            .get_branch(&self.current_branch)
+            .and_then(|meta| meta.compacted_through.as_ref())
+            .filter(|horizon| {
+                target_turn != **horizon
+                    && self
+                        .journal_reader(&self.current_branch)
+                        .is_ok_and(|reader| reader.segment_of(&target_turn).is_none())
+            })
+        {
+            return Err(error::RuntimeError::Journal(
+                error::JournalError::Compacted {
+                    turn: target_turn.to_string(),
+                    horizon: horizon.to_string(),
+                },
+            ));
+        }
+        let undone = self.effects_undone_by(&old_head, &target_turn)?;
+
         // Find nearest snapshot at or before target turn
         let snapshot_turn = self
             .snapshot_manager
             .nearest_snapshot(&self.current_branch, &target_turn)
             .map_err(|e| error::RuntimeError::Snapshot(e))?;
 
-        let mut entity_state_map: HashMap<uuid::Uuid, snapshot::EntityStateSnapshot> =
-            HashMap::new();
+        // Stream the snapshot into fresh actors, leaving live state alone
+        let mut restored = SnapshotRestore::default();
+        let snapshot = snapshot_turn
+            .map(|snap_count| {
+                self.snapshot_manager
+                    .stream_by_count(&self.current_branch, snap_count, &mut restored)
+                    .map_err(error::RuntimeError::Snapshot)
+            })
+            .transpose()?;
+
+        // Refuse before touching live state
+        self.check_replay_versions(
+            snapshot.as_ref().map(|header| &header.metadata),
+            &target_turn,
+        )?;
+        self.tasks.cancel_after(&self.current_branch, &target_turn);
 
-        // Reset runtime state
-        self.actors.clear();
+        // Reset runtime state, still acting for the same client
+        self.actors = restored.actors;
         self.scheduler = Scheduler::new(self.config.flow_control_limit as i64);
+        self.scheduler.set_initiator(client.clone());
         self.turn_count = 0;
         self.last_turn_per_actor.clear();
+        let entity_state_map = restored.entity_states;
 
-        let start_turn_id = if let Some(snap_count) = snapshot_turn {
-            let snapshot = self
-                .snapshot_manager
-                .load_by_count(&self.current_branch, snap_count)
-                .map_err(|e| error::RuntimeError::Snapshot(e))?;
-
-            // Restore state from snapshot
-            self.turn_count = snapshot.metadata.turn_count;
-
-            if !snapshot.entity_states.is_empty() {
-                entity_state_map = snapshot
-                    .entity_states
-                    .iter()
-                    .cloned()
-                    .map(|state| (state.entity_id, state))
-                    .collect();
-            }
-
-            // The snapshot contains aggregate state for all actors
-            // We need to reconstruct actors and apply the snapshot state
-            // For now, we'll collect the unique actors from the snapshot data
-
-            // Find all actors mentioned in assertions
-            let mut actor_ids = HashSet::new();
-            for (actor_id, _handle) in snapshot.assertions.active.keys() {
-                actor_ids.insert(actor_id.clone());
-            }
-            for facet_meta in snapshot.facets.facets.values() {
-                actor_ids.insert(facet_meta.actor.clone());
-            }
-
-            // Recreate actors and apply snapshot state
-            for actor_id in actor_ids {
-                let actor = Actor::new(actor_id.clone());
-
-                // Apply assertions for this actor
-                {
-                    let mut assertions = actor.assertions.write();
-                    *assertions = snapshot.assertions.clone();
-                }
-                // Apply facets
-                {
-                    let mut facets = actor.facets.write();
-                    *facets = snapshot.facets.clone();
-                }
-                // Apply capabilities
-                {
-                    let mut capabilities = actor.capabilities.write();
-                    *capabilities = snapshot.capabilities.clone();
-                }
-
-                self.actors.insert(actor_id, actor);
-            }
-
-            snapshot.metadata.turn_id.clone()
-        } else {
-            // No snapshot, replay from the beginning
-            TurnId::new("turn_00000000".to_string())
-        };
+        let start_turn_id = snapshot.map(|header| {
+            self.turn_count = header.metadata.turn_count;
+            header.metadata.turn_id
+        });
 
         // Replay journal from snapshot point to target
         let journal_reader = JournalReader::new(self.storage.clone(), self.current_branch.clone())
@@ -1270,10 +5139,15 @@ impl Runtime {
             .iter_all()
             .map_err(|e| error::RuntimeError::Journal(e))?;
 
-        while let Some(result) = iter.next() {
+        // A compacted journal no longer holds the snapshot turn itself
+        let at_snapshot = start_turn_id.as_ref() == Some(&target_turn);
+        while let Some(result) = iter.next().filter(|_| !at_snapshot) {
             let record = result.map_err(|e| error::RuntimeError::Journal(e))?;
 
-            if (!start_turn_id.as_str().eq("turn_00000000")) && record.turn_id <= start_turn_id {
+            if start_turn_id
+                .as_ref()
+                .is_some_and(|start| record.turn_id <= *start)
+            {
                 if record.turn_id == target_turn {
                     break;
                 }
@@ -1320,25 +5194,392 @@ impl Runtime {
             Some(&entity_state_map)
         };
 
-        self.hydrate_entities(state_map_opt)?;
+        self.restore_config_at(&target_turn)?;
+        self.hydrate_entities(state_map_opt)?;
+        self.hydrate_reactions()?;
+
+        // Update branch head
+        self.branch_manager
+            .update_head(&self.current_branch, target_turn.clone())
+            .map_err(|e| error::RuntimeError::Branch(e))?;
+        self.log_head_move(
+            &self.current_branch,
+            old_head.clone(),
+            &target_turn,
+            action,
+            client,
+        )?;
+        self.rebuild_branch_indexes()?;
+
+        self.notify_time_travel(&old_head, &target_turn);
+        self.warn_about_side_effects(old_head, target_turn, undone);
+        tracing::debug!(actors = self.actors.len(), "time travel complete");
+
+        Ok(())
+    }
+
+    /// Side effects of the turns between `target` and `head` on the current
+    /// branch, or none when `target` is not behind `head`.
+    fn effects_undone_by(
+        &self,
+        head: &TurnId,
+        target: &TurnId,
+    ) -> Result<Vec<effects::SideEffect>> {
+        if head == target || *head == TurnId::genesis() {
+            return Ok(Vec::new());
+        }
+        let journal_reader = JournalReader::new(self.storage.clone(), self.current_branch.clone())
+            .map_err(error::RuntimeError::Journal)?;
+
+        let mut passed_target = *target == TurnId::genesis();
+        let mut undone = Vec::new();
+        for result in journal_reader
+            .iter_all()
+            .map_err(error::RuntimeError::Journal)?
+        {
+            let record = result.map_err(error::RuntimeError::Journal)?;
+            let at_head = record.turn_id == *head;
+            if passed_target {
+                undone.push(record);
+            } else if record.turn_id == *target {
+                passed_target = true;
+            } else if at_head {
+                // Moving forward: nothing is undone
+                return Ok(Vec::new());
+            }
+            if at_head {
+                break;
+            }
+        }
+        Ok(effects::effects_in(&undone))
+    }
+
+    /// Record, log and announce side effects a rewind could not undo.
+    fn warn_about_side_effects(
+        &mut self,
+        from: TurnId,
+        to: TurnId,
+        undone: Vec<effects::SideEffect>,
+    ) {
+        if undone.is_empty() {
+            self.rewind_warning = None;
+            return;
+        }
+
+        let effects: Vec<effects::UndoneEffect> = undone
+            .into_iter()
+            .map(|effect| effects::UndoneEffect {
+                compensable: self.compensations.has(effect.kind),
+                effect,
+            })
+            .collect();
+        for undone in &effects {
+            warn!(
+                "rewind to {} leaves {} effect on {} in place: {}",
+                to, undone.effect.kind, undone.effect.target, undone.effect.description
+            );
+        }
+        let warning = effects::RewindWarning {
+            branch: self.current_branch.clone(),
+            from,
+            to,
+            effects,
+        };
+        self.notify(
+            notify::NotificationEvent::RewindPastSideEffects,
+            format!(
+                "rewind to {} left {} side effect(s) in place",
+                warning.to,
+                warning.effects.len()
+            ),
+            serde_json::to_value(&warning).unwrap_or_default(),
+        );
+        self.rewind_warning = Some(warning);
+    }
+
+    /// Side effects left in place by the most recent `goto` or `back`, if any.
+    pub fn rewind_warning(&self) -> Option<&effects::RewindWarning> {
+        self.rewind_warning.as_ref()
+    }
+
+    /// Register a hook that tries to undo side effects of `kind`.
+    pub fn register_compensation(
+        &mut self,
+        kind: effects::EffectKind,
+        hook: effects::CompensationHook,
+    ) {
+        self.compensations.register(kind, hook);
+    }
+
+    /// Run the compensation hook registered for `effect`'s kind.
+    pub fn compensate(&mut self, effect: &effects::SideEffect) -> Result<()> {
+        let hook = self.compensations.get(effect.kind).ok_or_else(|| {
+            error::RuntimeError::Compensation(format!(
+                "no compensation registered for {} effects",
+                effect.kind
+            ))
+        })?;
+        hook(effect).map_err(|reason| {
+            error::RuntimeError::Compensation(format!(
+                "{} effect on {}: {}",
+                effect.kind, effect.target, reason
+            ))
+        })
+    }
+
+    /// Put back the content `path` had before `turn` wrote it.
+    ///
+    /// Looks the turn up on the current branch's lineage and uses the first
+    /// backup it journaled for `path`, so a turn that wrote the file several
+    /// times is undone as a whole. A file the turn created is removed.
+    pub fn restore_file(&self, path: &str, turn: &TurnId) -> Result<effects::FileBackup> {
+        let records = self.lineage_records(&self.current_branch, Some(turn))?;
+        let backup = records
+            .last()
+            .filter(|record| record.turn_id == *turn)
+            .map(std::slice::from_ref)
+            .map(effects::backups_in)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|backup| backup.path == path)
+            .ok_or_else(|| {
+                error::RuntimeError::Restore(format!(
+                    "turn {} on branch {} recorded no backup of {}",
+                    turn, self.current_branch, path
+                ))
+            })?;
+
+        let location = Path::new(&backup.location);
+        let restored = match &backup.previous {
+            Some(content) => std::fs::write(location, content),
+            None if location.exists() => std::fs::remove_file(location),
+            None => Ok(()),
+        };
+        restored
+            .map_err(|err| error::RuntimeError::Restore(format!("{}: {}", backup.location, err)))?;
+        tracing::debug!(path, turn = %turn, "restored file from journal backup");
+        Ok(backup)
+    }
+
+    /// Merge source branch into target branch
+    ///
+    /// Following the implementation guide:
+    /// 1. Find LCA turn T where branches diverged
+    /// 2. Load state at T (from snapshot if available)
+    /// 3. Replay from T to get state_source and state_target
+    /// 4. Join states using CRDT semantics
+    /// 5. Create synthetic merge turn with the joined delta
+    pub fn merge(&mut self, source: &BranchId, target: &BranchId) -> Result<branch::MergeResult> {
+        let span = tracing::error_span!(
+            "merge",
+            source = %source,
+            target = %target,
+            turn = tracing::field::Empty,
+        );
+        let _entered = span.enter();
+
+        let MergePlan {
+            lca_turn,
+            source_head,
+            target_head,
+            source_delta,
+            mut joined_delta,
+            source_clock,
+            target_clock,
+            warnings,
+        } = self.plan_merge(source, target)?;
+
+        // Create a synthetic merge turn with provenance metadata
+        let merge_input = turn::TurnInput::Merge {
+            source_branch: source.clone(),
+            target_branch: target.clone(),
+            lca_turn: lca_turn.clone(),
+            source_head: Some(source_head.clone()),
+        };
+
+        // Use a special "merge" actor ID (deterministic)
+        let merge_actor = turn::ActorId::from_uuid(Uuid::nil());
+        let merge_clock = turn::LogicalClock::zero();
+
+        // Lamport rule: the merge turn follows both heads
+        let merge_seq = source_head.sequence().max(target_head.sequence()) + 1;
+
+        // Remember where each assertion brought over from the source came
+        // from; the joined delta alone does not say which side contributed it
+        let source_origins = self.assertion_origins(source, Some(&source_head))?;
+        let target_origins = self.assertion_origins(target, Some(&target_head))?;
+        let mut provenance: Vec<turn::TurnOutput> = source_delta
+            .assertions
+            .added
+            .iter()
+            .filter_map(|(actor, handle, _value, _version)| {
+                let origin = source_origins.get(handle)?;
+                // Already live on the target: nothing new was merged in
+                if target_origins
+                    .get(handle)
+                    .is_some_and(|live| live.turn == origin.turn)
+                {
+                    return None;
+                }
+                Some(turn::TurnOutput::MergeProvenance {
+                    actor: actor.clone(),
+                    handle: handle.clone(),
+                    source_branch: origin.branch.clone(),
+                    source_turn: origin.turn.clone(),
+                })
+            })
+            .collect();
+
+        self.config
+            .output_order
+            .apply(&mut provenance, &mut joined_delta);
+
+        let merge_record = turn::TurnRecord::new(
+            merge_actor,
+            target.clone(),
+            merge_clock,
+            Some(target_head),
+            vec![merge_input],
+            provenance,
+            joined_delta,
+        )
+        .with_sequence(merge_seq)
+        .with_vector_clock(source_clock.join(&target_clock));
+
+        let merge_turn_id = merge_record.turn_id.clone();
+        span.record("turn", tracing::field::display(&merge_turn_id));
 
-        // Update branch head
+        // Record merge turn in journal
+        self.journal_writer
+            .append(&merge_record)
+            .map_err(|e| error::RuntimeError::Journal(e))?;
+        self.turn_sinks.publish(&merge_record);
+
+        // Merging into the live branch applies the joined delta the same way
+        // replay would, so callers see merged state without a goto
+        if *target == self.current_branch {
+            self.actors
+                .entry(merge_record.actor.clone())
+                .or_insert_with(|| Actor::new(merge_record.actor.clone()))
+                .apply_delta(&merge_record.delta);
+            self.turn_count += 1;
+            self.last_turn_per_actor
+                .insert(merge_record.actor.clone(), merge_turn_id.clone());
+        }
+
+        // Update branch metadata
         self.branch_manager
-            .update_head(&self.current_branch, target_turn)
+            .update_head(target, merge_turn_id.clone())
             .map_err(|e| error::RuntimeError::Branch(e))?;
 
-        Ok(())
+        self.persist_branch_state()?;
+        self.log_head_move(
+            target,
+            merge_record.parent.clone().unwrap_or_else(TurnId::genesis),
+            &merge_turn_id,
+            reflog::HeadMove::Merge,
+            self.scheduler.initiator().cloned(),
+        )?;
+
+        self.record_branch_head(target.clone(), merge_turn_id.clone());
+
+        if !warnings.is_empty() {
+            self.notify(
+                notify::NotificationEvent::MergeWarning,
+                format!(
+                    "merge of {} into {} produced {} warning(s)",
+                    source.0,
+                    target.0,
+                    warnings.len()
+                ),
+                serde_json::json!({
+                    "source": source.0,
+                    "target": target.0,
+                    "merge_turn": merge_turn_id.to_string(),
+                    "warnings": warnings
+                        .iter()
+                        .map(|warning| serde_json::json!({
+                            "category": warning.category,
+                            "message": warning.message,
+                            "affected": warning.affected,
+                        }))
+                        .collect::<Vec<_>>(),
+                }),
+            );
+        }
+
+        tracing::debug!(warnings = warnings.len(), "merge committed");
+        Ok(branch::MergeResult {
+            merge_turn: merge_turn_id,
+            warnings,
+        })
     }
 
-    /// Merge source branch into target branch
+    /// Predict what merging `source` into `target` would do, without merging.
     ///
-    /// Following the implementation guide:
-    /// 1. Find LCA turn T where branches diverged
-    /// 2. Load state at T (from snapshot if available)
-    /// 3. Replay from T to get state_source and state_target
-    /// 4. Join states using CRDT semantics
-    /// 5. Create synthetic merge turn with the joined delta
-    pub fn merge(&mut self, source: &BranchId, target: &BranchId) -> Result<branch::MergeResult> {
+    /// Runs the merge up to conflict detection against state read from the
+    /// journals, so nothing is written and neither branch moves. The forecast
+    /// lists the warnings the merge would raise and the assertions that would
+    /// appear on or disappear from `target`.
+    pub fn merge_forecast(
+        &self,
+        source: &BranchId,
+        target: &BranchId,
+    ) -> Result<branch::MergeForecast> {
+        let plan = self.plan_merge(source, target)?;
+        let target_state = self.load_state_at_turn(&plan.target_head, target)?;
+        let live = self.assertion_origins(target, Some(&plan.target_head))?;
+        let retracted_handles: HashSet<&turn::Handle> = plan
+            .joined_delta
+            .assertions
+            .retracted
+            .iter()
+            .map(|(_actor, handle, _version)| handle)
+            .collect();
+
+        // Applying the joined delta adds everything it asserts, then drops
+        // everything it retracts
+        let mut added = Vec::new();
+        let mut seen = HashSet::new();
+        for (actor, handle, value, _version) in &plan.joined_delta.assertions.added {
+            if !live.contains_key(handle)
+                && !retracted_handles.contains(handle)
+                && seen.insert(handle.clone())
+            {
+                added.push(branch::ForecastAssertion {
+                    actor: actor.clone(),
+                    handle: handle.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+        let retracted = target_state
+            .assertions
+            .added
+            .iter()
+            .filter(|(_actor, handle, _value, _version)| {
+                live.contains_key(handle) && retracted_handles.contains(handle)
+            })
+            .filter(|(_actor, handle, _value, _version)| seen.insert(handle.clone()))
+            .map(
+                |(actor, handle, value, _version)| branch::ForecastAssertion {
+                    actor: actor.clone(),
+                    handle: handle.clone(),
+                    value: value.clone(),
+                },
+            )
+            .collect();
+
+        Ok(branch::MergeForecast {
+            lca: plan.lca_turn,
+            warnings: plan.warnings,
+            added,
+            retracted,
+        })
+    }
+
+    /// Steps 1-4 of [`Self::merge`]: everything it computes before writing
+    fn plan_merge(&self, source: &BranchId, target: &BranchId) -> Result<MergePlan> {
         // Find the lowest common ancestor
         let lca_turn = self
             .branch_manager
@@ -1376,48 +5617,29 @@ impl Runtime {
         // Join the deltas using CRDT semantics
         let joined_delta = source_delta.join(&target_delta);
 
-        // Detect conflicts and generate warnings
-        let warnings = self.detect_conflicts(&source_delta, &target_delta, &joined_delta);
+        // Heads where one descends from the other cannot conflict; only
+        // concurrent histories need conflict detection. Vector clocks are
+        // keyed by actor alone, so they cannot tell turns one actor ran on
+        // either side of a fork apart; ancestry decides instead.
+        let source_clock = self.vector_clock_at(source, &source_head);
+        let target_clock = self.vector_clock_at(target, &target_head);
+        let heads_ordered = lca_turn == source_head || lca_turn == target_head;
 
-        // Create a synthetic merge turn with provenance metadata
-        let merge_input = turn::TurnInput::Merge {
-            source_branch: source.clone(),
-            target_branch: target.clone(),
-            lca_turn: lca_turn.clone(),
+        // Detect conflicts and generate warnings
+        let warnings = if heads_ordered {
+            Vec::new()
+        } else {
+            self.detect_conflicts(&source_delta, &target_delta, &joined_delta)
         };
 
-        // Use a special "merge" actor ID (deterministic)
-        let merge_actor = turn::ActorId::from_uuid(Uuid::nil());
-        let merge_clock = turn::LogicalClock::zero();
-
-        let merge_record = turn::TurnRecord::new(
-            merge_actor,
-            target.clone(),
-            merge_clock,
-            Some(target_head),
-            vec![merge_input],
-            vec![], // No outputs for synthetic merge turn
+        Ok(MergePlan {
+            lca_turn,
+            source_head,
+            target_head,
+            source_delta,
             joined_delta,
-        );
-
-        let merge_turn_id = merge_record.turn_id.clone();
-
-        // Record merge turn in journal
-        self.journal_writer
-            .append(&merge_record)
-            .map_err(|e| error::RuntimeError::Journal(e))?;
-
-        // Update branch metadata
-        self.branch_manager
-            .update_head(target, merge_turn_id.clone())
-            .map_err(|e| error::RuntimeError::Branch(e))?;
-
-        self.persist_branch_state()?;
-
-        self.record_branch_head(target.clone(), merge_turn_id.clone());
-
-        Ok(branch::MergeResult {
-            merge_turn: merge_turn_id,
+            source_clock,
+            target_clock,
             warnings,
         })
     }
@@ -1495,7 +5717,7 @@ impl Runtime {
                     .iter()
                     .find(|(a, h, _, _)| a == actor && h == handle)
                 {
-                    if &source_item.2 != value {
+                    if canonical_encoding(&source_item.2) != canonical_encoding(value) {
                         warnings.push(branch::MergeWarning {
                             category: "concurrent-assertion".into(),
                             message: format!(
@@ -1546,15 +5768,13 @@ impl Runtime {
             .map_err(|e| error::RuntimeError::Journal(e))?;
 
         let mut turns = Vec::new();
-        let mut iter = journal_reader
-            .iter_all()
-            .map_err(|e| error::RuntimeError::Journal(e))?;
-
-        while let Some(result) = iter.next() {
-            let record = result.map_err(|e| error::RuntimeError::Journal(e))?;
-            turns.push(record.turn_id.clone());
-
-            if record.turn_id == current_head {
+        for header in journal_reader
+            .iter_headers()
+            .map_err(error::RuntimeError::Journal)?
+        {
+            let reached = header.turn_id == current_head;
+            turns.push(header.turn_id);
+            if reached {
                 break;
             }
         }
@@ -1563,7 +5783,7 @@ impl Runtime {
         if count >= turns.len() {
             // Go to the beginning
             if let Some(first_turn) = turns.first() {
-                self.goto(first_turn.clone())?;
+                self.travel_to(first_turn.clone(), reflog::HeadMove::Back)?;
                 Ok(first_turn.clone())
             } else {
                 Err(error::RuntimeError::Journal(
@@ -1573,7 +5793,7 @@ impl Runtime {
         } else {
             let target_idx = turns.len() - count - 1;
             let target_turn = turns[target_idx].clone();
-            self.goto(target_turn.clone())?;
+            self.travel_to(target_turn.clone(), reflog::HeadMove::Back)?;
             Ok(target_turn)
         }
     }
@@ -1682,7 +5902,9 @@ impl Runtime {
     /// Persist entity metadata to disk (atomic write)
     pub fn persist_entities(&self) -> Result<()> {
         let entity_meta_path = self.storage.meta_dir().join("entities.json");
-        self.entity_manager.save(&entity_meta_path)
+        self.entity_manager.save(&entity_meta_path)?;
+        self.entity_manager
+            .save_groups(&self.storage.meta_dir().join("groups.json"))
     }
 
     /// Persist reaction definitions to disk.
@@ -1825,15 +6047,242 @@ impl Runtime {
     /// Invoke a capability by identifier, returning the result payload.
     ///
     /// Schedules a synthetic turn for the capability issuer so the invocation
-    /// participates in causal ordering and journal replay.
+    /// participates in causal ordering and journal replay. Capabilities whose
+    /// kind is approval-gated are refused, as there is no actor to park the
+    /// request on.
     pub fn invoke_capability(
         &mut self,
         cap_id: uuid::Uuid,
         payload: preserves::IOValue,
     ) -> Result<preserves::IOValue> {
+        self.ensure_ungated(cap_id)?;
         CapabilityInvoker::invoke(self, cap_id, payload)
     }
 
+    /// Enqueue a capability invocation without running any turns.
+    ///
+    /// Returns a token for [`Runtime::take_invocation_result`]. When `reply_to`
+    /// is given, the outcome is also asserted on that actor as
+    /// `<invocation-result id capability value>`. Approval-gated capabilities
+    /// are refused as in [`Runtime::invoke_capability`].
+    pub fn invoke_capability_async(
+        &mut self,
+        cap_id: CapId,
+        payload: preserves::IOValue,
+        reply_to: Option<ActorId>,
+    ) -> Result<invocation::InvocationId> {
+        self.ensure_ungated(cap_id)?;
+        self.enqueue_invocation(cap_id, payload, reply_to)
+    }
+
+    /// Refuse a direct invocation of a capability whose kind needs approval.
+    fn ensure_ungated(&self, cap_id: CapId) -> Result<()> {
+        match self.approval_required_kind(cap_id) {
+            Some(kind) => Err(error::CapabilityError::ApprovalRequired(cap_id, kind).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Schedule an invocation turn on the capability issuer and track its outcome.
+    fn enqueue_invocation(
+        &mut self,
+        cap_id: CapId,
+        payload: preserves::IOValue,
+        reply_to: Option<ActorId>,
+    ) -> Result<invocation::InvocationId> {
+        use crate::runtime::error::CapabilityError;
+
+        let (issuer, metadata) = self
+            .lookup_capability(cap_id)
+            .ok_or(CapabilityError::NotFound(cap_id))?;
+        if metadata.status == CapabilityStatus::Revoked {
+            return Err(CapabilityError::Revoked(cap_id).into());
+        }
+
+        let id = uuid::Uuid::new_v4();
+        self.scheduler.enqueue(
+            issuer.clone(),
+            TurnInput::CapabilityInvocation {
+                capability: cap_id,
+                payload,
+                invocation: Some(id),
+            },
+            ScheduleCause::Capability,
+        );
+        self.invocations.submit(invocation::PendingInvocation {
+            id,
+            capability: cap_id,
+            issuer,
+            reply_to,
+            submitted_at: chrono::Utc::now(),
+        });
+        Ok(id)
+    }
+
+    /// Asynchronous invocations whose turn has not executed yet.
+    pub fn pending_invocations(&self) -> Vec<invocation::PendingInvocation> {
+        self.invocations.pending().cloned().collect()
+    }
+
+    /// Take the outcome of a finished asynchronous invocation.
+    ///
+    /// Returns `None` while the invocation is still pending, or once its
+    /// outcome has already been taken.
+    pub fn take_invocation_result(
+        &mut self,
+        id: invocation::InvocationId,
+    ) -> Option<invocation::InvocationOutcome> {
+        self.invocations.take_outcome(&id)
+    }
+
+    /// Start injecting faults according to `config`, replacing any earlier injector.
+    #[cfg(feature = "chaos")]
+    pub fn enable_chaos(&mut self, config: chaos::ChaosConfig) {
+        self.chaos = Some(chaos::ChaosMonkey::new(config));
+    }
+
+    /// Stop injecting faults, returning the injector with its fault log.
+    #[cfg(feature = "chaos")]
+    pub fn disable_chaos(&mut self) -> Option<chaos::ChaosMonkey> {
+        self.chaos.take()
+    }
+
+    /// Faults injected since chaos was enabled.
+    #[cfg(feature = "chaos")]
+    pub fn injected_faults(&self) -> &[chaos::InjectedFault] {
+        self.chaos
+            .as_ref()
+            .map(|monkey| monkey.injected())
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "chaos")]
+    fn inject_fault(&mut self, kind: chaos::FaultKind, detail: impl FnOnce() -> String) -> bool {
+        if self.chaos.is_none() || !self.flag_enabled(flags::FeatureFlag::Chaos) {
+            return false;
+        }
+        self.chaos
+            .as_mut()
+            .and_then(|monkey| monkey.roll(kind, detail))
+            .is_some()
+    }
+
+    /// Provide (or replace) a secret for entities after startup.
+    pub fn set_secret(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        Arc::make_mut(&mut self.secrets).insert(name, value);
+    }
+
+    /// Names of the secrets entities can look up.
+    pub fn secret_names(&self) -> Vec<String> {
+        self.secrets.names()
+    }
+
+    /// Record an invocation's outcome and publish it to the reply actor.
+    fn complete_invocation(
+        &mut self,
+        id: invocation::InvocationId,
+        capability: CapId,
+        outcome: invocation::InvocationOutcome,
+    ) {
+        let record = invocation::result_record(id, capability, &outcome);
+        let Some(pending) = self.invocations.finish(&id, outcome) else {
+            return;
+        };
+        if let Some(actor) = pending.reply_to {
+            let input = TurnInput::Assert {
+                actor: actor.clone(),
+                handle: Handle::new(),
+                value: record,
+                namespace: None,
+            };
+            self.scheduler
+                .enqueue(actor, input, ScheduleCause::Capability);
+        }
+    }
+
+    /// Export a live capability as a signed sturdy ref.
+    ///
+    /// `attenuation` caveats are patterns every redemption payload must match,
+    /// layered on top of the capability's own attenuation.
+    pub fn export_sturdy_ref(
+        &self,
+        cap_id: CapId,
+        attenuation: Vec<preserves::IOValue>,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<sturdy::SturdyRef> {
+        use crate::runtime::error::CapabilityError;
+
+        let (_issuer, metadata) = self
+            .lookup_capability(cap_id)
+            .ok_or(CapabilityError::NotFound(cap_id))?;
+        if metadata.status == CapabilityStatus::Revoked {
+            return Err(CapabilityError::Revoked(cap_id).into());
+        }
+
+        Ok(self
+            .sturdy_key
+            .sign(cap_id, metadata.kind, attenuation, expires_at))
+    }
+
+    /// Redeem a sturdy ref by invoking its capability with `payload`.
+    ///
+    /// Verifies the signature, expiry, and caveats before the invocation; the
+    /// capability's revocation status is checked by the invocation itself.
+    pub fn redeem_sturdy_ref(
+        &mut self,
+        sturdy_ref: &sturdy::SturdyRef,
+        payload: preserves::IOValue,
+    ) -> Result<preserves::IOValue> {
+        use crate::runtime::error::CapabilityError;
+
+        self.sturdy_key.verify(sturdy_ref)?;
+
+        if sturdy_ref.is_expired_at(chrono::Utc::now()) {
+            let deadline = sturdy_ref
+                .expires_at
+                .map(|deadline| deadline.to_rfc3339())
+                .unwrap_or_default();
+            return Err(CapabilityError::Expired(sturdy_ref.capability, deadline).into());
+        }
+
+        if !sturdy_ref.admits(&payload) {
+            return Err(CapabilityError::CaveatRejected(sturdy_ref.capability).into());
+        }
+
+        self.invoke_capability(sturdy_ref.capability, payload)
+    }
+
+    /// Active capability of `kind` held by `holder`, lowest id first.
+    fn held_capability_of_kind(&self, holder: &turn::ActorId, kind: &str) -> Option<CapId> {
+        self.actors
+            .values()
+            .flat_map(|actor| {
+                actor
+                    .capabilities
+                    .read()
+                    .capabilities
+                    .values()
+                    .filter(|metadata| {
+                        &metadata.holder == holder
+                            && metadata.kind == kind
+                            && metadata.status == CapabilityStatus::Active
+                    })
+                    .map(|metadata| metadata.id)
+                    .collect::<Vec<_>>()
+            })
+            .min()
+    }
+
+    /// Concurrency class declared for the type of `entity`.
+    ///
+    /// Unknown entities are treated as [`registry::ConcurrencyClass::Exclusive`].
+    fn entity_concurrency(&self, entity: uuid::Uuid) -> registry::ConcurrencyClass {
+        self.entity_manager
+            .get(&entity)
+            .and_then(|metadata| self.entity_registry.concurrency(&metadata.entity_type))
+            .unwrap_or_default()
+    }
+
     fn lookup_capability(&self, cap_id: CapId) -> Option<(turn::ActorId, CapabilityMetadata)> {
         for (actor_id, actor) in &self.actors {
             let capabilities = actor.capabilities.read();
@@ -1850,6 +6299,148 @@ pub use control::Control;
 pub use error::{Result, RuntimeError};
 pub use turn::{TurnId, TurnRecord};
 
+/// Fail unless `path` resolves to `parent` or a directory below it
+fn contained_in(parent: &Path, path: &Path) -> Result<()> {
+    let canonical = |path: &Path| {
+        path.canonicalize().map_err(|e| {
+            error::RuntimeError::Init(format!("Failed to resolve {}: {}", path.display(), e))
+        })
+    };
+    if canonical(path)?.starts_with(canonical(parent)?) {
+        Ok(())
+    } else {
+        Err(error::RuntimeError::Branch(
+            error::BranchError::DeleteRefused(format!(
+                "{} lies outside {}",
+                path.display(),
+                parent.display()
+            )),
+        ))
+    }
+}
+
+/// Remove the files a branch keeps in `dir`, a directory strictly below
+/// `parent`
+///
+/// Subdirectories belong to branches nested under this one's name
+/// (`agent` and `agent/req-1`) and are left alone.
+fn remove_branch_files(parent: &Path, dir: &Path) -> Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    let failed = |path: &Path, e: std::io::Error| {
+        error::RuntimeError::Init(format!("Failed to remove {}: {}", path.display(), e))
+    };
+    contained_in(parent, dir)?;
+    if dir.canonicalize().map_err(|e| failed(dir, e))?
+        == parent.canonicalize().map_err(|e| failed(parent, e))?
+    {
+        return Err(error::RuntimeError::Branch(
+            error::BranchError::DeleteRefused(format!(
+                "{} is not a branch directory",
+                dir.display()
+            )),
+        ));
+    }
+    for entry in std::fs::read_dir(dir).map_err(|e| failed(dir, e))? {
+        let entry = entry.map_err(|e| failed(dir, e))?;
+        let is_dir = entry
+            .file_type()
+            .map_err(|e| failed(&entry.path(), e))?
+            .is_dir();
+        if !is_dir {
+            std::fs::remove_file(entry.path()).map_err(|e| failed(&entry.path(), e))?;
+        }
+    }
+    // Still holds nested branches when it is not empty
+    let _ = std::fs::remove_dir(dir);
+    Ok(())
+}
+
+/// Snapshot contents routed to the actors that own them, as time travel
+/// restores them
+#[derive(Default)]
+struct SnapshotRestore {
+    actors: HashMap<turn::ActorId, Actor>,
+    entity_states: HashMap<uuid::Uuid, snapshot::EntityStateSnapshot>,
+}
+
+impl SnapshotRestore {
+    fn actor(&mut self, id: &turn::ActorId) -> &Actor {
+        self.actors
+            .entry(id.clone())
+            .or_insert_with(|| Actor::new(id.clone()))
+    }
+}
+
+impl snapshot::SnapshotSink for SnapshotRestore {
+    fn assertion(
+        &mut self,
+        key: (turn::ActorId, Handle),
+        value: preserves::IOValue,
+        version: uuid::Uuid,
+    ) {
+        let actor = self.actor(&key.0);
+        actor
+            .assertions
+            .write()
+            .active
+            .insert(key, (value, version));
+    }
+
+    fn tombstone(&mut self, tombstone: (turn::ActorId, Handle, uuid::Uuid)) {
+        let actor = self.actor(&tombstone.0);
+        actor.assertions.write().tombstones.insert(tombstone);
+    }
+
+    fn namespace(&mut self, key: (turn::ActorId, Handle), namespace: String) {
+        let actor = self.actor(&key.0);
+        actor.assertions.write().namespaces.insert(key, namespace);
+    }
+
+    fn facet(&mut self, id: turn::FacetId, metadata: FacetMetadata) {
+        let actor = self.actor(&metadata.actor);
+        actor.facets.write().facets.insert(id, metadata);
+    }
+
+    fn capability(&mut self, id: CapId, metadata: CapabilityMetadata) {
+        // Both ends consult a capability: the issuer to revoke it, the
+        // holder to use it
+        if metadata.holder != metadata.issuer {
+            let holder = self.actor(&metadata.holder);
+            holder
+                .capabilities
+                .write()
+                .capabilities
+                .insert(id, metadata.clone());
+        }
+        let issuer = self.actor(&metadata.issuer);
+        issuer
+            .capabilities
+            .write()
+            .capabilities
+            .insert(id, metadata);
+    }
+
+    fn entity_state(&mut self, state: snapshot::EntityStateSnapshot) {
+        self.entity_states.insert(state.entity_id, state);
+    }
+}
+
+/// Merge of two branches computed up to conflict detection
+struct MergePlan {
+    lca_turn: TurnId,
+    source_head: TurnId,
+    target_head: TurnId,
+    /// Source state since the common ancestor
+    source_delta: state::StateDelta,
+    /// Source and target states joined
+    joined_delta: state::StateDelta,
+    source_clock: turn::VectorClock,
+    target_clock: turn::VectorClock,
+    warnings: Vec<branch::MergeWarning>,
+}
+
 struct CapabilityInvoker;
 
 impl CapabilityInvoker {
@@ -1857,45 +6448,87 @@ impl CapabilityInvoker {
         runtime: &mut Runtime,
         cap_id: uuid::Uuid,
         payload: preserves::IOValue,
+    ) -> Result<preserves::IOValue> {
+        let result = Self::invoke_once(runtime, cap_id, payload);
+        if let Err(error::RuntimeError::Capability(error::CapabilityError::Denied(_, reason))) =
+            &result
+        {
+            runtime.notify(
+                notify::NotificationEvent::CapabilityDenied,
+                format!("capability {} denied: {}", cap_id, reason),
+                serde_json::json!({
+                    "capability": cap_id.to_string(),
+                    "reason": reason,
+                }),
+            );
+        }
+        result
+    }
+
+    /// Invoke `cap_id`, refusing to re-enter an exclusive entity that is
+    /// already serving an invocation further up the call chain.
+    fn invoke_once(
+        runtime: &mut Runtime,
+        cap_id: uuid::Uuid,
+        payload: preserves::IOValue,
     ) -> Result<preserves::IOValue> {
         use crate::runtime::error::CapabilityError;
 
-        let (issuer_actor, metadata) = runtime
+        let entity = runtime
             .lookup_capability(cap_id)
-            .ok_or_else(|| CapabilityError::NotFound(cap_id))?;
+            .and_then(|(_issuer, metadata)| metadata.issuer_entity);
+        let Some(entity) = entity else {
+            return Self::run_to_result(runtime, cap_id, payload);
+        };
 
-        if metadata.status == CapabilityStatus::Revoked {
-            return Err(CapabilityError::Revoked(cap_id).into());
+        if runtime.serving.contains(&entity) {
+            let class = runtime.entity_concurrency(entity);
+            if !class.allows_reentry() {
+                return Err(CapabilityError::Denied(
+                    cap_id,
+                    format!(
+                        "entity {} is {} and already serving an invocation",
+                        entity, class
+                    ),
+                )
+                .into());
+            }
         }
 
-        runtime.scheduler.enqueue(
-            issuer_actor.clone(),
-            TurnInput::CapabilityInvocation {
-                capability: cap_id,
-                payload,
-            },
-            ScheduleCause::Capability,
-        );
+        runtime.serving.push(entity);
+        let result = Self::run_to_result(runtime, cap_id, payload);
+        runtime.serving.pop();
+        result
+    }
+
+    fn run_to_result(
+        runtime: &mut Runtime,
+        cap_id: uuid::Uuid,
+        payload: preserves::IOValue,
+    ) -> Result<preserves::IOValue> {
+        use crate::runtime::error::CapabilityError;
+
+        let id = runtime.enqueue_invocation(cap_id, payload, None)?;
 
         loop {
             match runtime.execute_turn() {
                 Err(error::RuntimeError::Actor(err)) => {
+                    runtime.invocations.discard(&id);
                     return Err(CapabilityError::Denied(cap_id, err.to_string()).into());
                 }
-                Err(other) => return Err(other),
-                Ok(Some(record)) => {
-                    if let Some(result) = record.outputs.iter().find_map(|output| {
-                        if let TurnOutput::CapabilityResult { capability, result } = output {
-                            if *capability == cap_id {
-                                return Some(result.clone());
-                            }
-                        }
-                        None
-                    }) {
-                        return Ok(result);
-                    }
+                Err(other) => {
+                    runtime.invocations.discard(&id);
+                    return Err(other);
                 }
+                Ok(Some(_)) => match runtime.invocations.take_outcome(&id) {
+                    Some(invocation::InvocationOutcome::Completed(result)) => return Ok(result),
+                    Some(invocation::InvocationOutcome::Failed(reason)) => {
+                        return Err(CapabilityError::Denied(cap_id, reason).into());
+                    }
+                    None => {}
+                },
                 Ok(None) => {
+                    runtime.invocations.discard(&id);
                     return Err(CapabilityError::Denied(
                         cap_id,
                         "capability invocation did not produce a result".into(),
@@ -1906,3 +6539,24 @@ impl CapabilityInvoker {
         }
     }
 }
+
+fn outbox_error(err: std::io::Error) -> error::RuntimeError {
+    error::RuntimeError::Storage(StorageError::Io(err))
+}
+
+/// Artifact tag an archived branch is stored under. Archives are pulled back
+/// by manifest digest, so the tag only needs to be readable.
+fn archive_tag(branch: &BranchId) -> String {
+    let mut tag: String = format!("branch-{}", branch)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    tag.truncate(128);
+    tag
+}
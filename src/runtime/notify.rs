@@ -0,0 +1,235 @@
+//! Notification sinks for significant runtime events
+//!
+//! The runtime reports a small set of operator-relevant events (merge
+//! warnings, failed turns, completed agent responses, denied capability
+//! invocations, memory threshold crossings) to registered [`Notifier`]s.
+//! Sinks are configured through
+//! [`RuntimeConfig::notifiers`](super::RuntimeConfig::notifiers) or added
+//! programmatically via [`Runtime::add_notifier`](super::Runtime::add_notifier).
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::time::Duration;
+
+use super::turn::BranchId;
+
+/// Runtime events that can trigger a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// A merge completed with warnings
+    MergeWarning,
+    /// An actor failed while executing a turn
+    TurnFailed,
+    /// An agent published a response
+    AgentResponseCompleted,
+    /// A capability invocation was denied by its target
+    CapabilityDenied,
+    /// A rewind moved past turns with irreversible side effects
+    RewindPastSideEffects,
+    /// An actor crossed a memory accounting threshold
+    MemoryThreshold,
+}
+
+/// Payload delivered to notification sinks.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    /// Event that triggered the notification
+    pub event: NotificationEvent,
+    /// Branch the runtime was on when the event occurred
+    pub branch: BranchId,
+    /// When the event occurred
+    pub timestamp: DateTime<Utc>,
+    /// Short human-readable description
+    pub summary: String,
+    /// Event-specific structured details
+    pub details: Value,
+}
+
+/// Destination for runtime notifications.
+///
+/// Implementations must not block the runtime for long; slow transports
+/// should hand the work off to a background thread.
+pub trait Notifier: Send + Sync {
+    /// Deliver a notification.
+    fn notify(&self, notification: &Notification);
+}
+
+/// Configuration for a webhook notification sink.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotifierConfig {
+    /// URL receiving a JSON `POST` per notification
+    pub url: String,
+    /// Events to forward (empty = all events)
+    #[serde(default)]
+    pub events: Vec<NotificationEvent>,
+}
+
+/// Notifications queued per webhook sink before new ones are dropped
+const WEBHOOK_QUEUE_CAPACITY: usize = 64;
+
+/// Built-in sink posting notifications as JSON to a webhook URL.
+///
+/// Notifications are posted in order by one worker thread per sink. While the
+/// endpoint is behind by a full queue, new notifications are dropped.
+pub struct WebhookNotifier {
+    url: String,
+    queue: SyncSender<Value>,
+}
+
+impl WebhookNotifier {
+    /// Create a webhook sink for `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        let url = url.into();
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        let (queue, bodies) = mpsc::sync_channel::<Value>(WEBHOOK_QUEUE_CAPACITY);
+        let worker_url = url.clone();
+        // Deliver off the runtime thread so a slow endpoint cannot stall turns;
+        // the worker exits once the sink is dropped and its queue drained
+        let spawned = std::thread::Builder::new()
+            .name("duet-webhook".into())
+            .spawn(move || {
+                for body in bodies {
+                    let outcome = client
+                        .post(&worker_url)
+                        .json(&body)
+                        .send()
+                        .and_then(|response| response.error_for_status());
+                    if let Err(err) = outcome {
+                        tracing::warn!("webhook notification to {} failed: {}", worker_url, err);
+                    }
+                }
+            });
+        if let Err(err) = spawned {
+            tracing::warn!("failed to start webhook worker for {}: {}", url, err);
+        }
+        Self { url, queue }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&self, notification: &Notification) {
+        let body = serde_json::to_value(notification).unwrap_or_default();
+        if let Err(TrySendError::Full(_)) = self.queue.try_send(body) {
+            tracing::warn!(
+                "webhook queue for {} is full; dropping {:?} notification",
+                self.url,
+                notification.event
+            );
+        }
+    }
+}
+
+/// Registered sinks together with the events each one wants.
+#[derive(Default)]
+pub struct Notifications {
+    sinks: Vec<(Vec<NotificationEvent>, Arc<dyn Notifier>)>,
+}
+
+impl Notifications {
+    /// Build webhook sinks from configuration.
+    pub fn from_config(configs: &[NotifierConfig]) -> Self {
+        let mut notifications = Self::default();
+        for config in configs {
+            notifications.register(
+                config.events.clone(),
+                Arc::new(WebhookNotifier::new(config.url.clone())),
+            );
+        }
+        notifications
+    }
+
+    /// Register a sink for `events` (empty = all events).
+    pub fn register(&mut self, events: Vec<NotificationEvent>, notifier: Arc<dyn Notifier>) {
+        self.sinks.push((events, notifier));
+    }
+
+    /// Whether any sink wants `event`.
+    pub fn wants(&self, event: NotificationEvent) -> bool {
+        self.sinks
+            .iter()
+            .any(|(events, _)| events.is_empty() || events.contains(&event))
+    }
+
+    /// Deliver a notification to every interested sink.
+    pub fn emit(&self, notification: &Notification) {
+        for (events, notifier) in &self.sinks {
+            if events.is_empty() || events.contains(&notification.event) {
+                notifier.notify(notification);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Notifications {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notifications")
+            .field("sinks", &self.sinks.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    fn notification(summary: &str) -> Notification {
+        Notification {
+            event: NotificationEvent::TurnFailed,
+            branch: BranchId::main(),
+            timestamp: Utc::now(),
+            summary: summary.into(),
+            details: Value::Null,
+        }
+    }
+
+    /// Read one HTTP request from `stream` and return its body.
+    fn read_body(stream: &std::net::TcpStream) -> Value {
+        let mut reader = BufReader::new(stream);
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn webhook_notifications_are_posted_in_order_by_one_worker() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let notifier = WebhookNotifier::new(url);
+        for summary in ["first", "second", "third"] {
+            notifier.notify(&notification(summary));
+        }
+
+        let mut received = Vec::new();
+        for stream in listener.incoming().take(3) {
+            let mut stream = stream.unwrap();
+            let body = read_body(&stream);
+            received.push(body["summary"].as_str().unwrap().to_string());
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .unwrap();
+        }
+        assert_eq!(received, ["first", "second", "third"]);
+    }
+}
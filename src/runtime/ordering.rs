@@ -0,0 +1,157 @@
+//! Canonical ordering of turn outputs
+//!
+//! Entities emit outputs in the order their callbacks run, and some of that
+//! order follows hash-map iteration (which patterns match an assertion, for
+//! example). Two executions of the same turn can therefore record the same
+//! outputs in a different order. Under [`OutputOrder::Canonical`] the runtime
+//! sorts the dataspace outputs of every turn before committing it:
+//!
+//! - `Assert`, `Retract`, `PatternMatched` and `PatternUnmatched` outputs are
+//!   ordered by kind (in that order), then handle, then pattern id. They keep
+//!   the positions the turn gave to dataspace outputs, so messages, spawns and
+//!   other outputs whose order carries meaning stay where they were emitted.
+//! - Added and retracted assertions in the delta are ordered by actor and
+//!   handle. Deltas are applied as sets, so this never changes the result.
+//!
+//! [`Runtime::verify_replay`](super::Runtime::verify_replay) reports journaled
+//! turns that are not in canonical order.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::state::StateDelta;
+use super::turn::{TurnId, TurnOutput};
+
+/// How the outputs of a turn are ordered when it is committed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputOrder {
+    /// Sort dataspace outputs and deltas into canonical order
+    #[default]
+    Canonical,
+    /// Keep the order in which entities emitted them
+    Emission,
+}
+
+impl OutputOrder {
+    /// Order `outputs` and `delta` according to this policy.
+    pub fn apply(self, outputs: &mut [TurnOutput], delta: &mut StateDelta) {
+        if self == OutputOrder::Canonical {
+            canonicalize(outputs, delta);
+        }
+    }
+}
+
+/// Sort the dataspace outputs and assertion delta of a turn into canonical order.
+pub fn canonicalize(outputs: &mut [TurnOutput], delta: &mut StateDelta) {
+    let slots: Vec<usize> = outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, output)| sort_key(output).is_some())
+        .map(|(index, _)| index)
+        .collect();
+    let mut keyed: Vec<TurnOutput> = slots.iter().map(|&slot| outputs[slot].clone()).collect();
+    keyed.sort_by_key(sort_key);
+    for (slot, output) in slots.into_iter().zip(keyed) {
+        outputs[slot] = output;
+    }
+
+    let assertions = &mut delta.assertions;
+    assertions
+        .added
+        .sort_by(|a, b| (&a.0, a.1.0).cmp(&(&b.0, b.1.0)));
+    assertions
+        .retracted
+        .sort_by(|a, b| (&a.0, a.1.0).cmp(&(&b.0, b.1.0)));
+    assertions
+        .namespaces
+        .sort_by(|a, b| (&a.0, a.1.0).cmp(&(&b.0, b.1.0)));
+}
+
+/// Whether `outputs` and `delta` are already in canonical order.
+pub fn is_canonical(outputs: &[TurnOutput], delta: &StateDelta) -> bool {
+    let keys: Vec<_> = outputs.iter().filter_map(sort_key).collect();
+    let assertions = &delta.assertions;
+    keys.is_sorted()
+        && assertions.added.is_sorted_by_key(|a| (a.0.clone(), a.1.0))
+        && assertions
+            .retracted
+            .is_sorted_by_key(|a| (a.0.clone(), a.1.0))
+        && assertions
+            .namespaces
+            .is_sorted_by_key(|a| (a.0.clone(), a.1.0))
+}
+
+/// Position of a dataspace output in canonical order (`None` for other outputs).
+fn sort_key(output: &TurnOutput) -> Option<(u8, Uuid, Uuid)> {
+    match output {
+        TurnOutput::Assert { handle, .. } => Some((0, handle.0, Uuid::nil())),
+        TurnOutput::Retract { handle, .. } => Some((1, handle.0, Uuid::nil())),
+        TurnOutput::PatternMatched { pattern_id, handle } => Some((2, handle.0, *pattern_id)),
+        TurnOutput::PatternUnmatched { pattern_id, handle } => Some((3, handle.0, *pattern_id)),
+        _ => None,
+    }
+}
+
+/// Outcome of [`Runtime::verify_replay`](super::Runtime::verify_replay)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayVerification {
+    /// Number of turns checked
+    pub checked: usize,
+    /// Turns whose outputs or delta are not in canonical order
+    pub unordered: Vec<TurnId>,
+}
+
+impl ReplayVerification {
+    /// Whether every turn was in canonical order
+    pub fn is_ok(&self) -> bool {
+        self.unordered.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::turn::{ActorId, Handle};
+    use preserves::IOValue;
+
+    #[test]
+    fn test_canonicalize_sorts_dataspace_outputs_in_place() {
+        let (low, high) = {
+            let a = Handle::new();
+            let b = Handle::new();
+            if a.0 < b.0 { (a, b) } else { (b, a) }
+        };
+        let message = TurnOutput::Message {
+            target_actor: ActorId::new(),
+            target_facet: crate::runtime::turn::FacetId::new(),
+            payload: IOValue::symbol("ping"),
+        };
+        let mut outputs = vec![
+            TurnOutput::Retract {
+                handle: low.clone(),
+                namespace: None,
+            },
+            message,
+            TurnOutput::Assert {
+                handle: high.clone(),
+                value: IOValue::symbol("b"),
+                namespace: None,
+            },
+            TurnOutput::Assert {
+                handle: low.clone(),
+                value: IOValue::symbol("a"),
+                namespace: None,
+            },
+        ];
+        let mut delta = StateDelta::empty();
+        assert!(!is_canonical(&outputs, &delta));
+
+        canonicalize(&mut outputs, &mut delta);
+        assert!(is_canonical(&outputs, &delta));
+        assert!(matches!(&outputs[0], TurnOutput::Assert { handle, .. } if *handle == low));
+        assert!(matches!(&outputs[1], TurnOutput::Message { .. }));
+        assert!(matches!(&outputs[2], TurnOutput::Assert { handle, .. } if *handle == high));
+        assert!(matches!(&outputs[3], TurnOutput::Retract { handle, .. } if *handle == low));
+    }
+}
@@ -0,0 +1,884 @@
+//! Durable outbox for deliveries to external systems
+//!
+//! Entities that must tell the outside world about a turn (post to a
+//! webhook, run a process) do not call out from inside the turn, where a
+//! retry after a crash would call out twice. They stage the delivery with
+//! [`Activation::stage_delivery`](super::actor::Activation::stage_delivery),
+//! which journals a [`TurnOutput::OutboxStaged`] with the turn. Once the turn
+//! is committed the runtime hands the delivery to a worker thread that runs
+//! the [`Deliverer`] registered for its channel and sends back an
+//! acknowledgement.
+//!
+//! Deliveries are identified by their turn id and output index. Staged
+//! deliveries and acknowledgements are appended to the log in
+//! `meta/outbox.bin`; the worker logs each acknowledgement as soon as the
+//! delivery returns, whether or not the runtime is executing turns. An
+//! acknowledged delivery is never dispatched again: not after a restart, and
+//! not when a rewound turn is executed again under the same id. Once a
+//! snapshot covers a delivery's turn its acknowledgement is dropped, which
+//! keeps the log bounded; only rewinding past that snapshot can stage the
+//! delivery again. A delivery staged by the last turn before a crash is found
+//! again in the journal on startup. The remaining window in which a delivery
+//! can be repeated is a crash between the delivery and its acknowledgement;
+//! deliverers pass the delivery id along (the webhook deliverer as an
+//! `Idempotency-Key` header) so receivers can drop the repeat.
+
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use preserves::IOValue;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Instant;
+use uuid::Uuid;
+
+use super::turn::{ActorId, BranchId, TurnId, TurnOutput, TurnRecord};
+use crate::util::io_value::io_value_to_json;
+
+/// Longest wait between two attempts at a failing delivery
+const MAX_BACKOFF_SECS: i64 = 300;
+
+/// Log entries tolerated before superseded ones are compacted away
+const COMPACT_AFTER_ENTRIES: usize = 1024;
+
+/// Delivery to an external system, staged by a committed turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delivery {
+    /// Stable identifier: the staging turn and the output's index in it
+    pub id: String,
+    /// Turn that staged the delivery
+    pub turn_id: TurnId,
+    /// Index of the staging output within the turn's outputs
+    pub index: usize,
+    /// Branch the turn was recorded on
+    pub branch: BranchId,
+    /// Actor whose entity staged the delivery
+    pub actor: ActorId,
+    /// Entity instance that staged it, if known
+    pub entity_id: Option<Uuid>,
+    /// Outbox channel naming the receiving system
+    pub channel: String,
+    /// Delivery payload
+    pub payload: IOValue,
+    /// When the turn was recorded
+    pub staged_at: DateTime<Utc>,
+}
+
+impl Delivery {
+    /// JSON document sent to webhooks and processes
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "turn": self.turn_id.to_string(),
+            "branch": self.branch.0,
+            "actor": self.actor.to_string(),
+            "channel": self.channel,
+            "payload": io_value_to_json(&self.payload),
+            "staged_at": self.staged_at,
+        })
+    }
+}
+
+/// Deliveries staged by `record`, in output order.
+pub fn deliveries_in(record: &TurnRecord) -> Vec<Delivery> {
+    record
+        .outputs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, output)| match output {
+            TurnOutput::OutboxStaged {
+                entity_id,
+                channel,
+                payload,
+            } => Some(Delivery {
+                id: format!("{}#{}", record.turn_id, index),
+                turn_id: record.turn_id.clone(),
+                index,
+                branch: record.branch.clone(),
+                actor: record.actor.clone(),
+                entity_id: *entity_id,
+                channel: channel.clone(),
+                payload: payload.clone(),
+                staged_at: record.timestamp,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Receiving end of an outbox channel.
+///
+/// Deliveries run on the outbox worker thread, one at a time. A delivery
+/// counts as done once `deliver` returns `Ok`; an error is retried later.
+pub trait Deliverer: Send + Sync {
+    /// Hand `delivery` to the external system.
+    fn deliver(&self, delivery: &Delivery) -> Result<(), String>;
+}
+
+/// Configuration for a built-in deliverer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DelivererConfig {
+    /// `POST` each delivery as JSON to `url`
+    Webhook {
+        /// Receiving URL
+        url: String,
+    },
+    /// Run `command` with each delivery as JSON on stdin
+    Process {
+        /// Program followed by its arguments
+        command: Vec<String>,
+    },
+}
+
+/// Outbox channels configured for a runtime.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboxConfig {
+    /// Deliverer for each channel name
+    #[serde(default)]
+    pub channels: BTreeMap<String, DelivererConfig>,
+}
+
+impl DelivererConfig {
+    /// Build the configured deliverer.
+    pub fn build(&self) -> Arc<dyn Deliverer> {
+        match self {
+            DelivererConfig::Webhook { url } => Arc::new(WebhookDeliverer::new(url.clone())),
+            DelivererConfig::Process { command } => {
+                Arc::new(ProcessDeliverer::new(command.clone()))
+            }
+        }
+    }
+}
+
+/// Built-in deliverer posting deliveries as JSON to a webhook URL.
+///
+/// The delivery id is sent as the `Idempotency-Key` header.
+pub struct WebhookDeliverer {
+    url: String,
+    client: reqwest::blocking::Client,
+}
+
+impl WebhookDeliverer {
+    /// Create a deliverer for `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        Self {
+            url: url.into(),
+            client,
+        }
+    }
+}
+
+impl Deliverer for WebhookDeliverer {
+    fn deliver(&self, delivery: &Delivery) -> Result<(), String> {
+        self.client
+            .post(&self.url)
+            .header("Idempotency-Key", &delivery.id)
+            .json(&delivery.to_json())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Built-in deliverer running a process per delivery.
+///
+/// The delivery is written as JSON to the process's stdin, and its id is
+/// set in `DUET_DELIVERY_ID`; a zero exit status acknowledges it.
+pub struct ProcessDeliverer {
+    command: Vec<String>,
+}
+
+impl ProcessDeliverer {
+    /// Create a deliverer running `command` (program followed by arguments).
+    pub fn new(command: Vec<String>) -> Self {
+        Self { command }
+    }
+}
+
+impl Deliverer for ProcessDeliverer {
+    fn deliver(&self, delivery: &Delivery) -> Result<(), String> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| "empty delivery command".to_string())?;
+        let mut child = Command::new(program)
+            .args(args)
+            .env("DUET_DELIVERY_ID", &delivery.id)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("failed to run {}: {}", program, err))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(delivery.to_json().to_string().as_bytes())
+                .map_err(|err| err.to_string())?;
+        }
+        let status = child.wait().map_err(|err| err.to_string())?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("{} exited with {}", program, status))
+        }
+    }
+}
+
+/// Delivery waiting for an acknowledgement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingDelivery {
+    /// The delivery
+    pub delivery: Delivery,
+    /// Failed attempts so far
+    pub attempts: u32,
+    /// Error of the latest failed attempt
+    pub last_error: Option<String>,
+    /// Earliest time of the next attempt, after a failure
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+/// Snapshot of the outbox, as reported by
+/// [`Runtime::outbox_status`](super::Runtime::outbox_status)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxStatus {
+    /// Deliveries not acknowledged yet, oldest first
+    pub pending: Vec<PendingDelivery>,
+    /// Identifiers of the pending deliveries handed to a deliverer right now
+    pub in_flight: Vec<String>,
+    /// Number of acknowledged deliveries
+    pub acknowledged: usize,
+    /// Channels with a registered deliverer
+    pub channels: Vec<String>,
+}
+
+/// Delivery that was carried out, kept so it is never dispatched again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Acknowledgement {
+    id: String,
+    branch: BranchId,
+    turn_id: TurnId,
+}
+
+impl Acknowledgement {
+    fn of(delivery: &Delivery) -> Self {
+        Self {
+            id: delivery.id.clone(),
+            branch: delivery.branch.clone(),
+            turn_id: delivery.turn_id.clone(),
+        }
+    }
+}
+
+/// Entry of the outbox log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum LogEntry {
+    /// A delivery was staged, or its retry state changed
+    Pending(PendingDelivery),
+    /// A delivery was carried out
+    Acknowledged(Acknowledgement),
+    /// Acknowledgements on `branch` staged before `before` were dropped
+    Forgotten { branch: BranchId, before: TurnId },
+}
+
+/// Outbox state rebuilt from the log
+#[derive(Debug, Default)]
+struct OutboxState {
+    pending: Vec<PendingDelivery>,
+    acknowledged: BTreeMap<String, Acknowledgement>,
+}
+
+impl OutboxState {
+    fn apply(&mut self, entry: LogEntry) {
+        match entry {
+            LogEntry::Pending(pending) => {
+                if self.acknowledged.contains_key(&pending.delivery.id) {
+                    return;
+                }
+                match self
+                    .pending
+                    .iter_mut()
+                    .find(|existing| existing.delivery.id == pending.delivery.id)
+                {
+                    Some(existing) => *existing = pending,
+                    None => self.pending.push(pending),
+                }
+            }
+            LogEntry::Acknowledged(ack) => {
+                self.pending.retain(|pending| pending.delivery.id != ack.id);
+                self.acknowledged.insert(ack.id.clone(), ack);
+            }
+            LogEntry::Forgotten { branch, before } => {
+                self.acknowledged
+                    .retain(|_, ack| ack.branch != branch || ack.turn_id >= before);
+            }
+        }
+    }
+
+    /// Shortest log describing the state
+    fn entries(&self) -> Vec<LogEntry> {
+        self.acknowledged
+            .values()
+            .cloned()
+            .map(LogEntry::Acknowledged)
+            .chain(self.pending.iter().cloned().map(LogEntry::Pending))
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.pending.len() + self.acknowledged.len()
+    }
+}
+
+/// Append-only file of length-prefixed [`LogEntry`] records, shared with
+/// the worker thread so it can record acknowledgements itself.
+struct Log {
+    path: PathBuf,
+    file: File,
+    entries: usize,
+}
+
+impl Log {
+    /// Read the entries stored at `path`, stopping at a torn final record.
+    fn read(path: &Path) -> Result<Vec<LogEntry>, std::io::Error> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let data = std::fs::read(path)?;
+        let mut entries = Vec::new();
+        let mut rest = data.as_slice();
+        while rest.len() >= 4 {
+            let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+            let Some(frame) = rest.get(4..4 + len) else {
+                break;
+            };
+            entries.push(preserves::serde::from_bytes(frame).map_err(io_error)?);
+            rest = &rest[4 + len..];
+        }
+        Ok(entries)
+    }
+
+    /// Write `state` as a fresh log at `path` and open it for appending.
+    fn create(path: &Path, state: &OutboxState) -> Result<Self, std::io::Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let entries = state.entries();
+        let mut buf = Vec::new();
+        for entry in &entries {
+            buf.extend(frame(entry)?);
+        }
+        let staging = path.with_extension("bin.tmp");
+        std::fs::write(&staging, buf)?;
+        std::fs::rename(&staging, path)?;
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            entries: entries.len(),
+        })
+    }
+
+    fn append(&mut self, entry: &LogEntry) -> Result<(), std::io::Error> {
+        self.file.write_all(&frame(entry)?)?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    /// Rewrite the log as `state` once superseded entries outnumber live ones.
+    fn compact(&mut self, state: &OutboxState) -> Result<(), std::io::Error> {
+        if self.entries <= COMPACT_AFTER_ENTRIES.max(2 * state.len()) {
+            return Ok(());
+        }
+        *self = Self::create(&self.path, state)?;
+        Ok(())
+    }
+}
+
+fn frame(entry: &LogEntry) -> Result<Vec<u8>, std::io::Error> {
+    use preserves::PackedWriter;
+
+    let mut body = Vec::new();
+    let mut writer = PackedWriter::new(&mut body);
+    preserves::serde::to_writer(&mut writer, entry).map_err(io_error)?;
+    let mut buf = (body.len() as u32).to_le_bytes().to_vec();
+    buf.extend(body);
+    Ok(buf)
+}
+
+struct Job {
+    deliverer: Arc<dyn Deliverer>,
+    delivery: Delivery,
+}
+
+struct Ack {
+    id: String,
+    outcome: Result<(), String>,
+}
+
+/// Thread running deliveries in the order they are dispatched
+struct Worker {
+    jobs: Option<Sender<Job>>,
+    acks: Receiver<Ack>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    fn spawn(log: Arc<Mutex<Log>>) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let (ack, acks) = mpsc::channel();
+        let handle = std::thread::Builder::new()
+            .name("duet-outbox".into())
+            .spawn(move || {
+                for job in queue {
+                    let outcome = job.deliverer.deliver(&job.delivery);
+                    // Recorded here rather than by the runtime, which may sit
+                    // idle; the ack is sent under the lock so a compaction
+                    // never misses an entry it has not seen
+                    let mut log = log.lock();
+                    if outcome.is_ok() {
+                        let entry = LogEntry::Acknowledged(Acknowledgement::of(&job.delivery));
+                        if let Err(err) = log.append(&entry) {
+                            tracing::warn!(
+                                "failed to record outbox acknowledgement {}: {}",
+                                job.delivery.id,
+                                err
+                            );
+                        }
+                    }
+                    if ack
+                        .send(Ack {
+                            id: job.delivery.id,
+                            outcome,
+                        })
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            })
+            .expect("failed to spawn outbox worker");
+        Self {
+            jobs: Some(jobs),
+            acks,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // Closing the queue lets the worker finish the delivery at hand and exit
+        self.jobs.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Staged deliveries of a runtime and the deliverers that carry them out.
+pub struct Outbox {
+    log: Arc<Mutex<Log>>,
+    state: OutboxState,
+    deliverers: HashMap<String, Arc<dyn Deliverer>>,
+    in_flight: HashSet<String>,
+    worker: Option<Worker>,
+}
+
+impl std::fmt::Debug for Outbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Outbox")
+            .field("pending", &self.state.pending.len())
+            .field("acknowledged", &self.state.acknowledged.len())
+            .field("channels", &self.deliverers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Outbox {
+    /// Load the outbox logged at `path` (empty if the file does not exist),
+    /// rewriting the log compacted.
+    pub fn load(path: &Path) -> Result<Self, std::io::Error> {
+        let mut state = OutboxState::default();
+        for entry in Log::read(path)? {
+            state.apply(entry);
+        }
+        let log = Log::create(path, &state)?;
+        Ok(Self {
+            log: Arc::new(Mutex::new(log)),
+            state,
+            deliverers: HashMap::new(),
+            in_flight: HashSet::new(),
+            worker: None,
+        })
+    }
+
+    /// Carry out deliveries on `channel` with `deliverer`.
+    pub fn register(&mut self, channel: impl Into<String>, deliverer: Arc<dyn Deliverer>) {
+        self.deliverers.insert(channel.into(), deliverer);
+    }
+
+    /// Queue and log the deliveries staged by `record`, skipping any already
+    /// pending or acknowledged.
+    pub fn stage(&mut self, record: &TurnRecord) -> Result<(), std::io::Error> {
+        let log = self.log.clone();
+        let mut log = log.lock();
+        for delivery in deliveries_in(record) {
+            if self.state.acknowledged.contains_key(&delivery.id)
+                || self
+                    .state
+                    .pending
+                    .iter()
+                    .any(|pending| pending.delivery.id == delivery.id)
+            {
+                continue;
+            }
+            let entry = LogEntry::Pending(PendingDelivery {
+                delivery,
+                attempts: 0,
+                last_error: None,
+                retry_at: None,
+            });
+            log.append(&entry)?;
+            self.state.apply(entry);
+        }
+        log.compact(&self.state)
+    }
+
+    /// Record the acknowledgements that arrived and dispatch every pending
+    /// delivery that is due and has a deliverer.
+    pub fn pump(&mut self) -> Result<(), std::io::Error> {
+        self.collect_acks()?;
+        let now = Utc::now();
+        for pending in &self.state.pending {
+            let id = &pending.delivery.id;
+            if self.in_flight.contains(id) || pending.retry_at.is_some_and(|at| at > now) {
+                continue;
+            }
+            let Some(deliverer) = self.deliverers.get(&pending.delivery.channel) else {
+                continue;
+            };
+            let worker = self
+                .worker
+                .get_or_insert_with(|| Worker::spawn(self.log.clone()));
+            let sent = worker.jobs.as_ref().is_some_and(|jobs| {
+                jobs.send(Job {
+                    deliverer: deliverer.clone(),
+                    delivery: pending.delivery.clone(),
+                })
+                .is_ok()
+            });
+            if sent {
+                self.in_flight.insert(id.clone());
+            }
+        }
+        self.collect_acks()
+    }
+
+    /// Wait up to `timeout` for every in-flight delivery to be acknowledged.
+    pub fn wait(&mut self, timeout: std::time::Duration) -> Result<(), std::io::Error> {
+        let deadline = Instant::now() + timeout;
+        while !self.in_flight.is_empty() {
+            let Some(worker) = &self.worker else {
+                break;
+            };
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match worker.acks.recv_timeout(remaining) {
+                Ok(ack) => {
+                    let log = self.log.clone();
+                    self.acknowledge(&mut log.lock(), ack)?;
+                }
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the acknowledgements of deliveries staged on `branch` before
+    /// `before`, returning how many were dropped.
+    ///
+    /// Called once a snapshot covers `before`: the runtime neither recovers
+    /// nor replays turns behind it, so their deliveries cannot be staged
+    /// again except by rewinding past the snapshot.
+    pub fn forget_acknowledged_before(
+        &mut self,
+        branch: &BranchId,
+        before: &TurnId,
+    ) -> Result<usize, std::io::Error> {
+        let log = self.log.clone();
+        let mut log = log.lock();
+        let known = self.state.acknowledged.len();
+        let entry = LogEntry::Forgotten {
+            branch: branch.clone(),
+            before: before.clone(),
+        };
+        self.state.apply(entry.clone());
+        let forgotten = known - self.state.acknowledged.len();
+        if forgotten > 0 {
+            log.append(&entry)?;
+            log.compact(&self.state)?;
+        }
+        Ok(forgotten)
+    }
+
+    /// Pending deliveries and counters.
+    pub fn status(&self) -> OutboxStatus {
+        let mut channels: Vec<String> = self.deliverers.keys().cloned().collect();
+        channels.sort();
+        OutboxStatus {
+            pending: self.state.pending.clone(),
+            in_flight: self
+                .state
+                .pending
+                .iter()
+                .map(|pending| &pending.delivery.id)
+                .filter(|id| self.in_flight.contains(*id))
+                .cloned()
+                .collect(),
+            acknowledged: self.state.acknowledged.len(),
+            channels,
+        }
+    }
+
+    fn collect_acks(&mut self) -> Result<(), std::io::Error> {
+        let Some(worker) = &self.worker else {
+            return Ok(());
+        };
+        let log = self.log.clone();
+        let mut log = log.lock();
+        let acks: Vec<Ack> = worker.acks.try_iter().collect();
+        for ack in acks {
+            self.acknowledge(&mut log, ack)?;
+        }
+        log.compact(&self.state)
+    }
+
+    /// Apply an acknowledgement. Successes were logged by the worker;
+    /// failures are logged here with their backoff.
+    fn acknowledge(&mut self, log: &mut Log, ack: Ack) -> Result<(), std::io::Error> {
+        self.in_flight.remove(&ack.id);
+        let Some(pending) = self
+            .state
+            .pending
+            .iter()
+            .find(|pending| pending.delivery.id == ack.id)
+        else {
+            return Ok(());
+        };
+        let entry = match ack.outcome {
+            Ok(()) => LogEntry::Acknowledged(Acknowledgement::of(&pending.delivery)),
+            Err(err) => {
+                tracing::warn!(
+                    "outbox delivery {} on channel '{}' failed: {}",
+                    ack.id,
+                    pending.delivery.channel,
+                    err
+                );
+                let mut pending = pending.clone();
+                pending.attempts += 1;
+                let backoff = 1i64 << pending.attempts.min(16);
+                pending.retry_at =
+                    Some(Utc::now() + Duration::seconds(backoff.min(MAX_BACKOFF_SECS)));
+                pending.last_error = Some(err);
+                let entry = LogEntry::Pending(pending);
+                log.append(&entry)?;
+                entry
+            }
+        };
+        self.state.apply(entry);
+        Ok(())
+    }
+}
+
+fn io_error(err: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::state::StateDelta;
+    use crate::runtime::turn::LogicalClock;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    #[derive(Default)]
+    struct Recorder {
+        delivered: Mutex<Vec<String>>,
+        fail: Mutex<bool>,
+    }
+
+    impl Deliverer for Recorder {
+        fn deliver(&self, delivery: &Delivery) -> Result<(), String> {
+            if *self.fail.lock().unwrap() {
+                return Err("receiver unavailable".into());
+            }
+            self.delivered.lock().unwrap().push(delivery.id.clone());
+            Ok(())
+        }
+    }
+
+    fn staging_record() -> TurnRecord {
+        staging_record_at(1)
+    }
+
+    fn staging_record_at(seq: u64) -> TurnRecord {
+        TurnRecord::new(
+            ActorId::new(),
+            BranchId::main(),
+            LogicalClock::zero(),
+            None,
+            Vec::new(),
+            vec![TurnOutput::OutboxStaged {
+                entity_id: None,
+                channel: "hooks".into(),
+                payload: IOValue::symbol("built"),
+            }],
+            StateDelta::empty(),
+        )
+        .with_sequence(seq)
+    }
+
+    #[test]
+    fn test_acknowledged_deliveries_are_never_staged_again() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("outbox.bin");
+        let recorder = Arc::new(Recorder::default());
+        let record = staging_record();
+
+        let mut outbox = Outbox::load(&path).unwrap();
+        outbox.register("hooks", recorder.clone());
+        outbox.stage(&record).unwrap();
+        outbox.stage(&record).unwrap();
+        assert_eq!(outbox.status().pending.len(), 1);
+        outbox.pump().unwrap();
+        outbox.wait(std::time::Duration::from_secs(5)).unwrap();
+        assert_eq!(outbox.status().acknowledged, 1);
+        assert_eq!(recorder.delivered.lock().unwrap().len(), 1);
+
+        let mut reopened = Outbox::load(&path).unwrap();
+        reopened.register("hooks", recorder.clone());
+        reopened.stage(&record).unwrap();
+        reopened.pump().unwrap();
+        assert_eq!(reopened.status().acknowledged, 1);
+        assert!(reopened.status().pending.is_empty());
+        assert_eq!(recorder.delivered.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_worker_logs_acknowledgements_without_being_pumped() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("outbox.bin");
+        let recorder = Arc::new(Recorder::default());
+
+        let mut outbox = Outbox::load(&path).unwrap();
+        outbox.register("hooks", recorder.clone());
+        outbox.stage(&staging_record()).unwrap();
+        outbox.pump().unwrap();
+        // Dropping joins the worker once the delivery returns; the
+        // acknowledgement is never collected here
+        drop(outbox);
+        assert_eq!(recorder.delivered.lock().unwrap().len(), 1);
+
+        let reopened = Outbox::load(&path).unwrap();
+        assert!(reopened.status().pending.is_empty());
+        assert_eq!(reopened.status().acknowledged, 1);
+    }
+
+    #[test]
+    fn test_acknowledgements_behind_a_snapshot_are_forgotten() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("outbox.bin");
+        let recorder = Arc::new(Recorder::default());
+        let (older, newer) = (staging_record_at(1), staging_record_at(2));
+
+        let mut outbox = Outbox::load(&path).unwrap();
+        outbox.register("hooks", recorder.clone());
+        outbox.stage(&older).unwrap();
+        outbox.stage(&newer).unwrap();
+        outbox.pump().unwrap();
+        outbox.wait(std::time::Duration::from_secs(5)).unwrap();
+        assert_eq!(outbox.status().acknowledged, 2);
+
+        let other = BranchId::new("other");
+        assert_eq!(
+            outbox
+                .forget_acknowledged_before(&other, &newer.turn_id)
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            outbox
+                .forget_acknowledged_before(&BranchId::main(), &newer.turn_id)
+                .unwrap(),
+            1
+        );
+        drop(outbox);
+
+        let mut reopened = Outbox::load(&path).unwrap();
+        assert_eq!(reopened.status().acknowledged, 1);
+        reopened.stage(&newer).unwrap();
+        assert!(reopened.status().pending.is_empty());
+    }
+
+    #[test]
+    fn test_torn_final_log_record_is_dropped() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("outbox.bin");
+        let record = staging_record();
+
+        let mut outbox = Outbox::load(&path).unwrap();
+        outbox.stage(&record).unwrap();
+        let staged = std::fs::metadata(&path).unwrap().len();
+        assert!(staged > 0);
+
+        // A torn final record is ignored and the log rewritten without it
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[200, 0, 0, 0, 1]).unwrap();
+        drop(outbox);
+        let reopened = Outbox::load(&path).unwrap();
+        assert_eq!(reopened.status().pending.len(), 1);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), staged);
+    }
+
+    #[test]
+    fn test_failed_deliveries_stay_pending_with_backoff() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("outbox.bin");
+        let recorder = Arc::new(Recorder::default());
+        *recorder.fail.lock().unwrap() = true;
+
+        let mut outbox = Outbox::load(&path).unwrap();
+        outbox.register("hooks", recorder.clone());
+        outbox.stage(&staging_record()).unwrap();
+        outbox.pump().unwrap();
+        outbox.wait(std::time::Duration::from_secs(5)).unwrap();
+
+        let status = outbox.status();
+        assert_eq!(status.pending.len(), 1);
+        assert_eq!(status.pending[0].attempts, 1);
+        assert!(status.pending[0].retry_at.is_some());
+        assert!(status.in_flight.is_empty());
+
+        // Not due again until the backoff has passed
+        *recorder.fail.lock().unwrap() = false;
+        outbox.pump().unwrap();
+        assert!(outbox.status().in_flight.is_empty());
+        assert!(recorder.delivered.lock().unwrap().is_empty());
+
+        // The retry state is logged with the delivery
+        drop(outbox);
+        let reopened = Outbox::load(&path).unwrap();
+        assert_eq!(reopened.status().pending[0].attempts, 1);
+    }
+}
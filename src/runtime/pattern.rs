@@ -2,12 +2,19 @@
 //!
 //! Compiles and evaluates dataspace patterns, maintains subscription tables,
 //! and emits match/mismatch events.
+//!
+//! Patterns are preserves values in which wildcard symbols (`<_>`, `<id>`)
+//! match anything. [`parse_pattern`] reads them from a lighter textual
+//! syntax, `(agent-request $id _ _)`, so clients need not spell the
+//! wildcards out by hand.
 
+use preserves::IOValue;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
-use super::state::AssertionSet;
+use super::error::{Result, RuntimeError};
+use super::state::{AssertionSet, DEFAULT_NAMESPACE, namespace_matches};
 use super::turn::{ActorId, FacetId, Handle};
 
 /// Pattern identifier
@@ -25,6 +32,55 @@ pub struct Pattern {
 
     /// Facet that registered this pattern
     pub facet: FacetId,
+
+    /// Named dataspace this pattern observes (`None` = default dataspace)
+    #[serde(default)]
+    pub namespace: Option<String>,
+
+    /// Whose assertions this pattern observes
+    #[serde(default)]
+    pub scope: PatternScope,
+}
+
+/// Visibility of a pattern subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PatternScope {
+    /// Match only assertions made by the registering actor
+    #[default]
+    Actor,
+    /// Match assertions made by any actor in the dataspace
+    Dataspace,
+}
+
+impl Pattern {
+    /// Scope this pattern to a named dataspace.
+    pub fn in_namespace(mut self, namespace: impl Into<String>) -> Self {
+        let namespace = namespace.into();
+        self.namespace = (namespace != DEFAULT_NAMESPACE).then_some(namespace);
+        self
+    }
+
+    /// Observe assertions made by every actor, not just the registering one.
+    ///
+    /// Matching assertions from other actors are routed to the observer as
+    /// [`TurnInput::ObservedAssert`](super::turn::TurnInput::ObservedAssert) inputs.
+    pub fn dataspace_wide(mut self) -> Self {
+        self.scope = PatternScope::Dataspace;
+        self
+    }
+
+    /// Check whether `value`, published into `namespace`, satisfies this pattern.
+    pub fn matches(&self, value: &preserves::IOValue, namespace: Option<&str>) -> bool {
+        self.observes(namespace) && matches_pattern(&self.pattern, value)
+    }
+
+    /// Check whether an assertion published into `namespace` is visible to this pattern.
+    pub fn observes(&self, namespace: Option<&str>) -> bool {
+        namespace_matches(
+            self.namespace.as_deref().unwrap_or(DEFAULT_NAMESPACE),
+            namespace,
+        )
+    }
 }
 
 /// A match event
@@ -91,7 +147,10 @@ impl PatternEngine {
 
         let mut match_map = HashMap::new();
         for ((asserting_actor, handle), (value, _version)) in assertions.active.iter() {
-            if asserting_actor == actor_id && matches_pattern(&pattern.pattern, value) {
+            if asserting_actor == actor_id
+                && pattern.observes(assertions.namespace_of(asserting_actor, handle))
+                && matches_pattern(&pattern.pattern, value)
+            {
                 match_map.insert(
                     handle.clone(),
                     PatternMatch {
@@ -111,6 +170,33 @@ impl PatternEngine {
         self.matches.insert(pattern.id, match_map);
     }
 
+    /// Seed dataspace-wide matches from assertions made by other actors.
+    pub fn seed_remote_matches(&mut self, actor_id: &ActorId, assertions: &AssertionSet) {
+        for (pattern_id, pattern) in &self.patterns {
+            if pattern.scope != PatternScope::Dataspace {
+                continue;
+            }
+            for ((asserting_actor, handle), (value, _version)) in assertions.active.iter() {
+                if asserting_actor != actor_id
+                    && pattern.matches(value, assertions.namespace_of(asserting_actor, handle))
+                {
+                    self.matches.entry(*pattern_id).or_default().insert(
+                        handle.clone(),
+                        PatternMatch {
+                            pattern_id: *pattern_id,
+                            handle: handle.clone(),
+                            value: value.clone(),
+                        },
+                    );
+                    self.handle_to_patterns
+                        .entry(handle.clone())
+                        .or_default()
+                        .insert(*pattern_id);
+                }
+            }
+        }
+    }
+
     /// Unregister a pattern subscription
     pub fn unregister(&mut self, id: PatternId) {
         // Remove pattern
@@ -130,17 +216,62 @@ impl PatternEngine {
         }
     }
 
-    /// Evaluate all patterns against a new assertion
+    /// Evaluate all patterns against a new assertion in the default dataspace
     pub fn eval_assert(
         &mut self,
         handle: &Handle,
         value: &preserves::IOValue,
+    ) -> Vec<PatternMatch> {
+        self.eval_assert_in(handle, value, None)
+    }
+
+    /// Evaluate patterns scoped to `namespace` against a new assertion
+    pub fn eval_assert_in(
+        &mut self,
+        handle: &Handle,
+        value: &preserves::IOValue,
+        namespace: Option<&str>,
+    ) -> Vec<PatternMatch> {
+        self.eval_assert_scoped(handle, value, namespace, false)
+    }
+
+    /// Evaluate dataspace-wide patterns against an assertion made by another actor
+    pub fn eval_observed_assert(
+        &mut self,
+        handle: &Handle,
+        value: &preserves::IOValue,
+        namespace: Option<&str>,
+    ) -> Vec<PatternMatch> {
+        self.eval_assert_scoped(handle, value, namespace, true)
+    }
+
+    /// Check whether any dataspace-wide pattern would match an assertion from another actor
+    pub fn observes_remote(&self, value: &preserves::IOValue, namespace: Option<&str>) -> bool {
+        self.patterns.values().any(|pattern| {
+            pattern.scope == PatternScope::Dataspace && pattern.matches(value, namespace)
+        })
+    }
+
+    /// Check whether any pattern currently holds a match for `handle`
+    pub fn has_match_for(&self, handle: &Handle) -> bool {
+        self.handle_to_patterns.contains_key(handle)
+    }
+
+    fn eval_assert_scoped(
+        &mut self,
+        handle: &Handle,
+        value: &preserves::IOValue,
+        namespace: Option<&str>,
+        remote: bool,
     ) -> Vec<PatternMatch> {
         let mut new_matches = Vec::new();
 
-        // Test all registered patterns against this assertion
+        // Test all registered patterns in this namespace against this assertion
         for (pattern_id, pattern) in &self.patterns {
-            if matches_pattern(&pattern.pattern, value) {
+            if remote && pattern.scope != PatternScope::Dataspace {
+                continue;
+            }
+            if pattern.matches(value, namespace) {
                 let pattern_match = PatternMatch {
                     pattern_id: *pattern_id,
                     handle: handle.clone(),
@@ -201,7 +332,7 @@ impl PatternEngine {
 /// - Records match if labels match and all fields match recursively
 /// - Sequences match if lengths are equal and all elements match recursively
 /// - Sets and dictionaries use structural equality (no wildcard support yet)
-fn matches_pattern(pattern: &preserves::IOValue, value: &preserves::IOValue) -> bool {
+pub(crate) fn matches_pattern(pattern: &preserves::IOValue, value: &preserves::IOValue) -> bool {
     use preserves::ValueImpl;
 
     // Check for wildcard symbol pattern
@@ -311,6 +442,162 @@ fn is_wildcard_symbol(sym: &str) -> bool {
     sym.starts_with('<') && sym.ends_with('>')
 }
 
+/// Parse a pattern written in the textual pattern syntax.
+///
+/// - `(label field ...)` is a record and `[item ...]` a sequence
+/// - `_` matches anything, and so does `$name`, which also names the position
+/// - strings, numbers, `#t`/`#f` and bare symbols match themselves
+///
+/// Text starting with `<` is read as plain Preserves text instead, so
+/// patterns spelled with quoted wildcard symbols (`<agent-request '<_>'>`)
+/// keep working.
+pub fn parse_pattern(text: &str) -> Result<IOValue> {
+    if text.trim_start().starts_with('<') {
+        return text.parse().map_err(|err| RuntimeError::PatternSyntax {
+            offset: 0,
+            message: format!("{}", err),
+        });
+    }
+
+    let mut parser = PatternParser { text, offset: 0 };
+    let pattern = parser.term()?;
+    parser.skip_whitespace();
+    if parser.offset < text.len() {
+        return Err(parser.error("unexpected text after the pattern"));
+    }
+    Ok(pattern)
+}
+
+/// Recursive-descent reader for [`parse_pattern`].
+struct PatternParser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl PatternParser<'_> {
+    fn term(&mut self) -> Result<IOValue> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error("expected a pattern")),
+            Some('(') => {
+                self.offset += 1;
+                self.skip_whitespace();
+                if matches!(self.peek(), Some(')') | None) {
+                    return Err(self.error("expected a record label"));
+                }
+                let label = self.term()?;
+                let fields = self.items(')')?;
+                Ok(IOValue::record(label, fields))
+            }
+            Some('[') => {
+                self.offset += 1;
+                Ok(IOValue::new(self.items(']')?))
+            }
+            Some(close @ (')' | ']')) => Err(self.error(&format!("unexpected '{}'", close))),
+            Some('"') => self.string(),
+            Some(_) => self.atom(),
+        }
+    }
+
+    /// Terms up to and including the `close` delimiter.
+    fn items(&mut self, close: char) -> Result<Vec<IOValue>> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Err(self.error(&format!("expected '{}'", close))),
+                Some(c) if c == close => {
+                    self.offset += 1;
+                    return Ok(items);
+                }
+                Some(_) => items.push(self.term()?),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<IOValue> {
+        let start = self.offset;
+        self.offset += 1;
+        let mut value = String::new();
+        while let Some(c) = self.peek() {
+            self.offset += c.len_utf8();
+            match c {
+                '"' => return Ok(IOValue::new(value)),
+                '\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.error("unfinished escape"))?;
+                    self.offset += escaped.len_utf8();
+                    value.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        other => other,
+                    });
+                }
+                c => value.push(c),
+            }
+        }
+        self.offset = start;
+        Err(self.error("unterminated string"))
+    }
+
+    fn atom(&mut self) -> Result<IOValue> {
+        let start = self.offset;
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | '"') {
+                break;
+            }
+            self.offset += c.len_utf8();
+        }
+        let atom = &self.text[start..self.offset];
+
+        if atom == "_" {
+            return Ok(IOValue::symbol("<_>"));
+        }
+        if let Some(name) = atom.strip_prefix('$') {
+            if name.is_empty() {
+                self.offset = start;
+                return Err(self.error("expected a name after '$'"));
+            }
+            return Ok(IOValue::symbol(format!("<{}>", name)));
+        }
+        match atom {
+            "#t" => return Ok(IOValue::new(true)),
+            "#f" => return Ok(IOValue::new(false)),
+            _ => {}
+        }
+        let numeric = atom
+            .strip_prefix(['-', '+'])
+            .unwrap_or(atom)
+            .starts_with(|c: char| c.is_ascii_digit());
+        if numeric {
+            if let Ok(integer) = atom.parse::<i64>() {
+                return Ok(IOValue::new(integer));
+            }
+            if let Ok(float) = atom.parse::<f64>() {
+                return Ok(IOValue::new(float));
+            }
+        }
+        Ok(IOValue::symbol(atom.to_string()))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.offset += c.len_utf8();
+        }
+    }
+
+    fn error(&self, message: &str) -> RuntimeError {
+        RuntimeError::PatternSyntax {
+            offset: self.offset,
+            message: message.to_string(),
+        }
+    }
+}
+
 impl Default for PatternEngine {
     fn default() -> Self {
         Self::new()
@@ -329,6 +616,8 @@ mod tests {
             id: Uuid::new_v4(),
             pattern: IOValue::symbol("test-pattern"),
             facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         let id = pattern.id;
@@ -345,6 +634,8 @@ mod tests {
             id: pattern_id,
             pattern: IOValue::symbol("hello"),
             facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         engine.register(pattern);
@@ -369,6 +660,8 @@ mod tests {
             id: pattern_id,
             pattern: IOValue::symbol("<_>"),
             facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         engine.register(pattern);
@@ -402,6 +695,8 @@ mod tests {
             id: pattern_id,
             pattern: pattern_value,
             facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         engine.register(pattern);
@@ -447,6 +742,8 @@ mod tests {
             id: pattern_id,
             pattern: pattern_value,
             facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         engine.register(pattern);
@@ -488,6 +785,8 @@ mod tests {
             id: pattern_id,
             pattern: IOValue::symbol("<_>"),
             facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         engine.register(pattern);
@@ -521,6 +820,8 @@ mod tests {
             id: pattern1_id,
             pattern: IOValue::symbol("<_>"), // Matches anything
             facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
         engine.register(pattern1);
 
@@ -529,6 +830,8 @@ mod tests {
             id: pattern2_id,
             pattern: IOValue::symbol("test"), // Matches exact symbol
             facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
         engine.register(pattern2);
 
@@ -546,6 +849,52 @@ mod tests {
         assert_eq!(affected.len(), 2);
     }
 
+    #[test]
+    fn test_namespaced_patterns_only_see_their_dataspace() {
+        let mut engine = PatternEngine::new();
+        let scoped = Pattern {
+            id: Uuid::new_v4(),
+            pattern: IOValue::symbol("<_>"),
+            facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
+        }
+        .in_namespace("agents");
+        let unscoped = Pattern {
+            id: Uuid::new_v4(),
+            pattern: IOValue::symbol("<_>"),
+            facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
+        };
+        let scoped_id = scoped.id;
+        let unscoped_id = unscoped.id;
+        engine.register(scoped);
+        engine.register(unscoped);
+
+        let matches = engine.eval_assert_in(&Handle::new(), &IOValue::symbol("a"), Some("agents"));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].pattern_id, scoped_id);
+
+        let matches = engine.eval_assert(&Handle::new(), &IOValue::symbol("b"));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].pattern_id, unscoped_id);
+
+        let matches = engine.eval_assert_in(&Handle::new(), &IOValue::symbol("c"), Some("system"));
+        assert!(matches.is_empty());
+
+        // Naming the default dataspace explicitly is the same as leaving it unscoped
+        let explicit = Pattern {
+            id: Uuid::new_v4(),
+            pattern: IOValue::symbol("<_>"),
+            facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
+        }
+        .in_namespace(DEFAULT_NAMESPACE);
+        assert!(explicit.namespace.is_none());
+    }
+
     #[test]
     fn test_unregister_pattern() {
         let mut engine = PatternEngine::new();
@@ -554,6 +903,8 @@ mod tests {
             id: pattern_id,
             pattern: IOValue::symbol("<_>"),
             facet: FacetId::new(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         engine.register(pattern);
@@ -577,4 +928,33 @@ mod tests {
         assert!(!engine.handle_to_patterns.contains_key(&handle1));
         assert!(!engine.handle_to_patterns.contains_key(&handle2));
     }
+
+    #[test]
+    fn test_parse_pattern_syntax() {
+        let pattern = parse_pattern(r#"(agent-request $id _ "hi \"there\"" [1 -2.5 #t])"#).unwrap();
+        let expected: IOValue = r#"<agent-request '<id>' '<_>' "hi \"there\"" [1 -2.5 #t]>"#
+            .parse()
+            .unwrap();
+        assert_eq!(pattern, expected);
+
+        let value: IOValue = r#"<agent-request "r1" 7 "hi \"there\"" [1 -2.5 #t]>"#
+            .parse()
+            .unwrap();
+        assert!(matches_pattern(&pattern, &value));
+
+        // Preserves text is still accepted as is
+        assert_eq!(
+            parse_pattern("<agent-request '<_>'>").unwrap(),
+            parse_pattern("(agent-request _)").unwrap()
+        );
+
+        for (text, offset) in [("(agent-request $id", 18), ("(a) b", 4), ("()", 1)] {
+            match parse_pattern(text) {
+                Err(RuntimeError::PatternSyntax { offset: at, .. }) => {
+                    assert_eq!(at, offset, "{}", text)
+                }
+                other => panic!("{}: expected a syntax error, got {:?}", text, other),
+            }
+        }
+    }
 }
@@ -0,0 +1,212 @@
+//! Rate limits on external message ingestion
+//!
+//! Every external message becomes a journaled turn, so a client that floods
+//! `send_message` grows the journal and starves everyone else. Ingestion is
+//! therefore metered with token buckets: one per target actor, enforced by
+//! [`Control`](super::control::Control) for every message it accepts, and
+//! one per control-plane client, enforced by the service before a command
+//! reaches the runtime. A rejected message fails with
+//! [`RuntimeError::RateLimited`] before anything is scheduled, so the
+//! deterministic core never sees it.
+//!
+//! Limits are configured under
+//! [`LimitsConfig::ingress`](super::limits::LimitsConfig::ingress) and are
+//! off by default.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::error::{Result, RuntimeError};
+
+/// Token-bucket limit: a sustained rate plus a burst allowance.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Messages admitted per second once the burst is spent
+    pub per_second: f64,
+    /// Messages that may arrive back to back
+    pub burst: u32,
+}
+
+/// Ingestion limits per source (`None` = unlimited).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngressLimits {
+    /// Limit on messages delivered to any one actor
+    pub per_actor: Option<RateLimit>,
+    /// Limit on messages sent by any one control-plane client
+    pub per_client: Option<RateLimit>,
+}
+
+/// What a bucket is keyed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateScope {
+    /// Target actor
+    Actor,
+    /// Control-plane client
+    Client,
+}
+
+impl RateScope {
+    /// Name used in errors and metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateScope::Actor => "actor",
+            RateScope::Client => "client",
+        }
+    }
+}
+
+/// Admission counters for one source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitStats {
+    /// Kind of source
+    pub scope: RateScope,
+    /// Actor id or client name
+    pub key: String,
+    /// Messages let through
+    pub admitted: u64,
+    /// Messages rejected
+    pub limited: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counters {
+    admitted: u64,
+    limited: u64,
+}
+
+/// Token buckets for every source seen so far.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    limits: IngressLimits,
+    buckets: HashMap<(RateScope, String), Bucket>,
+    counters: BTreeMap<(RateScope, String), Counters>,
+}
+
+impl RateLimiter {
+    /// Create a limiter enforcing `limits`.
+    pub fn new(limits: IngressLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Limits in effect.
+    pub fn limits(&self) -> &IngressLimits {
+        &self.limits
+    }
+
+    /// Replace the limits; existing buckets start over.
+    pub fn set_limits(&mut self, limits: IngressLimits) {
+        self.limits = limits;
+        self.buckets.clear();
+    }
+
+    /// Take a token for one message from `key`, or fail with
+    /// [`RuntimeError::RateLimited`] when its bucket is empty.
+    pub fn admit(&mut self, scope: RateScope, key: &str) -> Result<()> {
+        self.admit_at(scope, key, Instant::now())
+    }
+
+    fn admit_at(&mut self, scope: RateScope, key: &str, now: Instant) -> Result<()> {
+        let limit = match scope {
+            RateScope::Actor => self.limits.per_actor,
+            RateScope::Client => self.limits.per_client,
+        };
+        let id = (scope, key.to_string());
+        let Some(limit) = limit else {
+            self.counters.entry(id).or_default().admitted += 1;
+            return Ok(());
+        };
+
+        let capacity = f64::from(limit.burst.max(1));
+        let bucket = self.buckets.entry(id.clone()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(capacity);
+        bucket.updated = now;
+
+        let counters = self.counters.entry(id).or_default();
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            counters.admitted += 1;
+            return Ok(());
+        }
+
+        counters.limited += 1;
+        let retry_after = if limit.per_second > 0.0 {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / limit.per_second)
+        } else {
+            Duration::MAX
+        };
+        tracing::warn!(scope = scope.as_str(), key, "rate limited external message");
+        Err(RuntimeError::RateLimited {
+            scope: scope.as_str().to_string(),
+            key: key.to_string(),
+            retry_after_ms: u64::try_from(retry_after.as_millis()).unwrap_or(u64::MAX),
+        })
+    }
+
+    /// Admission counters per source, ordered by scope and key.
+    pub fn stats(&self) -> Vec<RateLimitStats> {
+        self.counters
+            .iter()
+            .map(|((scope, key), counters)| RateLimitStats {
+                scope: *scope,
+                key: key.clone(),
+                admitted: counters.admitted,
+                limited: counters.limited,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_allow_bursts_then_refill_at_the_sustained_rate() {
+        let mut limiter = RateLimiter::new(IngressLimits {
+            per_actor: Some(RateLimit {
+                per_second: 2.0,
+                burst: 2,
+            }),
+            per_client: None,
+        });
+        let start = Instant::now();
+
+        assert!(limiter.admit_at(RateScope::Actor, "a", start).is_ok());
+        assert!(limiter.admit_at(RateScope::Actor, "a", start).is_ok());
+        match limiter.admit_at(RateScope::Actor, "a", start) {
+            Err(RuntimeError::RateLimited { retry_after_ms, .. }) => {
+                assert_eq!(retry_after_ms, 500)
+            }
+            other => panic!("expected RateLimited, got {other:?}"),
+        }
+
+        // Other actors and unlimited scopes are unaffected
+        assert!(limiter.admit_at(RateScope::Actor, "b", start).is_ok());
+        assert!(limiter.admit_at(RateScope::Client, "cli", start).is_ok());
+
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.admit_at(RateScope::Actor, "a", later).is_ok());
+        assert!(limiter.admit_at(RateScope::Actor, "a", later).is_err());
+
+        let stats = limiter.stats();
+        let a = stats.iter().find(|stat| stat.key == "a").unwrap();
+        assert_eq!((a.admitted, a.limited), (3, 2));
+    }
+}
@@ -4,9 +4,16 @@
 //! effects when matches occur. Reactions are scoped to the facet that
 //! registered them and execute within the same activation, ensuring
 //! compatibility with time-travel and replay.
+//!
+//! When several reactions match the same assertion they fire in order of
+//! descending [`ReactionDefinition::priority`], ties broken by reaction id, so
+//! dispatch is identical on every replay. A reaction marked
+//! [`consume`](ReactionDefinition::consume) stops reactions of lower priority
+//! from firing on that assertion once it has fired successfully.
 
 use super::pattern::{Pattern, PatternMatch};
 use super::registry::preserves_text_serde;
+use super::state::CapId;
 use super::turn::{ActorId, FacetId};
 use chrono::{DateTime, Utc};
 use preserves::IOValue;
@@ -39,6 +46,34 @@ pub enum ReactionEffect {
         /// Message payload.
         payload: ReactionValue,
     },
+    /// Invoke a capability; its result is asserted back to the reacting actor
+    /// as a `tool-result` record on the pattern facet.
+    InvokeCapability {
+        /// Capability to invoke.
+        capability: ReactionCapability,
+        /// Invocation payload.
+        payload: ReactionValue,
+        /// Correlation tag carried by the result (defaults to the reaction id).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+    },
+}
+
+/// Capability invoked by a [`ReactionEffect::InvokeCapability`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ReactionCapability {
+    /// A specific capability.
+    Id {
+        /// Capability identifier.
+        id: CapId,
+    },
+    /// The active capability of this kind held by the reacting actor (lowest
+    /// id first when there are several).
+    Kind {
+        /// Capability kind, e.g. `workspace/write`.
+        kind: String,
+    },
 }
 
 /// Reaction values can be literal preserves values or derived from the match.
@@ -69,6 +104,12 @@ pub struct ReactionDefinition {
     pub pattern: Pattern,
     /// Effect executed whenever the pattern matches.
     pub effect: ReactionEffect,
+    /// Dispatch priority; higher fires first on the same assertion.
+    #[serde(default)]
+    pub priority: i32,
+    /// Whether firing stops lower-priority reactions on the same assertion.
+    #[serde(default)]
+    pub consume: bool,
 }
 
 impl ReactionDefinition {
@@ -78,8 +119,22 @@ impl ReactionDefinition {
             id: ReactionId::new_v4(),
             pattern,
             effect,
+            priority: 0,
+            consume: false,
         }
     }
+
+    /// Set the dispatch priority.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Stop lower-priority reactions from firing after this one.
+    pub fn consuming(mut self) -> Self {
+        self.consume = true;
+        self
+    }
 }
 
 impl ReactionValue {
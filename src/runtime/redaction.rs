@@ -0,0 +1,278 @@
+//! Redaction of journaled payloads
+//!
+//! Transcripts, file contents and tool output reach the journal verbatim, so a
+//! `.duet` directory can leak whatever secrets passed through it. The
+//! [`Redactor`] rewrites string payloads before they are written, replacing
+//! every span matched by a configured rule with a placeholder of the form
+//! `[REDACTED:<rule>:<digest>]`. The digest is a truncated BLAKE3 hash of the
+//! matched text: the same secret always yields the same placeholder, so
+//! redacted journals stay comparable and anyone holding the original can check
+//! it, but the text itself is gone.
+//!
+//! Byte strings that hold UTF-8 text, such as the file contents journaled as
+//! write backups, are redacted like strings; other bytes are left alone. A
+//! file restored from a redacted backup gets the placeholders back, not the
+//! secrets.
+//!
+//! Redaction only touches what is persisted. Live state keeps the original
+//! values until it is rebuilt from the journal, e.g. by `goto`.
+
+use preserves::{AtomClass, CompoundClass, IOValue, Map, Set, ValueClass};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+use super::state::AssertionSet;
+use super::turn::TurnRecord;
+
+/// Hex digits of the matched text's hash kept in placeholders.
+const DIGEST_LEN: usize = 12;
+
+/// Redaction rules applied to journaled payloads.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    /// Rules applied in order
+    #[serde(default)]
+    pub rules: Vec<RedactionRule>,
+}
+
+/// A named pattern whose matches are redacted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionRule {
+    /// Name shown in placeholders
+    pub name: String,
+    /// Regular expression matching the spans to redact
+    pub pattern: String,
+}
+
+/// Compiled redaction rules.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    rules: Vec<(String, Regex)>,
+}
+
+impl Redactor {
+    /// Compile the rules in `config`.
+    pub fn from_config(config: &RedactionConfig) -> Result<Self, String> {
+        let rules = config
+            .rules
+            .iter()
+            .map(|rule| {
+                Regex::new(&rule.pattern)
+                    .map(|regex| (rule.name.clone(), regex))
+                    .map_err(|err| format!("invalid redaction rule '{}': {}", rule.name, err))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Whether any rules are configured.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Redact matched spans in `text`.
+    pub fn redact_str<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for (name, regex) in &self.rules {
+            if let Cow::Owned(replaced) = regex.replace_all(&text, |caps: &regex::Captures<'_>| {
+                let digest = blake3::hash(caps[0].as_bytes()).to_hex();
+                format!("[REDACTED:{}:{}]", name, &digest[..DIGEST_LEN])
+            }) {
+                text = Cow::Owned(replaced);
+            }
+        }
+        text
+    }
+
+    /// Redact every string inside `value`.
+    pub fn redact_value(&self, value: &IOValue) -> IOValue {
+        if self.is_empty() {
+            return value.clone();
+        }
+        self.rewrite(value)
+    }
+
+    fn rewrite(&self, value: &IOValue) -> IOValue {
+        match value.value_class() {
+            ValueClass::Atomic(AtomClass::String) => match value.as_string() {
+                Some(text) => match self.redact_str(&text) {
+                    Cow::Owned(redacted) => IOValue::new(redacted),
+                    Cow::Borrowed(_) => value.clone(),
+                },
+                None => value.clone(),
+            },
+            ValueClass::Atomic(AtomClass::ByteString) => {
+                let Some(bytes) = value.as_bytestring() else {
+                    return value.clone();
+                };
+                match std::str::from_utf8(&bytes).map(|text| self.redact_str(text)) {
+                    Ok(Cow::Owned(redacted)) => IOValue::bytes(redacted.into_bytes()),
+                    _ => value.clone(),
+                }
+            }
+            ValueClass::Atomic(_) | ValueClass::Embedded => value.clone(),
+            ValueClass::Compound(CompoundClass::Record) => IOValue::record(
+                self.rewrite(&IOValue::from(value.label())),
+                value
+                    .iter()
+                    .map(|field| self.rewrite(&IOValue::from(field)))
+                    .collect(),
+            ),
+            ValueClass::Compound(CompoundClass::Sequence) => IOValue::new(
+                value
+                    .iter()
+                    .map(|item| self.rewrite(&IOValue::from(item)))
+                    .collect::<Vec<_>>(),
+            ),
+            ValueClass::Compound(CompoundClass::Set) => IOValue::new(
+                value
+                    .iter()
+                    .map(|item| self.rewrite(&IOValue::from(item)))
+                    .collect::<Set<_>>(),
+            ),
+            ValueClass::Compound(CompoundClass::Dictionary) => IOValue::new(
+                value
+                    .entries()
+                    .map(|(key, entry)| {
+                        (
+                            self.rewrite(&IOValue::from(key)),
+                            self.rewrite(&IOValue::from(entry)),
+                        )
+                    })
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+
+    /// Copy of `record` with its payloads redacted.
+    ///
+    /// Ids, clocks and structure are left alone, so the turn id still hashes
+    /// the original inputs.
+    pub fn redact_record<'a>(&self, record: &'a TurnRecord) -> Cow<'a, TurnRecord> {
+        if self.is_empty() {
+            return Cow::Borrowed(record);
+        }
+        let mut record = record.clone();
+        for value in record.payloads_mut() {
+            *value = self.rewrite(value);
+        }
+        Cow::Owned(record)
+    }
+
+    /// Redact assertion values in place (used for snapshots).
+    pub fn redact_assertions(&self, assertions: &mut AssertionSet) {
+        if self.is_empty() {
+            return;
+        }
+        for (value, _version) in assertions.active.values_mut() {
+            *value = self.rewrite(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> Redactor {
+        Redactor::from_config(&RedactionConfig {
+            rules: vec![RedactionRule {
+                name: "api-key".into(),
+                pattern: r"sk-[A-Za-z0-9]+".into(),
+            }],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn placeholders_are_deterministic_and_nested_strings_are_redacted() {
+        let redactor = redactor();
+        let value = IOValue::record(
+            IOValue::symbol("transcript"),
+            vec![
+                IOValue::new("token sk-abc123 used".to_string()),
+                IOValue::new(vec![IOValue::new("again sk-abc123".to_string())]),
+                IOValue::symbol("sk-symbol"),
+            ],
+        );
+
+        let redacted = redactor.redact_value(&value);
+        let first = redacted.index(0).as_string().unwrap().into_owned();
+        let nested = redacted.index(1).index(0).as_string().unwrap().into_owned();
+
+        assert!(!first.contains("sk-abc123"));
+        assert!(first.starts_with("token [REDACTED:api-key:"));
+        let placeholder = &first["token ".len()..first.len() - " used".len()];
+        assert_eq!(nested, format!("again {}", placeholder));
+        // Symbols are structure, not payload
+        assert_eq!(
+            IOValue::from(redacted.index(2)),
+            IOValue::symbol("sk-symbol")
+        );
+    }
+
+    #[test]
+    fn file_backups_and_outbox_deliveries_are_redacted() {
+        use crate::runtime::state::StateDelta;
+        use crate::runtime::turn::{ActorId, BranchId, LogicalClock, TurnId, TurnOutput};
+
+        let backup = |previous: &[u8]| TurnOutput::FileBackup {
+            entity_id: None,
+            path: "config.env".into(),
+            location: "/work/config.env".into(),
+            previous: Some(IOValue::bytes(previous.to_vec())),
+        };
+        let record = TurnRecord {
+            turn_id: TurnId::new("turn_00000001".to_string()),
+            actor: ActorId::new(),
+            branch: BranchId::main(),
+            clock: LogicalClock::zero(),
+            parent: None,
+            inputs: vec![],
+            outputs: vec![
+                backup(b"API_KEY=sk-abc123\n"),
+                backup(&[0xff, 0xfe, b's', b'k', b'-', b'x']),
+                TurnOutput::OutboxStaged {
+                    entity_id: None,
+                    channel: "slack".into(),
+                    payload: IOValue::new("deploy with sk-abc123".to_string()),
+                },
+            ],
+            delta: StateDelta::empty(),
+            timestamp: chrono::Utc::now(),
+            vector_clock: Default::default(),
+            initiator: None,
+        };
+
+        let redacted = redactor().redact_record(&record).into_owned();
+        let previous = |index: usize| match &redacted.outputs[index] {
+            TurnOutput::FileBackup {
+                previous: Some(previous),
+                ..
+            } => previous.as_bytestring().unwrap().into_owned(),
+            other => panic!("unexpected output {other:?}"),
+        };
+        let text = String::from_utf8(previous(0)).unwrap();
+        assert!(text.starts_with("API_KEY=[REDACTED:api-key:"));
+        // Binary content is not text and stays as it was
+        assert_eq!(previous(1), vec![0xff, 0xfe, b's', b'k', b'-', b'x']);
+        assert!(matches!(
+            &redacted.outputs[2],
+            TurnOutput::OutboxStaged { payload, .. }
+                if !payload.as_string().unwrap().contains("sk-abc123")
+        ));
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let err = Redactor::from_config(&RedactionConfig {
+            rules: vec![RedactionRule {
+                name: "broken".into(),
+                pattern: "(".into(),
+            }],
+        })
+        .unwrap_err();
+        assert!(err.contains("broken"));
+    }
+}
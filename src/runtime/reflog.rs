@@ -0,0 +1,52 @@
+//! Record of where branch heads have been
+//!
+//! Like git's reflog, every movement of a branch head (a committed turn, a
+//! `goto` or `back`, a merge) is appended to a per-branch log with the time
+//! and the identity of the client that caused it. The journal only keeps the
+//! turns themselves, so without it a head moved back by mistake could only be
+//! found again by searching history; with it,
+//! [`Runtime::restore_head`](super::Runtime::restore_head) puts the head back
+//! where any entry left it.
+//!
+//! Entries are listed newest first: index 0 is the latest movement, the way
+//! `HEAD@{0}` is in git.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::identity::Identity;
+use super::turn::TurnId;
+
+/// What moved a branch head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadMove {
+    /// A turn was executed on the branch
+    Step,
+    /// An administrative turn (pause, resume, flag change) was journaled
+    Admin,
+    /// History jump to a chosen turn
+    Goto,
+    /// Rewind by a number of turns
+    Back,
+    /// Another branch was merged into this one
+    Merge,
+    /// Head put back to a reflog entry
+    Restore,
+}
+
+/// One movement of a branch head.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReflogEntry {
+    /// Head before the movement (genesis for the first turn)
+    pub from: TurnId,
+    /// Head after the movement
+    pub to: TurnId,
+    /// Command that moved the head
+    pub action: HeadMove,
+    /// Client that caused the movement, if it named itself
+    #[serde(default)]
+    pub client: Option<Identity>,
+    /// When the head moved
+    pub at: DateTime<Utc>,
+}
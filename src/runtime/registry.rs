@@ -8,13 +8,13 @@ use once_cell::sync::Lazy;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::any::Any;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 
 use super::actor::{Entity, HydratableEntity};
 use super::error::{ActorResult, Result, StorageError};
 use super::pattern::Pattern;
-use super::turn::{ActorId, FacetId};
+use super::turn::{ActorId, BranchId, FacetId};
 
 /// Entity type name (e.g., "llm-assistant", "timer-manager")
 pub type EntityTypeName = &'static str;
@@ -34,6 +34,92 @@ struct EntityTypeInfo {
     factory: EntityFactory,
     snapshot: Option<SnapshotHandler>,
     restore: Option<RestoreHandler>,
+    concurrency: ConcurrencyClass,
+}
+
+/// How instances of an entity type may be entered concurrently.
+///
+/// The capability invoker refuses to re-enter an [`Exclusive`] entity that
+/// is already serving an invocation further up the call chain; the parallel
+/// scheduler only runs turns of [`ParallelSafe`] entities alongside other
+/// work.
+///
+/// [`Exclusive`]: ConcurrencyClass::Exclusive
+/// [`ParallelSafe`]: ConcurrencyClass::ParallelSafe
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConcurrencyClass {
+    /// One call at a time; nested invocations of the same entity are refused
+    #[default]
+    Exclusive,
+    /// Tolerates nested invocations while a call is in progress
+    Reentrant,
+    /// Internally thread-safe; may run in parallel with other entities
+    ParallelSafe,
+}
+
+impl ConcurrencyClass {
+    /// Whether an instance may be entered while it is already serving a call.
+    pub fn allows_reentry(self) -> bool {
+        !matches!(self, Self::Exclusive)
+    }
+}
+
+impl std::fmt::Display for ConcurrencyClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Exclusive => "exclusive",
+            Self::Reentrant => "reentrant",
+            Self::ParallelSafe => "parallel_safe",
+        })
+    }
+}
+
+/// What an entity type understands, as reported by [`Entity::describe`].
+///
+/// Tools use descriptors to build UIs and to validate requests before
+/// sending them. The runtime publishes non-empty descriptors into the hosting
+/// actor's dataspace as `<entity-descriptor type [label ...] [kind ...] schema>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntityDescriptor {
+    /// Labels of the message records (or bare symbols) the entity handles
+    pub message_labels: Vec<String>,
+    /// Capability kinds the entity grants and serves
+    pub capability_kinds: Vec<String>,
+    /// Shape of the configuration value the entity expects, if any
+    pub config_schema: Option<preserves::IOValue>,
+}
+
+impl EntityDescriptor {
+    /// Whether the entity advertised nothing.
+    pub fn is_empty(&self) -> bool {
+        self.message_labels.is_empty()
+            && self.capability_kinds.is_empty()
+            && self.config_schema.is_none()
+    }
+
+    /// `entity-descriptor` assertion for `entity_type`.
+    pub fn to_value(&self, entity_type: &str) -> preserves::IOValue {
+        let strings = |items: &[String]| {
+            preserves::IOValue::new(
+                items
+                    .iter()
+                    .map(|item| preserves::IOValue::new(item.clone()))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        preserves::IOValue::record(
+            preserves::IOValue::symbol("entity-descriptor"),
+            vec![
+                preserves::IOValue::new(entity_type.to_string()),
+                strings(&self.message_labels),
+                strings(&self.capability_kinds),
+                self.config_schema
+                    .clone()
+                    .unwrap_or_else(|| preserves::IOValue::new(false)),
+            ],
+        )
+    }
 }
 
 /// Global catalog of entity definitions.
@@ -67,6 +153,7 @@ impl EntityCatalog {
                 factory: Arc::new(factory),
                 snapshot: None,
                 restore: None,
+                concurrency: ConcurrencyClass::default(),
             },
         );
     }
@@ -111,15 +198,32 @@ impl EntityCatalog {
                 factory: entity_factory,
                 snapshot: Some(snapshot_handler),
                 restore: Some(restore_handler),
+                concurrency: ConcurrencyClass::default(),
             },
         );
     }
 
+    /// Declare how instances of a registered type may be entered concurrently.
+    ///
+    /// Types are [`ConcurrencyClass::Exclusive`] until declared otherwise;
+    /// registering a type again resets its class. Returns `false` when
+    /// `type_name` has not been registered.
+    pub fn declare_concurrency(&self, type_name: &str, class: ConcurrencyClass) -> bool {
+        match self.types.write().get_mut(type_name) {
+            Some(info) => {
+                info.concurrency = class;
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Produce an immutable snapshot for a runtime instance.
     pub fn snapshot(&self) -> EntityRegistry {
         let types = self.types.read();
         EntityRegistry {
             types: Arc::new(types.clone()),
+            descriptors: Arc::default(),
         }
     }
 }
@@ -128,6 +232,7 @@ impl EntityCatalog {
 #[derive(Clone)]
 pub struct EntityRegistry {
     types: Arc<HashMap<String, EntityTypeInfo>>,
+    descriptors: Arc<RwLock<BTreeMap<String, EntityDescriptor>>>,
 }
 
 impl EntityRegistry {
@@ -140,7 +245,19 @@ impl EntityRegistry {
             ))
         })?;
 
-        (info.factory)(config)
+        let entity = (info.factory)(config)?;
+        self.descriptors
+            .write()
+            .insert(type_name.to_string(), entity.describe());
+        Ok(entity)
+    }
+
+    /// Descriptor reported by instances of `type_name`.
+    ///
+    /// Descriptors are collected as entities are instantiated, so this is
+    /// `None` until the runtime has created at least one instance.
+    pub fn describe(&self, type_name: &str) -> Option<EntityDescriptor> {
+        self.descriptors.read().get(type_name).cloned()
     }
 
     /// Check whether the registry snapshot contains the specified type.
@@ -148,6 +265,19 @@ impl EntityRegistry {
         self.types.contains_key(type_name)
     }
 
+    /// Short, stable fingerprint of the registered type names.
+    pub fn fingerprint(&self) -> String {
+        let mut names: Vec<&str> = self.types.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        let digest = blake3::hash(names.join("\n").as_bytes());
+        digest.to_hex()[..16].to_string()
+    }
+
+    /// Concurrency class declared for `type_name`, if the type is known.
+    pub fn concurrency(&self, type_name: &str) -> Option<ConcurrencyClass> {
+        self.types.get(type_name).map(|info| info.concurrency)
+    }
+
     /// List all entity type identifiers known to this snapshot.
     pub fn list_types(&self) -> Vec<String> {
         self.types.keys().cloned().collect()
@@ -211,6 +341,19 @@ pub struct EntityMetadata {
 
     /// Pattern subscriptions registered by this entity
     pub patterns: Vec<Pattern>,
+
+    /// Branches the entity exists on (`None` = every branch)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branches: Option<BTreeSet<BranchId>>,
+}
+
+impl EntityMetadata {
+    /// Whether the entity is instantiated while `branch` is active.
+    pub fn visible_on(&self, branch: &BranchId) -> bool {
+        self.branches
+            .as_ref()
+            .is_none_or(|branches| branches.contains(branch))
+    }
 }
 
 /// Custom serde module for preserves::IOValue (serialize as text)
@@ -240,6 +383,9 @@ pub mod preserves_text_serde {
 pub struct EntityManager {
     /// Registered entities by instance ID
     pub(crate) entities: HashMap<uuid::Uuid, EntityMetadata>,
+
+    /// Named actor groups used for broadcast addressing
+    groups: BTreeMap<String, BTreeSet<ActorId>>,
 }
 
 impl EntityManager {
@@ -247,6 +393,7 @@ impl EntityManager {
     pub fn new() -> Self {
         Self {
             entities: HashMap::new(),
+            groups: BTreeMap::new(),
         }
     }
 
@@ -256,7 +403,10 @@ impl EntityManager {
             let data = std::fs::read(path).map_err(StorageError::from)?;
             let entities: HashMap<uuid::Uuid, EntityMetadata> =
                 serde_json::from_slice(&data).map_err(StorageError::from)?;
-            Ok(Self { entities })
+            Ok(Self {
+                entities,
+                groups: BTreeMap::new(),
+            })
         } else {
             Ok(Self::new())
         }
@@ -272,6 +422,57 @@ impl EntityManager {
         Ok(())
     }
 
+    /// Load actor group membership from a JSON file, if present
+    pub fn load_groups(&mut self, path: &std::path::Path) -> Result<()> {
+        if path.exists() {
+            let data = std::fs::read(path).map_err(StorageError::from)?;
+            self.groups = serde_json::from_slice(&data).map_err(StorageError::from)?;
+        }
+        Ok(())
+    }
+
+    /// Save actor group membership to disk
+    pub fn save_groups(&self, path: &std::path::Path) -> Result<()> {
+        let data = serde_json::to_vec_pretty(&self.groups).map_err(StorageError::from)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(StorageError::from)?;
+        }
+        std::fs::write(path, data).map_err(StorageError::from)?;
+        Ok(())
+    }
+
+    /// Add an actor to a named group, returning whether it was newly added
+    pub fn add_to_group(&mut self, group: impl Into<String>, actor: ActorId) -> bool {
+        self.groups.entry(group.into()).or_default().insert(actor)
+    }
+
+    /// Remove an actor from a named group, returning whether it was a member
+    ///
+    /// Groups left without members are dropped.
+    pub fn remove_from_group(&mut self, group: &str, actor: &ActorId) -> bool {
+        let Some(members) = self.groups.get_mut(group) else {
+            return false;
+        };
+        let removed = members.remove(actor);
+        if members.is_empty() {
+            self.groups.remove(group);
+        }
+        removed
+    }
+
+    /// Members of a named group in identifier order (empty if unknown)
+    pub fn group_members(&self, group: &str) -> Vec<ActorId> {
+        self.groups
+            .get(group)
+            .map(|members| members.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Names of all groups with at least one member
+    pub fn groups(&self) -> Vec<String> {
+        self.groups.keys().cloned().collect()
+    }
+
     /// Register new entity metadata
     pub fn register(&mut self, metadata: EntityMetadata) {
         self.entities.insert(metadata.id, metadata);
@@ -307,6 +508,27 @@ impl EntityManager {
         self.entities.values().collect()
     }
 
+    /// List metadata entries for entities that exist on `branch`
+    pub fn list_on(&self, branch: &BranchId) -> Vec<&EntityMetadata> {
+        self.entities
+            .values()
+            .filter(|metadata| metadata.visible_on(branch))
+            .collect()
+    }
+
+    /// Extend branch-scoped entities living on `source` to a branch forked from it
+    pub fn inherit_branch_scope(&mut self, source: &BranchId, fork: &BranchId) {
+        let scoped = self
+            .entities
+            .values_mut()
+            .filter_map(|metadata| metadata.branches.as_mut());
+        for branches in scoped {
+            if branches.contains(source) {
+                branches.insert(fork.clone());
+            }
+        }
+    }
+
     /// List metadata entries for a specific actor
     pub fn list_for_actor(&self, actor: &ActorId) -> Vec<&EntityMetadata> {
         self.entities
@@ -0,0 +1,132 @@
+//! Deterministic randomness for entities
+//!
+//! Entities that need random numbers (request ids, sampling, jitter) must not
+//! reach for an OS-seeded generator: a turn executed again after a rewind, or
+//! the same turn on a fork, would then see different numbers and diverge.
+//! [`Activation::rng`](super::actor::Activation::rng) instead hands out a
+//! generator seeded from the actor and the turn's position on its branch, so
+//! every execution of that turn draws the same sequence. How many values a
+//! turn drew is journaled as a [`TurnOutput::RandomDrawn`](super::turn::TurnOutput::RandomDrawn),
+//! along with the seed, so the draws can be reproduced when auditing history.
+//!
+//! The generator is BLAKE3 in counter mode: draw `n` is the keyed hash of
+//! `n` under the seed. It is stable across platforms and releases, and not
+//! meant for cryptographic secrets.
+
+use std::ops::Range;
+use uuid::Uuid;
+
+use super::turn::ActorId;
+
+/// Domain separator for seeds
+const SEED_CONTEXT: &str = "duet 2024 actor rng seed";
+
+/// Seeded random number generator of one actor for one turn.
+#[derive(Debug, Clone)]
+pub struct DeterministicRng {
+    seed: [u8; 32],
+    draws: u64,
+}
+
+impl DeterministicRng {
+    /// Generator for `actor`'s turn at branch sequence `sequence`.
+    pub fn for_turn(actor: &ActorId, sequence: u64) -> Self {
+        let mut material = actor.0.as_bytes().to_vec();
+        material.extend_from_slice(&sequence.to_le_bytes());
+        Self::from_seed(blake3::derive_key(SEED_CONTEXT, &material))
+    }
+
+    /// Generator drawing from `seed`.
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self { seed, draws: 0 }
+    }
+
+    /// Seed the generator draws from
+    pub fn seed(&self) -> [u8; 32] {
+        self.seed
+    }
+
+    /// Number of 64-bit values drawn so far
+    pub fn draws(&self) -> u64 {
+        self.draws
+    }
+
+    /// Next uniformly distributed `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        let block = blake3::keyed_hash(&self.seed, &self.draws.to_le_bytes());
+        self.draws += 1;
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&block.as_bytes()[..8]);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Uniformly distributed `f64` in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniformly distributed value in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is empty.
+    pub fn gen_range(&mut self, range: Range<u64>) -> u64 {
+        assert!(range.start < range.end, "empty range");
+        let span = range.end - range.start;
+        // Reject the tail that would bias the modulo
+        let zone = u64::MAX - u64::MAX % span;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return range.start + value % span;
+            }
+        }
+    }
+
+    /// Whether an event of probability `p` happens.
+    pub fn gen_bool(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+
+    /// Fill `buf` with random bytes.
+    pub fn fill_bytes(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    /// Random (version 4) UUID, e.g. for request ids.
+    pub fn uuid(&mut self) -> Uuid {
+        let mut bytes = [0u8; 16];
+        self.fill_bytes(&mut bytes);
+        uuid::Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draws_depend_only_on_actor_and_sequence() {
+        let actor = ActorId::new();
+        let mut first = DeterministicRng::for_turn(&actor, 7);
+        let mut again = DeterministicRng::for_turn(&actor, 7);
+        let drawn: Vec<u64> = (0..4).map(|_| first.next_u64()).collect();
+        assert_eq!(drawn, (0..4).map(|_| again.next_u64()).collect::<Vec<_>>());
+        assert_eq!(first.draws(), 4);
+
+        let mut next_turn = DeterministicRng::for_turn(&actor, 8);
+        assert_ne!(drawn[0], next_turn.next_u64());
+        let mut other_actor = DeterministicRng::for_turn(&ActorId::new(), 7);
+        assert_ne!(drawn[0], other_actor.next_u64());
+
+        let mut rng = DeterministicRng::from_seed([0; 32]);
+        for _ in 0..100 {
+            assert!((10..13).contains(&rng.gen_range(10..13)));
+            assert!((0.0..1.0).contains(&rng.next_f64()));
+        }
+        assert_eq!(rng.uuid().get_version_num(), 4);
+    }
+}
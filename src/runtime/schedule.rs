@@ -0,0 +1,130 @@
+//! Deterministic recurring inputs
+//!
+//! A recurring schedule delivers a fixed message to an actor/facet every
+//! `every_n_turns` turns of branch history, so housekeeping entities (rescans,
+//! garbage collection, archival) run without an external driver. Schedules are
+//! driven by the branch turn sequence rather than the wall clock: a firing is
+//! enqueued after the turn whose sequence number is a multiple of the
+//! interval, and is journaled like any other external message, so replay and
+//! time travel reproduce it exactly.
+//!
+//! Firings carry the idempotency key `schedule:<id>:<seq>`; turns consisting
+//! only of firings do not trigger further firings, so short intervals cannot
+//! keep the runtime busy on their own.
+
+use chrono::{DateTime, Utc};
+use preserves::IOValue;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use super::turn::{ActorId, FacetId, TurnInput};
+
+/// Identifier of a recurring schedule.
+pub type ScheduleId = Uuid;
+
+/// Prefix of the idempotency keys attached to scheduled firings.
+pub const SCHEDULE_KEY_PREFIX: &str = "schedule:";
+
+/// Message delivered to an actor every `every_n_turns` turns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecurringSchedule {
+    /// Identifier used to cancel the schedule
+    pub id: ScheduleId,
+    /// Actor receiving the message
+    pub actor: ActorId,
+    /// Facet receiving the message
+    pub facet: FacetId,
+    /// Message payload
+    pub payload: IOValue,
+    /// Interval between firings, in branch turns
+    pub every_n_turns: u64,
+    /// When the schedule was registered
+    pub created_at: DateTime<Utc>,
+}
+
+impl RecurringSchedule {
+    /// Whether the schedule fires after the turn with sequence number `seq`.
+    pub fn due_at(&self, seq: u64) -> bool {
+        seq > 0 && seq.is_multiple_of(self.every_n_turns)
+    }
+
+    /// Idempotency key of the firing that follows turn `seq`.
+    pub fn firing_key(&self, seq: u64) -> String {
+        format!("{SCHEDULE_KEY_PREFIX}{}:{seq}", self.id)
+    }
+}
+
+/// Whether every input of a turn is a scheduled firing.
+pub fn is_scheduled_turn(inputs: &[TurnInput]) -> bool {
+    !inputs.is_empty()
+        && inputs.iter().all(|input| {
+            matches!(
+                input,
+                TurnInput::ExternalMessage {
+                    idempotency_key: Some(key),
+                    ..
+                } if key.starts_with(SCHEDULE_KEY_PREFIX)
+            )
+        })
+}
+
+/// Persistent set of recurring schedules.
+#[derive(Debug, Default)]
+pub struct ScheduleStore {
+    entries: BTreeMap<ScheduleId, RecurringSchedule>,
+}
+
+impl ScheduleStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load schedules from disk, returning an empty store if the file is absent.
+    pub fn load(path: &Path) -> Result<Self, std::io::Error> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let data = std::fs::read(path)?;
+        let entries: Vec<RecurringSchedule> =
+            preserves::serde::from_bytes(&data).map_err(io_error)?;
+        Ok(Self {
+            entries: entries.into_iter().map(|entry| (entry.id, entry)).collect(),
+        })
+    }
+
+    /// Persist the store to disk, creating parent directories as needed.
+    pub fn save(&self, path: &Path) -> Result<(), std::io::Error> {
+        use preserves::PackedWriter;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let entries: Vec<&RecurringSchedule> = self.entries.values().collect();
+        let mut buf = Vec::new();
+        let mut writer = PackedWriter::new(&mut buf);
+        preserves::serde::to_writer(&mut writer, &entries).map_err(io_error)?;
+        std::fs::write(path, buf)
+    }
+
+    /// Add a schedule.
+    pub fn insert(&mut self, entry: RecurringSchedule) {
+        self.entries.insert(entry.id, entry);
+    }
+
+    /// Remove a schedule.
+    pub fn remove(&mut self, id: &ScheduleId) -> Option<RecurringSchedule> {
+        self.entries.remove(id)
+    }
+
+    /// Iterate over schedules in identifier order.
+    pub fn iter(&self) -> impl Iterator<Item = &RecurringSchedule> {
+        self.entries.values()
+    }
+}
+
+fn io_error(err: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+}
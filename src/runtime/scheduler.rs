@@ -1,11 +1,13 @@
 //! Deterministic turn scheduler and flow control
 //!
 //! Maintains ready queues per actor, enforces causal ordering,
-//! and integrates flow-control account limits.
+//! and integrates flow-control account limits. Turns of paused actors are
+//! held aside until the actor is resumed.
 
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
 
+use super::identity::Identity;
 use super::turn::{ActorId, LogicalClock, TurnInput};
 
 /// Scheduled turn ready for execution
@@ -19,6 +21,8 @@ pub struct ScheduledTurn {
     pub inputs: Vec<TurnInput>,
     /// Scheduling cause (for observability)
     pub cause: ScheduleCause,
+    /// Client identity the turn runs on behalf of
+    pub initiator: Option<Identity>,
 }
 
 impl PartialEq for ScheduledTurn {
@@ -55,6 +59,8 @@ pub enum ScheduleCause {
     Sync,
     /// Capability invocation
     Capability,
+    /// Assertion routed to a dataspace-wide pattern observer
+    Observation,
 }
 
 /// Deterministic turn scheduler
@@ -70,6 +76,15 @@ pub struct Scheduler {
 
     /// Flow-control credit limit
     credit_limit: i64,
+
+    /// Actors whose turns are not scheduled
+    paused: BTreeSet<ActorId>,
+
+    /// Turns of paused actors, in enqueue order
+    held: Vec<ScheduledTurn>,
+
+    /// Identity attributed to turns enqueued from now on
+    initiator: Option<Identity>,
 }
 
 impl Scheduler {
@@ -80,9 +95,23 @@ impl Scheduler {
             actor_clocks: HashMap::new(),
             account_balances: HashMap::new(),
             credit_limit,
+            paused: BTreeSet::new(),
+            held: Vec::new(),
+            initiator: None,
         }
     }
 
+    /// Attribute turns enqueued from now on to `initiator`, returning the
+    /// identity they were attributed to before.
+    pub fn set_initiator(&mut self, initiator: Option<Identity>) -> Option<Identity> {
+        std::mem::replace(&mut self.initiator, initiator)
+    }
+
+    /// Identity attributed to turns enqueued now
+    pub fn initiator(&self) -> Option<&Identity> {
+        self.initiator.as_ref()
+    }
+
     /// Enqueue a turn input
     pub fn enqueue(&mut self, actor: ActorId, input: TurnInput, cause: ScheduleCause) {
         // Get or initialize actor clock
@@ -97,10 +126,62 @@ impl Scheduler {
             clock: next_clock,
             inputs: vec![input],
             cause,
+            initiator: self.initiator.clone(),
         };
 
-        self.ready_queue.push(turn);
         *clock = next_clock;
+        if self.paused.contains(&actor) {
+            self.held.push(turn);
+        } else {
+            self.ready_queue.push(turn);
+        }
+    }
+
+    /// Stop scheduling turns for `actor`, holding queued ones.
+    ///
+    /// Returns `false` if the actor was already paused.
+    pub fn pause(&mut self, actor: &ActorId) -> bool {
+        if !self.paused.insert(actor.clone()) {
+            return false;
+        }
+        let (held, ready): (Vec<_>, Vec<_>) = std::mem::take(&mut self.ready_queue)
+            .into_sorted_vec()
+            .into_iter()
+            .rev()
+            .partition(|turn| turn.actor == *actor);
+        self.ready_queue = ready.into();
+        self.held.extend(held);
+        true
+    }
+
+    /// Resume scheduling turns for `actor`, releasing held ones.
+    ///
+    /// Returns `false` if the actor was not paused.
+    pub fn resume(&mut self, actor: &ActorId) -> bool {
+        if !self.paused.remove(actor) {
+            return false;
+        }
+        let (released, held): (Vec<_>, Vec<_>) = std::mem::take(&mut self.held)
+            .into_iter()
+            .partition(|turn| turn.actor == *actor);
+        self.held = held;
+        self.ready_queue.extend(released);
+        true
+    }
+
+    /// Whether `actor` is paused.
+    pub fn is_paused(&self, actor: &ActorId) -> bool {
+        self.paused.contains(actor)
+    }
+
+    /// Paused actors, in order.
+    pub fn paused_actors(&self) -> impl Iterator<Item = &ActorId> {
+        self.paused.iter()
+    }
+
+    /// Number of turns held for paused actors
+    pub fn held_count(&self) -> usize {
+        self.held.len()
     }
 
     /// Get the next ready turn (if any)
@@ -132,9 +213,9 @@ impl Scheduler {
         !self.ready_queue.is_empty()
     }
 
-    /// Get the number of pending turns
+    /// Get the number of pending turns, including held ones
     pub fn pending_count(&self) -> usize {
-        self.ready_queue.len()
+        self.ready_queue.len() + self.held.len()
     }
 
     /// Inspect the current account balance for an actor
@@ -157,6 +238,8 @@ mod tests {
             actor: actor.clone(),
             facet: FacetId::new(),
             payload: preserves::IOValue::symbol("empty"),
+            idempotency_key: None,
+            broadcast: None,
         };
 
         scheduler.enqueue(actor, input, ScheduleCause::External);
@@ -173,6 +256,8 @@ mod tests {
                 actor: actor.clone(),
                 facet: FacetId::new(),
                 payload: preserves::IOValue::new(preserves::SignedInteger::from(i)),
+                idempotency_key: None,
+                broadcast: None,
             };
             scheduler.enqueue(actor.clone(), input, ScheduleCause::External);
         }
@@ -193,6 +278,8 @@ mod tests {
             actor: actor.clone(),
             facet: FacetId::new(),
             payload: preserves::IOValue::symbol("empty"),
+            idempotency_key: None,
+            broadcast: None,
         };
 
         scheduler.enqueue(actor.clone(), input, ScheduleCause::External);
@@ -204,4 +291,36 @@ mod tests {
         assert!(scheduler.next_turn().is_none());
         assert_eq!(scheduler.account_balance(&actor), 15);
     }
+
+    #[test]
+    fn test_paused_actor_turns_are_held() {
+        let mut scheduler = Scheduler::new(1000);
+        let paused = ActorId::new();
+        let other = ActorId::new();
+        let message = |actor: &ActorId| TurnInput::ExternalMessage {
+            actor: actor.clone(),
+            facet: FacetId::new(),
+            payload: preserves::IOValue::symbol("empty"),
+            idempotency_key: None,
+            broadcast: None,
+        };
+
+        scheduler.enqueue(paused.clone(), message(&paused), ScheduleCause::External);
+        assert!(scheduler.pause(&paused));
+        assert!(!scheduler.pause(&paused));
+        scheduler.enqueue(paused.clone(), message(&paused), ScheduleCause::External);
+        scheduler.enqueue(other.clone(), message(&other), ScheduleCause::External);
+
+        assert_eq!(scheduler.next_turn().unwrap().actor, other);
+        assert!(scheduler.next_turn().is_none());
+        assert_eq!(scheduler.held_count(), 2);
+        assert_eq!(scheduler.pending_count(), 2);
+
+        assert!(scheduler.resume(&paused));
+        let first = scheduler.next_turn().unwrap();
+        let second = scheduler.next_turn().unwrap();
+        assert_eq!(first.actor, paused);
+        assert!(first.clock < second.clock);
+        assert_eq!(scheduler.held_count(), 0);
+    }
 }
@@ -3,11 +3,21 @@
 //! Centralizes all preserves schema definitions for turn records, state deltas,
 //! capabilities, external request/response payloads, and CRDT components.
 //! Ensures stable schema identifiers for backward compatibility.
+//!
+//! Assertions exchanged in the dataspace are typed too: each
+//! [`AssertionSchema`] names a record label and the fields every instance
+//! carries, so clients can select e.g. all `AgentResponse` assertions without
+//! knowing how they are spelled on the wire.
 
 use blake3::Hasher;
-use std::collections::HashMap;
+use preserves::IOValue;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::OnceLock;
 
+use super::error::{Result, RuntimeError};
+use crate::util::io_value::record_with_label;
+
 /// Schema identifier computed from the schema definition
 pub type SchemaId = String;
 
@@ -18,6 +28,7 @@ static SCHEMA_REGISTRY: OnceLock<SchemaRegistry> = OnceLock::new();
 #[derive(Debug)]
 pub struct SchemaRegistry {
     schemas: HashMap<&'static str, SchemaDefinition>,
+    assertions: BTreeMap<&'static str, AssertionSchema>,
 }
 
 /// A schema definition with its hash and version
@@ -33,14 +44,34 @@ pub struct SchemaDefinition {
     pub version: &'static str,
 }
 
+/// Type of a dataspace assertion: a record label plus its leading fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AssertionSchema {
+    /// Type name clients refer to (e.g. `AgentResponse`)
+    pub name: &'static str,
+    /// Record label of matching assertions
+    pub label: &'static str,
+    /// Fields every instance carries, in order; later fields are optional
+    pub fields: &'static [&'static str],
+}
+
+impl AssertionSchema {
+    /// Whether `value` is an instance of this type.
+    pub fn matches(&self, value: &IOValue) -> bool {
+        record_with_label(value, self.label).is_some_and(|record| record.len() >= self.fields.len())
+    }
+}
+
 impl SchemaRegistry {
     /// Initialize the global schema registry
     pub fn init() -> &'static SchemaRegistry {
         SCHEMA_REGISTRY.get_or_init(|| {
             let mut registry = SchemaRegistry {
                 schemas: HashMap::new(),
+                assertions: BTreeMap::new(),
             };
             registry.register_builtin_schemas();
+            registry.register_assertion_schemas();
             registry
         })
     }
@@ -50,7 +81,7 @@ impl SchemaRegistry {
         // TurnRecord schema
         self.register(SchemaDefinition {
             name: "TurnRecord",
-            version: "1.0.0",
+            version: "1.1.0",
             definition: r#"
                 TurnRecord = {
                     turn_id: TurnId,
@@ -61,10 +92,11 @@ impl SchemaRegistry {
                     inputs: [TurnInput],
                     outputs: [TurnOutput],
                     delta: StateDelta,
-                    timestamp: i64
+                    timestamp: i64,
+                    vector_clock: {ActorId: u64}
                 }
             "#,
-            hash: compute_schema_hash("TurnRecord", "1.0.0"),
+            hash: compute_schema_hash("TurnRecord", "1.1.0"),
         });
 
         // StateDelta schema
@@ -104,11 +136,99 @@ impl SchemaRegistry {
         self.schemas.insert(schema.name, schema);
     }
 
+    /// Register the assertion types produced by the runtime and the
+    /// codebase entities
+    fn register_assertion_schemas(&mut self) {
+        use super::{approval, flags, invocation};
+        use crate::codebase::{agent, kv, symbols, workspace};
+
+        let schemas = [
+            AssertionSchema {
+                name: "AgentRequest",
+                label: agent::REQUEST_LABEL,
+                fields: &["agent_id", "request_id", "prompt"],
+            },
+            AssertionSchema {
+                name: "AgentResponse",
+                label: agent::RESPONSE_LABEL,
+                fields: &["agent_id", "request_id", "prompt", "response", "agent_kind"],
+            },
+            AssertionSchema {
+                name: "ApprovalDenied",
+                label: approval::APPROVAL_DENIED_LABEL,
+                fields: &["id", "kind", "reason"],
+            },
+            AssertionSchema {
+                name: "CapabilityOffer",
+                label: workspace::OFFER_LABEL,
+                fields: &["capability", "kind", "path"],
+            },
+            AssertionSchema {
+                name: "FeatureFlag",
+                label: flags::FLAG_LABEL,
+                fields: &["flag", "enabled"],
+            },
+            AssertionSchema {
+                name: "InvocationResult",
+                label: invocation::INVOCATION_RESULT_LABEL,
+                fields: &["invocation", "capability", "value"],
+            },
+            AssertionSchema {
+                name: "KvEntry",
+                label: kv::KV_ENTRY_LABEL,
+                fields: &["namespace", "key", "version", "value"],
+            },
+            AssertionSchema {
+                name: "PendingApproval",
+                label: approval::PENDING_APPROVAL_LABEL,
+                fields: &["id", "capability", "kind", "completion", "payload"],
+            },
+            AssertionSchema {
+                name: "SymbolEntry",
+                label: symbols::SYMBOL_ENTRY_LABEL,
+                fields: &["kind", "name", "path", "span"],
+            },
+            AssertionSchema {
+                name: "ToolError",
+                label: invocation::INVOCATION_ERROR_LABEL,
+                fields: &["message"],
+            },
+        ];
+        for schema in schemas {
+            self.assertions.insert(schema.name, schema);
+        }
+    }
+
     /// Get a schema by name
     pub fn get(&self, name: &str) -> Option<&SchemaDefinition> {
         self.schemas.get(name)
     }
 
+    /// Get an assertion type by name
+    pub fn assertion_schema(&self, name: &str) -> Option<&AssertionSchema> {
+        self.assertions.get(name)
+    }
+
+    /// All assertion types, ordered by name
+    pub fn assertion_schemas(&self) -> impl Iterator<Item = &AssertionSchema> {
+        self.assertions.values()
+    }
+
+    /// Look up an assertion type, failing with
+    /// [`RuntimeError::UnknownSchema`] when it is not registered
+    pub fn resolve_assertion_schema(&self, name: &str) -> Result<&AssertionSchema> {
+        self.assertion_schema(name)
+            .ok_or_else(|| RuntimeError::UnknownSchema {
+                name: name.to_string(),
+                known: self
+                    .assertions
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+    }
+
     /// Get all registered schema hashes for validation
     pub fn all_hashes(&self) -> HashMap<&'static str, SchemaId> {
         self.schemas
@@ -157,6 +277,25 @@ mod tests {
         assert_eq!(hash1, hash2, "Schema hashes must be deterministic");
     }
 
+    #[test]
+    fn test_assertion_schemas_match_by_label_and_arity() {
+        let registry = SchemaRegistry::init();
+        let flag = registry.resolve_assertion_schema("FeatureFlag").unwrap();
+        assert!(flag.matches(&crate::runtime::flags::FeatureFlag::Chaos.assertion(true)));
+        assert!(!flag.matches(&IOValue::record(
+            IOValue::symbol("feature-flag"),
+            vec![IOValue::symbol("chaos")],
+        )));
+        assert!(!flag.matches(&IOValue::symbol("feature-flag")));
+
+        match registry.resolve_assertion_schema("AgentReply") {
+            Err(RuntimeError::UnknownSchema { known, .. }) => {
+                assert!(known.contains("AgentResponse"))
+            }
+            other => panic!("expected UnknownSchema, got {other:?}"),
+        }
+    }
+
     #[test]
     fn test_schema_validation() {
         let registry = SchemaRegistry::init();
@@ -0,0 +1,128 @@
+//! Secrets available to entities
+//!
+//! Entities that read environment variables directly make their behaviour
+//! depend on state the journal never sees. Instead, the runtime resolves the
+//! secrets named in [`SecretsConfig`] once at startup and hands them out
+//! through [`Activation::secret`](super::actor::Activation::secret). Every
+//! lookup is journaled as a [`TurnOutput::SecretAccessed`] carrying the name
+//! only, so usage is auditable while values never reach disk. Replay applies
+//! journaled deltas and never calls back into entities, so it does not need
+//! the original environment.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use uuid::Uuid;
+
+use super::turn::{ActorId, TurnId, TurnOutput, TurnRecord};
+
+/// Secrets resolved at startup, keyed by the name entities ask for.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretsConfig {
+    /// Secret name -> environment variable holding its value
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// In-memory secret values; never serialized or logged.
+#[derive(Clone, Default)]
+pub struct SecretsProvider {
+    values: HashMap<String, String>,
+}
+
+impl fmt::Debug for SecretsProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.values.keys().collect();
+        names.sort();
+        f.debug_struct("SecretsProvider")
+            .field("names", &names)
+            .finish()
+    }
+}
+
+impl SecretsProvider {
+    /// Provider with no secrets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve every configured secret from the process environment.
+    ///
+    /// Variables that are unset or empty are skipped; entities asking for
+    /// them get `None`.
+    pub fn from_config(config: &SecretsConfig) -> Self {
+        let values = config
+            .env
+            .iter()
+            .filter_map(|(name, var)| {
+                std::env::var(var)
+                    .ok()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| (name.clone(), value))
+            })
+            .collect();
+        Self { values }
+    }
+
+    /// Add or replace a secret.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    /// Look up a secret without recording the access.
+    pub(crate) fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Names of the secrets available, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.values.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// Journaled secret lookup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretAccess {
+    /// Turn that performed the lookup
+    pub turn_id: TurnId,
+    /// Actor whose entity asked
+    pub actor: ActorId,
+    /// Entity instance that asked, if known
+    pub entity_id: Option<Uuid>,
+    /// Secret name
+    pub name: String,
+    /// Whether the secret was available
+    pub found: bool,
+    /// When the turn was recorded
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Secret lookups recorded in `records`, in journal order.
+pub fn accesses_in(records: &[TurnRecord]) -> Vec<SecretAccess> {
+    records
+        .iter()
+        .flat_map(|record| {
+            record
+                .outputs
+                .iter()
+                .filter_map(move |output| match output {
+                    TurnOutput::SecretAccessed {
+                        entity_id,
+                        name,
+                        found,
+                    } => Some(SecretAccess {
+                        turn_id: record.turn_id.clone(),
+                        actor: record.actor.clone(),
+                        entity_id: *entity_id,
+                        name: name.clone(),
+                        found: *found,
+                        timestamp: record.timestamp,
+                    }),
+                    _ => None,
+                })
+        })
+        .collect()
+}
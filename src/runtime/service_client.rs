@@ -6,7 +6,8 @@
 
 use crate::PROTOCOL_VERSION;
 use crate::runtime::control::{BranchInfo, RuntimeStatus, TurnSummary};
-use crate::runtime::turn::{BranchId, TurnId};
+use crate::runtime::history::{HistoryDetail, TurnView};
+use crate::runtime::turn::{BranchId, LogicalTimestamp, TurnId};
 use chrono::{DateTime, FixedOffset};
 use serde::Serialize;
 use serde_json::{Value, json};
@@ -86,6 +87,8 @@ pub struct HistoryRequest {
     pub start: Option<u64>,
     /// Maximum number of turns to return.
     pub limit: Option<u64>,
+    /// How much of each turn to include (defaults to a summary).
+    pub detail: Option<HistoryDetail>,
 }
 
 /// Parameters accepted by the `dataspace_events` command.
@@ -103,6 +106,8 @@ pub struct DataspaceEventsRequest {
     pub label: Option<String>,
     /// Filter events associated with an agent request ID.
     pub request_id: Option<String>,
+    /// Restrict results to an assertion type such as `AgentResponse`.
+    pub schema: Option<String>,
     /// Restrict results to the listed event kinds (`assert`/`retract`).
     pub event_types: Vec<String>,
     /// Wait for additional events up to the provided duration (milliseconds).
@@ -133,8 +138,10 @@ pub struct DataspaceEventBatch {
     pub actor_info: Value,
     /// Logical clock for the turn.
     pub clock: u64,
-    /// Timestamp when the turn executed.
+    /// Wall-clock time when the turn executed (advisory).
     pub timestamp: DateTime<FixedOffset>,
+    /// Deterministic timestamp of the turn, when the service reports one.
+    pub logical_time: Option<LogicalTimestamp>,
     /// Events triggered during the turn.
     pub events: Vec<DataspaceEvent>,
 }
@@ -267,6 +274,9 @@ impl DataspaceEventsRequest {
         if let Some(request_id) = self.request_id {
             map.insert("request_id".to_string(), Value::String(request_id));
         }
+        if let Some(schema) = self.schema {
+            map.insert("schema".to_string(), Value::String(schema));
+        }
         if !self.event_types.is_empty() {
             map.insert(
                 "event_types".to_string(),
@@ -303,6 +313,9 @@ impl HistoryRequest {
         if let Some(limit) = self.limit {
             map.insert("limit".to_string(), Value::Number(limit.into()));
         }
+        if let Some(detail) = self.detail {
+            map.insert("detail".to_string(), json!(detail));
+        }
         Value::Object(map)
     }
 }
@@ -367,6 +380,17 @@ impl ServiceClient {
         serde_json::from_value(turns_value).map_err(ClientError::from)
     }
 
+    /// Fetch branch history as curated turn views (inputs, output counts and
+    /// capabilities touched).
+    pub fn history_views(&mut self, request: HistoryRequest) -> Result<Vec<TurnView>, ClientError> {
+        let response = self.call("history", request.into_value())?;
+        let turns_value = response
+            .get("turns")
+            .cloned()
+            .unwrap_or(Value::Array(vec![]));
+        serde_json::from_value(turns_value).map_err(ClientError::from)
+    }
+
     /// List branches known to the runtime.
     pub fn list_branches(&mut self) -> Result<Vec<BranchInfo>, ClientError> {
         let response = self.call("list_branches", Value::Object(serde_json::Map::new()))?;
@@ -680,6 +704,17 @@ fn parse_dataspace_events_response(value: Value) -> Result<DataspaceEventsResult
             ClientError::MalformedResponse(format!("dataspace_events timestamp parse error: {err}"))
         })?;
 
+        let logical_time = batch_obj
+            .get("logical_time")
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|err| {
+                ClientError::MalformedResponse(format!(
+                    "dataspace_events logical_time parse error: {err}"
+                ))
+            })?;
+
         let event_values = batch_obj
             .get("events")
             .and_then(Value::as_array)
@@ -744,6 +779,7 @@ fn parse_dataspace_events_response(value: Value) -> Result<DataspaceEventsResult
             actor_info,
             clock,
             timestamp,
+            logical_time,
             events: parsed_events,
         });
     }
@@ -0,0 +1,279 @@
+//! Turn sinks for mirroring committed turns into external systems
+//!
+//! A [`TurnSink`] receives every [`TurnRecord`] after it has been appended to
+//! the journal, so external indexing pipelines see exactly what replay would.
+//! Each sink runs on its own worker thread behind a bounded queue; when the
+//! queue is full the sink's [`Backpressure`] policy decides whether the
+//! runtime waits for room or drops the record. Sinks are registered with
+//! [`Runtime::add_turn_sink`](super::Runtime::add_turn_sink).
+
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::turn::TurnRecord;
+
+/// Destination for committed turn records.
+///
+/// Delivery happens on a dedicated worker thread, in commit order.
+pub trait TurnSink: Send + Sync {
+    /// Accept one committed turn; an error is logged and counted.
+    fn accept(&self, record: &TurnRecord) -> Result<(), String>;
+}
+
+/// What to do with a record when a sink's queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backpressure {
+    /// Wait for the sink to catch up, stalling turn execution
+    #[default]
+    Block,
+    /// Drop the record and count it
+    Drop,
+}
+
+/// Queue settings for a registered sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SinkOptions {
+    /// Records that may wait for delivery
+    pub capacity: usize,
+    /// Behaviour once `capacity` records are waiting
+    pub backpressure: Backpressure,
+}
+
+impl Default for SinkOptions {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            backpressure: Backpressure::Block,
+        }
+    }
+}
+
+/// Delivery counters for one sink.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SinkStats {
+    /// Name the sink was registered under
+    pub name: String,
+    /// Records the sink accepted
+    pub delivered: u64,
+    /// Records the sink rejected with an error
+    pub failed: u64,
+    /// Records dropped because the queue was full
+    pub dropped: u64,
+}
+
+#[derive(Default)]
+struct Progress {
+    queued: u64,
+    stats: SinkStats,
+}
+
+struct RegisteredSink {
+    backpressure: Backpressure,
+    sender: Option<SyncSender<TurnRecord>>,
+    progress: Arc<(Mutex<Progress>, Condvar)>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl RegisteredSink {
+    fn spawn(name: String, sink: Arc<dyn TurnSink>, options: SinkOptions) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<TurnRecord>(options.capacity.max(1));
+        let progress = Arc::new((
+            Mutex::new(Progress {
+                queued: 0,
+                stats: SinkStats {
+                    name: name.clone(),
+                    ..SinkStats::default()
+                },
+            }),
+            Condvar::new(),
+        ));
+
+        let worker_progress = progress.clone();
+        let worker = std::thread::Builder::new()
+            .name(format!("duet-sink-{name}"))
+            .spawn(move || {
+                for record in receiver {
+                    let outcome = sink.accept(&record);
+                    if let Err(err) = &outcome {
+                        warn!(
+                            "turn sink '{}' rejected turn {}: {}",
+                            name, record.turn_id, err
+                        );
+                    }
+                    let (lock, cvar) = &*worker_progress;
+                    let mut progress = lock.lock().unwrap();
+                    match outcome {
+                        Ok(()) => progress.stats.delivered += 1,
+                        Err(_) => progress.stats.failed += 1,
+                    }
+                    cvar.notify_all();
+                }
+            })
+            .expect("failed to spawn turn sink worker");
+
+        Self {
+            backpressure: options.backpressure,
+            sender: Some(sender),
+            progress,
+            worker: Some(worker),
+        }
+    }
+
+    fn publish(&self, record: &TurnRecord) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        let (lock, _) = &*self.progress;
+        let sent = match self.backpressure {
+            Backpressure::Block => sender.send(record.clone()).is_ok(),
+            Backpressure::Drop => match sender.try_send(record.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    lock.lock().unwrap().stats.dropped += 1;
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            },
+        };
+        if sent {
+            lock.lock().unwrap().queued += 1;
+        }
+    }
+
+    fn flush(&self) {
+        let (lock, cvar) = &*self.progress;
+        let mut progress = lock.lock().unwrap();
+        while progress.stats.delivered + progress.stats.failed < progress.queued {
+            progress = cvar.wait(progress).unwrap();
+        }
+    }
+
+    fn stats(&self) -> SinkStats {
+        self.progress.0.lock().unwrap().stats.clone()
+    }
+}
+
+impl Drop for RegisteredSink {
+    fn drop(&mut self) {
+        // Closing the queue lets the worker drain what is left and exit
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Turn sinks registered on a runtime.
+#[derive(Default)]
+pub struct TurnSinks {
+    sinks: Vec<RegisteredSink>,
+}
+
+impl TurnSinks {
+    /// Start delivering committed turns to `sink`.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        sink: Arc<dyn TurnSink>,
+        options: SinkOptions,
+    ) {
+        self.sinks
+            .push(RegisteredSink::spawn(name.into(), sink, options));
+    }
+
+    /// Queue `record` for every sink.
+    pub fn publish(&self, record: &TurnRecord) {
+        for sink in &self.sinks {
+            sink.publish(record);
+        }
+    }
+
+    /// Wait until every queued record has been handled.
+    pub fn flush(&self) {
+        for sink in &self.sinks {
+            sink.flush();
+        }
+    }
+
+    /// Delivery counters, in registration order.
+    pub fn stats(&self) -> Vec<SinkStats> {
+        self.sinks.iter().map(RegisteredSink::stats).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::state::StateDelta;
+    use crate::runtime::turn::{ActorId, BranchId, LogicalClock};
+    use std::sync::mpsc::Receiver;
+
+    fn record() -> TurnRecord {
+        TurnRecord::new(
+            ActorId::new(),
+            BranchId::main(),
+            LogicalClock::zero(),
+            None,
+            Vec::new(),
+            Vec::new(),
+            StateDelta::empty(),
+        )
+    }
+
+    /// Sink that announces each record and waits for a go-ahead before
+    /// accepting it.
+    struct Gated {
+        entered: Mutex<mpsc::Sender<()>>,
+        gate: Mutex<Receiver<()>>,
+    }
+
+    impl TurnSink for Gated {
+        fn accept(&self, _record: &TurnRecord) -> Result<(), String> {
+            self.entered.lock().unwrap().send(()).unwrap();
+            self.gate
+                .lock()
+                .unwrap()
+                .recv()
+                .map_err(|err| err.to_string())
+        }
+    }
+
+    #[test]
+    fn drop_policy_counts_overflow() {
+        let (entered, busy) = mpsc::channel();
+        let (gate, waiting) = mpsc::channel();
+        let mut sinks = TurnSinks::default();
+        sinks.register(
+            "gated",
+            Arc::new(Gated {
+                entered: Mutex::new(entered),
+                gate: Mutex::new(waiting),
+            }),
+            SinkOptions {
+                capacity: 1,
+                backpressure: Backpressure::Drop,
+            },
+        );
+
+        // The first record occupies the worker, the second fills the queue
+        sinks.publish(&record());
+        busy.recv().unwrap();
+        for _ in 0..3 {
+            sinks.publish(&record());
+        }
+
+        gate.send(()).unwrap();
+        gate.send(()).unwrap();
+        sinks.flush();
+
+        let stats = &sinks.stats()[0];
+        assert_eq!(stats.name, "gated");
+        assert_eq!(stats.delivered, 2);
+        assert_eq!(stats.dropped, 2);
+    }
+}
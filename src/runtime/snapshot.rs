@@ -2,14 +2,29 @@
 //!
 //! Creates periodic snapshots of full runtime state for faster recovery
 //! and time-travel operations.
-
+//!
+//! Snapshots are read as a stream: [`SnapshotManager::stream_by_count`] hands
+//! each assertion, facet, capability and entity state to a [`SnapshotSink`]
+//! as soon as it is decoded, through a fixed-size read buffer, so restoring a
+//! large dataspace never holds the file, or a second copy of the state, in
+//! memory. [`SnapshotManager::load_by_count`] is the same stream collected
+//! into a [`RuntimeSnapshot`].
+
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
 
 use super::error::{SnapshotError, SnapshotResult};
-use super::state::{AssertionSet, CapabilityMap, FacetMap};
+use super::state::{
+    AssertionSet, AssertionValue, CapId, CapabilityMap, CapabilityMetadata, FacetMap, FacetMetadata,
+};
 use super::storage::Storage;
-use super::turn::{ActorId, BranchId, FacetId, TurnId};
+use super::turn::{ActorId, BranchId, FacetId, Handle, TurnId};
+
+/// Read buffer used while streaming a snapshot file
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
 
 /// Snapshot of entity private state (for HydratableEntity implementations)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +82,86 @@ pub struct SnapshotMetadata {
 
     /// Turn ID captured in this snapshot (for verification)
     pub turn_id: TurnId,
+
+    /// Runtime version and registry fingerprint that wrote the snapshot
+    #[serde(default)]
+    pub version: Option<super::version::VersionStamp>,
+}
+
+/// Digest of the replayable part of a snapshot (assertions, facets and
+/// capabilities).
+///
+/// Entity private state is excluded because the journal cannot re-derive
+/// it, and so are root facets, whose ids are minted when an actor is created
+/// rather than journaled. Entries are hashed in sorted order so the digest
+/// does not depend on hash map iteration order.
+pub fn state_hash(
+    assertions: &AssertionSet,
+    facets: &FacetMap,
+    capabilities: &CapabilityMap,
+) -> String {
+    fn sorted<T: Serialize>(entries: impl Iterator<Item = T>) -> Vec<String> {
+        let mut encoded: Vec<String> = entries
+            .map(|entry| format!("{:?}", preserves::serde::to_value(&entry)))
+            .collect();
+        encoded.sort_unstable();
+        encoded
+    }
+
+    let sections = [
+        sorted(assertions.active.iter()),
+        sorted(assertions.tombstones.iter()),
+        sorted(assertions.namespaces.iter()),
+        sorted(
+            facets
+                .facets
+                .iter()
+                .filter(|(_, metadata)| metadata.parent.is_some()),
+        ),
+        sorted(capabilities.capabilities.iter()),
+    ];
+    let mut hasher = blake3::Hasher::new();
+    for section in sections {
+        hasher.update(&(section.len() as u64).to_le_bytes());
+        for entry in section {
+            hasher.update(entry.as_bytes());
+            hasher.update(b"\n");
+        }
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Snapshot whose stored state disagrees with the journal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotMismatch {
+    /// Turn count identifying the snapshot
+    pub turn_count: u64,
+    /// Turn the snapshot claims to capture
+    pub turn_id: TurnId,
+    /// Hash of the stored state, if the snapshot could be read
+    pub stored_hash: Option<String>,
+    /// Hash of the state replayed from the journal, if replay succeeded
+    pub derived_hash: Option<String>,
+    /// What went wrong
+    pub reason: String,
+}
+
+/// Outcome of [`Runtime::verify_snapshots`](super::Runtime::verify_snapshots)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotVerification {
+    /// Branch whose snapshots were checked
+    pub branch: BranchId,
+    /// Number of snapshots checked
+    pub checked: usize,
+    /// Snapshots that failed verification
+    pub mismatches: Vec<SnapshotMismatch>,
+}
+
+impl SnapshotVerification {
+    /// Whether every snapshot matched the journal
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
 }
 
 /// Snapshot index entry mapping turn_id to turn_count
@@ -76,6 +171,74 @@ pub struct SnapshotIndexEntry {
     pub turn_id: TurnId,
     /// Turn count (for ordering)
     pub turn_count: u64,
+    /// Labels that keep the snapshot through pruning
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// Which snapshots of a branch pruning keeps.
+///
+/// A snapshot is kept while any configured rule covers it. With neither
+/// `keep_last` nor `keep_every` set (the default) every snapshot is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotRetention {
+    /// Keep this many of the branch's most recent snapshots
+    #[serde(default)]
+    pub keep_last: Option<u64>,
+    /// Keep the oldest snapshot of every window of this many turns
+    #[serde(default)]
+    pub keep_every: Option<u64>,
+    /// Keep snapshots carrying a tag
+    #[serde(default = "default_keep_tagged")]
+    pub keep_tagged: bool,
+}
+
+fn default_keep_tagged() -> bool {
+    true
+}
+
+impl Default for SnapshotRetention {
+    fn default() -> Self {
+        Self {
+            keep_last: None,
+            keep_every: None,
+            keep_tagged: true,
+        }
+    }
+}
+
+impl SnapshotRetention {
+    /// Whether the policy keeps every snapshot
+    pub fn is_unbounded(&self) -> bool {
+        self.keep_last.is_none() && self.keep_every.is_none()
+    }
+
+    /// Whether each of `entries` (oldest first) is kept
+    pub fn retained(&self, entries: &[SnapshotIndexEntry]) -> Vec<bool> {
+        if self.is_unbounded() {
+            return vec![true; entries.len()];
+        }
+        let recent = self
+            .keep_last
+            .map_or(0, |keep| entries.len().saturating_sub(keep as usize));
+        let mut last_window = None;
+        entries
+            .iter()
+            .enumerate()
+            .map(|(position, entry)| {
+                let first_of_window =
+                    self.keep_every
+                        .filter(|every| *every > 0)
+                        .is_some_and(|every| {
+                            let window = entry.turn_count / every;
+                            last_window.replace(window) != Some(window)
+                        });
+                (self.keep_last.is_some() && position >= recent)
+                    || first_of_window
+                    || (self.keep_tagged && !entry.tags.is_empty())
+            })
+            .collect()
+    }
 }
 
 /// Snapshot index for fast lookups
@@ -96,6 +259,7 @@ impl SnapshotIndex {
         let entry = SnapshotIndexEntry {
             turn_id,
             turn_count,
+            tags: Vec::new(),
         };
 
         self.snapshots
@@ -147,6 +311,358 @@ impl SnapshotIndex {
     }
 }
 
+/// Receives the contents of a snapshot as they are decoded.
+///
+/// Methods are called in file order: assertions, tombstones and namespaces,
+/// then facets, capabilities and entity states. Parts a sink does not
+/// implement are decoded and dropped.
+pub trait SnapshotSink {
+    /// An active assertion
+    fn assertion(&mut self, _key: (ActorId, Handle), _value: AssertionValue, _version: uuid::Uuid) {
+    }
+
+    /// A retracted assertion version
+    fn tombstone(&mut self, _tombstone: (ActorId, Handle, uuid::Uuid)) {}
+
+    /// Named dataspace of an active assertion
+    fn namespace(&mut self, _key: (ActorId, Handle), _namespace: String) {}
+
+    /// A facet
+    fn facet(&mut self, _id: FacetId, _metadata: FacetMetadata) {}
+
+    /// A capability
+    fn capability(&mut self, _id: CapId, _metadata: CapabilityMetadata) {}
+
+    /// Private state of a hydratable entity
+    fn entity_state(&mut self, _state: EntityStateSnapshot) {}
+}
+
+/// Identity of a streamed snapshot, known once the stream ends
+#[derive(Debug, Clone)]
+pub struct SnapshotHeader {
+    /// Branch the snapshot belongs to
+    pub branch: BranchId,
+    /// Turn ID at which the snapshot was taken
+    pub turn_id: TurnId,
+    /// Metadata (stored after the state)
+    pub metadata: SnapshotMetadata,
+}
+
+/// Sink collecting a whole [`RuntimeSnapshot`]
+#[derive(Default)]
+struct CollectSink {
+    assertions: AssertionSet,
+    facets: FacetMap,
+    capabilities: CapabilityMap,
+    entity_states: Vec<EntityStateSnapshot>,
+}
+
+impl CollectSink {
+    fn into_snapshot(self, header: SnapshotHeader) -> RuntimeSnapshot {
+        RuntimeSnapshot {
+            branch: header.branch,
+            turn_id: header.turn_id,
+            assertions: self.assertions,
+            facets: self.facets,
+            capabilities: self.capabilities,
+            entity_states: self.entity_states,
+            metadata: header.metadata,
+        }
+    }
+}
+
+impl SnapshotSink for CollectSink {
+    fn assertion(&mut self, key: (ActorId, Handle), value: AssertionValue, version: uuid::Uuid) {
+        self.assertions.active.insert(key, (value, version));
+    }
+
+    fn tombstone(&mut self, tombstone: (ActorId, Handle, uuid::Uuid)) {
+        self.assertions.tombstones.insert(tombstone);
+    }
+
+    fn namespace(&mut self, key: (ActorId, Handle), namespace: String) {
+        self.assertions.namespaces.insert(key, namespace);
+    }
+
+    fn facet(&mut self, id: FacetId, metadata: FacetMetadata) {
+        self.facets.facets.insert(id, metadata);
+    }
+
+    fn capability(&mut self, id: CapId, metadata: CapabilityMetadata) {
+        self.capabilities.capabilities.insert(id, metadata);
+    }
+
+    fn entity_state(&mut self, state: EntityStateSnapshot) {
+        self.entity_states.push(state);
+    }
+}
+
+/// Sink keeping only entity states
+#[derive(Default)]
+struct EntityStateSink(Vec<EntityStateSnapshot>);
+
+impl SnapshotSink for EntityStateSink {
+    fn entity_state(&mut self, state: EntityStateSnapshot) {
+        self.0.push(state);
+    }
+}
+
+/// Seed decoding a [`RuntimeSnapshot`] record into a sink
+struct SnapshotSeed<'s>(&'s mut dyn SnapshotSink);
+
+impl<'de> DeserializeSeed<'de> for SnapshotSeed<'_> {
+    type Value = SnapshotHeader;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct(
+            "RuntimeSnapshot",
+            &[
+                "branch",
+                "turn_id",
+                "assertions",
+                "facets",
+                "capabilities",
+                "entity_states",
+                "metadata",
+            ],
+            self,
+        )
+    }
+}
+
+impl<'de> Visitor<'de> for SnapshotSeed<'_> {
+    type Value = SnapshotHeader;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a runtime snapshot")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let sink = self.0;
+        let branch = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &"7 snapshot fields"))?;
+        let turn_id = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &"7 snapshot fields"))?;
+        seq.next_element_seed(AssertionSetSeed(&mut *sink))?
+            .ok_or_else(|| de::Error::invalid_length(2, &"7 snapshot fields"))?;
+        seq.next_element_seed(MapFieldSeed::new(
+            "FacetMap",
+            &["facets"],
+            |id, metadata| sink.facet(id, metadata),
+        ))?
+        .ok_or_else(|| de::Error::invalid_length(3, &"7 snapshot fields"))?;
+        seq.next_element_seed(MapFieldSeed::new(
+            "CapabilityMap",
+            &["capabilities"],
+            |id, metadata| sink.capability(id, metadata),
+        ))?
+        .ok_or_else(|| de::Error::invalid_length(4, &"7 snapshot fields"))?;
+        seq.next_element_seed(EachElement::new(|state| sink.entity_state(state)))?
+            .ok_or_else(|| de::Error::invalid_length(5, &"7 snapshot fields"))?;
+        let metadata = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(6, &"7 snapshot fields"))?;
+        Ok(SnapshotHeader {
+            branch,
+            turn_id,
+            metadata,
+        })
+    }
+}
+
+/// Seed decoding an [`AssertionSet`] record into a sink
+struct AssertionSetSeed<'s>(&'s mut dyn SnapshotSink);
+
+impl<'de> DeserializeSeed<'de> for AssertionSetSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_struct(
+            "AssertionSet",
+            &["active", "tombstones", "namespaces"],
+            self,
+        )
+    }
+}
+
+impl<'de> Visitor<'de> for AssertionSetSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an assertion set")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let sink = self.0;
+        seq.next_element_seed(EachEntry::new(
+            |key, (value, version): (AssertionValue, uuid::Uuid)| {
+                sink.assertion(key, value, version)
+            },
+        ))?
+        .ok_or_else(|| de::Error::invalid_length(0, &"active assertions"))?;
+        seq.next_element_seed(EachElement::new(|tombstone| sink.tombstone(tombstone)))?
+            .ok_or_else(|| de::Error::invalid_length(1, &"tombstones"))?;
+        // Absent from snapshots taken before named dataspaces
+        seq.next_element_seed(EachEntry::new(|key, namespace| {
+            sink.namespace(key, namespace)
+        }))?;
+        Ok(())
+    }
+}
+
+/// Seed decoding a struct record whose only field is a map, one entry at a
+/// time
+struct MapFieldSeed<K, V, F> {
+    name: &'static str,
+    field: &'static [&'static str],
+    each: F,
+    entries: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, F> MapFieldSeed<K, V, F> {
+    fn new(name: &'static str, field: &'static [&'static str], each: F) -> Self {
+        Self {
+            name,
+            field,
+            each,
+            entries: PhantomData,
+        }
+    }
+}
+
+impl<'de, K, V, F> DeserializeSeed<'de> for MapFieldSeed<K, V, F>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    F: FnMut(K, V),
+{
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_struct(self.name, self.field, self)
+    }
+}
+
+impl<'de, K, V, F> Visitor<'de> for MapFieldSeed<K, V, F>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    F: FnMut(K, V),
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a {} record", self.name)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let field = self.field[0];
+        seq.next_element_seed(EachEntry::new(self.each))?
+            .ok_or_else(|| de::Error::missing_field(field))
+    }
+}
+
+/// Seed handing each entry of a map to `each` instead of collecting them
+struct EachEntry<K, V, F> {
+    each: F,
+    entries: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, F> EachEntry<K, V, F> {
+    fn new(each: F) -> Self {
+        Self {
+            each,
+            entries: PhantomData,
+        }
+    }
+}
+
+impl<'de, K, V, F> DeserializeSeed<'de> for EachEntry<K, V, F>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    F: FnMut(K, V),
+{
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, K, V, F> Visitor<'de> for EachEntry<K, V, F>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    F: FnMut(K, V),
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key()? {
+            let value = map.next_value()?;
+            (self.each)(key, value);
+        }
+        Ok(())
+    }
+}
+
+/// Seed handing each element of a sequence or set to `each` instead of
+/// collecting them
+struct EachElement<T, F> {
+    each: F,
+    elements: PhantomData<fn() -> T>,
+}
+
+impl<T, F> EachElement<T, F> {
+    fn new(each: F) -> Self {
+        Self {
+            each,
+            elements: PhantomData,
+        }
+    }
+}
+
+impl<'de, T, F> DeserializeSeed<'de> for EachElement<T, F>
+where
+    T: Deserialize<'de>,
+    F: FnMut(T),
+{
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T, F> Visitor<'de> for EachElement<T, F>
+where
+    T: Deserialize<'de>,
+    F: FnMut(T),
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(element) = seq.next_element()? {
+            (self.each)(element);
+        }
+        Ok(())
+    }
+}
+
 /// Snapshot manager
 pub struct SnapshotManager {
     storage: Storage,
@@ -206,24 +722,56 @@ impl SnapshotManager {
         branch: &BranchId,
         turn_count: u64,
     ) -> SnapshotResult<RuntimeSnapshot> {
-        let snapshot_path = self.snapshot_path_by_count(branch, turn_count);
+        let mut parts = CollectSink::default();
+        let header = self.stream_by_count(branch, turn_count, &mut parts)?;
+        Ok(parts.into_snapshot(header))
+    }
 
-        let data = self.storage.read_file(&snapshot_path)?;
-        let snapshot: RuntimeSnapshot = preserves::serde::from_bytes(&data)
-            .map_err(|e| SnapshotError::InvalidFormat(e.to_string()))?;
+    /// Decode the snapshot taken at `turn_count` into `sink`, part by part
+    ///
+    /// Memory use is bounded by the read buffer and the largest single
+    /// entry, whatever the size of the snapshot.
+    pub fn stream_by_count(
+        &self,
+        branch: &BranchId,
+        turn_count: u64,
+        sink: &mut dyn SnapshotSink,
+    ) -> SnapshotResult<SnapshotHeader> {
+        self.stream_file(&self.snapshot_path_by_count(branch, turn_count), sink)
+    }
 
-        Ok(snapshot)
+    /// Entity private state stored in the snapshot taken at `turn_count`
+    pub fn entity_states_by_count(
+        &self,
+        branch: &BranchId,
+        turn_count: u64,
+    ) -> SnapshotResult<Vec<EntityStateSnapshot>> {
+        let mut states = EntityStateSink::default();
+        self.stream_by_count(branch, turn_count, &mut states)?;
+        Ok(states.0)
     }
 
     /// Load a snapshot from preserves encoding
     pub fn load(&self, branch: &BranchId, turn_id: &TurnId) -> SnapshotResult<RuntimeSnapshot> {
-        let snapshot_path = self.snapshot_path(branch, turn_id);
-
-        let data = self.storage.read_file(&snapshot_path)?;
-        let snapshot: RuntimeSnapshot = preserves::serde::from_bytes(&data)
-            .map_err(|e| SnapshotError::InvalidFormat(e.to_string()))?;
+        let mut parts = CollectSink::default();
+        let header = self.stream_file(&self.snapshot_path(branch, turn_id), &mut parts)?;
+        Ok(parts.into_snapshot(header))
+    }
 
-        Ok(snapshot)
+    fn stream_file(
+        &self,
+        path: &std::path::Path,
+        sink: &mut dyn SnapshotSink,
+    ) -> SnapshotResult<SnapshotHeader> {
+        use preserves::{IOBinarySource, PackedReader};
+        let file = std::fs::File::open(path)?;
+        let mut source =
+            IOBinarySource::new(std::io::BufReader::with_capacity(STREAM_BUFFER_SIZE, file));
+        let mut reader = PackedReader::new(&mut source);
+        let mut deserializer = preserves::serde::de::Deserializer::from_reader(&mut reader);
+        SnapshotSeed(sink)
+            .deserialize(&mut deserializer)
+            .map_err(|e| SnapshotError::InvalidFormat(e.to_string()))
     }
 
     /// Find the nearest snapshot at or before a given turn
@@ -297,6 +845,138 @@ impl SnapshotManager {
         Ok(best_count)
     }
 
+    /// Rewrite snapshot turn ids (index and snapshot files) via `mapping`
+    ///
+    /// Used after journal migration so snapshot lookups keep agreeing with the
+    /// rewritten journal.
+    pub fn remap_turn_ids(&self, mapping: &HashMap<TurnId, TurnId>) -> SnapshotResult<()> {
+        let mut index = self.index.write();
+        for (branch_name, entries) in index.snapshots.iter_mut() {
+            let branch = BranchId::new(branch_name.clone());
+            for entry in entries.iter_mut() {
+                let Some(mapped) = mapping.get(&entry.turn_id) else {
+                    continue;
+                };
+                entry.turn_id = mapped.clone();
+
+                if let Ok(mut snapshot) = self.load_by_count(&branch, entry.turn_count) {
+                    snapshot.turn_id = mapped.clone();
+                    snapshot.metadata.turn_id = mapped.clone();
+
+                    use preserves::PackedWriter;
+                    let mut buf = Vec::new();
+                    let mut writer = PackedWriter::new(&mut buf);
+                    preserves::serde::to_writer(&mut writer, &snapshot)
+                        .map_err(|e| SnapshotError::InvalidFormat(e.to_string()))?;
+                    let path = self.snapshot_path_by_count(&branch, entry.turn_count);
+                    self.storage.write_atomic(&path, &buf)?;
+                }
+            }
+        }
+
+        let index_path = self.storage.meta_dir().join("snapshots.json");
+        index.save(&self.storage, &index_path)
+    }
+
+    /// Delete the snapshots of `branch` taken before turn count `before`.
+    ///
+    /// Used by journal compaction once the journal no longer reaches them.
+    /// Returns the number of snapshots removed.
+    pub fn prune_before(&self, branch: &BranchId, before: u64) -> SnapshotResult<usize> {
+        let pruned = self.remove_where(branch, |entries| {
+            entries
+                .iter()
+                .map(|entry| entry.turn_count < before)
+                .collect()
+        })?;
+        Ok(pruned.len())
+    }
+
+    /// Delete the snapshots of `branch` that `policy` does not retain,
+    /// except those taken at a turn in `protected`.
+    ///
+    /// Index entries and files go together, so
+    /// [`nearest_snapshot`](Self::nearest_snapshot) falls back to the
+    /// nearest remaining snapshot. Returns the removed entries.
+    pub fn prune(
+        &self,
+        branch: &BranchId,
+        policy: &SnapshotRetention,
+        protected: &[TurnId],
+    ) -> SnapshotResult<Vec<SnapshotIndexEntry>> {
+        self.remove_where(branch, |entries| {
+            policy
+                .retained(entries)
+                .into_iter()
+                .zip(entries)
+                .map(|(kept, entry)| !kept && !protected.contains(&entry.turn_id))
+                .collect()
+        })
+    }
+
+    /// Remove the entries of `branch` flagged by `select`, and their files.
+    fn remove_where(
+        &self,
+        branch: &BranchId,
+        select: impl FnOnce(&[SnapshotIndexEntry]) -> Vec<bool>,
+    ) -> SnapshotResult<Vec<SnapshotIndexEntry>> {
+        let mut index = self.index.write();
+        let Some(entries) = index.snapshots.get_mut(&branch.0) else {
+            return Ok(Vec::new());
+        };
+        let mut selected = select(entries).into_iter();
+        let (pruned, kept): (Vec<_>, Vec<_>) = entries
+            .drain(..)
+            .partition(|_| selected.next().unwrap_or(false));
+        *entries = kept;
+        if pruned.is_empty() {
+            return Ok(pruned);
+        }
+        for entry in &pruned {
+            let path = self.snapshot_path_by_count(branch, entry.turn_count);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        let index_path = self.storage.meta_dir().join("snapshots.json");
+        index.save(&self.storage, &index_path)?;
+        Ok(pruned)
+    }
+
+    /// Attach `tag` to the snapshot of `branch` taken at `turn_id`.
+    ///
+    /// Returns whether the tag was new.
+    pub fn tag(&self, branch: &BranchId, turn_id: &TurnId, tag: &str) -> SnapshotResult<bool> {
+        let mut index = self.index.write();
+        let entry = index
+            .snapshots
+            .get_mut(&branch.0)
+            .and_then(|entries| entries.iter_mut().find(|entry| entry.turn_id == *turn_id))
+            .ok_or_else(|| SnapshotError::NotFound {
+                branch: branch.to_string(),
+                turn_id: turn_id.to_string(),
+            })?;
+        if entry.tags.iter().any(|existing| existing == tag) {
+            return Ok(false);
+        }
+        entry.tags.push(tag.to_string());
+
+        let index_path = self.storage.meta_dir().join("snapshots.json");
+        index.save(&self.storage, &index_path)?;
+        Ok(true)
+    }
+
+    /// Snapshots recorded for `branch`, oldest first
+    pub fn list(&self, branch: &BranchId) -> Vec<SnapshotIndexEntry> {
+        self.index
+            .read()
+            .snapshots
+            .get(&branch.0)
+            .cloned()
+            .unwrap_or_default()
+    }
+
     /// Check if a snapshot should be created based on interval
     pub fn should_snapshot(&self, turn_count: u64) -> bool {
         turn_count % self.interval == 0
@@ -320,7 +1000,9 @@ impl SnapshotManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use preserves::IOValue;
     use tempfile::TempDir;
+    use uuid::Uuid;
 
     #[test]
     fn test_snapshot_interval() {
@@ -364,6 +1046,155 @@ mod tests {
         assert_eq!(index.find_nearest(&branch, &turn_past), None);
     }
 
+    #[test]
+    fn test_pruned_snapshots_leave_nearest_lookup_to_kept_ones() {
+        let temp = TempDir::new().unwrap();
+        crate::runtime::storage::init_storage(temp.path()).unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let manager = SnapshotManager::new(storage, 10);
+        let branch = BranchId::main();
+        let turn = |count: u64| TurnId::new(format!("turn_{:08}", count));
+        for count in (10..=80).step_by(10) {
+            manager
+                .save(&RuntimeSnapshot {
+                    branch: branch.clone(),
+                    turn_id: turn(count),
+                    assertions: AssertionSet::new(),
+                    facets: FacetMap::new(),
+                    capabilities: CapabilityMap::new(),
+                    entity_states: Vec::new(),
+                    metadata: SnapshotMetadata {
+                        created_at: chrono::Utc::now(),
+                        turn_count: count,
+                        turn_id: turn(count),
+                        version: None,
+                    },
+                })
+                .unwrap();
+        }
+        assert!(manager.tag(&branch, &turn(30), "release").unwrap());
+        assert!(!manager.tag(&branch, &turn(30), "release").unwrap());
+
+        let policy = SnapshotRetention {
+            keep_last: Some(2),
+            keep_every: Some(40),
+            keep_tagged: true,
+        };
+        let pruned = manager.prune(&branch, &policy, &[turn(20)]).unwrap();
+        let kept: Vec<u64> = manager.list(&branch).iter().map(|e| e.turn_count).collect();
+        // 10 and 40 open their 40-turn windows, 70 and 80 are the latest two
+        assert_eq!(kept, vec![10, 20, 30, 40, 70, 80]);
+        assert_eq!(pruned.len(), 2);
+
+        assert_eq!(
+            manager.nearest_snapshot(&branch, &turn(65)).unwrap(),
+            Some(40)
+        );
+        assert!(manager.load_by_count(&branch, 50).is_err());
+        assert_eq!(manager.prune(&branch, &policy, &[]).unwrap().len(), 1);
+        assert!(SnapshotRetention::default().is_unbounded());
+    }
+
+    #[test]
+    fn test_streamed_snapshot_reaches_sink_part_by_part() {
+        #[derive(Default)]
+        struct Tally {
+            assertions: Vec<(ActorId, IOValue)>,
+            tombstones: usize,
+            namespaces: Vec<String>,
+            facets: Vec<ActorId>,
+        }
+
+        impl SnapshotSink for Tally {
+            fn assertion(&mut self, key: (ActorId, Handle), value: IOValue, _version: Uuid) {
+                self.assertions.push((key.0, value));
+            }
+
+            fn tombstone(&mut self, _tombstone: (ActorId, Handle, Uuid)) {
+                self.tombstones += 1;
+            }
+
+            fn namespace(&mut self, _key: (ActorId, Handle), namespace: String) {
+                self.namespaces.push(namespace);
+            }
+
+            fn facet(&mut self, _id: FacetId, metadata: FacetMetadata) {
+                self.facets.push(metadata.actor);
+            }
+        }
+
+        let temp = TempDir::new().unwrap();
+        crate::runtime::storage::init_storage(temp.path()).unwrap();
+        let manager = SnapshotManager::new(Storage::new(temp.path().to_path_buf()), 10);
+        let branch = BranchId::main();
+        let turn_id = TurnId::new("turn_00000010".to_string());
+        let (alice, bob) = (ActorId::new(), ActorId::new());
+
+        let mut assertions = AssertionSet::new();
+        let handle = Handle::new();
+        assertions.active.insert(
+            (alice.clone(), handle.clone()),
+            (IOValue::new("hello".to_string()), Uuid::new_v4()),
+        );
+        assertions
+            .namespaces
+            .insert((alice.clone(), handle), "scratch".to_string());
+        assertions
+            .tombstones
+            .insert((bob.clone(), Handle::new(), Uuid::new_v4()));
+        let mut facets = FacetMap::new();
+        let facet = FacetId::new();
+        facets.facets.insert(
+            facet.clone(),
+            FacetMetadata {
+                id: facet,
+                parent: None,
+                status: crate::runtime::state::FacetStatus::Alive,
+                actor: bob.clone(),
+            },
+        );
+        manager
+            .save(&RuntimeSnapshot {
+                branch: branch.clone(),
+                turn_id: turn_id.clone(),
+                assertions,
+                facets,
+                capabilities: CapabilityMap::new(),
+                entity_states: Vec::new(),
+                metadata: SnapshotMetadata {
+                    created_at: chrono::Utc::now(),
+                    turn_count: 10,
+                    turn_id: turn_id.clone(),
+                    version: None,
+                },
+            })
+            .unwrap();
+
+        let mut tally = Tally::default();
+        let header = manager.stream_by_count(&branch, 10, &mut tally).unwrap();
+        assert_eq!(header.turn_id, turn_id);
+        assert_eq!(header.metadata.turn_count, 10);
+        assert_eq!(
+            tally.assertions,
+            vec![(alice.clone(), IOValue::new("hello".to_string()))]
+        );
+        assert_eq!(tally.tombstones, 1);
+        assert_eq!(tally.namespaces, vec!["scratch".to_string()]);
+        assert_eq!(tally.facets, vec![bob]);
+
+        // Collecting the same stream gives back the whole snapshot
+        let loaded = manager.load_by_count(&branch, 10).unwrap();
+        assert_eq!(loaded.assertions.active.len(), 1);
+        assert_eq!(loaded.assertions.tombstones.len(), 1);
+        assert_eq!(loaded.facets.facets.len(), 1);
+        assert!(
+            manager
+                .entity_states_by_count(&branch, 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_snapshot_index_persistence() {
         use tempfile::TempDir;
@@ -2,12 +2,17 @@
 //!
 //! All persistent state is modeled as CRDTs (Conflict-free Replicated Data Types)
 //! to support deterministic merging across branches. Provides OR-sets for assertions,
-//! lattices for facets and capabilities, and PN-counters for flow control.
+//! lattices for facets and capabilities, and PN-counters for flow control, plus
+//! reusable counters, maps and sequences that entities can embed in their
+//! hydratable state.
 
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use uuid::Uuid;
 
+use crate::util::io_value::canonical_hash;
+
+use super::error::{ActorError, ActorResult};
 use super::turn::{ActorId, FacetId, Handle};
 
 /// Complete state delta produced by a turn
@@ -70,11 +75,41 @@ pub struct AssertionSet {
     pub active: HashMap<(ActorId, Handle), (AssertionValue, Uuid)>,
     /// Tombstones for retracted assertions
     pub tombstones: HashSet<(ActorId, Handle, Uuid)>,
+    /// Named dataspace of each active assertion (absent = default dataspace)
+    #[serde(default)]
+    pub namespaces: HashMap<(ActorId, Handle), String>,
 }
 
 /// Assertion value (preserves value)
 pub type AssertionValue = preserves::IOValue;
 
+/// Name of the implicit dataspace that unscoped assertions live in.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Check whether an assertion's namespace satisfies a requested namespace.
+///
+/// `None` on the assertion side denotes the default dataspace, so it only
+/// matches a request for [`DEFAULT_NAMESPACE`].
+pub fn namespace_matches(requested: &str, namespace: Option<&str>) -> bool {
+    namespace.unwrap_or(DEFAULT_NAMESPACE) == requested
+}
+
+/// Version of an assertion added by `actor` under `handle`.
+///
+/// Derived from the actor, the handle and the [`canonical_hash`] of the
+/// value, so replaying or merging the same assertion yields the same version
+/// and deltas deduplicate it.
+pub fn assertion_version(actor: &ActorId, handle: &Handle, value: &AssertionValue) -> Uuid {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(actor.0.as_bytes());
+    hasher.update(handle.0.as_bytes());
+    hasher.update(&canonical_hash(value));
+    let digest = hasher.finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest.as_bytes()[..16]);
+    Uuid::from_bytes(bytes)
+}
+
 /// Delta for assertion changes
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AssertionDelta {
@@ -82,6 +117,9 @@ pub struct AssertionDelta {
     pub added: Vec<(ActorId, Handle, AssertionValue, Uuid)>,
     /// Assertions retracted
     pub retracted: Vec<(ActorId, Handle, Uuid)>,
+    /// Named dataspaces for added assertions outside the default dataspace
+    #[serde(default)]
+    pub namespaces: Vec<(ActorId, Handle, String)>,
 }
 
 impl AssertionDelta {
@@ -113,6 +151,14 @@ impl AssertionDelta {
             }
         }
 
+        // Union of namespace tags (deduplicate by assertion key)
+        let mut seen_keys = HashSet::new();
+        for item in self.namespaces.iter().chain(other.namespaces.iter()) {
+            if seen_keys.insert((item.0.clone(), item.1.clone())) {
+                result.namespaces.push(item.clone());
+            }
+        }
+
         result
     }
 }
@@ -136,9 +182,17 @@ impl AssertionSet {
             }
         }
 
+        for (actor, handle, namespace) in &delta.namespaces {
+            let key = (actor.clone(), handle.clone());
+            if self.active.contains_key(&key) {
+                self.namespaces.insert(key, namespace.clone());
+            }
+        }
+
         for (actor, handle, version) in &delta.retracted {
             let key = (actor.clone(), handle.clone());
             self.active.remove(&key);
+            self.namespaces.remove(&key);
             self.tombstones
                 .insert((actor.clone(), handle.clone(), *version));
         }
@@ -161,8 +215,22 @@ impl AssertionSet {
             }
         }
 
+        // Keep namespace tags only for assertions that survived the merge
+        for (key, namespace) in self.namespaces.iter().chain(other.namespaces.iter()) {
+            if result.active.contains_key(key) {
+                result.namespaces.insert(key.clone(), namespace.clone());
+            }
+        }
+
         result
     }
+
+    /// Namespace an active assertion was published into (`None` = default dataspace).
+    pub fn namespace_of(&self, actor: &ActorId, handle: &Handle) -> Option<&str> {
+        self.namespaces
+            .get(&(actor.clone(), handle.clone()))
+            .map(String::as_str)
+    }
 }
 
 // ========== Facet Lifecycle CRDT ==========
@@ -574,6 +642,403 @@ impl AccountDelta {
     }
 }
 
+// ========== Entity State CRDTs ==========
+//
+// Runtime state above merges through deltas; the types below are for entities
+// that keep their own replicated state inside `HydratableEntity` snapshots.
+// Each one is a join-semilattice, so two branches' copies of the same entity
+// state can be joined without coordination, in any order, any number of times.
+
+/// Join-semilattice: `join` is commutative, associative and idempotent.
+pub trait Lattice {
+    /// Least upper bound of `self` and `other`
+    fn join(&self, other: &Self) -> Self;
+}
+
+/// Identifier of the replica (typically a branch) making an update.
+pub type ReplicaId = String;
+
+/// Counter that can go up and down, tracked per replica.
+///
+/// Unlike [`PNCounter`], joining a counter with itself (or with an older copy)
+/// does not double-count, which makes it safe to embed in entity state.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ReplicaCounter {
+    increments: BTreeMap<ReplicaId, u64>,
+    decrements: BTreeMap<ReplicaId, u64>,
+}
+
+impl ReplicaCounter {
+    /// Create a counter at zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current value across all replicas
+    pub fn value(&self) -> i64 {
+        let total = |map: &BTreeMap<ReplicaId, u64>| map.values().map(|v| *v as i64).sum::<i64>();
+        total(&self.increments) - total(&self.decrements)
+    }
+
+    /// Add `amount` on behalf of `replica`
+    pub fn increment(&mut self, replica: &str, amount: u64) {
+        *self.increments.entry(replica.to_string()).or_default() += amount;
+    }
+
+    /// Subtract `amount` on behalf of `replica`
+    pub fn decrement(&mut self, replica: &str, amount: u64) {
+        *self.decrements.entry(replica.to_string()).or_default() += amount;
+    }
+}
+
+impl Lattice for ReplicaCounter {
+    fn join(&self, other: &Self) -> Self {
+        let join_max = |a: &BTreeMap<ReplicaId, u64>, b: &BTreeMap<ReplicaId, u64>| {
+            let mut joined = a.clone();
+            for (replica, count) in b {
+                let entry = joined.entry(replica.clone()).or_default();
+                *entry = (*entry).max(*count);
+            }
+            joined
+        };
+        ReplicaCounter {
+            increments: join_max(&self.increments, &other.increments),
+            decrements: join_max(&self.decrements, &other.decrements),
+        }
+    }
+}
+
+/// Last-writer-wins register, ordered by `(timestamp, replica)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LwwRegister<T> {
+    /// Current value
+    pub value: T,
+    /// Logical timestamp of the last write
+    pub timestamp: u64,
+    /// Replica that made the last write
+    pub replica: ReplicaId,
+}
+
+impl<T: Clone> LwwRegister<T> {
+    /// Create a register holding `value`
+    pub fn new(value: T, timestamp: u64, replica: &str) -> Self {
+        Self {
+            value,
+            timestamp,
+            replica: replica.to_string(),
+        }
+    }
+
+    /// Overwrite the value if `(timestamp, replica)` is newer than the current write
+    pub fn set(&mut self, value: T, timestamp: u64, replica: &str) {
+        if (timestamp, replica) > (self.timestamp, self.replica.as_str()) {
+            *self = Self::new(value, timestamp, replica);
+        }
+    }
+}
+
+impl<T: Clone> Lattice for LwwRegister<T> {
+    fn join(&self, other: &Self) -> Self {
+        if (other.timestamp, &other.replica) > (self.timestamp, &self.replica) {
+            other.clone()
+        } else {
+            self.clone()
+        }
+    }
+}
+
+/// Entry of an [`OrMap`]: the value plus the tags of the writes that keep it alive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct OrMapEntry<V> {
+    value: V,
+    tags: BTreeSet<Uuid>,
+}
+
+/// Observed-remove map whose values are themselves lattices.
+///
+/// A remove only cancels the writes it has observed, so a concurrent write on
+/// another branch survives the join (add wins). Values written to the same key
+/// on both sides are joined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrMap<K: Ord, V> {
+    entries: BTreeMap<K, OrMapEntry<V>>,
+    removed: BTreeSet<Uuid>,
+}
+
+impl<K: Ord, V> Default for OrMap<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            removed: BTreeSet::new(),
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Lattice + Clone> OrMap<K, V> {
+    /// Create an empty map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write `value` under `key`, joining it with any value already present
+    pub fn insert(&mut self, key: K, value: V) {
+        let tag = Uuid::new_v4();
+        match self.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.value = entry.value.join(&value);
+                entry.tags.insert(tag);
+            }
+            None => {
+                self.entries.insert(
+                    key,
+                    OrMapEntry {
+                        value,
+                        tags: BTreeSet::from([tag]),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Remove `key`, cancelling every write to it observed so far
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let entry = self.entries.remove(key)?;
+        self.removed.extend(entry.tags);
+        Some(entry.value)
+    }
+
+    /// Value stored under `key`
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    /// Whether `key` is present
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Entries in key order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(key, entry)| (key, &entry.value))
+    }
+
+    /// Number of live keys
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the map has no live keys
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<K: Ord + Clone, V: Lattice + Clone> Lattice for OrMap<K, V> {
+    fn join(&self, other: &Self) -> Self {
+        let removed: BTreeSet<Uuid> = self.removed.union(&other.removed).cloned().collect();
+        let mut entries = BTreeMap::new();
+
+        let keys: BTreeSet<&K> = self.entries.keys().chain(other.entries.keys()).collect();
+        for key in keys {
+            let mut tags = BTreeSet::new();
+            let mut value: Option<V> = None;
+            for entry in [self.entries.get(key), other.entries.get(key)]
+                .into_iter()
+                .flatten()
+            {
+                let live: BTreeSet<Uuid> = entry.tags.difference(&removed).cloned().collect();
+                if live.is_empty() {
+                    continue;
+                }
+                tags.extend(live);
+                value = Some(match value {
+                    Some(existing) => existing.join(&entry.value),
+                    None => entry.value.clone(),
+                });
+            }
+            if let Some(value) = value {
+                entries.insert(key.clone(), OrMapEntry { value, tags });
+            }
+        }
+
+        OrMap { entries, removed }
+    }
+}
+
+/// Position identifier of an element in an [`Rga`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct RgaId {
+    /// Lamport counter at insertion time
+    pub counter: u64,
+    /// Replica that inserted the element
+    pub replica: ReplicaId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RgaNode<T> {
+    id: RgaId,
+    /// Element this one was inserted after (`None` = sequence start)
+    parent: Option<RgaId>,
+    value: T,
+    deleted: bool,
+}
+
+/// Replicated growable array: an ordered sequence supporting concurrent inserts.
+///
+/// Every element remembers the element it was inserted after; concurrent
+/// inserts at the same spot are ordered newest first, so every replica settles
+/// on the same sequence. Removed elements stay behind as tombstones to anchor
+/// later inserts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rga<T> {
+    /// Nodes sorted by id
+    nodes: Vec<RgaNode<T>>,
+}
+
+impl<T> Default for Rga<T> {
+    fn default() -> Self {
+        Self { nodes: Vec::new() }
+    }
+}
+
+impl<T: Clone> Rga<T> {
+    /// Create an empty sequence
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert `value` so that it ends up at `index`, returning its position id
+    ///
+    /// Indices past the end append.
+    pub fn insert(&mut self, replica: &str, index: usize, value: T) -> RgaId {
+        let parent = index
+            .checked_sub(1)
+            .and_then(|before| self.visible_ids().into_iter().nth(before))
+            .or_else(|| {
+                // Appending past the end anchors on the last visible element
+                (index > 0)
+                    .then(|| self.visible_ids().last().cloned())
+                    .flatten()
+            });
+        let counter = self
+            .nodes
+            .iter()
+            .map(|node| node.id.counter)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let id = RgaId {
+            counter,
+            replica: replica.to_string(),
+        };
+        self.add_node(RgaNode {
+            id: id.clone(),
+            parent,
+            value,
+            deleted: false,
+        });
+        id
+    }
+
+    /// Append `value` at the end of the sequence
+    pub fn push(&mut self, replica: &str, value: T) -> RgaId {
+        let len = self.len();
+        self.insert(replica, len, value)
+    }
+
+    /// Remove the element at `index`, returning it
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        let id = self.visible_ids().into_iter().nth(index)?;
+        let position = self.position(&id).ok()?;
+        let node = &mut self.nodes[position];
+        node.deleted = true;
+        Some(node.value.clone())
+    }
+
+    /// Visible elements in sequence order
+    pub fn to_vec(&self) -> Vec<T> {
+        self.ordered()
+            .into_iter()
+            .filter(|node| !node.deleted)
+            .map(|node| node.value.clone())
+            .collect()
+    }
+
+    /// Number of visible elements
+    pub fn len(&self) -> usize {
+        self.nodes.iter().filter(|node| !node.deleted).count()
+    }
+
+    /// Whether the sequence has no visible elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn position(&self, id: &RgaId) -> std::result::Result<usize, usize> {
+        self.nodes.binary_search_by(|node| node.id.cmp(id))
+    }
+
+    fn add_node(&mut self, node: RgaNode<T>) {
+        match self.position(&node.id) {
+            Ok(existing) => self.nodes[existing].deleted |= node.deleted,
+            Err(slot) => self.nodes.insert(slot, node),
+        }
+    }
+
+    fn visible_ids(&self) -> Vec<RgaId> {
+        self.ordered()
+            .into_iter()
+            .filter(|node| !node.deleted)
+            .map(|node| node.id.clone())
+            .collect()
+    }
+
+    /// All nodes, tombstones included, in sequence order
+    fn ordered(&self) -> Vec<&RgaNode<T>> {
+        let mut children: HashMap<Option<&RgaId>, Vec<&RgaNode<T>>> = HashMap::new();
+        // Nodes are sorted by id, so pushing in reverse yields newest-first siblings
+        for node in self.nodes.iter().rev() {
+            children.entry(node.parent.as_ref()).or_default().push(node);
+        }
+
+        let mut ordered = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<&RgaNode<T>> = children
+            .get(&None)
+            .map(|roots| roots.iter().rev().cloned().collect())
+            .unwrap_or_default();
+        while let Some(node) = stack.pop() {
+            ordered.push(node);
+            if let Some(kids) = children.get(&Some(&node.id)) {
+                stack.extend(kids.iter().rev().cloned());
+            }
+        }
+        ordered
+    }
+}
+
+impl<T: Clone> Lattice for Rga<T> {
+    fn join(&self, other: &Self) -> Self {
+        let mut joined = self.clone();
+        for node in &other.nodes {
+            joined.add_node(node.clone());
+        }
+        joined
+    }
+}
+
+/// Encode CRDT state as a preserves value for `HydratableEntity::snapshot_state`.
+pub fn to_state_value<T: Serialize>(state: &T) -> preserves::IOValue {
+    preserves::serde::to_value(state)
+}
+
+/// Decode CRDT state produced by [`to_state_value`].
+pub fn from_state_value<T: serde::de::DeserializeOwned>(
+    value: &preserves::IOValue,
+) -> ActorResult<T> {
+    preserves::serde::from_value(value)
+        .map_err(|err| ActorError::InvalidActivation(format!("invalid CRDT state: {}", err)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -590,6 +1055,7 @@ mod tests {
         let delta = AssertionDelta {
             added: vec![(actor.clone(), handle.clone(), value.clone(), version)],
             retracted: vec![],
+            namespaces: vec![],
         };
         set.apply(&delta);
 
@@ -599,6 +1065,7 @@ mod tests {
         let delta = AssertionDelta {
             added: vec![],
             retracted: vec![(actor.clone(), handle.clone(), version)],
+            namespaces: vec![],
         };
         set.apply(&delta);
 
@@ -606,6 +1073,22 @@ mod tests {
         assert_eq!(set.tombstones.len(), 1);
     }
 
+    #[test]
+    fn test_assertion_version_is_content_derived() {
+        let actor = ActorId::new();
+        let handle = Handle::new();
+        let value = preserves::IOValue::symbol("fact");
+
+        let version = assertion_version(&actor, &handle, &value);
+        assert_eq!(version, assertion_version(&actor, &handle, &value));
+        assert_ne!(
+            version,
+            assertion_version(&actor, &handle, &preserves::IOValue::symbol("other"))
+        );
+        assert_ne!(version, assertion_version(&actor, &Handle::new(), &value));
+        assert_ne!(version, assertion_version(&ActorId::new(), &handle, &value));
+    }
+
     #[test]
     fn test_assertion_set_join() {
         let actor = ActorId::new();
@@ -697,6 +1180,7 @@ mod tests {
                     v1,
                 )],
                 retracted: vec![],
+                namespaces: vec![],
             },
             facets: FacetDelta::default(),
             capabilities: CapabilityDelta::default(),
@@ -717,6 +1201,7 @@ mod tests {
                     v2,
                 )],
                 retracted: vec![],
+                namespaces: vec![],
             },
             facets: FacetDelta::default(),
             capabilities: CapabilityDelta::default(),
@@ -750,6 +1235,7 @@ mod tests {
                 version,
             )],
             retracted: vec![],
+            namespaces: vec![],
         };
 
         let delta_b = AssertionDelta {
@@ -760,6 +1246,7 @@ mod tests {
                 version,
             )],
             retracted: vec![],
+            namespaces: vec![],
         };
 
         let joined = delta_a.join(&delta_b);
@@ -768,6 +1255,52 @@ mod tests {
         assert_eq!(joined.added.len(), 1, "Should deduplicate same version");
     }
 
+    #[test]
+    fn test_assertion_namespaces_follow_lifecycle() {
+        let mut set = AssertionSet::new();
+        let actor = ActorId::new();
+        let scoped = Handle::new();
+        let unscoped = Handle::new();
+        let version = Uuid::new_v4();
+
+        set.apply(&AssertionDelta {
+            added: vec![
+                (
+                    actor.clone(),
+                    scoped.clone(),
+                    preserves::IOValue::symbol("a"),
+                    version,
+                ),
+                (
+                    actor.clone(),
+                    unscoped.clone(),
+                    preserves::IOValue::symbol("b"),
+                    Uuid::new_v4(),
+                ),
+            ],
+            retracted: vec![],
+            namespaces: vec![(actor.clone(), scoped.clone(), "agents".to_string())],
+        });
+
+        assert_eq!(set.namespace_of(&actor, &scoped), Some("agents"));
+        assert_eq!(set.namespace_of(&actor, &unscoped), None);
+        assert!(namespace_matches(
+            "agents",
+            set.namespace_of(&actor, &scoped)
+        ));
+        assert!(namespace_matches(
+            DEFAULT_NAMESPACE,
+            set.namespace_of(&actor, &unscoped)
+        ));
+
+        set.apply(&AssertionDelta {
+            added: vec![],
+            retracted: vec![(actor.clone(), scoped.clone(), version)],
+            namespaces: vec![],
+        });
+        assert!(set.namespaces.is_empty());
+    }
+
     #[test]
     fn test_facet_delta_join() {
         let facet1 = FacetId::new();
@@ -899,4 +1432,47 @@ mod tests {
         assert_eq!(meta.attenuation, vec![preserves::IOValue::symbol("new")]);
         assert!(meta.target.is_some());
     }
+
+    #[test]
+    fn test_entity_crdts_converge_across_branches() {
+        // Counter: joining is idempotent, so re-merging never double-counts
+        let mut main = ReplicaCounter::new();
+        main.increment("main", 5);
+        let mut experiment = main.clone();
+        experiment.decrement("experiment", 2);
+        main.increment("main", 1);
+        let merged = main.join(&experiment);
+        assert_eq!(merged.value(), 4);
+        assert_eq!(merged.join(&experiment), merged);
+
+        // OR-map: a concurrent write survives a remove that never observed it
+        let mut left: OrMap<String, LwwRegister<i64>> = OrMap::new();
+        left.insert("a".into(), LwwRegister::new(1, 1, "main"));
+        let mut right = left.clone();
+        left.remove(&"a".to_string());
+        right.insert("a".into(), LwwRegister::new(2, 2, "experiment"));
+        right.insert("b".into(), LwwRegister::new(3, 2, "experiment"));
+        let merged = left.join(&right);
+        assert_eq!(merged, right.join(&left));
+        assert_eq!(merged.get(&"a".to_string()).map(|r| r.value), Some(2));
+        assert_eq!(merged.len(), 2);
+
+        // RGA: concurrent inserts at the same spot settle on one order
+        let mut base = Rga::new();
+        base.push("main", 'a');
+        base.push("main", 'c');
+        let mut left = base.clone();
+        let mut right = base.clone();
+        left.insert("main", 1, 'b');
+        right.insert("experiment", 1, 'x');
+        right.remove(0);
+        let merged = left.join(&right);
+        assert_eq!(merged.to_vec(), right.join(&left).to_vec());
+        assert_eq!(merged.to_vec(), vec!['b', 'x', 'c']);
+
+        // Round-trip through the hydratable state encoding
+        let encoded = to_state_value(&merged);
+        let decoded: Rga<char> = from_state_value(&encoded).unwrap();
+        assert_eq!(decoded, merged);
+    }
 }
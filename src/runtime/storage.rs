@@ -11,6 +11,8 @@ use std::fs::{self, File, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
+pub mod blobs;
+
 const EXAMPLES_DIR: &str = "examples";
 const PROGRAMS_DIR: &str = "programs";
 
@@ -51,6 +53,11 @@ impl Storage {
         self.root.join("snapshots")
     }
 
+    /// Get the content-addressed blob directory path
+    pub fn blobs_dir(&self) -> PathBuf {
+        self.root.join("blobs")
+    }
+
     /// Get branch-specific meta directory
     pub fn branch_meta_dir(&self, branch: &BranchId) -> PathBuf {
         self.meta_dir().join(&branch.0)
@@ -223,6 +230,33 @@ pub fn load_branch_state(storage: &Storage) -> StorageResult<Option<BranchState>
     Ok(Some(state))
 }
 
+/// Append `record` as one JSON line to `path`, creating the file as needed
+pub fn append_json_line<T: serde::Serialize>(path: &Path, record: &T) -> StorageResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_vec(record).map_err(StorageError::from)?;
+    line.push(b'\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(&line)?;
+    Ok(())
+}
+
+/// Read every record of a JSON-lines file (empty if the file is absent)
+pub fn read_json_lines<T: serde::de::DeserializeOwned>(path: &Path) -> StorageResult<Vec<T>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(StorageError::from))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -256,6 +290,23 @@ mod tests {
             snapshot_interval: 100,
             flow_control_limit: 5000,
             debug: true,
+            approval_kinds: Vec::new(),
+            notifiers: Vec::new(),
+            limits: Default::default(),
+            secrets: Default::default(),
+            version_policy: Default::default(),
+            redaction: Default::default(),
+            branch_naming: Default::default(),
+            memory: Default::default(),
+            logging: Default::default(),
+            stubs: Default::default(),
+            output_order: Default::default(),
+            retention: Default::default(),
+            outbox: Default::default(),
+            identity: Default::default(),
+            snapshot_retention: Default::default(),
+            blobs: Default::default(),
+            durability: Default::default(),
         };
 
         write_config(&config).unwrap();
@@ -0,0 +1,411 @@
+//! Content-addressed store for large turn payloads
+//!
+//! Workspace reads, transcripts and tool results can carry megabytes of file
+//! content, and every copy lands in the journal, in each record that mentions
+//! it. Before a record is appended, payload values whose packed encoding is
+//! larger than [`BlobConfig::spill_threshold`] are written to the blob store
+//! and replaced by a `<duet:blob "sha256" size>` reference; reading the
+//! journal swaps the references back, so callers always see full records.
+//!
+//! Large compounds are spilled child by child where that is enough to bring
+//! them under the threshold, so the journaled record keeps its shape (and its
+//! small fields) and only the bulky parts move out. Blobs are keyed by the
+//! SHA-256 of their content and stored as `blobs/<2 hex>/<sha256>.blob`, so
+//! the same content spilled by many turns is stored once. Blobs are never
+//! deleted: every journal record referencing one may still be read.
+//!
+//! Payloads are user data and may themselves be `duet:blob` records. Only
+//! records framed with [`SPILLED_PREFIX`] are rehydrated, and before a record
+//! is spilled every payload value labelled `duet:blob` (or `duet:blob-escaped`)
+//! is wrapped as `<duet:blob-escaped value>`, which reading unwraps. A value
+//! can therefore never be mistaken for a reference to someone else's blob.
+
+use preserves::IOValue;
+use preserves::types::{CompoundClass, ValueClass};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use super::Storage;
+use crate::runtime::error::{JournalError, JournalResult, StorageError, StorageResult};
+use crate::runtime::turn::TurnRecord;
+use crate::util::io_value::record_with_label;
+
+/// Default size above which payload values are spilled (64 KiB)
+pub const DEFAULT_SPILL_THRESHOLD: usize = 64 * 1024;
+
+/// Record label of a spilled value's reference
+const BLOB_LABEL: &str = "duet:blob";
+
+/// Record label wrapping payload values that look like references
+const ESCAPE_LABEL: &str = "duet:blob-escaped";
+
+/// Bytes framing the packed encoding of a record that holds blob references
+///
+/// Packed records start with a record tag and zstd frames with their own
+/// magic, so neither can be confused with it.
+pub(crate) const SPILLED_PREFIX: [u8; 4] = [0xFF, b'B', b'L', b'B'];
+
+/// When journaled payloads move to the blob store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlobConfig {
+    /// Packed size in bytes above which a value is spilled (0 keeps every
+    /// value inline)
+    pub spill_threshold: usize,
+}
+
+impl Default for BlobConfig {
+    fn default() -> Self {
+        Self {
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+        }
+    }
+}
+
+/// Blobs under the storage root, keyed by SHA-256
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    storage: Storage,
+}
+
+impl BlobStore {
+    /// Blob store of `storage`
+    pub fn new(storage: Storage) -> Self {
+        Self { storage }
+    }
+
+    /// Path of the blob with hex digest `digest`
+    pub fn path(&self, digest: &str) -> PathBuf {
+        let shard = digest.get(..2).unwrap_or("00");
+        self.storage
+            .blobs_dir()
+            .join(shard)
+            .join(format!("{}.blob", digest))
+    }
+
+    /// Whether the blob `digest` is stored
+    pub fn contains(&self, digest: &str) -> bool {
+        self.path(digest).exists()
+    }
+
+    /// Store `bytes` and return their hex digest
+    ///
+    /// Content that is already stored is not written again.
+    pub fn put(&self, bytes: &[u8]) -> StorageResult<String> {
+        let digest = format!("{:x}", Sha256::digest(bytes));
+        let path = self.path(&digest);
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                self.storage.create_dir_all(parent)?;
+            }
+            self.storage.write_atomic(&path, bytes)?;
+        }
+        Ok(digest)
+    }
+
+    /// Content of the blob `digest`, checked against the digest
+    pub fn get(&self, digest: &str) -> StorageResult<Vec<u8>> {
+        let path = self.path(digest);
+        if !path.exists() {
+            return Err(StorageError::PathNotFound(path));
+        }
+        let bytes = self.storage.read_file(&path)?;
+        if format!("{:x}", Sha256::digest(&bytes)) != digest {
+            return Err(StorageError::BlobCorrupted(digest.to_string()));
+        }
+        Ok(bytes)
+    }
+}
+
+/// `record` with payload values larger than `threshold` moved to `store`
+///
+/// Returns `None` when every payload fits (or `threshold` is 0). A spilled
+/// record has its look-alike references escaped and must be journaled with
+/// [`SPILLED_PREFIX`].
+pub(crate) fn spill_record(
+    store: &BlobStore,
+    record: &TurnRecord,
+    threshold: usize,
+) -> JournalResult<Option<TurnRecord>> {
+    if threshold == 0 {
+        return Ok(None);
+    }
+    let mut spilled = record.clone();
+    let mut changed = false;
+    for value in spilled.payloads_mut() {
+        let escaped = escape(value);
+        if let Some(smaller) = spill(store, escaped.as_ref().unwrap_or(value), threshold)? {
+            *value = smaller;
+            changed = true;
+        } else if let Some(escaped) = escaped {
+            *value = escaped;
+        }
+    }
+    Ok(changed.then_some(spilled))
+}
+
+/// Replace the blob references in `record` with the values they stand for
+pub(crate) fn rehydrate_record(store: &BlobStore, record: &mut TurnRecord) -> JournalResult<()> {
+    for value in record.payloads_mut() {
+        if let Some(full) = rehydrate(store, value)? {
+            *value = full;
+        }
+    }
+    Ok(())
+}
+
+/// `value` with its bulk spilled, or `None` when it fits under `threshold`
+fn spill(store: &BlobStore, value: &IOValue, threshold: usize) -> JournalResult<Option<IOValue>> {
+    let packed = pack(value)?;
+    if packed.len() <= threshold {
+        return Ok(None);
+    }
+    let mut spill_child = |child: &IOValue| spill(store, child, threshold);
+    let smaller = match escaped(value) {
+        // An escaped value stands for itself, so only its children move out
+        Some(inner) => map_children(&inner, &mut spill_child)?.map(wrap_escaped),
+        None => map_children(value, &mut spill_child)?,
+    };
+    if let Some(smaller) = smaller {
+        let packed = pack(&smaller)?;
+        if packed.len() <= threshold {
+            return Ok(Some(smaller));
+        }
+        return blob_reference(store, &packed).map(Some);
+    }
+    blob_reference(store, &packed).map(Some)
+}
+
+/// `value` with its blob references resolved, or `None` when it has none
+fn rehydrate(store: &BlobStore, value: &IOValue) -> JournalResult<Option<IOValue>> {
+    if let Some(inner) = escaped(value) {
+        let children = map_children(&inner, &mut |child| rehydrate(store, child))?;
+        return Ok(Some(children.unwrap_or(inner)));
+    }
+    if let Some(reference) = record_with_label(value, BLOB_LABEL)
+        && let Some(digest) = reference.field_string(0)
+    {
+        let bytes = store.get(&digest).map_err(|e| JournalError::Blob {
+            digest: digest.clone(),
+            detail: e.to_string(),
+        })?;
+        let full: IOValue =
+            preserves::serde::from_bytes(&bytes).map_err(|e| JournalError::Blob {
+                digest,
+                detail: e.to_string(),
+            })?;
+        // A spilled compound may hold references to its spilled children
+        return Ok(Some(rehydrate(store, &full)?.unwrap_or(full)));
+    }
+    map_children(value, &mut |child| rehydrate(store, child))
+}
+
+/// `value` with every record that could be read as a reference or an escape
+/// wrapped in an escape, or `None` when it has none
+fn escape(value: &IOValue) -> Option<IOValue> {
+    let children =
+        map_children(value, &mut |child| Ok(escape(child))).expect("escaping does not fail");
+    let looks_reserved = [BLOB_LABEL, ESCAPE_LABEL]
+        .iter()
+        .any(|label| record_with_label(value, label).is_some());
+    match (looks_reserved, children) {
+        (true, children) => Some(wrap_escaped(children.unwrap_or_else(|| value.clone()))),
+        (false, children) => children,
+    }
+}
+
+/// `<duet:blob-escaped value>`
+fn wrap_escaped(value: IOValue) -> IOValue {
+    IOValue::record(IOValue::symbol(ESCAPE_LABEL), vec![value])
+}
+
+/// The value an escape wraps, if `value` is one
+fn escaped(value: &IOValue) -> Option<IOValue> {
+    record_with_label(value, ESCAPE_LABEL)
+        .filter(|record| record.len() == 1)
+        .map(|record| record.field(0))
+}
+
+/// Store `packed` and return the reference standing in for it
+fn blob_reference(store: &BlobStore, packed: &[u8]) -> JournalResult<IOValue> {
+    let digest = store.put(packed).map_err(|e| JournalError::Blob {
+        digest: format!("{:x}", Sha256::digest(packed)),
+        detail: e.to_string(),
+    })?;
+    Ok(IOValue::record(
+        IOValue::symbol(BLOB_LABEL),
+        vec![IOValue::new(digest), IOValue::new(packed.len() as i64)],
+    ))
+}
+
+/// Packed encoding of `value`, as it is journaled
+fn pack(value: &IOValue) -> JournalResult<Vec<u8>> {
+    use preserves::PackedWriter;
+    let mut buf = Vec::new();
+    let mut writer = PackedWriter::new(&mut buf);
+    preserves::serde::to_writer(&mut writer, value)
+        .map_err(|e| JournalError::EncodingError(e.to_string()))?;
+    Ok(buf)
+}
+
+/// `value` with `f` applied to its fields, items or dictionary values, or
+/// `None` when `f` changed none of them
+fn map_children(
+    value: &IOValue,
+    f: &mut dyn FnMut(&IOValue) -> JournalResult<Option<IOValue>>,
+) -> JournalResult<Option<IOValue>> {
+    let mut changed = false;
+    let mut apply = |child: IOValue| -> JournalResult<IOValue> {
+        Ok(match f(&child)? {
+            Some(mapped) => {
+                changed = true;
+                mapped
+            }
+            None => child,
+        })
+    };
+    let mapped = match value.value_class() {
+        ValueClass::Atomic(_) | ValueClass::Embedded => return Ok(None),
+        ValueClass::Compound(CompoundClass::Record) => {
+            let fields = value
+                .iter()
+                .map(|field| apply(IOValue::from(field)))
+                .collect::<JournalResult<Vec<_>>>()?;
+            IOValue::record(IOValue::from(value.label()), fields)
+        }
+        ValueClass::Compound(CompoundClass::Sequence) => IOValue::new(
+            value
+                .iter()
+                .map(|item| apply(IOValue::from(item)))
+                .collect::<JournalResult<Vec<_>>>()?,
+        ),
+        ValueClass::Compound(CompoundClass::Set) => IOValue::new(
+            value
+                .iter()
+                .map(|item| apply(IOValue::from(item)))
+                .collect::<JournalResult<preserves::Set<_>>>()?,
+        ),
+        ValueClass::Compound(CompoundClass::Dictionary) => IOValue::new(
+            value
+                .entries()
+                .map(|(key, entry)| Ok((IOValue::from(key), apply(IOValue::from(entry))?)))
+                .collect::<JournalResult<preserves::Map<_, _>>>()?,
+        ),
+    };
+    Ok(changed.then_some(mapped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_large_fields_spill_and_rehydrate() {
+        let temp = TempDir::new().unwrap();
+        let store = BlobStore::new(Storage::new(temp.path().to_path_buf()));
+        let content = "x".repeat(4096);
+        let value = IOValue::record(
+            IOValue::symbol("file-content"),
+            vec![
+                IOValue::new("notes.txt".to_string()),
+                IOValue::new(content.clone()),
+            ],
+        );
+
+        assert!(spill(&store, &value, 8192).unwrap().is_none());
+        let spilled = spill(&store, &value, 1024).unwrap().unwrap();
+        // Only the content moved out; the path stays inline
+        assert!(record_with_label(&spilled, "file-content").is_some());
+        assert!(pack(&spilled).unwrap().len() < 1024);
+        assert_eq!(rehydrate(&store, &spilled).unwrap(), Some(value.clone()));
+
+        // Equal content is stored once
+        let again = spill(&store, &value, 1024).unwrap().unwrap();
+        assert_eq!(again, spilled);
+        let reference = IOValue::from(spilled.index(1));
+        let digest = record_with_label(&reference, BLOB_LABEL)
+            .unwrap()
+            .field_string(0)
+            .unwrap();
+        assert!(store.contains(&digest));
+
+        std::fs::write(store.path(&digest), b"tampered").unwrap();
+        assert!(rehydrate(&store, &spilled).is_err());
+    }
+
+    #[test]
+    fn test_lookalike_references_are_escaped() {
+        let temp = TempDir::new().unwrap();
+        let store = BlobStore::new(Storage::new(temp.path().to_path_buf()));
+        let stored = store.put(b"someone else's secret").unwrap();
+        let forged = IOValue::record(
+            IOValue::symbol(BLOB_LABEL),
+            vec![IOValue::new(stored), IOValue::new(21i64)],
+        );
+        let escape_lookalike = IOValue::record(IOValue::symbol(ESCAPE_LABEL), vec![forged.clone()]);
+        let value = IOValue::new(vec![
+            forged,
+            escape_lookalike,
+            IOValue::new("x".repeat(4096)),
+        ]);
+
+        let escaped = escape(&value).unwrap();
+        let spilled = spill(&store, &escaped, 1024).unwrap().unwrap();
+        assert_eq!(rehydrate(&store, &spilled).unwrap(), Some(value));
+    }
+
+    #[test]
+    fn test_backups_and_outbox_deliveries_spill() {
+        use crate::runtime::state::StateDelta;
+        use crate::runtime::turn::{ActorId, BranchId, LogicalClock, TurnId, TurnOutput};
+
+        let temp = TempDir::new().unwrap();
+        let store = BlobStore::new(Storage::new(temp.path().to_path_buf()));
+        let content = "y".repeat(4096);
+        let record = TurnRecord {
+            turn_id: TurnId::new("turn_00000001".to_string()),
+            actor: ActorId::new(),
+            branch: BranchId::main(),
+            clock: LogicalClock::zero(),
+            parent: None,
+            inputs: vec![],
+            outputs: vec![
+                TurnOutput::FileBackup {
+                    entity_id: None,
+                    path: "big.txt".into(),
+                    location: "/work/big.txt".into(),
+                    previous: Some(IOValue::bytes(content.clone().into_bytes())),
+                },
+                TurnOutput::OutboxStaged {
+                    entity_id: None,
+                    channel: "mail".into(),
+                    payload: IOValue::new(content),
+                },
+            ],
+            delta: StateDelta::empty(),
+            timestamp: chrono::Utc::now(),
+            vector_clock: Default::default(),
+            initiator: None,
+        };
+
+        let mut spilled = spill_record(&store, &record, 1024).unwrap().unwrap();
+        for payload in spilled.payloads_mut() {
+            assert!(record_with_label(payload, BLOB_LABEL).is_some());
+        }
+        rehydrate_record(&store, &mut spilled).unwrap();
+        let mut original = record.clone();
+        let expected: Vec<IOValue> = original
+            .payloads_mut()
+            .into_iter()
+            .map(|v| v.clone())
+            .collect();
+        let restored: Vec<IOValue> = spilled
+            .payloads_mut()
+            .into_iter()
+            .map(|v| v.clone())
+            .collect();
+        assert_eq!(restored, expected);
+    }
+}
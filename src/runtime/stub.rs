@@ -0,0 +1,90 @@
+//! Recorder stubs standing in for entities during replay
+//!
+//! Replaying history only needs the journal: every turn's state delta is
+//! recorded, so time travel, forks and snapshot verification never run
+//! entity code. Hydration still instantiates every registered entity,
+//! though, which fails for types the current build does not provide and
+//! drags in nondeterministic ones (agents, workspace watchers) that a
+//! verification environment would rather not run. With [`StubConfig`] those
+//! entities are hydrated as a [`RecorderStub`] instead: it holds the entity's
+//! last snapshotted state so later snapshots carry it forward unchanged, and
+//! refuses to handle live input, so a stubbed environment can replay but
+//! never diverges from the recorded history.
+
+use preserves::IOValue;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use super::actor::{Activation, Entity};
+use super::error::{ActorError, ActorResult};
+use super::turn::Handle;
+
+/// Which entity types are replaced by recorder stubs when hydrating.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StubConfig {
+    /// Stub entities whose type is not registered instead of failing
+    pub unknown_types: bool,
+    /// Registered types to stub anyway, typically nondeterministic ones
+    pub types: BTreeSet<String>,
+}
+
+impl StubConfig {
+    /// Whether an entity of `entity_type` should be hydrated as a stub.
+    pub fn should_stub(&self, entity_type: &str, registered: bool) -> bool {
+        (!registered && self.unknown_types) || self.types.contains(entity_type)
+    }
+}
+
+/// Inert entity standing in for one that cannot or should not run.
+pub struct RecorderStub {
+    entity_type: String,
+    state: Option<IOValue>,
+}
+
+impl RecorderStub {
+    /// Stub for an entity of `entity_type` whose last snapshotted state was
+    /// `state`.
+    pub fn new(entity_type: impl Into<String>, state: Option<IOValue>) -> Self {
+        Self {
+            entity_type: entity_type.into(),
+            state,
+        }
+    }
+
+    /// Type of the entity this stub replaces.
+    pub fn entity_type(&self) -> &str {
+        &self.entity_type
+    }
+
+    /// Snapshotted state carried over from the replaced entity.
+    pub fn state(&self) -> Option<&IOValue> {
+        self.state.as_ref()
+    }
+
+    fn refuse(&self) -> ActorResult<()> {
+        Err(ActorError::ExecutionFailed(format!(
+            "entity type '{}' is stubbed for replay and cannot handle live input",
+            self.entity_type
+        )))
+    }
+}
+
+impl Entity for RecorderStub {
+    fn on_message(&self, _activation: &mut Activation, _payload: &IOValue) -> ActorResult<()> {
+        self.refuse()
+    }
+
+    fn on_assert(
+        &self,
+        _activation: &mut Activation,
+        _handle: &Handle,
+        _value: &IOValue,
+    ) -> ActorResult<()> {
+        self.refuse()
+    }
+
+    fn on_retract(&self, _activation: &mut Activation, _handle: &Handle) -> ActorResult<()> {
+        self.refuse()
+    }
+}
@@ -0,0 +1,250 @@
+//! Sturdy references: serializable, signed capability tokens
+//!
+//! A sturdy ref is the portable form of a live capability. It names the
+//! capability, carries extra attenuation caveats and an optional expiry, and
+//! is signed with a runtime-local secret so the issuing runtime can verify it
+//! when an external client or another runtime hands it back for redemption.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::util::hex;
+
+use super::error::{CapabilityError, StorageError, StorageResult};
+use super::pattern::matches_pattern;
+use super::state::CapId;
+use super::storage::Storage;
+
+/// Domain separator mixed into every sturdy-ref signature.
+const SIGNATURE_CONTEXT: &[u8] = b"duet/sturdy-ref/v1";
+
+/// File (under the meta directory) holding the runtime's signing secret.
+const KEY_FILE: &str = "sturdy.key";
+
+/// Prefix marking the textual token form of a sturdy ref.
+const TOKEN_PREFIX: &str = "sturdy:";
+
+/// Signed, serializable reference to a capability.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SturdyRef {
+    /// Capability this reference redeems to
+    pub capability: CapId,
+    /// Semantic kind of the capability at export time
+    pub kind: String,
+    /// Caveats every redemption payload must match (pattern syntax)
+    #[serde(with = "caveat_serde")]
+    pub attenuation: Vec<preserves::IOValue>,
+    /// Instant after which the reference can no longer be redeemed
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Hex-encoded keyed BLAKE3 signature over the fields above
+    pub signature: String,
+}
+
+impl SturdyRef {
+    /// Encode the reference as an opaque token suitable for copy/paste.
+    pub fn to_token(&self) -> String {
+        let json = serde_json::to_vec(self).expect("sturdy refs always serialize");
+        format!("{}{}", TOKEN_PREFIX, hex::encode(&json))
+    }
+
+    /// Decode a token produced by [`SturdyRef::to_token`].
+    pub fn from_token(token: &str) -> Result<Self, CapabilityError> {
+        let malformed = |detail: &str| CapabilityError::MalformedSturdyRef(detail.to_string());
+
+        let digits = token
+            .trim()
+            .strip_prefix(TOKEN_PREFIX)
+            .ok_or_else(|| malformed("missing sturdy: prefix"))?;
+        let bytes = hex::decode(digits).map_err(malformed)?;
+
+        serde_json::from_slice(&bytes).map_err(|err| malformed(&err.to_string()))
+    }
+
+    /// Check whether the reference has expired as of `now`.
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|deadline| now >= deadline)
+    }
+
+    /// Check a redemption payload against the reference's caveats.
+    pub fn admits(&self, payload: &preserves::IOValue) -> bool {
+        self.attenuation
+            .iter()
+            .all(|caveat| matches_pattern(caveat, payload))
+    }
+}
+
+/// Runtime-local secret used to sign and verify sturdy refs.
+#[derive(Clone)]
+pub struct SturdyKey([u8; 32]);
+
+impl SturdyKey {
+    /// Load the signing secret from storage, generating one on first use.
+    pub fn load_or_create(storage: &Storage) -> StorageResult<Self> {
+        let path = storage.meta_dir().join(KEY_FILE);
+        if storage.exists(&path) {
+            let text = String::from_utf8(storage.read_file(&path)?)
+                .map_err(|_| StorageError::ConfigError("sturdy key is not UTF-8".into()))?;
+            let hash = blake3::Hash::from_hex(text.trim())
+                .map_err(|err| StorageError::ConfigError(format!("invalid sturdy key: {}", err)))?;
+            return Ok(Self(*hash.as_bytes()));
+        }
+
+        let mut secret = [0u8; 32];
+        secret[..16].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+        secret[16..].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+        storage.create_dir_all(&storage.meta_dir())?;
+        storage.write_atomic(&path, blake3::Hash::from(secret).to_hex().as_bytes())?;
+        Ok(Self(secret))
+    }
+
+    /// Mint a signed reference for `capability`.
+    pub fn sign(
+        &self,
+        capability: CapId,
+        kind: impl Into<String>,
+        attenuation: Vec<preserves::IOValue>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> SturdyRef {
+        let mut sturdy = SturdyRef {
+            capability,
+            kind: kind.into(),
+            attenuation,
+            expires_at,
+            signature: String::new(),
+        };
+        sturdy.signature = self.digest(&sturdy).to_hex().to_string();
+        sturdy
+    }
+
+    /// Verify that `sturdy` was minted by this key and has not been altered.
+    pub fn verify(&self, sturdy: &SturdyRef) -> Result<(), CapabilityError> {
+        let provided = blake3::Hash::from_hex(&sturdy.signature)
+            .map_err(|_| CapabilityError::InvalidSignature(sturdy.capability))?;
+        // blake3::Hash equality is constant-time
+        if provided == self.digest(sturdy) {
+            Ok(())
+        } else {
+            Err(CapabilityError::InvalidSignature(sturdy.capability))
+        }
+    }
+
+    fn digest(&self, sturdy: &SturdyRef) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new_keyed(&self.0);
+        hasher.update(SIGNATURE_CONTEXT);
+        hasher.update(sturdy.capability.as_bytes());
+        hasher.update(&(sturdy.kind.len() as u64).to_le_bytes());
+        hasher.update(sturdy.kind.as_bytes());
+        for caveat in &sturdy.attenuation {
+            let text = format!("{:?}", caveat);
+            hasher.update(&(text.len() as u64).to_le_bytes());
+            hasher.update(text.as_bytes());
+        }
+        match sturdy.expires_at {
+            Some(deadline) => hasher.update(deadline.to_rfc3339().as_bytes()),
+            None => hasher.update(b"never"),
+        };
+        hasher.finalize()
+    }
+}
+
+impl std::fmt::Debug for SturdyKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SturdyKey(..)")
+    }
+}
+
+/// Serialize caveats as preserves text so tokens stay readable JSON.
+mod caveat_serde {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(values: &[preserves::IOValue], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        values
+            .iter()
+            .map(|value| format!("{:?}", value))
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<preserves::IOValue>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|text| text.parse().map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use preserves::IOValue;
+    use tempfile::tempdir;
+
+    #[test]
+    fn token_roundtrip_and_tamper_detection() {
+        let temp = tempdir().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let key = SturdyKey::load_or_create(&storage).unwrap();
+
+        let caveat = IOValue::record(IOValue::symbol("read"), vec![IOValue::symbol("<_>")]);
+        let sturdy = key.sign(uuid::Uuid::new_v4(), "workspace/read", vec![caveat], None);
+
+        let decoded = SturdyRef::from_token(&sturdy.to_token()).unwrap();
+        assert_eq!(decoded, sturdy);
+        key.verify(&decoded).unwrap();
+
+        // The key persists, so a reloaded runtime still accepts the ref
+        let reloaded = SturdyKey::load_or_create(&storage).unwrap();
+        reloaded.verify(&decoded).unwrap();
+
+        let mut widened = decoded.clone();
+        widened.attenuation.clear();
+        assert!(matches!(
+            key.verify(&widened),
+            Err(CapabilityError::InvalidSignature(_))
+        ));
+
+        let other_temp = tempdir().unwrap();
+        let other =
+            SturdyKey::load_or_create(&Storage::new(other_temp.path().to_path_buf())).unwrap();
+        assert!(other.verify(&decoded).is_err());
+    }
+
+    #[test]
+    fn caveats_and_expiry() {
+        let temp = tempdir().unwrap();
+        let key = SturdyKey::load_or_create(&Storage::new(temp.path().to_path_buf())).unwrap();
+        let caveat = IOValue::record(IOValue::symbol("read"), vec![IOValue::symbol("<_>")]);
+        let deadline = Utc::now();
+        let sturdy = key.sign(uuid::Uuid::new_v4(), "kind", vec![caveat], Some(deadline));
+
+        assert!(sturdy.admits(&IOValue::record(
+            IOValue::symbol("read"),
+            vec![IOValue::new("a.txt".to_string())]
+        )));
+        assert!(!sturdy.admits(&IOValue::symbol("write")));
+        assert!(sturdy.is_expired_at(deadline));
+        assert!(!sturdy.is_expired_at(deadline - chrono::Duration::seconds(1)));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected_without_panicking() {
+        for token in [
+            "sturdy:aé1",
+            "sturdy:éé",
+            "sturdy:abc",
+            "sturdy:zz",
+            "token",
+        ] {
+            assert!(matches!(
+                SturdyRef::from_token(token),
+                Err(CapabilityError::MalformedSturdyRef(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Background tasks owned by entity facets
+//!
+//! Entities that talk to the outside world (agents calling external APIs,
+//! for instance) do their slow work off the scheduler thread and report back
+//! with an asynchronous message. Tasks spawned with
+//! [`Activation::spawn_task`](super::actor::Activation::spawn_task) are
+//! tracked here rather than detached: a task only starts once the turn that
+//! spawned it has committed, and it is cancelled when its facet terminates or
+//! when a rewind discards that turn. Cancellation is cooperative – the task
+//! observes its [`CancellationToken`] – but it is decided by the journal, and
+//! anything a cancelled task still sends is dropped before it reaches the
+//! scheduler.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::AsyncMessage;
+use super::turn::{ActorId, BranchId, FacetId, TurnId};
+
+/// Identifier of a managed task.
+pub type TaskId = Uuid;
+
+/// Cooperative cancellation flag shared between the runtime and a task.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl CancellationToken {
+    /// Whether the task has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        *self.inner.0.lock().unwrap()
+    }
+
+    /// Cancel the task, waking it if it is waiting.
+    pub fn cancel(&self) {
+        let (lock, cvar) = &*self.inner;
+        *lock.lock().unwrap() = true;
+        cvar.notify_all();
+    }
+
+    /// Sleep for up to `timeout`, returning early with `true` if cancelled.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let (lock, cvar) = &*self.inner;
+        let guard = lock.lock().unwrap();
+        let (guard, _) = cvar
+            .wait_timeout_while(guard, timeout, |cancelled| !*cancelled)
+            .unwrap();
+        *guard
+    }
+}
+
+/// Handle passed to a running task.
+pub struct TaskContext {
+    id: TaskId,
+    actor: ActorId,
+    facet: FacetId,
+    token: CancellationToken,
+    sender: Sender<AsyncMessage>,
+}
+
+impl TaskContext {
+    /// Identifier of this task.
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Cancellation token to poll or wait on.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Whether the task has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Deliver `payload` to the owning facet, unless the task was cancelled.
+    ///
+    /// Returns whether the message was handed to the runtime.
+    pub fn send(&self, payload: preserves::IOValue) -> bool {
+        if self.is_cancelled() {
+            return false;
+        }
+        self.sender
+            .send(AsyncMessage {
+                actor: self.actor.clone(),
+                facet: self.facet.clone(),
+                payload,
+                cancellation: Some(self.token.clone()),
+            })
+            .is_ok()
+    }
+}
+
+/// Where a managed task is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    /// Waiting for the spawning turn to commit
+    Pending,
+    /// Running on its own thread
+    Running,
+}
+
+/// Description of a live managed task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    /// Task identifier
+    pub id: TaskId,
+    /// Name given by the spawning entity
+    pub name: String,
+    /// Actor owning the task
+    pub actor: ActorId,
+    /// Facet owning the task
+    pub facet: FacetId,
+    /// Entity that spawned the task, if known
+    pub entity: Option<Uuid>,
+    /// Branch of the spawning turn (`None` while pending)
+    pub branch: Option<BranchId>,
+    /// Spawning turn (`None` while pending)
+    pub turn: Option<TurnId>,
+    /// Lifecycle state
+    pub state: TaskState,
+    /// When the task was spawned
+    pub spawned_at: DateTime<Utc>,
+}
+
+type TaskFn = Box<dyn FnOnce(TaskContext) + Send + 'static>;
+
+struct Task {
+    info: TaskInfo,
+    token: CancellationToken,
+    /// Body and delivery channel, until the task starts
+    start: Option<(TaskFn, Sender<AsyncMessage>)>,
+}
+
+/// Tasks owned by the runtime's facets.
+#[derive(Clone, Default)]
+pub struct TaskManager {
+    tasks: Arc<Mutex<HashMap<TaskId, Task>>>,
+}
+
+impl TaskManager {
+    /// Create an empty manager.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a task for `facet`; it starts once the current turn commits.
+    pub(crate) fn spawn(
+        &self,
+        name: String,
+        actor: ActorId,
+        facet: FacetId,
+        entity: Option<Uuid>,
+        sender: Sender<AsyncMessage>,
+        body: TaskFn,
+    ) -> TaskId {
+        let id = Uuid::new_v4();
+        let task = Task {
+            info: TaskInfo {
+                id,
+                name,
+                actor,
+                facet,
+                entity,
+                branch: None,
+                turn: None,
+                state: TaskState::Pending,
+                spawned_at: Utc::now(),
+            },
+            token: CancellationToken::default(),
+            start: Some((body, sender)),
+        };
+        self.tasks.lock().unwrap().insert(id, task);
+        id
+    }
+
+    /// Start the tasks spawned by `turn`, which has just committed.
+    pub fn start_pending(&self, branch: &BranchId, turn: &TurnId) {
+        let mut tasks = self.tasks.lock().unwrap();
+        for task in tasks.values_mut() {
+            let Some((body, sender)) = task.start.take() else {
+                continue;
+            };
+            task.info.branch = Some(branch.clone());
+            task.info.turn = Some(turn.clone());
+            task.info.state = TaskState::Running;
+
+            let context = TaskContext {
+                id: task.info.id,
+                actor: task.info.actor.clone(),
+                facet: task.info.facet.clone(),
+                token: task.token.clone(),
+                sender,
+            };
+            let manager = self.clone();
+            std::thread::Builder::new()
+                .name(format!("duet-task-{}", task.info.name))
+                .spawn(move || {
+                    let id = context.id;
+                    body(context);
+                    manager.tasks.lock().unwrap().remove(&id);
+                })
+                .expect("failed to spawn task thread");
+        }
+    }
+
+    /// Drop tasks spawned by a turn that failed before committing.
+    pub fn discard_pending(&self) {
+        self.tasks
+            .lock()
+            .unwrap()
+            .retain(|_, task| task.start.is_none());
+    }
+
+    /// Cancel tasks owned by `actor` whose facet satisfies `terminated`.
+    pub fn cancel_facets(&self, actor: &ActorId, terminated: impl Fn(&FacetId) -> bool) -> usize {
+        self.cancel_where("facet terminated", |info| {
+            &info.actor == actor && terminated(&info.facet)
+        })
+    }
+
+    /// Cancel tasks spawned on `branch` after `target`, which a rewind to
+    /// `target` discards.
+    pub fn cancel_after(&self, branch: &BranchId, target: &TurnId) -> usize {
+        self.cancel_where("turn rewound", |info| {
+            info.branch.as_ref() == Some(branch)
+                && info
+                    .turn
+                    .as_ref()
+                    .is_some_and(|turn| turn.sequence() > target.sequence())
+        })
+    }
+
+    /// Live tasks, oldest first.
+    pub fn list(&self) -> Vec<TaskInfo> {
+        let mut tasks: Vec<TaskInfo> = self
+            .tasks
+            .lock()
+            .unwrap()
+            .values()
+            .map(|task| task.info.clone())
+            .collect();
+        tasks.sort_by_key(|info| info.spawned_at);
+        tasks
+    }
+
+    fn cancel_where(&self, reason: &str, matches: impl Fn(&TaskInfo) -> bool) -> usize {
+        let mut tasks = self.tasks.lock().unwrap();
+        let cancelled: Vec<TaskId> = tasks
+            .values()
+            .filter(|task| matches(&task.info))
+            .map(|task| task.info.id)
+            .collect();
+        for id in &cancelled {
+            if let Some(task) = tasks.remove(id) {
+                debug!(task = %id, name = %task.info.name, reason, "task cancelled");
+                task.token.cancel();
+            }
+        }
+        cancelled.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn tasks_start_on_commit_and_stop_on_cancel() {
+        let manager = TaskManager::new();
+        let (sender, inbox) = mpsc::channel();
+        let actor = ActorId::new();
+        let facet = FacetId::new();
+        let branch = BranchId::main();
+
+        let discarded = manager.spawn(
+            "discarded".into(),
+            actor.clone(),
+            facet.clone(),
+            None,
+            sender.clone(),
+            Box::new(|task| {
+                task.send(preserves::IOValue::symbol("never"));
+            }),
+        );
+        manager.discard_pending();
+        assert!(manager.list().iter().all(|task| task.id != discarded));
+
+        manager.spawn(
+            "waiter".into(),
+            actor.clone(),
+            facet.clone(),
+            None,
+            sender,
+            Box::new(|task| {
+                let cancelled = task.token().wait(Duration::from_secs(10));
+                task.send(preserves::IOValue::new(cancelled));
+            }),
+        );
+        assert_eq!(manager.list()[0].state, TaskState::Pending);
+
+        manager.start_pending(&branch, &TurnId::new("turn-1".into()));
+        assert_eq!(manager.list()[0].state, TaskState::Running);
+        assert_eq!(
+            manager.cancel_after(&branch, &TurnId::new("turn-1".into())),
+            0
+        );
+        assert_eq!(manager.cancel_facets(&actor, |f| f == &facet), 1);
+        assert!(manager.list().is_empty());
+
+        // The task wakes up cancelled and its late result is suppressed
+        assert!(inbox.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Wakeups entities schedule for themselves
+//!
+//! [`Activation::set_timer`](super::actor::Activation::set_timer) records a
+//! [`TurnOutput::TimerRegistered`] in the turn that sets the timer. Once its
+//! deadline passes on the wall clock, the runtime enqueues a
+//! [`TurnInput::Timer`] for the actor, and the entities on the timer's facet
+//! receive the payload in a turn of their own. Both ends are journaled: replay
+//! applies the timer turn at the position it was recorded at, and the set of
+//! pending timers is derived from the branch history (registered but not yet
+//! fired), so it follows time travel and branch switches.
+
+use chrono::{DateTime, Utc};
+use preserves::IOValue;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+use super::turn::{ActorId, FacetId, TurnInput, TurnOutput, TurnRecord};
+
+/// Timer registered on the current branch that has not fired yet.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PendingTimer {
+    /// Timer identifier
+    pub timer_id: Uuid,
+    /// Actor that set the timer
+    pub actor: ActorId,
+    /// Facet whose entities are woken up
+    pub facet: FacetId,
+    /// When the timer fires
+    pub deadline: DateTime<Utc>,
+    /// Payload delivered when it fires
+    pub payload: IOValue,
+}
+
+impl PendingTimer {
+    /// Input delivering this timer to its actor.
+    pub fn input(&self) -> TurnInput {
+        TurnInput::Timer {
+            actor: self.actor.clone(),
+            timer_id: self.timer_id,
+            deadline: self.deadline,
+            facet: self.facet.clone(),
+            payload: self.payload.clone(),
+        }
+    }
+}
+
+/// Pending timers of the current branch.
+#[derive(Debug, Default)]
+pub struct TimerTable {
+    pending: BTreeMap<Uuid, PendingTimer>,
+    /// Timers whose wakeup is queued but not yet executed
+    queued: BTreeSet<Uuid>,
+}
+
+impl TimerTable {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget every timer.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.queued.clear();
+    }
+
+    /// Apply the timers a turn set and fired.
+    pub fn record(&mut self, record: &TurnRecord) {
+        for input in &record.inputs {
+            if let TurnInput::Timer { timer_id, .. } = input {
+                self.pending.remove(timer_id);
+                self.queued.remove(timer_id);
+            }
+        }
+        for output in &record.outputs {
+            if let TurnOutput::TimerRegistered {
+                timer_id,
+                deadline,
+                facet,
+                payload,
+            } = output
+            {
+                self.pending.insert(
+                    *timer_id,
+                    PendingTimer {
+                        timer_id: *timer_id,
+                        actor: record.actor.clone(),
+                        facet: facet.clone(),
+                        deadline: *deadline,
+                        payload: payload.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Timers due at `now` whose wakeup is not queued yet, earliest first.
+    ///
+    /// The returned timers are marked queued.
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Vec<PendingTimer> {
+        let mut due: Vec<PendingTimer> = self
+            .pending
+            .values()
+            .filter(|timer| timer.deadline <= now && !self.queued.contains(&timer.timer_id))
+            .cloned()
+            .collect();
+        due.sort_by_key(|timer| (timer.deadline, timer.timer_id));
+        self.queued.extend(due.iter().map(|timer| timer.timer_id));
+        due
+    }
+
+    /// Earliest deadline of a timer whose wakeup is not queued yet.
+    pub fn next_deadline(&self) -> Option<DateTime<Utc>> {
+        self.pending
+            .values()
+            .filter(|timer| !self.queued.contains(&timer.timer_id))
+            .map(|timer| timer.deadline)
+            .min()
+    }
+
+    /// Pending timers, earliest deadline first.
+    pub fn pending(&self) -> Vec<PendingTimer> {
+        let mut timers: Vec<PendingTimer> = self.pending.values().cloned().collect();
+        timers.sort_by_key(|timer| (timer.deadline, timer.timer_id));
+        timers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::state::StateDelta;
+    use crate::runtime::turn::{BranchId, LogicalClock};
+
+    fn record(actor: &ActorId, inputs: Vec<TurnInput>, outputs: Vec<TurnOutput>) -> TurnRecord {
+        TurnRecord::new(
+            actor.clone(),
+            BranchId::main(),
+            LogicalClock::zero(),
+            None,
+            inputs,
+            outputs,
+            StateDelta::empty(),
+        )
+    }
+
+    #[test]
+    fn test_timers_fire_once_after_their_deadline() {
+        let actor = ActorId::new();
+        let facet = FacetId::new();
+        let now = Utc::now();
+        let timer_id = Uuid::new_v4();
+        let mut table = TimerTable::new();
+        table.record(&record(
+            &actor,
+            Vec::new(),
+            vec![TurnOutput::TimerRegistered {
+                timer_id,
+                deadline: now,
+                facet: facet.clone(),
+                payload: IOValue::symbol("wake"),
+            }],
+        ));
+
+        assert!(
+            table
+                .take_due(now - chrono::Duration::seconds(1))
+                .is_empty()
+        );
+        assert_eq!(table.next_deadline(), Some(now));
+        let due = table.take_due(now);
+        assert_eq!(due.len(), 1);
+        assert!(table.take_due(now).is_empty());
+        assert_eq!(table.next_deadline(), None);
+        assert_eq!(table.pending().len(), 1);
+
+        table.record(&record(&actor, vec![due[0].input()], Vec::new()));
+        assert!(table.pending().is_empty());
+    }
+}
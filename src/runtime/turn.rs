@@ -4,39 +4,200 @@
 //! and state deltas for a single deterministic execution step. Turn IDs are
 //! computed deterministically from inputs using Blake3 hashing.
 
+use super::identity::Identity;
 use super::pattern::Pattern;
 use super::state::{CapId, CapabilityTarget, StateDelta};
 use blake3::Hasher;
 use chrono::{DateTime, Utc};
 use preserves::serde::Error as PreservesSerdeError;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 use uuid::Uuid;
 
 /// Unique identifier for a turn, deterministically computed
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
-pub struct TurnId(String);
+///
+/// A turn id combines the branch Lamport sequence at which the turn was
+/// recorded, the actor that executed it, and a Blake3 digest of its inputs.
+/// Ids order by sequence first, so comparisons follow causal order along a
+/// branch rather than the lexical order of their rendered form.
+///
+/// The textual form is `turn-<seq>-<actor>-<digest>`, with the sequence
+/// zero-padded to twelve digits. Ids written by older runtimes
+/// (`turn_<hex>`, `turn_00000000`, ...) are still accepted: they parse as
+/// legacy ids with sequence zero and round-trip verbatim, so existing
+/// journals stay readable until they are migrated with
+/// [`migrate_legacy_turn_ids`](super::journal::migrate_legacy_turn_ids).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TurnId {
+    seq: u64,
+    actor: Option<Uuid>,
+    digest: String,
+    legacy: bool,
+    repr: String,
+}
+
+/// Prefix used by structured turn identifiers.
+const TURN_ID_PREFIX: &str = "turn-";
+
+/// Placeholder rendered in place of an actor for actor-less identifiers.
+const NO_ACTOR: &str = "root";
+
+/// Number of hex characters of the Blake3 digest kept in a turn id.
+const TURN_DIGEST_LEN: usize = 32;
 
 impl TurnId {
-    /// Create a new TurnId from a string
+    /// Parse a TurnId from its textual form
+    ///
+    /// Strings that are not structured ids are kept as legacy identifiers.
     pub fn new(id: String) -> Self {
-        Self(id)
+        Self::parse_structured(&id).unwrap_or_else(|| Self::legacy(id))
     }
 
-    /// Get the inner string
+    /// Build a structured turn id from its components
+    pub fn structured(seq: u64, actor: Option<&ActorId>, digest: impl Into<String>) -> Self {
+        let actor = actor.map(|actor| actor.0);
+        let digest = digest.into();
+        let repr = format!(
+            "{TURN_ID_PREFIX}{seq:012}-{}-{digest}",
+            actor
+                .map(|uuid| uuid.simple().to_string())
+                .unwrap_or_else(|| NO_ACTOR.to_string())
+        );
+        Self {
+            seq,
+            actor,
+            digest,
+            legacy: false,
+            repr,
+        }
+    }
+
+    /// The id of the empty history every branch starts from
+    pub fn genesis() -> Self {
+        Self::structured(0, None, "genesis")
+    }
+
+    fn legacy(id: String) -> Self {
+        Self {
+            seq: 0,
+            actor: None,
+            digest: id.clone(),
+            legacy: true,
+            repr: id,
+        }
+    }
+
+    fn parse_structured(id: &str) -> Option<Self> {
+        let rest = id.strip_prefix(TURN_ID_PREFIX)?;
+        let mut parts = rest.splitn(3, '-');
+        let seq_part = parts.next()?;
+        let actor_part = parts.next()?;
+        let digest = parts.next()?;
+        if seq_part.len() < 12 || digest.is_empty() {
+            return None;
+        }
+        let seq = seq_part.parse::<u64>().ok()?;
+        let actor = if actor_part == NO_ACTOR {
+            None
+        } else {
+            Some(ActorId::from_uuid(Uuid::try_parse(actor_part).ok()?))
+        };
+        Some(Self::structured(seq, actor.as_ref(), digest))
+    }
+
+    /// Get the textual form of this id
     pub fn as_str(&self) -> &str {
-        &self.0
+        &self.repr
+    }
+
+    /// Branch Lamport sequence at which the turn was recorded
+    pub fn sequence(&self) -> u64 {
+        self.seq
+    }
+
+    /// Actor that executed the turn, if encoded in the id
+    pub fn actor(&self) -> Option<ActorId> {
+        self.actor.map(ActorId::from_uuid)
+    }
+
+    /// Whether this id was produced by a runtime predating structured ids
+    pub fn is_legacy(&self) -> bool {
+        self.legacy
+    }
+
+    /// Whether this is the genesis placeholder
+    pub fn is_genesis(&self) -> bool {
+        *self == Self::genesis()
+    }
+}
+
+impl Ord for TurnId {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.seq
+            .cmp(&other.seq)
+            .then_with(|| self.actor.cmp(&other.actor))
+            .then_with(|| self.digest.cmp(&other.digest))
+    }
+}
+
+impl PartialOrd for TurnId {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::str::FromStr for TurnId {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s.to_string()))
     }
 }
 
 impl fmt::Display for TurnId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.repr)
+    }
+}
+
+// TurnIds keep the wire shape of the original `TurnId(String)` newtype so
+// existing journals, snapshots, and JSON metadata decode unchanged.
+impl Serialize for TurnId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct("TurnId", &self.repr)
+    }
+}
+
+impl<'de> Deserialize<'de> for TurnId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TurnIdVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for TurnIdVisitor {
+            type Value = TurnId;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a turn id string")
+            }
+
+            fn visit_newtype_struct<D: serde::Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<Self::Value, D::Error> {
+                String::deserialize(deserializer).map(TurnId::new)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                Ok(TurnId::new(value.to_string()))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct("TurnId", TurnIdVisitor)
     }
 }
 
 /// Actor identifier
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ActorId(pub Uuid);
 
 impl ActorId {
@@ -86,7 +247,7 @@ impl Default for FacetId {
 }
 
 /// Branch identifier
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct BranchId(pub String);
 
 impl BranchId {
@@ -99,6 +260,16 @@ impl BranchId {
     pub fn main() -> Self {
         Self("main".to_string())
     }
+
+    /// Reserved branch recording configuration history
+    pub fn config() -> Self {
+        Self("__config".to_string())
+    }
+
+    /// Whether the name is reserved for the runtime (`__` prefix)
+    pub fn is_reserved(&self) -> bool {
+        self.0.starts_with("__")
+    }
 }
 
 impl fmt::Display for BranchId {
@@ -128,6 +299,111 @@ impl LogicalClock {
     }
 }
 
+/// Deterministic timestamp of a recorded turn
+///
+/// Derived from the turn's branch sequence, the executing actor's logical
+/// clock and the branch itself, so replays reproduce it exactly and turns
+/// from different branches remain comparable. Ordering follows those fields
+/// in turn; the wall-clock [`TurnRecord::timestamp`] is advisory only.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LogicalTimestamp {
+    /// Lamport sequence of the turn on the branch that recorded it
+    pub sequence: u64,
+    /// Logical clock of the executing actor
+    pub clock: u64,
+    /// Branch that recorded the turn
+    pub branch: BranchId,
+}
+
+impl LogicalTimestamp {
+    /// Timestamp of `turn_id`, executed at `clock` on `branch`
+    pub fn of(turn_id: &TurnId, clock: LogicalClock, branch: &BranchId) -> Self {
+        Self {
+            sequence: turn_id.sequence(),
+            clock: clock.0,
+            branch: branch.clone(),
+        }
+    }
+}
+
+impl fmt::Display for LogicalTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}@{}", self.sequence, self.clock, self.branch)
+    }
+}
+
+/// Vector clock tracking causal history across actors
+///
+/// Each entry counts the turns of one actor that causally precede (or are)
+/// the stamped turn. Two turns whose clocks are incomparable executed
+/// concurrently, which merges and schedulers use to prove independence.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorClock(pub BTreeMap<ActorId, u64>);
+
+impl VectorClock {
+    /// Create an empty vector clock
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counter recorded for an actor
+    pub fn get(&self, actor: &ActorId) -> u64 {
+        self.0.get(actor).copied().unwrap_or(0)
+    }
+
+    /// Advance the entry for `actor`
+    pub fn increment(&mut self, actor: &ActorId) {
+        *self.0.entry(actor.clone()).or_insert(0) += 1;
+    }
+
+    /// Pointwise maximum of two clocks
+    pub fn join(&self, other: &Self) -> Self {
+        let mut joined = self.clone();
+        joined.join_in_place(other);
+        joined
+    }
+
+    /// Merge `other` into this clock
+    pub fn join_in_place(&mut self, other: &Self) {
+        for (actor, count) in &other.0 {
+            let entry = self.0.entry(actor.clone()).or_insert(0);
+            *entry = (*entry).max(*count);
+        }
+    }
+
+    /// Causal comparison; `None` means the clocks are concurrent
+    pub fn partial_cmp_causal(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        use std::cmp::Ordering;
+
+        let mut less = false;
+        let mut greater = false;
+        for actor in self.0.keys().chain(other.0.keys()) {
+            match self.get(actor).cmp(&other.get(actor)) {
+                Ordering::Less => less = true,
+                Ordering::Greater => greater = true,
+                Ordering::Equal => {}
+            }
+        }
+
+        match (less, greater) {
+            (false, false) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (true, true) => None,
+        }
+    }
+
+    /// Whether this clock strictly precedes `other`
+    pub fn happened_before(&self, other: &Self) -> bool {
+        self.partial_cmp_causal(other) == Some(std::cmp::Ordering::Less)
+    }
+
+    /// Whether neither clock precedes the other
+    pub fn concurrent_with(&self, other: &Self) -> bool {
+        self.partial_cmp_causal(other).is_none()
+    }
+}
+
 /// Handle for an assertion (unique per actor)
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Handle(pub Uuid);
@@ -162,6 +438,12 @@ pub enum TurnInput {
         facet: FacetId,
         /// Message payload
         payload: preserves::IOValue,
+        /// Caller-supplied token used to suppress redelivered duplicates
+        #[serde(default)]
+        idempotency_key: Option<String>,
+        /// Broadcast this message was fanned out from, if any
+        #[serde(default)]
+        broadcast: Option<Uuid>,
     },
 
     /// Assertion added to the dataspace
@@ -172,6 +454,9 @@ pub enum TurnInput {
         handle: Handle,
         /// Assertion value
         value: preserves::IOValue,
+        /// Named dataspace to publish into (`None` = default dataspace)
+        #[serde(default)]
+        namespace: Option<String>,
     },
 
     /// Retraction of a previous assertion
@@ -198,6 +483,10 @@ pub enum TurnInput {
         timer_id: Uuid,
         /// Deadline that was reached
         deadline: DateTime<Utc>,
+        /// Facet whose entities are woken up
+        facet: FacetId,
+        /// Payload the timer was set with
+        payload: preserves::IOValue,
     },
 
     /// Response from an external service
@@ -216,6 +505,9 @@ pub enum TurnInput {
         capability: CapId,
         /// Payload supplied with invocation
         payload: preserves::IOValue,
+        /// Token of an asynchronous invocation awaiting this result
+        #[serde(default)]
+        invocation: Option<Uuid>,
     },
 
     /// Remote message from another node (future)
@@ -236,6 +528,79 @@ pub enum TurnInput {
         target_branch: BranchId,
         /// LCA turn where branches diverged
         lca_turn: TurnId,
+        /// Source head the merge joined (absent from merges recorded before
+        /// it was kept)
+        source_head: Option<TurnId>,
+    },
+
+    /// Assertion made by another actor that matched a dataspace-wide pattern
+    ObservedAssert {
+        /// Actor holding the dataspace-wide pattern
+        observer: ActorId,
+        /// Actor that made the assertion
+        source: ActorId,
+        /// Handle of the observed assertion
+        handle: Handle,
+        /// Observed assertion value
+        value: preserves::IOValue,
+        /// Named dataspace of the observed assertion (`None` = default dataspace)
+        namespace: Option<String>,
+    },
+
+    /// Retraction of an assertion previously routed as [`TurnInput::ObservedAssert`]
+    ObservedRetract {
+        /// Actor holding the dataspace-wide pattern
+        observer: ActorId,
+        /// Actor that retracted the assertion
+        source: ActorId,
+        /// Handle of the retracted assertion
+        handle: Handle,
+    },
+
+    /// Administrative retraction of every assertion matching a pattern
+    RetractMatching {
+        /// Actor whose assertions are retracted
+        actor: ActorId,
+        /// Pattern the retracted assertions matched
+        pattern: preserves::IOValue,
+        /// Handles matched when the retraction was requested
+        handles: Vec<Handle>,
+    },
+
+    /// Administrative pause: the actor's turns are held until resumed
+    PauseActor {
+        /// Paused actor
+        actor: ActorId,
+    },
+
+    /// Administrative resume of a paused actor
+    ResumeActor {
+        /// Resumed actor
+        actor: ActorId,
+    },
+
+    /// Administrative change of a runtime feature flag, run by the system actor
+    SetFlag {
+        /// Flag being changed
+        flag: super::flags::FeatureFlag,
+        /// New value
+        enabled: bool,
+        /// Handle of the new `<feature-flag ...>` assertion
+        handle: Handle,
+        /// Handles of the flag's previous assertions
+        retracted: Vec<Handle>,
+    },
+
+    /// Configuration change journaled on the reserved config branch
+    ConfigChange {
+        /// Working branch the change was made on
+        branch: BranchId,
+        /// Head of `branch` when the change was made (`None` in journals
+        /// written before changes were anchored)
+        #[serde(default)]
+        at: Option<TurnId>,
+        /// The change
+        change: super::config_history::ConfigChange,
     },
 }
 
@@ -248,12 +613,18 @@ pub enum TurnOutput {
         handle: Handle,
         /// Assertion value
         value: preserves::IOValue,
+        /// Named dataspace the assertion was published into (`None` = default)
+        #[serde(default)]
+        namespace: Option<String>,
     },
 
     /// Retraction made during this turn
     Retract {
         /// Handle being retracted
         handle: Handle,
+        /// Named dataspace the retracted assertion lived in (`None` = default)
+        #[serde(default)]
+        namespace: Option<String>,
     },
 
     /// Message sent to another actor/facet
@@ -292,6 +663,10 @@ pub enum TurnOutput {
         timer_id: Uuid,
         /// Deadline
         deadline: DateTime<Utc>,
+        /// Facet whose entities are woken up
+        facet: FacetId,
+        /// Payload delivered when the timer fires
+        payload: preserves::IOValue,
     },
 
     /// External service request
@@ -418,6 +793,84 @@ pub enum TurnOutput {
         capability: CapId,
         /// Invocation result payload
         result: preserves::IOValue,
+        /// Token of the asynchronous invocation this result answers
+        #[serde(default)]
+        invocation: Option<Uuid>,
+    },
+
+    /// Entity looked up a runtime secret (the value is never recorded)
+    SecretAccessed {
+        /// Entity instance that performed the lookup
+        entity_id: Option<Uuid>,
+        /// Secret name
+        name: String,
+        /// Whether the secret was available
+        found: bool,
+    },
+
+    /// Entity performed an external effect that time travel cannot undo
+    SideEffect {
+        /// Entity instance that performed the effect
+        entity_id: Option<Uuid>,
+        /// Kind of effect
+        kind: super::effects::EffectKind,
+        /// What was affected (path, repository, URL, ...)
+        target: String,
+        /// Human-readable description
+        description: String,
+    },
+
+    /// Content a file write replaced, kept so it can be restored later
+    FileBackup {
+        /// Entity instance that performed the write
+        entity_id: Option<Uuid>,
+        /// Path as the entity reports it (workspace-relative for the workspace)
+        path: String,
+        /// Absolute location the write went to
+        location: String,
+        /// Previous content as a byte string (`None` if the file did not exist)
+        previous: Option<preserves::IOValue>,
+    },
+
+    /// Where an assertion joined by a merge turn was originally made
+    MergeProvenance {
+        /// Actor owning the assertion
+        actor: ActorId,
+        /// Handle of the merged assertion
+        handle: Handle,
+        /// Branch whose turn made the assertion
+        source_branch: BranchId,
+        /// Turn that made the assertion
+        source_turn: TurnId,
+    },
+
+    /// Invocation of whichever capability of `kind` the actor holds,
+    /// resolved by the runtime after the turn
+    CapabilityInvokeByKind {
+        /// Capability kind to look up
+        kind: String,
+        /// Payload supplied with the invocation
+        payload: preserves::IOValue,
+        /// Completion metadata describing how to publish the result
+        completion: CapabilityCompletion,
+    },
+
+    /// Delivery to an external system, handed to the outbox once the turn commits
+    OutboxStaged {
+        /// Entity instance that staged the delivery
+        entity_id: Option<Uuid>,
+        /// Outbox channel naming the receiving system
+        channel: String,
+        /// Delivery payload
+        payload: preserves::IOValue,
+    },
+
+    /// Random values the turn drew from its actor's deterministic generator
+    RandomDrawn {
+        /// Hex seed of the turn's generator
+        seed: String,
+        /// Number of 64-bit values drawn
+        draws: u64,
     },
 }
 
@@ -448,8 +901,17 @@ pub struct TurnRecord {
     /// State delta (CRDT changes)
     pub delta: StateDelta,
 
-    /// Debug timestamp (not used for determinism)
+    /// Wall-clock time the turn was recorded (advisory; order turns by
+    /// [`TurnRecord::logical_timestamp`])
     pub timestamp: DateTime<Utc>,
+
+    /// Causal history across actors at the time of this turn
+    #[serde(default)]
+    pub vector_clock: VectorClock,
+
+    /// Control-plane client on whose behalf the turn ran, if it named one
+    #[serde(default)]
+    pub initiator: Option<Identity>,
 }
 
 /// Describes how the runtime should publish the result of a capability invocation.
@@ -482,7 +944,7 @@ impl TurnRecord {
         outputs: Vec<TurnOutput>,
         delta: StateDelta,
     ) -> Self {
-        let turn_id = compute_turn_id(&actor, &clock, &inputs);
+        let turn_id = compute_turn_id(0, &actor, &clock, &inputs);
         Self {
             turn_id,
             actor,
@@ -493,9 +955,128 @@ impl TurnRecord {
             outputs,
             delta,
             timestamp: Utc::now(),
+            vector_clock: VectorClock::new(),
+            initiator: None,
         }
     }
 
+    /// Assign the branch Lamport sequence for this record
+    ///
+    /// Records built with [`TurnRecord::new`] are unsequenced (sequence zero);
+    /// the runtime assigns the position on the branch before journaling.
+    pub fn with_sequence(mut self, seq: u64) -> Self {
+        self.turn_id = compute_turn_id(seq, &self.actor, &self.clock, &self.inputs);
+        self
+    }
+
+    /// Attach the vector clock stamped by the runtime
+    pub fn with_vector_clock(mut self, vector_clock: VectorClock) -> Self {
+        self.vector_clock = vector_clock;
+        self
+    }
+
+    /// Attribute the turn to the client identity that caused it
+    pub fn with_initiator(mut self, initiator: Option<Identity>) -> Self {
+        self.initiator = initiator;
+        self
+    }
+
+    /// Payload values carried by this turn
+    ///
+    /// Covers message, request, result and config payloads of the inputs and
+    /// outputs, backed-up file contents, staged outbox deliveries, and the
+    /// values the delta asserts; ids and structure are left out. Redaction
+    /// and blob spilling rewrite exactly these.
+    pub fn payloads_mut(&mut self) -> Vec<&mut preserves::IOValue> {
+        let mut payloads = Vec::new();
+        for input in &mut self.inputs {
+            match input {
+                TurnInput::ExternalMessage { payload, .. }
+                | TurnInput::CapabilityInvocation { payload, .. }
+                | TurnInput::RemoteMessage { payload, .. }
+                | TurnInput::Timer { payload, .. } => payloads.push(payload),
+                TurnInput::Assert { value, .. } | TurnInput::ObservedAssert { value, .. } => {
+                    payloads.push(value)
+                }
+                TurnInput::ExternalResponse { response, .. } => payloads.push(response),
+                _ => {}
+            }
+        }
+        for output in &mut self.outputs {
+            match output {
+                TurnOutput::Assert { value, .. } => payloads.push(value),
+                TurnOutput::Message { payload, .. }
+                | TurnOutput::CapabilityInvoke { payload, .. }
+                | TurnOutput::CapabilityInvokeByKind { payload, .. }
+                | TurnOutput::TimerRegistered { payload, .. } => payloads.push(payload),
+                TurnOutput::ExternalRequest { request, .. } => payloads.push(request),
+                TurnOutput::CapabilityResult { result, .. } => payloads.push(result),
+                TurnOutput::EntitySpawned { config, .. }
+                | TurnOutput::EntityAttached { config, .. } => payloads.push(config),
+                TurnOutput::FileBackup {
+                    previous: Some(previous),
+                    ..
+                } => payloads.push(previous),
+                TurnOutput::OutboxStaged { payload, .. } => payloads.push(payload),
+                _ => {}
+            }
+        }
+        for (_actor, _handle, value, _version) in &mut self.delta.assertions.added {
+            payloads.push(value);
+        }
+        payloads
+    }
+
+    /// Deterministic timestamp of this turn, stable across replays
+    pub fn logical_timestamp(&self) -> LogicalTimestamp {
+        LogicalTimestamp::of(&self.turn_id, self.clock, &self.branch)
+    }
+
+    /// Whether this turn and `other` executed concurrently
+    ///
+    /// Turns recorded without vector clocks are conservatively treated as
+    /// ordered.
+    pub fn is_concurrent_with(&self, other: &TurnRecord) -> bool {
+        if self.vector_clock.0.is_empty() || other.vector_clock.0.is_empty() {
+            return false;
+        }
+        self.vector_clock.concurrent_with(&other.vector_clock)
+    }
+
+    /// Whether this turn was delivered to `facet` or acted on it.
+    ///
+    /// Counts messages, syncs and timer wakeups addressed to the facet, and
+    /// outputs that spawn, terminate, sync, attach entities to or set timers
+    /// on it or are issued from it.
+    pub fn involves_facet(&self, facet: &FacetId) -> bool {
+        let targeted = self.inputs.iter().any(|input| match input {
+            TurnInput::ExternalMessage { facet: target, .. }
+            | TurnInput::Sync { facet: target, .. }
+            | TurnInput::Timer { facet: target, .. } => target == facet,
+            _ => false,
+        });
+        targeted
+            || self.outputs.iter().any(|output| match output {
+                TurnOutput::Synced { facet: target }
+                | TurnOutput::FacetTerminated { facet: target }
+                | TurnOutput::EntityAttached { facet: target, .. }
+                | TurnOutput::TimerRegistered { facet: target, .. }
+                | TurnOutput::CapabilityGranted {
+                    issuer_facet: target,
+                    ..
+                }
+                | TurnOutput::EntitySpawned {
+                    parent_facet: target,
+                    ..
+                } => target == facet,
+                TurnOutput::FacetSpawned {
+                    facet: target,
+                    parent,
+                } => target == facet || parent.as_ref() == Some(facet),
+                _ => false,
+            })
+    }
+
     /// Encode this turn record to bytes using preserves
     ///
     /// Format: [4-byte length prefix (little-endian)] + [preserves-packed data]
@@ -536,7 +1117,13 @@ impl TurnRecord {
 /// Compute a deterministic turn ID from inputs
 ///
 /// Uses Blake3 to hash the canonical representation of (actor, clock, inputs)
-pub fn compute_turn_id(actor: &ActorId, clock: &LogicalClock, inputs: &[TurnInput]) -> TurnId {
+/// and combines the digest with the branch sequence and actor.
+pub fn compute_turn_id(
+    seq: u64,
+    actor: &ActorId,
+    clock: &LogicalClock,
+    inputs: &[TurnInput],
+) -> TurnId {
     use preserves::PackedWriter;
 
     let mut hasher = Hasher::new();
@@ -557,7 +1144,8 @@ pub fn compute_turn_id(actor: &ActorId, clock: &LogicalClock, inputs: &[TurnInpu
     }
 
     let hash = hasher.finalize();
-    TurnId::new(format!("turn_{}", hash.to_hex()))
+    let hex = hash.to_hex();
+    TurnId::structured(seq, Some(actor), &hex.as_str()[..TURN_DIGEST_LEN])
 }
 
 #[cfg(test)]
@@ -572,10 +1160,12 @@ mod tests {
             actor: actor.clone(),
             facet: FacetId::new(),
             payload: preserves::IOValue::symbol("test-data"),
+            idempotency_key: None,
+            broadcast: None,
         }];
 
-        let id1 = compute_turn_id(&actor, &clock, &inputs);
-        let id2 = compute_turn_id(&actor, &clock, &inputs);
+        let id1 = compute_turn_id(1, &actor, &clock, &inputs);
+        let id2 = compute_turn_id(1, &actor, &clock, &inputs);
 
         assert_eq!(id1, id2, "Turn IDs must be deterministic");
     }
@@ -588,15 +1178,19 @@ mod tests {
             actor: actor.clone(),
             facet: FacetId::new(),
             payload: preserves::IOValue::symbol("test-data1"),
+            idempotency_key: None,
+            broadcast: None,
         }];
         let inputs2 = vec![TurnInput::ExternalMessage {
             actor: actor.clone(),
             facet: FacetId::new(),
             payload: preserves::IOValue::symbol("test-data2"),
+            idempotency_key: None,
+            broadcast: None,
         }];
 
-        let id1 = compute_turn_id(&actor, &clock, &inputs1);
-        let id2 = compute_turn_id(&actor, &clock, &inputs2);
+        let id1 = compute_turn_id(1, &actor, &clock, &inputs1);
+        let id2 = compute_turn_id(1, &actor, &clock, &inputs2);
 
         assert_ne!(id1, id2, "Different inputs must produce different turn IDs");
     }
@@ -620,4 +1214,112 @@ mod tests {
         assert_eq!(record.turn_id, decoded.turn_id);
         assert_eq!(record.clock, decoded.clock);
     }
+
+    #[test]
+    fn test_turn_id_orders_by_sequence() {
+        let actor = ActorId::new();
+        let clock = LogicalClock(1);
+        let early = compute_turn_id(9, &actor, &clock, &[]);
+        let late = compute_turn_id(10, &actor, &clock, &[]);
+
+        assert!(early < late, "sequence must dominate ordering");
+        assert!(TurnId::genesis() < early);
+        assert_eq!(late.sequence(), 10);
+        assert_eq!(late.actor(), Some(actor));
+    }
+
+    #[test]
+    fn test_turn_id_textual_roundtrip() {
+        let actor = ActorId::new();
+        let id = compute_turn_id(42, &actor, &LogicalClock(3), &[]);
+        let parsed: TurnId = id.as_str().parse().unwrap();
+        assert_eq!(parsed, id);
+        assert!(!parsed.is_legacy());
+
+        let genesis = TurnId::new(TurnId::genesis().to_string());
+        assert!(genesis.is_genesis());
+    }
+
+    #[test]
+    fn test_legacy_turn_ids_are_preserved() {
+        let legacy = TurnId::new("turn_abc123".to_string());
+        assert!(legacy.is_legacy());
+        assert_eq!(legacy.sequence(), 0);
+        assert_eq!(legacy.as_str(), "turn_abc123");
+
+        let structured = compute_turn_id(1, &ActorId::new(), &LogicalClock(1), &[]);
+        assert!(legacy < structured);
+
+        let json = serde_json::to_string(&legacy).unwrap();
+        assert_eq!(json, "\"turn_abc123\"");
+        let decoded: TurnId = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, legacy);
+    }
+
+    #[test]
+    fn test_vector_clock_ordering() {
+        let a = ActorId::new();
+        let b = ActorId::new();
+
+        let mut first = VectorClock::new();
+        first.increment(&a);
+
+        let mut after = first.clone();
+        after.increment(&b);
+        assert!(first.happened_before(&after));
+        assert!(!after.happened_before(&first));
+
+        let mut concurrent = VectorClock::new();
+        concurrent.increment(&b);
+        assert!(first.concurrent_with(&concurrent));
+
+        let joined = first.join(&concurrent);
+        assert_eq!(joined.get(&a), 1);
+        assert_eq!(joined.get(&b), 1);
+        assert!(first.happened_before(&joined));
+        assert!(concurrent.happened_before(&joined));
+    }
+
+    #[test]
+    fn test_turn_record_vector_clock_roundtrip() {
+        let actor = ActorId::new();
+        let mut clock = VectorClock::new();
+        clock.increment(&actor);
+
+        let record = TurnRecord::new(
+            actor,
+            BranchId::main(),
+            LogicalClock(1),
+            None,
+            vec![],
+            vec![],
+            StateDelta::empty(),
+        )
+        .with_vector_clock(clock.clone());
+        let decoded = TurnRecord::decode(&record.encode().unwrap()).unwrap();
+        assert_eq!(decoded.vector_clock, clock);
+    }
+
+    #[test]
+    fn test_logical_timestamp_ignores_wall_clock() {
+        let actor = ActorId::new();
+        let record = |seq| {
+            TurnRecord::new(
+                actor.clone(),
+                BranchId::main(),
+                LogicalClock(3),
+                None,
+                vec![],
+                vec![],
+                StateDelta::empty(),
+            )
+            .with_sequence(seq)
+        };
+
+        let mut replayed = record(1);
+        replayed.timestamp = Utc::now() + chrono::Duration::hours(1);
+        assert_eq!(record(1).logical_timestamp(), replayed.logical_timestamp());
+        assert!(record(1).logical_timestamp() < record(2).logical_timestamp());
+        assert_eq!(record(2).logical_timestamp().to_string(), "2.3@main");
+    }
 }
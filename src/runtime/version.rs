@@ -0,0 +1,96 @@
+//! Version provenance for journals and snapshots
+//!
+//! Replay restores state recorded by whatever runtime and entity code wrote
+//! it. After an upgrade that code may have changed, and a replay can then
+//! diverge without any visible error. Every journal segment therefore carries
+//! a small header naming the runtime version and entity-registry fingerprint
+//! that wrote it (a new segment is started when either changes), and every
+//! snapshot records the same [`VersionStamp`]. Replaying across a version
+//! change is then handled according to [`VersionPolicy`].
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::warn;
+
+use super::error::{Result, RuntimeError};
+use super::registry::EntityRegistry;
+
+/// Version of this crate, recorded as the runtime version.
+pub const RUNTIME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Runtime version and entity-registry fingerprint that produced some data.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct VersionStamp {
+    /// Crate version of the runtime
+    pub runtime: String,
+    /// Fingerprint of the registered entity types
+    pub registry: String,
+}
+
+impl VersionStamp {
+    /// Stamp for this runtime running with `registry`.
+    pub fn current(registry: &EntityRegistry) -> Self {
+        Self {
+            runtime: RUNTIME_VERSION.to_string(),
+            registry: registry.fingerprint(),
+        }
+    }
+}
+
+impl fmt::Display for VersionStamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "duet {} (registry {})", self.runtime, self.registry)
+    }
+}
+
+/// Sidecar header describing who wrote a journal segment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentHeader {
+    /// Version that wrote the segment
+    pub version: VersionStamp,
+    /// When the segment was started
+    pub created_at: DateTime<Utc>,
+}
+
+/// What to do when replaying data written by a different version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionPolicy {
+    /// Replay silently
+    Ignore,
+    /// Replay, logging a warning
+    #[default]
+    Warn,
+    /// Fail the replay with [`RuntimeError::VersionMismatch`]
+    Refuse,
+}
+
+impl VersionPolicy {
+    /// Apply the policy to data from `source` stamped `recorded`.
+    pub fn check(
+        self,
+        source: &str,
+        recorded: &VersionStamp,
+        current: &VersionStamp,
+    ) -> Result<()> {
+        if recorded == current {
+            return Ok(());
+        }
+        match self {
+            VersionPolicy::Ignore => Ok(()),
+            VersionPolicy::Warn => {
+                warn!(
+                    "replaying {} written by {} with {}",
+                    source, recorded, current
+                );
+                Ok(())
+            }
+            VersionPolicy::Refuse => Err(RuntimeError::VersionMismatch {
+                origin: source.to_string(),
+                recorded: recorded.to_string(),
+                current: current.to_string(),
+            }),
+        }
+    }
+}
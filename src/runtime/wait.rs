@@ -0,0 +1,238 @@
+//! Long-poll registrations for clients waiting on new turns
+//!
+//! A client tailing a branch registers once and receives a [`WaitId`]. Every
+//! committed turn marks the registrations on its branch ready, so the turn is
+//! queued for the client even while it is not waiting: it can poll with a
+//! short (or zero) timeout, disconnect, reconnect and poll again with the same
+//! id without missing the advance. All waiters share one condition variable,
+//! so no thread is held on behalf of an idle registration. Registrations that
+//! are not polled for [`REGISTRATION_TTL`] are dropped.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::error::{Result, RuntimeError};
+use super::turn::{BranchId, TurnId};
+
+/// Identifier handed to clients for polling a registration.
+pub type WaitId = Uuid;
+
+/// How long a registration survives without being polled.
+pub const REGISTRATION_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// A client's interest in a branch advancing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WaitRegistration {
+    /// Identifier used to poll or cancel
+    pub id: WaitId,
+    /// Branch being watched
+    pub branch: BranchId,
+    /// Last head the client has seen (`None` = any head is new)
+    pub since: Option<TurnId>,
+    /// Head recorded since `since`, waiting to be collected
+    pub ready: Option<TurnId>,
+}
+
+struct Entry {
+    registration: WaitRegistration,
+    last_polled: Instant,
+}
+
+#[derive(Default)]
+struct State {
+    heads: HashMap<BranchId, TurnId>,
+    registrations: HashMap<WaitId, Entry>,
+}
+
+/// Branch heads and the registrations waiting on them.
+pub struct TurnWaiters {
+    state: Mutex<State>,
+    advanced: Condvar,
+    ttl: Duration,
+}
+
+impl Default for TurnWaiters {
+    fn default() -> Self {
+        Self::new(REGISTRATION_TTL)
+    }
+}
+
+impl TurnWaiters {
+    /// Create an empty table expiring registrations idle for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            advanced: Condvar::new(),
+            ttl,
+        }
+    }
+
+    /// Record that `branch` now ends at `head`, waking any waiters.
+    pub fn record_head(&self, branch: BranchId, head: TurnId) {
+        let mut state = self.state.lock().unwrap();
+        for entry in state.registrations.values_mut() {
+            let registration = &mut entry.registration;
+            if registration.branch == branch && registration.since.as_ref() != Some(&head) {
+                registration.ready = Some(head.clone());
+            }
+        }
+        state.heads.insert(branch, head);
+        self.advanced.notify_all();
+    }
+
+    /// Register interest in `branch` moving past `since`.
+    ///
+    /// `current` is the branch head as known to the caller, used when no
+    /// head has been recorded for the branch yet.
+    pub fn register(
+        &self,
+        branch: BranchId,
+        since: Option<TurnId>,
+        current: Option<&TurnId>,
+    ) -> WaitRegistration {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let ttl = self.ttl;
+        state
+            .registrations
+            .retain(|_, entry| now.duration_since(entry.last_polled) < ttl);
+
+        let head = state.heads.get(&branch).or(current).cloned();
+        let ready = head.filter(|head| since.as_ref() != Some(head));
+        let registration = WaitRegistration {
+            id: Uuid::new_v4(),
+            branch,
+            since,
+            ready,
+        };
+        state.registrations.insert(
+            registration.id,
+            Entry {
+                registration: registration.clone(),
+                last_polled: now,
+            },
+        );
+        registration
+    }
+
+    /// Collect the head queued for `id`, waiting up to `timeout` for one.
+    ///
+    /// A collected head becomes the registration's new `since`, so the next
+    /// poll waits for the turn after it.
+    pub fn poll(&self, id: WaitId, timeout: Duration) -> Result<Option<TurnId>> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let entry = state
+                .registrations
+                .get_mut(&id)
+                .ok_or(RuntimeError::WaitNotFound(id))?;
+            entry.last_polled = Instant::now();
+            if let Some(head) = entry.registration.ready.take() {
+                entry.registration.since = Some(head.clone());
+                return Ok(Some(head));
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            state = self.advanced.wait_timeout(state, remaining).unwrap().0;
+        }
+    }
+
+    /// Drop a registration, returning whether it existed.
+    pub fn cancel(&self, id: WaitId) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .registrations
+            .remove(&id)
+            .is_some()
+    }
+
+    /// Wait up to `timeout` for `branch` to move past `since` without keeping
+    /// a registration.
+    pub fn wait_once(
+        &self,
+        branch: &BranchId,
+        since: Option<&TurnId>,
+        current: Option<&TurnId>,
+        timeout: Duration,
+    ) -> Option<TurnId> {
+        let registration = self.register(branch.clone(), since.cloned(), current);
+        let head = self.poll(registration.id, timeout).ok().flatten();
+        self.cancel(registration.id);
+        head
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registrations_queue_heads_between_polls() {
+        let waiters = TurnWaiters::default();
+        let branch = BranchId::main();
+        let first = TurnId::new("turn-1".to_string());
+        let second = TurnId::new("turn-2".to_string());
+
+        let registration = waiters.register(branch.clone(), Some(first.clone()), Some(&first));
+        assert_eq!(registration.ready, None);
+        assert_eq!(waiters.poll(registration.id, Duration::ZERO).unwrap(), None);
+
+        // The head is queued while nobody is polling
+        waiters.record_head(branch.clone(), second.clone());
+        waiters.record_head(BranchId::new("other"), first.clone());
+        assert_eq!(
+            waiters.poll(registration.id, Duration::ZERO).unwrap(),
+            Some(second.clone())
+        );
+        assert_eq!(
+            waiters
+                .poll(registration.id, Duration::from_millis(5))
+                .unwrap(),
+            None
+        );
+
+        assert_eq!(
+            waiters.wait_once(&branch, Some(&first), None, Duration::ZERO),
+            Some(second)
+        );
+
+        assert!(waiters.cancel(registration.id));
+        assert!(matches!(
+            waiters.poll(registration.id, Duration::ZERO),
+            Err(RuntimeError::WaitNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn poll_wakes_when_the_branch_advances() {
+        let waiters = std::sync::Arc::new(TurnWaiters::default());
+        let registration = waiters.register(BranchId::main(), None, None);
+
+        let writer = waiters.clone();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            writer.record_head(BranchId::main(), TurnId::new("turn-1".to_string()));
+        });
+        let head = waiters
+            .poll(registration.id, Duration::from_secs(10))
+            .unwrap();
+        handle.join().unwrap();
+        assert_eq!(head, Some(TurnId::new("turn-1".to_string())));
+    }
+
+    #[test]
+    fn idle_registrations_expire() {
+        let waiters = TurnWaiters::new(Duration::ZERO);
+        let stale = waiters.register(BranchId::main(), None, None);
+        waiters.register(BranchId::main(), None, None);
+        assert!(waiters.poll(stale.id, Duration::ZERO).is_err());
+    }
+}
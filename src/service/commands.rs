@@ -0,0 +1,252 @@
+//! Control-plane commands contributed by embedding applications
+//!
+//! Daemons built on this crate extend the NDJSON protocol by registering
+//! [`ServiceCommand`]s with [`Service::register_command`](super::Service::register_command)
+//! before serving connections. A command declares the parameters it takes;
+//! requests are checked against them before the handler runs against the
+//! tenant's [`Control`], so handlers can read their parameters without
+//! re-validating them. Registered commands are listed, with their parameter
+//! schemas, in the handshake response.
+
+use crate::runtime::control::Control;
+use crate::runtime::error::RuntimeError;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// A command added to the control-plane protocol.
+pub trait ServiceCommand: Send + Sync {
+    /// Parameters the command accepts.
+    fn params(&self) -> Vec<ParamSpec> {
+        Vec::new()
+    }
+
+    /// One-line summary listed in the handshake.
+    fn description(&self) -> String {
+        String::new()
+    }
+
+    /// Run the command against the connection's runtime.
+    fn execute(&self, control: &mut Control, params: &Value) -> Result<Value, CommandError>;
+}
+
+/// JSON type of a command parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamKind {
+    /// JSON string
+    String,
+    /// Whole JSON number
+    Integer,
+    /// Any JSON number
+    Number,
+    /// `true` or `false`
+    Boolean,
+    /// JSON array
+    Array,
+    /// JSON object
+    Object,
+    /// Any JSON value
+    Any,
+}
+
+impl ParamKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ParamKind::String => "string",
+            ParamKind::Integer => "integer",
+            ParamKind::Number => "number",
+            ParamKind::Boolean => "boolean",
+            ParamKind::Array => "array",
+            ParamKind::Object => "object",
+            ParamKind::Any => "any",
+        }
+    }
+
+    fn accepts(self, value: &Value) -> bool {
+        match self {
+            ParamKind::String => value.is_string(),
+            ParamKind::Integer => value.is_i64() || value.is_u64(),
+            ParamKind::Number => value.is_number(),
+            ParamKind::Boolean => value.is_boolean(),
+            ParamKind::Array => value.is_array(),
+            ParamKind::Object => value.is_object(),
+            ParamKind::Any => true,
+        }
+    }
+}
+
+/// Declared parameter of a [`ServiceCommand`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParamSpec {
+    /// Field of the request's params object
+    pub name: String,
+    /// JSON type the value must have
+    pub kind: ParamKind,
+    /// Whether requests must supply a (non-null) value
+    pub required: bool,
+    /// What the parameter means, for clients
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+impl ParamSpec {
+    /// Parameter `name` that requests must supply.
+    pub fn required(name: impl Into<String>, kind: ParamKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            required: true,
+            description: String::new(),
+        }
+    }
+
+    /// Parameter `name` that requests may omit (or pass as null).
+    pub fn optional(name: impl Into<String>, kind: ParamKind) -> Self {
+        Self {
+            required: false,
+            ..Self::required(name, kind)
+        }
+    }
+
+    /// Attach a description shown to clients.
+    pub fn describe(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+}
+
+/// Failure of a registered command, reported to the client.
+#[derive(Debug, Error)]
+pub enum CommandError {
+    /// The request's parameters were rejected (`invalid_params`)
+    #[error("{0}")]
+    InvalidParams(String),
+
+    /// The runtime rejected an operation (reported like built-in runtime errors)
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+
+    /// The command failed for a domain-specific reason (`command_failed`)
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// [`ServiceCommand`] backed by a closure.
+struct FnCommand<F> {
+    params: Vec<ParamSpec>,
+    handler: F,
+}
+
+impl<F> ServiceCommand for FnCommand<F>
+where
+    F: Fn(&mut Control, &Value) -> Result<Value, CommandError> + Send + Sync,
+{
+    fn params(&self) -> Vec<ParamSpec> {
+        self.params.clone()
+    }
+
+    fn execute(&self, control: &mut Control, params: &Value) -> Result<Value, CommandError> {
+        (self.handler)(control, params)
+    }
+}
+
+/// Command taking `params` and running `handler`.
+pub fn command_fn<F>(params: Vec<ParamSpec>, handler: F) -> impl ServiceCommand
+where
+    F: Fn(&mut Control, &Value) -> Result<Value, CommandError> + Send + Sync,
+{
+    FnCommand { params, handler }
+}
+
+/// Commands registered with a service, by name.
+#[derive(Clone, Default)]
+pub(super) struct CommandRegistry {
+    commands: BTreeMap<String, Arc<dyn ServiceCommand>>,
+}
+
+impl CommandRegistry {
+    pub(super) fn insert(&mut self, name: String, command: Arc<dyn ServiceCommand>) -> bool {
+        if self.commands.contains_key(&name) {
+            return false;
+        }
+        self.commands.insert(name, command);
+        true
+    }
+
+    pub(super) fn get(&self, name: &str) -> Option<Arc<dyn ServiceCommand>> {
+        self.commands.get(name).cloned()
+    }
+
+    /// Names and schemas of the registered commands, for the handshake.
+    pub(super) fn describe(&self) -> Value {
+        Value::Array(
+            self.commands
+                .iter()
+                .map(|(name, command)| {
+                    json!({
+                        "name": name,
+                        "description": command.description(),
+                        "params": command.params(),
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Check `params` against the declared parameters of a command.
+pub(super) fn validate_params(specs: &[ParamSpec], params: &Value) -> Result<(), CommandError> {
+    let fields = match params {
+        Value::Object(fields) => Some(fields),
+        Value::Null => None,
+        _ => {
+            return Err(CommandError::InvalidParams(
+                "params must be an object".to_string(),
+            ));
+        }
+    };
+    for spec in specs {
+        match fields.and_then(|fields| fields.get(&spec.name)) {
+            None | Some(Value::Null) if spec.required => {
+                return Err(CommandError::InvalidParams(format!(
+                    "missing or invalid parameter: {}",
+                    spec.name
+                )));
+            }
+            None | Some(Value::Null) => {}
+            Some(value) if !spec.kind.accepts(value) => {
+                return Err(CommandError::InvalidParams(format!(
+                    "parameter {} must be of type {}",
+                    spec.name,
+                    spec.kind.as_str()
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_are_checked_against_their_specs() {
+        let specs = vec![
+            ParamSpec::required("name", ParamKind::String),
+            ParamSpec::optional("limit", ParamKind::Integer),
+        ];
+        assert!(validate_params(&specs, &json!({"name": "x"})).is_ok());
+        assert!(validate_params(&specs, &json!({"name": "x", "limit": 3})).is_ok());
+        assert!(validate_params(&specs, &json!({"name": "x", "limit": null})).is_ok());
+        assert!(validate_params(&specs, &json!({})).is_err());
+        assert!(validate_params(&specs, &Value::Null).is_err());
+        assert!(validate_params(&specs, &json!({"name": 1})).is_err());
+        assert!(validate_params(&specs, &json!({"name": "x", "limit": 1.5})).is_err());
+        assert!(validate_params(&[], &json!([1])).is_err());
+    }
+}
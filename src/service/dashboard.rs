@@ -0,0 +1,274 @@
+//! Embedded web dashboard (feature `dashboard`).
+//!
+//! Serves a single built-in HTML page plus a small JSON API over plain HTTP.
+//! The API is a thin veneer over the NDJSON [`Service`]: `POST /api/<command>`
+//! with a JSON params body returns the same response envelope the control
+//! plane would write, so the dashboard always sees exactly what CLI clients
+//! see. Only the read-only commands the page needs are exposed.
+//!
+//! Each dashboard draws a random token at launch. The page is served only to
+//! URLs carrying it (`/?token=...`, see [`Dashboard::url`]) and embeds it for
+//! its API calls, which must send it in an `X-Duet-Token` header with an
+//! `application/json` body. Other web pages the user visits can neither
+//! learn the token nor send the header without a CORS preflight, which the
+//! dashboard never grants. Connections are handled on their own threads and
+//! share one [`Service`].
+
+use super::Service;
+use crate::runtime::control::Control;
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+
+/// Built-in dashboard page: branches, history timeline, dataspace browser, transcripts.
+const INDEX_HTML: &str = include_str!("assets/dashboard.html");
+
+/// Placeholder in [`INDEX_HTML`] replaced by the launch token.
+const TOKEN_PLACEHOLDER: &str = "{{DUET_TOKEN}}";
+
+/// Header API calls carry the launch token in.
+const TOKEN_HEADER: &str = "x-duet-token";
+
+/// Service commands reachable through the API.
+const ALLOWED_COMMANDS: &[&str] = &[
+    "status",
+    "list_branches",
+    "branch_head",
+    "history",
+    "dataspace_assertions",
+    "transcript_show",
+];
+
+/// Upper bound on accepted request bodies.
+const MAX_BODY_BYTES: usize = 1 << 20;
+
+/// HTTP front end serving the dashboard and its JSON API.
+#[derive(Clone)]
+pub struct Dashboard {
+    service: Arc<Mutex<Service>>,
+    token: Arc<str>,
+}
+
+impl Dashboard {
+    /// Wrap a control interface for serving over HTTP, with a fresh token.
+    pub fn new(control: Control) -> Self {
+        Self {
+            service: Arc::new(Mutex::new(Service::new(control))),
+            token: uuid::Uuid::new_v4().simple().to_string().into(),
+        }
+    }
+
+    /// Token requests must carry.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Address to open the dashboard served at `addr` in a browser.
+    pub fn url(&self, addr: SocketAddr) -> String {
+        format!("http://{}/?token={}", addr, self.token)
+    }
+
+    /// Accept connections on `listener` until it fails, each on its own thread.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        for incoming in listener.incoming() {
+            match incoming {
+                Ok(stream) => {
+                    let peer = stream.peer_addr().ok();
+                    let dashboard = self.clone();
+                    std::thread::spawn(move || {
+                        if let Err(err) = dashboard.handle_connection(stream) {
+                            tracing::warn!("dashboard connection error from {:?}: {}", peer, err);
+                        }
+                    });
+                }
+                Err(err) => {
+                    tracing::warn!("dashboard failed to accept connection: {}", err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serve a single HTTP request read from `stream`, then close it.
+    pub fn handle_connection<S: Read + Write>(&self, stream: S) -> io::Result<()> {
+        let mut reader = BufReader::new(stream);
+        let response = match read_request(&mut reader)? {
+            Some(request) => self.route(&request),
+            None => Response::text(400, "Bad Request", "malformed request"),
+        };
+        response.write_to(reader.get_mut())
+    }
+
+    fn route(&self, request: &Request) -> Response {
+        let (path, query) = request
+            .path
+            .split_once('?')
+            .unwrap_or((request.path.as_str(), ""));
+        match (request.method.as_str(), path) {
+            ("GET", "/") | ("GET", "/index.html") => {
+                let token = query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("token="));
+                if token != Some(&*self.token) {
+                    return Response::text(403, "Forbidden", "open the URL codebased printed");
+                }
+                Response {
+                    status: 200,
+                    reason: "OK",
+                    content_type: "text/html; charset=utf-8",
+                    body: INDEX_HTML
+                        .replace(TOKEN_PLACEHOLDER, &self.token)
+                        .into_bytes(),
+                }
+            }
+            ("POST", path) if path.starts_with("/api/") => {
+                let command = &path["/api/".len()..];
+                if request.header(TOKEN_HEADER) != Some(&*self.token) {
+                    return Response::text(403, "Forbidden", "missing or wrong dashboard token");
+                }
+                let json_body = request.header("content-type").is_some_and(|value| {
+                    value
+                        .split(';')
+                        .next()
+                        .is_some_and(|media| media.trim().eq_ignore_ascii_case("application/json"))
+                });
+                if !json_body {
+                    return Response::text(
+                        415,
+                        "Unsupported Media Type",
+                        "API calls take an application/json body",
+                    );
+                }
+                if !ALLOWED_COMMANDS.contains(&command) {
+                    return Response::text(
+                        403,
+                        "Forbidden",
+                        "command not available to the dashboard",
+                    );
+                }
+                let params = if request.body.iter().all(u8::is_ascii_whitespace) {
+                    json!({})
+                } else {
+                    match serde_json::from_slice::<Value>(&request.body) {
+                        Ok(params) => params,
+                        Err(err) => {
+                            return Response::text(400, "Bad Request", &err.to_string());
+                        }
+                    }
+                };
+                Response::json(&self.service.lock().call(command, &params))
+            }
+            (_, path) if path.starts_with("/api/") => {
+                Response::text(405, "Method Not Allowed", "use POST for API calls")
+            }
+            _ => Response::text(404, "Not Found", "not found"),
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Value of header `name` (lowercase), if sent
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parse an HTTP/1.x request; `None` if the request line or headers are malformed.
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let method = method.to_string();
+    let path = path.to_string();
+
+    let mut content_length = 0usize;
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let name = name.trim().to_ascii_lowercase();
+        if name == "content-length" {
+            match value.trim().parse::<usize>() {
+                Ok(length) if length <= MAX_BODY_BYTES => content_length = length,
+                _ => return Ok(None),
+            }
+        }
+        headers.push((name, value.trim().to_string()));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(Request {
+        method,
+        path,
+        headers,
+        body,
+    }))
+}
+
+struct Response {
+    status: u16,
+    reason: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn text(status: u16, reason: &'static str, body: &str) -> Self {
+        Self {
+            status,
+            reason,
+            content_type: "text/plain; charset=utf-8",
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn json(value: &Value) -> Self {
+        Self {
+            status: 200,
+            reason: "OK",
+            content_type: "application/json",
+            body: serde_json::to_vec(value).unwrap_or_default(),
+        }
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            self.reason,
+            self.content_type,
+            self.body.len()
+        )?;
+        writer.write_all(&self.body)?;
+        writer.flush()
+    }
+}
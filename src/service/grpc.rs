@@ -0,0 +1,542 @@
+//! gRPC control-plane transport (feature `grpc`).
+//!
+//! Serves `proto/control.proto` next to the NDJSON transport. `Call` runs any
+//! control-plane command with the same JSON params and results the NDJSON
+//! protocol uses, so the two never drift apart; `TailEvents` streams
+//! `dataspace_events` batches and waits for the next committed turn once it
+//! has caught up, so clients do not need to long-poll.
+//!
+//! The [`Service`] lives on a dedicated worker thread and handles one command
+//! at a time, exactly as it does for NDJSON connections; gRPC handlers queue
+//! commands for it. A turn sink wakes event tails whenever a turn commits.
+//!
+//! Clients name their identity in the `duet-identity` request metadata (with
+//! its secret in `duet-identity-token`), as the NDJSON handshake would; the
+//! runtime's identity requirements apply to every call.
+
+use super::Service;
+use crate::runtime::control::Control;
+use crate::runtime::identity::IdentityClaim;
+use crate::runtime::sink::{SinkOptions, TurnSink};
+use crate::runtime::turn::TurnRecord;
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::{Arc, mpsc};
+use tokio::net::TcpListener;
+use tokio::sync::{oneshot, watch};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+
+/// Events fetched per `dataspace_events` call while a tail catches up
+const TAIL_PAGE: u64 = 100;
+
+/// Messages and service scaffolding for `duet.control.v1`.
+///
+/// Kept in step with `proto/control.proto` by hand so building the crate does
+/// not need `protoc`.
+pub mod proto {
+    /// Run one control-plane command.
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct CommandRequest {
+        /// Command name, as in the NDJSON protocol
+        #[prost(string, tag = "1")]
+        pub command: String,
+        /// JSON object of command params (empty means `{}`)
+        #[prost(string, tag = "2")]
+        pub params_json: String,
+    }
+
+    /// Outcome of a command.
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct CommandResponse {
+        /// JSON result when the command succeeded
+        #[prost(string, tag = "1")]
+        pub result_json: String,
+        /// Set when the command failed
+        #[prost(message, optional, tag = "2")]
+        pub error: Option<CommandError>,
+    }
+
+    /// Structured command failure.
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct CommandError {
+        /// Error code (`invalid_params`, `runtime_error`, ...)
+        #[prost(string, tag = "1")]
+        pub code: String,
+        /// Human-readable message
+        #[prost(string, tag = "2")]
+        pub message: String,
+        /// JSON object with structured details, when the error has any
+        #[prost(string, tag = "3")]
+        pub details_json: String,
+    }
+
+    /// Which events to stream.
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct TailRequest {
+        /// Branch to follow (default `main`)
+        #[prost(string, tag = "1")]
+        pub branch: String,
+        /// Resume after this turn (default: the start of the branch)
+        #[prost(string, tag = "2")]
+        pub since: String,
+        /// Only events from this actor (UUID)
+        #[prost(string, tag = "3")]
+        pub actor: String,
+        /// Only assertions with this record label
+        #[prost(string, tag = "4")]
+        pub label: String,
+        /// Only agent transcript events for this request id
+        #[prost(string, tag = "5")]
+        pub request_id: String,
+    }
+
+    /// Events produced by one turn.
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct EventBatch {
+        /// Turn that produced the events
+        #[prost(string, tag = "1")]
+        pub turn: String,
+        /// Actor that ran the turn
+        #[prost(string, tag = "2")]
+        pub actor: String,
+        /// The batch exactly as `dataspace_events` returns it, as JSON
+        #[prost(string, tag = "3")]
+        pub batch_json: String,
+    }
+
+    /// Server side of `duet.control.v1.ControlPlane`.
+    pub mod control_plane_server {
+        use tonic::codegen::*;
+
+        /// Fully qualified service name
+        pub const SERVICE_NAME: &str = "duet.control.v1.ControlPlane";
+
+        /// Handlers for the `ControlPlane` service.
+        #[async_trait]
+        pub trait ControlPlane: Send + Sync + 'static {
+            /// Stream type returned by [`ControlPlane::tail_events`]
+            type TailEventsStream: tokio_stream::Stream<Item = Result<super::EventBatch, tonic::Status>>
+                + Send
+                + 'static;
+
+            /// Run one control-plane command.
+            async fn call(
+                &self,
+                request: tonic::Request<super::CommandRequest>,
+            ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+
+            /// Stream assertion event batches as turns commit.
+            async fn tail_events(
+                &self,
+                request: tonic::Request<super::TailRequest>,
+            ) -> Result<tonic::Response<Self::TailEventsStream>, tonic::Status>;
+        }
+
+        /// Routes HTTP/2 requests to a [`ControlPlane`] implementation.
+        #[derive(Debug)]
+        pub struct ControlPlaneServer<T> {
+            inner: Arc<T>,
+        }
+
+        impl<T> ControlPlaneServer<T> {
+            /// Serve `inner`.
+            pub fn new(inner: T) -> Self {
+                Self {
+                    inner: Arc::new(inner),
+                }
+            }
+        }
+
+        impl<T> Clone for ControlPlaneServer<T> {
+            fn clone(&self) -> Self {
+                Self {
+                    inner: self.inner.clone(),
+                }
+            }
+        }
+
+        impl<T> tonic::server::NamedService for ControlPlaneServer<T> {
+            const NAME: &'static str = SERVICE_NAME;
+        }
+
+        struct CallSvc<T>(Arc<T>);
+
+        impl<T: ControlPlane> tonic::server::UnaryService<super::CommandRequest> for CallSvc<T> {
+            type Response = super::CommandResponse;
+            type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+
+            fn call(&mut self, request: tonic::Request<super::CommandRequest>) -> Self::Future {
+                let inner = Arc::clone(&self.0);
+                Box::pin(async move { inner.call(request).await })
+            }
+        }
+
+        struct TailEventsSvc<T>(Arc<T>);
+
+        impl<T: ControlPlane> tonic::server::ServerStreamingService<super::TailRequest>
+            for TailEventsSvc<T>
+        {
+            type Response = super::EventBatch;
+            type ResponseStream = T::TailEventsStream;
+            type Future = BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+
+            fn call(&mut self, request: tonic::Request<super::TailRequest>) -> Self::Future {
+                let inner = Arc::clone(&self.0);
+                Box::pin(async move { inner.tail_events(request).await })
+            }
+        }
+
+        impl<T, B> Service<http::Request<B>> for ControlPlaneServer<T>
+        where
+            T: ControlPlane,
+            B: Body + Send + 'static,
+            B::Error: Into<StdError> + Send + 'static,
+        {
+            type Response = http::Response<tonic::body::BoxBody>;
+            type Error = std::convert::Infallible;
+            type Future = BoxFuture<Self::Response, Self::Error>;
+
+            fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, request: http::Request<B>) -> Self::Future {
+                let inner = self.inner.clone();
+                match request.uri().path() {
+                    "/duet.control.v1.ControlPlane/Call" => Box::pin(async move {
+                        let mut grpc =
+                            tonic::server::Grpc::new(tonic::codec::ProstCodec::default());
+                        Ok(grpc.unary(CallSvc(inner), request).await)
+                    }),
+                    "/duet.control.v1.ControlPlane/TailEvents" => Box::pin(async move {
+                        let mut grpc =
+                            tonic::server::Grpc::new(tonic::codec::ProstCodec::default());
+                        Ok(grpc.server_streaming(TailEventsSvc(inner), request).await)
+                    }),
+                    _ => Box::pin(async move {
+                        let mut response = http::Response::new(empty_body());
+                        let headers = response.headers_mut();
+                        headers.insert(
+                            tonic::Status::GRPC_STATUS,
+                            (tonic::Code::Unimplemented as i32).into(),
+                        );
+                        headers.insert(
+                            http::header::CONTENT_TYPE,
+                            tonic::metadata::GRPC_CONTENT_TYPE,
+                        );
+                        Ok(response)
+                    }),
+                }
+            }
+        }
+    }
+
+    /// Client side of `duet.control.v1.ControlPlane`.
+    pub mod control_plane_client {
+        use tonic::codegen::http::uri::PathAndQuery;
+
+        /// Typed client over a tonic channel.
+        #[derive(Debug, Clone)]
+        pub struct ControlPlaneClient {
+            inner: tonic::client::Grpc<tonic::transport::Channel>,
+        }
+
+        impl ControlPlaneClient {
+            /// Connect to a server at `endpoint` (e.g. `http://127.0.0.1:7070`).
+            pub async fn connect(endpoint: String) -> Result<Self, tonic::transport::Error> {
+                let channel = tonic::transport::Endpoint::new(endpoint)?.connect().await?;
+                Ok(Self {
+                    inner: tonic::client::Grpc::new(channel),
+                })
+            }
+
+            /// Run one control-plane command.
+            pub async fn call(
+                &mut self,
+                request: super::CommandRequest,
+            ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+                self.ready().await?;
+                self.inner
+                    .unary(
+                        tonic::Request::new(request),
+                        PathAndQuery::from_static("/duet.control.v1.ControlPlane/Call"),
+                        tonic::codec::ProstCodec::default(),
+                    )
+                    .await
+            }
+
+            /// Stream assertion event batches as turns commit.
+            pub async fn tail_events(
+                &mut self,
+                request: super::TailRequest,
+            ) -> Result<tonic::Response<tonic::Streaming<super::EventBatch>>, tonic::Status>
+            {
+                self.ready().await?;
+                self.inner
+                    .server_streaming(
+                        tonic::Request::new(request),
+                        PathAndQuery::from_static("/duet.control.v1.ControlPlane/TailEvents"),
+                        tonic::codec::ProstCodec::default(),
+                    )
+                    .await
+            }
+
+            async fn ready(&mut self) -> Result<(), tonic::Status> {
+                self.inner
+                    .ready()
+                    .await
+                    .map_err(|e| tonic::Status::unavailable(format!("service not ready: {e}")))
+            }
+        }
+    }
+}
+
+use proto::control_plane_server::{ControlPlane, ControlPlaneServer};
+
+/// A command queued for the service worker thread
+struct Job {
+    command: String,
+    params: Value,
+    identity: Option<IdentityClaim>,
+    reply: oneshot::Sender<Value>,
+}
+
+/// Wakes event tails when a turn commits
+struct CommitSignal(watch::Sender<u64>);
+
+impl TurnSink for CommitSignal {
+    fn accept(&self, _record: &TurnRecord) -> Result<(), String> {
+        self.0.send_modify(|commits| *commits += 1);
+        Ok(())
+    }
+}
+
+/// [`ControlPlane`] implementation backed by a [`Service`] worker thread.
+#[derive(Clone)]
+pub struct GrpcControlPlane {
+    jobs: mpsc::Sender<Job>,
+    commits: watch::Receiver<u64>,
+}
+
+impl GrpcControlPlane {
+    /// Move `control` onto a worker thread and accept commands for it.
+    ///
+    /// The worker exits once every handle to the control plane is dropped.
+    pub fn new(mut control: Control) -> Self {
+        let (signal, commits) = watch::channel(0);
+        control.add_turn_sink(
+            "grpc-event-tails",
+            Arc::new(CommitSignal(signal)),
+            SinkOptions::default(),
+        );
+
+        let (jobs, queue) = mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("duet-grpc-service".into())
+            .spawn(move || {
+                let mut service = Service::new(control);
+                for job in queue {
+                    let _ = job.reply.send(service.call_as(
+                        &job.command,
+                        &job.params,
+                        job.identity.as_ref(),
+                    ));
+                }
+            })
+            .expect("failed to spawn gRPC service thread");
+
+        Self { jobs, commits }
+    }
+
+    /// Run `command` on the worker as `identity` and return its response envelope.
+    async fn run(
+        &self,
+        command: &str,
+        params: Value,
+        identity: Option<IdentityClaim>,
+    ) -> Result<Value, Status> {
+        let (reply, response) = oneshot::channel();
+        self.jobs
+            .send(Job {
+                command: command.to_string(),
+                params,
+                identity,
+                reply,
+            })
+            .map_err(|_| Status::unavailable("control plane stopped"))?;
+        response
+            .await
+            .map_err(|_| Status::unavailable("control plane stopped"))
+    }
+}
+
+#[tonic::async_trait]
+impl ControlPlane for GrpcControlPlane {
+    type TailEventsStream = ReceiverStream<Result<proto::EventBatch, Status>>;
+
+    async fn call(
+        &self,
+        request: Request<proto::CommandRequest>,
+    ) -> Result<Response<proto::CommandResponse>, Status> {
+        let identity = identity_claim(&request);
+        let request = request.into_inner();
+        let params = if request.params_json.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(&request.params_json)
+                .map_err(|e| Status::invalid_argument(format!("params_json: {e}")))?
+        };
+
+        let envelope = self.run(&request.command, params, identity).await?;
+        let response = match envelope.get("error").filter(|error| !error.is_null()) {
+            Some(error) => proto::CommandResponse {
+                result_json: String::new(),
+                error: Some(proto::CommandError {
+                    code: json_str(error, "code"),
+                    message: json_str(error, "message"),
+                    details_json: error
+                        .get("details")
+                        .filter(|details| !details.is_null())
+                        .map(Value::to_string)
+                        .unwrap_or_default(),
+                }),
+            },
+            None => proto::CommandResponse {
+                result_json: envelope
+                    .get("result")
+                    .cloned()
+                    .unwrap_or(Value::Null)
+                    .to_string(),
+                error: None,
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn tail_events(
+        &self,
+        request: Request<proto::TailRequest>,
+    ) -> Result<Response<Self::TailEventsStream>, Status> {
+        let identity = identity_claim(&request);
+        let tail = request.into_inner();
+        let mut params = json!({
+            "branch": if tail.branch.is_empty() { "main" } else { tail.branch.as_str() },
+            "limit": TAIL_PAGE,
+        });
+        for (key, value) in [
+            ("since", &tail.since),
+            ("actor", &tail.actor),
+            ("label", &tail.label),
+            ("request_id", &tail.request_id),
+        ] {
+            if !value.is_empty() {
+                params[key] = json!(value);
+            }
+        }
+
+        let plane = self.clone();
+        let (sender, batches) = tokio::sync::mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut commits = plane.commits.clone();
+            loop {
+                // Anything committed from here on wakes the wait below
+                commits.borrow_and_update();
+                let envelope = match plane
+                    .run("dataspace_events", params.clone(), identity.clone())
+                    .await
+                {
+                    Ok(envelope) => envelope,
+                    Err(status) => {
+                        let _ = sender.send(Err(status)).await;
+                        return;
+                    }
+                };
+                if let Some(error) = envelope.get("error").filter(|error| !error.is_null()) {
+                    let _ = sender.send(Err(status_for(error))).await;
+                    return;
+                }
+
+                let result = &envelope["result"];
+                let events = result["events"].as_array().cloned().unwrap_or_default();
+                for batch in &events {
+                    let batch = proto::EventBatch {
+                        turn: json_str(batch, "turn"),
+                        actor: json_str(batch, "actor"),
+                        batch_json: batch.to_string(),
+                    };
+                    if sender.send(Ok(batch)).await.is_err() {
+                        return;
+                    }
+                }
+                if let Some(next) = result.get("next_cursor").and_then(Value::as_str) {
+                    params["since"] = json!(next);
+                }
+
+                if events.is_empty() {
+                    tokio::select! {
+                        changed = commits.changed() => if changed.is_err() { return },
+                        _ = sender.closed() => return,
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(batches)))
+    }
+}
+
+/// Identity named in `request`'s metadata, if any
+fn identity_claim<T>(request: &Request<T>) -> Option<IdentityClaim> {
+    let value = |key: &str| {
+        request
+            .metadata()
+            .get(key)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    Some(IdentityClaim {
+        id: value("duet-identity")?,
+        kind: None,
+        display_name: None,
+        token: value("duet-identity-token"),
+    })
+}
+
+/// gRPC service for `control`, for mounting on a tonic server.
+pub fn server(control: Control) -> ControlPlaneServer<GrpcControlPlane> {
+    ControlPlaneServer::new(GrpcControlPlane::new(control))
+}
+
+/// Serve the control plane over gRPC on `addr` until the server fails.
+pub async fn serve(control: Control, addr: SocketAddr) -> std::io::Result<()> {
+    serve_listener(control, TcpListener::bind(addr).await?).await
+}
+
+/// Serve the control plane over gRPC on an already bound listener.
+pub async fn serve_listener(control: Control, listener: TcpListener) -> std::io::Result<()> {
+    tonic::transport::Server::builder()
+        .add_service(server(control))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+        .map_err(std::io::Error::other)
+}
+
+fn json_str(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// gRPC status for a service error envelope
+fn status_for(error: &Value) -> Status {
+    let message = json_str(error, "message");
+    match error.get("code").and_then(Value::as_str) {
+        Some("invalid_params" | "parse_error") => Status::invalid_argument(message),
+        Some("unsupported_command") => Status::unimplemented(message),
+        Some("rate_limited") => Status::resource_exhausted(message),
+        _ => Status::internal(message),
+    }
+}
@@ -5,60 +5,470 @@
 //! `codebased` command-line daemon and is intentionally conservative: commands are
 //! processed sequentially, and unsupported operations return structured errors.
 
+pub mod commands;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod session;
+mod stats;
+
 use crate::PROTOCOL_VERSION;
 use crate::codebase::{self, transcript};
+use crate::runtime::branch::{BranchDetails, ForecastAssertion};
+use crate::runtime::compression::TrainingOptions;
 use crate::runtime::control::{AssertionEventAction, AssertionEventFilter, Control};
+use crate::runtime::cursor::CursorDirection;
 use crate::runtime::error::{CapabilityError, RuntimeError};
-use crate::runtime::turn::{ActorId, BranchId, TurnId};
-use crate::util::io_value::{as_record, io_value_summary, io_value_to_json};
+use crate::runtime::flags::FeatureFlag;
+use crate::runtime::history::HistoryDetail;
+use crate::runtime::identity::{Identity, IdentityClaim};
+use crate::runtime::manager::{RuntimeManager, TenantInfo};
+use crate::runtime::pattern::{Pattern, PatternScope, matches_pattern, parse_pattern};
+use crate::runtime::reaction::{ReactionDefinition, ReactionEffect};
+use crate::runtime::schema::SchemaRegistry;
+use crate::runtime::sturdy::SturdyRef;
+use crate::runtime::turn::{ActorId, BranchId, FacetId, TurnId};
+use crate::util::io_value::{as_record, io_value_summary, io_value_to_json, json_to_io_value};
+use commands::{CommandError, CommandRegistry, ServiceCommand};
 use preserves::IOValue;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use std::collections::HashMap;
+use session::SessionStore;
+use stats::CommandStats;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{self, BufRead, Write};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-/// Service entry point: wraps a [`Control`] instance and writes responses to a writer.
+/// Rate-limit key for clients that never named themselves in a handshake.
+const ANONYMOUS_CLIENT: &str = "anonymous";
+
+/// Tenant name of the runtime a [`Service`] is created with.
+const DEFAULT_TENANT: &str = "default";
+
+/// Service entry point: routes requests to the runtimes of a [`RuntimeManager`]
+/// and writes responses to a writer.
+///
+/// Each connection talks to the default tenant until its handshake names
+/// another one (optionally with the `root` to host it from).
 pub struct Service {
-    control: Control,
+    runtimes: RuntimeManager,
+    tenants: HashMap<String, TenantState>,
+    /// Commands registered by the embedding application
+    commands: CommandRegistry,
+}
+
+/// Service state kept for each hosted runtime
+struct TenantState {
     pending_requests: HashMap<String, transcript::TranscriptCursor>,
+    sessions: SessionStore,
+    stats: CommandStats,
+}
+
+impl TenantState {
+    fn new(control: &Control) -> Self {
+        Self {
+            pending_requests: HashMap::new(),
+            sessions: SessionStore::new(&control.runtime().config().root),
+            stats: CommandStats::default(),
+        }
+    }
+}
+
+/// Protocol state of one client connection
+#[derive(Default)]
+struct Connection {
+    handshake_completed: bool,
+    client: Option<String>,
+    /// Token of the resumable session this connection is bound to
+    token: Option<String>,
+    /// Tenant selected by the handshake (the default tenant when `None`)
+    tenant: Option<String>,
+    /// Identity the client acts as, attributed to the changes it causes
+    identity: Option<Identity>,
 }
 
 impl Service {
     /// Create a new service wrapper around the provided control interface.
     pub fn new(control: Control) -> Self {
+        Self::with_manager(RuntimeManager::new(DEFAULT_TENANT, control))
+    }
+
+    /// Serve every runtime hosted by `runtimes`.
+    pub fn with_manager(runtimes: RuntimeManager) -> Self {
         Self {
-            control,
-            pending_requests: HashMap::new(),
+            runtimes,
+            tenants: HashMap::new(),
+            commands: CommandRegistry::default(),
+        }
+    }
+
+    /// Add command `name` to the protocol, run by `command`.
+    ///
+    /// Fails when `name` is a built-in command or already registered.
+    pub fn register_command(
+        &mut self,
+        name: impl Into<String>,
+        command: impl ServiceCommand + 'static,
+    ) -> Result<(), String> {
+        let name = name.into();
+        if name.is_empty() {
+            return Err("command name must not be empty".to_string());
+        }
+        if BUILTIN_COMMANDS.iter().any(|(builtin, _)| *builtin == name) {
+            return Err(format!("'{}' is a built-in command", name));
+        }
+        if !self.commands.insert(name.clone(), Arc::new(command)) {
+            return Err(format!("command '{}' is already registered", name));
         }
+        Ok(())
     }
 
     /// Process a single connection by consuming requests from the reader and writing responses.
-    pub fn handle<R: BufRead, W: Write>(&mut self, reader: R, writer: W) -> io::Result<()> {
-        let mut session = Session::new(&mut self.control, &mut self.pending_requests, writer);
-        session.run(reader)
+    pub fn handle<R: BufRead, W: Write>(&mut self, reader: R, mut writer: W) -> io::Result<()> {
+        let mut connection = Connection::default();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str::<RequestEnvelope>(&line) {
+                Ok(request) => self.respond(&mut connection, request),
+                Err(err) => {
+                    ResponseEnvelope::from_error(Value::Null, ServiceError::Parse(err.to_string()))
+                }
+            };
+            serde_json::to_writer(&mut writer, &response)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Execute a single command on behalf of an already-handshaken client
+    /// that named no identity; refused when the runtime requires one.
+    ///
+    /// Returns the serialized response envelope, exactly as it would be written
+    /// to an NDJSON connection (with a null request id). Commands run against
+    /// the default tenant.
+    pub fn call(&mut self, command: &str, params: &Value) -> Value {
+        self.call_as(command, params, None)
+    }
+
+    /// Execute a single command like [`call`](Self::call), acting as the
+    /// identity `claim` authenticates to, as a handshake naming it would.
+    pub fn call_as(
+        &mut self,
+        command: &str,
+        params: &Value,
+        claim: Option<&IdentityClaim>,
+    ) -> Value {
+        let identity = match self
+            .runtimes
+            .default_control_mut()
+            .runtime()
+            .config()
+            .identity
+            .authenticate(claim)
+        {
+            Ok(identity) => identity,
+            Err(err) => {
+                let error = ServiceError::Protocol(format!("authentication failed: {}", err));
+                return serde_json::to_value(ResponseEnvelope::from_error(Value::Null, error))
+                    .unwrap_or_default();
+            }
+        };
+        let mut connection = Connection {
+            handshake_completed: true,
+            identity,
+            ..Default::default()
+        };
+        let request = RequestEnvelope {
+            id: Value::Null,
+            command: command.to_string(),
+            params: params.clone(),
+            idempotency_key: None,
+        };
+        serde_json::to_value(self.respond(&mut connection, request)).unwrap_or_default()
+    }
+
+    /// Route `request` to the runtime of the connection's tenant.
+    fn respond(
+        &mut self,
+        connection: &mut Connection,
+        request: RequestEnvelope,
+    ) -> ResponseEnvelope {
+        if request.command == "handshake" {
+            match self.select_tenant(&request.params) {
+                // A new handshake starts over, possibly in another runtime
+                Ok(tenant) => {
+                    *connection = Connection {
+                        tenant,
+                        ..Default::default()
+                    }
+                }
+                Err(err) => return ResponseEnvelope::from_error(request.id, err),
+            }
+        }
+
+        let tenant = connection
+            .tenant
+            .clone()
+            .unwrap_or_else(|| self.runtimes.default_tenant().to_string());
+        let listing = match request.command.as_str() {
+            "tenants" => self.runtimes.tenants(),
+            _ => Vec::new(),
+        };
+        let control = match self.runtimes.control_mut(&tenant) {
+            Ok(control) => control,
+            Err(err) => return ResponseEnvelope::from_error(request.id, err.into()),
+        };
+        let state = self
+            .tenants
+            .entry(tenant.clone())
+            .or_insert_with(|| TenantState::new(control));
+        let mut session = Session {
+            control,
+            pending_requests: &mut state.pending_requests,
+            sessions: &mut state.sessions,
+            stats: &mut state.stats,
+            connection,
+            tenant: &tenant,
+            tenants: listing,
+            commands: &self.commands,
+        };
+        session.handle_request(request)
+    }
+
+    /// Tenant named by handshake `params`, admitting it first when a `root` is given.
+    fn select_tenant(&mut self, params: &Value) -> Result<Option<String>, ServiceError> {
+        let root = params.get("root").and_then(Value::as_str);
+        let name = match params.get("tenant") {
+            None | Some(Value::Null) if root.is_none() => return Ok(None),
+            Some(Value::String(name)) if !name.is_empty() => name.clone(),
+            _ => return Err(ServiceError::invalid_param("tenant")),
+        };
+        if let Some(root) = root {
+            self.runtimes
+                .admit(name.clone(), root)
+                .map_err(|err| ServiceError::InvalidParams(err.to_string()))?;
+        }
+        if !self.runtimes.contains(&name) {
+            return Err(ServiceError::InvalidParams(format!(
+                "unknown tenant '{}'",
+                name
+            )));
+        }
+        self.runtimes.control_mut(&name)?;
+        Ok(Some(name))
     }
 }
 
-struct Session<'a, W: Write> {
+/// Handler of a built-in command
+type Builtin = fn(&mut Session<'_>, &Value) -> Result<Value, ServiceError>;
+
+/// Commands every service understands, by name
+const BUILTIN_COMMANDS: &[(&str, Builtin)] = &[
+    ("handshake", |session, params| session.cmd_handshake(params)),
+    ("status", |session, params| session.cmd_status(params)),
+    ("tenants", |session, _| session.cmd_tenants()),
+    ("list_branches", |session, _| session.cmd_list_branches()),
+    ("branch_head", |session, params| {
+        session.cmd_branch_head(params)
+    }),
+    ("branch_lca", |session, params| {
+        session.cmd_branch_lca(params)
+    }),
+    ("history", |session, params| session.cmd_history(params)),
+    ("actor_history", |session, params| {
+        session.cmd_actor_history(params)
+    }),
+    ("explain", |session, params| session.cmd_explain(params)),
+    ("doctor", |session, _| session.cmd_doctor()),
+    ("step", |session, params| session.cmd_step(params)),
+    ("goto", |session, params| session.cmd_goto(params)),
+    ("back", |session, params| session.cmd_back(params)),
+    ("reflog", |session, params| session.cmd_reflog(params)),
+    ("restore_head", |session, params| {
+        session.cmd_restore_head(params)
+    }),
+    ("fork", |session, params| session.cmd_fork(params)),
+    ("merge", |session, params| session.cmd_merge(params)),
+    ("merge_forecast", |session, params| {
+        session.cmd_merge_forecast(params)
+    }),
+    ("list_entities", |session, params| {
+        session.cmd_list_entities(params)
+    }),
+    ("describe_entity_type", |session, params| {
+        session.cmd_describe_entity_type(params)
+    }),
+    ("entity_state", |session, params| {
+        session.cmd_entity_state(params)
+    }),
+    ("set_entity_state", |session, params| {
+        session.cmd_set_entity_state(params)
+    }),
+    ("list_capabilities", |session, params| {
+        session.cmd_list_capabilities(params)
+    }),
+    ("capability_export", |session, params| {
+        session.cmd_capability_export(params)
+    }),
+    ("capability_redeem", |session, params| {
+        session.cmd_capability_redeem(params)
+    }),
+    ("approval_list", |session, _| session.cmd_approval_list()),
+    ("approve", |session, params| session.cmd_approve(params)),
+    ("deny", |session, params| session.cmd_deny(params)),
+    ("workspace_entries", |session, _| {
+        session.cmd_workspace_entries()
+    }),
+    ("workspace_restore", |session, params| {
+        session.cmd_workspace_restore(params)
+    }),
+    ("workspace_offers", |session, _| {
+        session.cmd_workspace_offers()
+    }),
+    ("workspace_offer_accept", |session, params| {
+        session.cmd_workspace_offer_settle(params, true)
+    }),
+    ("workspace_offer_decline", |session, params| {
+        session.cmd_workspace_offer_settle(params, false)
+    }),
+    ("transcript_show", |session, params| {
+        session.cmd_transcript_show(params)
+    }),
+    ("transcript_tail", |session, params| {
+        session.cmd_transcript_tail(params)
+    }),
+    ("reaction_list", |session, _| session.cmd_reaction_list()),
+    ("reaction_register", |session, params| {
+        session.cmd_reaction_register(params)
+    }),
+    ("reaction_unregister", |session, params| {
+        session.cmd_reaction_unregister(params)
+    }),
+    ("pattern_register", |session, params| {
+        session.cmd_pattern_register(params)
+    }),
+    ("dataspace_assertions", |session, params| {
+        session.cmd_dataspace_assertions(params)
+    }),
+    ("dataspace_events", |session, params| {
+        session.cmd_dataspace_events(params)
+    }),
+    ("assertion_schemas", |session, _| {
+        session.cmd_assertion_schemas()
+    }),
+    ("retract_matching", |session, params| {
+        session.cmd_retract_matching(params)
+    }),
+    ("send_message", |session, params| {
+        session.cmd_send_message(params)
+    }),
+    ("invoke_capability", |session, params| {
+        session.cmd_invoke_capability(params)
+    }),
+    ("pause_actor", |session, params| {
+        session.cmd_set_actor_paused(params, true)
+    }),
+    ("memory_report", |session, params| {
+        session.cmd_memory_report(params)
+    }),
+    ("set_log_level", |session, params| {
+        session.cmd_set_log_level(params)
+    }),
+    ("service_stats", |session, params| {
+        session.cmd_service_stats(params)
+    }),
+    ("wait_register", |session, params| {
+        session.cmd_wait_register(params)
+    }),
+    ("wait_poll", |session, params| session.cmd_wait_poll(params)),
+    ("wait_cancel", |session, params| {
+        session.cmd_wait_cancel(params)
+    }),
+    ("checkpoint_push", |session, params| {
+        session.cmd_checkpoint_push(params)
+    }),
+    ("checkpoint_pull", |session, params| {
+        session.cmd_checkpoint_pull(params)
+    }),
+    ("branch_archive", |session, params| {
+        session.cmd_branch_archive(params)
+    }),
+    ("branch_unarchive", |session, params| {
+        session.cmd_branch_unarchive(params)
+    }),
+    ("delete_branch", |session, params| {
+        session.cmd_delete_branch(params)
+    }),
+    ("outbox", |session, params| session.cmd_outbox(params)),
+    ("backup", |session, params| session.cmd_backup(params)),
+    ("flush", |session, _| session.cmd_flush()),
+    ("journal_dictionary", |session, _| {
+        session.cmd_journal_dictionary()
+    }),
+    ("compact", |session, params| session.cmd_compact(params)),
+    ("prune_snapshots", |session, params| {
+        session.cmd_prune_snapshots(params)
+    }),
+    ("tag_snapshot", |session, params| {
+        session.cmd_tag_snapshot(params)
+    }),
+    ("journal_dictionary_train", |session, params| {
+        session.cmd_journal_dictionary_train(params)
+    }),
+    ("journal_dictionary_disable", |session, _| {
+        session.cmd_journal_dictionary_disable()
+    }),
+    ("fixture_run", |session, params| {
+        session.cmd_fixture_run(params)
+    }),
+    ("resume_actor", |session, params| {
+        session.cmd_set_actor_paused(params, false)
+    }),
+    ("flags", |session, _| session.cmd_flags()),
+    ("set_flag", |session, params| session.cmd_set_flag(params)),
+];
+
+struct Session<'a> {
     control: &'a mut Control,
     pending_requests: &'a mut HashMap<String, transcript::TranscriptCursor>,
-    writer: W,
-    handshake_completed: bool,
+    sessions: &'a mut SessionStore,
+    stats: &'a mut CommandStats,
+    connection: &'a mut Connection,
+    /// Tenant whose runtime `control` is
+    tenant: &'a str,
+    /// Hosted tenants, listed for the `tenants` command only
+    tenants: Vec<TenantInfo>,
+    /// Commands registered by the embedding application
+    commands: &'a CommandRegistry,
 }
 
-impl<'a, W: Write> Session<'a, W> {
-    fn new(
-        control: &'a mut Control,
-        pending_requests: &'a mut HashMap<String, transcript::TranscriptCursor>,
-        writer: W,
-    ) -> Self {
-        Self {
-            control,
-            pending_requests,
-            writer,
-            handshake_completed: false,
+impl<'a> Session<'a> {
+    /// Transcript cursors of the bound session, or the ones shared by
+    /// clients without a session.
+    fn cursors(&mut self) -> &mut HashMap<String, transcript::TranscriptCursor> {
+        match &self.connection.token {
+            Some(token) => &mut self.sessions.state(token).transcript_cursors,
+            None => self.pending_requests,
+        }
+    }
+
+    /// Persist the bound session, if any.
+    fn save_session(&mut self) -> Result<(), ServiceError> {
+        match &self.connection.token {
+            Some(token) => self
+                .sessions
+                .save(token)
+                .map_err(|err| ServiceError::Protocol(format!("failed to save session: {}", err))),
+            None => Ok(()),
         }
     }
 
@@ -89,71 +499,60 @@ impl<'a, W: Write> Session<'a, W> {
         Some(summary)
     }
 
-    fn run<R: BufRead>(&mut self, reader: R) -> io::Result<()> {
-        for line in reader.lines() {
-            let line = line?;
-            if line.trim().is_empty() {
-                continue;
-            }
+    fn handle_request(&mut self, request: RequestEnvelope) -> ResponseEnvelope {
+        // Retries of a request the bound session already ran get the same answer
+        let idempotency = self
+            .connection
+            .token
+            .clone()
+            .zip(request.idempotency_key.clone());
+        if let Some((token, key)) = &idempotency
+            && let Some(result) = self
+                .sessions
+                .state(token)
+                .cached_result(key, &request.command)
+        {
+            return ResponseEnvelope::success(request.id, result.clone());
+        }
 
-            let envelope: Result<RequestEnvelope, _> = serde_json::from_str(&line);
-            match envelope {
-                Ok(request) => {
-                    let response = self.handle_request(request);
-                    self.write_response(response)?;
-                }
-                Err(err) => {
-                    let response = ResponseEnvelope::from_error(
-                        Value::Null,
-                        ServiceError::Parse(err.to_string()),
-                    );
-                    self.write_response(response)?;
-                }
+        let started_at = chrono::Utc::now();
+        let timer = Instant::now();
+        let acting = self.control.set_identity(self.connection.identity.clone());
+        let mut result = self.dispatch(&request.command, &request.params);
+        self.control.set_identity(acting);
+        let elapsed = timer.elapsed();
+        if let (Some((token, key)), Ok(value)) = (&idempotency, &result) {
+            self.sessions
+                .state(token)
+                .cache_result(key, &request.command, value.clone());
+            if let Err(err) = self.save_session() {
+                result = Err(err);
             }
         }
 
-        Ok(())
-    }
-
-    fn write_response(&mut self, envelope: ResponseEnvelope) -> io::Result<()> {
-        serde_json::to_writer(&mut self.writer, &envelope)?;
-        self.writer.write_all(b"\n")?;
-        self.writer.flush()
-    }
-
-    fn handle_request(&mut self, request: RequestEnvelope) -> ResponseEnvelope {
-        let result = match self.dispatch(&request.command, &request.params) {
-            Ok(value) => Ok(value),
-            Err(err) => Err(err),
-        };
-
-        match result {
+        let response = match result {
             Ok(value) => ResponseEnvelope::success(request.id, value),
             Err(err) => ResponseEnvelope::from_error(request.id, err),
-        }
+        };
+        let code = response
+            .error
+            .as_ref()
+            .map_or("ok", |error| error.code.as_str());
+        self.stats
+            .record(&request.command, &request.params, started_at, elapsed, code);
+        response
     }
 
     fn dispatch(&mut self, command: &str, params: &Value) -> Result<Value, ServiceError> {
-        match command {
-            "handshake" => self.cmd_handshake(params),
-            "status" => self.cmd_status(params),
-            "list_branches" => self.cmd_list_branches(),
-            "history" => self.cmd_history(params),
-            "step" => self.cmd_step(params),
-            "goto" => self.cmd_goto(params),
-            "back" => self.cmd_back(params),
-            "fork" => self.cmd_fork(params),
-            "merge" => self.cmd_merge(params),
-            "list_entities" => self.cmd_list_entities(params),
-            "list_capabilities" => self.cmd_list_capabilities(params),
-            "workspace_entries" => self.cmd_workspace_entries(),
-            "transcript_show" => self.cmd_transcript_show(params),
-            "transcript_tail" => self.cmd_transcript_tail(params),
-            "reaction_list" => self.cmd_reaction_list(),
-            "dataspace_assertions" => self.cmd_dataspace_assertions(params),
-            "dataspace_events" => self.cmd_dataspace_events(params),
-            other => Err(ServiceError::Unsupported(other.to_string())),
+        if let Some((_, handler)) = BUILTIN_COMMANDS.iter().find(|(name, _)| *name == command) {
+            return handler(self, params);
         }
+        let Some(extension) = self.commands.get(command) else {
+            return Err(ServiceError::Unsupported(command.to_string()));
+        };
+        self.ensure_handshake()?;
+        commands::validate_params(&extension.params(), params)?;
+        Ok(extension.execute(self.control, params)?)
     }
 
     fn cmd_handshake(&mut self, params: &Value) -> Result<Value, ServiceError> {
@@ -174,10 +573,57 @@ impl<'a, W: Write> Session<'a, W> {
             )));
         }
 
-        self.handshake_completed = true;
+        let token = match params.get("session") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(token)) if !token.is_empty() => Some(token.clone()),
+            Some(_) => return Err(ServiceError::invalid_param("session")),
+        };
+
+        let claim = match params.get("identity") {
+            None | Some(Value::Null) => None,
+            Some(value) => Some(
+                serde_json::from_value::<IdentityClaim>(value.clone())
+                    .map_err(|err| ServiceError::invalid_field("identity", err))?,
+            ),
+        };
+        let identity = self
+            .control
+            .runtime()
+            .config()
+            .identity
+            .authenticate(claim.as_ref())
+            .map_err(|err| ServiceError::Protocol(format!("authentication failed: {}", err)))?;
+
+        self.connection.handshake_completed = true;
+        self.connection.client = Some(client.to_string());
+        self.connection.token = token.clone();
+        self.connection.identity = identity.clone();
+
+        let session = match &token {
+            Some(token) => {
+                let resumed = self.sessions.open(token).map_err(|err| {
+                    ServiceError::Protocol(format!("failed to load session: {}", err))
+                })?;
+                self.sessions.state(token).client = Some(client.to_string());
+                self.save_session()?;
+                let state = self.sessions.state(token);
+                let mut cursors: Vec<&String> = state.transcript_cursors.keys().collect();
+                cursors.sort();
+                json!({
+                    "resumed": resumed,
+                    "transcript_cursors": cursors,
+                    "subscriptions": state.subscriptions,
+                })
+            }
+            None => Value::Null,
+        };
 
         Ok(json!({
             "protocol_version": PROTOCOL_VERSION,
+            "session": session,
+            "tenant": self.tenant,
+            "identity": identity,
+            "commands": self.commands.describe(),
             "runtime": {
                 "version": crate::VERSION,
                 "client": client,
@@ -188,17 +634,55 @@ impl<'a, W: Write> Session<'a, W> {
                     "branching",
                     "entity_inspection",
                     "branch_listing",
+                    "branch_queries",
                     "dataspace_inspection",
                     "dataspace_events",
                     "transcript_inspection",
-                    "reaction_inspection"
+                    "reaction_inspection",
+                    "sturdy_refs",
+                    "approvals",
+                    "bulk_retract",
+                    "entity_discovery",
+                    "actor_pause",
+                    "memory_accounting",
+                    "log_levels",
+                    "service_stats",
+                    "long_poll",
+                    "checkpoint_artifacts",
+                    "fixtures",
+                    "read_your_writes",
+                    "entity_state",
+                    "actor_history",
+                    "capability_offers",
+                    "feature_flags",
+                    "rate_limits",
+                    "branch_archive",
+                    "schema_filters",
+                    "history_views",
+                    "backup",
+                    "session_resumption",
+                    "pattern_syntax",
+                    "reaction_registration",
+                    "explain",
+                    "doctor",
+                    "journal_compression",
+                    "compact",
+                    "tenants",
+                    "merge_forecast",
+                    "branch_delete",
+                    "outbox",
+                    "identity",
+                    "command_registry",
+                    "reflog",
+                    "snapshot_retention",
+                    "durability"
                 ]
             }
         }))
     }
 
     fn ensure_handshake(&self) -> Result<(), ServiceError> {
-        if self.handshake_completed {
+        if self.connection.handshake_completed {
             Ok(())
         } else {
             Err(ServiceError::Protocol(
@@ -207,147 +691,1280 @@ impl<'a, W: Write> Session<'a, W> {
         }
     }
 
+    fn cmd_tenants(&mut self) -> Result<Value, ServiceError> {
+        Ok(json!({
+            "tenant": self.tenant,
+            "tenants": self.tenants,
+        }))
+    }
+
     fn cmd_status(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
         if let Some(branch_name) = params.get("branch").and_then(Value::as_str) {
             self.switch_branch(branch_name)?;
         }
 
-        let status = self.control.status().map_err(ServiceError::from)?;
-        Ok(serde_json::to_value(status).unwrap_or_default())
+        let status = self.control.status().map_err(ServiceError::from)?;
+        Ok(serde_json::to_value(status).unwrap_or_default())
+    }
+
+    fn cmd_list_branches(&mut self) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let branches = self.control.list_branches().map_err(ServiceError::from)?;
+        let archived = self.control.list_archived_branches();
+        Ok(json!({ "branches": branches, "archived": archived }))
+    }
+
+    fn cmd_branch_head(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let branch_name = params
+            .get("branch")
+            .and_then(Value::as_str)
+            .unwrap_or("main");
+        let head = self
+            .control
+            .branch_head(&BranchId::new(branch_name))
+            .map_err(ServiceError::from)?;
+        Ok(serde_json::to_value(head).unwrap_or_default())
+    }
+
+    fn cmd_branch_lca(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let a = params
+            .get("a")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("a"))?;
+        let b = params
+            .get("b")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("b"))?;
+        let lca = self
+            .control
+            .branch_lca(&BranchId::new(a), &BranchId::new(b))
+            .map_err(ServiceError::from)?;
+        Ok(serde_json::to_value(lca).unwrap_or_default())
+    }
+
+    fn cmd_history(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let branch_name = params
+            .get("branch")
+            .and_then(Value::as_str)
+            .unwrap_or("main");
+        let start = params.get("start").and_then(Value::as_u64).unwrap_or(0) as usize;
+        let limit = params.get("limit").and_then(Value::as_u64).unwrap_or(20) as usize;
+
+        let detail = history_detail(params)?;
+
+        let branch = BranchId::new(branch_name);
+        if let Some((cursor, direction)) = page_params(params)? {
+            let page = self
+                .control
+                .history_page(&branch, cursor, direction, limit)
+                .map_err(ServiceError::from)?;
+            let turns = self
+                .control
+                .turn_views(&branch, page.items, detail)
+                .map_err(ServiceError::from)?;
+            return Ok(json!({ "turns": turns, "next_cursor": page.next_cursor }));
+        }
+
+        let history = self
+            .control
+            .history(&branch, start, limit)
+            .map_err(ServiceError::from)?;
+        let turns = self
+            .control
+            .turn_views(&branch, history, detail)
+            .map_err(ServiceError::from)?;
+
+        Ok(json!({ "turns": turns }))
+    }
+
+    fn cmd_actor_history(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let actor = params
+            .get("actor")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("actor"))?;
+        let actor = ActorId::from_uuid(parse_uuid(actor)?);
+        let facet = params
+            .get("facet")
+            .and_then(Value::as_str)
+            .map(|facet| parse_uuid(facet).map(FacetId::from_uuid))
+            .transpose()?;
+        let branch_name = params
+            .get("branch")
+            .and_then(Value::as_str)
+            .unwrap_or("main");
+        let start = params.get("start").and_then(Value::as_u64).unwrap_or(0) as usize;
+        let limit = params.get("limit").and_then(Value::as_u64).unwrap_or(20) as usize;
+        let detail = history_detail(params)?;
+
+        let branch = BranchId::new(branch_name);
+        let turns = self
+            .control
+            .actor_history(&branch, &actor, facet.as_ref(), start, limit)
+            .map_err(ServiceError::from)?;
+        let turns = self
+            .control
+            .turn_views(&branch, turns, detail)
+            .map_err(ServiceError::from)?;
+        Ok(json!({ "actor": actor.to_string(), "turns": turns }))
+    }
+
+    fn cmd_explain(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let turn_id = params
+            .get("turn_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("turn_id"))?;
+        let explanation = self
+            .control
+            .explain_turn(&TurnId::new(turn_id.to_string()))
+            .map_err(ServiceError::from)?;
+        Ok(serde_json::to_value(explanation).unwrap_or_default())
+    }
+
+    fn cmd_doctor(&mut self) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let report = self.control.doctor();
+        Ok(json!({
+            "healthy": report.healthy(),
+            "checks": report.checks,
+        }))
+    }
+
+    fn cmd_step(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        if let Some(branch_name) = params.get("branch").and_then(Value::as_str) {
+            self.switch_branch(branch_name)?;
+        }
+
+        let count = params.get("count").and_then(Value::as_u64).unwrap_or(1) as usize;
+        let turns = self.control.step(count).map_err(ServiceError::from)?;
+        Ok(json!({ "executed": turns }))
+    }
+
+    fn cmd_goto(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        if let Some(branch_name) = params.get("branch").and_then(Value::as_str) {
+            self.switch_branch(branch_name)?;
+        }
+
+        let turn_id_str = params
+            .get("turn_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("turn_id"))?;
+
+        let turn_id = TurnId::new(turn_id_str.to_string());
+        self.control
+            .goto(turn_id.clone())
+            .map_err(ServiceError::from)?;
+        Ok(json!({
+            "head": turn_id,
+            "irreversible_effects": self.rewind_effects(),
+        }))
+    }
+
+    fn cmd_back(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        if let Some(branch_name) = params.get("branch").and_then(Value::as_str) {
+            self.switch_branch(branch_name)?;
+        }
+
+        let count = params.get("count").and_then(Value::as_u64).unwrap_or(1) as usize;
+        let turn_id = self.control.back(count).map_err(ServiceError::from)?;
+        Ok(json!({
+            "head": turn_id,
+            "irreversible_effects": self.rewind_effects(),
+        }))
+    }
+
+    fn cmd_reflog(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let branch = match params.get("branch").and_then(Value::as_str) {
+            Some(name) => BranchId::new(name),
+            None => self.control.runtime().current_branch(),
+        };
+        let limit = params
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map_or(usize::MAX, |limit| limit as usize);
+
+        let entries = self.control.reflog(&branch).map_err(ServiceError::from)?;
+        let total = entries.len();
+        let entries: Vec<Value> = entries
+            .into_iter()
+            .take(limit)
+            .enumerate()
+            .map(|(index, entry)| {
+                json!({
+                    "index": index,
+                    "from": entry.from,
+                    "to": entry.to,
+                    "action": entry.action,
+                    "client": entry.client,
+                    "at": entry.at,
+                })
+            })
+            .collect();
+        Ok(json!({
+            "branch": branch,
+            "entries": entries,
+            "total": total,
+        }))
+    }
+
+    fn cmd_restore_head(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        if let Some(branch_name) = params.get("branch").and_then(Value::as_str) {
+            self.switch_branch(branch_name)?;
+        }
+
+        let index = params
+            .get("index")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| ServiceError::invalid_param("index"))? as usize;
+        let turn_id = self
+            .control
+            .restore_head(index)
+            .map_err(ServiceError::from)?;
+        Ok(json!({
+            "head": turn_id,
+            "irreversible_effects": self.rewind_effects(),
+        }))
+    }
+
+    /// Side effects the last rewind left in place, as reported by `goto` and `back`.
+    fn rewind_effects(&self) -> Value {
+        self.control
+            .rewind_warning()
+            .map(|warning| json!(warning.effects))
+            .unwrap_or_else(|| json!([]))
+    }
+
+    fn cmd_fork(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+
+        let source = params
+            .get("source")
+            .and_then(Value::as_str)
+            .unwrap_or("main");
+        let new_branch = match params.get("template").and_then(Value::as_str) {
+            Some(template) => {
+                let vars: BTreeMap<String, String> = params
+                    .get("vars")
+                    .and_then(Value::as_object)
+                    .map(|vars| {
+                        vars.iter()
+                            .filter_map(|(key, value)| {
+                                value.as_str().map(|value| (key.clone(), value.to_string()))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                self.control
+                    .branch_name(template, &vars)
+                    .map_err(ServiceError::from)?
+            }
+            None => params
+                .get("new_branch")
+                .and_then(Value::as_str)
+                .map(BranchId::new)
+                .ok_or_else(|| ServiceError::invalid_param("new_branch"))?,
+        };
+
+        let base_turn = params
+            .get("from_turn")
+            .and_then(Value::as_str)
+            .map(|s| TurnId::new(s.to_string()));
+
+        let details = BranchDetails {
+            description: params
+                .get("description")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            creator: params
+                .get("creator")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            tags: params
+                .get("tags")
+                .and_then(Value::as_array)
+                .map(|tags| {
+                    tags.iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+
+        let branch = self
+            .control
+            .fork(BranchId::new(source), new_branch, base_turn, details)
+            .map_err(ServiceError::from)?;
+
+        Ok(json!({ "branch": branch }))
+    }
+
+    fn cmd_merge(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+
+        let source = params
+            .get("source")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("source"))?;
+        let target = params
+            .get("target")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("target"))?;
+
+        let report = self
+            .control
+            .merge(BranchId::new(source), BranchId::new(target))
+            .map_err(ServiceError::from)?;
+
+        Ok(serde_json::to_value(report).unwrap_or_default())
+    }
+
+    fn cmd_merge_forecast(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+
+        let source = params
+            .get("source")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("source"))?;
+        let target = params
+            .get("target")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("target"))?;
+
+        let forecast = self
+            .control
+            .merge_forecast(&BranchId::new(source), &BranchId::new(target))
+            .map_err(ServiceError::from)?;
+
+        let describe = |assertions: &[ForecastAssertion]| {
+            assertions
+                .iter()
+                .map(|assertion| {
+                    json!({
+                        "actor": assertion.actor.to_string(),
+                        "handle": assertion.handle.to_string(),
+                        "summary": io_value_summary(&assertion.value, 80),
+                        "value_structured": io_value_to_json(&assertion.value),
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        Ok(json!({
+            "source": source,
+            "target": target,
+            "lca": forecast.lca.to_string(),
+            "warnings": forecast.warnings,
+            "added": describe(&forecast.added),
+            "retracted": describe(&forecast.retracted),
+        }))
+    }
+
+    fn cmd_list_entities(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        if let Some(actor_str) = params.get("actor").and_then(Value::as_str) {
+            let actor = ActorId::from_uuid(parse_uuid(actor_str)?);
+            let entities = self.control.list_entities_for_actor(&actor);
+            Ok(json!({ "entities": entities }))
+        } else if let Some((cursor, direction)) = page_params(params)? {
+            let limit = params.get("limit").and_then(Value::as_u64).unwrap_or(50) as usize;
+            let page = self
+                .control
+                .entities_page(cursor, direction, limit)
+                .map_err(ServiceError::from)?;
+            Ok(json!({ "entities": page.items, "next_cursor": page.next_cursor }))
+        } else {
+            let entities = self.control.list_entities();
+            Ok(json!({ "entities": entities }))
+        }
+    }
+
+    fn cmd_describe_entity_type(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let entity_type = params
+            .get("entity_type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("entity_type"))?;
+
+        let descriptor = self
+            .control
+            .describe_entity_type(entity_type)
+            .map_err(ServiceError::from)?;
+        let concurrency = self
+            .control
+            .entity_concurrency(entity_type)
+            .map_err(ServiceError::from)?;
+
+        Ok(json!({
+            "entity_type": entity_type,
+            "concurrency": concurrency,
+            "message_labels": descriptor.message_labels,
+            "capability_kinds": descriptor.capability_kinds,
+            "config_schema": descriptor
+                .config_schema
+                .as_ref()
+                .map(|schema| format!("{:?}", schema)),
+        }))
+    }
+
+    fn cmd_entity_state(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let entity_id = params
+            .get("entity_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("entity_id"))?;
+        let entity_id = parse_uuid(entity_id)?;
+
+        let state = self
+            .control
+            .entity_state(entity_id)
+            .map_err(ServiceError::from)?;
+        Ok(json!({
+            "entity_id": entity_id.to_string(),
+            "hydratable": state.is_some(),
+            "state": state.as_ref().map(io_value_to_json),
+            "state_text": state.as_ref().map(|state| format!("{:?}", state)),
+        }))
+    }
+
+    fn cmd_set_entity_state(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let entity_id = params
+            .get("entity_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("entity_id"))?;
+        let entity_id = parse_uuid(entity_id)?;
+        let state = value_param(params, "state")?;
+
+        self.control
+            .set_entity_state(entity_id, &state)
+            .map_err(ServiceError::from)?;
+        Ok(json!({
+            "entity_id": entity_id.to_string(),
+            "state": io_value_to_json(&state),
+        }))
+    }
+
+    fn cmd_list_capabilities(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        if let Some(actor_str) = params.get("actor").and_then(Value::as_str) {
+            let actor = ActorId::from_uuid(parse_uuid(actor_str)?);
+            let capabilities = self.control.list_capabilities_for_actor(&actor);
+            Ok(json!({ "capabilities": capabilities }))
+        } else if let Some((cursor, direction)) = page_params(params)? {
+            let limit = params.get("limit").and_then(Value::as_u64).unwrap_or(50) as usize;
+            let page = self
+                .control
+                .capabilities_page(cursor, direction, limit)
+                .map_err(ServiceError::from)?;
+            Ok(json!({ "capabilities": page.items, "next_cursor": page.next_cursor }))
+        } else {
+            let capabilities = self.control.list_capabilities();
+            Ok(json!({ "capabilities": capabilities }))
+        }
+    }
+
+    fn cmd_capability_export(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let capability = params
+            .get("capability")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("capability"))?;
+        let capability = parse_uuid(capability)?;
+
+        let mut caveats = Vec::new();
+        if let Some(values) = params.get("caveats").and_then(Value::as_array) {
+            for value in values {
+                let text = value
+                    .as_str()
+                    .ok_or_else(|| ServiceError::invalid_param("caveats"))?;
+                caveats.push(parse_pattern(text)?);
+            }
+        }
+
+        let expires_at = match params.get("expires_at").and_then(Value::as_str) {
+            Some(text) => Some(
+                chrono::DateTime::parse_from_rfc3339(text)
+                    .map_err(|err| {
+                        ServiceError::InvalidParams(format!(
+                            "invalid expires_at '{}': {}",
+                            text, err
+                        ))
+                    })?
+                    .with_timezone(&chrono::Utc),
+            ),
+            None => None,
+        };
+
+        let sturdy_ref = self
+            .control
+            .export_sturdy_ref(capability, caveats, expires_at)
+            .map_err(ServiceError::from)?;
+
+        Ok(json!({
+            "token": sturdy_ref.to_token(),
+            "sturdy_ref": sturdy_ref,
+        }))
+    }
+
+    fn cmd_capability_redeem(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let token = params
+            .get("token")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("token"))?;
+        let sturdy_ref = SturdyRef::from_token(token)
+            .map_err(|err| ServiceError::Runtime(RuntimeError::from(err)))?;
+
+        let payload = match params.get("payload").and_then(Value::as_str) {
+            Some(text) => parse_preserves_text(text)?,
+            None => IOValue::symbol("invoke"),
+        };
+
+        let result = self
+            .control
+            .redeem_sturdy_ref(&sturdy_ref, payload)
+            .map_err(ServiceError::from)?;
+
+        let response = json!({
+            "capability": sturdy_ref.capability.to_string(),
+            "summary": io_value_summary(&result, 80),
+            "result_structured": io_value_to_json(&result),
+        });
+        self.sync_if_requested(params, response)
+    }
+
+    fn cmd_approval_list(&mut self) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let approvals: Vec<Value> = self
+            .control
+            .pending_approvals()
+            .into_iter()
+            .map(|pending| {
+                json!({
+                    "approval_id": pending.id.to_string(),
+                    "capability": pending.capability.to_string(),
+                    "kind": pending.kind,
+                    "actor": pending.completion.origin_actor.to_string(),
+                    "tag": pending.completion.tag,
+                    "requested_at": pending.requested_at.to_rfc3339(),
+                    "payload_summary": io_value_summary(&pending.payload, 80),
+                    "payload_structured": io_value_to_json(&pending.payload),
+                })
+            })
+            .collect();
+
+        Ok(json!({ "approvals": approvals }))
+    }
+
+    fn cmd_approve(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let id = params
+            .get("approval_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("approval_id"))?;
+        let id = parse_uuid(id)?;
+
+        self.control.approve(id).map_err(ServiceError::from)?;
+        let response = json!({ "approval_id": id.to_string(), "decision": "approved" });
+        self.sync_if_requested(params, response)
+    }
+
+    fn cmd_deny(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let id = params
+            .get("approval_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("approval_id"))?;
+        let id = parse_uuid(id)?;
+        let reason = params
+            .get("reason")
+            .and_then(Value::as_str)
+            .unwrap_or("denied by operator");
+
+        self.control.deny(id, reason).map_err(ServiceError::from)?;
+        Ok(json!({ "approval_id": id.to_string(), "decision": "denied", "reason": reason }))
+    }
+
+    fn cmd_retract_matching(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let actor = params
+            .get("actor")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("actor"))?;
+        let actor = ActorId::from_uuid(parse_uuid(actor)?);
+        let pattern = params
+            .get("pattern")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("pattern"))?;
+        let pattern = parse_pattern(pattern)?;
+
+        let report = self
+            .control
+            .retract_matching(actor.clone(), pattern)
+            .map_err(ServiceError::from)?;
+
+        let response = json!({
+            "actor": actor.to_string(),
+            "turn_id": report.turn_id.to_string(),
+            "count": report.count,
+            "handles": report
+                .handles
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+        });
+        self.sync_if_requested(params, response)
+    }
+
+    fn cmd_send_message(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let (actor, facet) = self.resolve_message_target(params)?;
+        let payload = value_param(params, "payload")?;
+
+        if params
+            .get("validate")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            self.validate_message(&actor, &facet, &payload)?;
+        }
+
+        let client = self
+            .connection
+            .client
+            .as_deref()
+            .unwrap_or(ANONYMOUS_CLIENT);
+        self.control
+            .admit_client(client)
+            .map_err(ServiceError::from)?;
+        let turn_id = self
+            .control
+            .send_message(actor.clone(), facet.clone(), payload)
+            .map_err(ServiceError::from)?;
+        let response = json!({
+            "actor": actor.to_string(),
+            "facet": facet.0.to_string(),
+            "turn_id": turn_id.to_string(),
+        });
+        self.sync_if_requested(params, response)
+    }
+
+    /// Resolve the `actor`/`facet` parameters of `send_message`.
+    ///
+    /// `actor` may be an actor id, an entity id or the name of an entity type
+    /// with exactly one instance on the current branch. Without `facet`, an
+    /// entity target uses the entity's facet and an actor target its root
+    /// facet.
+    fn resolve_message_target(&self, params: &Value) -> Result<(ActorId, FacetId), ServiceError> {
+        let target = params
+            .get("actor")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("actor"))?;
+        let entities = self.control.list_entities();
+
+        let (actor, entity_facet) = match Uuid::parse_str(target) {
+            Ok(id) => match entities.iter().find(|entity| entity.id == id) {
+                Some(entity) => (entity.actor.clone(), Some(entity.facet.clone())),
+                None => (ActorId::from_uuid(id), None),
+            },
+            Err(_) => {
+                let mut matches = entities
+                    .iter()
+                    .filter(|entity| entity.entity_type == target);
+                let entity = matches.next().ok_or_else(|| {
+                    ServiceError::InvalidParams(format!("no entity of type '{}'", target))
+                })?;
+                if matches.next().is_some() {
+                    return Err(ServiceError::InvalidParams(format!(
+                        "entity type '{}' has several instances; pass an entity id",
+                        target
+                    )));
+                }
+                (entity.actor.clone(), Some(entity.facet.clone()))
+            }
+        };
+
+        let facet = match params.get("facet").and_then(Value::as_str) {
+            Some(facet) => FacetId::from_uuid(parse_uuid(facet)?),
+            None => match entity_facet {
+                Some(facet) => facet,
+                None => self.control.actor_root_facet(&actor).ok_or_else(|| {
+                    ServiceError::InvalidParams(format!("unknown actor {}", actor))
+                })?,
+            },
+        };
+        Ok((actor, facet))
+    }
+
+    /// Check `payload` against the message labels advertised by the entities
+    /// attached to `facet`.
+    fn validate_message(
+        &self,
+        actor: &ActorId,
+        facet: &FacetId,
+        payload: &IOValue,
+    ) -> Result<(), ServiceError> {
+        let label = if payload.is_record() {
+            payload.label().as_symbol().map(|sym| sym.to_string())
+        } else {
+            payload.as_symbol().map(|sym| sym.to_string())
+        };
+
+        let mut accepted = Vec::new();
+        for entity in self.control.list_entities_for_actor(actor) {
+            if &entity.facet != facet {
+                continue;
+            }
+            let descriptor = self
+                .control
+                .describe_entity_type(&entity.entity_type)
+                .map_err(ServiceError::from)?;
+            if descriptor.message_labels.is_empty() {
+                // The entity did not describe its messages; nothing to check.
+                return Ok(());
+            }
+            accepted.extend(descriptor.message_labels);
+        }
+        if accepted.is_empty() {
+            return Err(ServiceError::InvalidParams(
+                "no entity on the target facet to validate against".to_string(),
+            ));
+        }
+
+        match label {
+            Some(label) if accepted.contains(&label) => Ok(()),
+            _ => Err(ServiceError::InvalidParams(format!(
+                "payload {} is not a message the target accepts (expected one of: {})",
+                io_value_summary(payload, 80),
+                accepted.join(", ")
+            ))),
+        }
+    }
+
+    fn cmd_invoke_capability(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let capability = params
+            .get("capability")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("capability"))?;
+        let capability = parse_uuid(capability)?;
+        let payload = params
+            .get("payload")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("payload"))?;
+        let payload = parse_preserves_text(payload)?;
+
+        let result = self
+            .control
+            .invoke_capability(capability, payload)
+            .map_err(ServiceError::from)?;
+        let response = json!({
+            "capability": capability.to_string(),
+            "summary": io_value_summary(&result, 80),
+            "result_structured": io_value_to_json(&result),
+        });
+        self.sync_if_requested(params, response)
+    }
+
+    /// Run the turns a mutating command induced when the client passed
+    /// `sync: true`, adding the resulting branch head to `response`.
+    fn sync_if_requested(
+        &mut self,
+        params: &Value,
+        mut response: Value,
+    ) -> Result<Value, ServiceError> {
+        if params.get("sync").and_then(Value::as_bool).unwrap_or(false) {
+            let head = self.control.sync().map_err(ServiceError::from)?;
+            if let Some(map) = response.as_object_mut() {
+                map.insert("head".to_string(), Value::String(head.to_string()));
+            }
+        }
+        Ok(response)
+    }
+
+    fn cmd_set_actor_paused(
+        &mut self,
+        params: &Value,
+        paused: bool,
+    ) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let actor = params
+            .get("actor")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("actor"))?;
+        let actor = ActorId::from_uuid(parse_uuid(actor)?);
+
+        let turn_id = if paused {
+            self.control.pause_actor(actor.clone())
+        } else {
+            self.control.resume_actor(actor.clone())
+        }
+        .map_err(ServiceError::from)?;
+
+        Ok(json!({
+            "actor": actor.to_string(),
+            "paused": paused,
+            "changed": turn_id.is_some(),
+            "turn_id": turn_id.map(|turn| turn.to_string()),
+        }))
+    }
+
+    fn cmd_flags(&mut self) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        Ok(json!({ "flags": self.control.flags() }))
+    }
+
+    fn cmd_set_flag(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let flag: FeatureFlag = params
+            .get("flag")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("flag"))?
+            .parse()
+            .map_err(|err: RuntimeError| ServiceError::InvalidParams(err.to_string()))?;
+        let enabled = params
+            .get("enabled")
+            .and_then(Value::as_bool)
+            .ok_or_else(|| ServiceError::invalid_param("enabled"))?;
+
+        let turn_id = self
+            .control
+            .set_flag(flag, enabled)
+            .map_err(ServiceError::from)?;
+        Ok(json!({
+            "flag": flag,
+            "enabled": enabled,
+            "turn_id": turn_id.to_string(),
+        }))
+    }
+
+    fn cmd_memory_report(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let limit = params
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map(|limit| limit as usize);
+
+        let mut report = self.control.memory_report();
+        if let Some(limit) = limit {
+            report.actors.truncate(limit);
+        }
+        Ok(serde_json::to_value(report).unwrap_or_default())
+    }
+
+    fn cmd_set_log_level(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let level = params
+            .get("level")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("level"))?;
+        let subsystem = params.get("subsystem").and_then(Value::as_str);
+
+        let config = self
+            .control
+            .set_log_level(subsystem, level)
+            .map_err(ServiceError::from)?;
+        Ok(serde_json::to_value(config).unwrap_or_default())
+    }
+
+    fn cmd_wait_register(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let branch = BranchId::new(
+            params
+                .get("branch")
+                .and_then(Value::as_str)
+                .unwrap_or("main"),
+        );
+        let since = params
+            .get("since")
+            .and_then(Value::as_str)
+            .map(|s| TurnId::new(s.to_string()));
+
+        let registration = self.control.register_turn_wait(branch, since);
+        Ok(serde_json::to_value(registration).unwrap_or_default())
+    }
+
+    fn cmd_checkpoint_push(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let branch = BranchId::new(
+            params
+                .get("branch")
+                .and_then(Value::as_str)
+                .unwrap_or("main"),
+        );
+        let store = params
+            .get("store")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("store"))?;
+        let reference = params
+            .get("reference")
+            .and_then(Value::as_str)
+            .unwrap_or("latest");
+        let token = params
+            .get("token")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        let report = self
+            .control
+            .push_checkpoint(&branch, store, reference, token)
+            .map_err(ServiceError::from)?;
+        Ok(json!({ "branch": branch, "push": report }))
+    }
+
+    fn cmd_branch_archive(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let branch = params
+            .get("branch")
+            .and_then(Value::as_str)
+            .map(BranchId::new)
+            .ok_or_else(|| ServiceError::invalid_param("branch"))?;
+        let dest = params
+            .get("dest")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("dest"))?;
+
+        let archived = self
+            .control
+            .archive_branch(&branch, dest)
+            .map_err(ServiceError::from)?;
+        Ok(json!({ "archived": archived }))
+    }
+
+    fn cmd_branch_unarchive(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let branch = params
+            .get("branch")
+            .and_then(Value::as_str)
+            .map(BranchId::new)
+            .ok_or_else(|| ServiceError::invalid_param("branch"))?;
+
+        let branch = self
+            .control
+            .unarchive_branch(&branch)
+            .map_err(ServiceError::from)?;
+        let head = self
+            .control
+            .branch_head(&branch)
+            .map_err(ServiceError::from)?;
+        Ok(serde_json::to_value(head).unwrap_or_default())
+    }
+
+    fn cmd_delete_branch(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let branch = params
+            .get("branch")
+            .and_then(Value::as_str)
+            .map(BranchId::new)
+            .ok_or_else(|| ServiceError::invalid_param("branch"))?;
+        let force = params
+            .get("force")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        let deleted = self
+            .control
+            .delete_branch(&branch, force)
+            .map_err(ServiceError::from)?;
+        Ok(json!({ "deleted": deleted }))
+    }
+
+    fn cmd_outbox(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let wait_ms = params.get("wait_ms").and_then(Value::as_u64).unwrap_or(0);
+
+        let status = self
+            .control
+            .flush_outbox(Duration::from_millis(wait_ms))
+            .map_err(ServiceError::from)?;
+        let pending: Vec<Value> = status
+            .pending
+            .iter()
+            .map(|pending| {
+                json!({
+                    "delivery": pending.delivery.to_json(),
+                    "attempts": pending.attempts,
+                    "last_error": pending.last_error,
+                    "retry_at": pending.retry_at,
+                    "in_flight": status.in_flight.contains(&pending.delivery.id),
+                })
+            })
+            .collect();
+        Ok(json!({
+            "pending": pending,
+            "acknowledged": status.acknowledged,
+            "channels": status.channels,
+        }))
+    }
+
+    fn cmd_backup(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let dest = params
+            .get("dest")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("dest"))?;
+
+        let manifest = self.control.backup(dest).map_err(ServiceError::from)?;
+        Ok(json!({ "manifest": manifest }))
+    }
+
+    fn cmd_flush(&mut self) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let synced = self.control.flush().map_err(ServiceError::from)?;
+        Ok(json!({
+            "branch": self.control.runtime().current_branch(),
+            "synced_turns": synced,
+            "durability": self.control.runtime().config().durability,
+        }))
+    }
+
+    fn cmd_journal_dictionary(&mut self) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let dictionary = self
+            .control
+            .journal_dictionary()
+            .map_err(ServiceError::from)?;
+        Ok(json!({ "dictionary": dictionary }))
+    }
+
+    fn cmd_journal_dictionary_train(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let mut options = TrainingOptions::default();
+        if let Some(value) = params.get("max_samples") {
+            options.max_samples = value
+                .as_u64()
+                .filter(|samples| *samples > 0)
+                .ok_or_else(|| ServiceError::invalid_param("max_samples"))?
+                as usize;
+        }
+        if let Some(value) = params.get("max_dictionary_size") {
+            options.max_dictionary_size = value
+                .as_u64()
+                .filter(|size| *size > 0)
+                .ok_or_else(|| ServiceError::invalid_param("max_dictionary_size"))?
+                as usize;
+        }
+        if let Some(value) = params.get("level") {
+            options.level = value
+                .as_i64()
+                .and_then(|level| i32::try_from(level).ok())
+                .ok_or_else(|| ServiceError::invalid_param("level"))?;
+        }
+
+        let dictionary = self
+            .control
+            .train_journal_dictionary(&options)
+            .map_err(ServiceError::from)?;
+        Ok(json!({ "dictionary": dictionary }))
     }
 
-    fn cmd_list_branches(&mut self) -> Result<Value, ServiceError> {
+    fn cmd_journal_dictionary_disable(&mut self) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
-        let branches = self.control.list_branches().map_err(ServiceError::from)?;
-        Ok(json!({ "branches": branches }))
+        self.control
+            .disable_journal_compression()
+            .map_err(ServiceError::from)?;
+        Ok(json!({ "disabled": true }))
     }
 
-    fn cmd_history(&mut self, params: &Value) -> Result<Value, ServiceError> {
+    fn cmd_compact(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
-        let branch_name = params
-            .get("branch")
-            .and_then(Value::as_str)
-            .unwrap_or("main");
-        let start = params.get("start").and_then(Value::as_u64).unwrap_or(0) as usize;
-        let limit = params.get("limit").and_then(Value::as_u64).unwrap_or(20) as usize;
+        let branch = match params.get("branch").and_then(Value::as_str) {
+            Some(name) => BranchId::new(name),
+            None => self.control.runtime().current_branch(),
+        };
+        let overrides = ["keep_turns", "keep_days"]
+            .iter()
+            .any(|key| params.get(key).is_some());
+        let mut policy = self.control.runtime().config().retention;
+        for (key, bound) in [
+            ("keep_turns", &mut policy.keep_turns),
+            ("keep_days", &mut policy.keep_days),
+        ] {
+            if let Some(value) = params.get(key) {
+                *bound = Some(
+                    value
+                        .as_u64()
+                        .ok_or_else(|| ServiceError::invalid_param(key))?,
+                );
+            }
+        }
 
-        let branch = BranchId::new(branch_name);
-        let history = self
+        let report = self
             .control
-            .history(&branch, start, limit)
+            .compact_journal(&branch, overrides.then_some(&policy))
             .map_err(ServiceError::from)?;
-
-        Ok(json!({ "turns": history }))
+        Ok(serde_json::to_value(report).unwrap_or_default())
     }
 
-    fn cmd_step(&mut self, params: &Value) -> Result<Value, ServiceError> {
+    fn cmd_prune_snapshots(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
-        if let Some(branch_name) = params.get("branch").and_then(Value::as_str) {
-            self.switch_branch(branch_name)?;
+        let branch = BranchId::new(
+            params
+                .get("branch")
+                .and_then(Value::as_str)
+                .unwrap_or("main"),
+        );
+        let overrides = ["keep_last", "keep_every", "keep_tagged"]
+            .iter()
+            .any(|key| params.get(key).is_some());
+        let mut policy = self.control.runtime().config().snapshot_retention;
+        for (key, bound) in [
+            ("keep_last", &mut policy.keep_last),
+            ("keep_every", &mut policy.keep_every),
+        ] {
+            if let Some(value) = params.get(key) {
+                *bound = Some(
+                    value
+                        .as_u64()
+                        .ok_or_else(|| ServiceError::invalid_param(key))?,
+                );
+            }
+        }
+        if let Some(value) = params.get("keep_tagged") {
+            policy.keep_tagged = value
+                .as_bool()
+                .ok_or_else(|| ServiceError::invalid_param("keep_tagged"))?;
         }
 
-        let count = params.get("count").and_then(Value::as_u64).unwrap_or(1) as usize;
-        let turns = self.control.step(count).map_err(ServiceError::from)?;
-        Ok(json!({ "executed": turns }))
+        let removed = self
+            .control
+            .prune_snapshots(&branch, overrides.then_some(&policy))
+            .map_err(ServiceError::from)?;
+        Ok(json!({
+            "branch": branch,
+            "removed": removed,
+            "kept": self.control.snapshots(&branch),
+        }))
     }
 
-    fn cmd_goto(&mut self, params: &Value) -> Result<Value, ServiceError> {
+    fn cmd_tag_snapshot(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
-        if let Some(branch_name) = params.get("branch").and_then(Value::as_str) {
-            self.switch_branch(branch_name)?;
-        }
-
-        let turn_id_str = params
+        let branch = BranchId::new(
+            params
+                .get("branch")
+                .and_then(Value::as_str)
+                .unwrap_or("main"),
+        );
+        let turn_id = params
             .get("turn_id")
             .and_then(Value::as_str)
             .ok_or_else(|| ServiceError::invalid_param("turn_id"))?;
+        let tag = params
+            .get("tag")
+            .and_then(Value::as_str)
+            .filter(|tag| !tag.is_empty())
+            .ok_or_else(|| ServiceError::invalid_param("tag"))?;
 
-        let turn_id = TurnId::new(turn_id_str.to_string());
-        self.control
-            .goto(turn_id.clone())
+        let added = self
+            .control
+            .tag_snapshot(&branch, &TurnId::new(turn_id.to_string()), tag)
             .map_err(ServiceError::from)?;
-        Ok(json!({ "head": turn_id }))
-    }
-
-    fn cmd_back(&mut self, params: &Value) -> Result<Value, ServiceError> {
-        self.ensure_handshake()?;
-        if let Some(branch_name) = params.get("branch").and_then(Value::as_str) {
-            self.switch_branch(branch_name)?;
-        }
-
-        let count = params.get("count").and_then(Value::as_u64).unwrap_or(1) as usize;
-        let turn_id = self.control.back(count).map_err(ServiceError::from)?;
-        Ok(json!({ "head": turn_id }))
+        Ok(json!({ "added": added }))
     }
 
-    fn cmd_fork(&mut self, params: &Value) -> Result<Value, ServiceError> {
+    fn cmd_checkpoint_pull(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
-
-        let source = params
-            .get("source")
+        let store = params
+            .get("store")
             .and_then(Value::as_str)
-            .unwrap_or("main");
+            .ok_or_else(|| ServiceError::invalid_param("store"))?;
+        let reference = params
+            .get("reference")
+            .and_then(Value::as_str)
+            .unwrap_or("latest");
         let new_branch = params
             .get("new_branch")
             .and_then(Value::as_str)
             .ok_or_else(|| ServiceError::invalid_param("new_branch"))?;
-
-        let base_turn = params
-            .get("from_turn")
+        let token = params
+            .get("token")
             .and_then(Value::as_str)
-            .map(|s| TurnId::new(s.to_string()));
+            .map(str::to_string);
 
         let branch = self
             .control
-            .fork(BranchId::new(source), BranchId::new(new_branch), base_turn)
+            .pull_checkpoint(store, reference, new_branch, token)
             .map_err(ServiceError::from)?;
-
-        Ok(json!({ "branch": branch }))
+        let head = self
+            .control
+            .branch_head(&branch)
+            .map_err(ServiceError::from)?;
+        Ok(serde_json::to_value(head).unwrap_or_default())
     }
 
-    fn cmd_merge(&mut self, params: &Value) -> Result<Value, ServiceError> {
+    fn cmd_fixture_run(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
-
-        let source = params
-            .get("source")
+        let path = params
+            .get("path")
             .and_then(Value::as_str)
-            .ok_or_else(|| ServiceError::invalid_param("source"))?;
-        let target = params
-            .get("target")
+            .ok_or_else(|| ServiceError::invalid_param("path"))?;
+
+        let report = self.control.run_fixture(path).map_err(ServiceError::from)?;
+        Ok(serde_json::to_value(report).unwrap_or_default())
+    }
+
+    fn cmd_wait_poll(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let registration = params
+            .get("registration")
             .and_then(Value::as_str)
-            .ok_or_else(|| ServiceError::invalid_param("target"))?;
+            .ok_or_else(|| ServiceError::invalid_param("registration"))?;
+        let registration = parse_uuid(registration)?;
+        let wait = params
+            .get("wait_ms")
+            .and_then(Value::as_u64)
+            .map(Duration::from_millis)
+            .unwrap_or_default();
 
-        let report = self
+        self.control.drain_pending().map_err(ServiceError::from)?;
+
+        let turn = self
             .control
-            .merge(BranchId::new(source), BranchId::new(target))
+            .poll_turn_wait(registration, wait)
             .map_err(ServiceError::from)?;
-
-        Ok(serde_json::to_value(report).unwrap_or_default())
+        Ok(json!({
+            "registration": registration.to_string(),
+            "turn_id": turn.map(|turn| turn.to_string()),
+        }))
     }
 
-    fn cmd_list_entities(&mut self, params: &Value) -> Result<Value, ServiceError> {
+    fn cmd_wait_cancel(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
-        if let Some(actor_str) = params.get("actor").and_then(Value::as_str) {
-            let actor = ActorId::from_uuid(parse_uuid(actor_str)?);
-            let entities = self.control.list_entities_for_actor(&actor);
-            Ok(json!({ "entities": entities }))
-        } else {
-            let entities = self.control.list_entities();
-            Ok(json!({ "entities": entities }))
-        }
+        let registration = params
+            .get("registration")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("registration"))?;
+        let registration = parse_uuid(registration)?;
+
+        Ok(json!({
+            "registration": registration.to_string(),
+            "cancelled": self.control.cancel_turn_wait(registration),
+        }))
     }
 
-    fn cmd_list_capabilities(&mut self, params: &Value) -> Result<Value, ServiceError> {
+    fn cmd_service_stats(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
-        if let Some(actor_str) = params.get("actor").and_then(Value::as_str) {
-            let actor = ActorId::from_uuid(parse_uuid(actor_str)?);
-            let capabilities = self.control.list_capabilities_for_actor(&actor);
-            Ok(json!({ "capabilities": capabilities }))
-        } else {
-            let capabilities = self.control.list_capabilities();
-            Ok(json!({ "capabilities": capabilities }))
-        }
+        let limit = params
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map(|limit| limit as usize);
+        let mut summary = self.stats.summary(limit);
+        summary["rate_limits"] = json!({
+            "limits": self.control.ingress_limits(),
+            "sources": self.control.rate_limit_stats(),
+        });
+        Ok(summary)
     }
 
     fn cmd_workspace_entries(&mut self) -> Result<Value, ServiceError> {
@@ -360,6 +1977,70 @@ impl<'a, W: Write> Session<'a, W> {
         Ok(json!({ "entries": entries }))
     }
 
+    fn cmd_workspace_restore(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let path = params
+            .get("path")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("path"))?;
+        let turn_id = params
+            .get("turn_id")
+            .and_then(Value::as_str)
+            .map(|s| TurnId::new(s.to_string()))
+            .ok_or_else(|| ServiceError::invalid_param("turn_id"))?;
+        let handle = self
+            .workspace_handle()
+            .ok_or_else(|| ServiceError::Protocol("workspace entity not registered".into()))?;
+
+        let backup = codebase::restore_file(self.control, &handle, path, &turn_id)
+            .map_err(ServiceError::from)?;
+        Ok(json!({
+            "path": backup.path,
+            "turn_id": backup.turn_id.to_string(),
+            "existed": backup.previous.is_some(),
+            "bytes": backup.previous.as_ref().map(Vec::len).unwrap_or(0),
+        }))
+    }
+
+    fn cmd_workspace_offers(&mut self) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let handle = self
+            .workspace_handle()
+            .ok_or_else(|| ServiceError::Protocol("workspace entity not registered".into()))?;
+        let offers = codebase::list_capability_offers(self.control, &handle);
+        Ok(json!({ "offers": offers }))
+    }
+
+    fn cmd_workspace_offer_settle(
+        &mut self,
+        params: &Value,
+        accept: bool,
+    ) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let offer_id = params
+            .get("offer_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("offer_id"))?;
+        let offer_id = parse_uuid(offer_id)?;
+        let handle = self
+            .workspace_handle()
+            .ok_or_else(|| ServiceError::Protocol("workspace entity not registered".into()))?;
+
+        if accept {
+            let capability = codebase::accept_capability_offer(self.control, &handle, offer_id)
+                .map_err(ServiceError::from)?;
+            Ok(json!({
+                "offer_id": offer_id.to_string(),
+                "accepted": true,
+                "capability": capability.to_string(),
+            }))
+        } else {
+            codebase::decline_capability_offer(self.control, &handle, offer_id)
+                .map_err(ServiceError::from)?;
+            Ok(json!({ "offer_id": offer_id.to_string(), "accepted": false }))
+        }
+    }
+
     fn cmd_transcript_show(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
         let request_id = params
@@ -367,54 +2048,50 @@ impl<'a, W: Write> Session<'a, W> {
             .and_then(Value::as_str)
             .ok_or_else(|| ServiceError::invalid_param("request_id"))?;
 
-        if let Some(branch_name) = params.get("branch").and_then(Value::as_str) {
-            self.switch_branch(branch_name)?;
-        }
-
         let provided_branch = params
             .get("branch")
             .and_then(Value::as_str)
             .map(|s| BranchId::new(s.to_string()));
         let branch = if let Some(branch_id) = provided_branch {
             branch_id
-        } else if let Some(cursor) = self.pending_requests.get(request_id) {
+        } else if let Some(cursor) = self.cursors().get(request_id) {
             cursor.branch.clone()
         } else {
             BranchId::main()
         };
-        self.switch_branch(&branch.0)?;
 
         let limit = params.get("limit").and_then(Value::as_u64).unwrap_or(20) as usize;
 
         self.control.drain_pending().map_err(ServiceError::from)?;
 
-        let existing_cursor = self.pending_requests.get(request_id);
+        let existing_cursor = self.cursors().get(request_id).cloned();
         let (entries, mut cursor) = transcript::transcript_entries(
             &self.control,
             request_id,
-            existing_cursor,
+            existing_cursor.as_ref(),
             Some(&branch),
             limit,
         )
         .map_err(ServiceError::from)?;
 
         cursor.branch = branch.clone();
-        if let Some(existing) = self.pending_requests.get(request_id) {
+        if let Some(existing) = self.cursors().get(request_id) {
             if cursor.actor.is_none() {
                 cursor.actor = existing.actor.clone();
             }
             cursor.last_turn = existing.last_turn.clone();
         }
 
-        if let Some(existing) = self.pending_requests.get_mut(request_id) {
+        if let Some(existing) = self.cursors().get_mut(request_id) {
             existing.branch = cursor.branch.clone();
             if cursor.actor.is_some() {
                 existing.actor = cursor.actor.clone();
             }
         } else {
-            self.pending_requests
+            self.cursors()
                 .insert(request_id.to_string(), cursor.clone());
         }
+        self.save_session()?;
 
         let entries: Vec<Value> = entries
             .into_iter()
@@ -474,17 +2151,16 @@ impl<'a, W: Write> Session<'a, W> {
             .map(|s| BranchId::new(s.to_string()));
         let branch = if let Some(branch_id) = provided_branch {
             branch_id
-        } else if let Some(cursor) = self.pending_requests.get(request_id) {
+        } else if let Some(cursor) = self.cursors().get(request_id) {
             cursor.branch.clone()
         } else {
             BranchId::main()
         };
-        self.switch_branch(&branch.0)?;
 
         let since_turn = if let Some(s) = params.get("since").and_then(Value::as_str) {
             Some(TurnId::new(s.to_string()))
         } else {
-            self.pending_requests
+            self.cursors()
                 .get(request_id)
                 .map(|cursor| cursor.last_turn.clone())
         };
@@ -498,11 +2174,11 @@ impl<'a, W: Write> Session<'a, W> {
 
         self.control.drain_pending().map_err(ServiceError::from)?;
 
-        let existing_cursor = self.pending_requests.get(request_id);
+        let existing_cursor = self.cursors().get(request_id).cloned();
         let (mut cursor, chunk) = transcript::transcript_events(
             &mut self.control,
             request_id,
-            existing_cursor,
+            existing_cursor.as_ref(),
             Some(&branch),
             since_turn.as_ref(),
             limit,
@@ -511,12 +2187,13 @@ impl<'a, W: Write> Session<'a, W> {
         .map_err(ServiceError::from)?;
 
         cursor.branch = branch.clone();
-        if let Some(existing) = self.pending_requests.get_mut(request_id) {
+        if let Some(existing) = self.cursors().get_mut(request_id) {
             *existing = cursor.clone();
         } else {
-            self.pending_requests
+            self.cursors()
                 .insert(request_id.to_string(), cursor.clone());
         }
+        self.save_session()?;
 
         let events: Vec<Value> = transcript::event_batches_payload(&chunk);
 
@@ -538,6 +2215,101 @@ impl<'a, W: Write> Session<'a, W> {
         Ok(json!({ "reactions": serialized }))
     }
 
+    fn cmd_reaction_register(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let actor = params
+            .get("actor")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("actor"))?;
+        let actor = ActorId::from_uuid(parse_uuid(actor)?);
+        let facet = params
+            .get("facet")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("facet"))?;
+        let facet = FacetId::from_uuid(parse_uuid(facet)?);
+        let pattern = pattern_definition(params, facet)?;
+
+        let effect = params
+            .get("effect")
+            .filter(|effect| !effect.is_null())
+            .ok_or_else(|| ServiceError::invalid_param("effect"))?;
+        let effect: ReactionEffect = serde_json::from_value(effect.clone())
+            .map_err(|err| ServiceError::invalid_field("effect", err))?;
+
+        let mut definition = ReactionDefinition::new(pattern, effect);
+        if let Some(priority) = params.get("priority").filter(|value| !value.is_null()) {
+            let priority = priority
+                .as_i64()
+                .and_then(|priority| i32::try_from(priority).ok())
+                .ok_or_else(|| {
+                    ServiceError::invalid_field("priority", "expected a 32-bit integer")
+                })?;
+            definition = definition.with_priority(priority);
+        }
+        if params
+            .get("consume")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            definition = definition.consuming();
+        }
+
+        let reaction_id = self
+            .control
+            .register_reaction(actor.clone(), definition.clone())
+            .map_err(ServiceError::from)?;
+        let definition = serde_json::to_value(&definition)
+            .map_err(|err| ServiceError::Protocol(err.to_string()))?;
+        Ok(json!({
+            "reaction_id": reaction_id.to_string(),
+            "actor": actor.to_string(),
+            "definition": definition,
+        }))
+    }
+
+    fn cmd_reaction_unregister(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let reaction_id = params
+            .get("reaction_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("reaction_id"))?;
+        let reaction_id = parse_uuid(reaction_id)?;
+        let removed = self
+            .control
+            .unregister_reaction(reaction_id)
+            .map_err(ServiceError::from)?;
+        Ok(json!({
+            "reaction_id": reaction_id.to_string(),
+            "removed": removed,
+        }))
+    }
+
+    fn cmd_pattern_register(&mut self, params: &Value) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        let entity = params
+            .get("entity")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ServiceError::invalid_param("entity"))?;
+        let entity = parse_uuid(entity)?;
+        let facet = self
+            .control
+            .list_entities()
+            .into_iter()
+            .find(|info| info.id == entity)
+            .map(|info| info.facet)
+            .ok_or_else(|| ServiceError::invalid_field("entity", "no such entity"))?;
+        let pattern = pattern_definition(params, facet)?;
+
+        let pattern_id = self
+            .control
+            .register_pattern_for_entity(entity, pattern)
+            .map_err(ServiceError::from)?;
+        Ok(json!({
+            "pattern_id": pattern_id.to_string(),
+            "entity": entity.to_string(),
+        }))
+    }
+
     fn cmd_dataspace_assertions(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
 
@@ -556,6 +2328,12 @@ impl<'a, W: Write> Session<'a, W> {
             .get("request_id")
             .and_then(Value::as_str)
             .map(|s| s.to_string());
+        let namespace_filter = params.get("namespace").and_then(Value::as_str);
+        let pattern_filter = params
+            .get("pattern")
+            .and_then(Value::as_str)
+            .map(parse_pattern)
+            .transpose()?;
         let limit = params
             .get("limit")
             .and_then(Value::as_u64)
@@ -563,7 +2341,12 @@ impl<'a, W: Write> Session<'a, W> {
 
         self.control.drain_pending().map_err(ServiceError::from)?;
 
-        let mut assertions = self.control.list_assertions(actor_filter.as_ref());
+        let mut assertions = match namespace_filter {
+            Some(namespace) => self
+                .control
+                .list_assertions_in(namespace, actor_filter.as_ref()),
+            None => self.control.list_assertions(actor_filter.as_ref()),
+        };
 
         if let Some(label) = &label_filter {
             assertions.retain(|info| assertion_matches_label(&info.value, label));
@@ -573,10 +2356,19 @@ impl<'a, W: Write> Session<'a, W> {
             assertions.retain(|info| assertion_matches_request_id(&info.value, request_id));
         }
 
+        if let Some(pattern) = &pattern_filter {
+            assertions.retain(|info| matches_pattern(pattern, &info.value));
+        }
+
         if let Some(limit) = limit {
             assertions.truncate(limit);
         }
 
+        let origins = self
+            .control
+            .assertion_origins()
+            .map_err(ServiceError::from)?;
+
         let mut actor_cache: HashMap<ActorId, Value> = HashMap::new();
         let mut assertions_payload = Vec::new();
         for assertion in assertions {
@@ -593,6 +2385,9 @@ impl<'a, W: Write> Session<'a, W> {
                 "handle".to_string(),
                 Value::String(assertion.handle.to_string()),
             );
+            if let Some(namespace) = &assertion.namespace {
+                entry.insert("namespace".to_string(), Value::String(namespace.clone()));
+            }
             entry.insert(
                 "summary".to_string(),
                 Value::String(io_value_summary(&assertion.value, 80)),
@@ -601,6 +2396,19 @@ impl<'a, W: Write> Session<'a, W> {
                 "value_structured".to_string(),
                 io_value_to_json(&assertion.value),
             );
+            if let Some(origin) = origins
+                .get(&assertion.handle)
+                .filter(|origin| origin.merge_turn.is_some())
+            {
+                entry.insert(
+                    "origin".to_string(),
+                    json!({
+                        "branch": origin.branch.0,
+                        "turn": origin.turn.to_string(),
+                        "merge_turn": origin.merge_turn.as_ref().map(|t| t.to_string()),
+                    }),
+                );
+            }
 
             assertions_payload.push(Value::Object(entry));
         }
@@ -608,6 +2416,11 @@ impl<'a, W: Write> Session<'a, W> {
         Ok(json!({ "assertions": assertions_payload }))
     }
 
+    fn cmd_assertion_schemas(&mut self) -> Result<Value, ServiceError> {
+        self.ensure_handshake()?;
+        Ok(json!({ "schemas": self.control.assertion_schemas() }))
+    }
+
     fn cmd_dataspace_events(&mut self, params: &Value) -> Result<Value, ServiceError> {
         self.ensure_handshake()?;
 
@@ -617,10 +2430,25 @@ impl<'a, W: Write> Session<'a, W> {
             .unwrap_or("main");
         let branch = BranchId::new(branch_name);
 
-        let since_turn = params
-            .get("since")
-            .and_then(Value::as_str)
-            .map(|s| TurnId::new(s.to_string()));
+        // Named subscriptions resume from the last turn delivered to the session
+        let subscription = match params.get("subscription").and_then(Value::as_str) {
+            Some(name) => {
+                let token = self.connection.token.clone().ok_or_else(|| {
+                    ServiceError::InvalidParams(
+                        "subscriptions require a session token in the handshake".to_string(),
+                    )
+                })?;
+                Some((token, name.to_string()))
+            }
+            None => None,
+        };
+
+        let since_turn = match params.get("since").and_then(Value::as_str) {
+            Some(s) => Some(TurnId::new(s.to_string())),
+            None => subscription.as_ref().and_then(|(token, name)| {
+                self.sessions.state(token).subscriptions.get(name).cloned()
+            }),
+        };
 
         let limit = params
             .get("limit")
@@ -643,6 +2471,17 @@ impl<'a, W: Write> Session<'a, W> {
             filter.request_id = Some(request_id.to_string());
         }
 
+        if let Some(namespace) = params.get("namespace").and_then(Value::as_str) {
+            filter.namespace = Some(namespace.to_string());
+        }
+
+        if let Some(schema) = params.get("schema").and_then(Value::as_str) {
+            SchemaRegistry::init()
+                .resolve_assertion_schema(schema)
+                .map_err(|err| ServiceError::InvalidParams(err.to_string()))?;
+            filter.schema = Some(schema.to_string());
+        }
+
         if let Some(types) = params.get("event_types").and_then(Value::as_array) {
             filter.include_asserts = false;
             filter.include_retracts = false;
@@ -669,6 +2508,14 @@ impl<'a, W: Write> Session<'a, W> {
             .assertion_events_since(&branch, since_turn.as_ref(), limit, filter, wait_duration)
             .map_err(ServiceError::from)?;
 
+        if let (Some((token, name)), Some(next)) = (&subscription, &chunk.next_cursor) {
+            self.sessions
+                .state(token)
+                .subscriptions
+                .insert(name.clone(), next.clone());
+            self.save_session()?;
+        }
+
         let mut actor_cache: HashMap<ActorId, Value> = HashMap::new();
         let mut events_payload = Vec::new();
         for batch in &chunk.events {
@@ -685,6 +2532,7 @@ impl<'a, W: Write> Session<'a, W> {
                 "timestamp".to_string(),
                 Value::String(batch.timestamp.to_rfc3339()),
             );
+            batch_obj.insert("logical_time".to_string(), json!(batch.logical_time));
 
             let mut event_values = Vec::new();
             for event in &batch.events {
@@ -698,6 +2546,9 @@ impl<'a, W: Write> Session<'a, W> {
                     "handle".to_string(),
                     Value::String(event.handle.to_string()),
                 );
+                if let Some(namespace) = &event.namespace {
+                    event_obj.insert("namespace".to_string(), Value::String(namespace.clone()));
+                }
 
                 if let Some(value) = event.value.as_ref() {
                     event_obj.insert("value_structured".to_string(), io_value_to_json(value));
@@ -812,15 +2663,29 @@ fn assertion_matches_request_id(value: &IOValue, request_id: &str) -> bool {
 enum ServiceError {
     Parse(String),
     InvalidParams(String),
+    InvalidField {
+        field: String,
+        reason: String,
+    },
     Unsupported(String),
     Protocol(String),
     Runtime(RuntimeError),
+    /// A registered command failed
+    Command(String),
 }
 
 impl ServiceError {
     fn invalid_param(name: &str) -> Self {
         ServiceError::InvalidParams(format!("missing or invalid parameter: {}", name))
     }
+
+    /// A present parameter whose value was rejected, reported with the field name.
+    fn invalid_field(field: &str, reason: impl std::fmt::Display) -> Self {
+        ServiceError::InvalidField {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        }
+    }
 }
 
 impl From<RuntimeError> for ServiceError {
@@ -829,12 +2694,25 @@ impl From<RuntimeError> for ServiceError {
     }
 }
 
+impl From<CommandError> for ServiceError {
+    fn from(err: CommandError) -> Self {
+        match err {
+            CommandError::InvalidParams(message) => ServiceError::InvalidParams(message),
+            CommandError::Runtime(err) => ServiceError::Runtime(err),
+            CommandError::Failed(message) => ServiceError::Command(message),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 struct RequestEnvelope {
     id: Value,
     command: String,
     #[serde(default)]
     params: Value,
+    /// Key under which a session caches the result for retries
+    #[serde(default)]
+    idempotency_key: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -885,6 +2763,15 @@ impl From<ServiceError> for ErrorEnvelope {
                 message,
                 details: None,
             },
+            ServiceError::InvalidField { field, reason } => ErrorEnvelope {
+                code: "invalid_params".into(),
+                message: format!("invalid {}: {}", field, reason),
+                details: Some(json!({
+                    "category": "validation",
+                    "field": field,
+                    "reason": reason,
+                })),
+            },
             ServiceError::Unsupported(command) => ErrorEnvelope {
                 code: "unsupported_command".into(),
                 message: format!("Command '{command}' is not supported yet"),
@@ -895,6 +2782,11 @@ impl From<ServiceError> for ErrorEnvelope {
                 message,
                 details: None,
             },
+            ServiceError::Command(message) => ErrorEnvelope {
+                code: "command_failed".into(),
+                message,
+                details: None,
+            },
             ServiceError::Runtime(err) => {
                 let message = err.to_string();
                 let details = match &err {
@@ -905,6 +2797,24 @@ impl From<ServiceError> for ErrorEnvelope {
                             CapabilityError::Denied(id, detail) => {
                                 ("Denied", Some(id), Some(detail.as_str()))
                             }
+                            CapabilityError::MalformedSturdyRef(detail) => {
+                                ("MalformedSturdyRef", None, Some(detail.as_str()))
+                            }
+                            CapabilityError::InvalidSignature(id) => {
+                                ("InvalidSignature", Some(id), None)
+                            }
+                            CapabilityError::Expired(id, deadline) => {
+                                ("Expired", Some(id), Some(deadline.as_str()))
+                            }
+                            CapabilityError::CaveatRejected(id) => {
+                                ("CaveatRejected", Some(id), None)
+                            }
+                            CapabilityError::ApprovalRequired(id, kind) => {
+                                ("ApprovalRequired", Some(id), Some(kind.as_str()))
+                            }
+                            CapabilityError::ApprovalNotFound(id) => {
+                                ("ApprovalNotFound", Some(id), None)
+                            }
                         };
                         Some(json!({
                             "category": "capability",
@@ -913,11 +2823,31 @@ impl From<ServiceError> for ErrorEnvelope {
                             "reason": reason,
                         }))
                     }
+                    RuntimeError::PatternSyntax { offset, message } => Some(json!({
+                        "category": "pattern",
+                        "offset": offset,
+                        "reason": message,
+                    })),
+                    RuntimeError::RateLimited {
+                        scope,
+                        key,
+                        retry_after_ms,
+                    } => Some(json!({
+                        "category": "rate_limit",
+                        "scope": scope,
+                        "key": key,
+                        "retry_after_ms": retry_after_ms,
+                    })),
                     _ => None,
                 };
 
+                let code = match &err {
+                    RuntimeError::RateLimited { .. } => "rate_limited",
+                    RuntimeError::PatternSyntax { .. } => "invalid_params",
+                    _ => "runtime_error",
+                };
                 ErrorEnvelope {
-                    code: "runtime_error".into(),
+                    code: code.into(),
                     message,
                     details,
                 }
@@ -926,6 +2856,80 @@ impl From<ServiceError> for ErrorEnvelope {
     }
 }
 
+fn parse_preserves_text(text: &str) -> Result<IOValue, ServiceError> {
+    text.parse().map_err(|err| {
+        ServiceError::InvalidParams(format!("invalid preserves value '{}': {}", text, err))
+    })
+}
+
+/// The `pattern` parameter in the textual pattern syntax, subscribed on
+/// `facet` in the optional `namespace`; `scope` is `actor` (default) or
+/// `dataspace`.
+fn pattern_definition(params: &Value, facet: FacetId) -> Result<Pattern, ServiceError> {
+    let text = params
+        .get("pattern")
+        .and_then(Value::as_str)
+        .ok_or_else(|| ServiceError::invalid_param("pattern"))?;
+    let mut pattern = Pattern {
+        id: Uuid::new_v4(),
+        pattern: parse_pattern(text)?,
+        facet,
+        namespace: None,
+        scope: PatternScope::Actor,
+    };
+    if let Some(namespace) = params.get("namespace").and_then(Value::as_str) {
+        pattern = pattern.in_namespace(namespace);
+    }
+    match params.get("scope").and_then(Value::as_str) {
+        None | Some("actor") => {}
+        Some("dataspace") => pattern = pattern.dataspace_wide(),
+        Some(other) => {
+            return Err(ServiceError::invalid_field(
+                "scope",
+                format!("expected 'actor' or 'dataspace', got '{}'", other),
+            ));
+        }
+    }
+    Ok(pattern)
+}
+
+/// A value parameter given either as Preserves text or as JSON; see
+/// [`json_to_io_value`] for the JSON forms.
+fn value_param(params: &Value, name: &str) -> Result<IOValue, ServiceError> {
+    match params.get(name) {
+        Some(Value::String(text)) => parse_preserves_text(text),
+        Some(value) if !value.is_null() => json_to_io_value(value)
+            .map_err(|err| ServiceError::InvalidParams(format!("invalid {}: {}", name, err))),
+        _ => Err(ServiceError::invalid_param(name)),
+    }
+}
+
+/// How much of each turn history commands include (`detail`, default `summary`).
+fn history_detail(params: &Value) -> Result<HistoryDetail, ServiceError> {
+    match params.get("detail").and_then(Value::as_str) {
+        Some(detail) => detail.parse().map_err(ServiceError::InvalidParams),
+        None => Ok(HistoryDetail::Summary),
+    }
+}
+
+/// Cursor pagination parameters, present when the request asks for `cursor` or `direction`.
+fn page_params(params: &Value) -> Result<Option<(Option<&str>, CursorDirection)>, ServiceError> {
+    let cursor = params.get("cursor").and_then(Value::as_str);
+    let direction = match params.get("direction").and_then(Value::as_str) {
+        None if cursor.is_none() && !params.get("cursor").is_some_and(Value::is_null) => {
+            return Ok(None);
+        }
+        None | Some("forward") => CursorDirection::Forward,
+        Some("backward") => CursorDirection::Backward,
+        Some(other) => {
+            return Err(ServiceError::InvalidParams(format!(
+                "direction must be 'forward' or 'backward', got '{other}'"
+            )));
+        }
+    };
+    Ok(Some((cursor, direction)))
+}
+
 fn parse_uuid(value: &str) -> Result<Uuid, ServiceError> {
     Uuid::parse_str(value)
         .map_err(|err| ServiceError::InvalidParams(format!("invalid UUID '{}': {}", value, err)))
@@ -0,0 +1,123 @@
+//! Client sessions that survive reconnects
+//!
+//! A client that passes a `session` token in its handshake is bound to the
+//! state stored under that token: transcript cursors, named dataspace event
+//! subscriptions, and the results of requests it sent with an
+//! `idempotency_key`. The state is written to `meta/sessions/` whenever it
+//! changes, so a client reconnecting with the same token — even to a
+//! restarted daemon — resumes where it left off, and a retried request is
+//! answered from the cache instead of running twice. Tokens are only stored
+//! as digests.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::codebase::transcript::TranscriptCursor;
+use crate::runtime::storage::Storage;
+use crate::runtime::turn::TurnId;
+
+/// Results kept per session for idempotent retries.
+const CACHED_RESULTS: usize = 128;
+
+/// State a client resumes by presenting its session token.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct SessionState {
+    /// Client name given in the most recent handshake
+    pub client: Option<String>,
+    /// Transcript cursors by request id
+    pub transcript_cursors: HashMap<String, TranscriptCursor>,
+    /// Last delivered turn of each named dataspace event subscription
+    pub subscriptions: BTreeMap<String, TurnId>,
+    /// Results of idempotent requests, oldest first
+    results: VecDeque<CachedResult>,
+    /// When the session last changed
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedResult {
+    key: String,
+    command: String,
+    result: Value,
+}
+
+impl SessionState {
+    /// Result of an earlier `command` sent with idempotency `key`.
+    pub fn cached_result(&self, key: &str, command: &str) -> Option<&Value> {
+        self.results
+            .iter()
+            .find(|cached| cached.key == key && cached.command == command)
+            .map(|cached| &cached.result)
+    }
+
+    /// Remember `result` for retries of `command` with `key`.
+    pub fn cache_result(&mut self, key: &str, command: &str, result: Value) {
+        self.results.retain(|cached| cached.key != key);
+        if self.results.len() == CACHED_RESULTS {
+            self.results.pop_front();
+        }
+        self.results.push_back(CachedResult {
+            key: key.to_string(),
+            command: command.to_string(),
+            result,
+        });
+    }
+}
+
+/// Session state by token, backed by one file per session.
+pub(crate) struct SessionStore {
+    dir: PathBuf,
+    sessions: HashMap<String, SessionState>,
+}
+
+impl SessionStore {
+    /// Store keeping sessions under the `meta/sessions` directory of `root`.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            dir: Storage::new(root.to_path_buf()).meta_dir().join("sessions"),
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// Load the session for `token`, creating it if unknown.
+    ///
+    /// Returns whether an existing session was resumed.
+    pub fn open(&mut self, token: &str) -> io::Result<bool> {
+        if self.sessions.contains_key(token) {
+            return Ok(true);
+        }
+        let (state, resumed) = match std::fs::read(self.path(token)) {
+            Ok(bytes) => (serde_json::from_slice(&bytes)?, true),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (SessionState::default(), false),
+            Err(err) => return Err(err),
+        };
+        self.sessions.insert(token.to_string(), state);
+        Ok(resumed)
+    }
+
+    /// State of the opened session `token`.
+    pub fn state(&mut self, token: &str) -> &mut SessionState {
+        self.sessions.entry(token.to_string()).or_default()
+    }
+
+    /// Persist the session `token`.
+    pub fn save(&mut self, token: &str) -> io::Result<()> {
+        let state = self.state(token);
+        state.updated_at = Some(Utc::now());
+        let bytes = serde_json::to_vec_pretty(state)?;
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(token);
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, bytes)?;
+        std::fs::rename(temp, path)
+    }
+
+    fn path(&self, token: &str) -> PathBuf {
+        let digest = blake3::hash(token.as_bytes());
+        self.dir.join(format!("{}.json", &digest.to_hex()[..32]))
+    }
+}
@@ -0,0 +1,178 @@
+//! Per-command timing for the control plane
+//!
+//! Every dispatched command is timed and kept in a fixed-size ring of recent
+//! samples, alongside running totals per command name. Commands slower than
+//! [`SLOW_COMMAND`] are also logged, so clients issuing expensive requests
+//! such as long history scans show up without polling `service_stats`.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+use tracing::warn;
+
+/// Number of recent commands kept.
+const RECENT_COMMANDS: usize = 256;
+
+/// Commands taking at least this long are logged.
+pub const SLOW_COMMAND: Duration = Duration::from_millis(250);
+
+/// One timed command.
+#[derive(Debug, Clone, Serialize)]
+struct CommandSample {
+    command: String,
+    /// Short digest of the request parameters, so repeated calls with the
+    /// same arguments can be told apart without echoing their contents
+    params_digest: String,
+    started_at: DateTime<Utc>,
+    duration_ms: f64,
+    /// `ok` or the error code returned to the client
+    result: String,
+}
+
+/// Running totals for one command name.
+#[derive(Debug, Clone, Default, Serialize)]
+struct CommandTotals {
+    count: u64,
+    errors: u64,
+    total_ms: f64,
+    max_ms: f64,
+}
+
+/// Timing samples collected by a [`Service`](super::Service).
+#[derive(Debug, Default)]
+pub struct CommandStats {
+    recent: VecDeque<CommandSample>,
+    totals: BTreeMap<String, CommandTotals>,
+}
+
+impl CommandStats {
+    /// Record a command that started at `started_at` and took `duration`.
+    pub fn record(
+        &mut self,
+        command: &str,
+        params: &Value,
+        started_at: DateTime<Utc>,
+        duration: Duration,
+        result: &str,
+    ) {
+        let duration_ms = duration.as_secs_f64() * 1000.0;
+        let params_digest = digest(params);
+        if duration >= SLOW_COMMAND {
+            warn!(
+                command,
+                params_digest = %params_digest,
+                duration_ms,
+                result,
+                "slow control-plane command"
+            );
+        }
+
+        let totals = self.totals.entry(command.to_string()).or_default();
+        totals.count += 1;
+        if result != "ok" {
+            totals.errors += 1;
+        }
+        totals.total_ms += duration_ms;
+        totals.max_ms = totals.max_ms.max(duration_ms);
+
+        if self.recent.len() == RECENT_COMMANDS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(CommandSample {
+            command: command.to_string(),
+            params_digest,
+            started_at,
+            duration_ms,
+            result: result.to_string(),
+        });
+    }
+
+    /// Summary with up to `limit` recent commands (newest first) and the
+    /// per-command totals, most expensive first.
+    pub fn summary(&self, limit: Option<usize>) -> Value {
+        let recent: Vec<&CommandSample> = self
+            .recent
+            .iter()
+            .rev()
+            .take(limit.unwrap_or(RECENT_COMMANDS))
+            .collect();
+
+        let mut commands: Vec<Value> = self
+            .totals
+            .iter()
+            .map(|(command, totals)| {
+                json!({
+                    "command": command,
+                    "count": totals.count,
+                    "errors": totals.errors,
+                    "total_ms": totals.total_ms,
+                    "mean_ms": totals.total_ms / totals.count as f64,
+                    "max_ms": totals.max_ms,
+                })
+            })
+            .collect();
+        commands.sort_by(|a, b| {
+            b["total_ms"]
+                .as_f64()
+                .partial_cmp(&a["total_ms"].as_f64())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        json!({
+            "recent": recent,
+            "commands": commands,
+            "capacity": RECENT_COMMANDS,
+            "slow_threshold_ms": SLOW_COMMAND.as_millis() as u64,
+        })
+    }
+}
+
+fn digest(params: &Value) -> String {
+    let encoded = serde_json::to_vec(params).unwrap_or_default();
+    blake3::hash(&encoded).to_hex()[..16].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_keeps_newest_samples_and_totals() {
+        let mut stats = CommandStats::default();
+        for i in 0..(RECENT_COMMANDS + 4) {
+            let result = if i % 2 == 0 { "ok" } else { "invalid_params" };
+            stats.record(
+                "history",
+                &json!({ "start": i }),
+                Utc::now(),
+                Duration::from_millis(i as u64),
+                result,
+            );
+        }
+        stats.record("status", &json!({}), Utc::now(), Duration::ZERO, "ok");
+
+        let summary = stats.summary(Some(2));
+        let recent = summary["recent"].as_array().unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0]["command"], "status");
+        assert_eq!(recent[1]["duration_ms"], (RECENT_COMMANDS + 3) as f64);
+        assert_eq!(stats.recent.len(), RECENT_COMMANDS);
+
+        let commands = summary["commands"].as_array().unwrap();
+        assert_eq!(commands[0]["command"], "history");
+        assert_eq!(commands[0]["count"], (RECENT_COMMANDS + 4) as u64);
+        assert_eq!(commands[0]["errors"], (RECENT_COMMANDS / 2 + 2) as u64);
+        assert_eq!(commands[1]["count"], 1);
+
+        assert_eq!(
+            digest(&json!({ "start": 1 })),
+            digest(&json!({ "start": 1 }))
+        );
+        assert_ne!(
+            digest(&json!({ "start": 1 })),
+            digest(&json!({ "start": 2 }))
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Canonical forms for floats and timestamps in preserves values.
+//!
+//! Floats and timestamps have several spellings for the same quantity: NaNs
+//! carry platform-dependent payload bits, arithmetic may produce `-0.0` where
+//! another platform yields `0.0`, and a timestamp can be written with an
+//! offset, a `Z`, or a varying number of fractional digits. Values that differ
+//! only in these spellings must hash and compare alike, so state hashes and
+//! merges agree across platforms. The rules are:
+//!
+//! - every NaN is the quiet NaN `0x7ff8000000000000`, and `-0.0` is `0.0`;
+//! - a timestamp is an RFC 3339 string in UTC with nine fractional digits and
+//!   a `Z` suffix (`2024-05-01T12:00:00.000000000Z`).
+//!
+//! Built-in entities write timestamps with [`timestamp_value`].
+//! [`canonical_encoding`](super::io_value::canonical_encoding) applies the rules
+//! while encoding for hashes and comparisons only; recorded values, including
+//! those in journals written before the rules existed, are never rewritten.
+//! Any string that parses as an RFC 3339 timestamp is treated as one; it keeps
+//! denoting the same instant.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use preserves::IOValue;
+use preserves::types::{AtomClass, CompoundClass, ValueClass};
+
+/// Bit pattern every NaN is canonicalized to
+const CANONICAL_NAN_BITS: u64 = 0x7ff8_0000_0000_0000;
+
+/// Length of a canonical timestamp such as `2024-05-01T12:00:00.000000000Z`
+const CANONICAL_TIMESTAMP_LEN: usize = 30;
+
+/// Canonical form of `value`.
+pub fn canonical_f64(value: f64) -> f64 {
+    if value.is_nan() {
+        f64::from_bits(CANONICAL_NAN_BITS)
+    } else if value == 0.0 {
+        0.0
+    } else {
+        value
+    }
+}
+
+/// Canonical text of `timestamp`.
+pub fn canonical_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+/// Canonical float value.
+pub fn float_value(value: f64) -> IOValue {
+    IOValue::new(canonical_f64(value))
+}
+
+/// Canonical timestamp value.
+pub fn timestamp_value(timestamp: &DateTime<Utc>) -> IOValue {
+    IOValue::new(canonical_timestamp(timestamp))
+}
+
+/// Canonical spelling of `text` if it is a timestamp written some other way.
+pub fn recanonicalize_timestamp(text: &str) -> Option<String> {
+    // Cheap shape check before parsing: `YYYY-MM-DDTHH:MM:SS` at least
+    let bytes = text.as_bytes();
+    if bytes.len() < 20 || bytes[4] != b'-' || bytes[7] != b'-' || bytes[13] != b':' {
+        return None;
+    }
+    if bytes.len() == CANONICAL_TIMESTAMP_LEN && text.ends_with('Z') && bytes[19] == b'.' {
+        return None;
+    }
+    let parsed = DateTime::parse_from_rfc3339(text).ok()?;
+    let canonical = canonical_timestamp(&parsed.with_timezone(&Utc));
+    (canonical != text).then_some(canonical)
+}
+
+/// Canonical form of a float atom, if its bits are not canonical already.
+pub(crate) fn recanonicalize_f64(value: f64) -> Option<f64> {
+    let canonical = canonical_f64(value);
+    (canonical.to_bits() != value.to_bits()).then_some(canonical)
+}
+
+/// `value` with every float and timestamp in canonical form, or `None` when
+/// it is canonical already.
+///
+/// Rebuilt compounds lose their annotations.
+pub fn canonicalize(value: &IOValue) -> Option<IOValue> {
+    match value.value_class() {
+        ValueClass::Atomic(AtomClass::Double) => {
+            recanonicalize_f64(value.as_double()?).map(IOValue::new)
+        }
+        ValueClass::Atomic(AtomClass::String) => {
+            recanonicalize_timestamp(&value.as_string()?).map(IOValue::new)
+        }
+        ValueClass::Atomic(_) | ValueClass::Embedded => None,
+        ValueClass::Compound(CompoundClass::Record) => {
+            let label = IOValue::from(value.label());
+            let fields: Vec<IOValue> = value.iter().map(IOValue::from).collect();
+            let new_label = canonicalize(&label);
+            let new_fields = canonicalize_all(&fields);
+            if new_label.is_none() && new_fields.is_none() {
+                return None;
+            }
+            Some(IOValue::record(
+                new_label.unwrap_or(label),
+                new_fields.unwrap_or(fields),
+            ))
+        }
+        ValueClass::Compound(CompoundClass::Sequence) => {
+            let items: Vec<IOValue> = value.iter().map(IOValue::from).collect();
+            canonicalize_all(&items).map(IOValue::new)
+        }
+        ValueClass::Compound(CompoundClass::Set) => {
+            let items: Vec<IOValue> = value.iter().map(IOValue::from).collect();
+            canonicalize_all(&items)
+                .map(|items| IOValue::new(items.into_iter().collect::<preserves::Set<IOValue>>()))
+        }
+        ValueClass::Compound(CompoundClass::Dictionary) => {
+            let entries: Vec<(IOValue, IOValue)> = value
+                .entries()
+                .map(|(key, entry)| (IOValue::from(key), IOValue::from(entry)))
+                .collect();
+            let mut changed = false;
+            let entries: preserves::Map<IOValue, IOValue> = entries
+                .into_iter()
+                .map(|(key, entry)| {
+                    let key = canonicalize(&key)
+                        .inspect(|_| changed = true)
+                        .unwrap_or(key);
+                    let entry = canonicalize(&entry)
+                        .inspect(|_| changed = true)
+                        .unwrap_or(entry);
+                    (key, entry)
+                })
+                .collect();
+            changed.then(|| IOValue::new(entries))
+        }
+    }
+}
+
+fn canonicalize_all(items: &[IOValue]) -> Option<Vec<IOValue>> {
+    let canonical: Vec<Option<IOValue>> = items.iter().map(canonicalize).collect();
+    if canonical.iter().all(Option::is_none) {
+        return None;
+    }
+    Some(
+        canonical
+            .into_iter()
+            .zip(items)
+            .map(|(canonical, item)| canonical.unwrap_or_else(|| item.clone()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::io_value::canonical_hash;
+
+    #[test]
+    fn test_spellings_of_the_same_quantity_canonicalize_alike() {
+        let noisy_nan = f64::from_bits(0x7ff8_0000_0000_0001);
+        assert_eq!(canonical_f64(noisy_nan).to_bits(), CANONICAL_NAN_BITS);
+        assert_eq!(canonical_f64(-0.0).to_bits(), 0.0f64.to_bits());
+        assert_eq!(canonical_f64(1.5), 1.5);
+
+        assert_eq!(
+            recanonicalize_timestamp("2024-05-01T14:00:00+02:00").as_deref(),
+            Some("2024-05-01T12:00:00.000000000Z")
+        );
+        assert_eq!(
+            recanonicalize_timestamp("2024-05-01T12:00:00.000000000Z"),
+            None
+        );
+        assert_eq!(recanonicalize_timestamp("not a timestamp at all"), None);
+
+        let record = |stamp: &str, float: f64| {
+            IOValue::record(
+                IOValue::symbol("reading"),
+                vec![IOValue::new(stamp.to_string()), IOValue::new(float)],
+            )
+        };
+        let written = record("2024-05-01T12:00:00+00:00", -0.0);
+        let canonical = canonicalize(&written).unwrap();
+        assert_eq!(canonical, record("2024-05-01T12:00:00.000000000Z", 0.0));
+        assert_eq!(canonicalize(&canonical), None);
+        assert_eq!(canonical_hash(&written), canonical_hash(&canonical));
+    }
+}
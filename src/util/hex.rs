@@ -0,0 +1,44 @@
+//! Lowercase hex encoding for opaque tokens.
+
+/// Encode `bytes` as lowercase hex.
+pub fn encode(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
+
+/// Decode hex text, rejecting odd lengths and anything but ASCII hex digits.
+pub fn decode(hex: &str) -> Result<Vec<u8>, &'static str> {
+    let digits = hex.as_bytes();
+    if !digits.len().is_multiple_of(2) {
+        return Err("odd-length token");
+    }
+    digits
+        .chunks_exact(2)
+        .map(|pair| match (nibble(pair[0]), nibble(pair[1])) {
+            (Some(high), Some(low)) => Ok(high << 4 | low),
+            _ => Err("token is not hex encoded"),
+        })
+        .collect()
+}
+
+fn nibble(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_rejects_non_ascii_without_panicking() {
+        assert_eq!(decode(&encode(b"\x00\x7f\xff")).unwrap(), b"\x00\x7f\xff");
+        assert_eq!(decode("0A").unwrap(), vec![0x0a]);
+        assert_eq!(decode("aé1"), Err("token is not hex encoded"));
+        assert_eq!(decode("é"), Err("token is not hex encoded"));
+        assert_eq!(decode("abc"), Err("odd-length token"));
+        assert_eq!(decode("+1"), Err("token is not hex encoded"));
+    }
+}
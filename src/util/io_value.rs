@@ -6,6 +6,8 @@ use serde_json::{Value, json};
 use std::borrow::Cow;
 use std::convert::TryFrom;
 
+use super::canonical;
+
 /// Lightweight view over a preserves record.
 pub struct RecordView<'a> {
     value: &'a IOValue,
@@ -234,6 +236,148 @@ pub fn io_value_to_json(value: &IOValue) -> Value {
     node
 }
 
+/// Convert a JSON value into an `IOValue`.
+///
+/// Booleans, numbers, strings, arrays and objects map onto their preserves
+/// counterparts. Two object forms act as templates for values JSON cannot
+/// express directly: `{"$symbol": name}` produces a symbol and
+/// `{"$record": label, "fields": [...]}` a record. `null` has no preserves
+/// equivalent and is rejected.
+pub fn json_to_io_value(value: &Value) -> Result<IOValue, String> {
+    match value {
+        Value::Null => Err("null has no preserves representation".to_string()),
+        Value::Bool(boolean) => Ok(IOValue::new(*boolean)),
+        Value::Number(number) => {
+            if let Some(integer) = number.as_i64() {
+                Ok(IOValue::new(integer))
+            } else if let Some(float) = number.as_f64() {
+                Ok(IOValue::new(float))
+            } else {
+                Err(format!("number {number} is out of range"))
+            }
+        }
+        Value::String(string) => Ok(IOValue::new(string.clone())),
+        Value::Array(items) => items
+            .iter()
+            .map(json_to_io_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map(IOValue::new),
+        Value::Object(map) => {
+            if let Some(name) = map.get("$symbol") {
+                let name = name
+                    .as_str()
+                    .ok_or_else(|| "$symbol must be a string".to_string())?;
+                return Ok(IOValue::symbol(name.to_string()));
+            }
+            if let Some(label) = map.get("$record") {
+                let label = label
+                    .as_str()
+                    .ok_or_else(|| "$record label must be a string".to_string())?;
+                let fields = match map.get("fields") {
+                    None => Vec::new(),
+                    Some(Value::Array(fields)) => fields
+                        .iter()
+                        .map(json_to_io_value)
+                        .collect::<Result<Vec<_>, _>>()?,
+                    Some(_) => return Err("record fields must be an array".to_string()),
+                };
+                return Ok(IOValue::record(IOValue::symbol(label.to_string()), fields));
+            }
+            let entries = map
+                .iter()
+                .map(|(key, value)| Ok((IOValue::new(key.clone()), json_to_io_value(value)?)))
+                .collect::<Result<preserves::Map<IOValue, IOValue>, String>>()?;
+            Ok(IOValue::new(entries))
+        }
+    }
+}
+
+/// Canonical binary encoding of `value`.
+///
+/// This is the Preserves packed encoding with the canonical-form rules
+/// applied: annotations are dropped, and set elements and dictionary entries
+/// are written in ascending order of their own canonical encodings (entries
+/// by key), and floats and timestamps are written in the forms laid down in
+/// [`canonical`](super::canonical). Equal values therefore always encode to the
+/// same bytes, whether they were built in memory or decoded from the journal,
+/// and on whichever platform.
+pub fn canonical_encoding(value: &IOValue) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+/// Blake3 digest of [`canonical_encoding`], for content addressing,
+/// deduplication and state hashes.
+pub fn canonical_hash(value: &IOValue) -> [u8; 32] {
+    *blake3::hash(&canonical_encoding(value)).as_bytes()
+}
+
+const TAG_RECORD: u8 = 0xb4;
+const TAG_SEQUENCE: u8 = 0xb5;
+const TAG_SET: u8 = 0xb6;
+const TAG_DICTIONARY: u8 = 0xb7;
+const TAG_END: u8 = 0x84;
+
+fn write_canonical(value: &IOValue, out: &mut Vec<u8>) {
+    match value.value_class() {
+        ValueClass::Compound(CompoundClass::Record) => {
+            out.push(TAG_RECORD);
+            write_canonical(&IOValue::from(value.label()), out);
+            for field in value.iter() {
+                write_canonical(&IOValue::from(field), out);
+            }
+            out.push(TAG_END);
+        }
+        ValueClass::Compound(CompoundClass::Sequence) => {
+            out.push(TAG_SEQUENCE);
+            for item in value.iter() {
+                write_canonical(&IOValue::from(item), out);
+            }
+            out.push(TAG_END);
+        }
+        ValueClass::Compound(CompoundClass::Set) => {
+            let mut items: Vec<Vec<u8>> = value
+                .iter()
+                .map(|item| canonical_encoding(&IOValue::from(item)))
+                .collect();
+            items.sort();
+            out.push(TAG_SET);
+            items.iter().for_each(|item| out.extend_from_slice(item));
+            out.push(TAG_END);
+        }
+        ValueClass::Compound(CompoundClass::Dictionary) => {
+            let mut entries: Vec<(Vec<u8>, Vec<u8>)> = value
+                .entries()
+                .map(|(key, entry)| {
+                    (
+                        canonical_encoding(&IOValue::from(key)),
+                        canonical_encoding(&IOValue::from(entry)),
+                    )
+                })
+                .collect();
+            entries.sort();
+            out.push(TAG_DICTIONARY);
+            for (key, entry) in entries {
+                out.extend_from_slice(&key);
+                out.extend_from_slice(&entry);
+            }
+            out.push(TAG_END);
+        }
+        ValueClass::Atomic(AtomClass::Double) | ValueClass::Atomic(AtomClass::String) => {
+            match canonical::canonicalize(value) {
+                Some(canonical) => write_canonical(&canonical, out),
+                None => preserves::write_iovalue_packed_into(value, false, out)
+                    .expect("writing to a Vec cannot fail"),
+            }
+        }
+        ValueClass::Atomic(_) | ValueClass::Embedded => {
+            preserves::write_iovalue_packed_into(value, false, out)
+                .expect("writing to a Vec cannot fail");
+        }
+    }
+}
+
 /// Produce a concise textual summary for an `IOValue`.
 pub fn io_value_summary(value: &IOValue, limit: usize) -> String {
     if let Some(string) = value.as_string() {
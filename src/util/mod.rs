@@ -1,3 +1,5 @@
 //! Utility helpers used across the runtime and codebase modules.
 
+pub mod canonical;
+pub mod hex;
 pub mod io_value;
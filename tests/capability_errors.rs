@@ -5,14 +5,20 @@
 //! level actor errors.
 
 use duet::runtime::actor::{Activation, CapabilitySpec, Entity};
+use duet::runtime::approval::{APPROVAL_DENIED_LABEL, PENDING_APPROVAL_LABEL};
 use duet::runtime::error::{CapabilityError, RuntimeError};
+use duet::runtime::flags::{FLAG_LABEL, FeatureFlag, system_actor};
+use duet::runtime::invocation::{INVOCATION_RESULT_LABEL, InvocationOutcome};
+use duet::runtime::notify::{Notification, NotificationEvent, Notifier};
 use duet::runtime::registry::EntityCatalog;
 use duet::runtime::state::CapabilityTarget;
-use duet::runtime::turn::{ActorId, FacetId};
+use duet::runtime::sturdy::SturdyRef;
+use duet::runtime::turn::{ActorId, CapabilityCompletion, FacetId};
 use duet::runtime::{Control, RuntimeConfig};
+use duet::util::io_value::record_with_label;
 use once_cell::sync::Lazy;
 use preserves::IOValue;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
 use uuid::Uuid;
 
@@ -53,6 +59,27 @@ impl Entity for CapabilityHarness {
                         .lock()
                         .expect("capability mutex poisoned") = Some(cap_id);
                 }
+                "invoke" => {
+                    if let Some(cap_id) = *self
+                        .last_capability
+                        .lock()
+                        .expect("capability mutex poisoned")
+                    {
+                        activation.request_capability_invocation(
+                            cap_id,
+                            IOValue::symbol("payload"),
+                            CapabilityCompletion {
+                                origin_actor: activation.actor_id.clone(),
+                                origin_facet: activation.current_facet.clone(),
+                                instance_id: "instance".into(),
+                                role: "role".into(),
+                                capability_alias: "cap".into(),
+                                tag: "gated".into(),
+                                role_properties: None,
+                            },
+                        );
+                    }
+                }
                 "revoke" => {
                     if let Some(cap_id) = *self
                         .last_capability
@@ -90,12 +117,33 @@ impl Entity for CapabilityHarness {
 }
 
 fn new_control() -> (Control, TempDir) {
+    new_control_with_approvals(Vec::new())
+}
+
+fn new_control_with_approvals(approval_kinds: Vec<String>) -> (Control, TempDir) {
     let temp = TempDir::new().unwrap();
     let config = RuntimeConfig {
         root: temp.path().to_path_buf(),
         snapshot_interval: 5,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds,
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let control = Control::init(config).expect("control init failed");
@@ -193,6 +241,11 @@ fn entity_denial_maps_to_capability_denied() {
         .find(|cap| cap.kind == "test/capability")
         .expect("capability to be granted");
 
+    let notifier = Arc::new(RecordingNotifier::default());
+    control
+        .runtime_mut()
+        .add_notifier(vec![NotificationEvent::CapabilityDenied], notifier.clone());
+
     let err = control
         .invoke_capability(capability.id, IOValue::symbol("deny"))
         .expect_err("denied invocation should fail");
@@ -203,4 +256,466 @@ fn entity_denial_maps_to_capability_denied() {
         }
         other => panic!("expected CapabilityError::Denied, got {other:?}"),
     }
+
+    let notifications = notifier.received.lock().unwrap();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].event, NotificationEvent::CapabilityDenied);
+    assert_eq!(
+        notifications[0].details["capability"],
+        capability.id.to_string()
+    );
+}
+
+#[test]
+fn async_invocations_complete_when_turns_are_pumped() {
+    Lazy::force(&REGISTER_ENTITY);
+
+    let (mut control, _temp) = new_control();
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+    let observer = ActorId::new();
+
+    control
+        .register_entity(
+            actor_id.clone(),
+            facet_id.clone(),
+            "cap-error-harness".into(),
+            IOValue::symbol("config"),
+        )
+        .expect("entity registration");
+
+    control
+        .send_message(actor_id.clone(), facet_id.clone(), IOValue::symbol("grant"))
+        .expect("grant message should execute");
+
+    let capability = control
+        .list_capabilities()
+        .into_iter()
+        .find(|cap| cap.kind == "test/capability")
+        .expect("capability to be granted");
+
+    let ok = control
+        .invoke_capability_async(
+            capability.id,
+            IOValue::symbol("payload"),
+            Some(observer.clone()),
+        )
+        .expect("async invocation enqueued");
+    let denied = control
+        .invoke_capability_async(capability.id, IOValue::symbol("deny"), None)
+        .expect("async invocation enqueued");
+
+    // Nothing runs until the caller pumps the scheduler
+    let pending: Vec<Uuid> = control
+        .pending_invocations()
+        .into_iter()
+        .map(|pending| pending.id)
+        .collect();
+    assert_eq!(pending.len(), 2);
+    assert!(pending.contains(&ok) && pending.contains(&denied));
+    assert!(control.take_invocation_result(ok).is_none());
+
+    loop {
+        match control.runtime_mut().step() {
+            Ok(Some(_)) | Err(RuntimeError::Actor(_)) => continue,
+            Ok(None) => break,
+            Err(other) => panic!("unexpected runtime error: {other:?}"),
+        }
+    }
+
+    assert!(control.pending_invocations().is_empty());
+    assert_eq!(
+        control.take_invocation_result(ok),
+        Some(InvocationOutcome::Completed(IOValue::symbol("ok")))
+    );
+    assert!(matches!(
+        control.take_invocation_result(denied),
+        Some(InvocationOutcome::Failed(_))
+    ));
+    assert!(control.take_invocation_result(ok).is_none());
+
+    let results = labelled_assertions(&control, &observer, INVOCATION_RESULT_LABEL);
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        results[0].index(0).as_string().unwrap().as_ref(),
+        ok.to_string()
+    );
+}
+
+#[derive(Default)]
+struct RecordingNotifier {
+    received: Mutex<Vec<Notification>>,
+}
+
+impl Notifier for RecordingNotifier {
+    fn notify(&self, notification: &Notification) {
+        self.received.lock().unwrap().push(notification.clone());
+    }
+}
+
+#[test]
+fn sturdy_refs_verify_caveats_expiry_and_revocation() {
+    Lazy::force(&REGISTER_ENTITY);
+
+    let (mut control, _temp) = new_control();
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+
+    control
+        .register_entity(
+            actor_id.clone(),
+            facet_id.clone(),
+            "cap-error-harness".into(),
+            IOValue::symbol("config"),
+        )
+        .expect("entity registration");
+
+    control
+        .send_message(actor_id.clone(), facet_id.clone(), IOValue::symbol("grant"))
+        .expect("grant message should execute");
+
+    let capability = control
+        .list_capabilities()
+        .into_iter()
+        .find(|cap| cap.kind == "test/capability")
+        .expect("capability to be granted");
+
+    let caveat = IOValue::symbol("payload");
+    let sturdy = control
+        .export_sturdy_ref(capability.id, vec![caveat], None)
+        .expect("export sturdy ref");
+    let redeemed = SturdyRef::from_token(&sturdy.to_token()).expect("token decodes");
+
+    let result = control
+        .redeem_sturdy_ref(&redeemed, IOValue::symbol("payload"))
+        .expect("redemption succeeds");
+    assert_eq!(result, IOValue::symbol("ok"));
+
+    match control.redeem_sturdy_ref(&redeemed, IOValue::symbol("other")) {
+        Err(RuntimeError::Capability(CapabilityError::CaveatRejected(id))) => {
+            assert_eq!(id, capability.id);
+        }
+        other => panic!("expected CapabilityError::CaveatRejected, got {other:?}"),
+    }
+
+    let mut forged = redeemed.clone();
+    forged.attenuation.clear();
+    match control.redeem_sturdy_ref(&forged, IOValue::symbol("other")) {
+        Err(RuntimeError::Capability(CapabilityError::InvalidSignature(_))) => {}
+        other => panic!("expected CapabilityError::InvalidSignature, got {other:?}"),
+    }
+
+    let expired = control
+        .export_sturdy_ref(capability.id, Vec::new(), Some(chrono::Utc::now()))
+        .expect("export expiring sturdy ref");
+    match control.redeem_sturdy_ref(&expired, IOValue::symbol("payload")) {
+        Err(RuntimeError::Capability(CapabilityError::Expired(id, _))) => {
+            assert_eq!(id, capability.id);
+        }
+        other => panic!("expected CapabilityError::Expired, got {other:?}"),
+    }
+
+    control
+        .send_message(
+            actor_id.clone(),
+            facet_id.clone(),
+            IOValue::symbol("revoke"),
+        )
+        .expect("revoke message should execute");
+
+    match control.redeem_sturdy_ref(&redeemed, IOValue::symbol("payload")) {
+        Err(RuntimeError::Capability(CapabilityError::Revoked(id))) => {
+            assert_eq!(id, capability.id);
+        }
+        other => panic!("expected CapabilityError::Revoked, got {other:?}"),
+    }
+}
+
+fn labelled_assertions(control: &Control, actor: &ActorId, label: &str) -> Vec<IOValue> {
+    control
+        .list_assertions(Some(actor))
+        .into_iter()
+        .map(|info| info.value)
+        .filter(|value| record_with_label(value, label).is_some())
+        .collect()
+}
+
+#[test]
+fn approval_gated_invocations_wait_for_a_decision() {
+    Lazy::force(&REGISTER_ENTITY);
+
+    let (mut control, _temp) = new_control_with_approvals(vec!["test/capability".into()]);
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+
+    control
+        .register_entity(
+            actor_id.clone(),
+            facet_id.clone(),
+            "cap-error-harness".into(),
+            IOValue::symbol("config"),
+        )
+        .expect("entity registration");
+    control
+        .send_message(actor_id.clone(), facet_id.clone(), IOValue::symbol("grant"))
+        .expect("grant message should execute");
+
+    // First invocation is parked, then approved
+    control
+        .send_message(
+            actor_id.clone(),
+            facet_id.clone(),
+            IOValue::symbol("invoke"),
+        )
+        .expect("invoke message should execute");
+    control.drain_pending().expect("drain");
+
+    let pending = control.pending_approvals();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].kind, "test/capability");
+    assert_eq!(
+        labelled_assertions(&control, &actor_id, PENDING_APPROVAL_LABEL).len(),
+        1
+    );
+    assert!(labelled_assertions(&control, &actor_id, "tool-result").is_empty());
+
+    control.approve(pending[0].id).expect("approve");
+    assert!(control.pending_approvals().is_empty());
+    assert!(labelled_assertions(&control, &actor_id, PENDING_APPROVAL_LABEL).is_empty());
+    let results = labelled_assertions(&control, &actor_id, "tool-result");
+    assert_eq!(results.len(), 1);
+    let result = record_with_label(&results[0], "tool-result").unwrap();
+    assert_eq!(result.field(5), IOValue::symbol("ok"));
+
+    match control.approve(pending[0].id) {
+        Err(RuntimeError::Capability(CapabilityError::ApprovalNotFound(id))) => {
+            assert_eq!(id, pending[0].id);
+        }
+        other => panic!("expected CapabilityError::ApprovalNotFound, got {other:?}"),
+    }
+
+    // Second invocation is parked, then denied
+    control
+        .send_message(
+            actor_id.clone(),
+            facet_id.clone(),
+            IOValue::symbol("invoke"),
+        )
+        .expect("invoke message should execute");
+    control.drain_pending().expect("drain");
+
+    let pending = control.pending_approvals();
+    assert_eq!(pending.len(), 1);
+    control.deny(pending[0].id, "not today").expect("deny");
+
+    assert!(labelled_assertions(&control, &actor_id, PENDING_APPROVAL_LABEL).is_empty());
+    let results = labelled_assertions(&control, &actor_id, "tool-result");
+    assert_eq!(results.len(), 2);
+    let denied = results
+        .iter()
+        .filter_map(|value| record_with_label(value, "tool-result"))
+        .map(|record| record.field(5))
+        .find(|value| record_with_label(value, APPROVAL_DENIED_LABEL).is_some())
+        .expect("denial record delivered");
+    let denied = record_with_label(&denied, APPROVAL_DENIED_LABEL).unwrap();
+    assert_eq!(denied.field_string(2).as_deref(), Some("not today"));
+}
+
+#[test]
+fn approval_gates_flag_is_asserted_and_bypasses_approvals() {
+    Lazy::force(&REGISTER_ENTITY);
+
+    let (mut control, _temp) = new_control_with_approvals(vec!["test/capability".into()]);
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+
+    control
+        .register_entity(
+            actor_id.clone(),
+            facet_id.clone(),
+            "cap-error-harness".into(),
+            IOValue::symbol("config"),
+        )
+        .expect("entity registration");
+    control
+        .send_message(actor_id.clone(), facet_id.clone(), IOValue::symbol("grant"))
+        .expect("grant message should execute");
+
+    let gates = |control: &Control| {
+        control
+            .flags()
+            .into_iter()
+            .find(|status| status.flag == FeatureFlag::ApprovalGates)
+            .unwrap()
+    };
+    assert!(gates(&control).enabled);
+    assert!(!gates(&control).asserted);
+
+    let before = control.status().unwrap().head_turn;
+    control
+        .set_flag(FeatureFlag::ApprovalGates, false)
+        .expect("set flag");
+    let flags = labelled_assertions(&control, &system_actor(), FLAG_LABEL);
+    assert_eq!(flags, vec![FeatureFlag::ApprovalGates.assertion(false)]);
+    assert!(!gates(&control).enabled);
+
+    // Gate off: the invocation runs without waiting for a decision
+    control
+        .send_message(
+            actor_id.clone(),
+            facet_id.clone(),
+            IOValue::symbol("invoke"),
+        )
+        .expect("invoke message should execute");
+    control.drain_pending().expect("drain");
+    assert!(control.pending_approvals().is_empty());
+    assert_eq!(
+        labelled_assertions(&control, &actor_id, "tool-result").len(),
+        1
+    );
+
+    // Flipping again replaces the assertion rather than adding a second one
+    control
+        .set_flag(FeatureFlag::ApprovalGates, true)
+        .expect("set flag");
+    let flags = labelled_assertions(&control, &system_actor(), FLAG_LABEL);
+    assert_eq!(flags, vec![FeatureFlag::ApprovalGates.assertion(true)]);
+
+    // The flag is journaled state, so rewinding past it restores the default
+    control.goto(before).expect("goto");
+    assert!(labelled_assertions(&control, &system_actor(), FLAG_LABEL).is_empty());
+    assert!(!gates(&control).asserted);
+}
+
+#[test]
+fn pending_approvals_follow_time_travel() {
+    Lazy::force(&REGISTER_ENTITY);
+
+    let (mut control, _temp) = new_control_with_approvals(vec!["test/capability".into()]);
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+
+    control
+        .register_entity(
+            actor_id.clone(),
+            facet_id.clone(),
+            "cap-error-harness".into(),
+            IOValue::symbol("config"),
+        )
+        .expect("entity registration");
+    control
+        .send_message(actor_id.clone(), facet_id.clone(), IOValue::symbol("grant"))
+        .expect("grant message should execute");
+    let before_park = control.status().unwrap().head_turn;
+
+    control
+        .send_message(
+            actor_id.clone(),
+            facet_id.clone(),
+            IOValue::symbol("invoke"),
+        )
+        .expect("invoke message should execute");
+    control.drain_pending().expect("drain");
+    let parked = control.status().unwrap().head_turn;
+    let pending = control.pending_approvals();
+    assert_eq!(pending.len(), 1);
+
+    control.approve(pending[0].id).expect("approve");
+    control.drain_pending().expect("drain");
+    assert!(control.pending_approvals().is_empty());
+
+    // Rewinding past the decision makes the request pending again
+    control.goto(parked).expect("goto");
+    assert_eq!(control.pending_approvals().len(), 1);
+
+    // Rewinding past the request leaves nothing to approve
+    control.goto(before_park).expect("goto");
+    assert!(control.pending_approvals().is_empty());
+    match control.approve(pending[0].id) {
+        Err(RuntimeError::Capability(CapabilityError::ApprovalNotFound(id))) => {
+            assert_eq!(id, pending[0].id);
+        }
+        other => panic!("expected CapabilityError::ApprovalNotFound, got {other:?}"),
+    }
+    assert!(labelled_assertions(&control, &actor_id, "tool-result").is_empty());
+}
+
+#[test]
+fn direct_invocations_are_held_to_approval_gates() {
+    Lazy::force(&REGISTER_ENTITY);
+
+    let (mut control, _temp) = new_control_with_approvals(vec!["test/capability".into()]);
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+
+    control
+        .register_entity(
+            actor_id.clone(),
+            facet_id.clone(),
+            "cap-error-harness".into(),
+            IOValue::symbol("config"),
+        )
+        .expect("entity registration");
+    control
+        .send_message(actor_id.clone(), facet_id.clone(), IOValue::symbol("grant"))
+        .expect("grant message should execute");
+    let capability = control
+        .list_capabilities()
+        .into_iter()
+        .find(|cap| cap.kind == "test/capability")
+        .expect("capability to be granted");
+
+    let assert_gated = |result: Result<_, RuntimeError>| match result {
+        Err(RuntimeError::Capability(CapabilityError::ApprovalRequired(id, kind))) => {
+            assert_eq!(id, capability.id);
+            assert_eq!(kind, "test/capability");
+        }
+        other => panic!("expected CapabilityError::ApprovalRequired, got {other:?}"),
+    };
+
+    assert_gated(
+        control
+            .invoke_capability(capability.id, IOValue::symbol("payload"))
+            .map(drop),
+    );
+    assert_gated(
+        control
+            .invoke_capability_async(capability.id, IOValue::symbol("payload"), None)
+            .map(drop),
+    );
+    let sturdy = control
+        .export_sturdy_ref(capability.id, Vec::new(), None)
+        .expect("export sturdy ref");
+    assert_gated(
+        control
+            .redeem_sturdy_ref(&sturdy, IOValue::symbol("payload"))
+            .map(drop),
+    );
+    assert!(control.pending_invocations().is_empty());
+
+    // With the gate off the same calls run directly
+    control
+        .set_flag(FeatureFlag::ApprovalGates, false)
+        .expect("set flag");
+    let result = control
+        .invoke_capability(capability.id, IOValue::symbol("payload"))
+        .expect("ungated invocation");
+    assert_eq!(result, IOValue::symbol("ok"));
+    control
+        .set_flag(FeatureFlag::ApprovalGates, true)
+        .expect("set flag");
+
+    // The service command reports the refusal rather than running the capability
+    let mut service = duet::service::Service::new(control);
+    let response = service.call(
+        "invoke_capability",
+        &serde_json::json!({
+            "capability": capability.id.to_string(),
+            "payload": "payload",
+        }),
+    );
+    assert_eq!(
+        response["error"]["details"]["variant"], "ApprovalRequired",
+        "{response}"
+    );
 }
@@ -0,0 +1,162 @@
+//! Dashboard HTTP front end tests (feature `dashboard`)
+
+#![cfg(feature = "dashboard")]
+
+use duet::runtime::RuntimeConfig;
+use duet::runtime::control::Control;
+use duet::service::dashboard::Dashboard;
+use serde_json::Value;
+use std::io::{self, Cursor, Read, Write};
+use tempfile::TempDir;
+
+/// In-memory stream: reads a canned request and captures the response.
+struct Exchange {
+    request: Cursor<Vec<u8>>,
+    response: Vec<u8>,
+}
+
+impl Read for Exchange {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.request.read(buf)
+    }
+}
+
+impl Write for Exchange {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.response.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn roundtrip(dashboard: &Dashboard, request: &str) -> (String, String) {
+    let mut exchange = Exchange {
+        request: Cursor::new(request.as_bytes().to_vec()),
+        response: Vec::new(),
+    };
+    dashboard.handle_connection(&mut exchange).unwrap();
+    let text = String::from_utf8(exchange.response).unwrap();
+    let (head, body) = text.split_once("\r\n\r\n").expect("response has a body");
+    (head.to_string(), body.to_string())
+}
+
+fn new_dashboard() -> (Dashboard, TempDir) {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let control = Control::init(config).unwrap();
+    (Dashboard::new(control), temp)
+}
+
+#[test]
+fn dashboard_serves_page_and_api() {
+    let (dashboard, _temp) = new_dashboard();
+    let token = dashboard.token().to_string();
+
+    let (head, body) = roundtrip(
+        &dashboard,
+        &format!("GET /?token={token} HTTP/1.1\r\nHost: x\r\n\r\n"),
+    );
+    assert!(head.starts_with("HTTP/1.1 200"));
+    assert!(head.contains("text/html"));
+    assert!(body.contains("Duet dashboard"));
+    assert!(body.contains(&token));
+
+    let (head, body) = roundtrip(&dashboard, &api_request(&token, "list_branches", "{}"));
+    assert!(head.starts_with("HTTP/1.1 200"));
+    let envelope: Value = serde_json::from_str(&body).unwrap();
+    let branches = envelope["result"]["branches"].as_array().unwrap();
+    assert!(branches.iter().any(|branch| branch["name"] == "main"));
+
+    let (head, _) = roundtrip(&dashboard, "GET /api/status HTTP/1.1\r\n\r\n");
+    assert!(head.starts_with("HTTP/1.1 405"));
+    let (head, _) = roundtrip(&dashboard, "GET /missing HTTP/1.1\r\n\r\n");
+    assert!(head.starts_with("HTTP/1.1 404"));
+}
+
+#[test]
+fn dashboard_refuses_requests_without_its_token() {
+    let (dashboard, _temp) = new_dashboard();
+    let token = dashboard.token().to_string();
+
+    // The page is only served to the printed URL
+    let (head, body) = roundtrip(&dashboard, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+    assert!(head.starts_with("HTTP/1.1 403"));
+    assert!(!body.contains(&token));
+    let (head, _) = roundtrip(&dashboard, "GET /?token=guess HTTP/1.1\r\n\r\n");
+    assert!(head.starts_with("HTTP/1.1 403"));
+
+    // A cross-site form post carries neither the token nor a JSON body
+    let (head, _) = roundtrip(
+        &dashboard,
+        "POST /api/list_branches HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n{}",
+    );
+    assert!(head.starts_with("HTTP/1.1 403"));
+    let (head, _) = roundtrip(
+        &dashboard,
+        &format!(
+            "POST /api/list_branches HTTP/1.1\r\nX-Duet-Token: {token}\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n{{}}"
+        ),
+    );
+    assert!(head.starts_with("HTTP/1.1 415"));
+
+    // Commands that change the runtime are not exposed at all
+    for command in ["branch_delete", "compact", "restore_file", "nope"] {
+        let (head, _) = roundtrip(&dashboard, &api_request(&token, command, "{}"));
+        assert!(head.starts_with("HTTP/1.1 403"), "{command}: {head}");
+    }
+}
+
+#[test]
+fn dashboard_serves_connections_concurrently() {
+    let (dashboard, _temp) = new_dashboard();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = dashboard.clone();
+    std::thread::spawn(move || server.serve(listener));
+
+    // A client that never finishes its request does not hold up others
+    let mut stalled = std::net::TcpStream::connect(addr).unwrap();
+    stalled.write_all(b"POST /api/status HTTP/1.1\r\n").unwrap();
+
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(std::time::Duration::from_secs(10)))
+        .unwrap();
+    stream
+        .write_all(api_request(dashboard.token(), "list_branches", "{}").as_bytes())
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+}
+
+fn api_request(token: &str, command: &str, body: &str) -> String {
+    format!(
+        "POST /api/{command} HTTP/1.1\r\nX-Duet-Token: {token}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+}
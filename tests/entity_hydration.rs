@@ -4,10 +4,10 @@
 
 use duet::runtime::actor::{Activation, CapabilitySpec, Entity, HydratableEntity};
 use duet::runtime::error::{ActorError, ActorResult, RuntimeError};
-use duet::runtime::pattern::Pattern;
-use duet::runtime::registry::{EntityCatalog, EntityMetadata};
-use duet::runtime::state::CapabilityTarget;
-use duet::runtime::turn::{ActorId, FacetId, Handle, TurnId};
+use duet::runtime::pattern::{Pattern, PatternScope};
+use duet::runtime::registry::{ConcurrencyClass, EntityCatalog, EntityMetadata};
+use duet::runtime::state::{CapabilityMetadata, CapabilityTarget};
+use duet::runtime::turn::{ActorId, BranchId, CapabilityCompletion, FacetId, Handle, TurnId};
 use duet::runtime::{Control, RuntimeConfig};
 use once_cell::sync::Lazy;
 use std::convert::TryFrom;
@@ -62,6 +62,59 @@ impl Entity for CapabilityIssuerEntity {
     }
 }
 
+/// Entity serving a capability that re-invokes itself while the payload
+/// counts down, tallying every call it serves.
+struct SelfInvokingEntity {
+    calls: Arc<AtomicUsize>,
+}
+
+impl Entity for SelfInvokingEntity {
+    fn on_message(
+        &self,
+        activation: &mut Activation,
+        _payload: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        activation.grant_capability(CapabilitySpec {
+            holder: activation.actor_id.clone(),
+            holder_facet: activation.current_facet.clone(),
+            target: None,
+            kind: "test/countdown".to_string(),
+            attenuation: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn on_capability_invoke(
+        &self,
+        activation: &mut Activation,
+        capability: &CapabilityMetadata,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<preserves::IOValue> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let remaining = payload
+            .as_signed_integer()
+            .and_then(|value| i64::try_from(value.as_ref()).ok())
+            .unwrap_or(0);
+        if remaining > 0 {
+            let completion = CapabilityCompletion {
+                origin_actor: activation.actor_id.clone(),
+                origin_facet: activation.current_facet.clone(),
+                instance_id: "countdown".to_string(),
+                role: "self".to_string(),
+                capability_alias: "countdown".to_string(),
+                tag: format!("remaining-{}", remaining - 1),
+                role_properties: None,
+            };
+            activation.request_capability_invocation(
+                capability.id,
+                preserves::IOValue::new(remaining - 1),
+                completion,
+            );
+        }
+        Ok(preserves::IOValue::new(remaining))
+    }
+}
+
 /// Entity that exercises the flow-control borrow/repay helpers based on integer payloads.
 struct FlowControlEntity;
 
@@ -204,6 +257,23 @@ fn test_entity_registration_persists_across_restart() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let entity_id = {
@@ -252,6 +322,23 @@ fn test_entity_survives_time_travel() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let mut control = Control::init(config).unwrap();
@@ -308,6 +395,23 @@ fn test_workspace_capability_read_and_write() {
         snapshot_interval: 5,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let mut control = Control::init(config).unwrap();
@@ -406,6 +510,112 @@ fn test_workspace_capability_read_and_write() {
     assert_eq!(text_again.as_ref(), "updated");
 }
 
+#[test]
+fn test_workspace_stream_read_emits_chunks() {
+    use preserves::IOValue;
+
+    let temp = TempDir::new().unwrap();
+    let workspace_root = temp.path();
+    let contents: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(workspace_root.join("big.bin"), &contents).unwrap();
+
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+    control
+        .register_entity(
+            actor_id.clone(),
+            facet_id.clone(),
+            "workspace".to_string(),
+            IOValue::new(workspace_root.to_string_lossy().to_string()),
+        )
+        .unwrap();
+
+    let grant = IOValue::record(
+        IOValue::symbol("workspace-stream-read"),
+        vec![IOValue::new("big.bin".to_string())],
+    );
+    control
+        .send_message(actor_id.clone(), facet_id.clone(), grant)
+        .unwrap();
+    let stream_cap = control
+        .list_capabilities()
+        .into_iter()
+        .find(|cap| cap.kind == "workspace/stream-read")
+        .expect("stream-read capability should be granted");
+
+    let request = IOValue::record(
+        IOValue::symbol("workspace-stream-read"),
+        vec![IOValue::new("big.bin".to_string()), IOValue::new(4096i64)],
+    );
+    let summary = control.invoke_capability(stream_cap.id, request).unwrap();
+    let stream_id = summary.index(0).as_string().unwrap().to_string();
+    assert_eq!(
+        i64::try_from(summary.index(2).as_signed_integer().unwrap().as_ref()).unwrap(),
+        3
+    );
+    assert_eq!(
+        i64::try_from(summary.index(3).as_signed_integer().unwrap().as_ref()).unwrap(),
+        10_000
+    );
+
+    let mut chunks: Vec<(i64, Vec<u8>)> = control
+        .list_assertions_with_label("workspace-chunk", Some(&actor_id))
+        .into_iter()
+        .map(|info| {
+            assert_eq!(info.value.index(0).as_string().unwrap().as_ref(), stream_id);
+            let seq =
+                i64::try_from(info.value.index(2).as_signed_integer().unwrap().as_ref()).unwrap();
+            let bytes = info.value.index(3).as_bytestring().unwrap().to_vec();
+            (seq, bytes)
+        })
+        .collect();
+    chunks.sort();
+    assert_eq!(
+        chunks.iter().map(|(seq, _)| *seq).collect::<Vec<_>>(),
+        vec![0, 1, 2]
+    );
+    let streamed: Vec<u8> = chunks.into_iter().flat_map(|(_, bytes)| bytes).collect();
+    assert_eq!(streamed, contents);
+
+    let end = control.list_assertions_with_label("workspace-stream-end", Some(&actor_id));
+    assert_eq!(end.len(), 1);
+    assert_eq!(
+        i64::try_from(end[0].value.index(2).as_signed_integer().unwrap().as_ref()).unwrap(),
+        3
+    );
+
+    let too_large = IOValue::record(
+        IOValue::symbol("workspace-stream-read"),
+        vec![IOValue::new("big.bin".to_string()), IOValue::new(i64::MAX)],
+    );
+    assert!(control.invoke_capability(stream_cap.id, too_large).is_err());
+}
+
 #[test]
 fn test_entity_patterns_persist_through_restart_and_time_travel() {
     EntityCatalog::global().register("pattern-entity", |_config| {
@@ -420,6 +630,23 @@ fn test_entity_patterns_persist_through_restart_and_time_travel() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let actor_id = ActorId::new();
@@ -444,6 +671,8 @@ fn test_entity_patterns_persist_through_restart_and_time_travel() {
             id: Uuid::new_v4(),
             pattern: preserves::IOValue::symbol("<_>"),
             facet: facet_id.clone(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
 
         control
@@ -516,6 +745,23 @@ fn test_pattern_retractions_survive_restart() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let actor_id = ActorId::new();
@@ -545,6 +791,8 @@ fn test_pattern_retractions_survive_restart() {
             id: pattern_id,
             pattern: preserves::IOValue::symbol("<_>"),
             facet: facet_id.clone(),
+            namespace: None,
+            scope: PatternScope::Actor,
         };
         control
             .register_pattern_for_entity(watcher_id, pattern)
@@ -670,6 +918,23 @@ fn test_hydratable_entity_state_restored_on_goto() {
         snapshot_interval: 1,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let mut control = Control::init(config).unwrap();
@@ -711,6 +976,23 @@ fn test_capabilities_persist_across_time_travel_and_restart() {
         snapshot_interval: 5,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let actor_id = ActorId::new();
@@ -795,6 +1077,23 @@ fn test_flow_control_account_balance_and_time_travel() {
         snapshot_interval: 5,
         flow_control_limit: 5,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let actor_id = ActorId::new();
@@ -864,6 +1163,23 @@ fn test_attach_entity_persists_and_rewinds() {
         snapshot_interval: 5,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let actor_id = ActorId::new();
@@ -939,3 +1255,2165 @@ fn test_attach_entity_persists_and_rewinds() {
         );
     }
 }
+
+static BROADCAST_DELIVERIES: Lazy<Arc<Mutex<Vec<String>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(Vec::new())));
+
+/// Entity that records the `name` it was configured with whenever it receives a message.
+struct BroadcastListener {
+    name: String,
+}
+
+impl Entity for BroadcastListener {
+    fn on_message(
+        &self,
+        _activation: &mut Activation,
+        _payload: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        BROADCAST_DELIVERIES.lock().unwrap().push(self.name.clone());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_broadcast_fans_out_to_group_members() {
+    EntityCatalog::global().register("test/broadcast-listener", |config| {
+        Ok(Box::new(BroadcastListener {
+            name: config
+                .as_string()
+                .map(|name| name.to_string())
+                .unwrap_or_default(),
+        }))
+    });
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    let group = "agents";
+    let mut members = Vec::new();
+    {
+        let mut control = Control::init(config.clone()).unwrap();
+        for name in ["alpha", "beta", "outsider"] {
+            let actor = ActorId::new();
+            control
+                .register_entity(
+                    actor.clone(),
+                    FacetId::new(),
+                    "test/broadcast-listener".to_string(),
+                    preserves::IOValue::new(format!("{name}-{}", temp.path().display())),
+                )
+                .unwrap();
+            if name != "outsider" {
+                assert!(control.add_to_group(group, actor.clone()).unwrap());
+                members.push(actor);
+            }
+        }
+        assert!(!control.add_to_group(group, members[0].clone()).unwrap());
+    }
+
+    // Membership survives a restart
+    let mut control = Control::new(config).unwrap();
+    let mut expected = members.clone();
+    expected.sort();
+    assert_eq!(control.group_members(group), expected);
+
+    let record = control
+        .broadcast(group, preserves::IOValue::symbol("workspace-changed"))
+        .unwrap();
+    assert_eq!(record.recipients.len(), 2);
+
+    let suffix = format!("-{}", temp.path().display());
+    let mut delivered: Vec<String> = BROADCAST_DELIVERIES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|name| name.strip_suffix(&suffix).map(str::to_string))
+        .collect();
+    delivered.sort();
+    assert_eq!(delivered, vec!["alpha", "beta"]);
+
+    // One logical broadcast, with every fanned-out turn pointing back at it
+    assert_eq!(control.broadcasts().unwrap(), vec![record.clone()]);
+    let branch = control.runtime().current_branch();
+    let reader = control.runtime().journal_reader(&branch).unwrap();
+    let fanned_out = reader
+        .iter_all()
+        .unwrap()
+        .map(Result::unwrap)
+        .filter(|turn| {
+            turn.inputs.iter().any(|input| {
+                matches!(
+                    input,
+                    duet::runtime::turn::TurnInput::ExternalMessage { broadcast: Some(id), .. }
+                        if *id == record.id
+                )
+            })
+        })
+        .count();
+    assert_eq!(fanned_out, 2);
+
+    assert!(control.remove_from_group(group, &members[0]).unwrap());
+    assert_eq!(control.group_members(group), vec![members[1].clone()]);
+}
+
+/// (from, to) pairs of each history jump seen by [`TimeTraveller`].
+type Jumps = Arc<Mutex<Vec<(TurnId, TurnId)>>>;
+
+static TIME_TRAVEL_JUMPS: Lazy<Jumps> = Lazy::new(|| Arc::new(Mutex::new(Vec::new())));
+
+/// Entity that records every history jump it is told about.
+struct TimeTraveller;
+
+impl Entity for TimeTraveller {
+    fn on_message(
+        &self,
+        _activation: &mut Activation,
+        _payload: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        Ok(())
+    }
+
+    fn on_time_travel(&self, old_head: &TurnId, new_head: &TurnId) -> ActorResult<()> {
+        TIME_TRAVEL_JUMPS
+            .lock()
+            .unwrap()
+            .push((old_head.clone(), new_head.clone()));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_entities_observe_time_travel() {
+    EntityCatalog::global().register("test/time-traveller", |_config| Ok(Box::new(TimeTraveller)));
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor = ActorId::new();
+    let facet = FacetId::new();
+    control
+        .register_entity(
+            actor.clone(),
+            facet.clone(),
+            "test/time-traveller".to_string(),
+            preserves::IOValue::symbol("config"),
+        )
+        .unwrap();
+
+    for _ in 0..2 {
+        control
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::symbol("tick"),
+            )
+            .unwrap();
+    }
+    let before = control.status().unwrap().head_turn;
+
+    let rewound = control.back(1).unwrap();
+    assert_eq!(
+        TIME_TRAVEL_JUMPS.lock().unwrap().as_slice(),
+        &[(before.clone(), rewound.clone())]
+    );
+
+    let experiment = control
+        .fork(
+            duet::runtime::turn::BranchId::main(),
+            duet::runtime::turn::BranchId::new("experiment"),
+            None,
+            Default::default(),
+        )
+        .unwrap();
+    control.switch_branch(experiment).unwrap();
+    let jumps = TIME_TRAVEL_JUMPS.lock().unwrap();
+    assert_eq!(jumps.len(), 2);
+    assert_eq!(jumps[1].0, rewound);
+}
+
+static SCOPED_DELIVERIES: Lazy<Arc<AtomicUsize>> = Lazy::new(|| Arc::new(AtomicUsize::new(0)));
+
+#[test]
+fn test_branch_scoped_entities_only_run_on_their_branches() {
+    use duet::runtime::turn::BranchId;
+
+    EntityCatalog::global().register("test/branch-scoped", |_config| {
+        Ok(Box::new(CounterEntity {
+            count: SCOPED_DELIVERIES.clone(),
+        }))
+    });
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor = ActorId::new();
+    let facet = FacetId::new();
+
+    let global = control
+        .register_entity(
+            actor.clone(),
+            facet.clone(),
+            "test/branch-scoped".to_string(),
+            preserves::IOValue::symbol("global"),
+        )
+        .unwrap();
+    let main_only = control
+        .register_entity_on_branch(
+            actor.clone(),
+            facet.clone(),
+            "test/branch-scoped".to_string(),
+            preserves::IOValue::symbol("main"),
+            BranchId::main(),
+        )
+        .unwrap();
+
+    // Forking carries main-scoped entities over to the new branch
+    let experiment = control
+        .fork(
+            BranchId::main(),
+            BranchId::new("experiment"),
+            None,
+            Default::default(),
+        )
+        .unwrap();
+    let experiment_only = control
+        .register_entity_on_branch(
+            actor.clone(),
+            facet.clone(),
+            "test/branch-scoped".to_string(),
+            preserves::IOValue::symbol("experiment"),
+            experiment.clone(),
+        )
+        .unwrap();
+
+    let listed = |control: &Control| {
+        let mut ids: Vec<Uuid> = control.list_entities().iter().map(|info| info.id).collect();
+        ids.sort();
+        ids
+    };
+    let sorted = |mut ids: Vec<Uuid>| {
+        ids.sort();
+        ids
+    };
+    let deliveries = |control: &mut Control| {
+        let before = SCOPED_DELIVERIES.load(Ordering::SeqCst);
+        control
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::symbol("ping"),
+            )
+            .unwrap();
+        SCOPED_DELIVERIES.load(Ordering::SeqCst) - before
+    };
+
+    assert_eq!(listed(&control), sorted(vec![global, main_only]));
+    assert_eq!(deliveries(&mut control), 2);
+
+    control.switch_branch(experiment.clone()).unwrap();
+    assert_eq!(
+        listed(&control),
+        sorted(vec![global, main_only, experiment_only])
+    );
+    assert_eq!(deliveries(&mut control), 3);
+
+    // Narrowing the scope detaches the instance from the current branch
+    control
+        .set_entity_branches(main_only, Some([BranchId::main()].into_iter().collect()))
+        .unwrap();
+    assert_eq!(listed(&control), sorted(vec![global, experiment_only]));
+    assert_eq!(deliveries(&mut control), 2);
+
+    control.switch_branch(BranchId::main()).unwrap();
+    assert_eq!(listed(&control), sorted(vec![global, main_only]));
+    assert_eq!(deliveries(&mut control), 2);
+}
+
+/// Entity that asserts whether the secrets it asks for are available.
+struct SecretReader;
+
+impl Entity for SecretReader {
+    fn on_message(
+        &self,
+        activation: &mut Activation,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        let name = payload
+            .as_symbol()
+            .map(|name| name.to_string())
+            .unwrap_or_default();
+        let length = activation
+            .secret(&name)
+            .map(|value| value.len())
+            .unwrap_or(0);
+        activation.assert(
+            Handle::new(),
+            preserves::IOValue::record(
+                preserves::IOValue::symbol("secret-length"),
+                vec![preserves::IOValue::new(length as i64)],
+            ),
+        );
+        Ok(())
+    }
+}
+
+#[test]
+fn test_secret_access_is_journaled_without_values() {
+    EntityCatalog::global().register("test/secret-reader", |_config| Ok(Box::new(SecretReader)));
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 1,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    control.set_secret("api-key", "sk-very-secret-value");
+
+    let actor = ActorId::new();
+    let facet = FacetId::new();
+    control
+        .register_entity(
+            actor.clone(),
+            facet.clone(),
+            "test/secret-reader".to_string(),
+            preserves::IOValue::symbol("config"),
+        )
+        .unwrap();
+    for name in ["api-key", "missing"] {
+        control
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::symbol(name),
+            )
+            .unwrap();
+    }
+
+    let accesses = control
+        .secret_accesses(&duet::runtime::turn::BranchId::main())
+        .unwrap();
+    let seen: Vec<(&str, bool)> = accesses
+        .iter()
+        .map(|access| (access.name.as_str(), access.found))
+        .collect();
+    assert_eq!(seen, vec![("api-key", true), ("missing", false)]);
+    assert!(accesses.iter().all(|access| access.actor == actor));
+
+    // Neither the journal nor snapshots ever contain the value
+    fn contains_secret(dir: &std::path::Path) -> bool {
+        fs::read_dir(dir).unwrap().flatten().any(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                contains_secret(&path)
+            } else {
+                let bytes = fs::read(&path).unwrap();
+                bytes
+                    .windows(b"sk-very-secret-value".len())
+                    .any(|window| window == b"sk-very-secret-value")
+            }
+        })
+    }
+    assert!(!contains_secret(temp.path()));
+}
+
+#[test]
+fn test_export_actor_state_at_head_and_earlier_turn() {
+    struct NoteTaker {
+        last: Mutex<String>,
+    }
+
+    impl Entity for NoteTaker {
+        fn on_message(
+            &self,
+            activation: &mut Activation,
+            payload: &preserves::IOValue,
+        ) -> ActorResult<()> {
+            let note = payload.as_string().unwrap_or_default().to_string();
+            activation.assert(Handle::new(), preserves::IOValue::new(note.clone()));
+            *self.last.lock().unwrap() = note;
+            Ok(())
+        }
+    }
+
+    impl HydratableEntity for NoteTaker {
+        fn snapshot_state(&self) -> preserves::IOValue {
+            preserves::IOValue::new(self.last.lock().unwrap().clone())
+        }
+
+        fn restore_state(&mut self, state: &preserves::IOValue) -> ActorResult<()> {
+            *self.last.lock().unwrap() = state.as_string().unwrap_or_default().to_string();
+            Ok(())
+        }
+    }
+
+    EntityCatalog::global().register_hydratable("export-note-taker", |_config| {
+        Ok(NoteTaker {
+            last: Mutex::new(String::new()),
+        })
+    });
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 1,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+    control
+        .register_entity(
+            actor_id.clone(),
+            facet_id.clone(),
+            "export-note-taker".to_string(),
+            preserves::IOValue::symbol("cfg"),
+        )
+        .unwrap();
+    let send = |control: &mut Control, note: &str| {
+        control
+            .send_message(
+                actor_id.clone(),
+                facet_id.clone(),
+                preserves::IOValue::new(note.to_string()),
+            )
+            .unwrap()
+    };
+    let first = send(&mut control, "first");
+    send(&mut control, "second");
+    // Another actor's state stays out of the export
+    control
+        .assert_value(ActorId::new(), preserves::IOValue::symbol("elsewhere"))
+        .unwrap();
+
+    let notes = |document: &preserves::IOValue| -> Vec<String> {
+        let mut notes: Vec<String> = document
+            .index(2)
+            .iter()
+            .map(|assertion| assertion.index(1).as_string().unwrap().to_string())
+            .collect();
+        notes.sort();
+        notes
+    };
+    let entity_states = |document: &preserves::IOValue| -> Vec<String> {
+        document
+            .index(5)
+            .entries()
+            .map(|(_id, entity)| entity.index(2).as_string().unwrap().to_string())
+            .collect()
+    };
+
+    let head = control.export_actor_state(&actor_id, None).unwrap();
+    assert_eq!(head.label().as_symbol().unwrap().as_ref(), "actor-state");
+    assert_eq!(
+        head.index(0).as_string().unwrap().as_ref(),
+        actor_id.to_string()
+    );
+    assert_eq!(notes(&head), vec!["first", "second"]);
+    assert_eq!(entity_states(&head), vec!["second"]);
+    // Canonical: exporting unchanged state again gives an equal document
+    assert_eq!(head, control.export_actor_state(&actor_id, None).unwrap());
+
+    let earlier = control.export_actor_state(&actor_id, Some(&first)).unwrap();
+    assert_eq!(
+        earlier.index(1).as_string().unwrap().as_ref(),
+        first.to_string()
+    );
+    assert_eq!(notes(&earlier), vec!["first"]);
+    assert_eq!(entity_states(&earlier), vec!["first"]);
+
+    let unknown = control.export_actor_state(&actor_id, Some(&TurnId::new("missing".into())));
+    assert!(matches!(unknown, Err(RuntimeError::Journal(_))));
+}
+
+#[test]
+fn test_rewind_reports_side_effects_and_offers_compensation() {
+    use duet::runtime::effects::{EffectKind, SideEffect};
+    use preserves::IOValue;
+
+    let temp = TempDir::new().unwrap();
+    let workspace_root = temp.path().join("project");
+    fs::create_dir_all(&workspace_root).unwrap();
+    fs::write(workspace_root.join("notes.txt"), "original").unwrap();
+
+    let config = RuntimeConfig {
+        root: temp.path().join("state"),
+        snapshot_interval: 100,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+    control
+        .register_entity(
+            actor_id.clone(),
+            facet_id.clone(),
+            "workspace".to_string(),
+            IOValue::new(workspace_root.to_string_lossy().to_string()),
+        )
+        .unwrap();
+    control
+        .send_message(
+            actor_id.clone(),
+            facet_id.clone(),
+            IOValue::symbol("workspace-rescan"),
+        )
+        .unwrap();
+    let before_write = control
+        .send_message(
+            actor_id.clone(),
+            facet_id.clone(),
+            IOValue::record(
+                IOValue::symbol("workspace-write"),
+                vec![IOValue::new("notes.txt".to_string())],
+            ),
+        )
+        .unwrap();
+    let write_cap = control
+        .list_capabilities()
+        .into_iter()
+        .find(|cap| cap.kind == "workspace/write")
+        .unwrap();
+    control
+        .invoke_capability(
+            write_cap.id,
+            IOValue::record(
+                IOValue::symbol("workspace-write"),
+                vec![
+                    IOValue::new("notes.txt".to_string()),
+                    IOValue::new("changed".to_string()),
+                ],
+            ),
+        )
+        .unwrap();
+
+    let ledger = control.side_effects(&BranchId::main()).unwrap();
+    assert_eq!(ledger.len(), 1);
+    assert_eq!(ledger[0].kind, EffectKind::FileWrite);
+    assert_eq!(ledger[0].target, "notes.txt");
+
+    let restore_root = workspace_root.clone();
+    control.register_compensation(
+        EffectKind::FileWrite,
+        Arc::new(move |effect: &SideEffect| {
+            fs::write(restore_root.join(&effect.target), "original").map_err(|e| e.to_string())
+        }),
+    );
+
+    let head = control.branch_head(&BranchId::main()).unwrap().turn_id;
+    control.goto(before_write.clone()).unwrap();
+
+    // The file is still changed, and the rewind says so
+    assert_eq!(
+        fs::read_to_string(workspace_root.join("notes.txt")).unwrap(),
+        "changed"
+    );
+    let warning = control.rewind_warning().cloned().expect("rewind warning");
+    assert_eq!(warning.from, head);
+    assert_eq!(warning.to, before_write);
+    assert_eq!(warning.effects.len(), 1);
+    assert!(warning.effects[0].compensable);
+    assert_eq!(warning.effects[0].effect, ledger[0]);
+
+    control.compensate(&warning.effects[0].effect).unwrap();
+    assert_eq!(
+        fs::read_to_string(workspace_root.join("notes.txt")).unwrap(),
+        "original"
+    );
+
+    // Moving forward again undoes nothing
+    control.goto(head).unwrap();
+    assert!(control.rewind_warning().is_none());
+
+    let unhandled = SideEffect {
+        kind: EffectKind::Network,
+        ..ledger[0].clone()
+    };
+    assert!(matches!(
+        control.compensate(&unhandled),
+        Err(RuntimeError::Compensation(_))
+    ));
+}
+
+#[test]
+fn test_replay_hydrates_unavailable_entities_as_stubs() {
+    EntityCatalog::global().register("stub-counter", |_config| {
+        Ok(Box::new(CounterEntity {
+            count: Arc::new(AtomicUsize::new(0)),
+        }))
+    });
+    EntityCatalog::global().register("stub-retired", |_config| {
+        Ok(Box::new(CounterEntity {
+            count: Arc::new(AtomicUsize::new(0)),
+        }))
+    });
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    let (actor, facet) = (ActorId::new(), FacetId::new());
+    let (counter, retired, first_turn) = {
+        let mut control = Control::init(config.clone()).unwrap();
+        let counter = control
+            .register_entity(
+                actor.clone(),
+                facet.clone(),
+                "stub-counter".to_string(),
+                preserves::IOValue::symbol("nil"),
+            )
+            .unwrap();
+        let retired = control
+            .register_entity(
+                ActorId::new(),
+                FacetId::new(),
+                "stub-retired".to_string(),
+                preserves::IOValue::symbol("nil"),
+            )
+            .unwrap();
+        let first_turn = control
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::symbol("a"),
+            )
+            .unwrap();
+        control
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::symbol("b"),
+            )
+            .unwrap();
+        (counter, retired, first_turn)
+    };
+
+    // A trimmed-down build no longer provides one of the types
+    let entities_path = temp.path().join("meta").join("entities.json");
+    let entities = fs::read_to_string(&entities_path).unwrap();
+    fs::write(
+        &entities_path,
+        entities.replace("\"stub-retired\"", "\"stub-unavailable\""),
+    )
+    .unwrap();
+    assert!(Control::new(config.clone()).is_err());
+
+    let mut stubbed_config = config;
+    stubbed_config.stubs.unknown_types = true;
+    stubbed_config
+        .stubs
+        .types
+        .insert("stub-counter".to_string());
+    let mut control = Control::new(stubbed_config).unwrap();
+    let mut expected = vec![counter, retired];
+    expected.sort();
+    assert_eq!(control.stubbed_entities(), expected);
+
+    // Replay works from the journal alone
+    control.goto(first_turn).unwrap();
+    assert_eq!(control.stubbed_entities(), expected);
+
+    // Stubs refuse live input rather than diverging from the journal
+    assert!(
+        control
+            .send_message(actor, facet, preserves::IOValue::symbol("c"))
+            .is_err()
+    );
+}
+
+#[test]
+fn test_checkpoint_push_and_pull_rebuilds_branch() {
+    EntityCatalog::global().register("checkpoint-counter", |_config| {
+        Ok(Box::new(CounterEntity {
+            count: Arc::new(AtomicUsize::new(0)),
+        }))
+    });
+
+    let config_for = |root: &std::path::Path| RuntimeConfig {
+        root: root.to_path_buf(),
+        snapshot_interval: 2,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let store = TempDir::new().unwrap();
+    let store = store.path().to_str().unwrap().to_string();
+
+    let source_dir = TempDir::new().unwrap();
+    let (actor, facet) = (ActorId::new(), FacetId::new());
+    let mut source = Control::init(config_for(source_dir.path())).unwrap();
+    let entity = source
+        .register_entity(
+            actor.clone(),
+            facet.clone(),
+            "checkpoint-counter".to_string(),
+            preserves::IOValue::symbol("nil"),
+        )
+        .unwrap();
+    for payload in ["a", "b", "c"] {
+        source
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::symbol(payload),
+            )
+            .unwrap();
+    }
+    let head = source.branch_head(&BranchId::main()).unwrap();
+
+    let first = source
+        .push_checkpoint(&BranchId::main(), &store, "v1", None)
+        .unwrap();
+    assert_eq!((first.uploaded, first.reused), (4, 0));
+    // Only the config blob (with its export time) changes between pushes
+    let second = source
+        .push_checkpoint(&BranchId::main(), &store, "v2", None)
+        .unwrap();
+    assert_eq!((second.uploaded, second.reused), (1, 3));
+
+    let target_dir = TempDir::new().unwrap();
+    let mut target = Control::init(config_for(target_dir.path())).unwrap();
+    let imported = target
+        .pull_checkpoint(&store, &first.manifest_digest, "imported", None)
+        .unwrap();
+    let imported_head = target.branch_head(&imported).unwrap();
+    assert_eq!(imported_head.turn_id, head.turn_id);
+    assert_eq!(imported_head.turn_count, head.turn_count);
+    assert!(
+        target
+            .pull_checkpoint(&store, "v1", "imported", None)
+            .is_err()
+    );
+    assert!(target.pull_checkpoint(&store, "v3", "other", None).is_err());
+
+    // The rebuilt branch replays and keeps running on its own
+    assert!(target.list_entities().is_empty());
+    target.switch_branch(imported.clone()).unwrap();
+    target.goto(head.turn_id.clone()).unwrap();
+    let entities = target.list_entities();
+    assert_eq!(entities.len(), 1);
+    assert_eq!(entities[0].id, entity);
+    target
+        .send_message(actor, facet, preserves::IOValue::symbol("d"))
+        .unwrap();
+    assert_eq!(
+        target.branch_head(&imported).unwrap().turn_count,
+        head.turn_count + 1
+    );
+}
+
+#[test]
+fn test_archived_branches_leave_active_state_and_restore_intact() {
+    let temp = TempDir::new().unwrap();
+    let archive = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 2,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config.clone()).unwrap();
+    let (actor, facet) = (ActorId::new(), FacetId::new());
+    control
+        .send_message(
+            actor.clone(),
+            facet.clone(),
+            preserves::IOValue::symbol("a"),
+        )
+        .unwrap();
+
+    let experiment = control
+        .fork(
+            BranchId::main(),
+            BranchId::new("experiment"),
+            None,
+            Default::default(),
+        )
+        .unwrap();
+    control.switch_branch(experiment.clone()).unwrap();
+    for payload in ["b", "c", "d"] {
+        control
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::symbol(payload),
+            )
+            .unwrap();
+    }
+    let head = control.branch_head(&experiment).unwrap();
+    let history = control.history(&experiment, 0, 100).unwrap();
+
+    // The current branch cannot be archived
+    assert!(control.archive_branch(&experiment, archive.path()).is_err());
+    control.switch_branch(BranchId::main()).unwrap();
+    assert!(
+        control
+            .archive_branch(&BranchId::main(), archive.path())
+            .is_err()
+    );
+
+    let tombstone = control.archive_branch(&experiment, archive.path()).unwrap();
+    assert_eq!(tombstone.head_turn, head.turn_id);
+    assert_eq!(tombstone.turns, head.turn_count);
+    assert!(
+        control
+            .list_branches()
+            .unwrap()
+            .iter()
+            .all(|info| info.name != experiment)
+    );
+    assert!(!temp.path().join("journal").join("experiment").exists());
+    assert!(control.branch_head(&experiment).is_err());
+    // The name stays taken while the branch is archived
+    assert!(
+        control
+            .fork(
+                BranchId::main(),
+                experiment.clone(),
+                None,
+                Default::default()
+            )
+            .is_err()
+    );
+
+    // Tombstones survive a restart
+    drop(control);
+    let mut control = Control::new(config).unwrap();
+    let archived = control.list_archived_branches();
+    assert_eq!(archived, vec![tombstone]);
+
+    let restored = control.unarchive_branch(&experiment).unwrap();
+    assert_eq!(restored, experiment);
+    assert!(control.list_archived_branches().is_empty());
+    let restored_head = control.branch_head(&experiment).unwrap();
+    assert_eq!(restored_head.turn_id, head.turn_id);
+    assert_eq!(restored_head.turn_count, head.turn_count);
+    let restored_history: Vec<TurnId> = control
+        .history(&experiment, 0, 100)
+        .unwrap()
+        .into_iter()
+        .map(|summary| summary.turn_id)
+        .collect();
+    let history: Vec<TurnId> = history.into_iter().map(|summary| summary.turn_id).collect();
+    // Restored as a root branch, so inherited turns are now its own
+    assert_eq!(restored_history.len(), head.turn_count);
+    assert!(restored_history.ends_with(&history));
+    assert!(control.unarchive_branch(&experiment).is_err());
+}
+
+#[test]
+fn test_delete_branch_refuses_unmerged_work_unless_forced() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 2,
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    let (actor, facet) = (ActorId::new(), FacetId::new());
+    control
+        .send_message(
+            actor.clone(),
+            facet.clone(),
+            preserves::IOValue::symbol("a"),
+        )
+        .unwrap();
+
+    let fork_with_turns = |control: &mut Control, name: &str| {
+        control.switch_branch(BranchId::main()).unwrap();
+        let branch = control
+            .fork(
+                BranchId::main(),
+                BranchId::new(name),
+                None,
+                Default::default(),
+            )
+            .unwrap();
+        control.switch_branch(branch.clone()).unwrap();
+        for payload in ["b", "c", "d"] {
+            control
+                .send_message(
+                    actor.clone(),
+                    facet.clone(),
+                    preserves::IOValue::symbol(payload),
+                )
+                .unwrap();
+        }
+        branch
+    };
+    let merged = fork_with_turns(&mut control, "merged");
+    let scratch = fork_with_turns(&mut control, "scratch");
+
+    // The current branch cannot be deleted, even with force
+    assert!(control.delete_branch(&scratch, true).is_err());
+    control.switch_branch(BranchId::main()).unwrap();
+    assert!(control.delete_branch(&BranchId::main(), true).is_err());
+
+    assert!(control.delete_branch(&merged, false).is_err());
+    control.merge(merged.clone(), BranchId::main()).unwrap();
+    let deleted = control.delete_branch(&merged, false).unwrap();
+    assert_eq!(deleted.id, merged);
+    assert!(!temp.path().join("journal").join("merged").exists());
+    assert!(control.branch_head(&merged).is_err());
+
+    assert!(control.delete_branch(&scratch, false).is_err());
+    control.delete_branch(&scratch, true).unwrap();
+    assert!(!temp.path().join("journal").join("scratch").exists());
+
+    let branches = std::fs::read_to_string(temp.path().join("meta").join("branches.json")).unwrap();
+    assert!(!branches.contains("merged") && !branches.contains("scratch"));
+    assert_eq!(control.list_branches().unwrap().len(), 1);
+
+    // The name can be reused once the branch is gone
+    control
+        .fork(BranchId::main(), merged, None, Default::default())
+        .unwrap();
+}
+
+#[test]
+fn test_delete_branch_refuses_turns_recorded_after_a_merge() {
+    let temp = TempDir::new().unwrap();
+    let mut control = Control::init(RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 100,
+        ..Default::default()
+    })
+    .unwrap();
+    let (actor, facet) = (ActorId::new(), FacetId::new());
+    let send = |control: &mut Control, count: usize| {
+        for _ in 0..count {
+            control
+                .send_message(
+                    actor.clone(),
+                    facet.clone(),
+                    preserves::IOValue::symbol("tick"),
+                )
+                .unwrap();
+        }
+    };
+
+    send(&mut control, 3);
+    let experiment = control
+        .fork(
+            BranchId::main(),
+            BranchId::new("exp"),
+            None,
+            Default::default(),
+        )
+        .unwrap();
+    send(&mut control, 5);
+    control.switch_branch(experiment.clone()).unwrap();
+    send(&mut control, 1);
+    control.switch_branch(BranchId::main()).unwrap();
+    control.merge(experiment.clone(), BranchId::main()).unwrap();
+
+    // The actor's clock on main now dominates anything it does on exp
+    control.switch_branch(experiment.clone()).unwrap();
+    send(&mut control, 1);
+    control.switch_branch(BranchId::main()).unwrap();
+    assert!(control.delete_branch(&experiment, false).is_err());
+
+    control.merge(experiment.clone(), BranchId::main()).unwrap();
+    control.delete_branch(&experiment, false).unwrap();
+}
+
+#[test]
+fn test_branch_names_cannot_escape_storage() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    control
+        .assert_value(ActorId::new(), preserves::IOValue::symbol("a"))
+        .unwrap();
+
+    for name in ["..", ".", "../../outside", "a/../../b", "/tmp/abs", "a\\b"] {
+        assert!(
+            control
+                .fork(
+                    BranchId::main(),
+                    BranchId::new(name),
+                    None,
+                    Default::default()
+                )
+                .is_err(),
+            "fork {name:?} should be refused"
+        );
+    }
+    assert!(control.delete_branch(&BranchId::new(".."), true).is_err());
+    assert!(temp.path().join("journal").join("main").exists());
+
+    // Deleting a branch leaves the storage of branches nested under its name
+    let agent = control
+        .fork(
+            BranchId::main(),
+            BranchId::new("agent"),
+            None,
+            Default::default(),
+        )
+        .unwrap();
+    let nested = control
+        .fork(
+            BranchId::main(),
+            BranchId::new("agent/req-1"),
+            None,
+            Default::default(),
+        )
+        .unwrap();
+    control.switch_branch(nested.clone()).unwrap();
+    control
+        .assert_value(ActorId::new(), preserves::IOValue::symbol("nested"))
+        .unwrap();
+    let before = control.history(&nested, 0, 10).unwrap();
+    assert!(!before.is_empty());
+    control.switch_branch(BranchId::main()).unwrap();
+    control.delete_branch(&agent, true).unwrap();
+    control.switch_branch(nested.clone()).unwrap();
+    assert_eq!(control.history(&nested, 0, 10).unwrap().len(), before.len());
+    assert!(
+        temp.path()
+            .join("journal")
+            .join("agent")
+            .join("req-1")
+            .exists()
+    );
+}
+
+#[test]
+fn test_kv_store_follows_time_travel_and_restart() {
+    use duet::codebase::{self, kv};
+    use preserves::IOValue;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 1,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    let get = |control: &mut Control, cap: Uuid| {
+        control
+            .invoke_capability(
+                cap,
+                IOValue::record(
+                    IOValue::symbol("kv-get"),
+                    vec![IOValue::new("plan".to_string())],
+                ),
+            )
+            .unwrap()
+    };
+    let value_reply = |version: i64, value: &str| {
+        IOValue::record(
+            IOValue::symbol("kv-value"),
+            vec![
+                IOValue::new("plan".to_string()),
+                IOValue::new(version),
+                IOValue::new(value.to_string()),
+            ],
+        )
+    };
+
+    let (get_cap, drafted) = {
+        let mut control = Control::init(config.clone()).unwrap();
+        let store = codebase::ensure_kv_entity(&mut control, "scratch").unwrap();
+        let put_cap =
+            codebase::request_kv_capability(&mut control, &store, kv::CAP_KIND_PUT, "").unwrap();
+        let cas_cap =
+            codebase::request_kv_capability(&mut control, &store, kv::CAP_KIND_CAS, "").unwrap();
+        let get_cap =
+            codebase::request_kv_capability(&mut control, &store, kv::CAP_KIND_GET, "").unwrap();
+
+        let put = IOValue::record(
+            IOValue::symbol("kv-put"),
+            vec![
+                IOValue::new("plan".to_string()),
+                IOValue::new("draft".to_string()),
+            ],
+        );
+        control.invoke_capability(put_cap, put).unwrap();
+        let drafted = control
+            .runtime()
+            .branch_manager()
+            .head(&control.runtime().current_branch())
+            .cloned()
+            .unwrap();
+
+        let cas = IOValue::record(
+            IOValue::symbol("kv-cas"),
+            vec![
+                IOValue::new("plan".to_string()),
+                IOValue::new(1),
+                IOValue::new("final".to_string()),
+            ],
+        );
+        control.invoke_capability(cas_cap, cas).unwrap();
+        assert_eq!(get(&mut control, get_cap), value_reply(2, "final"));
+
+        // The store is visible as dataspace assertions
+        let entries: Vec<_> = control
+            .list_assertions_for_actor(&store.actor)
+            .into_iter()
+            .filter(|(_, value)| {
+                duet::util::io_value::record_with_label(value, kv::KV_ENTRY_LABEL).is_some()
+            })
+            .collect();
+        assert_eq!(entries.len(), 1);
+
+        control.goto(drafted.clone()).unwrap();
+        assert_eq!(get(&mut control, get_cap), value_reply(1, "draft"));
+        (get_cap, drafted)
+    };
+
+    let mut control = Control::new(config).unwrap();
+    control.goto(drafted).unwrap();
+    assert_eq!(get(&mut control, get_cap), value_reply(1, "draft"));
+}
+
+#[test]
+fn test_init_templates_register_profiles() {
+    use duet::codebase::template::{self, InitTemplate};
+
+    let temp = TempDir::new().unwrap();
+    let config = |root: std::path::PathBuf| RuntimeConfig {
+        root,
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    let bare_root = temp.path().join("bare");
+    let mut control = Control::init(config(bare_root.clone())).unwrap();
+    let manifest = template::apply_template(&mut control, InitTemplate::Bare, temp.path()).unwrap();
+    assert!(manifest.entities.is_empty());
+    assert!(control.list_entities().is_empty());
+
+    let sandbox_root = temp.path().join("sandbox");
+    let mut control = Control::init(config(sandbox_root.clone())).unwrap();
+    let manifest =
+        template::apply_template(&mut control, InitTemplate::AgentSandbox, temp.path()).unwrap();
+    let mut types: Vec<_> = manifest
+        .entities
+        .iter()
+        .map(|entity| entity.entity_type.as_str())
+        .collect();
+    types.sort();
+    assert_eq!(types.len(), 4);
+    assert!(types.contains(&"kv"));
+    assert!(!types.contains(&"workspace"));
+    assert_eq!(manifest.reactions.len(), 3);
+
+    // Re-applying reuses what is already there
+    let again =
+        template::apply_template(&mut control, InitTemplate::AgentSandbox, temp.path()).unwrap();
+    assert_eq!(again.entities, manifest.entities);
+    assert_eq!(again.reactions, manifest.reactions);
+    assert_eq!(control.list_entities().len(), 4);
+    assert_eq!(control.list_reactions().len(), 3);
+
+    let loaded = template::load_manifest(&sandbox_root).unwrap().unwrap();
+    assert_eq!(loaded.template, InitTemplate::AgentSandbox);
+    assert_eq!(loaded.entities, manifest.entities);
+    assert!(template::load_manifest(temp.path()).unwrap().is_none());
+}
+
+#[test]
+fn test_bootstrap_detects_plans_and_applies_setup() {
+    use duet::codebase::{
+        self,
+        bootstrap::{Bootstrap, RUNTIME_DIR},
+        template::InitTemplate,
+    };
+
+    let project = TempDir::new().unwrap();
+    fs::write(project.path().join("Cargo.toml"), "[package]\n").unwrap();
+    fs::write(project.path().join(".gitignore"), "secrets.env\n").unwrap();
+    fs::write(project.path().join("secrets.env"), "TOKEN=1").unwrap();
+    fs::write(project.path().join("main.rs"), "fn main() {}").unwrap();
+    fs::create_dir_all(project.path().join("target")).unwrap();
+    fs::write(project.path().join("target").join("out.bin"), "bin").unwrap();
+
+    let mut bootstrap = Bootstrap::detect(project.path()).unwrap();
+    assert!(!bootstrap.detection().initialized);
+    assert!(bootstrap.plan().ignore.contains(&"target".to_string()));
+    assert!(bootstrap.plan().ignore.contains(&"secrets.env".to_string()));
+    assert!(bootstrap.plan().config.root.ends_with(RUNTIME_DIR));
+    bootstrap.plan_mut().agents = Vec::new();
+
+    let outcome = bootstrap.apply().unwrap();
+    assert!(outcome.created);
+    assert_eq!(outcome.manifest.template, InitTemplate::CodebaseDaemon);
+    assert_eq!(outcome.manifest.entities.len(), 2);
+
+    let control = outcome.control;
+    let handle = codebase::workspace_handle(&control).unwrap();
+    let paths: Vec<String> = codebase::list_workspace_entries(&control, &handle)
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect();
+    assert!(paths.iter().any(|path| path == "main.rs"));
+    assert!(!paths.iter().any(|path| path.starts_with("target")));
+    assert!(!paths.iter().any(|path| path == "secrets.env"));
+    assert!(!paths.iter().any(|path| path.starts_with(RUNTIME_DIR)));
+    drop(control);
+
+    // A second run sees the existing runtime and keeps what it set up
+    let mut again = Bootstrap::detect(project.path()).unwrap();
+    assert!(again.detection().initialized);
+    assert_eq!(
+        again.detection().manifest.as_ref().unwrap().entities,
+        outcome.manifest.entities
+    );
+    again.plan_mut().agents = Vec::new();
+    let outcome = again.apply().unwrap();
+    assert!(!outcome.created);
+    assert_eq!(outcome.control.list_entities().len(), 2);
+}
+
+#[test]
+fn test_workspace_writes_back_up_and_restore_previous_content() {
+    use duet::codebase;
+
+    let temp = TempDir::new().unwrap();
+    let workspace_root = temp.path().join("workspace");
+    fs::create_dir_all(&workspace_root).unwrap();
+    fs::write(workspace_root.join("notes.txt"), "original").unwrap();
+
+    let config = RuntimeConfig {
+        root: temp.path().join("runtime"),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let handle = codebase::ensure_workspace_entity(&mut control, &workspace_root).unwrap();
+
+    let head = |control: &Control| {
+        control
+            .runtime()
+            .branch_manager()
+            .head(&control.runtime().current_branch())
+            .cloned()
+            .unwrap()
+    };
+
+    codebase::write_file(&mut control, &handle, "notes.txt", "edited").unwrap();
+    let edit_turn = head(&control);
+    codebase::write_file(&mut control, &handle, "fresh.txt", "new file").unwrap();
+    let create_turn = head(&control);
+
+    let backups = control.file_backups(&BranchId::main()).unwrap();
+    assert_eq!(backups.len(), 2);
+    assert_eq!(backups[0].path, "notes.txt");
+    assert_eq!(backups[0].previous.as_deref(), Some(&b"original"[..]));
+    assert_eq!(backups[1].previous, None);
+
+    let restored = codebase::restore_file(&mut control, &handle, "notes.txt", &edit_turn).unwrap();
+    assert_eq!(restored.turn_id, edit_turn);
+    assert_eq!(
+        fs::read_to_string(workspace_root.join("notes.txt")).unwrap(),
+        "original"
+    );
+
+    codebase::restore_file(&mut control, &handle, "fresh.txt", &create_turn).unwrap();
+    assert!(!workspace_root.join("fresh.txt").exists());
+    assert!(
+        codebase::list_workspace_entries(&control, &handle)
+            .iter()
+            .all(|entry| entry.path != "fresh.txt")
+    );
+
+    assert!(matches!(
+        control.restore_file("notes.txt", &create_turn),
+        Err(RuntimeError::Restore(_))
+    ));
+}
+
+#[test]
+fn test_entity_state_can_be_read_and_repaired() {
+    struct Tally(Mutex<i64>);
+
+    impl Entity for Tally {
+        fn on_message(
+            &self,
+            _activation: &mut Activation,
+            payload: &preserves::IOValue,
+        ) -> ActorResult<()> {
+            if let Some(amount) = payload.as_signed_integer() {
+                *self.0.lock().unwrap() += i64::try_from(amount.as_ref()).unwrap_or(0);
+            }
+            Ok(())
+        }
+    }
+
+    impl HydratableEntity for Tally {
+        fn snapshot_state(&self) -> preserves::IOValue {
+            preserves::IOValue::new(*self.0.lock().unwrap())
+        }
+
+        fn restore_state(&mut self, state: &preserves::IOValue) -> ActorResult<()> {
+            let value = state
+                .as_signed_integer()
+                .and_then(|value| i64::try_from(value.as_ref()).ok())
+                .ok_or_else(|| ActorError::InvalidActivation("expected integer".to_string()))?;
+            *self.0.lock().unwrap() = value;
+            Ok(())
+        }
+    }
+
+    EntityCatalog::global()
+        .register_hydratable("repairable-tally", |_config| Ok(Tally(Mutex::new(0))));
+    EntityCatalog::global().register("stateless-counter", |_config| {
+        Ok(Box::new(CounterEntity {
+            count: Arc::new(AtomicUsize::new(0)),
+        }))
+    });
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 100,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    let tally = {
+        let mut control = Control::init(config.clone()).unwrap();
+        let actor = ActorId::new();
+        let facet = FacetId::new();
+        let tally = control
+            .register_entity(
+                actor.clone(),
+                facet.clone(),
+                "repairable-tally".to_string(),
+                preserves::IOValue::symbol("cfg"),
+            )
+            .unwrap();
+        let counter = control
+            .register_entity(
+                actor.clone(),
+                facet.clone(),
+                "stateless-counter".to_string(),
+                preserves::IOValue::symbol("cfg"),
+            )
+            .unwrap();
+
+        control
+            .send_message(actor.clone(), facet.clone(), preserves::IOValue::new(5))
+            .unwrap();
+        control
+            .send_message(actor, facet, preserves::IOValue::new(2))
+            .unwrap();
+        assert_eq!(
+            control.entity_state(tally).unwrap(),
+            Some(preserves::IOValue::new(7))
+        );
+        assert_eq!(control.entity_state(counter).unwrap(), None);
+        assert!(control.entity_state(Uuid::new_v4()).is_err());
+
+        control
+            .set_entity_state(tally, &preserves::IOValue::new(40))
+            .unwrap();
+        assert_eq!(
+            control.entity_state(tally).unwrap(),
+            Some(preserves::IOValue::new(40))
+        );
+
+        // Restore hooks validate the state, and stateless entities refuse it
+        assert!(
+            control
+                .set_entity_state(tally, &preserves::IOValue::symbol("bogus"))
+                .is_err()
+        );
+        assert!(
+            control
+                .set_entity_state(counter, &preserves::IOValue::new(1))
+                .is_err()
+        );
+        tally
+    };
+
+    // The repair is snapshotted, so rehydrating the head after a restart keeps it
+    let mut control = Control::new(config).unwrap();
+    let head = control
+        .runtime()
+        .branch_manager()
+        .head(&control.runtime().current_branch())
+        .cloned()
+        .unwrap();
+    control.goto(head).unwrap();
+    assert_eq!(
+        control.entity_state(tally).unwrap(),
+        Some(preserves::IOValue::new(40))
+    );
+}
+
+#[test]
+fn test_workspace_offers_grant_capabilities_only_once_accepted() {
+    use duet::codebase;
+    use preserves::IOValue;
+
+    let temp = TempDir::new().unwrap();
+    let workspace_root = temp.path().join("ws");
+    fs::create_dir_all(&workspace_root).unwrap();
+    fs::write(workspace_root.join("notes.txt"), "draft").unwrap();
+
+    let config = RuntimeConfig {
+        root: temp.path().join("runtime"),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    control
+        .register_entity(
+            ActorId::new(),
+            FacetId::new(),
+            "workspace".to_string(),
+            IOValue::record(
+                IOValue::symbol("workspace-config"),
+                vec![
+                    IOValue::new(workspace_root.to_string_lossy().to_string()),
+                    IOValue::record(IOValue::symbol("offers"), vec![]),
+                ],
+            ),
+        )
+        .unwrap();
+    let handle = codebase::workspace_handle(&control).unwrap();
+    let request = |control: &mut Control| {
+        control
+            .send_message(
+                handle.actor.clone(),
+                handle.facet.clone(),
+                IOValue::record(
+                    IOValue::symbol("workspace-read"),
+                    vec![IOValue::new("notes.txt".to_string())],
+                ),
+            )
+            .unwrap();
+    };
+    let read_caps = |control: &Control| {
+        control
+            .list_capabilities()
+            .into_iter()
+            .filter(|cap| cap.kind == "workspace/read")
+            .count()
+    };
+
+    // A request only produces an offer
+    request(&mut control);
+    let offers = codebase::list_capability_offers(&control, &handle);
+    assert_eq!(offers.len(), 1);
+    assert_eq!(offers[0].kind, "workspace/read");
+    assert_eq!(offers[0].path, "notes.txt");
+    assert_eq!(read_caps(&control), 0);
+
+    // Declining withdraws it without granting anything
+    codebase::decline_capability_offer(&mut control, &handle, offers[0].id).unwrap();
+    assert!(codebase::list_capability_offers(&control, &handle).is_empty());
+    assert_eq!(read_caps(&control), 0);
+
+    // Accepting is its own turn and grants the capability
+    request(&mut control);
+    let offer = codebase::list_capability_offers(&control, &handle)[0].clone();
+    let before = control.history(&BranchId::main(), 0, 100).unwrap().len();
+    let cap = codebase::accept_capability_offer(&mut control, &handle, offer.id).unwrap();
+    assert_eq!(
+        control.history(&BranchId::main(), 0, 100).unwrap().len(),
+        before + 1
+    );
+    assert!(codebase::list_capability_offers(&control, &handle).is_empty());
+    let content = control
+        .invoke_capability(
+            cap,
+            IOValue::record(
+                IOValue::symbol("workspace-read"),
+                vec![IOValue::new("notes.txt".to_string())],
+            ),
+        )
+        .unwrap();
+    assert_eq!(content.as_string().unwrap().as_ref(), "draft");
+
+    // Offers are settled once; the helpers accept on the caller's behalf
+    assert!(codebase::accept_capability_offer(&mut control, &handle, offer.id).is_err());
+    assert_eq!(
+        codebase::read_file(&mut control, &handle, "notes.txt").unwrap(),
+        "draft"
+    );
+    assert!(codebase::list_capability_offers(&control, &handle).is_empty());
+}
+
+#[test]
+fn test_workspace_reads_decode_non_utf8_content_on_request() {
+    use duet::codebase::{
+        self,
+        workspace::encoding::{DecodedData, ReadEncoding},
+    };
+
+    let temp = TempDir::new().unwrap();
+    let workspace_root = temp.path().join("ws");
+    fs::create_dir_all(&workspace_root).unwrap();
+    let latin1 = b"na\xefve caf\xe9".to_vec();
+    fs::write(workspace_root.join("legacy.txt"), &latin1).unwrap();
+
+    let config = RuntimeConfig {
+        root: temp.path().join("runtime"),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let handle = codebase::ensure_workspace_entity(&mut control, &workspace_root).unwrap();
+
+    // The plain form stays strict
+    let err = codebase::read_file(&mut control, &handle, "legacy.txt").unwrap_err();
+    assert!(err.to_string().contains("not valid UTF-8"), "{err}");
+
+    let bytes =
+        codebase::read_file_with(&mut control, &handle, "legacy.txt", ReadEncoding::Bytes).unwrap();
+    assert_eq!(bytes.encoding, "binary");
+    assert_eq!(bytes.data, DecodedData::Bytes(latin1.clone()));
+    assert_eq!(bytes.size, latin1.len() as u64);
+    assert_eq!(bytes.digest, blake3::hash(&latin1).to_hex().to_string());
+
+    let lossy =
+        codebase::read_file_with(&mut control, &handle, "legacy.txt", ReadEncoding::Lossy).unwrap();
+    assert_eq!(
+        lossy.data,
+        DecodedData::Text("na\u{FFFD}ve caf\u{FFFD}".into())
+    );
+    assert_eq!(lossy.replacements, 2);
+
+    let detected =
+        codebase::read_file_with(&mut control, &handle, "legacy.txt", ReadEncoding::Detect)
+            .unwrap();
+    assert_eq!(detected.encoding, "iso-8859-1");
+    assert_eq!(detected.data, DecodedData::Text("naïve café".into()));
+    assert_eq!(detected.digest, bytes.digest);
+
+    // Decoding depends on the bytes alone, so repeated reads agree exactly
+    let again = codebase::read_file_with(&mut control, &handle, "legacy.txt", ReadEncoding::Detect)
+        .unwrap();
+    assert_eq!(again, detected);
+}
+
+#[test]
+fn test_capability_invoker_respects_concurrency_class() {
+    let serve = |entity_type: &'static str, class: Option<ConcurrencyClass>| {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        EntityCatalog::global().register(entity_type, move |_config| {
+            Ok(Box::new(SelfInvokingEntity {
+                calls: counter.clone(),
+            }))
+        });
+        if let Some(class) = class {
+            assert!(EntityCatalog::global().declare_concurrency(entity_type, class));
+        }
+
+        let temp = TempDir::new().unwrap();
+        let config = RuntimeConfig {
+            root: temp.path().to_path_buf(),
+            ..Default::default()
+        };
+        let mut control = Control::init(config).unwrap();
+        let actor = ActorId::new();
+        let facet = FacetId::new();
+        control
+            .register_entity(
+                actor.clone(),
+                facet.clone(),
+                entity_type.to_string(),
+                preserves::IOValue::symbol("cfg"),
+            )
+            .unwrap();
+        control
+            .send_message(actor.clone(), facet, preserves::IOValue::symbol("grant"))
+            .unwrap();
+        let cap = control.list_capabilities_for_actor(&actor)[0].id;
+
+        let result = control
+            .invoke_capability(cap, preserves::IOValue::new(2))
+            .unwrap();
+        assert_eq!(result, preserves::IOValue::new(2));
+        (
+            control.entity_concurrency(entity_type).unwrap(),
+            calls.load(Ordering::SeqCst),
+        )
+    };
+
+    assert!(
+        !EntityCatalog::global()
+            .declare_concurrency("test/undeclared", ConcurrencyClass::Reentrant)
+    );
+
+    // Exclusive entities refuse the nested call back into themselves
+    assert_eq!(
+        serve("test/countdown-exclusive", None),
+        (ConcurrencyClass::Exclusive, 1)
+    );
+    assert_eq!(
+        serve(
+            "test/countdown-reentrant",
+            Some(ConcurrencyClass::Reentrant)
+        ),
+        (ConcurrencyClass::Reentrant, 3)
+    );
+}
+
+struct DeliveryStager;
+
+impl Entity for DeliveryStager {
+    fn on_message(
+        &self,
+        activation: &mut Activation,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        activation.stage_delivery("hooks", payload.clone());
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct RecordingDeliverer {
+    delivered: Mutex<Vec<(String, preserves::IOValue)>>,
+}
+
+impl duet::runtime::outbox::Deliverer for RecordingDeliverer {
+    fn deliver(&self, delivery: &duet::runtime::outbox::Delivery) -> Result<(), String> {
+        self.delivered
+            .lock()
+            .unwrap()
+            .push((delivery.id.clone(), delivery.payload.clone()));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_outbox_delivers_staged_deliveries_once_across_restarts() {
+    EntityCatalog::global().register("delivery-stager", |_config| Ok(Box::new(DeliveryStager)));
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let (actor, facet) = (ActorId::new(), FacetId::new());
+    let timeout = std::time::Duration::from_secs(5);
+
+    let turn = {
+        let mut control = Control::init(config.clone()).unwrap();
+        control
+            .register_entity(
+                actor.clone(),
+                facet.clone(),
+                "delivery-stager".into(),
+                preserves::IOValue::symbol("config"),
+            )
+            .unwrap();
+        let turn = control
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::symbol("built"),
+            )
+            .unwrap();
+        // No deliverer for the channel yet: the delivery waits
+        let status = control.flush_outbox(timeout).unwrap();
+        assert_eq!(status.pending.len(), 1);
+        assert_eq!(status.pending[0].delivery.turn_id, turn);
+        turn
+    };
+
+    let deliverer = Arc::new(RecordingDeliverer::default());
+    {
+        let mut control = Control::new(config.clone()).unwrap();
+        assert_eq!(control.outbox_status().pending.len(), 1);
+        control
+            .register_deliverer("hooks", deliverer.clone())
+            .unwrap();
+        let status = control.flush_outbox(timeout).unwrap();
+        assert!(status.pending.is_empty());
+        assert_eq!(status.acknowledged, 1);
+    }
+    {
+        let mut control = Control::new(config).unwrap();
+        control
+            .register_deliverer("hooks", deliverer.clone())
+            .unwrap();
+        let status = control.flush_outbox(timeout).unwrap();
+        assert!(status.pending.is_empty());
+        assert_eq!(status.acknowledged, 1);
+    }
+
+    let delivered = deliverer.delivered.lock().unwrap();
+    assert_eq!(delivered.len(), 1);
+    assert!(delivered[0].0.starts_with(&turn.to_string()));
+    assert_eq!(delivered[0].1, preserves::IOValue::symbol("built"));
+}
+
+static MINTED_IDS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Entity minting request ids from the actor's deterministic generator
+struct RequestMinter;
+
+impl Entity for RequestMinter {
+    fn on_message(
+        &self,
+        activation: &mut Activation,
+        _payload: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        let id = activation.rng().uuid().to_string();
+        MINTED_IDS.lock().unwrap().push(id.clone());
+        activation.assert(
+            Handle::new(),
+            preserves::IOValue::record(
+                preserves::IOValue::symbol("request-id"),
+                vec![preserves::IOValue::new(id)],
+            ),
+        );
+        Ok(())
+    }
+}
+
+#[test]
+fn test_rng_draws_repeat_on_forks_from_the_same_turn() {
+    use duet::runtime::branch::BranchDetails;
+    use duet::runtime::history::HistoryDetail;
+
+    EntityCatalog::global().register("request-minter", |_config| Ok(Box::new(RequestMinter)));
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let (actor, facet) = (ActorId::new(), FacetId::new());
+    let mut control = Control::init(config).unwrap();
+    control
+        .register_entity(
+            actor.clone(),
+            facet.clone(),
+            "request-minter".into(),
+            preserves::IOValue::symbol("config"),
+        )
+        .unwrap();
+    let base = control.branch_head(&BranchId::main()).unwrap().turn_id;
+    let mint = |control: &mut Control| {
+        control
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::symbol("mint"),
+            )
+            .unwrap();
+        MINTED_IDS.lock().unwrap().last().cloned().unwrap()
+    };
+
+    let first = mint(&mut control);
+    let second = mint(&mut control);
+    assert_ne!(first, second);
+
+    // The draw is journaled with the turn
+    let main = BranchId::main();
+    let summaries = control.history(&main, 0, 100).unwrap();
+    let views = control
+        .turn_views(&main, summaries, HistoryDetail::Full)
+        .unwrap();
+    let drawn = views
+        .iter()
+        .flat_map(|view| view.outputs.iter().flatten())
+        .filter(|output| output.kind == "random_drawn")
+        .count();
+    assert_eq!(drawn, 2);
+
+    // A fork from the same point draws the same sequence
+    control
+        .fork(
+            main.clone(),
+            BranchId::new("retry"),
+            Some(base),
+            BranchDetails::default(),
+        )
+        .unwrap();
+    control.switch_branch(BranchId::new("retry")).unwrap();
+    assert_eq!(mint(&mut control), first);
+    assert_eq!(mint(&mut control), second);
+}
+
+/// Entity arming timers that ring back into its own facet
+struct AlarmClock;
+
+impl Entity for AlarmClock {
+    fn on_message(
+        &self,
+        activation: &mut Activation,
+        payload: &preserves::IOValue,
+    ) -> ActorResult<()> {
+        let symbol = payload.as_symbol();
+        let delay = match symbol.as_ref().map(|symbol| symbol.as_ref()) {
+            Some("arm") => std::time::Duration::ZERO,
+            Some("arm-later") => std::time::Duration::from_secs(3600),
+            _ => {
+                activation.assert(Handle::new(), preserves::IOValue::symbol("rang"));
+                return Ok(());
+            }
+        };
+        activation.set_timer(delay, preserves::IOValue::symbol("ring"));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_timers_fire_as_journaled_turns_and_follow_time_travel() {
+    use duet::runtime::history::HistoryDetail;
+
+    EntityCatalog::global().register("alarm-clock", |_config| Ok(Box::new(AlarmClock)));
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let (actor, facet) = (ActorId::new(), FacetId::new());
+    let mut control = Control::init(config).unwrap();
+    control
+        .register_entity(
+            actor.clone(),
+            facet.clone(),
+            "alarm-clock".into(),
+            preserves::IOValue::symbol("config"),
+        )
+        .unwrap();
+
+    control
+        .send_message(
+            actor.clone(),
+            facet.clone(),
+            preserves::IOValue::symbol("arm-later"),
+        )
+        .unwrap();
+    assert_eq!(control.pending_timers().len(), 1);
+    assert!(control.next_timer_deadline().unwrap() > chrono::Utc::now());
+    assert_eq!(control.fire_due_timers().unwrap(), 0);
+
+    // A due timer fires as a turn of its own
+    control
+        .send_message(
+            actor.clone(),
+            facet.clone(),
+            preserves::IOValue::symbol("arm"),
+        )
+        .unwrap();
+    assert_eq!(control.pending_timers().len(), 2);
+    assert_eq!(control.fire_due_timers().unwrap(), 1);
+    let main = BranchId::main();
+    let summaries = control.history(&main, 0, 100).unwrap();
+    let views = control
+        .turn_views(&main, summaries, HistoryDetail::Summary)
+        .unwrap();
+    let last = views.last().unwrap();
+    assert_eq!(last.inputs.len(), 1);
+    assert_eq!(last.inputs[0].kind, "timer");
+    assert_eq!(control.pending_timers().len(), 1);
+    let rang = preserves::IOValue::symbol("rang");
+    let rang_count = |control: &Control| {
+        control
+            .runtime()
+            .assertions_for_actor(&actor)
+            .unwrap()
+            .into_iter()
+            .filter(|(_, value)| *value == rang)
+            .count()
+    };
+    assert_eq!(rang_count(&control), 1);
+
+    // Rewinding before the wakeup makes the timer pending again
+    control.back(1).unwrap();
+    assert_eq!(rang_count(&control), 0);
+    assert_eq!(control.pending_timers().len(), 2);
+    assert_eq!(control.fire_due_timers().unwrap(), 1);
+    assert_eq!(rang_count(&control), 1);
+    assert_eq!(control.pending_timers().len(), 1);
+}
@@ -0,0 +1,85 @@
+//! gRPC control-plane transport tests (feature `grpc`)
+
+#![cfg(feature = "grpc")]
+
+use duet::runtime::RuntimeConfig;
+use duet::runtime::control::Control;
+use duet::service::grpc::{
+    self,
+    proto::{CommandRequest, TailRequest, control_plane_client::ControlPlaneClient},
+};
+use serde_json::{Value, json};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+
+async fn start(temp: &TempDir) -> ControlPlaneClient {
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    Control::init(config.clone()).unwrap();
+    let control = Control::new(config).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(grpc::serve_listener(control, listener));
+    ControlPlaneClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap()
+}
+
+async fn call(client: &mut ControlPlaneClient, command: &str, params: Value) -> Value {
+    let response = client
+        .call(CommandRequest {
+            command: command.to_string(),
+            params_json: params.to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    match response.error {
+        Some(error) => json!({"error": {"code": error.code, "message": error.message}}),
+        None => json!({"result": serde_json::from_str::<Value>(&response.result_json).unwrap()}),
+    }
+}
+
+#[tokio::test]
+async fn grpc_calls_commands_and_streams_event_tails() {
+    let temp = TempDir::new().unwrap();
+    let mut client = start(&temp).await;
+
+    let status = call(&mut client, "status", json!({})).await;
+    assert_eq!(status["result"]["active_branch"], "main");
+
+    let unknown = call(&mut client, "no_such_command", json!({})).await;
+    assert_eq!(unknown["error"]["code"], "unsupported_command");
+
+    let mut tail = client
+        .tail_events(TailRequest {
+            label: "feature-flag".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    let set = call(
+        &mut client,
+        "set_flag",
+        json!({"flag": "chaos", "enabled": true}),
+    )
+    .await;
+    let turn = set["result"]["turn_id"].as_str().unwrap().to_string();
+
+    let batch = tokio::time::timeout(Duration::from_secs(10), tail.message())
+        .await
+        .expect("tail delivers the committed turn")
+        .unwrap()
+        .unwrap();
+    assert_eq!(batch.turn, turn);
+    let batch: Value = serde_json::from_str(&batch.batch_json).unwrap();
+    let event = &batch["events"][0];
+    assert_eq!(event["action"], "assert");
+    assert_eq!(event["value_structured"]["fields"][0]["value"], "chaos");
+}
@@ -1,4 +1,6 @@
-use duet::util::io_value::io_value_to_json;
+use duet::util::io_value::{
+    canonical_encoding, canonical_hash, io_value_to_json, json_to_io_value,
+};
 use preserves::IOValue;
 
 #[test]
@@ -54,3 +56,75 @@ fn atomic_string_includes_summary() {
     assert_eq!(obj.get("value").and_then(|v| v.as_str()), Some("hello"));
     assert_eq!(obj.get("summary").and_then(|v| v.as_str()), Some("hello"));
 }
+
+#[test]
+fn json_payloads_convert_to_preserves() {
+    let value = json_to_io_value(&serde_json::json!({
+        "$record": "task",
+        "fields": ["write docs", 3, 1.5, true, [{"$symbol": "urgent"}], {"owner": "ana"}],
+    }))
+    .expect("template converts");
+
+    let mut owner = preserves::Map::new();
+    owner.insert(
+        IOValue::new("owner".to_string()),
+        IOValue::new("ana".to_string()),
+    );
+    let expected = IOValue::record(
+        IOValue::symbol("task"),
+        vec![
+            IOValue::new("write docs".to_string()),
+            IOValue::new(3_i64),
+            IOValue::new(1.5_f64),
+            IOValue::new(true),
+            IOValue::new(vec![IOValue::symbol("urgent")]),
+            IOValue::new(owner),
+        ],
+    );
+    assert_eq!(value, expected);
+
+    assert!(json_to_io_value(&serde_json::Value::Null).is_err());
+    assert!(json_to_io_value(&serde_json::json!({"$record": 1})).is_err());
+}
+
+#[test]
+fn canonical_hash_ignores_construction_order_and_encoding_origin() {
+    let entry = |key: &str, value: i64| (IOValue::new(key.to_string()), IOValue::new(value));
+    let forward: preserves::Map<IOValue, IOValue> = [entry("a", 1), entry("b", 2), entry("c", 3)]
+        .into_iter()
+        .collect();
+    let backward: preserves::Map<IOValue, IOValue> = [entry("c", 3), entry("b", 2), entry("a", 1)]
+        .into_iter()
+        .collect();
+    let record = |dict| {
+        IOValue::record(
+            IOValue::symbol("config"),
+            vec![IOValue::new(dict), IOValue::new(vec![IOValue::symbol("x")])],
+        )
+    };
+    let built = record(forward);
+    assert_eq!(canonical_hash(&built), canonical_hash(&record(backward)));
+
+    // A value decoded from its packed form hashes like the original
+    let packed = preserves::write_iovalue_packed(&built, false).unwrap();
+    let decoded = preserves::read_iovalue_packed(&packed, false).unwrap();
+    assert_eq!(canonical_encoding(&decoded), canonical_encoding(&built));
+    let parsed: IOValue = r#"<config {"c": 3, "b": 2, "a": 1} [x]>"#.parse().unwrap();
+    assert_eq!(canonical_hash(&parsed), canonical_hash(&built));
+
+    // Structure matters, not just contents
+    assert_ne!(
+        canonical_hash(&IOValue::new(vec![
+            IOValue::new(1_i64),
+            IOValue::new(2_i64)
+        ])),
+        canonical_hash(&IOValue::new(vec![
+            IOValue::new(2_i64),
+            IOValue::new(1_i64)
+        ]))
+    );
+    assert_ne!(
+        canonical_hash(&IOValue::symbol("x")),
+        canonical_hash(&IOValue::new("x".to_string()))
+    );
+}
@@ -2,7 +2,10 @@ use duet::runtime::control::Control;
 use duet::runtime::error::ActorResult;
 use duet::runtime::reaction::{ReactionDefinition, ReactionEffect, ReactionValue};
 use duet::runtime::turn::{ActorId, FacetId};
-use duet::runtime::{RuntimeConfig, pattern::Pattern};
+use duet::runtime::{
+    RuntimeConfig,
+    pattern::{Pattern, PatternScope},
+};
 use preserves::IOValue;
 use std::sync::Once;
 use tempfile::TempDir;
@@ -38,6 +41,23 @@ fn reactions_persist_and_rehydrate() {
         snapshot_interval: 5,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let actor = ActorId::new();
@@ -62,6 +82,8 @@ fn reactions_persist_and_rehydrate() {
         id: Uuid::new_v4(),
         pattern: IOValue::record(IOValue::symbol("mirror"), vec![IOValue::symbol("<_>")]),
         facet: facet.clone(),
+        namespace: None,
+        scope: PatternScope::Actor,
     };
 
     let effect = ReactionEffect::Assert {
@@ -105,3 +127,687 @@ fn reactions_persist_and_rehydrate() {
     });
     assert!(has_reaction, "reaction assertion not found");
 }
+
+#[test]
+fn reactions_fire_by_priority_and_consume_stops_lower_ones() {
+    use duet::runtime::turn::{BranchId, TurnOutput};
+
+    ensure_mirror_registered();
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        // Firing order is read back from the order of the asserted outputs
+        output_order: duet::runtime::ordering::OutputOrder::Emission,
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let actor = ActorId::new();
+    let mut control = Control::init(config).unwrap();
+    control
+        .register_entity(
+            actor.clone(),
+            FacetId::new(),
+            "mirror-entity".to_string(),
+            IOValue::symbol("mirror-config"),
+        )
+        .unwrap();
+    let facet = control.list_entities().first().unwrap().facet.clone();
+
+    let reaction = |tag: &'static str| {
+        ReactionDefinition::new(
+            Pattern {
+                id: Uuid::new_v4(),
+                pattern: IOValue::record(IOValue::symbol("event"), vec![IOValue::symbol("<_>")]),
+                facet: facet.clone(),
+                namespace: None,
+                scope: PatternScope::Actor,
+            },
+            ReactionEffect::Assert {
+                value: ReactionValue::Literal {
+                    value: IOValue::symbol(tag),
+                },
+                target_facet: None,
+            },
+        )
+    };
+    // Registered out of order on purpose
+    for definition in [
+        reaction("low"),
+        reaction("mid").with_priority(5).consuming(),
+        reaction("high").with_priority(10),
+        reaction("also-mid").with_priority(5),
+    ] {
+        control
+            .register_reaction(actor.clone(), definition)
+            .unwrap();
+    }
+    assert!(
+        control
+            .list_reactions()
+            .iter()
+            .any(|info| info.definition.priority == 5 && info.definition.consume)
+    );
+
+    let turn = control
+        .send_message(
+            actor.clone(),
+            facet,
+            IOValue::record(IOValue::symbol("event"), vec![IOValue::new(1_i64)]),
+        )
+        .unwrap();
+
+    let reader = control.runtime().journal_reader(&BranchId::main()).unwrap();
+    let fired: Vec<String> = reader
+        .read(&turn)
+        .unwrap()
+        .outputs
+        .into_iter()
+        .filter_map(|output| match output {
+            TurnOutput::Assert { value, .. } => value.as_symbol().map(|s| s.to_string()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(fired.first().map(String::as_str), Some("high"));
+    let mut tied = fired[1..].to_vec();
+    tied.sort();
+    // Equal priorities still fire; only lower ones are consumed
+    assert_eq!(tied, vec!["also-mid", "mid"]);
+}
+
+#[test]
+fn reaction_invokes_capability_by_kind_and_asserts_result() {
+    use duet::runtime::reaction::ReactionCapability;
+
+    duet::codebase::register_codebase_entities();
+
+    let temp = TempDir::new().unwrap();
+    let workspace_root = temp.path().join("project");
+    std::fs::create_dir_all(&workspace_root).unwrap();
+    std::fs::write(workspace_root.join("notes.txt"), "draft").unwrap();
+
+    let config = RuntimeConfig {
+        root: temp.path().join("state"),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+
+    let actor = ActorId::new();
+    let facet = FacetId::new();
+    control
+        .register_entity(
+            actor.clone(),
+            facet.clone(),
+            "workspace".to_string(),
+            IOValue::new(workspace_root.to_string_lossy().to_string()),
+        )
+        .unwrap();
+    control
+        .send_message(
+            actor.clone(),
+            facet.clone(),
+            IOValue::symbol("workspace-rescan"),
+        )
+        .unwrap();
+    control
+        .send_message(
+            actor.clone(),
+            facet.clone(),
+            IOValue::record(
+                IOValue::symbol("workspace-write"),
+                vec![IOValue::new("notes.txt".to_string())],
+            ),
+        )
+        .unwrap();
+
+    // "When a write request appears, actually perform it"
+    let definition = ReactionDefinition::new(
+        Pattern {
+            id: Uuid::new_v4(),
+            pattern: IOValue::record(
+                IOValue::symbol("write-request"),
+                vec![IOValue::symbol("<_>")],
+            ),
+            facet: facet.clone(),
+            namespace: None,
+            scope: PatternScope::Actor,
+        },
+        ReactionEffect::InvokeCapability {
+            capability: ReactionCapability::Kind {
+                kind: "workspace/write".to_string(),
+            },
+            payload: ReactionValue::MatchIndex { index: 0 },
+            tag: Some("apply-write".to_string()),
+        },
+    );
+    control
+        .register_reaction(actor.clone(), definition)
+        .unwrap();
+
+    control
+        .assert_value(
+            actor.clone(),
+            IOValue::record(
+                IOValue::symbol("write-request"),
+                vec![IOValue::record(
+                    IOValue::symbol("workspace-write"),
+                    vec![
+                        IOValue::new("notes.txt".to_string()),
+                        IOValue::new("final".to_string()),
+                    ],
+                )],
+            ),
+        )
+        .unwrap();
+    control.drain_pending().unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(workspace_root.join("notes.txt")).unwrap(),
+        "final"
+    );
+    let result = control
+        .list_assertions_with_label("tool-result", Some(&actor))
+        .into_iter()
+        .find(|info| info.value.index(1).as_string().unwrap().as_ref() == "apply-write")
+        .expect("reaction result asserted");
+    assert_eq!(result.value.index(5).as_symbol().unwrap().as_ref(), "ok");
+}
+
+#[test]
+fn configuration_changes_are_journaled_on_the_config_branch() {
+    use duet::runtime::config_history::ConfigChange;
+    use duet::runtime::turn::BranchId;
+
+    ensure_mirror_registered();
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    let actor = ActorId::new();
+    let mut control = Control::init(config.clone()).unwrap();
+
+    let entity_id = control
+        .register_entity(
+            actor.clone(),
+            FacetId::new(),
+            "mirror-entity".to_string(),
+            IOValue::symbol("mirror-config"),
+        )
+        .unwrap();
+    let facet = control.list_entities().first().unwrap().facet.clone();
+
+    let pattern = Pattern {
+        id: Uuid::new_v4(),
+        pattern: IOValue::record(IOValue::symbol("mirror"), vec![IOValue::symbol("<_>")]),
+        facet,
+        namespace: None,
+        scope: PatternScope::Actor,
+    };
+    let effect = ReactionEffect::Assert {
+        value: ReactionValue::MatchIndex { index: 0 },
+        target_facet: None,
+    };
+    let reaction_id = control
+        .register_reaction(actor.clone(), ReactionDefinition::new(pattern, effect))
+        .unwrap();
+    assert!(control.unregister_entity(entity_id).unwrap());
+
+    // Configuration turns never land on the working branch
+    assert!(
+        control
+            .history(&BranchId::main(), 0, 10)
+            .unwrap()
+            .is_empty()
+    );
+
+    let history = control.config_history().unwrap();
+    assert_eq!(history.len(), 3);
+    assert!(history.iter().all(|entry| entry.branch == BranchId::main()));
+    assert!(matches!(
+        history[0].change,
+        ConfigChange::EntityRegistered { ref entity } if entity.id == entity_id
+    ));
+    assert!(matches!(
+        history[2].change,
+        ConfigChange::EntityUnregistered { entity_id: removed } if removed == entity_id
+    ));
+
+    let before_removal = control.config_at(Some(&history[1].turn_id)).unwrap();
+    assert!(before_removal.entities.contains_key(&entity_id));
+    assert!(before_removal.reactions.contains_key(&reaction_id));
+    let latest = control.config_at(None).unwrap();
+    assert!(latest.entities.is_empty());
+    assert!(latest.reactions.contains_key(&reaction_id));
+
+    // The config branch is reserved
+    assert!(
+        control
+            .fork(
+                BranchId::main(),
+                BranchId::config(),
+                None,
+                Default::default()
+            )
+            .is_err()
+    );
+    assert!(control.switch_branch(BranchId::config()).is_err());
+
+    drop(control);
+    let control = Control::new(config).unwrap();
+    assert_eq!(control.config_history().unwrap().len(), 3);
+}
+
+#[test]
+fn time_travel_restores_registrations_in_effect_at_the_turn() {
+    ensure_mirror_registered();
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let mut control = Control::init(config.clone()).unwrap();
+    let actor = ActorId::new();
+    let facet = FacetId::new();
+
+    let register = |control: &mut Control, actor: &ActorId, facet: &FacetId| {
+        control
+            .register_entity(
+                actor.clone(),
+                facet.clone(),
+                "mirror-entity".to_string(),
+                IOValue::symbol("mirror-config"),
+            )
+            .unwrap()
+    };
+    let entities = |control: &Control| {
+        let mut ids: Vec<Uuid> = control.list_entities().iter().map(|e| e.id).collect();
+        ids.sort();
+        ids
+    };
+    let reactions = |control: &Control| control.list_reactions().len();
+
+    let kept = register(&mut control, &actor, &facet);
+    let send = |control: &mut Control, label: &'static str| {
+        control
+            .send_message(actor.clone(), facet.clone(), IOValue::symbol(label))
+            .unwrap()
+    };
+    let first = send(&mut control, "one");
+    let second = send(&mut control, "two");
+
+    // Registered while the second turn is the head
+    let removed = register(&mut control, &ActorId::new(), &FacetId::new());
+    let pattern = Pattern {
+        id: Uuid::new_v4(),
+        pattern: IOValue::symbol("<_>"),
+        facet: facet.clone(),
+        namespace: None,
+        scope: PatternScope::Actor,
+    };
+    let effect = ReactionEffect::Assert {
+        value: ReactionValue::MatchIndex { index: 0 },
+        target_facet: None,
+    };
+    control
+        .register_reaction(actor.clone(), ReactionDefinition::new(pattern, effect))
+        .unwrap();
+    send(&mut control, "three");
+
+    assert!(control.unregister_entity(removed).unwrap());
+    let fourth = send(&mut control, "four");
+
+    let mut both = vec![kept, removed];
+    both.sort();
+
+    control.goto(first).unwrap();
+    assert_eq!(entities(&control), vec![kept]);
+    assert_eq!(reactions(&control), 0);
+
+    control.goto(second).unwrap();
+    assert_eq!(entities(&control), both);
+    assert_eq!(reactions(&control), 1);
+
+    control.goto(fourth).unwrap();
+    assert_eq!(entities(&control), vec![kept]);
+    assert_eq!(reactions(&control), 1);
+
+    // The side files are a cache rebuilt from the journal
+    drop(control);
+    std::fs::remove_file(temp.path().join("meta/entities.json")).unwrap();
+    let control = Control::new(config).unwrap();
+    assert_eq!(entities(&control), vec![kept]);
+    assert_eq!(reactions(&control), 1);
+}
+
+#[test]
+fn fixtures_check_reaction_outputs_in_scratch_runtimes() {
+    ensure_mirror_registered();
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().join("state"),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let control = Control::init(config).unwrap();
+
+    let path = temp.path().join("mirror.fixture");
+    std::fs::write(
+        &path,
+        r#"
+        <fixture "mirror echoes and priorities hold" {
+          entities: [<entity "mirror-entity" mirror-config>]
+          reactions: [
+            <reaction <mirror '<_>'> <assert <match-index 0>>>
+            <reaction <event '<_>'> <assert <literal low>>>
+            <reaction <event '<_>'> <assert <literal high>> {priority: 10 consume: #t}>
+          ]
+          steps: [
+            <message <mirror "hello">>
+            <assert <event 1>>
+            <retract <mirror '<_>'>>
+          ]
+          expect: [
+            <asserted "hello">
+            <absent <mirror '<_>'>>
+            <emitted <mirror "hello">>
+            <count high 1>
+            <absent low>
+          ]
+        }>
+        <fixture "regression caught" {
+          reactions: [<reaction <ping '<_>'> <assert <literal pong>>>]
+          steps: [<assert <ping 1>>]
+          expect: [<asserted pong> <count pong 2>]
+        }>
+        "#,
+    )
+    .unwrap();
+
+    let report = control.run_fixture(&path).unwrap();
+    assert!(!report.passed);
+    assert_eq!(report.fixtures.len(), 2);
+    let first = &report.fixtures[0];
+    assert!(first.passed, "{:?}", first.checks);
+    assert_eq!(first.turns, 3);
+    let second = &report.fixtures[1];
+    assert!(!second.passed);
+    assert!(second.checks[0].passed);
+    assert_eq!(
+        second.checks[1].detail.as_deref(),
+        Some("1 live assertions match")
+    );
+
+    // Scenarios never touch the launching runtime
+    assert!(control.list_entities().is_empty());
+    assert!(control.list_reactions().is_empty());
+    assert!(
+        control
+            .history(&duet::runtime::turn::BranchId::main(), 0, 10)
+            .unwrap()
+            .is_empty()
+    );
+
+    std::fs::write(&path, r#"<fixture "broken" {steps: [<sleep 1>]}>"#).unwrap();
+    assert!(control.run_fixture(&path).is_err());
+}
+
+#[test]
+fn reactions_and_patterns_register_through_the_service() {
+    use duet::service::Service;
+    use serde_json::json;
+
+    ensure_mirror_registered();
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor = ActorId::new();
+    control
+        .register_entity(
+            actor.clone(),
+            FacetId::new(),
+            "mirror-entity".to_string(),
+            IOValue::symbol("mirror-config"),
+        )
+        .unwrap();
+    let entity = control.list_entities().first().unwrap().clone();
+    let mut service = Service::new(control);
+
+    let register = |service: &mut Service, pattern: &str, effect_type: &str, scope: &str| {
+        service.call(
+            "reaction_register",
+            &json!({
+                "actor": actor.to_string(),
+                "facet": entity.facet.0.to_string(),
+                "pattern": pattern,
+                "effect": {"type": effect_type, "value": {"type": "match-index", "index": 0}},
+                "scope": scope,
+            }),
+        )
+    };
+
+    let registered = register(&mut service, "(mirror $text)", "assert", "actor");
+    let reaction_id = registered["result"]["reaction_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let listed = service.call("reaction_list", &json!({}));
+    assert_eq!(listed["result"]["reactions"][0]["reaction_id"], reaction_id);
+
+    let sent = service.call(
+        "send_message",
+        &json!({"actor": "mirror-entity", "payload": "<mirror \"hello\">", "sync": true}),
+    );
+    assert!(sent["result"]["turn_id"].is_string(), "{}", sent);
+    let mirrored = service.call("dataspace_assertions", &json!({"pattern": "\"hello\""}));
+    assert_eq!(
+        mirrored["result"]["assertions"].as_array().unwrap().len(),
+        1
+    );
+
+    // Validation errors name what was wrong
+    let bad_pattern = register(&mut service, "(mirror $text", "assert", "actor");
+    assert_eq!(bad_pattern["error"]["code"], "invalid_params");
+    assert_eq!(bad_pattern["error"]["details"]["category"], "pattern");
+    assert_eq!(bad_pattern["error"]["details"]["offset"], 13);
+    let bad_effect = register(&mut service, "(mirror _)", "explode", "actor");
+    assert_eq!(bad_effect["error"]["details"]["field"], "effect");
+    let bad_scope = register(&mut service, "(mirror _)", "assert", "everywhere");
+    assert_eq!(bad_scope["error"]["details"]["field"], "scope");
+
+    let unregistered = service.call("reaction_unregister", &json!({"reaction_id": reaction_id}));
+    assert_eq!(unregistered["result"]["removed"], true);
+    let listed = service.call("reaction_list", &json!({}));
+    assert!(listed["result"]["reactions"].as_array().unwrap().is_empty());
+
+    let subscribed = service.call(
+        "pattern_register",
+        &json!({"entity": entity.id.to_string(), "pattern": "(mirror _)", "scope": "dataspace"}),
+    );
+    assert!(
+        subscribed["result"]["pattern_id"].is_string(),
+        "{}",
+        subscribed
+    );
+    let entities = service.call("list_entities", &json!({}));
+    assert_eq!(entities["result"]["entities"][0]["pattern_count"], 1);
+}
+
+#[test]
+fn explain_links_a_turn_to_its_route_cause_and_follow_ups() {
+    use duet::runtime::turn::BranchId;
+    use duet::service::Service;
+    use serde_json::json;
+
+    ensure_mirror_registered();
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    let (sender, receiver) = (ActorId::new(), ActorId::new());
+    for actor in [&sender, &receiver] {
+        control
+            .register_entity(
+                actor.clone(),
+                FacetId::new(),
+                "mirror-entity".to_string(),
+                IOValue::symbol("mirror-config"),
+            )
+            .unwrap();
+    }
+    let facet_of = |control: &Control, actor: &ActorId| {
+        control
+            .list_entities()
+            .into_iter()
+            .find(|entity| entity.actor == *actor)
+            .unwrap()
+            .facet
+    };
+    let (sender_facet, receiver_facet) =
+        (facet_of(&control, &sender), facet_of(&control, &receiver));
+
+    let definition = ReactionDefinition::new(
+        Pattern {
+            id: Uuid::new_v4(),
+            pattern: IOValue::record(IOValue::symbol("ping"), vec![IOValue::symbol("<_>")]),
+            facet: sender_facet.clone(),
+            namespace: None,
+            scope: PatternScope::Actor,
+        },
+        ReactionEffect::SendMessage {
+            actor: receiver.clone(),
+            facet: receiver_facet,
+            payload: ReactionValue::Literal {
+                value: IOValue::symbol("pong"),
+            },
+        },
+    );
+    let reaction_id = definition.id;
+    control
+        .register_reaction(sender.clone(), definition)
+        .unwrap();
+
+    let ping = control
+        .send_message(
+            sender.clone(),
+            sender_facet,
+            IOValue::record(IOValue::symbol("ping"), vec![IOValue::new(1_i64)]),
+        )
+        .unwrap();
+    control.step(10).unwrap();
+    let pong = control
+        .history(&BranchId::main(), 0, 100)
+        .unwrap()
+        .into_iter()
+        .find(|record| record.actor == receiver && record.turn_id != ping)
+        .map(|record| record.turn_id)
+        .expect("receiver ran a turn");
+
+    let mut service = Service::new(control);
+    let explained = service.call("explain", &json!({"turn_id": ping.to_string()}));
+    let explanation = &explained["result"];
+    assert_eq!(explanation["actor"], sender.to_string(), "{}", explained);
+    assert!(explanation["caused_by"].is_null());
+    assert_eq!(explanation["routes"][0]["kind"], "reaction");
+    assert_eq!(
+        explanation["routes"][0]["reaction_id"],
+        reaction_id.to_string()
+    );
+    assert_eq!(explanation["triggered"][0]["turn_id"], pong.to_string());
+    assert_eq!(explanation["triggered"][0]["via"]["kind"], "message");
+
+    let explained = service.call("explain", &json!({"turn_id": pong.to_string()}));
+    assert_eq!(
+        explained["result"]["caused_by"]["turn_id"],
+        ping.to_string(),
+        "{}",
+        explained
+    );
+
+    let missing = service.call("explain", &json!({"turn_id": "nope"}));
+    assert!(missing["error"].is_object());
+}
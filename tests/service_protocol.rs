@@ -24,6 +24,23 @@ fn service_handles_basic_commands() {
         snapshot_interval: 5,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     // Initialise storage
@@ -98,6 +115,187 @@ fn service_handles_basic_commands() {
     assert_eq!(lines[8]["error"]["code"], "unsupported_command");
 }
 
+#[test]
+fn branch_head_and_lca_commands_report_divergence() {
+    use duet::runtime::turn::{ActorId, BranchId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Control::init(config.clone()).unwrap();
+    let mut control = Control::new(config).unwrap();
+    let actor = ActorId::new();
+
+    let fork_point = control
+        .assert_value(actor.clone(), IOValue::symbol("base"))
+        .unwrap();
+    let experiment = control.runtime_mut().fork("experiment", None).unwrap();
+    control.runtime_mut().switch_branch(experiment).unwrap();
+    for name in ["one", "two"] {
+        control
+            .assert_value(actor.clone(), IOValue::symbol(name))
+            .unwrap();
+    }
+    control
+        .runtime_mut()
+        .switch_branch(BranchId::main())
+        .unwrap();
+    let main_head = control
+        .assert_value(actor.clone(), IOValue::symbol("main"))
+        .unwrap();
+
+    let sink = Rc::new(RefCell::new(Vec::<u8>::new()));
+    let mut service = Service::new(control);
+
+    let requests = vec![
+        json!({"id": 1, "command": "handshake", "params": {"client": "test", "protocol_version": duet::PROTOCOL_VERSION}}),
+        json!({"id": 2, "command": "branch_head", "params": {"branch": "main"}}),
+        json!({"id": 3, "command": "branch_head", "params": {"branch": "experiment"}}),
+        json!({"id": 4, "command": "branch_lca", "params": {"a": "main", "b": "experiment"}}),
+        json!({"id": 5, "command": "branch_lca", "params": {"a": "main", "b": "missing"}}),
+    ];
+
+    let input_data = requests
+        .into_iter()
+        .map(|req| serde_json::to_string(&req).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let reader = Cursor::new(format!("{}\n", input_data));
+    service.handle(reader, SharedWriter(sink.clone())).unwrap();
+
+    let output = sink.borrow();
+    let lines: Vec<_> = output
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice::<Value>(line).unwrap())
+        .collect();
+
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[1]["result"]["turn_id"], main_head.to_string());
+    assert_eq!(lines[1]["result"]["turn_count"], 2);
+    assert!(lines[1]["result"]["timestamp"].is_string());
+    assert_eq!(lines[2]["result"]["turn_count"], 3);
+
+    let lca = &lines[3]["result"];
+    assert_eq!(lca["lca"], fork_point.to_string());
+    assert_eq!(lca["a_turns_since"], 1);
+    assert_eq!(lca["b_turns_since"], 2);
+    assert!(lca["timestamp"].is_string());
+
+    assert!(lines[4]["error"].is_object());
+}
+
+#[test]
+fn fork_records_branch_details_and_renders_naming_templates() {
+    use duet::runtime::branch::BranchNamingConfig;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: BranchNamingConfig {
+            templates: [("agent".to_string(), "agent/{request_id}".to_string())].into(),
+            pattern: Some("main|agent/.+".to_string()),
+        },
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Control::init(config.clone()).unwrap();
+    let control = Control::new(config).unwrap();
+    let sink = Rc::new(RefCell::new(Vec::<u8>::new()));
+    let mut service = Service::new(control);
+
+    let requests = vec![
+        json!({"id": 1, "command": "handshake", "params": {"client": "test", "protocol_version": duet::PROTOCOL_VERSION}}),
+        json!({"id": 2, "command": "fork", "params": {
+            "template": "agent",
+            "vars": {"request_id": "req-42"},
+            "description": "try the parser rewrite",
+            "creator": "planner",
+            "tags": ["experiment", "parser"],
+        }}),
+        json!({"id": 3, "command": "fork", "params": {"new_branch": "scratch"}}),
+        json!({"id": 4, "command": "fork", "params": {"template": "agent", "vars": {}}}),
+        json!({"id": 5, "command": "list_branches", "params": {}}),
+    ];
+
+    let input_data = requests
+        .into_iter()
+        .map(|req| serde_json::to_string(&req).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let reader = Cursor::new(format!("{}\n", input_data));
+    service.handle(reader, SharedWriter(sink.clone())).unwrap();
+
+    let output = sink.borrow();
+    let lines: Vec<_> = output
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice::<Value>(line).unwrap())
+        .collect();
+
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[1]["result"]["branch"], "agent/req-42");
+    // Rejected by the naming pattern
+    assert!(lines[2]["error"].is_object());
+    // Template placeholder left unfilled
+    assert!(lines[3]["error"].is_object());
+
+    let branches = lines[4]["result"]["branches"].as_array().unwrap();
+    assert_eq!(branches.len(), 2);
+    let agent = branches
+        .iter()
+        .find(|branch| branch["name"] == "agent/req-42")
+        .unwrap();
+    assert_eq!(agent["parent"], "main");
+    assert_eq!(agent["description"], "try the parser rewrite");
+    assert_eq!(agent["creator"], "planner");
+    assert_eq!(agent["tags"], json!(["experiment", "parser"]));
+    assert!(agent["created_at"].is_string());
+    assert!(agent["base_turn"].is_string());
+}
+
 #[test]
 fn workspace_commands_expose_entries() {
     let temp = TempDir::new().unwrap();
@@ -106,6 +304,23 @@ fn workspace_commands_expose_entries() {
         snapshot_interval: 5,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     let file_path = temp.path().join("note.txt");
@@ -151,6 +366,694 @@ fn workspace_commands_expose_entries() {
     assert!(paths.iter().any(|path| path.contains("note.txt")));
 }
 
+#[test]
+fn describe_entity_type_reports_workspace_descriptor() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Control::init(config.clone()).unwrap();
+    let mut control = Control::new(config).unwrap();
+    duet::codebase::ensure_workspace_entity(&mut control, temp.path()).unwrap();
+
+    let sink = Rc::new(RefCell::new(Vec::<u8>::new()));
+    let mut service = Service::new(control);
+
+    let requests = vec![
+        json!({"id": 1, "command": "handshake", "params": {"client": "test", "protocol_version": duet::PROTOCOL_VERSION}}),
+        json!({"id": 2, "command": "describe_entity_type", "params": {"entity_type": "workspace"}}),
+        json!({"id": 3, "command": "describe_entity_type", "params": {"entity_type": "no-such-type"}}),
+        json!({"id": 4, "command": "dataspace_assertions", "params": {"label": "entity-descriptor"}}),
+    ];
+
+    let input_data = requests
+        .into_iter()
+        .map(|req| serde_json::to_string(&req).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let reader = Cursor::new(format!("{}\n", input_data));
+    service.handle(reader, SharedWriter(sink.clone())).unwrap();
+
+    let output = sink.borrow();
+    let lines: Vec<_> = output
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice::<Value>(line).unwrap())
+        .collect();
+
+    assert_eq!(lines.len(), 4);
+    let descriptor = &lines[1]["result"];
+    assert_eq!(descriptor["entity_type"], "workspace");
+    assert!(
+        descriptor["message_labels"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("workspace-read"))
+    );
+    assert_eq!(
+        descriptor["capability_kinds"],
+        json!(["workspace/read", "workspace/write", "workspace/stream-read"])
+    );
+    assert!(
+        descriptor["config_schema"]
+            .as_str()
+            .unwrap()
+            .starts_with("<workspace-config")
+    );
+
+    assert!(lines[2]["error"].is_object());
+
+    let assertions = lines[3]["result"]["assertions"].as_array().unwrap();
+    assert_eq!(assertions.len(), 1);
+}
+
+#[test]
+fn service_stats_reports_recent_commands() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Control::init(config.clone()).unwrap();
+    let control = Control::new(config).unwrap();
+    let mut service = Service::new(control);
+
+    service.call("status", &json!({}));
+    service.call(
+        "history",
+        &json!({"branch": "main", "start": 0, "limit": 5}),
+    );
+    service.call(
+        "history",
+        &json!({"branch": "main", "start": 0, "limit": 5}),
+    );
+    service.call("goto", &json!({}));
+
+    let stats = service.call("service_stats", &json!({"limit": 3}));
+    let recent = stats["result"]["recent"].as_array().unwrap();
+    assert_eq!(recent.len(), 3);
+    assert_eq!(recent[0]["command"], "goto");
+    assert_eq!(recent[0]["result"], "invalid_params");
+    assert_eq!(recent[1]["command"], "history");
+    assert_eq!(recent[1]["result"], "ok");
+    assert_eq!(recent[1]["params_digest"], recent[2]["params_digest"]);
+    assert!(recent[1]["duration_ms"].as_f64().unwrap() >= 0.0);
+
+    let commands = stats["result"]["commands"].as_array().unwrap();
+    let history = commands
+        .iter()
+        .find(|entry| entry["command"] == "history")
+        .unwrap();
+    assert_eq!(history["count"], 2);
+    assert_eq!(history["errors"], 0);
+    assert_eq!(commands.len(), 3);
+}
+
+#[test]
+fn wait_registrations_survive_reconnects() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Control::init(config.clone()).unwrap();
+    let control = Control::new(config).unwrap();
+    let mut service = Service::new(control);
+
+    let registered = service.call("wait_register", &json!({"branch": "main"}));
+    let registration = registered["result"]["id"].as_str().unwrap().to_string();
+    // Collect whatever head existed before registering
+    service.call("wait_poll", &json!({"registration": registration}));
+    let idle = service.call("wait_poll", &json!({"registration": registration}));
+    assert!(idle["result"]["turn_id"].is_null());
+
+    // The branch advances while the client is disconnected
+    let paused = service.call(
+        "pause_actor",
+        &json!({"actor": duet::runtime::turn::ActorId::new().to_string()}),
+    );
+    let turn_id = paused["result"]["turn_id"].clone();
+    assert!(turn_id.is_string());
+
+    let sink = Rc::new(RefCell::new(Vec::<u8>::new()));
+    let requests = [
+        json!({"id": 1, "command": "handshake", "params": {"client": "test", "protocol_version": duet::PROTOCOL_VERSION}}),
+        json!({"id": 2, "command": "wait_poll", "params": {"registration": registration, "wait_ms": 1000}}),
+        json!({"id": 3, "command": "wait_cancel", "params": {"registration": registration}}),
+        json!({"id": 4, "command": "wait_poll", "params": {"registration": registration}}),
+    ];
+    let input_data = requests
+        .iter()
+        .map(|req| serde_json::to_string(req).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+    service
+        .handle(
+            Cursor::new(format!("{}\n", input_data)),
+            SharedWriter(sink.clone()),
+        )
+        .unwrap();
+
+    let output = sink.borrow();
+    let lines: Vec<_> = output
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice::<Value>(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[1]["result"]["turn_id"], turn_id);
+    assert_eq!(lines[2]["result"]["cancelled"], true);
+    assert_eq!(lines[3]["error"]["code"], "runtime_error");
+}
+
+#[test]
+fn sync_flag_runs_induced_turns_before_replying() {
+    EntityCatalog::global().register("service-relay", |_config| Ok(Box::new(RelayEntity)));
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Control::init(config.clone()).unwrap();
+    let mut control = Control::new(config).unwrap();
+    let actor = duet::runtime::turn::ActorId::new();
+    let facet = duet::runtime::turn::FacetId::new();
+    control
+        .register_entity(
+            actor.clone(),
+            facet.clone(),
+            "service-relay".to_string(),
+            IOValue::symbol("nil"),
+        )
+        .unwrap();
+    let mut service = Service::new(control);
+
+    let send = |service: &mut Service, sync: bool| {
+        service.call(
+            "send_message",
+            &json!({
+                "actor": actor.to_string(),
+                "facet": facet.0.to_string(),
+                "payload": "3",
+                "sync": sync,
+            }),
+        )
+    };
+    let history_len = |service: &mut Service| {
+        service.call("history", &json!({"branch": "main", "limit": 100}))["result"]["turns"]
+            .as_array()
+            .unwrap()
+            .len()
+    };
+
+    // Without sync only the message's own turn has run
+    let before = history_len(&mut service);
+    let unsynced = send(&mut service, false);
+    assert!(unsynced["result"]["turn_id"].is_string());
+    assert!(unsynced["result"].get("head").is_none());
+    assert_eq!(history_len(&mut service), before + 1);
+
+    // With sync the relay chain (and the leftover one) has finished
+    let synced = send(&mut service, true);
+    let after = history_len(&mut service);
+    assert_eq!(after, before + 8);
+    let turns = service.call("history", &json!({"branch": "main", "limit": 100}));
+    let last = turns["result"]["turns"].as_array().unwrap().last().unwrap()["turn_id"].clone();
+    assert_eq!(synced["result"]["head"], last);
+}
+
+#[test]
+fn send_message_resolves_names_and_converts_json_payloads() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Control::init(config.clone()).unwrap();
+    let mut control = Control::new(config).unwrap();
+    let store = duet::codebase::ensure_kv_entity(&mut control, "scratch").unwrap();
+    let mut service = Service::new(control);
+
+    // By entity type name, with a templated record payload and validation
+    let sent = service.call(
+        "send_message",
+        &json!({
+            "actor": "kv",
+            "payload": {"$record": "kv-get", "fields": ["notes/"]},
+            "validate": true,
+        }),
+    );
+    assert_eq!(sent["result"]["actor"], store.actor.to_string());
+    assert_eq!(sent["result"]["facet"], store.facet.0.to_string());
+    assert!(sent["result"]["turn_id"].is_string());
+
+    // By entity id, with a preserves text payload
+    let sent = service.call(
+        "send_message",
+        &json!({
+            "actor": store.entity_id.to_string(),
+            "payload": "<kv-put \"notes/\">",
+        }),
+    );
+    assert_eq!(sent["result"]["facet"], store.facet.0.to_string());
+
+    // Validation rejects labels the entity does not advertise
+    let rejected = service.call(
+        "send_message",
+        &json!({
+            "actor": "kv",
+            "payload": {"$record": "kv-drop", "fields": []},
+            "validate": true,
+        }),
+    );
+    assert_eq!(rejected["error"]["code"], "invalid_params");
+
+    let unknown = service.call(
+        "send_message",
+        &json!({"actor": "no-such-type", "payload": {"$symbol": "ping"}}),
+    );
+    assert_eq!(unknown["error"]["code"], "invalid_params");
+}
+
+#[test]
+fn send_message_is_rate_limited_per_actor_and_per_client() {
+    use duet::runtime::limits::LimitsConfig;
+    use duet::runtime::ratelimit::{IngressLimits, RateLimit};
+
+    let temp = TempDir::new().unwrap();
+    let slow = RateLimit {
+        per_second: 0.001,
+        burst: 2,
+    };
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: LimitsConfig {
+            ingress: IngressLimits {
+                per_actor: Some(slow),
+                per_client: None,
+            },
+            ..Default::default()
+        },
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Control::init(config.clone()).unwrap();
+    let mut control = Control::new(config).unwrap();
+    let first = duet::codebase::ensure_kv_entity(&mut control, "first").unwrap();
+    let second = duet::codebase::ensure_kv_entity(&mut control, "second").unwrap();
+    let mut service = Service::new(control);
+    let send = |service: &mut Service, actor: &str| {
+        service.call(
+            "send_message",
+            &json!({"actor": actor, "payload": "<kv-get \"notes/\">"}),
+        )
+    };
+
+    let first_actor = first.actor.to_string();
+    assert!(send(&mut service, &first_actor)["result"].is_object());
+    assert!(send(&mut service, &first_actor)["result"].is_object());
+    let limited = send(&mut service, &first_actor);
+    assert_eq!(limited["error"]["code"], "rate_limited");
+    assert_eq!(limited["error"]["details"]["scope"], "actor");
+    assert_eq!(limited["error"]["details"]["key"], first_actor);
+    assert!(
+        limited["error"]["details"]["retry_after_ms"]
+            .as_u64()
+            .unwrap()
+            > 0
+    );
+
+    // The bucket is per actor
+    assert!(send(&mut service, &second.actor.to_string())["result"].is_object());
+
+    let stats = service.call("service_stats", &json!({}));
+    let sources = stats["result"]["rate_limits"]["sources"]
+        .as_array()
+        .unwrap();
+    let first_stats = sources
+        .iter()
+        .find(|source| source["key"] == first_actor)
+        .unwrap();
+    assert_eq!(first_stats["admitted"], 2);
+    assert_eq!(first_stats["limited"], 1);
+
+    // Per-client limits apply across actors
+    let mut control = Control::new(RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    })
+    .unwrap();
+    control.set_ingress_limits(IngressLimits {
+        per_actor: None,
+        per_client: Some(slow),
+    });
+    let mut service = Service::new(control);
+    assert!(send(&mut service, &first_actor)["result"].is_object());
+    assert!(send(&mut service, &second.actor.to_string())["result"].is_object());
+    let limited = send(&mut service, &first_actor);
+    assert_eq!(limited["error"]["details"]["scope"], "client");
+    assert_eq!(limited["error"]["details"]["key"], "anonymous");
+}
+
+#[test]
+fn dataspace_events_filter_by_assertion_schema() {
+    use duet::runtime::flags::FeatureFlag;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    Control::init(config.clone()).unwrap();
+    let mut control = Control::new(config).unwrap();
+    duet::codebase::ensure_kv_entity(&mut control, "notes").unwrap();
+    control.set_flag(FeatureFlag::Chaos, false).unwrap();
+    let mut service = Service::new(control);
+
+    let schemas = service.call("assertion_schemas", &json!({}));
+    assert!(
+        schemas["result"]["schemas"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|schema| schema["name"] == "AgentResponse")
+    );
+
+    let all = service.call("dataspace_events", &json!({"event_types": ["assert"]}));
+    let typed = service.call(
+        "dataspace_events",
+        &json!({"schema": "FeatureFlag", "event_types": ["assert"]}),
+    );
+    let events = |response: &Value| {
+        response["result"]["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|batch| batch["events"].as_array().unwrap().clone())
+            .collect::<Vec<_>>()
+    };
+    let typed_events = events(&typed);
+    assert_eq!(typed_events.len(), 1);
+    assert!(
+        typed_events[0]["summary"]
+            .as_str()
+            .unwrap()
+            .contains("feature-flag")
+    );
+    assert!(events(&all).len() > typed_events.len());
+
+    let unknown = service.call("dataspace_events", &json!({"schema": "AgentReply"}));
+    assert_eq!(unknown["error"]["code"], "invalid_params");
+    assert!(
+        unknown["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("AgentResponse")
+    );
+}
+
+#[test]
+fn dataspace_assertions_filter_by_textual_pattern() {
+    use duet::runtime::flags::FeatureFlag;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    Control::init(config.clone()).unwrap();
+    let mut control = Control::new(config).unwrap();
+    control.set_flag(FeatureFlag::Chaos, false).unwrap();
+    let mut service = Service::new(control);
+
+    let query = |service: &mut Service, pattern: &str| {
+        service.call("dataspace_assertions", &json!({"pattern": pattern}))
+    };
+    let disabled = query(&mut service, "(feature-flag $name #f)");
+    let assertions = disabled["result"]["assertions"].as_array().unwrap();
+    assert_eq!(assertions.len(), 1);
+    assert!(
+        assertions[0]["summary"]
+            .as_str()
+            .unwrap()
+            .contains("feature-flag")
+    );
+    let enabled = query(&mut service, "(feature-flag chaos #t)");
+    assert!(
+        enabled["result"]["assertions"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+
+    let malformed = query(&mut service, "(feature-flag $name");
+    assert_eq!(malformed["error"]["code"], "invalid_params");
+    assert_eq!(malformed["error"]["details"]["category"], "pattern");
+    assert_eq!(malformed["error"]["details"]["offset"], 19);
+}
+
+#[test]
+fn history_returns_curated_turn_views() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    Control::init(config.clone()).unwrap();
+    let mut control = Control::new(config).unwrap();
+    let actor = duet::runtime::turn::ActorId::new();
+    control
+        .runtime_mut()
+        .assert_value(actor.clone(), IOValue::symbol("ready"));
+    control.runtime_mut().step().unwrap().expect("assert turn");
+    let mut service = Service::new(control);
+
+    let summary = service.call("history", &json!({"branch": "main"}));
+    let turn = &summary["result"]["turns"][0];
+    assert_eq!(turn["input_count"], 1);
+    assert_eq!(turn["inputs"][0]["kind"], "assert");
+    assert_eq!(turn["inputs"][0]["actor"], actor.to_string());
+    assert_eq!(turn["inputs"][0]["summary"], ":ready");
+    assert!(turn["inputs"][0].get("payload").is_none());
+    assert_eq!(
+        turn["counts"],
+        json!({"asserts": 1, "retracts": 0, "messages": 0})
+    );
+    assert_eq!(turn["capabilities"], json!([]));
+    assert!(turn.get("outputs").is_none());
+
+    let full = service.call("history", &json!({"branch": "main", "detail": "full"}));
+    let turn = &full["result"]["turns"][0];
+    assert!(turn["inputs"][0]["payload"].is_object());
+    assert_eq!(turn["outputs"][0]["kind"], "assert");
+    assert_eq!(turn["outputs"][0]["summary"], ":ready");
+
+    let invalid = service.call("history", &json!({"detail": "verbose"}));
+    assert_eq!(invalid["error"]["code"], "invalid_params");
+}
+
+#[test]
+fn sessions_resume_subscriptions_and_replay_idempotent_requests() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    Control::init(config.clone()).unwrap();
+
+    let connect = |service: &mut Service, requests: &[Value]| -> Vec<Value> {
+        let mut all = vec![json!({"id": 0, "command": "handshake", "params": {
+            "client": "test",
+            "protocol_version": duet::PROTOCOL_VERSION,
+            "session": "resumable-token",
+        }})];
+        all.extend_from_slice(requests);
+        let input_data = all
+            .iter()
+            .map(|req| serde_json::to_string(req).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let sink = Rc::new(RefCell::new(Vec::<u8>::new()));
+        service
+            .handle(
+                Cursor::new(format!("{}\n", input_data)),
+                SharedWriter(sink.clone()),
+            )
+            .unwrap();
+        let output = sink.borrow();
+        output
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice::<Value>(line).unwrap())
+            .collect()
+    };
+    let set_chaos = |id: u64, enabled: bool, key: &str| {
+        json!({"id": id, "command": "set_flag", "idempotency_key": key,
+            "params": {"flag": "chaos", "enabled": enabled}})
+    };
+    let feed = json!({"id": 9, "command": "dataspace_events",
+        "params": {"subscription": "feed", "schema": "FeatureFlag", "event_types": ["assert"]}});
+
+    let mut service = Service::new(Control::new(config.clone()).unwrap());
+    let first = connect(
+        &mut service,
+        &[set_chaos(1, false, "disable"), feed.clone()],
+    );
+    assert_eq!(first[0]["result"]["session"]["resumed"], false);
+    let disabled_turn = first[1]["result"]["turn_id"].clone();
+    assert_eq!(
+        first[2]["result"]["next_cursor"], first[2]["result"]["head"],
+        "the subscription delivered everything up to the head"
+    );
+    drop(service);
+
+    // A restarted daemon resumes the session: the retried request is answered
+    // from the cache and the subscription only delivers newer events
+    let mut service = Service::new(Control::new(config).unwrap());
+    let second = connect(
+        &mut service,
+        &[
+            set_chaos(1, false, "disable"),
+            set_chaos(2, true, "enable"),
+            feed.clone(),
+            feed,
+        ],
+    );
+    let session = &second[0]["result"]["session"];
+    assert_eq!(session["resumed"], true);
+    assert_eq!(
+        session["subscriptions"]["feed"],
+        first[2]["result"]["next_cursor"]
+    );
+    assert_eq!(second[1]["result"]["turn_id"], disabled_turn);
+    let enabled_turn = second[2]["result"]["turn_id"].clone();
+    let batches = second[3]["result"]["events"].as_array().unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0]["turn"], enabled_turn);
+    assert!(second[4]["result"]["events"].as_array().unwrap().is_empty());
+}
+
 struct SharedWriter(Rc<RefCell<Vec<u8>>>);
 
 impl Write for SharedWriter {
@@ -171,3 +1074,385 @@ impl Entity for SimpleEntity {
         Ok(())
     }
 }
+
+/// Forwards a countdown to itself until it reaches zero.
+struct RelayEntity;
+
+impl Entity for RelayEntity {
+    fn on_message(&self, activation: &mut Activation, payload: &IOValue) -> ActorResult<()> {
+        let remaining = payload
+            .as_signed_integer()
+            .and_then(|n| i64::try_from(n.as_ref()).ok())
+            .unwrap_or(0);
+        if remaining > 0 {
+            activation.send_message(
+                activation.actor_id.clone(),
+                activation.current_facet.clone(),
+                IOValue::new(remaining - 1),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn doctor_reports_environment_checks() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    Control::init(config.clone()).unwrap();
+    let mut control = Control::new(config).unwrap();
+    control.runtime_mut().assert_value(
+        duet::runtime::turn::ActorId::new(),
+        IOValue::symbol("ready"),
+    );
+    control.runtime_mut().step().unwrap().expect("assert turn");
+    let journal_dir = control
+        .runtime()
+        .storage()
+        .branch_journal_dir(&duet::runtime::turn::BranchId::main());
+    let mut service = Service::new(control);
+
+    let response = service.call("doctor", &json!({}));
+    let report = &response["result"];
+    assert_eq!(report["healthy"], true, "{}", response);
+    let checks = report["checks"].as_array().unwrap();
+    let status = |checks: &[serde_json::Value], name: &str| {
+        checks
+            .iter()
+            .find(|check| check["name"] == name)
+            .map(|check| check["status"].clone())
+    };
+    assert_eq!(status(checks, "storage.writable"), Some(json!("pass")));
+    assert_eq!(status(checks, "journal.integrity"), Some(json!("pass")));
+    assert_eq!(status(checks, "entities.factories"), Some(json!("pass")));
+    assert!(
+        checks
+            .iter()
+            .filter(|check| check["name"].as_str().unwrap().starts_with("agent."))
+            .all(|check| check["advisory"] == true)
+    );
+
+    for segment in std::fs::read_dir(&journal_dir).unwrap() {
+        let path = segment.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "turnlog") {
+            std::fs::write(&path, b"").unwrap();
+        }
+    }
+    let response = service.call("doctor", &json!({}));
+    assert_eq!(response["result"]["healthy"], false);
+    let checks = response["result"]["checks"].as_array().unwrap();
+    assert_eq!(status(checks, "journal.integrity"), Some(json!("fail")));
+}
+
+#[test]
+fn handshake_selects_an_isolated_tenant_runtime() {
+    use duet::runtime::manager::RuntimeManager;
+    use duet::runtime::turn::ActorId;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().join("default"),
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    control
+        .assert_value(ActorId::new(), IOValue::symbol("default-only"))
+        .unwrap();
+    let mut runtimes = RuntimeManager::new("default", control);
+    runtimes.allow_roots_under(temp.path().join("projects"));
+    let mut service = Service::with_manager(runtimes);
+    let project_root = temp.path().join("projects").join("project");
+
+    let mut run = |requests: Vec<Value>| {
+        let sink = Rc::new(RefCell::new(Vec::<u8>::new()));
+        let input = requests
+            .iter()
+            .map(|req| serde_json::to_string(req).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        service
+            .handle(
+                Cursor::new(format!("{}\n", input)),
+                SharedWriter(sink.clone()),
+            )
+            .unwrap();
+        let output = sink.borrow();
+        output
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice::<Value>(line).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let lines = run(vec![
+        json!({"id": 1, "command": "handshake", "params": {
+            "client": "test",
+            "protocol_version": duet::PROTOCOL_VERSION,
+            "tenant": "project",
+            "root": project_root,
+        }}),
+        json!({"id": 2, "command": "branch_head", "params": {"branch": "main"}}),
+        json!({"id": 3, "command": "tenants", "params": {}}),
+    ]);
+    assert_eq!(lines[0]["result"]["tenant"], "project");
+    assert_eq!(lines[1]["result"]["turn_count"], 0);
+    assert_eq!(lines[2]["result"]["tenant"], "project");
+    let tenants = lines[2]["result"]["tenants"].as_array().unwrap();
+    assert_eq!(tenants.len(), 2);
+    assert_eq!(tenants[0]["name"], "default");
+    assert_eq!(tenants[0]["default"], true);
+    assert_eq!(tenants[1]["name"], "project");
+    assert_eq!(tenants[1]["open"], true);
+    assert!(project_root.exists());
+
+    let lines = run(vec![
+        json!({"id": 1, "command": "handshake", "params": {"client": "test", "protocol_version": duet::PROTOCOL_VERSION}}),
+        json!({"id": 2, "command": "branch_head", "params": {"branch": "main"}}),
+        json!({"id": 3, "command": "handshake", "params": {
+            "client": "test",
+            "protocol_version": duet::PROTOCOL_VERSION,
+            "tenant": "missing",
+        }}),
+    ]);
+    assert_eq!(lines[0]["result"]["tenant"], "default");
+    assert_eq!(lines[1]["result"]["turn_count"], 1);
+    assert!(lines[2]["error"].is_object());
+
+    // Clients cannot open roots outside the allowed directory, nor host a
+    // root twice
+    let handshake = |tenant: &str, root: std::path::PathBuf| {
+        json!({"id": 1, "command": "handshake", "params": {
+            "client": "test",
+            "protocol_version": duet::PROTOCOL_VERSION,
+            "tenant": tenant,
+            "root": root,
+        }})
+    };
+    let lines = run(vec![
+        handshake("stray", temp.path().join("stray")),
+        handshake("default-again", temp.path().join("default")),
+        handshake("project-again", project_root.clone()),
+    ]);
+    assert!(lines.iter().all(|line| line["error"].is_object()));
+    assert!(!temp.path().join("stray").exists());
+}
+
+#[test]
+fn handshake_identities_are_recorded_on_turns_and_forks() {
+    use duet::runtime::identity::{IdentityKind, Principal, token_digest};
+
+    let temp = TempDir::new().unwrap();
+    let mut config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    config.identity.principals.insert(
+        "ci".to_string(),
+        Principal {
+            kind: IdentityKind::Bot,
+            display_name: Some("CI".to_string()),
+            token_sha256: token_digest("ci-token"),
+        },
+    );
+    let mut control = Control::init(config).unwrap();
+    duet::codebase::ensure_kv_entity(&mut control, "scratch").unwrap();
+    let mut service = Service::new(control);
+
+    let mut run = |requests: Vec<Value>| {
+        let sink = Rc::new(RefCell::new(Vec::<u8>::new()));
+        let input = requests
+            .iter()
+            .map(|req| serde_json::to_string(req).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        service
+            .handle(
+                Cursor::new(format!("{}\n", input)),
+                SharedWriter(sink.clone()),
+            )
+            .unwrap();
+        let output = sink.borrow();
+        output
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice::<Value>(line).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let handshake = |identity: Value| {
+        json!({"id": 1, "command": "handshake", "params": {
+            "client": "test",
+            "protocol_version": duet::PROTOCOL_VERSION,
+            "identity": identity,
+        }})
+    };
+
+    let lines = run(vec![
+        handshake(json!({"id": "alice"})),
+        json!({"id": 2, "command": "send_message", "params": {
+            "actor": "kv",
+            "payload": "<kv-put \"notes/\">",
+        }}),
+        json!({"id": 3, "command": "fork", "params": {"new_branch": "alice-experiment"}}),
+    ]);
+    assert_eq!(lines[0]["result"]["identity"]["id"], "alice");
+    assert_eq!(lines[0]["result"]["identity"]["authenticated"], false);
+    let alice_turn = lines[1]["result"]["turn_id"].clone();
+
+    let lines = run(vec![
+        handshake(json!({"id": "ci", "token": "wrong"})),
+        handshake(json!({"id": "ci", "token": "ci-token"})),
+        json!({"id": 2, "command": "send_message", "params": {
+            "actor": "kv",
+            "payload": "<kv-put \"build/\">",
+        }}),
+        json!({"id": 3, "command": "history", "params": {"branch": "main", "limit": 100}}),
+        json!({"id": 4, "command": "list_branches", "params": {}}),
+    ]);
+    assert_eq!(lines[0]["error"]["code"], "protocol_error");
+    assert_eq!(lines[1]["result"]["identity"]["kind"], "bot");
+    assert_eq!(lines[1]["result"]["identity"]["authenticated"], true);
+    let ci_turn = lines[2]["result"]["turn_id"].clone();
+
+    let turns = lines[3]["result"]["turns"].as_array().unwrap();
+    let initiator = |turn_id: &Value| {
+        turns
+            .iter()
+            .find(|turn| turn["turn_id"] == *turn_id)
+            .unwrap()["initiator"]
+            .clone()
+    };
+    assert_eq!(initiator(&alice_turn)["id"], "alice");
+    assert_eq!(initiator(&ci_turn)["id"], "ci");
+    assert_eq!(initiator(&ci_turn)["display_name"], "CI");
+    // Turns run before any client named itself stay unattributed
+    assert!(turns[0].get("initiator").is_none());
+
+    let branches = lines[4]["result"]["branches"].as_array().unwrap();
+    let forked = branches
+        .iter()
+        .find(|branch| branch["name"] == "alice-experiment")
+        .unwrap();
+    assert_eq!(forked["creator"], "alice");
+    assert_eq!(forked["created_by"]["id"], "alice");
+}
+
+#[test]
+fn registered_commands_extend_the_protocol() {
+    use duet::service::commands::{CommandError, ParamKind, ParamSpec, command_fn};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let control = Control::init(config).unwrap();
+    let mut service = Service::new(control);
+
+    let greet = || {
+        command_fn(
+            vec![
+                ParamSpec::required("name", ParamKind::String).describe("who to greet"),
+                ParamSpec::optional("shout", ParamKind::Boolean),
+            ],
+            |control, params| {
+                let name = params["name"].as_str().unwrap();
+                if name == "nobody" {
+                    return Err(CommandError::Failed("nobody to greet".to_string()));
+                }
+                let mut greeting = format!("hello, {}", name);
+                if params["shout"].as_bool().unwrap_or(false) {
+                    greeting = greeting.to_uppercase();
+                }
+                let status = control.status()?;
+                Ok(json!({
+                    "greeting": greeting,
+                    "branch": status.active_branch.to_string(),
+                }))
+            },
+        )
+    };
+    service.register_command("greet", greet()).unwrap();
+    assert!(service.register_command("greet", greet()).is_err());
+    assert!(service.register_command("status", greet()).is_err());
+
+    let handshake = service.call(
+        "handshake",
+        &json!({"client": "test", "protocol_version": duet::PROTOCOL_VERSION}),
+    );
+    let listed = &handshake["result"]["commands"][0];
+    assert_eq!(listed["name"], "greet");
+    assert_eq!(listed["params"][0]["name"], "name");
+    assert_eq!(listed["params"][0]["kind"], "string");
+    assert_eq!(listed["params"][1]["required"], false);
+
+    let response = service.call("greet", &json!({"name": "ada", "shout": true}));
+    assert_eq!(response["result"]["greeting"], "HELLO, ADA");
+    assert_eq!(response["result"]["branch"], "main");
+
+    let missing = service.call("greet", &json!({}));
+    assert_eq!(missing["error"]["code"], "invalid_params");
+    let mistyped = service.call("greet", &json!({"name": "ada", "shout": "yes"}));
+    assert_eq!(mistyped["error"]["code"], "invalid_params");
+    let failed = service.call("greet", &json!({"name": "nobody"}));
+    assert_eq!(failed["error"]["code"], "command_failed");
+    assert_eq!(failed["error"]["message"], "nobody to greet");
+
+    let unknown = service.call("farewell", &json!({}));
+    assert_eq!(unknown["error"]["code"], "unsupported_command");
+}
+
+#[test]
+fn direct_calls_are_held_to_identity_requirements() {
+    use duet::runtime::identity::{IdentityClaim, Principal, token_digest};
+
+    let temp = TempDir::new().unwrap();
+    let mut config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    config.identity.required = true;
+    config.identity.principals.insert(
+        "ci".to_string(),
+        Principal {
+            kind: Default::default(),
+            display_name: None,
+            token_sha256: token_digest("ci-token"),
+        },
+    );
+    let mut control = Control::init(config).unwrap();
+    duet::codebase::ensure_kv_entity(&mut control, "scratch").unwrap();
+    let mut service = Service::new(control);
+    let put = json!({"actor": "kv", "payload": "<kv-put \"notes/\">"});
+
+    // Transports without a handshake (dashboard, gRPC) cannot skip it
+    let response = service.call("send_message", &put);
+    assert_eq!(response["error"]["code"], "protocol_error");
+    let claim = |token: &str| IdentityClaim {
+        id: "ci".to_string(),
+        kind: None,
+        display_name: None,
+        token: Some(token.to_string()),
+    };
+    let response = service.call_as("send_message", &put, Some(&claim("wrong")));
+    assert_eq!(response["error"]["code"], "protocol_error");
+
+    let response = service.call_as("send_message", &put, Some(&claim("ci-token")));
+    let turn_id = response["result"]["turn_id"].clone();
+    assert!(turn_id.is_string(), "{response}");
+    let history = service.call_as(
+        "history",
+        &json!({"branch": "main", "limit": 100}),
+        Some(&claim("ci-token")),
+    );
+    let turn = history["result"]["turns"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|turn| turn["turn_id"] == turn_id)
+        .cloned()
+        .unwrap();
+    assert_eq!(turn["initiator"]["id"], "ci");
+}
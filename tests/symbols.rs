@@ -0,0 +1,150 @@
+//! Symbol catalog integration tests
+
+use duet::codebase::{self, SymbolEntry};
+use duet::runtime::RuntimeConfig;
+use duet::runtime::control::Control;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+fn new_control() -> (Control, TempDir) {
+    let temp = TempDir::new().unwrap();
+    let control = Control::init(config(temp.path())).expect("control init failed");
+    (control, temp)
+}
+
+fn config(root: &Path) -> RuntimeConfig {
+    RuntimeConfig {
+        root: root.to_path_buf(),
+        snapshot_interval: 5,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    }
+}
+
+/// Run queued turns until no extraction task is left to report back.
+fn settle(control: &mut Control) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        control.drain_pending().unwrap();
+        let extracting = control
+            .list_tasks()
+            .iter()
+            .any(|task| task.name.starts_with("symbols-"));
+        if !extracting {
+            break;
+        }
+        assert!(Instant::now() < deadline, "symbol extraction timed out");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    control.drain_pending().unwrap();
+}
+
+fn names(symbols: &[SymbolEntry]) -> Vec<(String, String, String)> {
+    let mut names: Vec<_> = symbols
+        .iter()
+        .map(|s| (s.path.clone(), s.kind.clone(), s.name.clone()))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn symbols_follow_workspace_rescans() {
+    let (mut control, _storage) = new_control();
+    let workspace = TempDir::new().unwrap();
+    fs::write(workspace.path().join("lib.rs"), "pub struct Alpha;\n").unwrap();
+    fs::write(workspace.path().join("notes.txt"), "fn not_code() {}\n").unwrap();
+
+    let ws = codebase::ensure_workspace_entity(&mut control, workspace.path()).unwrap();
+    let symbols = codebase::ensure_symbols_entity(&mut control, workspace.path()).unwrap();
+    settle(&mut control);
+
+    let found = codebase::list_symbols(&control, &symbols);
+    assert_eq!(
+        names(&found),
+        vec![("lib.rs".into(), "struct".into(), "Alpha".into())]
+    );
+    assert_eq!(found[0].start_line, 1);
+
+    fs::write(
+        workspace.path().join("lib.rs"),
+        "pub struct Alpha;\n\npub fn beta() {}\n",
+    )
+    .unwrap();
+    fs::write(workspace.path().join("tool.py"), "class Gamma:\n    pass\n").unwrap();
+    codebase::workspace_rescan(&mut control, &ws).unwrap();
+    settle(&mut control);
+
+    assert_eq!(
+        names(&codebase::list_symbols(&control, &symbols)),
+        vec![
+            ("lib.rs".into(), "function".into(), "beta".into()),
+            ("lib.rs".into(), "struct".into(), "Alpha".into()),
+            ("tool.py".into(), "class".into(), "Gamma".into()),
+        ]
+    );
+
+    fs::remove_file(workspace.path().join("lib.rs")).unwrap();
+    codebase::workspace_rescan(&mut control, &ws).unwrap();
+    settle(&mut control);
+
+    assert_eq!(
+        names(&codebase::list_symbols(&control, &symbols)),
+        vec![("tool.py".into(), "class".into(), "Gamma".into())]
+    );
+}
+
+#[test]
+fn symbols_are_retracted_after_a_restart() {
+    let storage = TempDir::new().unwrap();
+    let workspace = TempDir::new().unwrap();
+    fs::write(workspace.path().join("lib.rs"), "pub struct Alpha;\n").unwrap();
+    fs::write(workspace.path().join("tool.py"), "class Gamma:\n    pass\n").unwrap();
+
+    // Snapshot every turn, so the catalog hydrates with the head's state
+    let mut config = config(storage.path());
+    config.snapshot_interval = 1;
+
+    {
+        let mut control = Control::init(config.clone()).unwrap();
+        codebase::ensure_workspace_entity(&mut control, workspace.path()).unwrap();
+        codebase::ensure_symbols_entity(&mut control, workspace.path()).unwrap();
+        settle(&mut control);
+    }
+
+    let mut control = Control::new(config.clone()).unwrap();
+    let head = control.status().unwrap().head_turn;
+    control.goto(head).unwrap();
+    let ws = codebase::workspace_handle(&control).unwrap();
+    let symbols = codebase::ensure_symbols_entity(&mut control, workspace.path()).unwrap();
+    settle(&mut control);
+    assert_eq!(codebase::list_symbols(&control, &symbols).len(), 2);
+
+    // The hydrated catalog still knows which symbols belong to lib.rs
+    fs::remove_file(workspace.path().join("lib.rs")).unwrap();
+    codebase::workspace_rescan(&mut control, &ws).unwrap();
+    settle(&mut control);
+    assert_eq!(
+        names(&codebase::list_symbols(&control, &symbols)),
+        vec![("tool.py".into(), "class".into(), "Gamma".into())]
+    );
+}
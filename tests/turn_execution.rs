@@ -13,6 +13,23 @@ fn test_runtime_initialization() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     // Initialize storage
@@ -32,6 +49,23 @@ fn test_single_turn_execution() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     Runtime::init(config.clone()).unwrap();
@@ -62,6 +96,23 @@ fn test_multiple_turns_deterministic_ordering() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     Runtime::init(config.clone()).unwrap();
@@ -98,6 +149,23 @@ fn test_journal_persistence_after_execution() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     Runtime::init(config.clone()).unwrap();
@@ -134,6 +202,23 @@ fn test_parent_turn_tracking() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     Runtime::init(config.clone()).unwrap();
@@ -201,6 +286,23 @@ fn test_snapshot_creation_at_interval() {
         snapshot_interval: 3, // Snapshot every 3 turns
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     Runtime::init(config.clone()).unwrap();
@@ -224,6 +326,68 @@ fn test_snapshot_creation_at_interval() {
     // (Verification would require reading snapshot files, which we'll add later)
 }
 
+#[test]
+fn test_verify_snapshots_detects_divergent_state() {
+    use duet::runtime::snapshot::SnapshotManager;
+    use duet::runtime::storage::Storage;
+    use duet::runtime::turn::{ActorId, BranchId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 2,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Runtime::init(config.clone()).unwrap();
+    let mut runtime = Runtime::new(config).unwrap();
+
+    let actor_id = ActorId::new();
+    for i in 0..5 {
+        let value = preserves::IOValue::new(preserves::SignedInteger::from(i));
+        runtime.assert_value(actor_id.clone(), value);
+        runtime.step().unwrap().expect("turn");
+    }
+
+    let report = runtime.verify_snapshots(&BranchId::main()).unwrap();
+    assert_eq!(report.checked, 2);
+    assert!(report.is_ok(), "{:?}", report.mismatches);
+
+    // Drop an assertion from the first snapshot behind the runtime's back
+    let manager = SnapshotManager::new(Storage::new(temp.path().to_path_buf()), 2);
+    let first = manager.list(&BranchId::main())[0].turn_count;
+    let mut snapshot = manager.load_by_count(&BranchId::main(), first).unwrap();
+    snapshot.assertions.active.clear();
+    manager.save(&snapshot).unwrap();
+
+    let report = runtime.verify_snapshots(&BranchId::main()).unwrap();
+    assert_eq!(report.checked, 2);
+    assert_eq!(report.mismatches.len(), 1);
+    assert_eq!(report.mismatches[0].turn_count, first);
+    assert_ne!(
+        report.mismatches[0].stored_hash,
+        report.mismatches[0].derived_hash
+    );
+}
+
 #[test]
 fn test_no_turns_when_queue_empty() {
     let temp = TempDir::new().unwrap();
@@ -232,6 +396,23 @@ fn test_no_turns_when_queue_empty() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     Runtime::init(config.clone()).unwrap();
@@ -252,6 +433,23 @@ fn test_flow_control_blocking() {
         snapshot_interval: 10,
         flow_control_limit: 5, // Low limit to test blocking
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     Runtime::init(config.clone()).unwrap();
@@ -296,6 +494,23 @@ fn test_branch_fork_and_switch() {
         snapshot_interval: 10,
         flow_control_limit: 100,
         debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
     };
 
     Runtime::init(config.clone()).unwrap();
@@ -335,3 +550,2244 @@ fn test_branch_fork_and_switch() {
     runtime.switch_branch(BranchId::main()).unwrap();
     assert_eq!(runtime.current_branch().0.as_str(), "main");
 }
+
+#[test]
+fn test_history_on_reads_other_branches_without_switching() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::{ActorId, BranchId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+
+    control
+        .runtime_mut()
+        .assert_value(actor_id.clone(), preserves::IOValue::symbol("shared"));
+    control.runtime_mut().step().unwrap().expect("main turn");
+
+    let experiment = control.runtime_mut().fork("experiment", None).unwrap();
+    control
+        .runtime_mut()
+        .switch_branch(experiment.clone())
+        .unwrap();
+    control
+        .runtime_mut()
+        .assert_value(actor_id.clone(), preserves::IOValue::symbol("experimental"));
+    let fork_turn = control.runtime_mut().step().unwrap().expect("fork turn");
+    control
+        .runtime_mut()
+        .switch_branch(BranchId::main())
+        .unwrap();
+
+    // The fork's history includes the turn inherited from main
+    let history = control.history_on(&experiment, 0, 10).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].turn_id, fork_turn.turn_id);
+
+    // Logical timestamps attribute inherited turns to the branch that
+    // recorded them and order the history causally
+    assert_eq!(history[0].logical_time.branch, BranchId::main());
+    assert_eq!(history[1].logical_time, fork_turn.logical_timestamp());
+    assert!(history[0].logical_time.sequence < history[1].logical_time.sequence);
+    assert_eq!(
+        control.branch_head(&experiment).unwrap().logical_time,
+        Some(fork_turn.logical_timestamp())
+    );
+
+    let values = |infos: Vec<duet::runtime::control::AssertionInfo>| -> Vec<_> {
+        infos.into_iter().map(|info| info.value).collect()
+    };
+    assert_eq!(
+        values(control.assertions_on(&experiment, None).unwrap()),
+        vec![
+            preserves::IOValue::symbol("shared"),
+            preserves::IOValue::symbol("experimental")
+        ]
+    );
+    assert_eq!(
+        values(
+            control
+                .assertions_on(&experiment, Some(&history[0].turn_id))
+                .unwrap()
+        ),
+        vec![preserves::IOValue::symbol("shared")]
+    );
+
+    // Reading never moved the live branch
+    assert_eq!(control.runtime().current_branch(), BranchId::main());
+}
+
+#[test]
+fn test_experiment_runs_variants_on_ephemeral_forks() {
+    use duet::runtime::Control;
+    use duet::runtime::experiment::{ExperimentOptions, ExperimentVariant, Promotion};
+    use duet::runtime::turn::{ActorId, BranchId};
+    use preserves::IOValue;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor = ActorId::new();
+
+    control
+        .runtime_mut()
+        .assert_value(actor.clone(), IOValue::symbol("base"));
+    let base = control.runtime_mut().step().unwrap().expect("base turn");
+    control
+        .runtime_mut()
+        .assert_value(actor.clone(), IOValue::symbol("later"));
+    let head = control.runtime_mut().step().unwrap().expect("later turn");
+
+    let variants = vec![
+        ExperimentVariant::new("one").assert(actor.clone(), IOValue::symbol("a")),
+        ExperimentVariant::new("two")
+            .assert(actor.clone(), IOValue::symbol("b"))
+            .assert(actor.clone(), IOValue::symbol("c")),
+    ];
+    let report = control
+        .experiment(
+            base.turn_id.clone(),
+            variants,
+            ExperimentOptions {
+                max_turns: 10,
+                promote: Some(Promotion {
+                    branch: BranchId::new("winner"),
+                    details: Default::default(),
+                    pick: |outcomes| {
+                        outcomes
+                            .iter()
+                            .max_by_key(|outcome| outcome.asserted.len())
+                            .map(|outcome| outcome.index)
+                    },
+                }),
+            },
+        )
+        .unwrap();
+
+    // Each variant starts from the base, so neither sees the later turn
+    assert_eq!(report.variants.len(), 2);
+    let one = &report.variants[0];
+    assert!(one.quiescent && one.warnings.is_empty());
+    assert_eq!(one.turns.len(), 1);
+    assert_eq!(
+        one.asserted
+            .iter()
+            .map(|change| change.value.clone())
+            .collect::<Vec<_>>(),
+        vec![IOValue::symbol("a")]
+    );
+    assert!(one.retracted.is_empty());
+    assert_eq!(report.variants[1].asserted.len(), 2);
+
+    // The winner is kept as a named fork; the ephemeral forks are gone
+    let promoted = report.promoted.expect("promoted variant");
+    assert_eq!(promoted.index, 1);
+    let branches: Vec<BranchId> = control
+        .list_branches()
+        .unwrap()
+        .into_iter()
+        .map(|info| info.name)
+        .collect();
+    assert!(branches.contains(&BranchId::new("winner")));
+    assert!(!branches.iter().any(|branch| branch.0.starts_with("__")));
+    let values: Vec<IOValue> = control
+        .assertions_on(&BranchId::new("winner"), None)
+        .unwrap()
+        .into_iter()
+        .map(|info| info.value)
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            IOValue::symbol("base"),
+            IOValue::symbol("b"),
+            IOValue::symbol("c")
+        ]
+    );
+
+    // The origin is back at its head with its live state intact
+    assert_eq!(control.runtime().current_branch(), BranchId::main());
+    assert_eq!(control.status().unwrap().head_turn, head.turn_id);
+    let mut live: Vec<IOValue> = control
+        .runtime()
+        .assertions_for_actor(&actor)
+        .unwrap()
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    live.sort();
+    assert_eq!(
+        live,
+        vec![IOValue::symbol("base"), IOValue::symbol("later")]
+    );
+}
+
+#[test]
+fn test_actor_history_follows_parent_links_and_filters_facets() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::{ActorId, BranchId, FacetId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor = ActorId::new();
+    let other = ActorId::new();
+    let inbox = FacetId::new();
+    let outbox = FacetId::new();
+
+    // Interleave the two actors so the actor's turns are not contiguous
+    let mut expected = Vec::new();
+    let mut on_inbox = Vec::new();
+    for (index, facet) in [&inbox, &outbox, &inbox].into_iter().enumerate() {
+        let turn = control
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::new(index as i64),
+            )
+            .unwrap();
+        if facet == &inbox {
+            on_inbox.push(turn.clone());
+        }
+        expected.push(turn);
+        control
+            .send_message(
+                other.clone(),
+                inbox.clone(),
+                preserves::IOValue::symbol("noise"),
+            )
+            .unwrap();
+    }
+
+    let main = BranchId::main();
+    let ids = |turns: Vec<duet::runtime::control::TurnSummary>| -> Vec<_> {
+        turns.into_iter().map(|turn| turn.turn_id).collect()
+    };
+    assert_eq!(
+        ids(control.actor_history(&main, &actor, None, 0, 10).unwrap()),
+        expected
+    );
+    assert_eq!(
+        ids(control
+            .actor_history(&main, &actor, Some(&inbox), 0, 10)
+            .unwrap()),
+        on_inbox
+    );
+    assert_eq!(
+        ids(control.actor_history(&main, &actor, None, 1, 1).unwrap()),
+        vec![expected[1].clone()]
+    );
+    assert!(
+        control
+            .actor_history(&main, &ActorId::new(), None, 0, 10)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_history_cursor_is_stable_during_execution() {
+    use duet::runtime::Control;
+    use duet::runtime::cursor::CursorDirection;
+    use duet::runtime::turn::{ActorId, BranchId, FacetId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+    let run = |control: &mut Control, count: i64| {
+        for i in 0..count {
+            let payload = preserves::IOValue::new(preserves::SignedInteger::from(i));
+            control
+                .runtime_mut()
+                .send_message(actor_id.clone(), facet_id.clone(), payload);
+            control
+                .runtime_mut()
+                .step()
+                .unwrap()
+                .expect("turn executes");
+        }
+    };
+    let main = BranchId::main();
+
+    run(&mut control, 3);
+    let first = control
+        .history_page(&main, None, CursorDirection::Forward, 2)
+        .unwrap();
+    assert_eq!(first.items.len(), 2);
+
+    // Turns landing between pages do not shift the next page
+    run(&mut control, 2);
+    let second = control
+        .history_page(
+            &main,
+            first.next_cursor.as_deref(),
+            CursorDirection::Forward,
+            2,
+        )
+        .unwrap();
+    let all = control.history(&main, 0, 10).unwrap();
+    assert_eq!(all.len(), 5);
+    assert_eq!(second.items[0].turn_id, all[2].turn_id);
+    assert_eq!(second.items[1].turn_id, all[3].turn_id);
+
+    let last = control
+        .history_page(
+            &main,
+            second.next_cursor.as_deref(),
+            CursorDirection::Forward,
+            2,
+        )
+        .unwrap();
+    assert_eq!(last.items.len(), 1);
+    assert!(last.next_cursor.is_none());
+
+    let newest = control
+        .history_page(&main, None, CursorDirection::Backward, 1)
+        .unwrap();
+    assert_eq!(newest.items[0].turn_id, all[4].turn_id);
+
+    // Cursors are tied to the listing they were issued for
+    assert!(
+        control
+            .entities_page(first.next_cursor.as_deref(), CursorDirection::Forward, 10)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_idempotent_messages_execute_once() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Runtime::init(config.clone()).unwrap();
+    let mut runtime = Runtime::new(config.clone()).unwrap();
+
+    use duet::runtime::turn::{ActorId, FacetId};
+
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+    let payload = preserves::IOValue::symbol("delivery");
+
+    let send = |runtime: &mut Runtime, key: &str| {
+        runtime
+            .send_message_idempotent(actor_id.clone(), facet_id.clone(), payload.clone(), key)
+            .unwrap()
+    };
+
+    runtime.send_message(
+        actor_id.clone(),
+        facet_id.clone(),
+        preserves::IOValue::symbol("setup"),
+    );
+    runtime.step().unwrap().expect("setup turn");
+
+    assert!(send(&mut runtime, "msg-1"));
+    // A redelivery while the original is still queued is suppressed
+    assert!(!send(&mut runtime, "msg-1"));
+    let original = runtime.step().unwrap().expect("original executes");
+    assert!(runtime.step().unwrap().is_none(), "duplicate never runs");
+
+    // ... as is a redelivery after it executed, even across restarts
+    drop(runtime);
+    let mut runtime = Runtime::new(config).unwrap();
+    assert!(!send(&mut runtime, "msg-1"));
+    assert!(send(&mut runtime, "msg-2"));
+    runtime.step().unwrap().expect("new key executes");
+
+    let annotations = runtime.duplicate_annotations().unwrap();
+    assert_eq!(annotations.len(), 2);
+    assert!(annotations.iter().all(|a| a.key == "msg-1"));
+    assert_eq!(annotations[0].original_turn, None);
+    assert_eq!(annotations[1].original_turn, Some(original.turn_id.clone()));
+
+    // Rewinding past the original forgets its key
+    runtime.back(2).unwrap();
+    assert!(send(&mut runtime, "msg-1"));
+}
+
+#[test]
+fn test_recurring_schedule_fires_every_n_turns() {
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+
+    Runtime::init(config.clone()).unwrap();
+    let mut runtime = Runtime::new(config.clone()).unwrap();
+
+    use duet::runtime::turn::{ActorId, FacetId, TurnInput};
+
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+    let tick = preserves::IOValue::symbol("housekeeping");
+    assert!(
+        runtime
+            .schedule_recurring(actor_id.clone(), facet_id.clone(), tick.clone(), 0)
+            .is_err()
+    );
+    let schedule_id = runtime
+        .schedule_recurring(actor_id.clone(), facet_id.clone(), tick.clone(), 2)
+        .unwrap();
+
+    let is_tick = |record: &duet::runtime::turn::TurnRecord| {
+        record.inputs.iter().any(
+            |input| matches!(input, TurnInput::ExternalMessage { payload, .. } if *payload == tick),
+        )
+    };
+
+    let run = |runtime: &mut Runtime, messages: usize| {
+        for _ in 0..messages {
+            runtime.send_message(
+                actor_id.clone(),
+                facet_id.clone(),
+                preserves::IOValue::symbol("work"),
+            );
+        }
+        let mut ticks = 0;
+        while let Some(record) = runtime.step().unwrap() {
+            if is_tick(&record) {
+                ticks += 1;
+            }
+        }
+        ticks
+    };
+
+    // Turns 1 and 2 run; turn 2 triggers a firing (turn 3), which does not
+    // trigger another on its own
+    assert_eq!(run(&mut runtime, 2), 1);
+    // Turn 4 triggers the next firing
+    assert_eq!(run(&mut runtime, 1), 1);
+
+    // Schedules persist across restarts
+    drop(runtime);
+    let mut runtime = Runtime::new(config).unwrap();
+    assert_eq!(runtime.recurring_schedules().len(), 1);
+    assert_eq!(run(&mut runtime, 2), 1);
+
+    assert!(runtime.cancel_recurring(schedule_id).unwrap());
+    assert_eq!(run(&mut runtime, 4), 0);
+}
+
+#[test]
+fn test_replay_across_runtime_versions_follows_policy() {
+    use duet::runtime::Control;
+    use duet::runtime::error::RuntimeError;
+    use duet::runtime::storage::Storage;
+    use duet::runtime::turn::{ActorId, BranchId};
+    use duet::runtime::version::{SegmentHeader, VersionPolicy, VersionStamp};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 100,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: VersionPolicy::Refuse,
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let actor_id = ActorId::new();
+    let first = {
+        let mut control = Control::init(config.clone()).unwrap();
+        control
+            .assert_value(actor_id.clone(), preserves::IOValue::symbol("old"))
+            .unwrap()
+    };
+
+    // Pretend an older release wrote the existing segment
+    let old = VersionStamp {
+        runtime: "0.0.1".into(),
+        registry: "0000000000000000".into(),
+    };
+    let header_path = Storage::new(temp.path().to_path_buf())
+        .branch_journal_dir(&BranchId::main())
+        .join("segment-000000.header.json");
+    let header = SegmentHeader {
+        version: old.clone(),
+        created_at: chrono::Utc::now(),
+    };
+    std::fs::write(&header_path, serde_json::to_vec(&header).unwrap()).unwrap();
+
+    // New turns go to a fresh segment stamped with the running version
+    let mut control = Control::new(config.clone()).unwrap();
+    let second = control
+        .assert_value(actor_id.clone(), preserves::IOValue::symbol("new"))
+        .unwrap();
+    let current = control.runtime().version().clone();
+    assert_eq!(
+        control.status().unwrap().journal_versions,
+        vec![old.clone(), current.clone()]
+    );
+
+    let history = control.history(&BranchId::main(), 0, 10).unwrap();
+    assert_eq!(history[0].version.as_ref(), Some(&old));
+    assert_eq!(history[1].version.as_ref(), Some(&current));
+
+    // Replaying the old segment is refused without disturbing the live head
+    let err = control.goto(first.clone()).unwrap_err();
+    assert!(matches!(err, RuntimeError::VersionMismatch { .. }), "{err}");
+    assert_eq!(control.status().unwrap().head_turn, second);
+
+    // The default policy only warns
+    drop(control);
+    let mut control = Control::new(RuntimeConfig {
+        version_policy: VersionPolicy::Warn,
+        ..config
+    })
+    .unwrap();
+    control.goto(first).unwrap();
+}
+
+#[test]
+fn test_merge_records_assertion_provenance() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::{ActorId, BranchId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+
+    let shared_turn = control
+        .assert_value(actor_id.clone(), preserves::IOValue::symbol("shared"))
+        .unwrap();
+    let experiment = control.runtime_mut().fork("experiment", None).unwrap();
+    control
+        .runtime_mut()
+        .switch_branch(experiment.clone())
+        .unwrap();
+    let fork_turn = control
+        .assert_value(actor_id.clone(), preserves::IOValue::symbol("experimental"))
+        .unwrap();
+    control
+        .runtime_mut()
+        .switch_branch(BranchId::main())
+        .unwrap();
+
+    let report = control.merge(experiment.clone(), BranchId::main()).unwrap();
+
+    let handle_of = |control: &Control, name: &'static str| {
+        control
+            .list_assertions(None)
+            .into_iter()
+            .find(|info| info.value == preserves::IOValue::symbol(name))
+            .map(|info| info.handle)
+            .unwrap()
+    };
+    let origins = control.assertion_origins().unwrap();
+
+    let merged = &origins[&handle_of(&control, "experimental")];
+    assert_eq!(merged.branch, experiment);
+    assert_eq!(merged.turn, fork_turn);
+    assert_eq!(merged.merge_turn.as_ref(), Some(&report.merge_turn));
+
+    let local = &origins[&handle_of(&control, "shared")];
+    assert_eq!(local.branch, BranchId::main());
+    assert_eq!(local.turn, shared_turn);
+    assert_eq!(local.merge_turn, None);
+}
+
+#[test]
+fn test_concurrent_edits_conflict_after_branch_switch_and_restart() {
+    use duet::runtime::Control;
+    use duet::runtime::actor::{Activation, Entity};
+    use duet::runtime::error::ActorResult;
+    use duet::runtime::registry::EntityCatalog;
+    use duet::runtime::turn::{ActorId, BranchId, FacetId, Handle};
+    use uuid::Uuid;
+
+    // Always writes to the same handle, so edits on two branches collide
+    struct SlotWriter;
+
+    impl Entity for SlotWriter {
+        fn on_message(
+            &self,
+            activation: &mut Activation,
+            payload: &preserves::IOValue,
+        ) -> ActorResult<()> {
+            activation.assert(Handle(Uuid::from_u128(7)), payload.clone());
+            Ok(())
+        }
+    }
+
+    EntityCatalog::global().register("slot-writer", |_config| Ok(Box::new(SlotWriter)));
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let mut control = Control::init(config.clone()).unwrap();
+    let actor = ActorId::new();
+    let facet = FacetId::new();
+    control
+        .register_entity(
+            actor.clone(),
+            facet.clone(),
+            "slot-writer".to_string(),
+            preserves::IOValue::symbol("nil"),
+        )
+        .unwrap();
+    let switched = control.runtime_mut().fork("switched", None).unwrap();
+    let restarted = control.runtime_mut().fork("restarted", None).unwrap();
+
+    control
+        .send_message(
+            actor.clone(),
+            facet.clone(),
+            preserves::IOValue::symbol("left"),
+        )
+        .unwrap();
+
+    // The fork must not inherit the clock main reached after forking
+    control
+        .runtime_mut()
+        .switch_branch(switched.clone())
+        .unwrap();
+    control
+        .send_message(
+            actor.clone(),
+            facet.clone(),
+            preserves::IOValue::symbol("right"),
+        )
+        .unwrap();
+    control
+        .runtime_mut()
+        .switch_branch(BranchId::main())
+        .unwrap();
+    let report = control.merge(switched, BranchId::main()).unwrap();
+    assert!(!report.warnings.is_empty(), "{:?}", report);
+
+    // Nor may a restart forget the clocks the journal recorded
+    drop(control);
+    let mut control = Control::new(config).unwrap();
+    control
+        .runtime_mut()
+        .switch_branch(restarted.clone())
+        .unwrap();
+    control
+        .send_message(
+            actor.clone(),
+            facet.clone(),
+            preserves::IOValue::symbol("other"),
+        )
+        .unwrap();
+    control
+        .runtime_mut()
+        .switch_branch(BranchId::main())
+        .unwrap();
+    let report = control.merge(restarted, BranchId::main()).unwrap();
+    assert!(!report.warnings.is_empty(), "{:?}", report);
+}
+
+#[test]
+fn test_unequal_turn_counts_across_a_fork_still_conflict() {
+    use duet::runtime::Control;
+    use duet::runtime::actor::{Activation, Entity};
+    use duet::runtime::error::ActorResult;
+    use duet::runtime::registry::EntityCatalog;
+    use duet::runtime::turn::{ActorId, BranchId, FacetId, Handle};
+    use uuid::Uuid;
+
+    // Writes "left"/"right" to one handle and ignores everything else
+    struct SideWriter;
+
+    impl Entity for SideWriter {
+        fn on_message(
+            &self,
+            activation: &mut Activation,
+            payload: &preserves::IOValue,
+        ) -> ActorResult<()> {
+            if matches!(payload.as_symbol().as_deref(), Some("left" | "right")) {
+                activation.assert(Handle(Uuid::from_u128(8)), payload.clone());
+            }
+            Ok(())
+        }
+    }
+
+    EntityCatalog::global().register("side-writer", |_config| Ok(Box::new(SideWriter)));
+
+    let temp = TempDir::new().unwrap();
+    let mut control = Control::init(RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    })
+    .unwrap();
+    let actor = ActorId::new();
+    let facet = FacetId::new();
+    control
+        .register_entity(
+            actor.clone(),
+            facet.clone(),
+            "side-writer".to_string(),
+            preserves::IOValue::symbol("nil"),
+        )
+        .unwrap();
+    let experiment = control.runtime_mut().fork("experiment", None).unwrap();
+
+    // The actor runs more turns on main, so its clock there dominates
+    for payload in ["noise", "noise", "left"] {
+        control
+            .send_message(
+                actor.clone(),
+                facet.clone(),
+                preserves::IOValue::symbol(payload),
+            )
+            .unwrap();
+    }
+    control
+        .runtime_mut()
+        .switch_branch(experiment.clone())
+        .unwrap();
+    control
+        .send_message(
+            actor.clone(),
+            facet.clone(),
+            preserves::IOValue::symbol("right"),
+        )
+        .unwrap();
+    control
+        .runtime_mut()
+        .switch_branch(BranchId::main())
+        .unwrap();
+
+    let report = control.merge(experiment, BranchId::main()).unwrap();
+    assert!(!report.warnings.is_empty(), "{:?}", report);
+}
+
+#[test]
+fn test_merge_forecast_predicts_changes_without_merging() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::{ActorId, BranchId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+
+    control
+        .assert_value(actor_id.clone(), preserves::IOValue::symbol("shared"))
+        .unwrap();
+    let experiment = control.runtime_mut().fork("experiment", None).unwrap();
+    control
+        .runtime_mut()
+        .switch_branch(experiment.clone())
+        .unwrap();
+    control
+        .assert_value(actor_id.clone(), preserves::IOValue::symbol("experimental"))
+        .unwrap();
+    control
+        .retract_matching(actor_id.clone(), preserves::IOValue::symbol("shared"))
+        .unwrap();
+    control
+        .runtime_mut()
+        .switch_branch(BranchId::main())
+        .unwrap();
+    let head_before = control.branch_head(&BranchId::main()).unwrap();
+
+    let forecast = control
+        .merge_forecast(&experiment, &BranchId::main())
+        .unwrap();
+    let values = |assertions: &[duet::runtime::branch::ForecastAssertion]| {
+        assertions
+            .iter()
+            .map(|assertion| assertion.value.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        values(&forecast.added),
+        vec![preserves::IOValue::symbol("experimental")]
+    );
+    assert_eq!(
+        values(&forecast.retracted),
+        vec![preserves::IOValue::symbol("shared")]
+    );
+    assert!(forecast.warnings.is_empty());
+
+    let head_after = control.branch_head(&BranchId::main()).unwrap();
+    assert_eq!(head_after.turn_id, head_before.turn_id);
+    assert_eq!(head_after.turn_count, head_before.turn_count);
+    assert_eq!(control.list_assertions(None).len(), 1);
+}
+
+#[test]
+fn test_journal_redacts_configured_patterns() {
+    use duet::runtime::Control;
+    use duet::runtime::redaction::{RedactionConfig, RedactionRule};
+    use duet::runtime::turn::{ActorId, BranchId, TurnOutput};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 100,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: RedactionConfig {
+            rules: vec![RedactionRule {
+                name: "api-key".into(),
+                pattern: r"sk-[A-Za-z0-9]{8,}".into(),
+            }],
+        },
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+    let transcript = |text: &str| {
+        preserves::IOValue::record(
+            preserves::IOValue::symbol("transcript"),
+            vec![preserves::IOValue::new(text.to_string())],
+        )
+    };
+
+    let first = control
+        .assert_value(actor_id.clone(), transcript("export KEY=sk-live12345678"))
+        .unwrap();
+    let second = control
+        .assert_value(actor_id.clone(), transcript("again: sk-live12345678"))
+        .unwrap();
+
+    // Live state keeps the original text
+    assert!(
+        control
+            .list_assertions(None)
+            .iter()
+            .any(|info| info.value == transcript("export KEY=sk-live12345678"))
+    );
+
+    let reader = control.runtime().journal_reader(&BranchId::main()).unwrap();
+    let asserted = |turn| {
+        reader
+            .read(turn)
+            .unwrap()
+            .outputs
+            .into_iter()
+            .find_map(|output| match output {
+                TurnOutput::Assert { value, .. } => {
+                    value.index(0).as_string().map(|s| s.into_owned())
+                }
+                _ => None,
+            })
+            .unwrap()
+    };
+    let first_text = asserted(&first);
+    let second_text = asserted(&second);
+    assert!(!first_text.contains("sk-live12345678"));
+    assert!(first_text.starts_with("export KEY=[REDACTED:api-key:"));
+
+    // The same secret always maps to the same placeholder
+    let placeholder = first_text.trim_start_matches("export KEY=");
+    assert_eq!(second_text, format!("again: {}", placeholder));
+}
+
+#[test]
+fn test_retract_matching_runs_as_one_turn() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::{ActorId, BranchId, TurnInput, TurnOutput};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+
+    for text in [
+        "<task 1 \"stale\">",
+        "<task 2 \"stale\">",
+        "<task 3 \"live\">",
+        "<note \"stale\">",
+    ] {
+        control
+            .assert_value(actor_id.clone(), text.parse().unwrap())
+            .unwrap();
+    }
+
+    let pattern: preserves::IOValue = "<task '<_>' \"stale\">".parse().unwrap();
+    let report = control.retract_matching(actor_id.clone(), pattern).unwrap();
+    assert_eq!(report.count, 2);
+
+    let record = control
+        .runtime()
+        .lineage_records(&BranchId::main(), Some(&report.turn_id))
+        .unwrap()
+        .pop()
+        .unwrap();
+    assert_eq!(record.turn_id, report.turn_id);
+    assert!(matches!(
+        record.inputs.as_slice(),
+        [TurnInput::RetractMatching { handles, .. }] if *handles == report.handles
+    ));
+    let retracted = record
+        .outputs
+        .iter()
+        .filter(|output| matches!(output, TurnOutput::Retract { .. }))
+        .count();
+    assert_eq!(retracted, 2);
+
+    let remaining: Vec<preserves::IOValue> = control
+        .list_assertions(Some(&actor_id))
+        .into_iter()
+        .map(|info| info.value)
+        .collect();
+    assert_eq!(remaining.len(), 2);
+    for text in ["<note \"stale\">", "<task 3 \"live\">"] {
+        assert!(remaining.contains(&text.parse().unwrap()));
+    }
+}
+
+#[test]
+fn test_turn_sink_receives_committed_turns_in_order() {
+    use duet::runtime::sink::{SinkOptions, TurnSink};
+    use duet::runtime::turn::{ActorId, FacetId, TurnId, TurnRecord};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Collect(Mutex<Vec<TurnId>>);
+
+    impl TurnSink for Collect {
+        fn accept(&self, record: &TurnRecord) -> Result<(), String> {
+            self.0.lock().unwrap().push(record.turn_id.clone());
+            Ok(())
+        }
+    }
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    Runtime::init(config.clone()).unwrap();
+    let mut runtime = Runtime::new(config).unwrap();
+
+    let sink = Arc::new(Collect::default());
+    runtime.add_turn_sink("collect", sink.clone(), SinkOptions::default());
+
+    let actor_id = ActorId::new();
+    let facet_id = FacetId::new();
+    let mut executed = Vec::new();
+    for i in 0..3 {
+        runtime.send_message(
+            actor_id.clone(),
+            facet_id.clone(),
+            preserves::IOValue::new(i as i64),
+        );
+        executed.push(runtime.step().unwrap().unwrap().turn_id);
+    }
+
+    runtime.flush_turn_sinks();
+    assert_eq!(*sink.0.lock().unwrap(), executed);
+    let stats = runtime.turn_sink_stats();
+    assert_eq!(stats[0].name, "collect");
+    assert_eq!((stats[0].delivered, stats[0].dropped), (3, 0));
+}
+
+#[test]
+fn test_paused_actor_holds_inputs_until_resumed() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::{ActorId, BranchId, FacetId, TurnInput};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config.clone()).unwrap();
+    let runaway = ActorId::new();
+    let other = ActorId::new();
+
+    let pause_turn = control.pause_actor(runaway.clone()).unwrap().unwrap();
+    assert!(control.pause_actor(runaway.clone()).unwrap().is_none());
+
+    for actor in [&runaway, &other] {
+        control.runtime_mut().send_message(
+            actor.clone(),
+            FacetId::new(),
+            preserves::IOValue::symbol("work"),
+        );
+    }
+    let executed = control.step(10).unwrap();
+    assert_eq!(executed.len(), 1);
+    assert_eq!(executed[0].actor, other);
+
+    let status = control.status().unwrap();
+    assert_eq!(status.paused_actors, vec![runaway.clone()]);
+    assert_eq!(status.pending_inputs, 1);
+
+    let record = control
+        .runtime()
+        .lineage_records(&BranchId::main(), Some(&pause_turn))
+        .unwrap()
+        .pop()
+        .unwrap();
+    assert!(matches!(
+        record.inputs.as_slice(),
+        [TurnInput::PauseActor { actor }] if *actor == runaway
+    ));
+
+    // The pause is rebuilt from the journal after a restart
+    drop(control);
+    let mut control = Control::new(config).unwrap();
+    assert_eq!(
+        control.status().unwrap().paused_actors,
+        vec![runaway.clone()]
+    );
+
+    control.runtime_mut().send_message(
+        runaway.clone(),
+        FacetId::new(),
+        preserves::IOValue::symbol("work"),
+    );
+    assert!(control.step(10).unwrap().is_empty());
+
+    assert!(control.resume_actor(runaway.clone()).unwrap().is_some());
+    let executed = control.step(10).unwrap();
+    assert_eq!(executed.len(), 1);
+    assert_eq!(executed[0].actor, runaway);
+    assert!(control.status().unwrap().paused_actors.is_empty());
+}
+
+#[test]
+fn test_memory_report_flags_unretracted_growth() {
+    use duet::runtime::Control;
+    use duet::runtime::memory::{MemoryThresholds, MemoryWarningKind};
+    use duet::runtime::turn::ActorId;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 10,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: MemoryThresholds {
+            assertions_per_actor: Some(10),
+            bytes_per_actor: None,
+            growth_turns: Some(5),
+        },
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let leaky = ActorId::new();
+    let tidy = ActorId::new();
+
+    for i in 0..6 {
+        control
+            .assert_value(leaky.clone(), preserves::IOValue::new(i as i64))
+            .unwrap();
+    }
+    control
+        .assert_value(tidy.clone(), preserves::IOValue::symbol("one"))
+        .unwrap();
+
+    let report = control.memory_report();
+    assert_eq!(report.actors[0].actor, leaky);
+    assert_eq!(report.actors[0].assertions, 6);
+    assert_eq!(report.actors[0].growth_turns, 6);
+    assert!(report.actors[0].approx_bytes > report.actors[1].approx_bytes);
+    assert_eq!(report.total_assertions, 7);
+
+    let kinds: Vec<_> = report
+        .warnings
+        .iter()
+        .map(|warning| (warning.actor.clone(), warning.kind))
+        .collect();
+    assert_eq!(kinds, vec![(leaky, MemoryWarningKind::SustainedGrowth)]);
+}
+
+#[test]
+fn test_managed_tasks_cancelled_by_facet_stop_and_rewind() {
+    use duet::runtime::Control;
+    use duet::runtime::actor::{Activation, Entity};
+    use duet::runtime::error::ActorResult;
+    use duet::runtime::registry::EntityCatalog;
+    use duet::runtime::turn::{ActorId, FacetId};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    static CANCELLED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Spawner;
+
+    impl Entity for Spawner {
+        fn on_message(
+            &self,
+            activation: &mut Activation,
+            payload: &preserves::IOValue,
+        ) -> ActorResult<()> {
+            match payload.as_symbol().map(|s| s.to_string()).as_deref() {
+                Some("spawn") => {
+                    activation.spawn_task("waiter", |task| {
+                        if task.token().wait(Duration::from_secs(30)) {
+                            CANCELLED.fetch_add(1, Ordering::SeqCst);
+                        }
+                    });
+                }
+                Some("stop") => {
+                    let facet = activation.current_facet.clone();
+                    activation.terminate_facet(facet);
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    fn wait_for_cancellations(count: usize) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while CANCELLED.load(Ordering::SeqCst) < count {
+            assert!(Instant::now() < deadline, "task was not cancelled");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    EntityCatalog::global().register("task-spawner", |_config| Ok(Box::new(Spawner)));
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 100,
+        flow_control_limit: 100,
+        debug: false,
+        approval_kinds: Vec::new(),
+        notifiers: Vec::new(),
+        limits: Default::default(),
+        secrets: Default::default(),
+        version_policy: Default::default(),
+        redaction: Default::default(),
+        branch_naming: Default::default(),
+        memory: Default::default(),
+        logging: Default::default(),
+        stubs: Default::default(),
+        output_order: Default::default(),
+        retention: Default::default(),
+        outbox: Default::default(),
+        identity: Default::default(),
+        snapshot_retention: Default::default(),
+        blobs: Default::default(),
+        durability: Default::default(),
+    };
+    let mut control = Control::init(config).unwrap();
+    let (first, second) = (ActorId::new(), ActorId::new());
+    let facet = FacetId::new();
+    for actor in [&first, &second] {
+        control
+            .register_entity(
+                actor.clone(),
+                facet.clone(),
+                "task-spawner".to_string(),
+                preserves::IOValue::symbol("nil"),
+            )
+            .unwrap();
+    }
+
+    let spawn = preserves::IOValue::symbol("spawn");
+    let kept_turn = control
+        .send_message(first.clone(), facet.clone(), spawn.clone())
+        .unwrap();
+    control
+        .send_message(second.clone(), facet.clone(), spawn)
+        .unwrap();
+    let tasks = control.list_tasks();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].actor, first);
+    assert_eq!(tasks[0].turn.as_ref(), Some(&kept_turn));
+
+    // Rewinding discards only the task spawned after the target
+    control.goto(kept_turn).unwrap();
+    wait_for_cancellations(1);
+    let tasks = control.list_tasks();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].actor, first);
+
+    // Stopping the owning facet cancels the rest
+    control
+        .send_message(first, facet, preserves::IOValue::symbol("stop"))
+        .unwrap();
+    wait_for_cancellations(2);
+    assert!(control.list_tasks().is_empty());
+}
+
+#[test]
+fn test_backup_is_consistent_while_turns_continue() {
+    use duet::runtime::Control;
+    use duet::runtime::backup::MANIFEST_FILE;
+    use duet::runtime::turn::{ActorId, BranchId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().join("live"),
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+
+    let mut heads = Vec::new();
+    for label in ["first", "second"] {
+        control
+            .runtime_mut()
+            .assert_value(actor_id.clone(), preserves::IOValue::symbol(label));
+        heads.push(control.runtime_mut().step().unwrap().expect("turn").turn_id);
+    }
+
+    let dest = temp.path().join("backup");
+    let manifest = control.backup(&dest).unwrap();
+    assert_eq!(manifest.heads.get(&BranchId::main()), heads.last());
+    assert!(dest.join(MANIFEST_FILE).exists());
+    assert!(
+        manifest
+            .files
+            .iter()
+            .any(|file| file.path.starts_with("journal"))
+    );
+
+    // The runtime keeps going without affecting the backup
+    control
+        .runtime_mut()
+        .assert_value(actor_id.clone(), preserves::IOValue::symbol("third"));
+    control
+        .runtime_mut()
+        .step()
+        .unwrap()
+        .expect("turn after backup");
+    assert_eq!(control.history(&BranchId::main(), 0, 10).unwrap().len(), 3);
+
+    // A destination that already holds a backup is refused
+    assert!(control.backup(&dest).is_err());
+
+    let restored = Control::new(RuntimeConfig {
+        root: dest,
+        ..Default::default()
+    })
+    .unwrap();
+    let history = restored.history(&BranchId::main(), 0, 10).unwrap();
+    let turns: Vec<_> = history.into_iter().map(|turn| turn.turn_id).collect();
+    assert_eq!(turns, heads);
+}
+
+#[test]
+fn test_turn_outputs_are_committed_in_canonical_order() {
+    use duet::runtime::actor::{Activation, Entity};
+    use duet::runtime::control::Control;
+    use duet::runtime::error::ActorResult;
+    use duet::runtime::ordering::OutputOrder;
+    use duet::runtime::registry::EntityCatalog;
+    use duet::runtime::turn::{ActorId, BranchId, FacetId, Handle, TurnOutput};
+
+    /// Asserts a batch of values under fresh (unordered) handles.
+    struct FanOut;
+
+    impl Entity for FanOut {
+        fn on_message(
+            &self,
+            activation: &mut Activation,
+            _payload: &preserves::IOValue,
+        ) -> ActorResult<()> {
+            for i in 0..16 {
+                activation.assert(Handle::new(), preserves::IOValue::new(i64::from(i)));
+            }
+            Ok(())
+        }
+    }
+
+    EntityCatalog::global().register("fan-out", |_config| Ok(Box::new(FanOut)));
+
+    let run = |output_order: OutputOrder| {
+        let temp = TempDir::new().unwrap();
+        let config = RuntimeConfig {
+            root: temp.path().to_path_buf(),
+            output_order,
+            ..Default::default()
+        };
+        let mut control = Control::init(config).unwrap();
+        let (actor, facet) = (ActorId::new(), FacetId::new());
+        control
+            .register_entity(
+                actor.clone(),
+                facet.clone(),
+                "fan-out".to_string(),
+                preserves::IOValue::symbol("nil"),
+            )
+            .unwrap();
+        let turn = control
+            .send_message(actor, facet, preserves::IOValue::symbol("go"))
+            .unwrap();
+        let report = control.verify_replay(&BranchId::main()).unwrap();
+        let record = control
+            .runtime()
+            .lineage_records(&BranchId::main(), None)
+            .unwrap()
+            .into_iter()
+            .find(|record| record.turn_id == turn)
+            .unwrap();
+        (report, turn, record)
+    };
+
+    let (report, _, record) = run(OutputOrder::Canonical);
+    assert!(report.is_ok(), "{:?}", report.unordered);
+    let handles: Vec<_> = record
+        .outputs
+        .iter()
+        .filter_map(|output| match output {
+            TurnOutput::Assert { handle, .. } => Some(handle.0),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(handles.len(), 16);
+    assert!(handles.is_sorted());
+
+    // Emission order keeps the entity's order, which verification flags
+    let (report, turn, _) = run(OutputOrder::Emission);
+    assert_eq!(report.unordered, vec![turn]);
+}
+
+#[test]
+fn test_dictionary_compression_shrinks_new_records_and_replays() {
+    use duet::runtime::Control;
+    use duet::runtime::compression::TrainingOptions;
+    use duet::runtime::turn::{ActorId, BranchId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let segment = temp.path().join("journal/main/segment-000000.turnlog");
+    let journal_size = || std::fs::metadata(&segment).unwrap().len();
+    let mut control = Control::init(config.clone()).unwrap();
+    let actor_id = ActorId::new();
+    let assert_transcripts = |control: &mut Control, range: std::ops::Range<usize>| {
+        for i in range {
+            control
+                .assert_value(
+                    actor_id.clone(),
+                    preserves::IOValue::record(
+                        preserves::IOValue::symbol("transcript"),
+                        vec![preserves::IOValue::new(format!(
+                            "agent turn {i}: Please show me READ src/runtime/journal.rs and \
+                             summarise the segment rotation logic for the reviewer"
+                        ))],
+                    ),
+                )
+                .unwrap();
+        }
+    };
+
+    assert!(control.journal_dictionary().unwrap().is_none());
+    assert_transcripts(&mut control, 0..100);
+    let uncompressed = journal_size();
+
+    let info = control
+        .train_journal_dictionary(&TrainingOptions {
+            max_dictionary_size: 8 * 1024,
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(info.samples, 100);
+    assert!(info.dictionary_bytes < info.generic_bytes);
+    assert_eq!(control.journal_dictionary().unwrap(), Some(info));
+
+    assert_transcripts(&mut control, 100..200);
+    let compressed = journal_size() - uncompressed;
+    assert!(
+        compressed < uncompressed / 2,
+        "{compressed} vs {uncompressed}"
+    );
+
+    // Mixed segments replay after a restart, and disabling keeps them readable
+    drop(control);
+    let mut control = Control::new(config.clone()).unwrap();
+    let head = control.history(&BranchId::main(), 199, 1).unwrap()[0]
+        .turn_id
+        .clone();
+    control.goto(head).unwrap();
+    assert_eq!(control.list_assertions(None).len(), 200);
+    control.disable_journal_compression().unwrap();
+    assert_transcripts(&mut control, 200..201);
+    let history = control.history(&BranchId::main(), 0, 1000).unwrap();
+    assert_eq!(history.len(), 201);
+    assert!(control.journal_dictionary().unwrap().is_none());
+}
+
+#[test]
+fn test_compaction_trims_journal_below_retention_horizon() {
+    use duet::runtime::Control;
+    use duet::runtime::error::{JournalError, RuntimeError};
+    use duet::runtime::journal::compactor::RetentionPolicy;
+    use duet::runtime::turn::{ActorId, BranchId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        ..Default::default()
+    };
+    let mut control = Control::init(config.clone()).unwrap();
+    let actor_id = ActorId::new();
+    let assert_notes = |control: &mut Control, range: std::ops::Range<usize>| {
+        for i in range {
+            control
+                .assert_value(
+                    actor_id.clone(),
+                    preserves::IOValue::record(
+                        preserves::IOValue::symbol("note"),
+                        vec![preserves::IOValue::new(i as i64)],
+                    ),
+                )
+                .unwrap();
+        }
+    };
+    assert_notes(&mut control, 0..23);
+    let main = BranchId::main();
+    let before = control.history(&main, 0, 1000).unwrap();
+    let first = before[0].turn_id.clone();
+    let head = before.last().unwrap().turn_id.clone();
+
+    // Nothing is compacted under the default policy
+    let untouched = control.compact_journal(&main, None).unwrap();
+    assert!(untouched.horizon.is_none());
+    assert_eq!(untouched.turns_kept, before.len());
+
+    let report = control
+        .compact_journal(
+            &main,
+            Some(&RetentionPolicy {
+                keep_turns: Some(6),
+                keep_days: None,
+            }),
+        )
+        .unwrap();
+    let horizon = report
+        .horizon
+        .clone()
+        .expect("a snapshot below the horizon");
+    assert_eq!(report.turns_kept, 6);
+    assert_eq!(report.turns_kept + report.turns_removed, before.len());
+    assert!(report.bytes_after < report.bytes_before);
+    assert!(report.snapshots_removed > 0);
+
+    // The horizon falls between periodic snapshots, so one is consolidated there
+    let snapshots = control.runtime().snapshots(&main);
+    assert_eq!(snapshots[0].turn_id, horizon);
+    assert_eq!(snapshots[0].turn_count, report.turns_removed as u64);
+
+    let after = control.history(&main, 0, 1000).unwrap();
+    assert_eq!(after.len(), report.turns_kept);
+    assert!(after.iter().all(|turn| turn.turn_id > horizon));
+
+    // The horizon snapshot stands in for the dropped records
+    control.goto(head.clone()).unwrap();
+    assert_eq!(control.list_assertions(None).len(), 23);
+    control.goto(horizon.clone()).unwrap();
+    assert_eq!(
+        control.list_assertions(None).len(),
+        before
+            .iter()
+            .position(|turn| turn.turn_id == horizon)
+            .unwrap()
+            + 1
+    );
+    assert!(matches!(
+        control.goto(first),
+        Err(RuntimeError::Journal(JournalError::Compacted { .. }))
+    ));
+
+    // New turns append to the trimmed journal and survive a restart
+    control.goto(head).unwrap();
+    assert_notes(&mut control, 23..24);
+    drop(control);
+    let mut control = Control::new(config).unwrap();
+    let turns = control.history(&main, 0, 1000).unwrap();
+    assert_eq!(turns.len(), report.turns_kept + 1);
+    control.goto(turns.last().unwrap().turn_id.clone()).unwrap();
+    assert_eq!(control.list_assertions(None).len(), 24);
+}
+
+#[test]
+fn test_reflog_records_head_movements_and_restores_them() {
+    use duet::runtime::Control;
+    use duet::runtime::error::{BranchError, RuntimeError};
+    use duet::runtime::identity::{Identity, IdentityKind};
+    use duet::runtime::reflog::HeadMove;
+    use duet::runtime::turn::{ActorId, BranchId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+    for i in 0..5 {
+        control
+            .assert_value(actor_id.clone(), preserves::IOValue::new(i as i64))
+            .unwrap();
+    }
+    let main = BranchId::main();
+    let head = control.branch_head(&main).unwrap().turn_id;
+
+    // Each turn is one step in the reflog, newest first
+    let steps = control.reflog(&main).unwrap();
+    assert_eq!(steps.len(), 5);
+    assert!(steps.iter().all(|entry| entry.action == HeadMove::Step));
+    assert_eq!(steps[0].to, head);
+    assert_eq!(steps[0].from, steps[1].to);
+
+    // An accidental rewind is logged with the client that caused it
+    control.set_identity(Some(Identity::new("alice", IdentityKind::Human)));
+    let rewound = control.back(3).unwrap();
+    control.set_identity(None);
+    let entries = control.reflog(&main).unwrap();
+    assert_eq!(entries[0].action, HeadMove::Back);
+    assert_eq!((&entries[0].from, &entries[0].to), (&head, &rewound));
+    assert_eq!(entries[0].client.as_ref().unwrap().id, "alice");
+    assert_eq!(control.list_assertions(None).len(), 2);
+
+    // Entry 1 is where the head was before the rewind
+    assert_eq!(control.restore_head(1).unwrap(), head);
+    assert_eq!(control.branch_head(&main).unwrap().turn_id, head);
+    assert_eq!(control.list_assertions(None).len(), 5);
+    let entries = control.reflog(&main).unwrap();
+    assert_eq!(entries[0].action, HeadMove::Restore);
+    assert_eq!(entries.len(), 7);
+
+    assert!(matches!(
+        control.restore_head(99),
+        Err(RuntimeError::Branch(BranchError::ReflogEntryNotFound {
+            index: 99,
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn test_snapshots_are_pruned_after_each_new_one() {
+    use duet::runtime::Control;
+    use duet::runtime::snapshot::SnapshotRetention;
+    use duet::runtime::turn::{ActorId, BranchId};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 5,
+        snapshot_retention: SnapshotRetention {
+            keep_last: Some(2),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+    for i in 0..23 {
+        control
+            .assert_value(actor_id.clone(), preserves::IOValue::new(i as i64))
+            .unwrap();
+    }
+    let main = BranchId::main();
+    let counts = |control: &Control| -> Vec<u64> {
+        control
+            .snapshots(&main)
+            .iter()
+            .map(|entry| entry.turn_count)
+            .collect()
+    };
+    assert_eq!(counts(&control), vec![15, 20]);
+
+    // Turns before the oldest kept snapshot replay from the journal
+    let history = control.history(&main, 0, 100).unwrap();
+    control.goto(history[7].turn_id.clone()).unwrap();
+    assert_eq!(control.list_assertions(None).len(), 8);
+    control.goto(history[17].turn_id.clone()).unwrap();
+    assert_eq!(control.list_assertions(None).len(), 18);
+
+    // A tagged snapshot survives an explicit prune down to one
+    let tagged = control.snapshots(&main)[0].turn_id.clone();
+    assert!(control.tag_snapshot(&main, &tagged, "keep").unwrap());
+    let removed = control
+        .prune_snapshots(
+            &main,
+            Some(&SnapshotRetention {
+                keep_last: Some(0),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+    assert_eq!(removed.len(), 1);
+    assert_eq!(counts(&control), vec![15]);
+}
+
+#[test]
+fn test_large_payloads_spill_into_blobs_and_read_back_whole() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::{ActorId, BranchId, TurnInput};
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let threshold = config.blobs.spill_threshold;
+    let content = "line of a large file\n".repeat(threshold / 8);
+    let value = preserves::IOValue::record(
+        preserves::IOValue::symbol("file-content"),
+        vec![
+            preserves::IOValue::new("notes.txt".to_string()),
+            preserves::IOValue::new(content.clone()),
+        ],
+    );
+
+    let main = BranchId::main();
+    let actor_id = ActorId::new();
+    let turn_id = {
+        let mut control = Control::init(config.clone()).unwrap();
+        control
+            .assert_value(actor_id.clone(), value.clone())
+            .unwrap()
+    };
+
+    // The content went to the blob store once, not into the journal
+    let segments: u64 = std::fs::read_dir(temp.path().join("journal/main"))
+        .unwrap()
+        .map(|entry| entry.unwrap().metadata().unwrap().len())
+        .sum();
+    assert!(segments < threshold as u64);
+    let blobs: Vec<_> = walk_files(&temp.path().join("blobs"));
+    assert!(!blobs.is_empty());
+    assert!(blobs.iter().all(|path| path.extension().unwrap() == "blob"));
+
+    // Readers, and state replayed from the journal, see the original payload
+    let mut control = Control::new(config).unwrap();
+    let record = control
+        .runtime()
+        .journal_reader(&main)
+        .unwrap()
+        .read(&turn_id)
+        .unwrap();
+    assert!(matches!(
+        &record.inputs[0],
+        TurnInput::Assert { value: recorded, .. } if *recorded == value
+    ));
+    control.goto(turn_id.clone()).unwrap();
+    let assertions = control.list_assertions(Some(&actor_id));
+    assert_eq!(assertions.len(), 1);
+    assert_eq!(assertions[0].value, value);
+
+    // Values shaped like references to a stored blob are data, whether or
+    // not their record was spilled
+    let digest = blobs[0].file_stem().unwrap().to_string_lossy().into_owned();
+    let forged = preserves::IOValue::record(
+        preserves::IOValue::symbol("duet:blob"),
+        vec![
+            preserves::IOValue::new(digest),
+            preserves::IOValue::new(5i64),
+        ],
+    );
+    let alongside = preserves::IOValue::new(vec![forged.clone(), value.clone()]);
+    let forged_turns: Vec<_> = [forged, alongside]
+        .into_iter()
+        .map(|payload| {
+            let turn = control
+                .assert_value(actor_id.clone(), payload.clone())
+                .unwrap();
+            (turn, payload)
+        })
+        .collect();
+    let reader = control.runtime().journal_reader(&main).unwrap();
+    for (turn, payload) in forged_turns {
+        let record = reader.read(&turn).unwrap();
+        assert!(matches!(
+            &record.inputs[0],
+            TurnInput::Assert { value: recorded, .. } if *recorded == payload
+        ));
+    }
+}
+
+fn walk_files(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(walk_files(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+#[test]
+fn test_typed_event_subscription_decodes_and_resumes() {
+    use duet::codebase::AgentResponse;
+    use duet::runtime::Control;
+    use duet::runtime::control::AssertionEventAction;
+    use duet::runtime::turn::ActorId;
+    use preserves::IOValue;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+    let response = |request: &str| {
+        IOValue::record(
+            IOValue::symbol("agent-response"),
+            vec![
+                IOValue::new("agent-1".to_string()),
+                IOValue::new(request.to_string()),
+                IOValue::new("prompt".to_string()),
+                IOValue::new(format!("answer to {}", request)),
+                IOValue::symbol("claude-code"),
+            ],
+        )
+    };
+    control
+        .assert_value(actor_id.clone(), response("req-1"))
+        .unwrap();
+    control
+        .assert_value(actor_id.clone(), IOValue::symbol("unrelated"))
+        .unwrap();
+    // Labelled like a response but missing fields: skipped when decoding
+    control
+        .assert_value(
+            actor_id.clone(),
+            IOValue::record(IOValue::symbol("agent-response"), vec![]),
+        )
+        .unwrap();
+    control
+        .assert_value(actor_id.clone(), response("req-2"))
+        .unwrap();
+
+    let raw: Vec<_> = control
+        .events()
+        .label("agent-response")
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(raw.len(), 3);
+
+    let mut subscription = control
+        .events()
+        .label("agent-response")
+        .batch_size(1)
+        .decode::<AgentResponse>();
+    let decoded: Vec<_> = subscription.by_ref().collect::<Result<_, _>>().unwrap();
+    let requests: Vec<_> = decoded
+        .iter()
+        .map(|event| event.value.as_ref().unwrap().request_id.as_str())
+        .collect();
+    assert_eq!(requests, ["req-1", "req-2"]);
+    assert!(matches!(decoded[1].action, AssertionEventAction::Assert));
+    let cursor = subscription.cursor().cloned().unwrap();
+    assert_eq!(cursor, decoded[1].turn_id);
+
+    // A later subscription picks up after the cursor
+    control
+        .assert_value(actor_id.clone(), response("req-3"))
+        .unwrap();
+    let newer: Vec<_> = control
+        .events()
+        .label("agent-response")
+        .since(cursor)
+        .decode::<AgentResponse>()
+        .map(|event| event.unwrap().value.unwrap().request_id)
+        .collect();
+    assert_eq!(newer, ["req-3"]);
+}
+
+#[test]
+fn test_goto_restores_snapshot_state_to_owning_actors() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::ActorId;
+    use preserves::IOValue;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 2,
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    let (alice, bob) = (ActorId::new(), ActorId::new());
+
+    for index in 0..2 {
+        control
+            .assert_value(alice.clone(), IOValue::new(index as i64))
+            .unwrap();
+        control
+            .assert_value(bob.clone(), IOValue::new(index as i64))
+            .unwrap();
+    }
+    let target = control
+        .assert_value(alice.clone(), IOValue::symbol("after-snapshot"))
+        .unwrap();
+    control
+        .assert_value(bob.clone(), IOValue::symbol("discarded"))
+        .unwrap();
+
+    control.goto(target).unwrap();
+
+    // Each assertion comes back once, held by the actor that made it
+    let all = control.list_assertions(None);
+    assert_eq!(all.len(), 5);
+    assert_eq!(control.list_assertions(Some(&alice)).len(), 3);
+    assert_eq!(control.list_assertions(Some(&bob)).len(), 2);
+    assert!(
+        all.iter()
+            .all(|info| info.value != IOValue::symbol("discarded"))
+    );
+}
+
+#[test]
+fn test_flush_is_a_barrier_for_deferred_fsyncs() {
+    use duet::runtime::Control;
+    use duet::runtime::journal::DurabilityMode;
+    use duet::runtime::turn::ActorId;
+    use preserves::IOValue;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        snapshot_interval: 4,
+        durability: DurabilityMode::OnSnapshot,
+        ..Default::default()
+    };
+    let mut control = Control::init(config).unwrap();
+    let actor_id = ActorId::new();
+
+    for index in 0..3 {
+        control
+            .assert_value(actor_id.clone(), IOValue::new(index as i64))
+            .unwrap();
+    }
+    assert_eq!(control.flush().unwrap(), 3);
+    assert_eq!(control.flush().unwrap(), 0);
+
+    // Reaching the snapshot interval syncs the turns it describes
+    let turn = control
+        .assert_value(actor_id.clone(), IOValue::symbol("snapshotted"))
+        .unwrap();
+    assert!(
+        control
+            .snapshots(&duet::runtime::turn::BranchId::main())
+            .iter()
+            .any(|entry| entry.turn_id == turn)
+    );
+    assert_eq!(control.flush().unwrap(), 0);
+}
+
+/// Copy the runtime root checked in under `tests/fixtures/<name>` into `dest`
+fn copy_fixture(name: &str, dest: &std::path::Path) {
+    let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    for file in walk_files(&fixture) {
+        let target = dest.join(file.strip_prefix(&fixture).unwrap());
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::copy(&file, &target).unwrap();
+    }
+}
+
+#[test]
+fn test_baseline_root_is_migrated_on_startup() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::BranchId;
+
+    let temp = TempDir::new().unwrap();
+    copy_fixture("baseline-root", temp.path());
+    let legacy_head = "turn_8b954831d456a886b3a816d4433b5bd745b408dd0fb80fd728909c7ad40257d5";
+
+    let mut control = Control::new(RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let head = control.status().unwrap().head_turn;
+    assert!(!head.is_legacy(), "head still legacy: {head}");
+    assert_eq!(head.sequence(), 6);
+    let branches = std::fs::read_to_string(temp.path().join("meta/branches.json")).unwrap();
+    assert!(!branches.contains(legacy_head), "{branches}");
+
+    let history = control.history(&BranchId::main(), 0, 100).unwrap();
+    assert_eq!(history.len(), 6);
+    assert!(history.iter().all(|turn| !turn.turn_id.is_legacy()));
+    let side = control
+        .history(&BranchId::new("experiment"), 0, 100)
+        .unwrap();
+    assert_eq!(side.len(), 2);
+
+    let target = control.back(2).unwrap();
+    assert_eq!(target, history[3].turn_id);
+}
+
+#[test]
+fn test_baseline_root_without_index_and_with_torn_tail_is_migrated() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::BranchId;
+    use std::io::Write;
+
+    let temp = TempDir::new().unwrap();
+    copy_fixture("baseline-root", temp.path());
+    for branch in ["main", "experiment"] {
+        std::fs::remove_file(temp.path().join("meta").join(branch).join("journal.index")).unwrap();
+    }
+    // A crash mid-append left a length prefix without its full record
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(temp.path().join("journal/main/segment-000000.turnlog"))
+        .unwrap()
+        .write_all(&[64, 0, 0, 0, 0xb4, 0xb3])
+        .unwrap();
+
+    let control = Control::new(RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let head = control.status().unwrap().head_turn;
+    assert!(!head.is_legacy(), "head still legacy: {head}");
+    let history = control.history(&BranchId::main(), 0, 100).unwrap();
+    assert_eq!(history.len(), 6);
+    assert_eq!(history[5].turn_id, head);
+}
+
+#[test]
+fn test_startup_fails_on_a_complete_record_that_does_not_decode() {
+    use duet::runtime::Control;
+    use duet::runtime::turn::ActorId;
+    use std::io::Write;
+
+    let temp = TempDir::new().unwrap();
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    {
+        let mut control = Control::init(config.clone()).unwrap();
+        control
+            .assert_value(ActorId::new(), preserves::IOValue::symbol("kept"))
+            .unwrap();
+    }
+
+    let segment = temp.path().join("journal/main/segment-000000.turnlog");
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&segment)
+        .unwrap();
+    file.write_all(&4u32.to_le_bytes()).unwrap();
+    file.write_all(b"junk").unwrap();
+    drop(file);
+    let written = std::fs::metadata(&segment).unwrap().len();
+
+    let err = Control::new(config).err().expect("startup should fail");
+    assert!(err.to_string().contains("Corrupted segment 0"), "{err}");
+    assert_eq!(std::fs::metadata(&segment).unwrap().len(), written);
+}
+
+#[test]
+fn test_refuse_policy_stops_on_undecodable_unversioned_segment() {
+    use duet::runtime::Control;
+    use duet::runtime::error::RuntimeError;
+    use duet::runtime::version::VersionPolicy;
+    use std::io::Write;
+
+    // Unversioned segments that still decode are replayed
+    let temp = TempDir::new().unwrap();
+    copy_fixture("baseline-root", temp.path());
+    let config = RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        version_policy: VersionPolicy::Refuse,
+        ..Default::default()
+    };
+    Control::new(config.clone()).unwrap();
+
+    let temp = TempDir::new().unwrap();
+    copy_fixture("baseline-root", temp.path());
+    let segment = temp.path().join("journal/main/segment-000000.turnlog");
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&segment)
+        .unwrap();
+    file.write_all(&4u32.to_le_bytes()).unwrap();
+    file.write_all(b"junk").unwrap();
+    drop(file);
+    let written = std::fs::read(&segment).unwrap();
+    let branches = std::fs::read(temp.path().join("meta/branches.json")).unwrap();
+
+    let err = Control::new(RuntimeConfig {
+        root: temp.path().to_path_buf(),
+        ..config
+    })
+    .err()
+    .expect("startup should be refused");
+    assert!(
+        matches!(&err, RuntimeError::VersionMismatch { recorded, .. } if recorded == "unversioned"),
+        "{err}"
+    );
+    // Neither repair nor migration ran
+    assert_eq!(std::fs::read(&segment).unwrap(), written);
+    assert_eq!(
+        std::fs::read(temp.path().join("meta/branches.json")).unwrap(),
+        branches
+    );
+}